@@ -0,0 +1,36 @@
+// Print the relative positions used as fixtures by ycrdt-jni/src/sticky.rs,
+// encoded by Yjs itself with Y.encodeRelativePosition.
+//
+// Usage:
+//   npm install --no-save yjs@13.6.27
+//   node scripts/yjs-sticky-fixtures.mjs
+//
+// Output: one Rust constant per fixture, to paste into the tests of sticky.rs
+
+import * as Y from 'yjs'
+
+const hex = (bytes) => Array.from(bytes, (b) => '0x' + b.toString(16).padStart(2, '0')).join(', ')
+
+const print = (name, rpos) => {
+  console.log(`const ${name}: &[u8] = &[${hex(Y.encodeRelativePosition(rpos))}];`)
+}
+
+// Item (42, 2) of a root text, associated with the character before it
+const textDoc = new Y.Doc()
+textDoc.clientID = 42
+const text = textDoc.getText('text')
+text.insert(0, 'abc')
+print('ITEM_BEFORE', Y.createRelativePositionFromTypeIndex(text, 3, -1))
+
+// End of the root text "text", associated with what follows
+print('ROOT_AFTER', Y.createRelativePositionFromTypeIndex(text, 3, 0))
+
+// Start of an empty text nested in item (3000000000, 7), whose client ID
+// needs all five bytes of a var-uint
+const nestedDoc = new Y.Doc()
+nestedDoc.clientID = 3_000_000_000
+const array = nestedDoc.getArray('array')
+array.insert(0, [0, 1, 2, 3, 4, 5, 6])
+const nested = new Y.Text()
+array.insert(7, [nested])
+print('NESTED_AFTER', Y.createRelativePositionFromTypeIndex(nested, 0, 0))
@@ -0,0 +1,83 @@
+package net.carcdr.ycrdt.benchmarks;
+
+import net.carcdr.ycrdt.jni.JniYArray;
+import net.carcdr.ycrdt.jni.JniYDoc;
+import net.carcdr.ycrdt.jni.JniYTransaction;
+import org.openjdk.jmh.annotations.Benchmark;
+import org.openjdk.jmh.annotations.BenchmarkMode;
+import org.openjdk.jmh.annotations.Level;
+import org.openjdk.jmh.annotations.Mode;
+import org.openjdk.jmh.annotations.OutputTimeUnit;
+import org.openjdk.jmh.annotations.Param;
+import org.openjdk.jmh.annotations.Scope;
+import org.openjdk.jmh.annotations.Setup;
+import org.openjdk.jmh.annotations.State;
+import org.openjdk.jmh.annotations.TearDown;
+
+import java.util.concurrent.TimeUnit;
+
+/**
+ * Reading a numeric YArray in bulk, JNI only.
+ *
+ * <p>Compares reading every element with {@code getDouble} against the
+ * {@code toDoubleArray} and {@code toLongArray} fast paths, which fill a
+ * primitive array natively instead of crossing JNI once per element.</p>
+ */
+@State(Scope.Thread)
+@BenchmarkMode(Mode.Throughput)
+@OutputTimeUnit(TimeUnit.SECONDS)
+public class NumberArrayBenchmark {
+
+    @Param({"1000", "100000"})
+    private int size;
+
+    private JniYDoc doc;
+    private JniYArray array;
+
+    @Setup(Level.Trial)
+    public void setup() {
+        doc = new JniYDoc();
+        array = doc.getArray("series");
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            for (int i = 0; i < size; i++) {
+                array.pushDouble(txn, i);
+            }
+        }
+    }
+
+    @TearDown(Level.Trial)
+    public void teardown() {
+        array.close();
+        doc.close();
+    }
+
+    /**
+     * Benchmark: Read every element with getDouble in one transaction.
+     */
+    @Benchmark
+    public double getDoublePerElement() {
+        double sum = 0;
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            for (int i = 0; i < size; i++) {
+                sum += array.getDouble(txn, i);
+            }
+        }
+        return sum;
+    }
+
+    /**
+     * Benchmark: Read the whole array as a double[].
+     */
+    @Benchmark
+    public double[] toDoubleArray() {
+        return array.toDoubleArray();
+    }
+
+    /**
+     * Benchmark: Read the whole array as a long[].
+     */
+    @Benchmark
+    public long[] toLongArray() {
+        return array.toLongArray();
+    }
+}
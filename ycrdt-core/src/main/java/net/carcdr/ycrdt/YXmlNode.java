@@ -12,7 +12,9 @@ public interface YXmlNode {
         /** An XML element node. */
         ELEMENT,
         /** An XML text node. */
-        TEXT
+        TEXT,
+        /** An XML fragment nested in an element. */
+        FRAGMENT
     }
 
     /**
@@ -0,0 +1,27 @@
+//! Build script: exposes the resolved `yrs` version to the crate as the
+//! `YRS_VERSION` environment variable, for `nativeGetVersionInfo`.
+
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let version = locked_version(&lock, "yrs").unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=YRS_VERSION={}", version);
+}
+
+/// Finds the version of a package in the contents of a `Cargo.lock` file
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == name_line {
+            let version = lines.next()?.trim();
+            return version
+                .strip_prefix("version = \"")
+                .and_then(|v| v.strip_suffix('"'))
+                .map(str::to_string);
+        }
+    }
+    None
+}
@@ -0,0 +1,140 @@
+//! Optional record of when each root type was last modified and by whom.
+//!
+//! yrs keeps no timestamps and does not attribute changes to root types, so
+//! this information cannot be recovered from a document's history. When
+//! tracking is enabled on a document, every committed transaction that
+//! changes a root type (directly or through nested types) records the
+//! current time and the clients whose clocks the transaction advanced
+//! against that root. Only changes made while tracking is enabled are seen.
+//!
+//! A transaction that only deletes content advances no clocks, so its entry
+//! has no clients.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use yrs::block::ClientID;
+use yrs::branch::BranchID;
+use yrs::{Doc, Subscription, TransactionAcqError, TransactionCleanupEvent, TransactionMut};
+
+/// The most recent recorded change to a root type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootActivity {
+    /// Clients whose clocks the change advanced, in ascending order
+    pub clients: Vec<ClientID>,
+    /// Time of the change in milliseconds since the Unix epoch
+    pub at_millis: i64,
+}
+
+type ActivityMap = HashMap<Arc<str>, RootActivity>;
+
+/// Last-modified information per root type of one document
+#[derive(Default)]
+pub struct RootActivityLog {
+    roots: Arc<Mutex<ActivityMap>>,
+    subscription: Mutex<Option<Subscription>>,
+}
+
+impl RootActivityLog {
+    /// Starts or stops recording changes to `doc`
+    ///
+    /// Stopping keeps the entries recorded so far. Fails if a transaction is
+    /// open on the document.
+    pub fn set_enabled(&self, doc: &Doc, enabled: bool) -> Result<(), TransactionAcqError> {
+        let mut subscription = self.subscription.lock().unwrap_or_else(|e| e.into_inner());
+        if !enabled {
+            *subscription = None;
+        } else if subscription.is_none() {
+            let roots = Arc::clone(&self.roots);
+            *subscription = Some(doc.observe_transaction_cleanup(move |txn, event| {
+                record(&roots, txn, event);
+            })?);
+        }
+        Ok(())
+    }
+
+    /// Whether changes are being recorded
+    pub fn is_enabled(&self) -> bool {
+        self.subscription
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// The recorded entries, sorted by root name
+    pub fn snapshot(&self) -> Vec<(String, RootActivity)> {
+        let mut entries: Vec<_> = lock(&self.roots)
+            .iter()
+            .map(|(name, activity)| (name.to_string(), activity.clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+}
+
+fn record(roots: &Mutex<ActivityMap>, txn: &TransactionMut, event: &TransactionCleanupEvent) {
+    let changed: Vec<Arc<str>> = txn
+        .changed_parent_types()
+        .iter()
+        .filter_map(|branch| match branch.id() {
+            BranchID::Root(name) => Some(name),
+            BranchID::Nested(_) => None,
+        })
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    let mut clients: Vec<ClientID> = event
+        .after_state
+        .iter()
+        .filter(|(client, clock)| **clock > event.before_state.get(client))
+        .map(|(client, _)| *client)
+        .collect();
+    clients.sort_unstable();
+    let at_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX));
+
+    let mut roots = lock(roots);
+    for name in changed {
+        roots.insert(
+            name,
+            RootActivity {
+                clients: clients.clone(),
+                at_millis,
+            },
+        );
+    }
+}
+
+fn lock(roots: &Mutex<ActivityMap>) -> MutexGuard<'_, ActivityMap> {
+    roots.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Array, Map, MapPrelim, Text, Transact};
+
+    #[test]
+    fn test_records_roots_changed_through_nested_types() {
+        let doc = Doc::with_client_id(7);
+        let text = doc.get_or_insert_text("text");
+        let array = doc.get_or_insert_array("array");
+        let log = RootActivityLog::default();
+
+        text.insert(&mut doc.transact_mut(), 0, "before");
+        log.set_enabled(&doc, true).unwrap();
+        let nested = array.push_back(&mut doc.transact_mut(), MapPrelim::default());
+        nested.insert(&mut doc.transact_mut(), "k", 1);
+
+        let entries = log.snapshot();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "array");
+        assert_eq!(entries[0].1.clients, vec![7]);
+
+        log.set_enabled(&doc, false).unwrap();
+        text.insert(&mut doc.transact_mut(), 0, "after");
+        assert_eq!(log.snapshot().len(), 1);
+    }
+}
@@ -0,0 +1,479 @@
+//! Named annotation ranges over a text, such as comment anchors.
+//!
+//! Each annotation is stored in a companion map under its ID as a plain map
+//! holding the v1 encodings of two sticky indexes: `start`, associated with
+//! the first annotated character, and `end`, associated with the last one. The
+//! range therefore follows the annotated content through local and remote
+//! edits, and does not grow when text is typed at either boundary. An
+//! annotation is collapsed once all of its content has been deleted.
+
+use crate::{
+    check_writable_or_throw, decode_sticky_index_v1, find_callback_class, get_mut_or_throw,
+    get_ref_or_throw, get_string_or_throw, is_silent, resolve_in, throw_exception, trace_native,
+    DocPtr, JniEnvExt, JniResult, JniResultExt, MapPtr, TextPtr, TxnPtr,
+};
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::sys::{jint, jlong, jobject, jobjectArray};
+use jni::JNIEnv;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use yrs::updates::encoder::Encode;
+use yrs::{
+    Any, Assoc, IndexedSequence, Map, MapRef, Observable, Out, ReadTxn, Text, TextRef,
+    TransactionMut,
+};
+
+const START_KEY: &str = "start";
+const END_KEY: &str = "end";
+
+/// The current range of an annotation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAnnotation {
+    pub id: String,
+    pub start: u32,
+    pub end: u32,
+    /// Encoding of the start index, which identifies this instance of the
+    /// annotation if the ID is reused
+    start_encoded: Vec<u8>,
+}
+
+impl ResolvedAnnotation {
+    /// Whether all annotated content has been deleted
+    pub fn is_collapsed(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Stores an annotation covering `start..end` of `text` in `store`, replacing
+/// any annotation with the same ID
+///
+/// Returns `false` if the range is empty or out of bounds.
+pub fn annotate(
+    txn: &mut TransactionMut,
+    text: &TextRef,
+    store: &MapRef,
+    id: &str,
+    start: u32,
+    end: u32,
+) -> bool {
+    if start >= end || end > text.len(txn) {
+        return false;
+    }
+    let (Some(start_index), Some(end_index)) = (
+        text.sticky_index(txn, start, Assoc::After),
+        text.sticky_index(txn, end, Assoc::Before),
+    ) else {
+        return false;
+    };
+    let value = Any::from(HashMap::from([
+        (START_KEY.to_string(), Any::from(start_index.encode_v1())),
+        (END_KEY.to_string(), Any::from(end_index.encode_v1())),
+    ]));
+    store.insert(txn, id, value);
+    true
+}
+
+/// Resolves the annotation stored under `id`, if there is one that points
+/// into `text`
+pub fn resolve_annotation<T: ReadTxn>(
+    txn: &T,
+    text: &TextRef,
+    store: &MapRef,
+    id: &str,
+) -> Option<ResolvedAnnotation> {
+    resolve_value(txn, text, id, &store.get(txn, id)?)
+}
+
+/// Resolves every annotation in `store` that points into `text`, ordered by
+/// start position and then ID
+pub fn list_annotations<T: ReadTxn>(
+    txn: &T,
+    text: &TextRef,
+    store: &MapRef,
+) -> Vec<ResolvedAnnotation> {
+    let mut annotations: Vec<_> = store
+        .iter(txn)
+        .filter_map(|(id, value)| resolve_value(txn, text, id, &value))
+        .collect();
+    annotations.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.id.cmp(&b.id)));
+    annotations
+}
+
+fn resolve_value<T: ReadTxn>(
+    txn: &T,
+    text: &TextRef,
+    id: &str,
+    value: &Out,
+) -> Option<ResolvedAnnotation> {
+    let Out::Any(Any::Map(entries)) = value else {
+        return None;
+    };
+    let (Some(Any::Buffer(start_encoded)), Some(Any::Buffer(end_encoded))) =
+        (entries.get(START_KEY), entries.get(END_KEY))
+    else {
+        return None;
+    };
+    let start_index = decode_sticky_index_v1(start_encoded).ok()?;
+    let end_index = decode_sticky_index_v1(end_encoded).ok()?;
+    // Once their characters are deleted, both indexes resolve to the place
+    // the deleted content used to be, so a collapsed range has start == end
+    let start = resolve_in(txn, &start_index, text)?;
+    let end = resolve_in(txn, &end_index, text)?;
+    Some(ResolvedAnnotation {
+        id: id.to_string(),
+        start,
+        end: end.max(start),
+        start_encoded: start_encoded.to_vec(),
+    })
+}
+
+/// Builds a `JniAnnotation`
+fn annotation_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    annotation: &ResolvedAnnotation,
+) -> jni::errors::Result<JObject<'local>> {
+    let id = env.new_string(&annotation.id)?;
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniAnnotation")?;
+    env.new_object(
+        &class,
+        "(Ljava/lang/String;II)V",
+        &[
+            JValue::Object(&id),
+            JValue::Int(annotation.start as jint),
+            JValue::Int(annotation.end as jint),
+        ],
+    )
+}
+
+/// Creates or replaces an annotation using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the annotated YText
+/// - `map_ptr`: Pointer to the YMap storing the annotations
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `id`: The annotation ID
+/// - `start`: Index of the first annotated character
+/// - `end`: Index after the last annotated character
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeAddWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    id: JString,
+    start: jint,
+    end: jint,
+) {
+    trace_native!(
+        "JniYAnnotations.nativeAddWithTxn",
+        doc_ptr,
+        text_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let store = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let id = get_string_or_throw!(&mut env, wrapper, id);
+
+    let len = text.len(txn);
+    if start < 0 || end <= start || end as u32 > len {
+        let _ = env.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            format!(
+                "Range [{}, {}) is empty or out of bounds for length {}",
+                start, end, len
+            ),
+        );
+        return;
+    }
+    if !annotate(txn, text, store, &id, start as u32, end as u32) {
+        throw_exception(&mut env, "Failed to create sticky indexes for annotation");
+    }
+}
+
+/// Resolves an annotation using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the annotated YText
+/// - `map_ptr`: Pointer to the YMap storing the annotations
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `id`: The annotation ID
+///
+/// # Returns
+/// A `JniAnnotation`, or null if there is no annotation with this ID in the text
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeGetWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    id: JString,
+) -> jobject {
+    trace_native!(
+        "JniYAnnotations.nativeGetWithTxn",
+        doc_ptr,
+        text_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let store = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let id = get_string_or_throw!(&mut env, wrapper, id, std::ptr::null_mut());
+
+    let Some(annotation) = resolve_annotation(txn, text, store, &id) else {
+        return std::ptr::null_mut();
+    };
+    annotation_to_java(&mut env, &annotation)
+        .map(JObject::into_raw)
+        .map_err(Into::into)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Lists all annotations in the text using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the annotated YText
+/// - `map_ptr`: Pointer to the YMap storing the annotations
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A `JniAnnotation[]` ordered by start index and then ID
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeListWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    text_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobjectArray {
+    trace_native!(
+        "JniYAnnotations.nativeListWithTxn",
+        _doc_ptr,
+        text_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let store = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    annotations_to_java(&mut env, &list_annotations(txn, text, store)).unwrap_or_throw(&mut env)
+}
+
+fn annotations_to_java(
+    env: &mut JNIEnv,
+    annotations: &[ResolvedAnnotation],
+) -> JniResult<jobjectArray> {
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniAnnotation")?;
+    let array = env.new_object_array(annotations.len() as jint, &class, JObject::null())?;
+    for (i, annotation) in annotations.iter().enumerate() {
+        let obj = annotation_to_java(env, annotation)?;
+        env.set_object_array_element(&array, i as jint, &obj)?;
+        env.delete_local_ref(obj)?;
+    }
+    Ok(array.into_raw())
+}
+
+/// Registers a listener for annotations whose range collapses
+///
+/// The text is observed, and after each change every annotation is resolved.
+/// Annotations that are collapsed now but were not before are passed to
+/// `JniYAnnotations.dispatchCollapsed`. Annotations that are already
+/// collapsed when the listener is registered are not reported.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the annotated YText
+/// - `map_ptr`: Pointer to the YMap storing the annotations
+/// - `txn_ptr`: Pointer to a transaction used to read the current annotations
+/// - `subscription_id`: The subscription ID allocated by the document
+/// - `annotations_obj`: The Java JniYAnnotations object
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeObserveCollapsed(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    subscription_id: jlong,
+    annotations_obj: JObject,
+) {
+    trace_native!(
+        "JniYAnnotations.nativeObserveCollapsed",
+        doc_ptr,
+        text_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let store = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
+            return;
+        }
+    };
+    let global_ref = match env.new_global_ref(annotations_obj) {
+        Ok(r) => r,
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to create global ref: {:?}", e));
+            return;
+        }
+    };
+    let gate = wrapper.dispatch_gate();
+
+    // Instances (by start index encoding) that have already been reported
+    let collapsed: Mutex<HashSet<(String, Vec<u8>)>> = Mutex::new(
+        list_annotations(txn, text, store)
+            .into_iter()
+            .filter(ResolvedAnnotation::is_collapsed)
+            .map(|a| (a.id, a.start_encoded))
+            .collect(),
+    );
+    let observed_text = text.clone();
+    let store = store.clone();
+    let subscription = text.observe(move |txn, _event| {
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
+        let newly_collapsed = {
+            let mut collapsed = collapsed.lock().unwrap_or_else(|e| e.into_inner());
+            let annotations = list_annotations(txn, &observed_text, &store);
+            let current: HashSet<_> = annotations
+                .iter()
+                .filter(|a| a.is_collapsed())
+                .map(|a| (a.id.clone(), a.start_encoded.clone()))
+                .collect();
+            let newly: Vec<_> = annotations
+                .into_iter()
+                .filter(|a| a.is_collapsed())
+                .filter(|a| !collapsed.contains(&(a.id.clone(), a.start_encoded.clone())))
+                .collect();
+            *collapsed = current;
+            newly
+        };
+        if newly_collapsed.is_empty() || is_silent(txn) {
+            return;
+        }
+        let _ = executor.with_attached(|env| {
+            dispatch_collapsed(env, doc_ptr, subscription_id, &newly_collapsed)
+        });
+    });
+
+    wrapper.add_subscription(subscription_id, text_ptr, subscription, global_ref);
+}
+
+/// Passes collapsed annotations to the Java JniYAnnotations object
+fn dispatch_collapsed(
+    env: &mut JNIEnv,
+    doc_ptr: jlong,
+    subscription_id: jlong,
+    annotations: &[ResolvedAnnotation],
+) -> Result<(), jni::errors::Error> {
+    let wrapper = match unsafe { DocPtr::from_raw(doc_ptr).as_ref() } {
+        Some(w) => w,
+        None => return Ok(()),
+    };
+    let Some(target) = wrapper.get_java_ref(subscription_id) else {
+        return Ok(());
+    };
+    for annotation in annotations {
+        let obj = annotation_to_java(env, annotation)?;
+        env.call_method(
+            target.as_obj(),
+            "dispatchCollapsed",
+            "(JLnet/carcdr/ycrdt/jni/JniAnnotation;)V",
+            &[JValue::Long(subscription_id), JValue::Object(&obj)],
+        )?;
+        env.delete_local_ref(obj)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, Transact};
+
+    #[test]
+    fn test_annotation_follows_and_collapses() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        let store = doc.get_or_insert_map("comments");
+        let mut txn = doc.transact_mut();
+        text.insert(&mut txn, 0, "hello world");
+        assert!(annotate(&mut txn, &text, &store, "c1", 6, 11));
+        assert!(!annotate(&mut txn, &text, &store, "empty", 3, 3));
+
+        // Typing at either boundary does not grow the range
+        text.insert(&mut txn, 11, "!");
+        text.insert(&mut txn, 6, ">");
+        text.insert(&mut txn, 0, "oh ");
+        let a = resolve_annotation(&txn, &text, &store, "c1").unwrap();
+        assert_eq!((a.start, a.end), (10, 15));
+        assert!(!a.is_collapsed());
+
+        text.remove_range(&mut txn, 11, 3);
+        let a = resolve_annotation(&txn, &text, &store, "c1").unwrap();
+        assert_eq!((a.start, a.end), (10, 12));
+
+        text.remove_range(&mut txn, 10, 2);
+        let a = resolve_annotation(&txn, &text, &store, "c1").unwrap();
+        assert_eq!((a.start, a.end), (10, 10));
+        assert!(a.is_collapsed());
+        assert_eq!(list_annotations(&txn, &text, &store), vec![a]);
+    }
+}
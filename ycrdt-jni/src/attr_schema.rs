@@ -0,0 +1,99 @@
+//! Per-document allowlist of formatting attribute keys.
+//!
+//! When a document has an allowlist, insert and format operations that carry
+//! formatting attributes are checked against it before they reach the CRDT.
+//! Setting an attribute that is not listed throws `IllegalArgumentException`
+//! and the operation is not applied. Removing an attribute (a null value) is
+//! always allowed, so content written before the allowlist existed can still
+//! be cleaned up.
+
+use jni::JNIEnv;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use yrs::types::Attrs;
+use yrs::Any;
+
+/// The set of formatting attribute keys a document accepts
+#[derive(Default)]
+pub struct AttributeSchema {
+    allowed: RwLock<Option<Arc<HashSet<String>>>>,
+}
+
+impl AttributeSchema {
+    /// Replaces the allowlist, or removes it if `keys` is `None`
+    pub fn set_allowed(&self, keys: Option<HashSet<String>>) {
+        let mut allowed = self.allowed.write().unwrap_or_else(|e| e.into_inner());
+        *allowed = keys.map(Arc::new);
+    }
+
+    /// Returns the first key in `attrs` that sets a value not in the allowlist
+    pub fn first_rejected<'a>(&self, attrs: &'a Attrs) -> Option<&'a str> {
+        let allowed = self
+            .allowed
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()?;
+        attrs
+            .iter()
+            .find(|(key, value)| !matches!(value, Any::Null) && !allowed.contains(key.as_ref()))
+            .map(|(key, _)| key.as_ref())
+    }
+
+    /// Checks `attrs` against the allowlist, throwing
+    /// `IllegalArgumentException` and returning `false` if a key is rejected
+    pub fn check_or_throw(&self, env: &mut JNIEnv, attrs: &Attrs) -> bool {
+        match self.first_rejected(attrs) {
+            Some(key) => {
+                let _ = env.throw_new(
+                    "java/lang/IllegalArgumentException",
+                    format!("Formatting attribute '{}' is not allowed", key),
+                );
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs(entries: &[(&str, Any)]) -> Attrs {
+        entries
+            .iter()
+            .map(|(k, v)| (Arc::from(*k), v.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_allowlist_accepts_everything() {
+        let schema = AttributeSchema::default();
+        assert_eq!(
+            schema.first_rejected(&attrs(&[("x", Any::Bool(true))])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_allowlist_rejects_unknown_keys() {
+        let schema = AttributeSchema::default();
+        schema.set_allowed(Some(HashSet::from(["b".to_string()])));
+        assert_eq!(
+            schema.first_rejected(&attrs(&[("b", Any::Bool(true))])),
+            None
+        );
+        assert_eq!(
+            schema.first_rejected(&attrs(&[("font", Any::from("serif"))])),
+            Some("font")
+        );
+        // Removing an attribute is always allowed
+        assert_eq!(schema.first_rejected(&attrs(&[("font", Any::Null)])), None);
+
+        schema.set_allowed(None);
+        assert_eq!(
+            schema.first_rejected(&attrs(&[("font", Any::from("serif"))])),
+            None
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Batches of mutations executed on a per-document worker thread.
+//!
+//! A batch is a list of [`BatchOp`]s converted from Java on the submitting
+//! thread and sent to a worker thread owned by the document. The worker
+//! applies each batch in its own transaction, waiting for any transaction
+//! held by a Java thread to finish first, and then reports the update the
+//! transaction produced to the batch's completion object. Batches run in the
+//! order they were submitted.
+//!
+//! Operations are checked against the document as they are applied. The
+//! first invalid operation stops the batch; yrs cannot roll back, so the
+//! operations before it stay applied and are part of the reported update.
+
+use crate::{hold_transaction, release_transaction};
+use jni::objects::{GlobalRef, JValue};
+use jni::sys::jlong;
+use jni::{Executor, JNIEnv};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use yrs::{Any, Array, Doc, Map, Text, Transact, TransactionMut, WriteTxn};
+
+/// Operation kinds, matching the `JniBatchOp` kind constants
+pub const BATCH_OP_TEXT_INSERT: i32 = 0;
+pub const BATCH_OP_TEXT_DELETE: i32 = 1;
+pub const BATCH_OP_MAP_SET: i32 = 2;
+pub const BATCH_OP_MAP_REMOVE: i32 = 3;
+pub const BATCH_OP_ARRAY_INSERT: i32 = 4;
+pub const BATCH_OP_ARRAY_DELETE: i32 = 5;
+
+/// A single mutation of a root type, addressed by the root's name
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchOp {
+    TextInsert {
+        root: String,
+        index: i32,
+        chunk: String,
+    },
+    TextDelete {
+        root: String,
+        index: i32,
+        length: i32,
+    },
+    MapSet {
+        root: String,
+        key: String,
+        value: Any,
+    },
+    MapRemove {
+        root: String,
+        key: String,
+    },
+    ArrayInsert {
+        root: String,
+        index: i32,
+        value: Any,
+    },
+    ArrayDelete {
+        root: String,
+        index: i32,
+        length: i32,
+    },
+}
+
+/// Result of running a batch
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BatchOutcome {
+    /// The v1 update produced by the batch's transaction
+    pub update: Vec<u8>,
+    /// Number of operations applied
+    pub applied: usize,
+    /// Why the operation after the applied ones failed, if one did
+    pub error: Option<String>,
+}
+
+/// Applies `ops` in order, stopping at the first one that is out of range
+pub fn apply_batch(txn: &mut TransactionMut, ops: &[BatchOp]) -> BatchOutcome {
+    let mut outcome = BatchOutcome::default();
+    for op in ops {
+        if let Err(e) = apply_op(txn, op) {
+            outcome.error = Some(format!("Operation {}: {}", outcome.applied, e));
+            break;
+        }
+        outcome.applied += 1;
+    }
+    outcome.update = txn.encode_update_v1();
+    outcome
+}
+
+fn apply_op(txn: &mut TransactionMut, op: &BatchOp) -> Result<(), String> {
+    match op {
+        BatchOp::TextInsert { root, index, chunk } => {
+            let text = txn.get_or_insert_text(root.as_str());
+            let index = check_range(*index, 0, text.len(txn))?;
+            text.insert(txn, index, chunk);
+        }
+        BatchOp::TextDelete {
+            root,
+            index,
+            length,
+        } => {
+            let text = txn.get_or_insert_text(root.as_str());
+            let index = check_range(*index, *length, text.len(txn))?;
+            text.remove_range(txn, index, *length as u32);
+        }
+        BatchOp::MapSet { root, key, value } => {
+            let map = txn.get_or_insert_map(root.as_str());
+            map.insert(txn, key.as_str(), value.clone());
+        }
+        BatchOp::MapRemove { root, key } => {
+            let map = txn.get_or_insert_map(root.as_str());
+            map.remove(txn, key);
+        }
+        BatchOp::ArrayInsert { root, index, value } => {
+            let array = txn.get_or_insert_array(root.as_str());
+            let index = check_range(*index, 0, array.len(txn))?;
+            array.insert(txn, index, value.clone());
+        }
+        BatchOp::ArrayDelete {
+            root,
+            index,
+            length,
+        } => {
+            let array = txn.get_or_insert_array(root.as_str());
+            let index = check_range(*index, *length, array.len(txn))?;
+            array.remove_range(txn, index, *length as u32);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `[index, index + length)` lies within `[0, len]`
+fn check_range(index: i32, length: i32, len: u32) -> Result<u32, String> {
+    if index < 0 || length < 0 || i64::from(index) + i64::from(length) > i64::from(len) {
+        return Err(format!(
+            "range {}..{} is out of bounds for length {}",
+            index,
+            i64::from(index) + i64::from(length),
+            len
+        ));
+    }
+    Ok(index as u32)
+}
+
+/// A submitted batch and the Java object to report its outcome to
+struct BatchJob {
+    ops: Vec<BatchOp>,
+    completion: GlobalRef,
+}
+
+/// Per-document queue feeding the batch worker thread
+///
+/// The worker is started by the first submission and exits once the queue is
+/// dropped and every submitted batch has run.
+#[derive(Default)]
+pub struct BatchQueue {
+    sender: Mutex<Option<Sender<BatchJob>>>,
+}
+
+impl BatchQueue {
+    /// Queues `ops` to run on the worker thread, starting it if needed
+    ///
+    /// `completion.complete(byte[], int, String)` is called on the worker
+    /// thread with the update, the number of applied operations and the error
+    /// message, or null if every operation was applied.
+    pub fn submit(
+        &self,
+        doc: &Doc,
+        executor: &Executor,
+        ops: Vec<BatchOp>,
+        completion: GlobalRef,
+    ) -> std::io::Result<()> {
+        let mut sender = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+        if sender.is_none() {
+            let (tx, rx) = channel::<BatchJob>();
+            let doc = doc.clone();
+            let executor = executor.clone();
+            std::thread::Builder::new()
+                .name("ycrdt-batch".to_string())
+                .spawn(move || {
+                    for job in rx {
+                        let outcome = {
+                            let mut txn = doc.transact_mut();
+                            // Observers run on this thread when the batch commits
+                            let txn_key = &txn as *const TransactionMut as jlong;
+                            hold_transaction(txn_key, &doc);
+                            let outcome = apply_batch(&mut txn, &job.ops);
+                            drop(txn);
+                            release_transaction(txn_key);
+                            outcome
+                        };
+                        let _ = executor.with_attached(|env| {
+                            let result = complete(env, &job, &outcome);
+                            // The completion reports its own failures; never
+                            // leave an exception pending on this thread
+                            if env.exception_check().unwrap_or(false) {
+                                let _ = env.exception_clear();
+                            }
+                            result
+                        });
+                    }
+                })?;
+            *sender = Some(tx);
+        }
+        if let Some(tx) = sender.as_ref() {
+            // The worker only stops after the sender is dropped, so this cannot fail
+            let _ = tx.send(BatchJob { ops, completion });
+        }
+        Ok(())
+    }
+}
+
+fn complete(env: &mut JNIEnv, job: &BatchJob, outcome: &BatchOutcome) -> jni::errors::Result<()> {
+    let update = env.byte_array_from_slice(&outcome.update)?;
+    let error = match &outcome.error {
+        Some(e) => env.new_string(e)?.into(),
+        None => jni::objects::JObject::null(),
+    };
+    env.call_method(
+        &job.completion,
+        "complete",
+        "([BILjava/lang/String;)V",
+        &[
+            JValue::Object(&update),
+            JValue::Int(i32::try_from(outcome.applied).unwrap_or(i32::MAX)),
+            JValue::Object(&error),
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::decoder::Decode;
+    use yrs::{GetString, ReadTxn};
+
+    #[test]
+    fn test_batch_applies_ops_in_one_transaction() {
+        let doc = Doc::new();
+        let ops = vec![
+            BatchOp::TextInsert {
+                root: "text".into(),
+                index: 0,
+                chunk: "hello".into(),
+            },
+            BatchOp::TextDelete {
+                root: "text".into(),
+                index: 0,
+                length: 1,
+            },
+            BatchOp::MapSet {
+                root: "map".into(),
+                key: "k".into(),
+                value: Any::from(1.5),
+            },
+            BatchOp::ArrayInsert {
+                root: "array".into(),
+                index: 0,
+                value: Any::from("x"),
+            },
+        ];
+        let outcome = apply_batch(&mut doc.transact_mut(), &ops);
+        assert_eq!(outcome.applied, 4);
+        assert_eq!(outcome.error, None);
+
+        let replica = Doc::new();
+        let text = replica.get_or_insert_text("text");
+        replica
+            .transact_mut()
+            .apply_update(yrs::Update::decode_v1(&outcome.update).unwrap())
+            .unwrap();
+        assert_eq!(text.get_string(&replica.transact()), "ello");
+        assert_eq!(
+            replica.transact().state_vector(),
+            doc.transact().state_vector()
+        );
+    }
+
+    #[test]
+    fn test_batch_stops_at_out_of_range_op() {
+        let doc = Doc::new();
+        let ops = vec![
+            BatchOp::ArrayInsert {
+                root: "array".into(),
+                index: 0,
+                value: Any::from(true),
+            },
+            BatchOp::ArrayDelete {
+                root: "array".into(),
+                index: 0,
+                length: 2,
+            },
+            BatchOp::ArrayInsert {
+                root: "array".into(),
+                index: 1,
+                value: Any::from(false),
+            },
+        ];
+        let outcome = apply_batch(&mut doc.transact_mut(), &ops);
+        assert_eq!(outcome.applied, 1);
+        assert!(outcome.error.unwrap().starts_with("Operation 1:"));
+        assert_eq!(doc.get_or_insert_array("array").len(&doc.transact()), 1);
+    }
+}
@@ -0,0 +1,90 @@
+//! Count of the changes committed to a document.
+//!
+//! Caches derived from a document are usually invalidated by comparing state
+//! vectors, which have to be encoded and copied to Java each time. The counter
+//! is a single number that increases by one with every committed transaction
+//! that changes the document, local or remote, so reading it costs one atomic
+//! load. Transactions that change nothing leave it unchanged.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use yrs::{Doc, Subscription, TransactionCleanupEvent};
+
+/// Monotonic count of changing transactions on one document
+#[derive(Default)]
+pub struct ChangeCounter {
+    count: Arc<AtomicU64>,
+    subscription: Option<Subscription>,
+}
+
+impl ChangeCounter {
+    /// Starts counting the changes committed to `doc`
+    ///
+    /// Counting cannot start while a transaction is open on `doc`; the counter
+    /// then stays at zero.
+    pub fn new(doc: &Doc) -> Self {
+        let mut counter = Self::default();
+        counter.watch(doc);
+        counter
+    }
+
+    /// Counts the changes of `doc` from now on, continuing from the current
+    /// value, and counts the switch itself as one change
+    pub fn replace_doc(&mut self, doc: &Doc) {
+        self.subscription = None;
+        self.count.fetch_add(1, Ordering::AcqRel);
+        self.watch(doc);
+    }
+
+    /// Number of changing transactions committed so far
+    pub fn get(&self) -> u64 {
+        self.count.load(Ordering::Acquire)
+    }
+
+    fn watch(&mut self, doc: &Doc) {
+        let count = Arc::clone(&self.count);
+        self.subscription = doc
+            .observe_transaction_cleanup(move |_, event| {
+                if changed(event) {
+                    count.fetch_add(1, Ordering::AcqRel);
+                }
+            })
+            .ok();
+    }
+}
+
+fn changed(event: &TransactionCleanupEvent) -> bool {
+    event.before_state != event.after_state || !event.delete_set.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{GetString, Text, Transact};
+
+    #[test]
+    fn test_counts_transactions_that_change_the_document() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        let mut counter = ChangeCounter::new(&doc);
+        assert_eq!(counter.get(), 0);
+
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        assert_eq!(counter.get(), 1);
+        drop(doc.transact_mut());
+        assert_eq!(counter.get(), 1);
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
+        assert_eq!(counter.get(), 2);
+        assert_eq!(text.get_string(&doc.transact()), "ello");
+
+        let fresh = Doc::new();
+        counter.replace_doc(&fresh);
+        assert_eq!(counter.get(), 3);
+        text.insert(&mut doc.transact_mut(), 0, "ignored");
+        assert_eq!(counter.get(), 3);
+        fresh
+            .get_or_insert_text("text")
+            .insert(&mut fresh.transact_mut(), 0, "x");
+        assert_eq!(counter.get(), 4);
+    }
+}
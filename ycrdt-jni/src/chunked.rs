@@ -0,0 +1,196 @@
+//! Chunked transfer of large text content to Java.
+//!
+//! Converting a whole text to one `jstring` needs the full content on the
+//! Java heap at once. These helpers hand it over in pieces instead, either one
+//! piece per call or all pieces to a `java.util.function.Consumer` in a single
+//! pass. Offsets and sizes count UTF-8 bytes of the text's string form, and a
+//! chunk never ends inside a character.
+//!
+//! [`write_to_direct_buffer`] skips the Java string entirely and copies the
+//! UTF-8 bytes into memory the caller owns and can reuse.
+
+use crate::{throw_exception, to_jstring};
+use jni::objects::{JByteBuffer, JObject, JValue};
+use jni::sys::{jint, jstring};
+use jni::JNIEnv;
+use std::ops::Range;
+
+/// Returns the byte range of the chunk starting at `start`, or `None` if
+/// `start` is past the end or inside a character
+///
+/// The chunk is at most `max_bytes` long unless the character at `start` is
+/// longer, in which case it holds just that character so readers always make
+/// progress.
+pub fn chunk_range(s: &str, start: usize, max_bytes: usize) -> Option<Range<usize>> {
+    if !s.is_char_boundary(start) {
+        return None;
+    }
+    let mut end = (start + max_bytes).min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    if end == start && start < s.len() {
+        end = s[start..]
+            .chars()
+            .next()
+            .map_or(start, |c| start + c.len_utf8());
+    }
+    Some(start..end)
+}
+
+/// Converts the chunk of `content` starting at `offset` to a Java string
+///
+/// Returns an empty string at the end of the content. Throws
+/// `IllegalArgumentException` if `max_bytes` is not positive or `offset` is
+/// negative, past the end or inside a character.
+pub fn string_chunk_to_java(
+    env: &mut JNIEnv,
+    content: &str,
+    offset: jint,
+    max_bytes: jint,
+) -> jstring {
+    if max_bytes <= 0 {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Chunk size must be positive",
+        );
+        return std::ptr::null_mut();
+    }
+    let range = usize::try_from(offset)
+        .ok()
+        .and_then(|start| chunk_range(content, start, max_bytes as usize));
+    match range {
+        Some(range) => to_jstring(env, &content[range]),
+        None => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!(
+                    "Offset {} is not a character boundary in {} bytes",
+                    offset,
+                    content.len()
+                ),
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Passes `content` to `consumer.accept` in chunks of at most `max_bytes`
+///
+/// Each chunk's local reference is released before the next is created.
+/// Stops at the first exception thrown by the consumer, leaving it pending.
+pub fn stream_string_chunks(env: &mut JNIEnv, content: &str, max_bytes: jint, consumer: &JObject) {
+    if max_bytes <= 0 {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Chunk size must be positive",
+        );
+        return;
+    }
+    let mut start = 0;
+    while let Some(range) = chunk_range(content, start, max_bytes as usize) {
+        if range.is_empty() {
+            return;
+        }
+        start = range.end;
+        let result = env.new_string(&content[range]).and_then(|chunk| {
+            env.call_method(
+                consumer,
+                "accept",
+                "(Ljava/lang/Object;)V",
+                &[JValue::Object(&chunk)],
+            )?;
+            env.delete_local_ref(chunk)
+        });
+        if let Err(e) = result {
+            // An exception thrown by the consumer is already pending
+            if !env.exception_check().unwrap_or(false) {
+                throw_exception(env, &format!("Failed to pass text chunk: {}", e));
+            }
+            return;
+        }
+    }
+}
+
+/// Copies the UTF-8 bytes of `content` into a direct `ByteBuffer` at
+/// `offset`, if they fit in the `length` bytes available there
+///
+/// Returns the number of bytes in `content` whether or not they were written,
+/// so callers can retry with a larger buffer. Throws
+/// `IllegalArgumentException` if the buffer is not direct or the region lies
+/// outside of it, and `IllegalStateException` if the content is 2 GiB or more.
+pub fn write_to_direct_buffer(
+    env: &mut JNIEnv,
+    buffer: &JByteBuffer,
+    offset: jint,
+    length: jint,
+    content: &str,
+) -> jint {
+    let region = env.get_direct_buffer_address(buffer).and_then(|address| {
+        let capacity = env.get_direct_buffer_capacity(buffer)?;
+        Ok((address, capacity))
+    });
+    let Ok((address, capacity)) = region else {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Buffer must be a direct ByteBuffer",
+        );
+        return 0;
+    };
+    let (Ok(offset), Ok(length)) = (usize::try_from(offset), usize::try_from(length)) else {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Offset and length cannot be negative",
+        );
+        return 0;
+    };
+    if offset.checked_add(length).is_none_or(|end| end > capacity) {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            format!(
+                "Region of {} bytes at {} exceeds buffer capacity {}",
+                length, offset, capacity
+            ),
+        );
+        return 0;
+    }
+    let Ok(size) = jint::try_from(content.len()) else {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            format!(
+                "Content of {} bytes is too large for a ByteBuffer",
+                content.len()
+            ),
+        );
+        return 0;
+    };
+    if content.len() <= length {
+        // SAFETY: the region was checked to lie within the buffer's capacity
+        unsafe {
+            std::ptr::copy_nonoverlapping(content.as_ptr(), address.add(offset), content.len());
+        }
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_range_stops_at_char_boundary() {
+        // "é" is two bytes, "😀" is four
+        let s = "aé😀b";
+        assert_eq!(chunk_range(s, 0, 2), Some(0..1));
+        assert_eq!(chunk_range(s, 1, 2), Some(1..3));
+        assert_eq!(chunk_range(s, 3, 10), Some(3..8));
+        assert_eq!(chunk_range(s, 8, 10), Some(8..8));
+        assert_eq!(chunk_range(s, 2, 10), None);
+        assert_eq!(chunk_range(s, 9, 10), None);
+    }
+
+    #[test]
+    fn test_chunk_range_makes_progress_on_wide_char() {
+        assert_eq!(chunk_range("😀x", 0, 1), Some(0..4));
+    }
+}
@@ -0,0 +1,140 @@
+//! Lookup of the library's Java classes from native code.
+//!
+//! `FindClass` resolves names with the class loader of the Java method that
+//! called into native code. On a thread that native code attached itself there
+//! is no such method and the system class loader is used, which does not see
+//! the library's classes when they were loaded by a child class loader, as in
+//! OSGi bundles or Spring Boot fat jars. Observer dispatch on those threads
+//! would fail with `NoClassDefFoundError`.
+//!
+//! Classes are therefore cached as global references. The callback classes
+//! are resolved in `JNI_OnLoad`, which runs with the loading class loader
+//! current, and that loader is kept to resolve any other class that
+//! `FindClass` cannot find. Applications can register a different loader with
+//! `JniYDoc.setCallbackClassLoader`.
+
+use crate::{throw_exception, trace_native, JniResult};
+use dashmap::DashMap;
+use jni::objects::{GlobalRef, JClass, JObject, JValue};
+use jni::JNIEnv;
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Classes resolved so far, keyed by their JNI name
+    static ref CLASSES: DashMap<String, GlobalRef> = DashMap::new();
+    /// Loader registered by the application, tried before the library's own
+    static ref REGISTERED_LOADER: RwLock<Option<GlobalRef>> = RwLock::new(None);
+    /// Loader of the library's classes, captured in `JNI_OnLoad`
+    static ref LIBRARY_LOADER: RwLock<Option<GlobalRef>> = RwLock::new(None);
+}
+
+/// Classes instantiated by observer callbacks, resolved in `JNI_OnLoad`
+const CALLBACK_CLASSES: &[&str] = &[
+    "net/carcdr/ycrdt/YChange$Type",
+    "net/carcdr/ycrdt/jni/JniEventOrigin",
+    "net/carcdr/ycrdt/jni/JniJsonPatchOp",
+    "net/carcdr/ycrdt/jni/JniTreeNode",
+    "net/carcdr/ycrdt/jni/JniUndefined",
+    "net/carcdr/ycrdt/jni/JniYArrayChange",
+    "net/carcdr/ycrdt/jni/JniYEvent",
+    "net/carcdr/ycrdt/jni/JniYMapChange",
+    "net/carcdr/ycrdt/jni/JniYTextChange",
+    "net/carcdr/ycrdt/jni/JniYXmlElementChange",
+];
+
+/// Returns a library class by its JNI name, such as
+/// `net/carcdr/ycrdt/jni/JniYEvent`
+///
+/// The class is looked up once and cached; a class `FindClass` cannot find is
+/// loaded with the registered class loader, then the library's own.
+pub fn find_callback_class<'local>(
+    env: &mut JNIEnv<'local>,
+    name: &str,
+) -> jni::errors::Result<JClass<'local>> {
+    let cached = CLASSES.get(name).map(|entry| entry.value().clone());
+    let global = match cached {
+        Some(global) => global,
+        None => {
+            let class = load_class(env, name)?;
+            let global = env.new_global_ref(&class)?;
+            env.delete_local_ref(class)?;
+            CLASSES.insert(name.to_string(), global.clone());
+            global
+        }
+    };
+    Ok(JClass::from(env.new_local_ref(&global)?))
+}
+
+fn load_class<'local>(env: &mut JNIEnv<'local>, name: &str) -> jni::errors::Result<JClass<'local>> {
+    let error = match env.find_class(name) {
+        Ok(class) => return Ok(class),
+        Err(error) => error,
+    };
+    let loaders = [&*REGISTERED_LOADER, &*LIBRARY_LOADER]
+        .into_iter()
+        .filter_map(|loader| loader.read().ok().and_then(|loader| loader.clone()));
+    for loader in loaders {
+        // Drop the NoClassDefFoundError of the failed lookup before calling Java
+        env.exception_clear()?;
+        let binary_name = env.new_string(name.replace('/', "."))?;
+        let class = env.call_method(
+            &loader,
+            "loadClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            &[JValue::Object(&binary_name)],
+        );
+        env.delete_local_ref(binary_name)?;
+        if let Ok(class) = class.and_then(|class| class.l()) {
+            return Ok(JClass::from(class));
+        }
+    }
+    Err(error)
+}
+
+/// Registers the class loader used for classes `FindClass` cannot find
+///
+/// # Parameters
+/// - `loader`: The class loader, or null to use only the library's own
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetCallbackClassLoader(
+    mut env: JNIEnv,
+    _class: JClass,
+    loader: JObject,
+) {
+    trace_native!("JniYDoc.nativeSetCallbackClassLoader");
+    let loader = if loader.is_null() {
+        None
+    } else {
+        match env.new_global_ref(&loader) {
+            Ok(loader) => Some(loader),
+            Err(e) => {
+                throw_exception(&mut env, &format!("Failed to create global ref: {:?}", e));
+                return;
+            }
+        }
+    };
+    if let Ok(mut registered) = REGISTERED_LOADER.write() {
+        *registered = loader;
+    }
+}
+
+/// Remembers the loader of the library's classes and resolves the callback
+/// classes while it is current
+pub(crate) fn init_callback_classes(env: &mut JNIEnv) -> JniResult<()> {
+    let ydoc = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYDoc")?;
+    let loader = env
+        .call_method(&ydoc, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+        .l()?;
+    if !loader.is_null() {
+        let loader = env.new_global_ref(&loader)?;
+        if let Ok(mut library) = LIBRARY_LOADER.write() {
+            *library = Some(loader);
+        }
+    }
+    for name in CALLBACK_CLASSES {
+        let class = find_callback_class(env, name)?;
+        env.delete_local_ref(class)?;
+    }
+    Ok(())
+}
@@ -4,14 +4,23 @@
 //! and Java objects via JNI. These are consolidated here to avoid duplication
 //! across the various type modules.
 
-use jni::objects::{JObject, JString, JValue};
+use crate::{export_out, find_callback_class, JniEnvExt, JniError, JniResult, StringPolicy};
+use jni::objects::{JIntArray, JMap, JObject, JObjectArray, JString, JValue};
+use jni::sys::jint;
 use jni::JNIEnv;
-use yrs::types::Attrs;
-use yrs::{Any, Out};
+use std::collections::HashSet;
+use std::sync::Arc;
+use yrs::types::{Attrs, Delta};
+use yrs::{Any, Out, ReadTxn};
+
+/// JNI name of the class of `JniUndefined.UNDEFINED`
+const UNDEFINED_CLASS: &str = "net/carcdr/ycrdt/jni/JniUndefined";
 
 /// Convert a yrs::Any value to a Java JObject.
 ///
 /// Handles the following types:
+/// - `Any::Null` -> Java null
+/// - `Any::Undefined` -> `JniUndefined.UNDEFINED`
 /// - `Any::String` -> Java String
 /// - `Any::Bool` -> Java Boolean
 /// - `Any::Number` -> Java Double
@@ -22,7 +31,13 @@ pub fn any_to_jobject<'local>(
     value: &Any,
 ) -> Result<JObject<'local>, jni::errors::Error> {
     match value {
-        Any::Null | Any::Undefined => Ok(JObject::null()),
+        Any::Null => Ok(JObject::null()),
+        Any::Undefined => {
+            let class = find_callback_class(env, UNDEFINED_CLASS)?;
+            let undefined =
+                env.get_static_field(&class, "UNDEFINED", "Lnet/carcdr/ycrdt/jni/JniUndefined;")?;
+            undefined.l()
+        }
         Any::String(s) => {
             let jstr = env.new_string(s.as_ref())?;
             Ok(jstr.into())
@@ -57,32 +72,16 @@ pub fn any_to_jobject<'local>(
 
 /// Convert a yrs::Out value to a Java JObject.
 ///
-/// For `Out::Any`, delegates to `any_to_jobject`.
-/// For complex types (YText, YArray, YMap, etc.), returns their string representation.
-pub fn out_to_jobject<'local>(
+/// Plain values are converted by `any_to_java_deep`, so nested arrays and maps
+/// become Java `List` and `Map` values. Shared types (YText, YArray, YMap, XML
+/// nodes and subdocuments) are read through `txn` and become fully expanded
+/// `JniTreeNode`s, so a value can be compared with a later one.
+pub fn out_to_jobject<'local, T: ReadTxn>(
     env: &mut JNIEnv<'local>,
+    txn: &T,
     value: &Out,
 ) -> Result<JObject<'local>, jni::errors::Error> {
-    match value {
-        Out::Any(any) => any_to_jobject(env, any),
-        Out::YText(_)
-        | Out::YArray(_)
-        | Out::YMap(_)
-        | Out::YXmlElement(_)
-        | Out::YXmlText(_)
-        | Out::YDoc(_) => {
-            // For complex types, return string representation
-            let s = value.to_string();
-            let jstr = env.new_string(&s)?;
-            Ok(jstr.into())
-        }
-        _ => {
-            // For other types, convert to string
-            let s = value.to_string();
-            let jstr = env.new_string(&s)?;
-            Ok(jstr.into())
-        }
-    }
+    export_out(env, txn, value, u32::MAX)
 }
 
 /// Failure modes for [`jobject_to_any`].
@@ -92,6 +91,8 @@ pub enum AnyConversionError {
     Unsupported(String),
     /// A JNI call failed while inspecting or unboxing the value.
     Jni(jni::errors::Error),
+    /// A string value was rejected by `StringPolicy::Strict`.
+    InvalidString(String),
 }
 
 impl From<jni::errors::Error> for AnyConversionError {
@@ -100,23 +101,82 @@ impl From<jni::errors::Error> for AnyConversionError {
     }
 }
 
+/// Reads a Java String[] into a set
+pub fn string_array_to_set(
+    env: &mut JNIEnv,
+    array: &JObjectArray,
+    policy: StringPolicy,
+) -> JniResult<HashSet<String>> {
+    let len = env.get_array_length(array)?;
+    let mut set = HashSet::with_capacity(len as usize);
+    for i in 0..len {
+        let element = env.get_object_array_element(array, i)?;
+        let key = env.get_rust_string(&JString::from(element), policy)?;
+        set.insert(key);
+    }
+    Ok(set)
+}
+
+/// Converts strings to a Java String[]
+pub fn strings_to_java<'local, S: AsRef<str>>(
+    env: &mut JNIEnv<'local>,
+    strings: impl ExactSizeIterator<Item = S>,
+) -> JniResult<JObjectArray<'local>> {
+    let array = env.new_object_array(strings.len() as i32, "java/lang/String", JObject::null())?;
+    for (i, s) in strings.enumerate() {
+        let s = env.new_string(s.as_ref())?;
+        env.set_object_array_element(&array, i as i32, &s)?;
+        env.delete_local_ref(s)?;
+    }
+    Ok(array)
+}
+
+/// Converts every element of a Java Object[] with [`jobject_to_any`]
+pub fn object_array_to_any(
+    env: &mut JNIEnv,
+    array: &JObjectArray,
+    policy: StringPolicy,
+) -> Result<Vec<Any>, AnyConversionError> {
+    let len = env.get_array_length(array)?;
+    let mut values = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let element = env.get_object_array_element(array, i)?;
+        values.push(jobject_to_any(env, &element, policy)?);
+        env.delete_local_ref(element)?;
+    }
+    Ok(values)
+}
+
 /// Convert a Java `JObject` to a `yrs::Any`.
 ///
 /// Supported Java classes: `String`, `Long`, `Integer`, `Double`, `Float`,
-/// `Boolean`, and `null`. `Integer` widens to `Any::BigInt`; `Float` widens to
+/// `Boolean`, `JniUndefined`, and `null`. `Integer` widens to `Any::BigInt`; `Float` widens to
 /// `Any::Number`. Any other class returns
-/// `Err(AnyConversionError::Unsupported(class_name))`.
-pub fn jobject_to_any(env: &mut JNIEnv, value: &JObject) -> Result<Any, AnyConversionError> {
+/// `Err(AnyConversionError::Unsupported(class_name))`. Strings are converted
+/// according to `policy`.
+pub fn jobject_to_any(
+    env: &mut JNIEnv,
+    value: &JObject,
+    policy: StringPolicy,
+) -> Result<Any, AnyConversionError> {
     if value.is_null() {
         return Ok(Any::Null);
     }
 
     if env.is_instance_of(value, "java/lang/String")? {
         let jstr = JString::from(unsafe { JObject::from_raw(value.as_raw()) });
-        let rust_str: String = env.get_string(&jstr)?.into();
+        let rust_str = env.get_rust_string(&jstr, policy).map_err(|e| match e {
+            JniError::Jni(e) => AnyConversionError::Jni(e),
+            e => AnyConversionError::InvalidString(e.to_string()),
+        })?;
         return Ok(Any::String(rust_str.into()));
     }
 
+    let undefined_class = find_callback_class(env, UNDEFINED_CLASS)?;
+    if env.is_instance_of(value, &undefined_class)? {
+        return Ok(Any::Undefined);
+    }
+
     if env.is_instance_of(value, "java/lang/Boolean")? {
         let b = env.call_method(value, "booleanValue", "()Z", &[])?.z()?;
         return Ok(Any::Bool(b));
@@ -144,6 +204,330 @@ pub fn jobject_to_any(env: &mut JNIEnv, value: &JObject) -> Result<Any, AnyConve
     Err(AnyConversionError::Unsupported(class_name))
 }
 
+/// Convert a Java `Map<String, Object>` of formatting attributes to yrs `Attrs`.
+///
+/// Values are converted like `jobject_to_any`, except that unsupported classes
+/// fall back to their `toString()` form. Keys and string values are converted
+/// according to `policy`.
+pub fn java_map_to_attrs(
+    env: &mut JNIEnv,
+    java_map: &JObject,
+    policy: StringPolicy,
+) -> Result<Attrs, JniError> {
+    let mut attrs = Attrs::new();
+
+    // Get the Map interface
+    let map = JMap::from_env(env, java_map).map_err(jni_context("Failed to get map"))?;
+
+    // Iterate through the map entries
+    let mut iter = map
+        .iter(env)
+        .map_err(jni_context("Failed to iterate map"))?;
+
+    while let Some((key, value)) = iter
+        .next(env)
+        .map_err(jni_context("Failed to get next entry"))?
+    {
+        // Get the key as String
+        let key_jstring = JString::from(key);
+        let key_str = env.get_rust_string(&key_jstring, policy)?;
+
+        // Convert the value to yrs::Any
+        let any_value = if value.is_null() {
+            Any::Null
+        } else {
+            // Check the type of the value
+            let value_class = env
+                .get_object_class(&value)
+                .map_err(jni_context("Failed to get value class"))?;
+
+            let class_name = env
+                .call_method(&value_class, "getName", "()Ljava/lang/String;", &[])
+                .map_err(jni_context("Failed to get class name"))?;
+
+            let class_name_obj = class_name
+                .l()
+                .map_err(jni_context("Failed to get class name object"))?;
+            let class_name_str: String = env
+                .get_string(&JString::from(class_name_obj))
+                .map_err(jni_context("Failed to convert class name"))?
+                .into();
+
+            match class_name_str.as_str() {
+                "java.lang.Boolean" => {
+                    let bool_val = env
+                        .call_method(&value, "booleanValue", "()Z", &[])
+                        .map_err(jni_context("Failed to get boolean value"))?;
+                    Any::Bool(
+                        bool_val
+                            .z()
+                            .map_err(jni_context("Failed to convert to bool"))?,
+                    )
+                }
+                "java.lang.Integer" | "java.lang.Long" => {
+                    let long_val = env
+                        .call_method(&value, "longValue", "()J", &[])
+                        .map_err(jni_context("Failed to get long value"))?;
+                    Any::BigInt(
+                        long_val
+                            .j()
+                            .map_err(jni_context("Failed to convert to long"))?,
+                    )
+                }
+                "java.lang.Double" | "java.lang.Float" => {
+                    let double_val = env
+                        .call_method(&value, "doubleValue", "()D", &[])
+                        .map_err(jni_context("Failed to get double value"))?;
+                    Any::Number(
+                        double_val
+                            .d()
+                            .map_err(jni_context("Failed to convert to double"))?,
+                    )
+                }
+                "net.carcdr.ycrdt.jni.JniUndefined" => Any::Undefined,
+                "java.lang.String" => {
+                    let string_val = JString::from(value);
+                    let rust_str = env.get_rust_string(&string_val, policy)?;
+                    Any::String(rust_str.into())
+                }
+                _ => {
+                    // Try to convert to string as fallback
+                    let string_val = env
+                        .call_method(&value, "toString", "()Ljava/lang/String;", &[])
+                        .map_err(jni_context("Failed to call toString"))?;
+                    let string_obj = string_val
+                        .l()
+                        .map_err(jni_context("Failed to get string object"))?;
+                    let rust_str = env.get_rust_string(&JString::from(string_obj), policy)?;
+                    Any::String(rust_str.into())
+                }
+            }
+        };
+
+        attrs.insert(Arc::from(key_str.as_str()), any_value);
+    }
+
+    Ok(attrs)
+}
+
+/// Converts an index or length to a jint for a method returning `int`.
+///
+/// yrs counts in `u32`, so a large text or array has positions past
+/// `Integer.MAX_VALUE`. Throws `ArithmeticException` for those instead of
+/// returning a negative number.
+///
+/// # Returns
+/// The value, or `None` with the exception pending
+pub fn jint_or_throw(env: &mut JNIEnv, value: u32, what: &str) -> Option<jint> {
+    match jint::try_from(value) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            let _ = env.throw_new(
+                "java/lang/ArithmeticException",
+                format!("{} {} does not fit in an int", what, value),
+            );
+            None
+        }
+    }
+}
+
+/// Wraps a JNI error with a description of the step that failed
+fn jni_context(context: &'static str) -> impl FnOnce(jni::errors::Error) -> JniError {
+    move |e| JniError::Other(format!("{}: {:?}", context, e))
+}
+
+/// One range of a bulk formatting call
+pub struct FormatRun {
+    pub index: u32,
+    pub len: u32,
+    pub attrs: Attrs,
+}
+
+/// Decode the arguments of a bulk formatting call.
+///
+/// `ranges` holds one `(index, length)` pair per run and `attributes` holds
+/// the matching `Map<String, Object>` for each run.
+pub fn java_format_runs(
+    env: &mut JNIEnv,
+    ranges: &JIntArray,
+    attributes: &JObjectArray,
+    policy: StringPolicy,
+) -> Result<Vec<FormatRun>, String> {
+    let range_len = env
+        .get_array_length(ranges)
+        .map_err(|e| format!("Failed to read ranges: {:?}", e))? as usize;
+    let attr_len = env
+        .get_array_length(attributes)
+        .map_err(|e| format!("Failed to read attributes: {:?}", e))? as usize;
+    if range_len != attr_len * 2 {
+        return Err(format!(
+            "Expected {} range values for {} attribute maps, got {}",
+            attr_len * 2,
+            attr_len,
+            range_len
+        ));
+    }
+
+    let mut bounds = vec![0; range_len];
+    env.get_int_array_region(ranges, 0, &mut bounds)
+        .map_err(|e| format!("Failed to read ranges: {:?}", e))?;
+
+    let mut runs = Vec::with_capacity(attr_len);
+    for (i, pair) in bounds.chunks_exact(2).enumerate() {
+        let (index, len) = (pair[0], pair[1]);
+        if index < 0 || len < 0 {
+            return Err(format!(
+                "Range {} has negative index or length: ({}, {})",
+                i, index, len
+            ));
+        }
+        let map = env
+            .get_object_array_element(attributes, i as i32)
+            .map_err(|e| format!("Failed to read attributes: {:?}", e))?;
+        if map.is_null() {
+            return Err(format!("Attributes for range {} cannot be null", i));
+        }
+        let attrs = java_map_to_attrs(env, &map, policy).map_err(|e| e.to_string())?;
+        env.delete_local_ref(map)
+            .map_err(|e| format!("Failed to release attributes: {:?}", e))?;
+        runs.push(FormatRun {
+            index: index as u32,
+            len: len as u32,
+            attrs,
+        });
+    }
+    Ok(runs)
+}
+
+/// Create the `JniYTextChange` for an inserted chunk of a text delta.
+///
+/// String chunks carry their text. Embeds carry the string form of the value
+/// as content and the value itself, converted by `out_to_jobject`, as the embed.
+///
+/// A string longer than `content_limit` UTF-16 units is summarized: the change
+/// carries only its first `content_limit` units, together with its full length
+/// and the `String.hashCode()` of the full text, so a large insertion is not
+/// copied into Java for observers that do not read it.
+pub fn text_insert_change<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    value: &Out,
+    attrs: Option<&Attrs>,
+    content_limit: Option<usize>,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    let attrs_map = match attrs {
+        Some(attrs) => attrs_to_java_hashmap(env, attrs)?,
+        None => JObject::null(),
+    };
+    if let Out::Any(Any::String(s)) = value {
+        if let Some(summary) = content_limit.and_then(|limit| summarize_content(s, limit)) {
+            let prefix = env.new_string(summary.prefix)?;
+            let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYTextChange")?;
+            return env.new_object(
+                &class,
+                "(Ljava/lang/String;IILjava/util/Map;)V",
+                &[
+                    JValue::Object(&prefix),
+                    JValue::Int(summary.length),
+                    JValue::Int(summary.hash),
+                    JValue::Object(&attrs_map),
+                ],
+            );
+        }
+    }
+    let (content, embed) = match value {
+        Out::Any(Any::String(s)) => (env.new_string(s.as_ref())?, JObject::null()),
+        other => (
+            env.new_string(other.to_string())?,
+            out_to_jobject(env, txn, other)?,
+        ),
+    };
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYTextChange")?;
+    env.new_object(
+        &class,
+        "(Ljava/lang/String;Ljava/lang/Object;Ljava/util/Map;)V",
+        &[
+            JValue::Object(&content),
+            JValue::Object(&embed),
+            JValue::Object(&attrs_map),
+        ],
+    )
+}
+
+/// Create the `JniYTextChange` for a retained or deleted span of a text delta.
+///
+/// The change carries the span's length in bytes, as measured by yrs, and its
+/// length in UTF-16 units, or -1 if that is not known.
+pub fn text_span_change<'local>(
+    env: &mut JNIEnv<'local>,
+    delta: &Delta,
+    utf16_len: Option<u32>,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    let (type_name, len, attrs) = match delta {
+        Delta::Deleted(len) => ("DELETE", *len, None),
+        Delta::Retain(len, attrs) => ("RETAIN", *len, attrs.as_deref()),
+        Delta::Inserted(..) => unreachable!("inserts are converted by text_insert_change"),
+    };
+    let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
+    let change_type = env
+        .get_static_field(type_class, type_name, "Lnet/carcdr/ycrdt/YChange$Type;")?
+        .l()?;
+    let attrs_map = match attrs {
+        Some(attrs) => attrs_to_java_hashmap(env, attrs)?,
+        None => JObject::null(),
+    };
+    let utf16_len = utf16_len.map_or(-1, |len| len as i32);
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYTextChange")?;
+    env.new_object(
+        &class,
+        "(Lnet/carcdr/ycrdt/YChange$Type;IILjava/util/Map;)V",
+        &[
+            JValue::Object(&change_type),
+            JValue::Int(len as i32),
+            JValue::Int(utf16_len),
+            JValue::Object(&attrs_map),
+        ],
+    )
+}
+
+/// An inserted string shortened for delivery to an observer
+#[derive(Debug, PartialEq, Eq)]
+pub struct ContentSummary<'a> {
+    /// The leading part of the string, at most the limit in UTF-16 units
+    pub prefix: &'a str,
+    /// Length of the full string in UTF-16 units
+    pub length: i32,
+    /// `String.hashCode()` of the full string
+    pub hash: i32,
+}
+
+/// Summarizes `s` if it is longer than `limit` UTF-16 units
+///
+/// The prefix never ends in the middle of a surrogate pair, so it can be one
+/// unit shorter than `limit`.
+pub fn summarize_content(s: &str, limit: usize) -> Option<ContentSummary<'_>> {
+    let mut units = 0usize;
+    let mut prefix_end = None;
+    let mut hash = 0i32;
+    for (offset, c) in s.char_indices() {
+        let width = c.len_utf16();
+        if prefix_end.is_none() && units + width > limit {
+            prefix_end = Some(offset);
+        }
+        let mut buf = [0u16; 2];
+        for unit in c.encode_utf16(&mut buf) {
+            hash = hash.wrapping_mul(31).wrapping_add(i32::from(*unit));
+        }
+        units += width;
+    }
+    let prefix_end = prefix_end?;
+    Some(ContentSummary {
+        prefix: &s[..prefix_end],
+        length: i32::try_from(units).unwrap_or(i32::MAX),
+        hash,
+    })
+}
+
 /// Create a Java HashMap from yrs Attrs.
 ///
 /// Each attribute key becomes a String key in the HashMap,
@@ -168,3 +552,26 @@ pub fn attrs_to_java_hashmap<'local>(
 
     Ok(hashmap)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_content() {
+        assert_eq!(summarize_content("hello", 5), None);
+        assert_eq!(
+            summarize_content("hello", 2),
+            Some(ContentSummary {
+                prefix: "he",
+                length: 5,
+                hash: 99_162_322,
+            })
+        );
+        // The pair for U+1F600 is not split
+        let summary = summarize_content("a\u{1F600}b", 2).unwrap();
+        assert_eq!(summary.prefix, "a");
+        assert_eq!(summary.length, 4);
+        assert_eq!(summarize_content("", 0), None);
+    }
+}
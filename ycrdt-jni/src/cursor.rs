@@ -0,0 +1,307 @@
+//! JSON form of sticky indexes and awareness cursors.
+//!
+//! Yjs clients publish their selection in the awareness state as
+//! `{"anchor": <pos>, "head": <pos>}`, where each position is the output of
+//! `Y.relativePositionToJSON`: an object with an `item` ID (`{client, clock}`),
+//! or a `tname` or `type` for positions in an empty type, and an `assoc`.
+//! These helpers convert between that format and yrs sticky indexes.
+
+use crate::{
+    decode_sticky_index_v1, throw_exception, to_json_string, to_jstring, trace_native, JniEnvExt,
+    JniResult, JniResultExt, StringPolicy,
+};
+use jni::objects::{JByteArray, JClass, JObject, JString};
+use jni::sys::{jbyteArray, jobjectArray, jstring};
+use jni::JNIEnv;
+use std::collections::HashMap;
+use std::sync::Arc;
+use yrs::block::ID;
+use yrs::updates::encoder::Encode;
+use yrs::{Any, Assoc, IndexScope, StickyIndex};
+
+/// The Yjs JSON form of a sticky index
+pub fn sticky_index_to_any(index: &StickyIndex) -> Any {
+    let (key, value) = match index.scope() {
+        IndexScope::Relative(id) => ("item", id_to_any(id)),
+        IndexScope::Nested(id) => ("type", id_to_any(id)),
+        IndexScope::Root(name) => ("tname", Any::from(name.as_ref())),
+    };
+    let assoc = match index.assoc {
+        Assoc::After => 0,
+        Assoc::Before => -1,
+    };
+    Any::from(HashMap::from([
+        (key.to_string(), value),
+        ("assoc".to_string(), Any::BigInt(assoc)),
+    ]))
+}
+
+fn id_to_any(id: &ID) -> Any {
+    Any::from(HashMap::from([
+        ("client".to_string(), Any::BigInt(id.client as i64)),
+        ("clock".to_string(), Any::BigInt(i64::from(id.clock))),
+    ]))
+}
+
+/// Reads a sticky index from its Yjs JSON form
+///
+/// Like `Y.createRelativePositionFromJSON`, a missing `assoc` means 0 and any
+/// negative value associates with the content before the position.
+pub fn sticky_index_from_any(value: &Any) -> Result<StickyIndex, String> {
+    let Any::Map(fields) = value else {
+        return Err("position must be an object".to_string());
+    };
+    let present = |key: &str| fields.get(key).filter(|v| !matches!(v, Any::Null));
+    let scope = if let Some(item) = present("item") {
+        IndexScope::Relative(id_from_any(item)?)
+    } else if let Some(name) = present("tname") {
+        match name {
+            Any::String(name) => IndexScope::Root(Arc::clone(name)),
+            _ => return Err("tname must be a string".to_string()),
+        }
+    } else if let Some(ty) = present("type") {
+        IndexScope::Nested(id_from_any(ty)?)
+    } else {
+        return Err("position needs an item, tname or type".to_string());
+    };
+    let assoc = match present("assoc") {
+        None => 0,
+        Some(assoc) => integer(assoc).ok_or("assoc must be an integer")?,
+    };
+    let assoc = if assoc < 0 {
+        Assoc::Before
+    } else {
+        Assoc::After
+    };
+    Ok(StickyIndex::new(scope, assoc))
+}
+
+fn id_from_any(value: &Any) -> Result<ID, String> {
+    let Any::Map(fields) = value else {
+        return Err("ID must be an object".to_string());
+    };
+    let client = fields.get("client").and_then(integer);
+    let clock = fields.get("clock").and_then(integer);
+    match (client, clock) {
+        (Some(client), Some(clock))
+            if client >= 0 && (0..=i64::from(u32::MAX)).contains(&clock) =>
+        {
+            Ok(ID::new(client as u64, clock as u32))
+        }
+        _ => Err("ID needs a non-negative integer client and clock".to_string()),
+    }
+}
+
+fn integer(value: &Any) -> Option<i64> {
+    match value {
+        Any::BigInt(n) => Some(*n),
+        Any::Number(n) if n.fract() == 0.0 => Some(*n as i64),
+        _ => None,
+    }
+}
+
+/// The awareness cursor JSON for a selection from `anchor` to `head`
+pub fn cursor_to_json(anchor: &StickyIndex, head: &StickyIndex) -> String {
+    let cursor = Any::from(HashMap::from([
+        ("anchor".to_string(), sticky_index_to_any(anchor)),
+        ("head".to_string(), sticky_index_to_any(head)),
+    ]));
+    to_json_string(&cursor, true)
+}
+
+/// Reads the anchor and head of an awareness cursor
+pub fn cursor_from_json(json: &str) -> Result<(StickyIndex, StickyIndex), String> {
+    let cursor = Any::from_json(json).map_err(|e| e.to_string())?;
+    let Any::Map(fields) = &cursor else {
+        return Err("cursor must be an object".to_string());
+    };
+    let position = |key: &str| match fields.get(key) {
+        Some(value) => sticky_index_from_any(value).map_err(|e| format!("{}: {}", key, e)),
+        None => Err(format!("cursor has no {}", key)),
+    };
+    Ok((position("anchor")?, position("head")?))
+}
+
+fn throw_invalid(env: &mut JNIEnv, message: &str) {
+    let _ = env.throw_new("java/lang/IllegalArgumentException", message);
+}
+
+/// Decodes a v1-encoded sticky index passed from Java, throwing
+/// `IllegalArgumentException` if it is malformed
+fn decode_index(env: &mut JNIEnv, bytes: &JByteArray) -> Option<StickyIndex> {
+    let decoded = match env.convert_byte_array(bytes) {
+        Ok(data) => decode_sticky_index_v1(&data),
+        Err(_) => {
+            throw_exception(env, "Failed to convert byte array");
+            return None;
+        }
+    };
+    match decoded {
+        Ok(index) => Some(index),
+        Err(e) => {
+            throw_invalid(env, &format!("Invalid sticky index: {}", e));
+            None
+        }
+    }
+}
+
+/// Converts a sticky index to the JSON produced by `Y.relativePositionToJSON`
+///
+/// # Parameters
+/// - `encoded`: The v1-encoded sticky index
+///
+/// # Returns
+/// The JSON string
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniStickyIndex_nativeToJson(
+    mut env: JNIEnv,
+    _class: JClass,
+    encoded: JByteArray,
+) -> jstring {
+    trace_native!("JniStickyIndex.nativeToJson");
+    let Some(index) = decode_index(&mut env, &encoded) else {
+        return std::ptr::null_mut();
+    };
+    to_jstring(
+        &mut env,
+        &to_json_string(&sticky_index_to_any(&index), true),
+    )
+}
+
+/// Reads a sticky index from the JSON produced by `Y.relativePositionToJSON`
+///
+/// # Parameters
+/// - `json`: The JSON string
+///
+/// # Returns
+/// The v1-encoded sticky index
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniStickyIndex_nativeFromJson(
+    mut env: JNIEnv,
+    _class: JClass,
+    json: JString,
+) -> jbyteArray {
+    trace_native!("JniStickyIndex.nativeFromJson");
+    let json = match env.get_rust_string(&json, StringPolicy::default()) {
+        Ok(json) => json,
+        Err(e) => {
+            throw_invalid(&mut env, &format!("Invalid JSON string: {}", e));
+            return std::ptr::null_mut();
+        }
+    };
+    let parsed = Any::from_json(&json)
+        .map_err(|e| e.to_string())
+        .and_then(|value| sticky_index_from_any(&value));
+    match parsed {
+        Ok(index) => env
+            .byte_array_from_slice(&index.encode_v1())
+            .map(JByteArray::into_raw)
+            .map_err(Into::into)
+            .unwrap_or_throw(&mut env),
+        Err(e) => {
+            throw_invalid(&mut env, &format!("Invalid position JSON: {}", e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Converts a selection to the awareness cursor JSON used by Yjs editor bindings
+///
+/// # Parameters
+/// - `anchor`: The v1-encoded sticky index where the selection starts
+/// - `head`: The v1-encoded sticky index where the selection ends
+///
+/// # Returns
+/// The JSON string `{"anchor":...,"head":...}`
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniCursor_nativeToJson(
+    mut env: JNIEnv,
+    _class: JClass,
+    anchor: JByteArray,
+    head: JByteArray,
+) -> jstring {
+    trace_native!("JniCursor.nativeToJson");
+    let Some(anchor) = decode_index(&mut env, &anchor) else {
+        return std::ptr::null_mut();
+    };
+    let Some(head) = decode_index(&mut env, &head) else {
+        return std::ptr::null_mut();
+    };
+    to_jstring(&mut env, &cursor_to_json(&anchor, &head))
+}
+
+/// Reads a selection from awareness cursor JSON
+///
+/// # Parameters
+/// - `json`: The JSON string `{"anchor":...,"head":...}`
+///
+/// # Returns
+/// A `byte[][]` holding the v1-encoded anchor and head
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniCursor_nativeFromJson(
+    mut env: JNIEnv,
+    _class: JClass,
+    json: JString,
+) -> jobjectArray {
+    trace_native!("JniCursor.nativeFromJson");
+    let json = match env.get_rust_string(&json, StringPolicy::default()) {
+        Ok(json) => json,
+        Err(e) => {
+            throw_invalid(&mut env, &format!("Invalid JSON string: {}", e));
+            return std::ptr::null_mut();
+        }
+    };
+    match cursor_from_json(&json) {
+        Ok((anchor, head)) => {
+            encoded_pair_to_java(&mut env, &anchor, &head).unwrap_or_throw(&mut env)
+        }
+        Err(e) => {
+            throw_invalid(&mut env, &format!("Invalid cursor JSON: {}", e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn encoded_pair_to_java(
+    env: &mut JNIEnv,
+    anchor: &StickyIndex,
+    head: &StickyIndex,
+) -> JniResult<jobjectArray> {
+    let array = env.new_object_array(2, "[B", JObject::null())?;
+    for (i, index) in [anchor, head].into_iter().enumerate() {
+        let bytes = env.byte_array_from_slice(&index.encode_v1())?;
+        env.set_object_array_element(&array, i as i32, &bytes)?;
+    }
+    Ok(array.into_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, IndexedSequence, Text, Transact};
+
+    #[test]
+    fn test_cursor_json_round_trip() {
+        let doc = Doc::with_options(yrs::Options::with_client_id(42));
+        let text = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        let empty = StickyIndex::new(IndexScope::Root("text".into()), Assoc::After);
+        text.insert(&mut txn, 0, "hello");
+        let head = text.sticky_index(&txn, 3, Assoc::Before).unwrap();
+
+        let json = cursor_to_json(&empty, &head);
+        assert_eq!(
+            json,
+            r#"{"anchor":{"assoc":0,"tname":"text"},"head":{"assoc":-1,"item":{"client":42,"clock":2}}}"#
+        );
+        assert_eq!(cursor_from_json(&json).unwrap(), (empty, head));
+
+        // Yjs omits assoc when it is 0 and writes null for unset fields
+        let (anchor, _) = cursor_from_json(
+            r#"{"anchor":{"type":null,"tname":null,"item":{"client":42,"clock":0}},"head":{"tname":"text"}}"#,
+        )
+        .unwrap();
+        assert_eq!(anchor.assoc, Assoc::After);
+        assert!(cursor_from_json(r#"{"anchor":{"assoc":0}}"#).is_err());
+    }
+}
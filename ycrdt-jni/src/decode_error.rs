@@ -0,0 +1,120 @@
+//! Decoding of updates with enough context to debug corrupt payloads.
+//!
+//! yrs reports why an update failed to decode but not where. Updates decoded
+//! through [`decode_update_v1`] keep the position the decoder had reached, and
+//! [`UpdateDecodeError`] shows it together with a hex excerpt of the bytes
+//! around it. The whole payload is kept so it can be logged on request.
+
+use std::fmt;
+use yrs::encoding::read::Error as DecodeError;
+use yrs::updates::decoder::{Decode, Decoder, DecoderV1};
+use yrs::Update;
+
+/// Bytes shown on each side of the failing offset
+const EXCERPT_RADIUS: usize = 16;
+
+/// A v1 update that could not be decoded
+#[derive(Debug)]
+pub struct UpdateDecodeError {
+    /// Why decoding failed
+    pub error: DecodeError,
+    /// Number of bytes the decoder had consumed when it failed
+    pub offset: usize,
+    payload: Vec<u8>,
+}
+
+impl UpdateDecodeError {
+    /// Records a failure at `offset` while decoding `payload`
+    pub fn new(error: DecodeError, payload: &[u8], offset: usize) -> Self {
+        Self {
+            error,
+            offset: offset.min(payload.len()),
+            payload: payload.to_vec(),
+        }
+    }
+
+    /// The undecodable update
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Hex dump of the bytes around the failing offset, with the byte at the
+    /// offset in brackets
+    pub fn excerpt(&self) -> String {
+        let start = self.offset.saturating_sub(EXCERPT_RADIUS);
+        let end = (self.offset + EXCERPT_RADIUS).min(self.payload.len());
+        let mut out = format!("bytes {}..{}:", start, end);
+        for (i, byte) in self.payload[start..end].iter().enumerate() {
+            if start + i == self.offset {
+                out.push_str(&format!(" [{:02x}]", byte));
+            } else {
+                out.push_str(&format!(" {:02x}", byte));
+            }
+        }
+        if self.offset == self.payload.len() {
+            out.push_str(" [end]");
+        }
+        out
+    }
+}
+
+impl fmt::Display for UpdateDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at byte {} of {} ({})",
+            self.error,
+            self.offset,
+            self.payload.len(),
+            self.excerpt()
+        )
+    }
+}
+
+/// Decodes a v1 update, recording where decoding stopped if it fails
+pub fn decode_update_v1(bytes: &[u8]) -> Result<Update, UpdateDecodeError> {
+    let mut decoder = DecoderV1::from(bytes);
+    Update::decode(&mut decoder).map_err(|error| {
+        let remaining = decoder.read_to_end().map_or(0, <[u8]>::len);
+        UpdateDecodeError::new(error, bytes, bytes.len() - remaining)
+    })
+}
+
+/// Formats bytes as lowercase hex without separators
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, ReadTxn, Text, Transact};
+
+    #[test]
+    fn test_truncated_update_reports_offset() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("t");
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+        let update = doc
+            .transact()
+            .encode_state_as_update_v1(&Default::default());
+        assert!(decode_update_v1(&update).is_ok());
+
+        let truncated = &update[..update.len() - 4];
+        let err = decode_update_v1(truncated).unwrap_err();
+        assert!(err.offset > 0 && err.offset <= truncated.len());
+        assert_eq!(err.payload(), truncated);
+        let message = err.to_string();
+        assert!(message.contains(&format!("of {}", truncated.len())));
+        assert!(message.contains('['));
+    }
+
+    #[test]
+    fn test_excerpt_is_clamped_to_payload() {
+        let err = UpdateDecodeError::new(DecodeError::UnexpectedValue, &[1, 2, 3], 1);
+        assert_eq!(err.excerpt(), "bytes 0..3: 01 [02] 03");
+        let err = UpdateDecodeError::new(DecodeError::EndOfBuffer(1), &[0xab], 1);
+        assert_eq!(err.excerpt(), "bytes 0..1: ab [end]");
+        assert_eq!(to_hex(&[0x0f, 0xa0]), "0fa0");
+    }
+}
@@ -0,0 +1,249 @@
+//! Updates that carry a document together with its subdocuments.
+//!
+//! The state of a subdocument is kept in its own store, so a regular update of
+//! a document only says where its subdocuments are, not what they contain. A
+//! deep update bundles both into one container:
+//!
+//! ```text
+//! varuint  format version (1)
+//! buf      v1 update with the full state of the document
+//! varuint  number of subdocuments
+//! repeated:
+//!   string GUID of the subdocument
+//!   buf    deep update of the subdocument
+//! ```
+//!
+//! Only subdocuments that should load are included, in GUID order. Applying a
+//! deep update decodes the whole container first, then applies the document's
+//! update and looks up each subdocument by GUID among the document's
+//! subdocuments once that update is committed.
+
+use crate::{
+    decode_update_v1, forget_remote, held_by_current_thread, mark_remote, UpdateDecodeError,
+};
+use jni::sys::jlong;
+use std::fmt;
+use yrs::encoding::read::{Cursor, Read};
+use yrs::encoding::write::Write;
+use yrs::{Doc, ReadTxn, StateVector, Transact, TransactionMut, Update};
+
+/// Version written at the start of every deep update
+pub const DEEP_UPDATE_VERSION: u32 = 1;
+
+/// Deepest subdocument nesting accepted when decoding
+const MAX_DEPTH: usize = 512;
+
+/// Error returned by [`encode_deep_update`] and [`apply_deep_update`]
+#[derive(Debug)]
+pub enum DeepUpdateError {
+    /// The container is truncated, has trailing bytes or an unknown version
+    Malformed(String),
+    /// The update of the document or of a subdocument could not be decoded
+    Decode(UpdateDecodeError),
+    /// An update could not be integrated
+    Apply(String),
+    /// The container names a subdocument the document does not have
+    MissingSubdoc(String),
+    /// The current thread holds a transaction on the document or a subdocument
+    Held(String),
+}
+
+impl fmt::Display for DeepUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepUpdateError::Malformed(reason) => write!(f, "malformed deep update: {}", reason),
+            DeepUpdateError::Decode(e) => write!(f, "{}", e),
+            DeepUpdateError::Apply(reason) => f.write_str(reason),
+            DeepUpdateError::MissingSubdoc(guid) => {
+                write!(f, "document has no subdocument with GUID {}", guid)
+            }
+            DeepUpdateError::Held(guid) => write!(
+                f,
+                "this thread already holds a transaction on document {}",
+                guid
+            ),
+        }
+    }
+}
+
+/// A decoded deep update
+struct DeepUpdate {
+    update: Update,
+    subdocs: Vec<(String, DeepUpdate)>,
+}
+
+/// Encodes the full state of the document read by `txn` and of its
+/// subdocuments, recursively
+pub fn encode_deep_update<T: ReadTxn>(txn: &T) -> Result<Vec<u8>, DeepUpdateError> {
+    let mut buf = Vec::new();
+    buf.write_var(DEEP_UPDATE_VERSION);
+    buf.write_buf(txn.encode_state_as_update_v1(&StateVector::default()));
+
+    let mut subdocs: Vec<Doc> = txn
+        .subdocs()
+        .filter(|subdoc| subdoc.should_load())
+        .cloned()
+        .collect();
+    subdocs.sort_by_cached_key(|subdoc| subdoc.guid().to_string());
+    buf.write_var(subdocs.len() as u32);
+    for subdoc in subdocs {
+        let guid = subdoc.guid().to_string();
+        if held_by_current_thread(&subdoc) {
+            return Err(DeepUpdateError::Held(guid));
+        }
+        let nested = encode_deep_update(&subdoc.transact())?;
+        buf.write_string(&guid);
+        buf.write_buf(nested);
+    }
+    Ok(buf)
+}
+
+/// Applies a deep update to `doc` and its subdocuments
+///
+/// Each document is updated in a transaction of its own, since subdocuments
+/// only become reachable once the transaction that adds them has been
+/// committed. Nothing is applied if the container cannot be decoded; a missing
+/// subdocument is reported after the updates before it have been applied.
+pub fn apply_deep_update(doc: &Doc, bytes: &[u8]) -> Result<(), DeepUpdateError> {
+    let deep = decode_deep_update(bytes, 0)?;
+    apply_decoded(doc, deep)
+}
+
+fn apply_decoded(doc: &Doc, deep: DeepUpdate) -> Result<(), DeepUpdateError> {
+    if held_by_current_thread(doc) {
+        return Err(DeepUpdateError::Held(doc.guid().to_string()));
+    }
+    {
+        let mut txn = doc.transact_mut();
+        let txn_key = &txn as *const TransactionMut as jlong;
+        mark_remote(&txn);
+        let applied = txn.apply_update(deep.update);
+        // Observers run on commit and still need to see the mark
+        txn.commit();
+        forget_remote(txn_key);
+        applied.map_err(|e| DeepUpdateError::Apply(format!("failed to apply update: {:?}", e)))?;
+    }
+    if deep.subdocs.is_empty() {
+        return Ok(());
+    }
+    let subdocs: Vec<Doc> = doc.transact().subdocs().cloned().collect();
+    for (guid, nested) in deep.subdocs {
+        let subdoc = subdocs
+            .iter()
+            .find(|subdoc| subdoc.guid().as_ref() == guid)
+            .ok_or(DeepUpdateError::MissingSubdoc(guid))?;
+        apply_decoded(subdoc, nested)?;
+    }
+    Ok(())
+}
+
+fn decode_deep_update(bytes: &[u8], depth: usize) -> Result<DeepUpdate, DeepUpdateError> {
+    if depth > MAX_DEPTH {
+        return Err(DeepUpdateError::Malformed(format!(
+            "subdocuments nested more than {} levels deep",
+            MAX_DEPTH
+        )));
+    }
+    let malformed = |e: yrs::encoding::read::Error| DeepUpdateError::Malformed(e.to_string());
+    let mut cursor = Cursor::new(bytes);
+    let version: u32 = cursor.read_var().map_err(malformed)?;
+    if version != DEEP_UPDATE_VERSION {
+        return Err(DeepUpdateError::Malformed(format!(
+            "unsupported version {}",
+            version
+        )));
+    }
+    let update =
+        decode_update_v1(cursor.read_buf().map_err(malformed)?).map_err(DeepUpdateError::Decode)?;
+    let count: u32 = cursor.read_var().map_err(malformed)?;
+    let mut subdocs = Vec::new();
+    for _ in 0..count {
+        let guid = cursor.read_string().map_err(malformed)?.to_string();
+        let nested = decode_deep_update(cursor.read_buf().map_err(malformed)?, depth + 1)?;
+        subdocs.push((guid, nested));
+    }
+    if cursor.has_content() {
+        return Err(DeepUpdateError::Malformed(
+            "trailing bytes after the last subdocument".to_string(),
+        ));
+    }
+    Ok(DeepUpdate { update, subdocs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Array, GetString, Options, Out, Text};
+
+    fn subdoc_with_text(guid: &str, body: &str) -> Doc {
+        let doc = Doc::with_options(Options {
+            guid: guid.into(),
+            ..Options::default()
+        });
+        let text = doc.get_or_insert_text("body");
+        text.push(&mut doc.transact_mut(), body);
+        doc
+    }
+
+    fn first_subdoc(doc: &Doc) -> Doc {
+        let array = doc.get_or_insert_array("docs");
+        match array.get(&doc.transact(), 0) {
+            Some(Out::YDoc(subdoc)) => subdoc,
+            other => panic!("expected a subdocument, got {:?}", other),
+        }
+    }
+
+    fn body(doc: &Doc) -> String {
+        let text = doc.get_or_insert_text("body");
+        let txn = doc.transact();
+        text.get_string(&txn)
+    }
+
+    #[test]
+    fn test_deep_update_round_trip() {
+        let child = subdoc_with_text("child", "child text");
+        let grandchild = subdoc_with_text("grandchild", "grandchild text");
+        child
+            .get_or_insert_array("docs")
+            .push_back(&mut child.transact_mut(), grandchild);
+        let source = Doc::new();
+        source
+            .get_or_insert_array("docs")
+            .push_back(&mut source.transact_mut(), child);
+
+        let encoded = encode_deep_update(&source.transact()).unwrap();
+
+        let target = Doc::new();
+        apply_deep_update(&target, &encoded).unwrap();
+        let child = first_subdoc(&target);
+        assert_eq!(child.guid().as_ref(), "child");
+        assert_eq!(body(&child), "child text");
+        let grandchild = first_subdoc(&child);
+        assert_eq!(grandchild.guid().as_ref(), "grandchild");
+        assert_eq!(body(&grandchild), "grandchild text");
+    }
+
+    #[test]
+    fn test_malformed_deep_update_is_rejected() {
+        let source = Doc::new();
+        let mut encoded = encode_deep_update(&source.transact()).unwrap();
+        encoded.push(0);
+
+        let target = Doc::new();
+        let result = apply_deep_update(&target, &encoded);
+        assert!(matches!(result, Err(DeepUpdateError::Malformed(_))));
+
+        let mut missing = Vec::new();
+        missing.write_var(DEEP_UPDATE_VERSION);
+        missing.write_buf(
+            source
+                .transact()
+                .encode_state_as_update_v1(&StateVector::default()),
+        );
+        missing.write_var(1u32);
+        missing.write_string("absent");
+        missing.write_buf(encode_deep_update(&source.transact()).unwrap());
+        let result = apply_deep_update(&target, &missing);
+        assert!(matches!(result, Err(DeepUpdateError::MissingSubdoc(guid)) if guid == "absent"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Barrier between observer dispatch and document destruction.
+//!
+//! Every observer closure enters the document's `DispatchGate` before touching
+//! the `DocWrapper`, and `nativeDestroy` closes the gate and waits for all
+//! in-flight dispatches to finish before freeing it. Once closed, the gate
+//! turns later callbacks into no-ops.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+#[derive(Default)]
+struct GateState {
+    /// Set once the owning document starts being destroyed
+    closed: bool,
+    /// Threads currently inside a dispatch (one entry per nested dispatch)
+    active: Vec<ThreadId>,
+}
+
+/// Tracks in-flight observer dispatches for one document
+#[derive(Default)]
+pub struct DispatchGate {
+    state: Mutex<GateState>,
+    idle: Condvar,
+}
+
+/// Error returned when a document is destroyed from inside one of its own callbacks
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReentrantClose;
+
+/// Marks a dispatch as in flight until dropped
+pub struct DispatchGuard {
+    gate: Arc<DispatchGate>,
+    thread: ThreadId,
+}
+
+impl DispatchGate {
+    fn lock(&self) -> MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Registers an in-flight dispatch, or returns `None` if the document is
+    /// being destroyed and the callback should be skipped.
+    pub fn enter(self: &Arc<Self>) -> Option<DispatchGuard> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        let thread = thread::current().id();
+        state.active.push(thread);
+        Some(DispatchGuard {
+            gate: Arc::clone(self),
+            thread,
+        })
+    }
+
+    /// Closes the gate and blocks until no dispatch is in flight.
+    ///
+    /// Fails without closing the gate if the current thread is itself inside a
+    /// dispatch, since waiting would deadlock and freeing would pull the
+    /// document out from under the running callback.
+    pub fn close_and_wait(&self) -> Result<(), ReentrantClose> {
+        let current = thread::current().id();
+        let mut state = self.lock();
+        if state.active.contains(&current) {
+            return Err(ReentrantClose);
+        }
+        state.closed = true;
+        while !state.active.is_empty() {
+            state = self.idle.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        Ok(())
+    }
+
+    /// Whether the current thread is inside a dispatch
+    pub fn dispatching_on_current_thread(&self) -> bool {
+        self.lock().active.contains(&thread::current().id())
+    }
+
+    /// Number of dispatches currently in flight
+    pub fn in_flight(&self) -> usize {
+        self.lock().active.len()
+    }
+}
+
+impl Drop for DispatchGuard {
+    fn drop(&mut self) {
+        let mut state = self.gate.lock();
+        if let Some(pos) = state.active.iter().position(|t| *t == self.thread) {
+            state.active.swap_remove(pos);
+        }
+        if state.active.is_empty() {
+            self.gate.idle.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_enter_after_close_is_rejected() {
+        let gate = Arc::new(DispatchGate::default());
+        assert_eq!(gate.close_and_wait(), Ok(()));
+        assert!(gate.enter().is_none());
+    }
+
+    #[test]
+    fn test_reentrant_close_fails() {
+        let gate = Arc::new(DispatchGate::default());
+        let guard = gate.enter().unwrap();
+        assert!(gate.dispatching_on_current_thread());
+        assert_eq!(gate.close_and_wait(), Err(ReentrantClose));
+        drop(guard);
+        assert!(!gate.dispatching_on_current_thread());
+        assert_eq!(gate.close_and_wait(), Ok(()));
+    }
+
+    #[test]
+    fn test_close_waits_for_in_flight_dispatch() {
+        let gate = Arc::new(DispatchGate::default());
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let worker_gate = Arc::clone(&gate);
+        let worker = thread::spawn(move || {
+            let _guard = worker_gate.enter().unwrap();
+            entered_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        entered_rx.recv().unwrap();
+        assert_eq!(gate.in_flight(), 1);
+
+        let closer_gate = Arc::clone(&gate);
+        let (closed_tx, closed_rx) = mpsc::channel();
+        let closer = thread::spawn(move || {
+            closer_gate.close_and_wait().unwrap();
+            closed_tx.send(()).unwrap();
+        });
+
+        // The closer must still be blocked while the dispatch is in flight
+        assert!(closed_rx.recv_timeout(Duration::from_millis(50)).is_err());
+        release_tx.send(()).unwrap();
+        closed_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        worker.join().unwrap();
+        closer.join().unwrap();
+        assert_eq!(gate.in_flight(), 0);
+    }
+}
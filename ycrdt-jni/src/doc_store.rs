@@ -0,0 +1,748 @@
+//! A named collection of documents with a capacity limit.
+//!
+//! Servers that host many documents keep a map from document name to
+//! `JniYDoc`, create entries on first use and close the ones no client has
+//! touched for a while. Done by hand, the lookup and the creation race: two
+//! connections for the same name can both miss and end up editing different
+//! documents, or one closes a document the other has just looked up. A store
+//! does the get-or-create under one lock and evicts the least recently used
+//! documents once it holds more than its capacity.
+//!
+//! The store records document handles, not documents: creating a document
+//! hands its pointer to Java like `JniYDoc.nativeCreate`, and the evicted
+//! pointers are returned to Java, which closes the wrappers it holds for them.
+//!
+//! A store can also compact its idle documents: a document whose change
+//! counter has not moved for a while, and that changed since it was last
+//! compacted, is encoded as a single update and reported to Java, which can
+//! replace the update log it persisted for the document with that snapshot.
+//! Compaction runs on demand or on a background thread per store; documents
+//! are encoded outside the store lock, and skipped while a transaction is open.
+
+use crate::ydoc::long_array_to_java;
+use crate::{
+    free_if_valid, get_ref_or_throw, strings_to_java, throw_exception, throw_jni_error,
+    to_java_ptr, trace_native, DocPtr, DocWrapper, JavaPtr, JniEnvExt, JniResultExt, StringPolicy,
+};
+use jni::objects::{JClass, JObject, JObjectArray, JString, JValue};
+use jni::sys::{jboolean, jint, jlong, jlongArray, jobjectArray, JNI_FALSE, JNI_TRUE};
+use jni::{Executor, JNIEnv};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use yrs::{Doc, ReadTxn, StateVector, Transact};
+
+/// Signature of `JniDocStore.CompactionCallback.compacted`
+const COMPACTED_SIG: &str = "(Ljava/lang/String;J[B)V";
+
+/// An entry of the store
+struct StoreEntry {
+    /// Handle of the document
+    ptr: jlong,
+    /// Value of the store clock when the entry was last looked up
+    last_used: u64,
+    /// Change counter of the document when a compaction pass last saw it
+    seen_changes: u64,
+    /// When a compaction pass first saw `seen_changes`
+    seen_at: Instant,
+    /// Change counter of the document when it was last compacted
+    compacted_changes: u64,
+}
+
+impl StoreEntry {
+    fn new(ptr: jlong, last_used: u64) -> Self {
+        StoreEntry {
+            ptr,
+            last_used,
+            seen_changes: 0,
+            seen_at: Instant::now(),
+            compacted_changes: 0,
+        }
+    }
+}
+
+struct StoreInner {
+    /// Largest number of documents kept, or 0 for no limit
+    capacity: usize,
+    /// Incremented on every lookup, to order entries by last use
+    clock: u64,
+    entries: HashMap<String, StoreEntry>,
+}
+
+/// Result of [`DocStore::get_or_insert_with`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lookup {
+    /// Handle of the document stored under the name
+    pub ptr: jlong,
+    /// Whether the document was created by this lookup
+    pub created: bool,
+    /// Names and handles of the documents evicted to make room for it
+    pub evicted: Vec<(String, jlong)>,
+}
+
+/// A document encoded by a compaction pass
+#[derive(Debug)]
+pub struct Compaction {
+    /// Name the document is stored under
+    pub name: String,
+    /// Handle of the document
+    pub ptr: jlong,
+    /// The whole document state, v1-encoded
+    pub state: Vec<u8>,
+}
+
+/// Background thread compacting the documents of one store
+struct Compactor {
+    /// Dropped to stop the thread
+    _stop: Sender<()>,
+    /// Cleared when the thread is stopped, so a pass in progress stops reporting
+    running: Arc<AtomicBool>,
+}
+
+impl Drop for Compactor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+    }
+}
+
+/// Documents keyed by name, evicted least recently used first
+pub struct DocStore {
+    inner: Arc<Mutex<StoreInner>>,
+    compactor: Mutex<Option<Compactor>>,
+}
+
+impl DocStore {
+    /// Creates an empty store holding at most `capacity` documents, or any
+    /// number of them if `capacity` is 0
+    pub fn new(capacity: usize) -> Self {
+        DocStore {
+            inner: Arc::new(Mutex::new(StoreInner {
+                capacity,
+                clock: 0,
+                entries: HashMap::new(),
+            })),
+            compactor: Mutex::new(None),
+        }
+    }
+
+    /// Returns the document stored under `name`, storing the one returned by
+    /// `create` if there is none. Storing a document beyond the capacity
+    /// evicts the least recently used others; the returned one is never evicted.
+    pub fn get_or_insert_with(&self, name: &str, create: impl FnOnce() -> jlong) -> Lookup {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.clock += 1;
+        let now = inner.clock;
+        if let Some(entry) = inner.entries.get_mut(name) {
+            entry.last_used = now;
+            return Lookup {
+                ptr: entry.ptr,
+                created: false,
+                evicted: Vec::new(),
+            };
+        }
+        let ptr = create();
+        inner
+            .entries
+            .insert(name.to_string(), StoreEntry::new(ptr, now));
+        let mut evicted = Vec::new();
+        while inner.capacity > 0 && inner.entries.len() > inner.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .filter(|(key, _)| key.as_str() != name)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            let Some(oldest) = oldest else {
+                break;
+            };
+            if let Some(entry) = inner.entries.remove(&oldest) {
+                evicted.push((oldest, entry.ptr));
+            }
+        }
+        Lookup {
+            ptr,
+            created: true,
+            evicted,
+        }
+    }
+
+    /// Returns the handle of the document stored under `name`, marking it as
+    /// used
+    pub fn get(&self, name: &str) -> Option<jlong> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.clock += 1;
+        let now = inner.clock;
+        inner.entries.get_mut(name).map(|entry| {
+            entry.last_used = now;
+            entry.ptr
+        })
+    }
+
+    /// Removes the document stored under `name`, if it is the one with handle
+    /// `ptr` when given. Returns the handle of the removed document.
+    pub fn remove(&self, name: &str, ptr: Option<jlong>) -> Option<jlong> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match inner.entries.get(name) {
+            Some(entry) if ptr.is_none_or(|ptr| ptr == entry.ptr) => {
+                inner.entries.remove(name).map(|entry| entry.ptr)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the stored documents, sorted
+    pub fn names(&self) -> Vec<String> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut names: Vec<String> = inner.entries.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the number of stored documents
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    /// Returns whether the store holds no documents
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every document, returning their handles least recently used first
+    pub fn drain(&self) -> Vec<jlong> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries: Vec<StoreEntry> = inner.entries.drain().map(|(_, entry)| entry).collect();
+        entries.sort_by_key(|entry| entry.last_used);
+        entries.into_iter().map(|entry| entry.ptr).collect()
+    }
+
+    /// Encodes the documents that changed since they were last compacted and
+    /// have not changed for `idle_after`
+    pub fn compact_idle(&self, idle_after: Duration) -> Vec<Compaction> {
+        compact_idle(&self.inner, idle_after)
+    }
+
+    /// Starts compacting idle documents every `interval` on a background
+    /// thread, replacing the thread already running. `report` is called on
+    /// that thread with the documents of each pass while it is running.
+    pub fn start_compaction(
+        &self,
+        interval: Duration,
+        idle_after: Duration,
+        report: impl Fn(Vec<Compaction>, &AtomicBool) + Send + 'static,
+    ) -> std::io::Result<()> {
+        let mut compactor = self.compactor.lock().unwrap_or_else(|e| e.into_inner());
+        *compactor = None;
+        let (stop, stopped) = channel::<()>();
+        let running = Arc::new(AtomicBool::new(true));
+        let inner = Arc::clone(&self.inner);
+        let still_running = Arc::clone(&running);
+        std::thread::Builder::new()
+            .name("ycrdt-compaction".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let compactions = compact_idle(&inner, idle_after);
+                    if !compactions.is_empty() && still_running.load(Ordering::Acquire) {
+                        report(compactions, &still_running);
+                    }
+                }
+            })?;
+        *compactor = Some(Compactor {
+            _stop: stop,
+            running,
+        });
+        Ok(())
+    }
+
+    /// Stops the background compaction thread, if any. A pass in progress
+    /// finishes encoding but reports nothing more.
+    pub fn stop_compaction(&self) {
+        *self.compactor.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+fn compact_idle(inner: &Mutex<StoreInner>, idle_after: Duration) -> Vec<Compaction> {
+    let candidates: Vec<(String, jlong, Doc, u64)> = {
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let mut candidates = Vec::new();
+        for (name, entry) in inner.entries.iter_mut() {
+            // Entries are removed before their document is destroyed, so the
+            // handle of an entry is live while the lock is held
+            let Some(wrapper) = (unsafe { DocPtr::from_raw(entry.ptr).as_ref() }) else {
+                continue;
+            };
+            let changes = wrapper.change_counter().get();
+            if changes != entry.seen_changes {
+                entry.seen_changes = changes;
+                entry.seen_at = now;
+            }
+            if changes != entry.compacted_changes && now.duration_since(entry.seen_at) >= idle_after
+            {
+                candidates.push((name.clone(), entry.ptr, wrapper.doc.clone(), changes));
+            }
+        }
+        candidates
+    };
+
+    let mut compactions = Vec::new();
+    for (name, ptr, doc, changes) in candidates {
+        // A document in a transaction is not idle; try again on the next pass
+        let Ok(txn) = doc.try_transact() else {
+            continue;
+        };
+        let state = txn.encode_state_as_update_v1(&StateVector::default());
+        drop(txn);
+        if let Some(entry) = inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .get_mut(&name)
+        {
+            if entry.ptr == ptr {
+                entry.compacted_changes = changes;
+            }
+        }
+        compactions.push(Compaction { name, ptr, state });
+    }
+    compactions
+}
+
+/// Passes one compacted document to `JniDocStore.CompactionCallback`
+fn report_compaction(
+    env: &mut JNIEnv,
+    callback: &JObject,
+    compaction: &Compaction,
+) -> jni::errors::Result<()> {
+    let name = JObject::from(env.new_string(&compaction.name)?);
+    let state = JObject::from(env.byte_array_from_slice(&compaction.state)?);
+    let result = env.call_method(
+        callback,
+        "compacted",
+        COMPACTED_SIG,
+        &[
+            JValue::Object(&name),
+            JValue::Long(compaction.ptr),
+            JValue::Object(&state),
+        ],
+    );
+    env.delete_local_ref(name)?;
+    env.delete_local_ref(state)?;
+    result.map(|_| ())
+}
+
+pub type DocStorePtr = JavaPtr<DocStore>;
+
+/// Creates an empty document store
+///
+/// # Parameters
+/// - `capacity`: Largest number of documents kept, or 0 for no limit
+///
+/// # Returns
+/// A pointer to the store
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeCreate(
+    _env: JNIEnv,
+    _class: JClass,
+    capacity: jint,
+) -> jlong {
+    trace_native!("JniDocStore.nativeCreate");
+    to_java_ptr(DocStore::new(capacity.max(0) as usize))
+}
+
+/// Destroys a document store. The documents it held are not freed; Java
+/// drains and closes them first.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeDestroy(
+    _env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) {
+    trace_native!("JniDocStore.nativeDestroy", ptr);
+    free_if_valid!(DocStorePtr::from_raw(ptr), DocStore);
+}
+
+/// Returns the document stored under a name, creating it if there is none
+///
+/// A created document has the name as its GUID.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+/// - `name`: Name of the document
+///
+/// # Returns
+/// The document pointer, 1 if it was created or 0 if it was already stored,
+/// then the pointers of the documents evicted to make room for it
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeGetOrCreate(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    name: JString,
+) -> jlongArray {
+    trace_native!("JniDocStore.nativeGetOrCreate", ptr);
+    let store = get_ref_or_throw!(
+        &mut env,
+        DocStorePtr::from_raw(ptr),
+        "DocStore",
+        std::ptr::null_mut()
+    );
+    let name = match env.get_rust_string(&name, StringPolicy::default()) {
+        Ok(name) => name,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    };
+    let lookup = store.get_or_insert_with(&name, || {
+        let options = yrs::Options {
+            guid: name.as_str().into(),
+            ..Default::default()
+        };
+        to_java_ptr(DocWrapper::with_options(options))
+    });
+    let mut result = vec![lookup.ptr, lookup.created as jlong];
+    result.extend(lookup.evicted.iter().map(|(_, ptr)| *ptr));
+    long_array_to_java(&mut env, &result).unwrap_or_throw(&mut env)
+}
+
+/// Returns the document stored under a name, marking it as used
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+/// - `name`: Name of the document
+///
+/// # Returns
+/// The document pointer, or 0 if no document is stored under the name
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeGet(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    name: JString,
+) -> jlong {
+    trace_native!("JniDocStore.nativeGet", ptr);
+    let store = get_ref_or_throw!(&mut env, DocStorePtr::from_raw(ptr), "DocStore", 0);
+    match env.get_rust_string(&name, StringPolicy::default()) {
+        Ok(name) => store.get(&name).unwrap_or(0),
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            0
+        }
+    }
+}
+
+/// Removes the document stored under a name, if it is the given document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+/// - `name`: Name of the document
+/// - `doc_ptr`: Pointer of the document to remove, or 0 for any
+///
+/// # Returns
+/// Whether a document was removed
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeRemove(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    name: JString,
+    doc_ptr: jlong,
+) -> jboolean {
+    trace_native!("JniDocStore.nativeRemove", ptr, doc_ptr);
+    let store = get_ref_or_throw!(&mut env, DocStorePtr::from_raw(ptr), "DocStore", JNI_FALSE);
+    let name = match env.get_rust_string(&name, StringPolicy::default()) {
+        Ok(name) => name,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return JNI_FALSE;
+        }
+    };
+    let expected = (doc_ptr != 0).then_some(doc_ptr);
+    if store.remove(&name, expected).is_some() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Compacts the idle documents of a store on the calling thread
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+/// - `idle_millis`: How long a document must not have changed to be compacted
+/// - `callback`: The `JniDocStore.CompactionCallback` to report documents to
+///
+/// # Returns
+/// The number of documents compacted
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeCompactIdle(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    idle_millis: jlong,
+    callback: JObject,
+) -> jint {
+    trace_native!("JniDocStore.nativeCompactIdle", ptr, idle_millis);
+    let store = get_ref_or_throw!(&mut env, DocStorePtr::from_raw(ptr), "DocStore", 0);
+    let compactions = store.compact_idle(Duration::from_millis(idle_millis.max(0) as u64));
+    for compaction in &compactions {
+        // The callback reports its own failures; an error here is a pending
+        // exception that is left for the caller
+        if report_compaction(&mut env, &callback, compaction).is_err() {
+            return 0;
+        }
+    }
+    compactions.len() as jint
+}
+
+/// Starts compacting the idle documents of a store on a background thread
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+/// - `interval_millis`: Time between compaction passes
+/// - `idle_millis`: How long a document must not have changed to be compacted
+/// - `callback`: The `JniDocStore.CompactionCallback` to report documents to
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeStartCompaction(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    interval_millis: jlong,
+    idle_millis: jlong,
+    callback: JObject,
+) {
+    trace_native!(
+        "JniDocStore.nativeStartCompaction",
+        ptr,
+        interval_millis,
+        idle_millis
+    );
+    let store = get_ref_or_throw!(&mut env, DocStorePtr::from_raw(ptr), "DocStore");
+    let setup = env.new_global_ref(&callback).and_then(|callback| {
+        let executor = Executor::new(Arc::new(env.get_java_vm()?));
+        Ok((callback, executor))
+    });
+    let (callback, executor) = match setup {
+        Ok(setup) => setup,
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to start compaction: {}", e));
+            return;
+        }
+    };
+    let report = move |compactions: Vec<Compaction>, running: &AtomicBool| {
+        let _ = executor.with_attached(|env| {
+            for compaction in &compactions {
+                if !running.load(Ordering::Acquire) {
+                    break;
+                }
+                let result = report_compaction(env, callback.as_obj(), compaction);
+                // The callback reports its own failures; never leave an
+                // exception pending on this thread
+                if env.exception_check().unwrap_or(false) {
+                    let _ = env.exception_clear();
+                }
+                result?;
+            }
+            Ok::<(), jni::errors::Error>(())
+        });
+    };
+    if let Err(e) = store.start_compaction(
+        Duration::from_millis(interval_millis.max(1) as u64),
+        Duration::from_millis(idle_millis.max(0) as u64),
+        report,
+    ) {
+        throw_exception(&mut env, &format!("Failed to start compaction: {}", e));
+    }
+}
+
+/// Stops the background compaction of a store
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeStopCompaction(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) {
+    trace_native!("JniDocStore.nativeStopCompaction", ptr);
+    let store = get_ref_or_throw!(&mut env, DocStorePtr::from_raw(ptr), "DocStore");
+    store.stop_compaction();
+}
+
+/// Returns the names of the stored documents, sorted
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeNames(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jobjectArray {
+    trace_native!("JniDocStore.nativeNames", ptr);
+    let store = get_ref_or_throw!(
+        &mut env,
+        DocStorePtr::from_raw(ptr),
+        "DocStore",
+        std::ptr::null_mut()
+    );
+    let names = store.names();
+    strings_to_java(&mut env, names.iter())
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Returns the number of stored documents
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeSize(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniDocStore.nativeSize", ptr);
+    let store = get_ref_or_throw!(&mut env, DocStorePtr::from_raw(ptr), "DocStore", 0);
+    store.len() as jint
+}
+
+/// Removes every document from the store
+///
+/// # Parameters
+/// - `ptr`: Pointer to the store
+///
+/// # Returns
+/// The pointers of the removed documents, least recently used first
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniDocStore_nativeDrain(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jlongArray {
+    trace_native!("JniDocStore.nativeDrain", ptr);
+    let store = get_ref_or_throw!(
+        &mut env,
+        DocStorePtr::from_raw(ptr),
+        "DocStore",
+        std::ptr::null_mut()
+    );
+    let drained = store.drain();
+    long_array_to_java(&mut env, &drained).unwrap_or_throw(&mut env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_or_insert_evicts_least_recently_used() {
+        let store = DocStore::new(2);
+        let mut next = 0;
+        let mut create = || {
+            next += 1;
+            next
+        };
+        assert_eq!(
+            store.get_or_insert_with("a", &mut create),
+            Lookup {
+                ptr: 1,
+                created: true,
+                evicted: vec![]
+            }
+        );
+        store.get_or_insert_with("b", &mut create);
+        // Touching "a" makes "b" the least recently used
+        assert_eq!(store.get("a"), Some(1));
+        let lookup = store.get_or_insert_with("c", &mut create);
+        assert_eq!(lookup.ptr, 3);
+        assert_eq!(lookup.evicted, vec![("b".to_string(), 2)]);
+        assert_eq!(store.names(), vec!["a", "c"]);
+
+        let again = store.get_or_insert_with("a", &mut create);
+        assert!(!again.created);
+        assert_eq!(again.ptr, 1);
+    }
+
+    #[test]
+    fn test_compact_idle_encodes_changed_documents_once() {
+        use yrs::updates::decoder::Decode;
+        use yrs::{GetString, Text, Update};
+
+        let store = DocStore::new(0);
+        let lookup = store.get_or_insert_with("a", || to_java_ptr(DocWrapper::new()));
+        store.get_or_insert_with("b", || to_java_ptr(DocWrapper::new()));
+        assert!(store.compact_idle(Duration::ZERO).is_empty());
+
+        let wrapper = unsafe { DocPtr::from_raw(lookup.ptr).as_ref() }.unwrap();
+        let text = wrapper.doc.get_or_insert_text("text");
+        text.insert(&mut wrapper.doc.transact_mut(), 0, "hello");
+        // Not idle yet: the change was first seen by this pass
+        assert!(store.compact_idle(Duration::from_secs(3600)).is_empty());
+
+        let compactions = store.compact_idle(Duration::ZERO);
+        assert_eq!(compactions.len(), 1);
+        assert_eq!(compactions[0].name, "a");
+        let restored = Doc::new();
+        let restored_text = restored.get_or_insert_text("text");
+        restored
+            .transact_mut()
+            .apply_update(Update::decode_v1(&compactions[0].state).unwrap())
+            .unwrap();
+        assert_eq!(restored_text.get_string(&restored.transact()), "hello");
+        assert!(store.compact_idle(Duration::ZERO).is_empty());
+
+        // A document in a transaction is skipped until the transaction ends
+        text.insert(&mut wrapper.doc.transact_mut(), 5, "!");
+        let txn = wrapper.doc.transact_mut();
+        assert!(store.compact_idle(Duration::ZERO).is_empty());
+        drop(txn);
+        assert_eq!(store.compact_idle(Duration::ZERO).len(), 1);
+
+        for ptr in store.drain() {
+            unsafe { crate::free_java_ptr::<DocWrapper>(ptr) };
+        }
+    }
+
+    #[test]
+    fn test_remove_checks_handle() {
+        let store = DocStore::new(0);
+        store.get_or_insert_with("a", || 10);
+        assert_eq!(store.remove("a", Some(11)), None);
+        assert_eq!(store.remove("a", Some(10)), Some(10));
+        assert!(store.is_empty());
+
+        store.get_or_insert_with("x", || 1);
+        store.get_or_insert_with("y", || 2);
+        store.get("x");
+        assert_eq!(store.drain(), vec![2, 1]);
+        assert_eq!(store.len(), 0);
+    }
+
+    #[test]
+    fn test_store_survives_a_panic_while_locked() {
+        let store = DocStore::new(0);
+        store.get_or_insert_with("a", || 1);
+        let poisoned = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _inner = store.inner.lock().unwrap();
+            panic!("poison the store");
+        }));
+        assert!(poisoned.is_err());
+        assert!(store.inner.is_poisoned());
+
+        assert_eq!(store.get("a"), Some(1));
+        assert_eq!(store.remove("a", None), Some(1));
+        assert!(store.is_empty());
+    }
+}
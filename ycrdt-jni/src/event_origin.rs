@@ -0,0 +1,143 @@
+//! Origin information attached to observer events.
+//!
+//! yrs does not record whether a transaction applied an update from a peer,
+//! so the natives that apply updates mark their transaction here. Marks are
+//! keyed by the transaction's address, which for transactions handed to Java
+//! is also its handle, and are removed when the transaction is freed.
+
+use crate::find_callback_class;
+use dashmap::DashSet;
+use jni::objects::{JObject, JValue};
+use jni::sys::jlong;
+use jni::JNIEnv;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use yrs::block::ClientID;
+use yrs::{ReadTxn, TransactionMut};
+
+lazy_static! {
+    /// Transactions that have applied at least one remote update
+    static ref REMOTE_TXNS: DashSet<jlong> = DashSet::new();
+}
+
+/// Number of entries in `REMOTE_TXNS`, so freeing a transaction skips the
+/// map when no remote transactions are open.
+static REMOTE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn txn_key(txn: &TransactionMut) -> jlong {
+    txn as *const TransactionMut as jlong
+}
+
+/// Records that `txn` applied an update received from a peer
+pub fn mark_remote(txn: &TransactionMut) {
+    if REMOTE_TXNS.insert(txn_key(txn)) {
+        REMOTE_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Whether `txn` applied an update received from a peer
+pub fn is_remote(txn: &TransactionMut) -> bool {
+    REMOTE_COUNT.load(Ordering::Acquire) != 0 && REMOTE_TXNS.contains(&txn_key(txn))
+}
+
+/// Drops the mark of a transaction that is being freed
+pub fn forget_remote(txn_ptr: jlong) {
+    if REMOTE_COUNT.load(Ordering::Acquire) != 0 && REMOTE_TXNS.remove(&txn_ptr).is_some() {
+        REMOTE_COUNT.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// The client that made the changes in `txn`
+///
+/// Local transactions belong to the document's own client. For remote ones
+/// this is the only client whose clock advanced, or `None` if several did or
+/// the update only deleted content.
+pub fn originating_client(txn: &TransactionMut) -> Option<ClientID> {
+    if !is_remote(txn) {
+        return Some(txn.doc().client_id());
+    }
+    let before = txn.before_state();
+    let current = txn.state_vector();
+    let mut advanced = current
+        .iter()
+        .filter(|(client, clock)| **clock > before.get(client))
+        .map(|(client, _)| *client);
+    match (advanced.next(), advanced.next()) {
+        (Some(client), None) => Some(client),
+        _ => None,
+    }
+}
+
+/// Builds a `JniEventOrigin` describing `txn`
+pub fn event_origin_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    txn: &TransactionMut,
+) -> jni::errors::Result<JObject<'local>> {
+    let bytes = match txn.origin() {
+        Some(origin) => JObject::from(env.byte_array_from_slice(origin.as_ref())?),
+        None => JObject::null(),
+    };
+    let client_id = originating_client(txn).map_or(-1, |client| client as jlong);
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniEventOrigin")?;
+    let origin = env.new_object(
+        &class,
+        "([BJZ)V",
+        &[
+            JValue::Object(&bytes),
+            JValue::Long(client_id),
+            JValue::Bool(u8::from(!is_remote(txn))),
+        ],
+    )?;
+    Ok(origin)
+}
+
+/// Builds the `JniYEvent` passed to observers of a shared type
+pub fn new_java_event<'local>(
+    env: &mut JNIEnv<'local>,
+    txn: &TransactionMut,
+    target: &JObject,
+    changes: &JObject,
+) -> jni::errors::Result<JObject<'local>> {
+    let origin = event_origin_to_java(env, txn)?;
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYEvent")?;
+    let event = env.new_object(
+        &class,
+        "(Ljava/lang/Object;Ljava/util/List;Lnet/carcdr/ycrdt/jni/JniEventOrigin;)V",
+        &[
+            JValue::Object(target),
+            JValue::Object(changes),
+            JValue::Object(&origin),
+        ],
+    )?;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::decoder::Decode;
+    use yrs::{Doc, Options, Text, Transact, Update};
+
+    #[test]
+    fn test_originating_client() {
+        let remote = Doc::with_options(Options::with_client_id(7));
+        let text = remote.get_or_insert_text("text");
+        text.insert(&mut remote.transact_mut(), 0, "hi");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&Default::default());
+
+        let doc = Doc::with_options(Options::with_client_id(1));
+        let mut txn = doc.transact_mut();
+        // Marks left behind by other tests' transactions at the same address
+        forget_remote(txn_key(&txn));
+        assert_eq!(originating_client(&txn), Some(1));
+        txn.apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        mark_remote(&txn);
+        assert!(is_remote(&txn));
+        assert_eq!(originating_client(&txn), Some(7));
+        forget_remote(txn_key(&txn));
+        assert!(!is_remote(&txn));
+    }
+}
@@ -0,0 +1,308 @@
+//! Generic, depth-limited export of shared types into Java objects.
+//!
+//! Every shared type (map, array, text, XML node, subdocument) becomes a
+//! `JniTreeNode`; plain values become Java strings, numbers, booleans,
+//! `byte[]`, `List` and `Map`. Shared types nested deeper than the requested
+//! depth are returned as truncated nodes that carry their kind but no content.
+
+use crate::{any_to_jobject, find_callback_class, JniResult};
+use jni::objects::{JObject, JValue};
+use jni::sys::jint;
+use jni::JNIEnv;
+use yrs::{Any, Array, ArrayRef, GetString, Map, MapRef, Out, ReadTxn, Xml, XmlFragment, XmlOut};
+
+/// Node kinds, matching the declaration order of `JniTreeNode.Kind`
+pub const TREE_KIND_MAP: jint = 0;
+pub const TREE_KIND_ARRAY: jint = 1;
+pub const TREE_KIND_TEXT: jint = 2;
+pub const TREE_KIND_XML_ELEMENT: jint = 3;
+pub const TREE_KIND_XML_FRAGMENT: jint = 4;
+pub const TREE_KIND_XML_TEXT: jint = 5;
+pub const TREE_KIND_DOC: jint = 6;
+pub const TREE_KIND_UNKNOWN: jint = 7;
+pub const TREE_KIND_LINK: jint = 8;
+
+const TREE_NODE_CLASS: &str = "net/carcdr/ycrdt/jni/JniTreeNode";
+const TREE_NODE_CTOR: &str =
+    "(ILjava/lang/String;Ljava/lang/String;Ljava/util/Map;Ljava/util/Map;Ljava/util/List;Z)V";
+
+/// Content of a node before it is turned into a `JniTreeNode`
+#[derive(Default)]
+struct NodeParts<'local> {
+    name: Option<String>,
+    text: Option<String>,
+    attributes: Option<JObject<'local>>,
+    entries: Option<JObject<'local>>,
+    items: Option<JObject<'local>>,
+}
+
+/// Exports every root type of a document into a `Map<String, JniTreeNode>`
+/// ordered by root name
+pub fn export_roots<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    max_depth: u32,
+) -> JniResult<JObject<'local>> {
+    let mut roots: Vec<(&str, Out)> = txn.root_refs().collect();
+    roots.sort_by(|a, b| a.0.cmp(b.0));
+    let map = env.new_object("java/util/LinkedHashMap", "()V", &[])?;
+    for (name, value) in roots {
+        let node = export_out(env, txn, &value, max_depth)?;
+        put(env, &map, name, node)?;
+    }
+    Ok(map)
+}
+
+/// Exports a value. `depth` is the number of shared-type levels still to expand.
+pub fn export_out<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    value: &Out,
+    depth: u32,
+) -> jni::errors::Result<JObject<'local>> {
+    let expand = depth > 0;
+    let child_depth = depth.saturating_sub(1);
+    let (kind, parts) = match value {
+        Out::Any(any) => return any_to_java_deep(env, any),
+        Out::YMap(map) => (
+            TREE_KIND_MAP,
+            NodeParts {
+                entries: expand
+                    .then(|| export_map_entries(env, txn, map, child_depth))
+                    .transpose()?,
+                ..Default::default()
+            },
+        ),
+        Out::YArray(array) => (
+            TREE_KIND_ARRAY,
+            NodeParts {
+                items: expand
+                    .then(|| export_array_items(env, txn, array, child_depth))
+                    .transpose()?,
+                ..Default::default()
+            },
+        ),
+        Out::YText(text) => (
+            TREE_KIND_TEXT,
+            NodeParts {
+                text: expand.then(|| text.get_string(txn)),
+                ..Default::default()
+            },
+        ),
+        Out::YXmlElement(element) => {
+            let mut parts = NodeParts {
+                name: Some(element.tag().to_string()),
+                ..Default::default()
+            };
+            if expand {
+                parts.attributes = Some(export_xml_attributes(env, txn, element.attributes(txn))?);
+                parts.items = Some(export_xml_children(env, txn, element, child_depth)?);
+            }
+            (TREE_KIND_XML_ELEMENT, parts)
+        }
+        Out::YXmlFragment(fragment) => (
+            TREE_KIND_XML_FRAGMENT,
+            NodeParts {
+                items: expand
+                    .then(|| export_xml_children(env, txn, fragment, child_depth))
+                    .transpose()?,
+                ..Default::default()
+            },
+        ),
+        Out::YXmlText(text) => {
+            let mut parts = NodeParts::default();
+            if expand {
+                parts.text = Some(text.get_string(txn));
+                parts.attributes = Some(export_xml_attributes(env, txn, text.attributes(txn))?);
+            }
+            (TREE_KIND_XML_TEXT, parts)
+        }
+        Out::YDoc(doc) => (
+            TREE_KIND_DOC,
+            NodeParts {
+                name: Some(doc.guid().to_string()),
+                ..Default::default()
+            },
+        ),
+        // Not followed, since a link may refer to an entry containing itself
+        Out::YWeakLink(_) => (TREE_KIND_LINK, NodeParts::default()),
+        Out::UndefinedRef(branch) => {
+            // The type has not been materialized locally; show whatever map
+            // entries and sequence items it holds
+            let mut parts = NodeParts::default();
+            if expand {
+                let map = MapRef::from(*branch);
+                let array = ArrayRef::from(*branch);
+                parts.entries = Some(export_map_entries(env, txn, &map, child_depth)?);
+                parts.items = Some(export_array_items(env, txn, &array, child_depth)?);
+            }
+            (TREE_KIND_UNKNOWN, parts)
+        }
+    };
+    new_tree_node(env, kind, parts, !expand)
+}
+
+fn export_map_entries<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    map: &MapRef,
+    depth: u32,
+) -> jni::errors::Result<JObject<'local>> {
+    let mut entries: Vec<(&str, Out)> = map.iter(txn).collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    let result = env.new_object("java/util/LinkedHashMap", "()V", &[])?;
+    for (key, value) in entries {
+        let value = export_out(env, txn, &value, depth)?;
+        put(env, &result, key, value)?;
+    }
+    Ok(result)
+}
+
+fn export_array_items<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    array: &ArrayRef,
+    depth: u32,
+) -> jni::errors::Result<JObject<'local>> {
+    let result = env.new_object("java/util/ArrayList", "()V", &[])?;
+    for value in array.iter(txn) {
+        let value = export_out(env, txn, &value, depth)?;
+        add(env, &result, value)?;
+    }
+    Ok(result)
+}
+
+fn export_xml_children<'local, T: ReadTxn, F: XmlFragment>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    parent: &F,
+    depth: u32,
+) -> jni::errors::Result<JObject<'local>> {
+    let result = env.new_object("java/util/ArrayList", "()V", &[])?;
+    for child in parent.children(txn) {
+        let child: Out = match child {
+            XmlOut::Element(e) => Out::YXmlElement(e),
+            XmlOut::Fragment(f) => Out::YXmlFragment(f),
+            XmlOut::Text(t) => Out::YXmlText(t),
+        };
+        let value = export_out(env, txn, &child, depth)?;
+        add(env, &result, value)?;
+    }
+    Ok(result)
+}
+
+fn export_xml_attributes<'local, 'a, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    attributes: impl Iterator<Item = (&'a str, Out)>,
+) -> jni::errors::Result<JObject<'local>> {
+    let mut attributes: Vec<(&str, Out)> = attributes.collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    let result = env.new_object("java/util/LinkedHashMap", "()V", &[])?;
+    for (key, value) in attributes {
+        // Attribute values are plain values; shared types are not expanded
+        let value = export_out(env, txn, &value, 0)?;
+        put(env, &result, key, value)?;
+    }
+    Ok(result)
+}
+
+/// Converts a plain value, turning arrays and maps into Java `List`/`Map`
+/// and buffers into `byte[]`
+pub fn any_to_java_deep<'local>(
+    env: &mut JNIEnv<'local>,
+    value: &Any,
+) -> jni::errors::Result<JObject<'local>> {
+    match value {
+        Any::Array(items) => {
+            let list = env.new_object("java/util/ArrayList", "()V", &[])?;
+            for item in items.iter() {
+                let item = any_to_java_deep(env, item)?;
+                add(env, &list, item)?;
+            }
+            Ok(list)
+        }
+        Any::Map(entries) => {
+            let mut entries: Vec<_> = entries.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let map = env.new_object("java/util/LinkedHashMap", "()V", &[])?;
+            for (key, value) in entries {
+                let value = any_to_java_deep(env, value)?;
+                put(env, &map, key, value)?;
+            }
+            Ok(map)
+        }
+        Any::Buffer(bytes) => Ok(env.byte_array_from_slice(bytes)?.into()),
+        other => any_to_jobject(env, other),
+    }
+}
+
+fn new_tree_node<'local>(
+    env: &mut JNIEnv<'local>,
+    kind: jint,
+    parts: NodeParts<'local>,
+    truncated: bool,
+) -> jni::errors::Result<JObject<'local>> {
+    let name = match parts.name {
+        Some(name) => JObject::from(env.new_string(name)?),
+        None => JObject::null(),
+    };
+    let text = match parts.text {
+        Some(text) => JObject::from(env.new_string(text)?),
+        None => JObject::null(),
+    };
+    let attributes = parts.attributes.unwrap_or_default();
+    let entries = parts.entries.unwrap_or_default();
+    let items = parts.items.unwrap_or_default();
+    let class = find_callback_class(env, TREE_NODE_CLASS)?;
+    let node = env.new_object(
+        &class,
+        TREE_NODE_CTOR,
+        &[
+            JValue::Int(kind),
+            JValue::Object(&name),
+            JValue::Object(&text),
+            JValue::Object(&attributes),
+            JValue::Object(&entries),
+            JValue::Object(&items),
+            JValue::Bool(truncated as u8),
+        ],
+    )?;
+    for local in [name, text, attributes, entries, items] {
+        env.delete_local_ref(local)?;
+    }
+    Ok(node)
+}
+
+fn put<'local>(
+    env: &mut JNIEnv<'local>,
+    map: &JObject,
+    key: &str,
+    value: JObject<'local>,
+) -> jni::errors::Result<()> {
+    let key = env.new_string(key)?;
+    env.call_method(
+        map,
+        "put",
+        "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        &[JValue::Object(&key), JValue::Object(&value)],
+    )?;
+    env.delete_local_ref(key)?;
+    env.delete_local_ref(value)?;
+    Ok(())
+}
+
+fn add<'local>(
+    env: &mut JNIEnv<'local>,
+    list: &JObject,
+    value: JObject<'local>,
+) -> jni::errors::Result<()> {
+    env.call_method(
+        list,
+        "add",
+        "(Ljava/lang/Object;)Z",
+        &[JValue::Object(&value)],
+    )?;
+    env.delete_local_ref(value)?;
+    Ok(())
+}
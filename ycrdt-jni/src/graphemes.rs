@@ -0,0 +1,84 @@
+//! Deleting whole grapheme clusters.
+//!
+//! What a user sees as one character can be several code points: an emoji
+//! with a skin tone modifier, a flag, a family joined with zero width joiners,
+//! or a letter with combining marks. Java code that handles backspace by
+//! deleting one `char` or code point leaves the rest of such a cluster in the
+//! text. The ranges here cover whole extended grapheme clusters as defined by
+//! Unicode Standard Annex #29.
+
+use crate::unit_len;
+use unicode_segmentation::UnicodeSegmentation;
+use yrs::OffsetKind;
+
+/// Range of `count` grapheme clusters of `content` next to `index`
+///
+/// A positive `count` covers the clusters from the one containing `index`
+/// onwards, a negative one the clusters before `index`, including the one it is
+/// inside of. The range stops at the start or end of the content. Offsets are
+/// in the units of `kind`.
+///
+/// # Returns
+/// The start and length of the range, or `None` if `index` is past the end
+pub fn grapheme_range(
+    content: &str,
+    kind: OffsetKind,
+    index: u32,
+    count: i32,
+) -> Option<(u32, u32)> {
+    let mut bounds = vec![0];
+    let mut offset = 0;
+    for cluster in content.graphemes(true) {
+        offset += unit_len(cluster, kind);
+        bounds.push(offset);
+    }
+    if index > offset {
+        return None;
+    }
+    // Last cluster boundary at or before index
+    let at = bounds.partition_point(|&b| b <= index) - 1;
+    let (first, last) = if count >= 0 {
+        (at, (at + count as usize).min(bounds.len() - 1))
+    } else {
+        let end = if bounds[at] == index { at } else { at + 1 };
+        (end.saturating_sub(count.unsigned_abs() as usize), end)
+    };
+    Some((bounds[first], bounds[last] - bounds[first]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "a", a family of three joined by ZWJs, "e" with a combining acute, "b"
+    const CONTENT: &str = "a\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}e\u{301}b";
+
+    #[test]
+    fn test_forward_range_covers_whole_clusters() {
+        let kind = OffsetKind::Utf16;
+        assert_eq!(grapheme_range(CONTENT, kind, 0, 1), Some((0, 1)));
+        assert_eq!(grapheme_range(CONTENT, kind, 1, 1), Some((1, 8)));
+        // Inside the family
+        assert_eq!(grapheme_range(CONTENT, kind, 4, 1), Some((1, 8)));
+        assert_eq!(grapheme_range(CONTENT, kind, 1, 2), Some((1, 10)));
+        assert_eq!(grapheme_range(CONTENT, kind, 9, 5), Some((9, 3)));
+        assert_eq!(grapheme_range(CONTENT, kind, 12, 1), Some((12, 0)));
+        assert_eq!(grapheme_range(CONTENT, kind, 13, 1), None);
+    }
+
+    #[test]
+    fn test_backward_range_covers_whole_clusters() {
+        let kind = OffsetKind::Bytes;
+        let len = CONTENT.len() as u32;
+        assert_eq!(grapheme_range(CONTENT, kind, len, -1), Some((len - 1, 1)));
+        assert_eq!(
+            grapheme_range(CONTENT, kind, len - 1, -1),
+            Some((len - 4, 3))
+        );
+        assert_eq!(grapheme_range(CONTENT, kind, len - 4, -1), Some((1, 18)));
+        // Inside the family
+        assert_eq!(grapheme_range(CONTENT, kind, 5, -1), Some((1, 18)));
+        assert_eq!(grapheme_range(CONTENT, kind, 5, -9), Some((0, 19)));
+        assert_eq!(grapheme_range(CONTENT, kind, 0, -1), Some((0, 0)));
+    }
+}
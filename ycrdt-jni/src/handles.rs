@@ -0,0 +1,298 @@
+//! Registry of live native handles.
+//!
+//! Every pointer handed to Java through `to_java_ptr` is recorded here so that
+//! `free_java_ptr` can tolerate repeated destroy calls, and so that a shared
+//! type destroyed while observers still reference it is freed only once the
+//! last of those observers is removed.
+//!
+//! Each boxed value is preceded by a [`Header`] recording its type. `JavaPtr`
+//! checks it before every dereference, so a handle of one type passed where
+//! another is expected (a map where a text is expected, say) is rejected
+//! instead of being reinterpreted. The handle still points at the value itself,
+//! and checking it only reads the memory next to the value.
+//!
+//! A box whose value is dropped can be reused for a new value of the same type,
+//! as the transaction pool does. The header then counts the reuses, and the
+//! count is carried in bits of the handle that user-space addresses leave
+//! clear, so a handle to the dropped value no longer matches the box.
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use jni::sys::jlong;
+use lazy_static::lazy_static;
+use std::any::type_name;
+use std::mem::{align_of, size_of};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Position of the generation in a handle
+const GENERATION_SHIFT: u32 = 48;
+
+/// Bits of a handle holding the generation. User-space addresses leave them
+/// clear on the supported 64-bit targets; Android keeps its pointer tag in the
+/// byte above them.
+const GENERATION_MASK: jlong = 0xff << GENERATION_SHIFT;
+
+/// Returns the address of the value a handle points at
+pub fn handle_address(ptr: jlong) -> jlong {
+    ptr & !GENERATION_MASK
+}
+
+fn handle_generation(ptr: jlong) -> u8 {
+    ((ptr & GENERATION_MASK) >> GENERATION_SHIFT) as u8
+}
+
+/// Type tag stored immediately before every boxed value handed to Java
+#[repr(C, align(16))]
+pub(crate) struct Header {
+    /// Type of the boxed value, as reported by `std::any::type_name`
+    type_name: &'static str,
+    /// Set when Java destroyed the handle; the value may still be waiting for
+    /// its observers to be removed
+    released: AtomicBool,
+    /// Number of times the box was reused, wrapping around
+    generation: AtomicU8,
+}
+
+impl Header {
+    fn new<T>() -> Self {
+        Self {
+            type_name: type_name::<T>(),
+            released: AtomicBool::new(false),
+            generation: AtomicU8::new(0),
+        }
+    }
+}
+
+/// A value and its header, laid out so the value starts right after the header
+#[repr(C)]
+pub(crate) struct Tagged<T> {
+    header: Header,
+    value: T,
+}
+
+impl<T> Tagged<T> {
+    /// Wraps `value` with a header tagging it as a `U`
+    pub(crate) fn new_as<U>(value: T) -> Self {
+        const {
+            assert!(align_of::<T>() <= align_of::<Header>());
+        }
+        Self {
+            header: Header::new::<U>(),
+            value,
+        }
+    }
+
+    /// Returns the handle of a boxed value
+    ///
+    /// # Safety
+    /// `tagged` must point to a live box
+    pub(crate) unsafe fn handle(tagged: *mut Self) -> jlong {
+        let address = std::ptr::addr_of_mut!((*tagged).value) as jlong;
+        assert_eq!(
+            address & GENERATION_MASK,
+            0,
+            "address overlaps the handle generation"
+        );
+        let generation = (*tagged).header.generation.load(Ordering::Acquire);
+        address | (jlong::from(generation) << GENERATION_SHIFT)
+    }
+
+    /// Returns the box a handle points into
+    pub(crate) fn from_handle(ptr: jlong) -> *mut Self {
+        (handle_address(ptr) as *mut u8)
+            .wrapping_sub(size_of::<Header>())
+            .cast()
+    }
+
+    /// Returns the boxed value
+    pub(crate) fn value_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    /// Gives a box whose value was dropped a new generation, so handles to the
+    /// dropped value are rejected once the box is reused
+    pub(crate) fn retire(&self) {
+        self.header.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Returns the header of a handle
+///
+/// # Safety
+/// `ptr` must be a handle whose box has not been freed
+unsafe fn header<'a>(ptr: jlong) -> &'a Header {
+    &*Tagged::<()>::from_handle(ptr).cast::<Header>()
+}
+
+/// Book-keeping for a single live handle
+struct HandleState {
+    /// Number of subscriptions registered against this handle
+    observers: usize,
+    /// Set when Java destroyed the handle while observers were still active;
+    /// the handle is freed with this function once `observers` reaches zero
+    pending_drop: Option<unsafe fn(jlong)>,
+}
+
+lazy_static! {
+    static ref HANDLES: DashMap<jlong, HandleState> = DashMap::new();
+}
+
+unsafe fn drop_boxed<T>(ptr: jlong) {
+    drop(Box::from_raw(Tagged::<T>::from_handle(ptr)));
+}
+
+/// Moves `value` to the heap behind a header and records it as live
+pub(crate) fn box_handle<T>(value: T) -> jlong {
+    // SAFETY: the box was just allocated
+    let ptr = unsafe { Tagged::handle(Box::into_raw(Box::new(Tagged::new_as::<T>(value)))) };
+    register_handle(ptr);
+    ptr
+}
+
+/// Records a newly boxed value as live
+pub(crate) fn register_handle(ptr: jlong) {
+    HANDLES.insert(
+        ptr,
+        HandleState {
+            observers: 0,
+            pending_drop: None,
+        },
+    );
+}
+
+/// Forgets a handle whose value is freed by other means
+pub(crate) fn unregister_handle(ptr: jlong) {
+    HANDLES.remove(&ptr);
+}
+
+/// Returns whether the pointer is a live handle
+pub fn is_live_handle(ptr: jlong) -> bool {
+    HANDLES.contains_key(&ptr)
+}
+
+/// Returns whether a handle holds a `T` that Java has not destroyed, and was
+/// not handed out before its box was reused
+///
+/// Only the header is read, so the registry is not consulted.
+///
+/// # Safety
+/// `ptr` must be a handle whose box has not been freed. Java wrappers refuse
+/// calls once they are closed, so a handle reaching native code is one.
+pub unsafe fn is_handle_of<T>(ptr: jlong) -> bool {
+    let header = header(ptr);
+    header.type_name == type_name::<T>()
+        && !header.released.load(Ordering::Acquire)
+        && header.generation.load(Ordering::Acquire) == handle_generation(ptr)
+}
+
+/// Releases a handle, freeing the boxed value unless observers still reference it.
+///
+/// Releasing a pointer that is not a live handle to a `T` (already freed,
+/// never registered, or holding another type) is a no-op.
+///
+/// # Safety
+/// The header of a registered handle must describe its boxed value.
+pub(crate) unsafe fn release_handle<T>(ptr: jlong) {
+    let free_now = match HANDLES.entry(ptr) {
+        Entry::Occupied(_) if header(ptr).type_name != type_name::<T>() => false,
+        Entry::Occupied(mut entry) => {
+            header(ptr).released.store(true, Ordering::Release);
+            if entry.get().observers > 0 {
+                entry.get_mut().pending_drop = Some(drop_boxed::<T>);
+                false
+            } else {
+                entry.remove();
+                true
+            }
+        }
+        Entry::Vacant(_) => false,
+    };
+    if free_now {
+        drop_boxed::<T>(ptr);
+    }
+}
+/// Marks a handle as referenced by one more subscription
+pub fn retain_observed_handle(ptr: jlong) {
+    if let Some(mut state) = HANDLES.get_mut(&ptr) {
+        state.observers += 1;
+    }
+}
+
+/// Drops one subscription reference from a handle, completing a deferred free
+/// if the handle was destroyed while observed.
+pub fn release_observed_handle(ptr: jlong) {
+    let pending = match HANDLES.entry(ptr) {
+        Entry::Occupied(mut entry) => {
+            let state = entry.get_mut();
+            state.observers = state.observers.saturating_sub(1);
+            if state.observers == 0 && state.pending_drop.is_some() {
+                entry.remove().pending_drop
+            } else {
+                None
+            }
+        }
+        Entry::Vacant(_) => None,
+    };
+    if let Some(drop_fn) = pending {
+        // SAFETY: drop_fn was recorded by release_handle for this pointer's type
+        unsafe { drop_fn(ptr) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{free_java_ptr, to_java_ptr};
+
+    #[test]
+    fn test_double_free_is_noop() {
+        let ptr = to_java_ptr(String::from("value"));
+        assert!(is_live_handle(ptr));
+        unsafe {
+            free_java_ptr::<String>(ptr);
+            free_java_ptr::<String>(ptr);
+        }
+        assert!(!is_live_handle(ptr));
+    }
+
+    #[test]
+    fn test_type_tag_is_checked() {
+        let ptr = to_java_ptr(String::from("tagged"));
+        unsafe {
+            assert!(is_handle_of::<String>(ptr));
+            assert!(!is_handle_of::<u64>(ptr));
+            // Releasing under the wrong type leaves the handle alone
+            free_java_ptr::<u64>(ptr);
+            assert!(is_handle_of::<String>(ptr));
+            free_java_ptr::<String>(ptr);
+        }
+        assert!(!is_live_handle(ptr));
+    }
+
+    #[test]
+    fn test_free_deferred_while_observed() {
+        let ptr = to_java_ptr(String::from("observed"));
+        retain_observed_handle(ptr);
+        unsafe {
+            free_java_ptr::<String>(ptr);
+        }
+        // Still live: an observer references it, but no longer usable
+        assert!(is_live_handle(ptr));
+        assert!(!unsafe { is_handle_of::<String>(ptr) });
+
+        release_observed_handle(ptr);
+        assert!(!is_live_handle(ptr));
+    }
+
+    #[test]
+    fn test_unobserve_without_destroy_keeps_handle() {
+        let ptr = to_java_ptr(String::from("kept"));
+        retain_observed_handle(ptr);
+        release_observed_handle(ptr);
+        assert!(is_live_handle(ptr));
+        unsafe {
+            free_java_ptr::<String>(ptr);
+        }
+        assert!(!is_live_handle(ptr));
+    }
+}
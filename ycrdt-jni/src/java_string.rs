@@ -0,0 +1,147 @@
+//! Conversion of Java strings to Rust strings under a per-document policy.
+//!
+//! JNI hands strings over in modified UTF-8: NUL is encoded as two bytes and
+//! characters outside the Basic Multilingual Plane as a pair of encoded
+//! surrogates. A Java string may also hold unpaired surrogates, which have no
+//! UTF-8 form at all. Under [`StringPolicy::Lossy`] each unpaired surrogate
+//! becomes U+FFFD and the rest of the string is kept intact; under
+//! [`StringPolicy::Strict`] the conversion fails and the operation throws
+//! `IllegalArgumentException`.
+
+use crate::JniError;
+
+/// How Java strings that are not valid UTF-16 are converted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StringPolicy {
+    /// Replace unpaired surrogates with U+FFFD
+    #[default]
+    Lossy,
+    /// Reject strings with unpaired surrogates
+    Strict,
+}
+
+impl StringPolicy {
+    /// The policy for a `JniYDoc.StringPolicy` ordinal, or `None` if unknown
+    pub fn from_ordinal(ordinal: i32) -> Option<Self> {
+        match ordinal {
+            0 => Some(StringPolicy::Lossy),
+            1 => Some(StringPolicy::Strict),
+            _ => None,
+        }
+    }
+
+    /// The `JniYDoc.StringPolicy` ordinal of this policy
+    pub const fn ordinal(self) -> i32 {
+        match self {
+            StringPolicy::Lossy => 0,
+            StringPolicy::Strict => 1,
+        }
+    }
+}
+
+/// Decodes the modified UTF-8 bytes of a Java string
+pub fn decode_java_string(bytes: &[u8], policy: StringPolicy) -> Result<String, JniError> {
+    // Strings without NUL, supplementary characters or unpaired surrogates
+    // are already valid UTF-8
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return Ok(s.to_owned());
+    }
+    decode_utf16_units(utf16_units(bytes)?, policy)
+}
+
+/// Decodes UTF-16 code units, such as the contents of a Java `char[]`
+pub fn decode_utf16_units<I>(units: I, policy: StringPolicy) -> Result<String, JniError>
+where
+    I: IntoIterator<Item = u16>,
+{
+    let units = units.into_iter();
+    let mut out = String::with_capacity(units.size_hint().0);
+    for c in char::decode_utf16(units) {
+        match (c, policy) {
+            (Ok(c), _) => out.push(c),
+            (Err(_), StringPolicy::Lossy) => out.push(char::REPLACEMENT_CHARACTER),
+            (Err(e), StringPolicy::Strict) => {
+                return Err(JniError::InvalidString(format!(
+                    "String contains an unpaired surrogate U+{:04X}",
+                    e.unpaired_surrogate()
+                )))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Splits modified UTF-8 into the UTF-16 code units it encodes
+fn utf16_units(bytes: &[u8]) -> Result<Vec<u16>, JniError> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let (unit, width) = match b {
+            0x00..=0x7f => (u16::from(b), 1),
+            0xc0..=0xdf => (
+                (u16::from(b & 0x1f) << 6) | u16::from(continuation(bytes, i + 1)?),
+                2,
+            ),
+            0xe0..=0xef => (
+                (u16::from(b & 0x0f) << 12)
+                    | (u16::from(continuation(bytes, i + 1)?) << 6)
+                    | u16::from(continuation(bytes, i + 2)?),
+                3,
+            ),
+            _ => return Err(JniError::Utf8Error),
+        };
+        units.push(unit);
+        i += width;
+    }
+    Ok(units)
+}
+
+/// The payload of the continuation byte at `i`
+fn continuation(bytes: &[u8], i: usize) -> Result<u8, JniError> {
+    match bytes.get(i) {
+        Some(b) if b & 0xc0 == 0x80 => Ok(b & 0x3f),
+        _ => Err(JniError::Utf8Error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Modified UTF-8 of the surrogate code unit `unit`
+    fn surrogate(unit: u16) -> [u8; 3] {
+        [
+            0xe0 | (unit >> 12) as u8,
+            0x80 | ((unit >> 6) & 0x3f) as u8,
+            0x80 | (unit & 0x3f) as u8,
+        ]
+    }
+
+    #[test]
+    fn test_decodes_nul_and_surrogate_pairs() {
+        let mut bytes = vec![b'a', 0xc0, 0x80];
+        // U+1F600 as the pair D83D DE00
+        bytes.extend(surrogate(0xd83d));
+        bytes.extend(surrogate(0xde00));
+        for policy in [StringPolicy::Lossy, StringPolicy::Strict] {
+            assert_eq!(decode_java_string(&bytes, policy).unwrap(), "a\0😀");
+        }
+    }
+
+    #[test]
+    fn test_unpaired_surrogate_follows_policy() {
+        let mut bytes = vec![b'a'];
+        bytes.extend(surrogate(0xd83d));
+        bytes.extend(surrogate(0xd83d));
+        bytes.extend(surrogate(0xde00));
+        assert_eq!(
+            decode_java_string(&bytes, StringPolicy::Lossy).unwrap(),
+            "a\u{fffd}😀"
+        );
+        assert!(matches!(
+            decode_java_string(&bytes, StringPolicy::Strict),
+            Err(JniError::InvalidString(_))
+        ));
+    }
+}
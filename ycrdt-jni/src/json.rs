@@ -0,0 +1,77 @@
+//! JSON output for plain values.
+//!
+//! Maps in `Any` are hash maps, so `Any::to_json` writes their keys in an
+//! order that changes between runs. [`write_json`] can sort keys instead,
+//! which documents created with `nativeCreateDeterministic` use for every
+//! JSON string they return.
+
+use yrs::Any;
+
+/// Appends the JSON form of `value`, writing map keys in sorted order if
+/// `sort_keys` is set
+pub fn write_json(json: &mut String, value: &Any, sort_keys: bool) {
+    match value {
+        Any::Array(items) => {
+            json.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_json(json, item, sort_keys);
+            }
+            json.push(']');
+        }
+        Any::Map(entries) => {
+            let mut entries: Vec<_> = entries.iter().collect();
+            if sort_keys {
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+            }
+            json.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_leaf(json, &Any::from(key.as_str()));
+                json.push(':');
+                write_json(json, value, sort_keys);
+            }
+            json.push('}');
+        }
+        leaf => write_leaf(json, leaf),
+    }
+}
+
+/// The JSON form of `value`; see [`write_json`]
+pub fn to_json_string(value: &Any, sort_keys: bool) -> String {
+    let mut json = String::new();
+    write_json(&mut json, value, sort_keys);
+    json
+}
+
+/// `Any::to_json` writes from the start of its buffer, so it is given a fresh one.
+fn write_leaf(json: &mut String, value: &Any) {
+    let mut buf = String::new();
+    value.to_json(&mut buf);
+    json.push_str(&buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sorted_keys_are_stable() {
+        let inner: HashMap<String, Any> = (0..20)
+            .map(|i| (format!("k{:02}", i), Any::from(i)))
+            .collect();
+        let value = Any::from(HashMap::from([
+            ("b".to_string(), Any::from(inner)),
+            ("a".to_string(), Any::from("say \"hi\"")),
+        ]));
+        let json = to_json_string(&value, true);
+        assert!(json.starts_with("{\"a\":\"say \\\"hi\\\"\",\"b\":{\"k00\":0,\"k01\":1,"));
+        assert!(json.ends_with("\"k19\":19}}"));
+        assert_eq!(json.len(), to_json_string(&value, false).len());
+    }
+}
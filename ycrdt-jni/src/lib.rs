@@ -1,29 +1,144 @@
 use dashmap::DashMap;
 use jni::objects::GlobalRef;
 use jni::sys::{jlong, jstring};
-use jni::JNIEnv;
+use jni::{Executor, JNIEnv};
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use yrs::{ArrayRef, Doc, MapRef, Subscription, TextRef, TransactionMut};
+#[cfg(feature = "xml")]
 use yrs::{XmlElementRef, XmlFragmentRef, XmlTextRef};
 
+mod activity;
+mod annotations;
+mod attr_schema;
+mod batch;
+mod change_counter;
+mod chunked;
+mod class_cache;
 mod conversions;
+mod cursor;
+mod decode_error;
+mod deep_update;
+mod dispatch_gate;
+mod doc_store;
+mod event_origin;
+mod export;
+mod graphemes;
+mod handles;
+mod java_string;
+mod json;
+mod line_index;
+#[cfg(feature = "xml")]
+mod native_ref;
+mod pending;
+mod prelim;
+mod protocol;
+mod quill;
+mod registration;
+mod roots;
+mod schema;
+mod silent_txn;
+mod single_writer;
+mod staging;
+mod sticky;
+mod store_stats;
+mod sync_message;
+mod text_blocks;
+mod text_shadow;
+mod text_stats;
+mod trace;
+mod txn_guard;
+mod txn_label;
+mod txn_pool;
+mod txn_stats;
+mod type_path;
+mod undo;
+mod update_filter;
+mod update_size;
+mod update_stream;
+#[cfg(feature = "xml")]
+mod xml_snapshot;
 mod yarray;
 mod ydoc;
+mod yjson;
 mod ymap;
 mod ytext;
+#[cfg(feature = "xml")]
 mod yxmlelement;
+#[cfg(feature = "xml")]
 mod yxmlfragment;
+#[cfg(feature = "xml")]
 mod yxmltext;
 
+pub use activity::*;
+pub use annotations::*;
+pub use attr_schema::*;
+pub use batch::*;
+pub use change_counter::*;
+pub use chunked::*;
+pub use class_cache::*;
 pub use conversions::*;
+pub use cursor::*;
+pub use decode_error::*;
+pub use deep_update::*;
+pub use dispatch_gate::*;
+pub use doc_store::*;
+pub use event_origin::*;
+pub use export::*;
+pub use graphemes::*;
+pub use handles::*;
+pub use java_string::*;
+pub use json::*;
+pub use line_index::*;
+#[cfg(feature = "xml")]
+pub use native_ref::*;
+pub use pending::*;
+pub use prelim::*;
+pub use protocol::*;
+pub use quill::*;
+pub use roots::*;
+pub use schema::*;
+pub use silent_txn::*;
+pub use single_writer::*;
+pub use staging::*;
+pub use sticky::*;
+pub use store_stats::*;
+pub use sync_message::*;
+pub use text_blocks::*;
+pub use text_shadow::*;
+pub use text_stats::*;
+pub use trace::*;
+pub use txn_guard::*;
+pub use txn_label::*;
+pub use txn_pool::*;
+pub use txn_stats::*;
+pub use type_path::*;
+pub use undo::*;
+pub use update_filter::*;
+pub use update_size::*;
+pub use update_stream::*;
+#[cfg(feature = "xml")]
+pub use xml_snapshot::*;
 pub use yarray::*;
 pub use ydoc::*;
+pub use yjson::*;
 pub use ymap::*;
 pub use ytext::*;
+#[cfg(feature = "xml")]
 pub use yxmlelement::*;
+#[cfg(feature = "xml")]
 pub use yxmlfragment::*;
+#[cfg(feature = "xml")]
 pub use yxmltext::*;
 
+/// Access mode: local edits and applied updates are allowed
+pub const ACCESS_READ_WRITE: u8 = 0;
+/// Access mode: every mutating operation throws
+pub const ACCESS_READ_ONLY: u8 = 1;
+/// Access mode: local edits throw, but updates from peers may still be applied
+pub const ACCESS_READ_ONLY_ALLOW_UPDATES: u8 = 2;
+
 /// Wrapper around yrs::Doc that owns subscriptions and Java GlobalRefs.
 /// This ensures subscriptions are properly cleaned up when the document is destroyed,
 /// avoiding the need for global static storage and eliminating potential deadlocks.
@@ -36,47 +151,285 @@ pub struct DocWrapper {
     subscriptions: DashMap<jlong, Subscription>,
     /// Java GlobalRefs for callback objects, keyed by subscription ID
     java_refs: DashMap<jlong, GlobalRef>,
+    /// Shared-type handle observed by each subscription, keyed by subscription ID
+    subscription_targets: DashMap<jlong, jlong>,
+    /// Whether new transactions record their opening thread and reject use from others
+    single_writer: AtomicBool,
+    /// Barrier that lets `nativeDestroy` wait for in-flight observer callbacks
+    dispatch_gate: Arc<DispatchGate>,
+    /// Whether new transactions are timed and measured on commit
+    txn_stats_enabled: AtomicBool,
+    /// Running totals for measured transactions
+    txn_stats: TxnStatsTotals,
+    /// Optional allowlist of formatting attribute keys
+    attribute_schema: AttributeSchema,
+    /// One of the `ACCESS_*` constants
+    access: AtomicU8,
+    /// Updates waiting for the clocks they depend on
+    staging: UpdateStaging,
+    /// Ordinal of the `StringPolicy` applied to strings from Java
+    string_policy: AtomicU8,
+    /// Last-modified information per root type, when tracking is enabled
+    root_activity: RootActivityLog,
+    /// Number of committed transactions that changed the document
+    change_counter: ChangeCounter,
+    /// Queue of mutation batches run on this document's worker thread
+    batch_queue: BatchQueue,
+    /// Attaches callback and worker threads to the JVM; created on first use
+    executor: OnceLock<Executor>,
+    /// Whether updates that fail to decode are written to stderr in full
+    log_decode_failures: AtomicBool,
+    /// Whether map keys are returned in sorted order, for reproducible output
+    sorted_keys: AtomicBool,
+    /// Longest inserted string delivered in full to text observers, in UTF-16 units
+    event_content_limit: AtomicUsize,
+    /// Root-type handles handed to Java, released when the document is destroyed
+    roots: RootHandles,
+    /// Copies of observed texts, used to measure the spans of their events
+    text_shadows: TextShadows,
 }
 
 impl DocWrapper {
     /// Create a new DocWrapper with a new document
     pub fn new() -> Self {
-        Self {
-            doc: Doc::new(),
-            subscriptions: DashMap::new(),
-            java_refs: DashMap::new(),
-        }
+        Self::from_doc(Doc::new())
     }
 
     /// Create a new DocWrapper with a document using the given options
     pub fn with_options(options: yrs::Options) -> Self {
-        Self {
-            doc: Doc::with_options(options),
-            subscriptions: DashMap::new(),
-            java_refs: DashMap::new(),
-        }
+        Self::from_doc(Doc::with_options(options))
     }
 
     /// Create a DocWrapper from an existing Doc (e.g., for subdocuments)
     pub fn from_doc(doc: Doc) -> Self {
+        let change_counter = ChangeCounter::new(&doc);
         Self {
             doc,
             subscriptions: DashMap::new(),
             java_refs: DashMap::new(),
+            subscription_targets: DashMap::new(),
+            single_writer: AtomicBool::new(false),
+            dispatch_gate: Arc::new(DispatchGate::default()),
+            txn_stats_enabled: AtomicBool::new(false),
+            txn_stats: TxnStatsTotals::default(),
+            attribute_schema: AttributeSchema::default(),
+            access: AtomicU8::new(ACCESS_READ_WRITE),
+            staging: UpdateStaging::default(),
+            string_policy: AtomicU8::new(StringPolicy::default().ordinal() as u8),
+            root_activity: RootActivityLog::default(),
+            change_counter,
+            batch_queue: BatchQueue::default(),
+            executor: OnceLock::new(),
+            log_decode_failures: AtomicBool::new(false),
+            sorted_keys: AtomicBool::new(false),
+            event_content_limit: AtomicUsize::new(usize::MAX),
+            roots: RootHandles::default(),
+            text_shadows: TextShadows::default(),
+        }
+    }
+
+    /// Enable or disable single-writer checks for transactions opened on this document
+    pub fn set_single_writer(&self, enabled: bool) {
+        self.single_writer.store(enabled, Ordering::Release);
+    }
+
+    /// Whether transactions opened on this document record and enforce their owning thread
+    pub fn is_single_writer(&self) -> bool {
+        self.single_writer.load(Ordering::Acquire)
+    }
+
+    /// Enable or disable statistics for transactions opened on this document
+    pub fn set_txn_stats_enabled(&self, enabled: bool) {
+        self.txn_stats_enabled.store(enabled, Ordering::Release);
+    }
+
+    /// Whether transactions opened on this document are measured
+    pub fn is_txn_stats_enabled(&self) -> bool {
+        self.txn_stats_enabled.load(Ordering::Acquire)
+    }
+
+    /// Running totals for transactions measured on this document
+    pub fn txn_stats(&self) -> &TxnStatsTotals {
+        &self.txn_stats
+    }
+
+    /// Allowlist of formatting attribute keys checked by insert and format operations
+    pub fn attribute_schema(&self) -> &AttributeSchema {
+        &self.attribute_schema
+    }
+
+    /// The staging area for out-of-order updates
+    pub fn staging(&self) -> &UpdateStaging {
+        &self.staging
+    }
+
+    /// Set how strings passed from Java to this document are converted
+    pub fn set_string_policy(&self, policy: StringPolicy) {
+        self.string_policy
+            .store(policy.ordinal() as u8, Ordering::Release);
+    }
+
+    /// How strings passed from Java to this document are converted
+    pub fn string_policy(&self) -> StringPolicy {
+        StringPolicy::from_ordinal(i32::from(self.string_policy.load(Ordering::Acquire)))
+            .unwrap_or_default()
+    }
+
+    /// Enable or disable logging of the full payload of undecodable updates
+    pub fn set_log_decode_failures(&self, enabled: bool) {
+        self.log_decode_failures.store(enabled, Ordering::Release);
+    }
+
+    /// Whether the full payload of undecodable updates is logged
+    pub fn logs_decode_failures(&self) -> bool {
+        self.log_decode_failures.load(Ordering::Acquire)
+    }
+
+    /// Return map keys in sorted order from JSON output and key listings
+    pub fn set_sorted_keys(&self, enabled: bool) {
+        self.sorted_keys.store(enabled, Ordering::Release);
+    }
+
+    /// Whether map keys are returned in sorted order
+    pub fn sorts_keys(&self) -> bool {
+        self.sorted_keys.load(Ordering::Acquire)
+    }
+
+    /// Set the longest inserted string that text observers receive in full
+    ///
+    /// `None` delivers every insertion in full.
+    pub fn set_event_content_limit(&self, limit: Option<usize>) {
+        self.event_content_limit
+            .store(limit.unwrap_or(usize::MAX), Ordering::Release);
+    }
+
+    /// The longest inserted string that text observers receive in full, in
+    /// UTF-16 units
+    pub fn event_content_limit(&self) -> Option<usize> {
+        match self.event_content_limit.load(Ordering::Acquire) {
+            usize::MAX => None,
+            limit => Some(limit),
         }
     }
 
-    /// Store a subscription and its associated Java GlobalRef
-    pub fn add_subscription(&self, id: jlong, subscription: Subscription, java_ref: GlobalRef) {
+    /// Root-type handles handed to Java
+    pub fn roots(&self) -> &RootHandles {
+        &self.roots
+    }
+
+    /// Copies of the texts observed on this document
+    pub fn text_shadows(&self) -> &TextShadows {
+        &self.text_shadows
+    }
+
+    /// Last-modified information per root type
+    pub fn root_activity(&self) -> &RootActivityLog {
+        &self.root_activity
+    }
+
+    /// Number of committed transactions that changed the document
+    pub fn change_counter(&self) -> &ChangeCounter {
+        &self.change_counter
+    }
+
+    /// Queue of mutation batches run on this document's worker thread
+    pub fn batch_queue(&self) -> &BatchQueue {
+        &self.batch_queue
+    }
+
+    /// The executor shared by this document's observers and worker threads
+    ///
+    /// Every callback into Java attaches its thread through this executor, so
+    /// the attach strategy is decided here. It is created from the JVM of the
+    /// first caller and reused afterwards.
+    pub fn executor(&self, env: &JNIEnv) -> jni::errors::Result<Executor> {
+        if let Some(executor) = self.executor.get() {
+            return Ok(executor.clone());
+        }
+        let executor = Executor::new(Arc::new(env.get_java_vm()?));
+        Ok(self.executor.get_or_init(|| executor).clone())
+    }
+
+    /// Set the access mode, one of the `ACCESS_*` constants
+    pub fn set_access(&self, access: u8) {
+        self.access.store(access, Ordering::Release);
+    }
+
+    /// The access mode, one of the `ACCESS_*` constants
+    pub fn access(&self) -> u8 {
+        self.access.load(Ordering::Acquire)
+    }
+
+    /// Whether local edits are allowed on this document
+    pub fn is_writable(&self) -> bool {
+        self.access() == ACCESS_READ_WRITE
+    }
+
+    /// Whether updates may be applied to this document
+    pub fn accepts_updates(&self) -> bool {
+        self.access() != ACCESS_READ_ONLY
+    }
+
+    /// Returns the dispatch gate that observer closures must enter before
+    /// dereferencing this wrapper
+    pub fn dispatch_gate(&self) -> Arc<DispatchGate> {
+        Arc::clone(&self.dispatch_gate)
+    }
+
+    /// Store a subscription and its associated Java GlobalRef.
+    ///
+    /// `target` is the handle of the observed shared type (0 for document-level
+    /// observers). While the subscription exists, destroying that handle defers
+    /// the free until the subscription is removed.
+    pub fn add_subscription(
+        &self,
+        id: jlong,
+        target: jlong,
+        subscription: Subscription,
+        java_ref: GlobalRef,
+    ) {
         self.subscriptions.insert(id, subscription);
         self.java_refs.insert(id, java_ref);
+        if target != 0 {
+            retain_observed_handle(target);
+            self.subscription_targets.insert(id, target);
+        }
     }
 
     /// Remove a subscription and its associated Java GlobalRef
     /// Returns the removed subscription (if any) so it can be dropped outside any locks
     pub fn remove_subscription(&self, id: jlong) -> Option<Subscription> {
         self.java_refs.remove(&id);
-        self.subscriptions.remove(&id).map(|(_, sub)| sub)
+        let subscription = self.subscriptions.remove(&id).map(|(_, sub)| sub);
+        if let Some((_, target)) = self.subscription_targets.remove(&id) {
+            release_observed_handle(target);
+        }
+        subscription
+    }
+
+    /// Replace the document with `doc`, keeping this wrapper's settings
+    ///
+    /// Every subscription is dropped, root handles are released, and staged
+    /// updates, root activity and transaction totals are discarded. Root
+    /// activity is recorded for the new document if it was being recorded.
+    /// The change counter keeps counting and counts the reset as a change.
+    pub fn reset(&mut self, doc: Doc) {
+        self.subscriptions.clear();
+        self.java_refs.clear();
+        for (_, target) in std::mem::take(&mut self.subscription_targets) {
+            release_observed_handle(target);
+        }
+        unsafe { release_all_roots(self) };
+        self.staging.clear();
+        self.txn_stats.reset();
+        let track_activity = self.root_activity.is_enabled();
+        self.root_activity = RootActivityLog::default();
+        self.doc = doc;
+        self.change_counter.replace_doc(&self.doc);
+        if track_activity {
+            // No transaction can be open on a document nobody has seen yet
+            let _ = self.root_activity.set_enabled(&self.doc, true);
+        }
     }
 
     /// Get a reference to a Java GlobalRef by subscription ID
@@ -85,6 +438,17 @@ impl DocWrapper {
     }
 }
 
+impl Drop for DocWrapper {
+    fn drop(&mut self) {
+        // Subscriptions die with the document, so release their hold on observed
+        // handles and complete any frees that were deferred on their account.
+        self.subscriptions.clear();
+        for (_, target) in std::mem::take(&mut self.subscription_targets) {
+            release_observed_handle(target);
+        }
+    }
+}
+
 impl Default for DocWrapper {
     fn default() -> Self {
         Self::new()
@@ -120,31 +484,44 @@ impl<T> JavaPtr<T> {
         self.ptr == 0
     }
 
+    /// Whether the pointer is a handle to a `T` that Java has not destroyed
+    ///
+    /// # Safety
+    /// A non-null pointer must be a handle whose value has not been freed.
+    pub unsafe fn is_valid(&self) -> bool {
+        self.ptr != 0 && is_handle_of::<T>(self.ptr)
+    }
+
     /// Get an immutable reference to the pointed value
     ///
+    /// Returns `None` if the pointer is null, was destroyed by Java, or holds a
+    /// different type.
+    ///
     /// # Safety
-    /// The pointer must be valid and point to a properly initialized value of type T.
+    /// The value must not be freed while the reference is in use.
     /// The returned reference has 'static lifetime because the pointed value is
     /// heap-allocated and will outlive this JavaPtr wrapper.
     pub unsafe fn as_ref(&self) -> Option<&'static T> {
-        if self.ptr == 0 {
+        if !self.is_valid() {
             None
         } else {
-            Some(&*(self.ptr as *const T))
+            Some(&*(handle_address(self.ptr) as *const T))
         }
     }
 
     /// Get a mutable reference to the pointed value
     ///
+    /// Returns `None` under the same conditions as [`JavaPtr::as_ref`].
+    ///
     /// # Safety
-    /// The pointer must be valid and point to a properly initialized value of type T.
+    /// The value must not be freed while the reference is in use.
     /// The returned reference has 'static lifetime because the pointed value is
     /// heap-allocated and will outlive this JavaPtr wrapper.
     pub unsafe fn as_mut(&self) -> Option<&'static mut T> {
-        if self.ptr == 0 {
+        if !self.is_valid() {
             None
         } else {
-            Some(&mut *(self.ptr as *mut T))
+            Some(&mut *(handle_address(self.ptr) as *mut T))
         }
     }
 }
@@ -154,8 +531,11 @@ pub type DocPtr = JavaPtr<DocWrapper>;
 pub type TextPtr = JavaPtr<TextRef>;
 pub type ArrayPtr = JavaPtr<ArrayRef>;
 pub type MapPtr = JavaPtr<MapRef>;
+#[cfg(feature = "xml")]
 pub type XmlElementPtr = JavaPtr<XmlElementRef>;
+#[cfg(feature = "xml")]
 pub type XmlFragmentPtr = JavaPtr<XmlFragmentRef>;
+#[cfg(feature = "xml")]
 pub type XmlTextPtr = JavaPtr<XmlTextRef>;
 pub type TxnPtr<'a> = JavaPtr<TransactionMut<'a>>;
 
@@ -190,8 +570,51 @@ macro_rules! get_ref_or_throw {
     }};
 }
 
+/// Throw `IllegalStateException` and return if a document does not allow local edits,
+/// or if it is called from one of the document's observers.
+///
+/// # Arguments
+/// * `$env` - Mutable reference to JNIEnv
+/// * `$wrapper` - The DocWrapper being edited
+/// * `$ret` - Value to return if the edit is not allowed (omit for unit-returning functions)
+#[macro_export]
+macro_rules! check_writable_or_throw {
+    ($env:expr, $wrapper:expr) => {
+        $crate::check_writable_or_throw!($env, $wrapper, ())
+    };
+    ($env:expr, $wrapper:expr, $ret:expr) => {
+        if !$wrapper.is_writable() {
+            let _ = $env.throw_new("java/lang/IllegalStateException", "YDoc is read-only");
+            return $ret;
+        }
+        $crate::check_not_dispatching_or_throw!($env, $wrapper, $ret);
+    };
+}
+
+/// Throw `IllegalStateException` and return if called from one of a document's
+/// observers while the transaction that notified it commits.
+///
+/// # Arguments
+/// * `$env` - Mutable reference to JNIEnv
+/// * `$wrapper` - The DocWrapper being changed
+/// * `$ret` - Value to return if called from an observer (omit for unit-returning functions)
+#[macro_export]
+macro_rules! check_not_dispatching_or_throw {
+    ($env:expr, $wrapper:expr) => {
+        $crate::check_not_dispatching_or_throw!($env, $wrapper, ())
+    };
+    ($env:expr, $wrapper:expr, $ret:expr) => {
+        if !$crate::check_not_dispatching_or_throw($env, &$wrapper.dispatch_gate) {
+            return $ret;
+        }
+    };
+}
+
 /// Validate a pointer and get a mutable reference, or throw an exception and return.
 ///
+/// For transactions opened with single-writer checks enabled, this also throws
+/// `IllegalStateException` when called from a thread other than the owner.
+///
 /// # Arguments
 /// * `$env` - Mutable reference to JNIEnv
 /// * `$ptr` - The JavaPtr to validate
@@ -202,7 +625,12 @@ macro_rules! get_mut_or_throw {
     ($env:expr, $ptr:expr, $name:expr) => {{
         let ptr = $ptr;
         match unsafe { ptr.as_mut() } {
-            Some(r) => r,
+            Some(r) => {
+                if !$crate::check_txn_owner($env, ptr.raw()) {
+                    return;
+                }
+                r
+            }
             None => {
                 $crate::throw_exception($env, concat!("Invalid ", $name, " pointer"));
                 return;
@@ -212,7 +640,12 @@ macro_rules! get_mut_or_throw {
     ($env:expr, $ptr:expr, $name:expr, $ret:expr) => {{
         let ptr = $ptr;
         match unsafe { ptr.as_mut() } {
-            Some(r) => r,
+            Some(r) => {
+                if !$crate::check_txn_owner($env, ptr.raw()) {
+                    return $ret;
+                }
+                r
+            }
             None => {
                 $crate::throw_exception($env, concat!("Invalid ", $name, " pointer"));
                 return $ret;
@@ -239,24 +672,25 @@ macro_rules! free_if_valid {
 ///
 /// # Arguments
 /// * `$env` - Mutable reference to JNIEnv
+/// * `$wrapper` - The DocWrapper whose `StringPolicy` applies
 /// * `$jstring` - The JString to convert
 /// * `$ret` - Value to return if conversion fails (omit for unit-returning functions)
 #[macro_export]
 macro_rules! get_string_or_throw {
-    ($env:expr, $jstring:expr) => {{
-        match $env.get_rust_string(&$jstring) {
+    ($env:expr, $wrapper:expr, $jstring:expr) => {{
+        match $env.get_rust_string(&$jstring, $wrapper.string_policy()) {
             Ok(s) => s,
             Err(e) => {
-                $crate::throw_exception($env, &e.to_string());
+                $crate::throw_jni_error($env, &e);
                 return;
             }
         }
     }};
-    ($env:expr, $jstring:expr, $ret:expr) => {{
-        match $env.get_rust_string(&$jstring) {
+    ($env:expr, $wrapper:expr, $jstring:expr, $ret:expr) => {{
+        match $env.get_rust_string(&$jstring, $wrapper.string_policy()) {
             Ok(s) => s,
             Err(e) => {
-                $crate::throw_exception($env, &e.to_string());
+                $crate::throw_jni_error($env, &e);
                 return $ret;
             }
         }
@@ -268,6 +702,7 @@ macro_rules! get_string_or_throw {
 //=============================================================================
 
 use jni::objects::JString;
+use jni::strings::JNIStr;
 use jni::sys::{jbyteArray, jdouble, jint};
 use std::fmt;
 
@@ -282,6 +717,8 @@ pub enum JniError {
     StringConversion(&'static str),
     /// UTF-8 encoding error
     Utf8Error,
+    /// A Java string rejected by `StringPolicy::Strict`
+    InvalidString(String),
     /// Y-CRDT operation failed
     Yrs(String),
     /// Generic error with message
@@ -295,6 +732,7 @@ impl fmt::Display for JniError {
             JniError::InvalidPointer(name) => write!(f, "Invalid {} pointer", name),
             JniError::StringConversion(ctx) => write!(f, "Failed to get {} string", ctx),
             JniError::Utf8Error => write!(f, "Invalid UTF-8 in string"),
+            JniError::InvalidString(msg) => write!(f, "{}", msg),
             JniError::Yrs(msg) => write!(f, "Y-CRDT error: {}", msg),
             JniError::Other(msg) => write!(f, "{}", msg),
         }
@@ -381,7 +819,7 @@ impl<T> JniResultExt<T> for JniResult<T> {
         match self {
             Ok(v) => v,
             Err(e) => {
-                throw_exception(env, &e.to_string());
+                throw_jni_error(env, &e);
                 T::jni_default()
             }
         }
@@ -394,8 +832,8 @@ impl<T> JniResultExt<T> for JniResult<T> {
 
 /// Extension trait for JNIEnv to simplify common operations
 pub trait JniEnvExt<'local> {
-    /// Get a Rust String from a JString, with proper error handling
-    fn get_rust_string(&mut self, s: &JString) -> JniResult<String>;
+    /// Get a Rust String from a JString, converting invalid UTF-16 according to `policy`
+    fn get_rust_string(&mut self, s: &JString, policy: StringPolicy) -> JniResult<String>;
 
     /// Create a jstring from a Rust str
     fn create_jstring(&mut self, s: &str) -> JniResult<jstring>;
@@ -405,12 +843,12 @@ pub trait JniEnvExt<'local> {
 }
 
 impl<'local> JniEnvExt<'local> for JNIEnv<'local> {
-    fn get_rust_string(&mut self, s: &JString) -> JniResult<String> {
+    fn get_rust_string(&mut self, s: &JString, policy: StringPolicy) -> JniResult<String> {
         let jstr = self
             .get_string(s)
             .map_err(|_| JniError::StringConversion("java string"))?;
-        // Use Into<String> which properly handles Modified UTF-8 (CESU-8) to UTF-8 conversion
-        Ok(jstr.into())
+        let jni_str: &JNIStr = &jstr;
+        decode_java_string(jni_str.to_bytes(), policy)
     }
 
     fn create_jstring(&mut self, s: &str) -> JniResult<jstring> {
@@ -432,7 +870,7 @@ pub unsafe fn get_transaction_mut<'a>(txn_ptr: jlong) -> Option<&'a mut Transact
     if txn_ptr == 0 {
         return None;
     }
-    let ptr = txn_ptr as *mut TransactionMut<'a>;
+    let ptr = handle_address(txn_ptr) as *mut TransactionMut<'a>;
     Some(&mut *ptr)
 }
 
@@ -442,8 +880,20 @@ pub unsafe fn get_transaction_mut<'a>(txn_ptr: jlong) -> Option<&'a mut Transact
 /// The caller must ensure the pointer is valid and has not been freed
 pub unsafe fn free_transaction(txn_ptr: jlong) {
     if txn_ptr != 0 {
-        // Reconstruct the Box and drop it to free memory and commit the transaction
-        let _ = Box::from_raw(txn_ptr as *mut TransactionMut);
+        let txn = handle_address(txn_ptr) as *mut TransactionMut;
+        // Commit first: observers run during the commit and may still use the
+        // transaction through its handle
+        std::ptr::drop_in_place(txn);
+        // Remote and silent marks are keyed by the transaction's address
+        forget_remote(txn as jlong);
+        forget_silent(txn as jlong);
+        // Released after the commit, whose observers run on this thread
+        release_transaction(txn_ptr);
+        forget_txn_label(txn_ptr);
+        // Unregister before the memory is freed or pooled, so the address
+        // cannot be handed out again while it is still registered
+        handles::unregister_handle(txn_ptr);
+        recycle_transaction_box(txn_ptr);
     }
 }
 
@@ -460,6 +910,19 @@ pub fn throw_exception(env: &mut JNIEnv, message: &str) {
     let _ = env.throw_new("java/lang/RuntimeException", message);
 }
 
+/// Throw the Java exception matching a `JniError`
+///
+/// Strings rejected by `StringPolicy::Strict` throw `IllegalArgumentException`;
+/// everything else throws `RuntimeException`.
+pub fn throw_jni_error(env: &mut JNIEnv, error: &JniError) {
+    match error {
+        JniError::InvalidString(msg) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+        }
+        _ => throw_exception(env, &error.to_string()),
+    }
+}
+
 /// Helper function to convert a Java pointer (long) to a Rust reference
 ///
 /// # Safety
@@ -470,16 +933,19 @@ pub unsafe fn from_java_ptr<T>(ptr: jlong) -> &'static mut T {
 
 /// Helper function to convert a Rust reference to a Java pointer (long)
 pub fn to_java_ptr<T>(obj: T) -> jlong {
-    Box::into_raw(Box::new(obj)) as jlong
+    handles::box_handle(obj)
 }
 
 /// Helper function to free a Rust object from a Java pointer
 ///
+/// Freeing a pointer that was already freed is a no-op. If subscriptions still
+/// observe the pointer, the free is deferred until the last one is removed.
+///
 /// # Safety
-/// The pointer must be valid and point to the expected type
+/// If the pointer is live, it must point to the expected type
 pub unsafe fn free_java_ptr<T>(ptr: jlong) {
     if ptr != 0 {
-        let _ = Box::from_raw(ptr as *mut T);
+        handles::release_handle::<T>(ptr);
     }
 }
 
@@ -498,6 +964,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_single_writer_flag() {
+        let doc = DocWrapper::new();
+        assert!(!doc.is_single_writer());
+        doc.set_single_writer(true);
+        assert!(doc.is_single_writer());
+        doc.set_single_writer(false);
+        assert!(!doc.is_single_writer());
+    }
+
     #[test]
     fn test_java_ptr_null() {
         let ptr: JavaPtr<DocWrapper> = JavaPtr::from_raw(0);
@@ -523,6 +999,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_java_ptr_rejects_other_type() {
+        let doc = Doc::new();
+        let raw = to_java_ptr(doc.get_or_insert_map("map"));
+        assert!(unsafe { MapPtr::from_raw(raw).as_ref() }.is_some());
+        assert!(unsafe { TextPtr::from_raw(raw).as_ref() }.is_none());
+        assert!(unsafe { DocPtr::from_raw(raw).as_mut() }.is_none());
+
+        unsafe {
+            free_java_ptr::<MapRef>(raw);
+        }
+        assert!(!is_live_handle(raw));
+    }
+
     #[test]
     fn test_type_aliases() {
         // Test that type aliases work correctly
@@ -530,8 +1020,11 @@ mod tests {
         let _text_ptr: TextPtr = TextPtr::from_raw(0);
         let _array_ptr: ArrayPtr = ArrayPtr::from_raw(0);
         let _map_ptr: MapPtr = MapPtr::from_raw(0);
-        let _xml_element_ptr: XmlElementPtr = XmlElementPtr::from_raw(0);
-        let _xml_fragment_ptr: XmlFragmentPtr = XmlFragmentPtr::from_raw(0);
-        let _xml_text_ptr: XmlTextPtr = XmlTextPtr::from_raw(0);
+        #[cfg(feature = "xml")]
+        {
+            let _xml_element_ptr: XmlElementPtr = XmlElementPtr::from_raw(0);
+            let _xml_fragment_ptr: XmlFragmentPtr = XmlFragmentPtr::from_raw(0);
+            let _xml_text_ptr: XmlTextPtr = XmlTextPtr::from_raw(0);
+        }
     }
 }
@@ -0,0 +1,107 @@
+//! Line positions in text content.
+//!
+//! Editors show line numbers and report cursors as a line and column, which
+//! otherwise needs the whole text copied to Java after every change. A
+//! [`LineIndex`] is built from the content natively and only the positions
+//! cross the JNI boundary. Lines end after each `\n`, so a `\r` before it
+//! belongs to the line it ends. Offsets and columns are measured in the
+//! document's offset kind, the same units as the indexes of text operations.
+
+use yrs::OffsetKind;
+
+/// Length of `s` in the units of `kind`
+pub fn unit_len(s: &str, kind: OffsetKind) -> u32 {
+    match kind {
+        OffsetKind::Bytes => s.len() as u32,
+        OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+    }
+}
+
+/// Start offsets of the lines of a text
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineIndex {
+    starts: Vec<u32>,
+    len: u32,
+}
+
+impl LineIndex {
+    /// Indexes the lines of `content`, measuring offsets in the units of `kind`
+    pub fn new(content: &str, kind: OffsetKind) -> Self {
+        let mut starts = vec![0];
+        let mut len = 0;
+        for line in content.split_inclusive('\n') {
+            len += unit_len(line, kind);
+            if line.ends_with('\n') {
+                starts.push(len);
+            }
+        }
+        Self { starts, len }
+    }
+
+    /// Offsets at which the lines start; the first is always 0
+    pub fn line_starts(&self) -> &[u32] {
+        &self.starts
+    }
+
+    /// Number of lines, which is one more than the number of line breaks
+    pub fn line_count(&self) -> u32 {
+        self.starts.len() as u32
+    }
+
+    /// Line and column of `offset`, or `None` if it is past the end of the text
+    pub fn position(&self, offset: u32) -> Option<(u32, u32)> {
+        if offset > self.len {
+            return None;
+        }
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        Some((line as u32, offset - self.starts[line]))
+    }
+
+    /// Offset of `column` in `line`, or `None` if there is no such line or the
+    /// column is past the line break ending it
+    pub fn offset(&self, line: u32, column: u32) -> Option<u32> {
+        let line = line as usize;
+        let start = *self.starts.get(line)?;
+        let end = match self.starts.get(line + 1) {
+            Some(next) => next - 1,
+            None => self.len,
+        };
+        start.checked_add(column).filter(|&offset| offset <= end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_indexed_in_offset_units() {
+        let content = "a😀\nbc\r\n\nd";
+        let bytes = LineIndex::new(content, OffsetKind::Bytes);
+        assert_eq!(bytes.line_starts(), &[0, 6, 10, 11]);
+        let utf16 = LineIndex::new(content, OffsetKind::Utf16);
+        assert_eq!(utf16.line_starts(), &[0, 4, 8, 9]);
+        assert_eq!(utf16.line_count(), 4);
+
+        assert_eq!(utf16.position(0), Some((0, 0)));
+        assert_eq!(utf16.position(3), Some((0, 3)));
+        assert_eq!(utf16.position(4), Some((1, 0)));
+        assert_eq!(utf16.position(8), Some((2, 0)));
+        assert_eq!(utf16.position(10), Some((3, 1)));
+        assert_eq!(utf16.position(11), None);
+
+        assert_eq!(utf16.offset(1, 3), Some(7));
+        assert_eq!(utf16.offset(1, 4), None);
+        assert_eq!(utf16.offset(3, 1), Some(10));
+        assert_eq!(utf16.offset(4, 0), None);
+    }
+
+    #[test]
+    fn test_empty_text_has_one_line() {
+        let index = LineIndex::new("", OffsetKind::Bytes);
+        assert_eq!(index.line_starts(), &[0]);
+        assert_eq!(index.position(0), Some((0, 0)));
+        assert_eq!(index.offset(0, 0), Some(0));
+        assert_eq!(index.offset(0, 1), None);
+    }
+}
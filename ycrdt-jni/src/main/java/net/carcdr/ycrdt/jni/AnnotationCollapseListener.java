@@ -0,0 +1,21 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Callback invoked when all content covered by an annotation has been deleted.
+ *
+ * <p>The listener runs on the thread that commits the change, while the
+ * transaction is being committed, so it must not modify the document. Exceptions
+ * thrown by the listener are passed to the document's observer error handler.</p>
+ *
+ * @see JniYAnnotations#onCollapsed(AnnotationCollapseListener)
+ */
+@FunctionalInterface
+public interface AnnotationCollapseListener {
+
+    /**
+     * Called once when an annotation's range becomes empty.
+     *
+     * @param annotation the collapsed annotation
+     */
+    void onCollapsed(JniAnnotation annotation);
+}
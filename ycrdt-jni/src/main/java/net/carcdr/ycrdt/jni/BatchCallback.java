@@ -0,0 +1,22 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Callback invoked when a batch submitted with
+ * {@link JniYDoc#submitBatch(java.util.List, BatchCallback)} has been committed.
+ *
+ * <p>The callback runs on the document's batch worker thread after the batch's
+ * transaction has been committed, so observers of the document have already seen
+ * the change. Exceptions thrown by the callback are passed to the document's
+ * observer error handler.</p>
+ */
+@FunctionalInterface
+public interface BatchCallback {
+
+    /**
+     * Called after the batch has been committed.
+     *
+     * @param doc the document the batch ran on
+     * @param result the update and the number of applied operations
+     */
+    void onComplete(JniYDoc doc, JniBatchResult result);
+}
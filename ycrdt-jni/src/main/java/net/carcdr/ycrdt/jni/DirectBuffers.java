@@ -0,0 +1,47 @@
+package net.carcdr.ycrdt.jni;
+
+import java.nio.ByteBuffer;
+import java.nio.ReadOnlyBufferException;
+
+/**
+ * Checks shared by the methods that write UTF-8 output into a caller-provided
+ * direct {@link ByteBuffer}.
+ */
+final class DirectBuffers {
+
+    private DirectBuffers() {
+    }
+
+    /**
+     * Checks that native code may write into the buffer.
+     *
+     * @param target the buffer passed by the caller
+     * @throws IllegalArgumentException if target is null or not direct
+     * @throws ReadOnlyBufferException if target is read-only
+     */
+    static void checkWritable(ByteBuffer target) {
+        if (target == null) {
+            throw new IllegalArgumentException("Buffer cannot be null");
+        }
+        if (!target.isDirect()) {
+            throw new IllegalArgumentException("Buffer must be a direct ByteBuffer");
+        }
+        if (target.isReadOnly()) {
+            throw new ReadOnlyBufferException();
+        }
+    }
+
+    /**
+     * Advances the buffer past the bytes native code wrote, if they fit.
+     *
+     * @param target the buffer written to, starting at its position
+     * @param size the size of the output in bytes
+     * @return size
+     */
+    static int advance(ByteBuffer target, int size) {
+        if (size <= target.remaining()) {
+            target.position(target.position() + size);
+        }
+        return size;
+    }
+}
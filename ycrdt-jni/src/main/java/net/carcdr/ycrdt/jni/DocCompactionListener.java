@@ -0,0 +1,24 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Callback invoked when a {@link JniDocStore} has compacted an idle document.
+ *
+ * <p>The state is the whole document encoded as a single v1 update, as returned by
+ * {@link JniYDoc#encodeStateAsUpdate()}. Applying it to an empty document restores
+ * the document, so it can replace the updates persisted for the document up to
+ * this point. Exceptions thrown by the listener are passed to the document's
+ * observer error handler.</p>
+ *
+ * @see JniDocStore#startCompaction(java.time.Duration, java.time.Duration, DocCompactionListener)
+ */
+@FunctionalInterface
+public interface DocCompactionListener {
+
+    /**
+     * Called after an idle document has been encoded.
+     *
+     * @param name the name the document is stored under
+     * @param state the v1-encoded state of the whole document
+     */
+    void onCompact(String name, byte[] state);
+}
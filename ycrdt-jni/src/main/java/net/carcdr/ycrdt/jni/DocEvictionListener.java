@@ -0,0 +1,24 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Callback invoked when a {@link JniDocStore} is about to close one of its
+ * documents.
+ *
+ * <p>The listener runs on the thread that caused the eviction, after the
+ * document has been removed from the store and before it is closed, so it can
+ * still persist the document's state. It runs without the store's lock held.
+ * The document is closed even if the listener throws.</p>
+ *
+ * @see JniDocStore#setEvictionListener(DocEvictionListener)
+ */
+@FunctionalInterface
+public interface DocEvictionListener {
+
+    /**
+     * Called before an evicted document is closed.
+     *
+     * @param name the name the document was stored under
+     * @param doc the document, still open
+     */
+    void onEvict(String name, JniYDoc doc);
+}
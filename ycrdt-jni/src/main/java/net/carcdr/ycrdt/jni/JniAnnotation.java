@@ -0,0 +1,96 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * The current range of a named annotation over a text.
+ *
+ * <p>Instances are snapshots returned by {@link JniYAnnotations}; they do not
+ * change when the text is edited afterwards.</p>
+ *
+ * @see JniYAnnotations
+ */
+public final class JniAnnotation {
+
+    private final String id;
+    private final int start;
+    private final int end;
+
+    /**
+     * Package-private constructor. Annotations are created by the native layer.
+     *
+     * @param id the annotation ID
+     * @param start the index of the first annotated character
+     * @param end the index after the last annotated character
+     */
+    JniAnnotation(String id, int start, int end) {
+        this.id = id;
+        this.start = start;
+        this.end = end;
+    }
+
+    /**
+     * Returns the annotation ID.
+     *
+     * @return the ID
+     */
+    public String getId() {
+        return id;
+    }
+
+    /**
+     * Returns the index of the first annotated character.
+     *
+     * @return the start index
+     */
+    public int getStart() {
+        return start;
+    }
+
+    /**
+     * Returns the index after the last annotated character.
+     *
+     * @return the end index
+     */
+    public int getEnd() {
+        return end;
+    }
+
+    /**
+     * Returns the number of annotated characters.
+     *
+     * @return the length
+     */
+    public int getLength() {
+        return end - start;
+    }
+
+    /**
+     * Checks if all annotated content has been deleted.
+     *
+     * @return true if the range is empty
+     */
+    public boolean isCollapsed() {
+        return start == end;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (!(o instanceof JniAnnotation)) {
+            return false;
+        }
+        JniAnnotation other = (JniAnnotation) o;
+        return start == other.start && end == other.end && id.equals(other.id);
+    }
+
+    @Override
+    public int hashCode() {
+        return 31 * (31 * id.hashCode() + start) + end;
+    }
+
+    @Override
+    public String toString() {
+        return "JniAnnotation{id=" + id + ", start=" + start + ", end=" + end + "}";
+    }
+}
@@ -0,0 +1,78 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * What applying an update did to a document, returned by
+ * {@link JniYDoc#applyUpdateWithResult(byte[])}.
+ *
+ * <p>A sync layer can act on the status right away: a {@linkplain Status#PENDING
+ * pending} update depends on updates that have not arrived, and the
+ * {@linkplain #getMissingStateVector() missing state vector} can be sent to a peer,
+ * which can answer with {@link JniYDoc#encodeDiff(byte[])}. A
+ * {@linkplain Status#DUPLICATE duplicate} needs no further handling, such as
+ * relaying it to other peers.</p>
+ */
+public final class JniApplyResult {
+
+    /**
+     * The outcome of applying an update.
+     */
+    public enum Status {
+        /** The update changed the document and nothing is waiting for missing updates. */
+        APPLIED,
+        /**
+         * Some of the update is held back until updates it depends on arrive; the
+         * parts that did not depend on them may have been applied.
+         */
+        PENDING,
+        /** The document already contained everything in the update. */
+        DUPLICATE
+    }
+
+    private final Status status;
+    private final byte[] missingStateVector;
+
+    /**
+     * Called from native code.
+     *
+     * @param status ordinal of the status
+     * @param missingStateVector the encoded state vector of the missing clocks
+     */
+    JniApplyResult(int status, byte[] missingStateVector) {
+        this.status = Status.values()[status];
+        this.missingStateVector = missingStateVector;
+    }
+
+    /**
+     * Returns the outcome of applying the update.
+     *
+     * @return the status
+     */
+    public Status getStatus() {
+        return status;
+    }
+
+    /**
+     * Returns whether the update left content waiting for missing updates.
+     *
+     * @return true if the status is {@link Status#PENDING}
+     */
+    public boolean isPending() {
+        return status == Status.PENDING;
+    }
+
+    /**
+     * Returns the lowest clock per client that must arrive before the pending
+     * content can be integrated, as an encoded state vector.
+     *
+     * @return a copy of the encoded state vector, empty unless the update is pending
+     * @see JniYDoc#getPendingState()
+     */
+    public byte[] getMissingStateVector() {
+        return missingStateVector.clone();
+    }
+
+    @Override
+    public String toString() {
+        return "JniApplyResult{" + status + '}';
+    }
+}
@@ -0,0 +1,145 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A mutation of a root type, for use in a batch submitted with
+ * {@link JniYDoc#submitBatch(java.util.List, BatchCallback)}.
+ *
+ * <p>Operations address root types by name, so they can be built on any thread
+ * without holding shared type handles. Indexes and lengths follow the rules of the
+ * matching {@link JniYText}, {@link JniYArray} and {@link JniYMap} methods and are
+ * checked when the batch runs. Values may be {@link String}, {@link Long},
+ * {@link Integer}, {@link Double}, {@link Float}, {@link Boolean} or {@code null}.</p>
+ */
+public final class JniBatchOp {
+
+    static final int TEXT_INSERT = 0;
+    static final int TEXT_DELETE = 1;
+    static final int MAP_SET = 2;
+    static final int MAP_REMOVE = 3;
+    static final int ARRAY_INSERT = 4;
+    static final int ARRAY_DELETE = 5;
+
+    final int kind;
+    final String root;
+    final int index;
+    final int length;
+    final String string;
+    final Object value;
+
+    private JniBatchOp(int kind, String root, int index, int length, String string,
+                       Object value) {
+        if (root == null) {
+            throw new IllegalArgumentException("Root name cannot be null");
+        }
+        this.kind = kind;
+        this.root = root;
+        this.index = index;
+        this.length = length;
+        this.string = string;
+        this.value = value;
+    }
+
+    /**
+     * Inserts text into a root YText.
+     *
+     * @param root the name of the text
+     * @param index the position to insert at
+     * @param chunk the text to insert
+     * @return the operation
+     * @throws IllegalArgumentException if root or chunk is null
+     */
+    public static JniBatchOp textInsert(String root, int index, String chunk) {
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
+        }
+        return new JniBatchOp(TEXT_INSERT, root, index, 0, chunk, null);
+    }
+
+    /**
+     * Deletes a range of a root YText.
+     *
+     * @param root the name of the text
+     * @param index the start of the range
+     * @param length the length of the range
+     * @return the operation
+     * @throws IllegalArgumentException if root is null
+     */
+    public static JniBatchOp textDelete(String root, int index, int length) {
+        return new JniBatchOp(TEXT_DELETE, root, index, length, null, null);
+    }
+
+    /**
+     * Sets a key of a root YMap.
+     *
+     * @param root the name of the map
+     * @param key the key to set
+     * @param value the value to store
+     * @return the operation
+     * @throws IllegalArgumentException if root or key is null, or value is not a
+     *         supported type
+     */
+    public static JniBatchOp mapSet(String root, String key, Object value) {
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        checkValue(value);
+        return new JniBatchOp(MAP_SET, root, 0, 0, key, value);
+    }
+
+    /**
+     * Removes a key from a root YMap.
+     *
+     * @param root the name of the map
+     * @param key the key to remove
+     * @return the operation
+     * @throws IllegalArgumentException if root or key is null
+     */
+    public static JniBatchOp mapRemove(String root, String key) {
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        return new JniBatchOp(MAP_REMOVE, root, 0, 0, key, null);
+    }
+
+    /**
+     * Inserts a value into a root YArray.
+     *
+     * @param root the name of the array
+     * @param index the position to insert at
+     * @param value the value to insert
+     * @return the operation
+     * @throws IllegalArgumentException if root is null or value is not a supported type
+     */
+    public static JniBatchOp arrayInsert(String root, int index, Object value) {
+        checkValue(value);
+        return new JniBatchOp(ARRAY_INSERT, root, index, 0, null, value);
+    }
+
+    /**
+     * Deletes a range of a root YArray.
+     *
+     * @param root the name of the array
+     * @param index the start of the range
+     * @param length the number of elements to delete
+     * @return the operation
+     * @throws IllegalArgumentException if root is null
+     */
+    public static JniBatchOp arrayDelete(String root, int index, int length) {
+        return new JniBatchOp(ARRAY_DELETE, root, index, length, null, null);
+    }
+
+    private static void checkValue(Object value) {
+        if (value == null
+                || value == JniUndefined.UNDEFINED
+                || value instanceof String
+                || value instanceof Long
+                || value instanceof Integer
+                || value instanceof Double
+                || value instanceof Float
+                || value instanceof Boolean) {
+            return;
+        }
+        throw new IllegalArgumentException(
+            "Unsupported batch value type: " + value.getClass().getName());
+    }
+}
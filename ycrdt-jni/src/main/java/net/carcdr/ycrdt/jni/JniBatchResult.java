@@ -0,0 +1,64 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * The outcome of a batch submitted with
+ * {@link JniYDoc#submitBatch(java.util.List, BatchCallback)}.
+ *
+ * <p>Operations run in order and the first invalid one stops the batch. The
+ * operations before it cannot be rolled back: they stay applied and are part of the
+ * {@linkplain #getUpdate() update}.</p>
+ */
+public final class JniBatchResult {
+
+    private final byte[] update;
+    private final int appliedCount;
+    private final String error;
+
+    JniBatchResult(byte[] update, int appliedCount, String error) {
+        this.update = update;
+        this.appliedCount = appliedCount;
+        this.error = error;
+    }
+
+    /**
+     * Returns the v1 update produced by the batch's transaction.
+     *
+     * @return a copy of the update
+     */
+    public byte[] getUpdate() {
+        return update.clone();
+    }
+
+    /**
+     * Returns the number of operations that were applied.
+     *
+     * @return the applied operation count
+     */
+    public int getAppliedCount() {
+        return appliedCount;
+    }
+
+    /**
+     * Returns why the operation after the applied ones failed.
+     *
+     * @return the error message, or null if every operation was applied
+     */
+    public String getError() {
+        return error;
+    }
+
+    /**
+     * Checks whether every operation was applied.
+     *
+     * @return true if the batch completed without error
+     */
+    public boolean isSuccess() {
+        return error == null;
+    }
+
+    @Override
+    public String toString() {
+        return "JniBatchResult{applied=" + appliedCount + ", updateBytes=" + update.length
+            + (error == null ? "" : ", error=" + error) + '}';
+    }
+}
@@ -0,0 +1,110 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A selection in a text, in the form Yjs editor bindings publish in the
+ * awareness state.
+ *
+ * <p>Bindings such as y-prosemirror and y-quill set the {@code cursor} field of
+ * the local awareness state to {@code {"anchor": ..., "head": ...}}, where both
+ * positions are relative positions in their JSON form. {@link #toJson()} produces
+ * the same JSON, so a Java client can show its selection to Yjs clients, and
+ * {@link #fromJson(String)} reads the selections they publish:</p>
+ * <pre>{@code
+ * JniCursor cursor = new JniCursor(
+ *     text.stickyIndex(anchor, JniStickyIndex.Assoc.AFTER),
+ *     text.stickyIndex(head, JniStickyIndex.Assoc.AFTER));
+ * String json = cursor.toJson(); // value of the awareness "cursor" field
+ * int peerHead = text.resolveStickyIndex(JniCursor.fromJson(peerJson).getHead());
+ * }</pre>
+ *
+ * <p>Instances are immutable values and do not reference native memory.</p>
+ */
+public final class JniCursor {
+
+    private final JniStickyIndex anchor;
+    private final JniStickyIndex head;
+
+    /**
+     * Creates a cursor.
+     *
+     * @param anchor the position where the selection starts
+     * @param head the position where the selection ends; equal to anchor for a caret
+     * @throws IllegalArgumentException if anchor or head is null
+     */
+    public JniCursor(JniStickyIndex anchor, JniStickyIndex head) {
+        if (anchor == null || head == null) {
+            throw new IllegalArgumentException("Anchor and head cannot be null");
+        }
+        this.anchor = anchor;
+        this.head = head;
+    }
+
+    /**
+     * Reads a cursor from awareness cursor JSON.
+     *
+     * @param json a JSON object with {@code anchor} and {@code head} positions
+     * @return the cursor
+     * @throws IllegalArgumentException if json is null or not a valid cursor
+     */
+    public static JniCursor fromJson(String json) {
+        if (json == null) {
+            throw new IllegalArgumentException("JSON cannot be null");
+        }
+        byte[][] encoded = nativeFromJson(json);
+        return new JniCursor(JniStickyIndex.fromNative(encoded[0]),
+            JniStickyIndex.fromNative(encoded[1]));
+    }
+
+    /**
+     * Returns the awareness cursor JSON for this selection.
+     *
+     * @return a JSON object with {@code anchor} and {@code head} positions
+     */
+    public String toJson() {
+        return nativeToJson(anchor.getEncoded(), head.getEncoded());
+    }
+
+    /**
+     * Returns the position where the selection starts.
+     *
+     * @return the anchor
+     */
+    public JniStickyIndex getAnchor() {
+        return anchor;
+    }
+
+    /**
+     * Returns the position where the selection ends.
+     *
+     * @return the head
+     */
+    public JniStickyIndex getHead() {
+        return head;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (!(o instanceof JniCursor)) {
+            return false;
+        }
+        JniCursor other = (JniCursor) o;
+        return anchor.equals(other.anchor) && head.equals(other.head);
+    }
+
+    @Override
+    public int hashCode() {
+        return 31 * anchor.hashCode() + head.hashCode();
+    }
+
+    @Override
+    public String toString() {
+        return "JniCursor{anchor=" + anchor + ", head=" + head + "}";
+    }
+
+    private static native String nativeToJson(byte[] anchor, byte[] head);
+
+    private static native byte[][] nativeFromJson(String json);
+}
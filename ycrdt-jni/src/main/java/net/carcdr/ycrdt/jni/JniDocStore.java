@@ -0,0 +1,461 @@
+package net.carcdr.ycrdt.jni;
+
+import java.time.Duration;
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+import java.util.function.BiConsumer;
+
+/**
+ * A collection of documents keyed by name, with get-or-create and a capacity limit.
+ *
+ * <p>Servers hosting many documents usually keep a map from document name to
+ * document, create entries on the first connection and close them once idle.
+ * Looking up and creating separately races: two connections can both create a
+ * document for the same name, or one can close a document another has just looked
+ * up. A store does both under one lock and keeps exactly one {@link JniYDoc} per
+ * name:</p>
+ * <pre>{@code
+ * try (JniDocStore store = new JniDocStore(1000)) {
+ *     store.setEvictionListener((name, doc) -> persist(name, doc.encodeStateAsUpdate()));
+ *     JniYDoc doc = store.getOrCreate("room-42");
+ * }
+ * }</pre>
+ *
+ * <p>A document created by the store has its name as its GUID. Once the store
+ * holds more documents than its capacity, creating another evicts the least
+ * recently used ones: they are removed, passed to the eviction listener and
+ * closed. Closing a stored document directly removes it from the store, and
+ * closing the store closes every document it still holds.</p>
+ *
+ * <p>Idle documents can be compacted: a document that changed since it was last
+ * compacted, and has not changed for a while, is encoded as a single update and
+ * passed to a {@link DocCompactionListener}, which can replace the updates it
+ * persisted for the document with that snapshot. Compaction runs on demand with
+ * {@link #compactIdle(Duration, DocCompactionListener)}, or periodically on a
+ * native background thread started with
+ * {@link #startCompaction(Duration, Duration, DocCompactionListener)}. Documents
+ * with an open transaction are skipped until a later pass.</p>
+ *
+ * <p>All methods are thread-safe.</p>
+ */
+public final class JniDocStore implements AutoCloseable {
+
+    static {
+        NativeLoader.loadLibrary();
+    }
+
+    /**
+     * A document held by the store, with the name it is stored under.
+     */
+    private static final class StoredDoc {
+        final String name;
+        final long ptr;
+        final JniYDoc doc;
+
+        StoredDoc(String name, long ptr, JniYDoc doc) {
+            this.name = name;
+            this.ptr = ptr;
+            this.doc = doc;
+        }
+    }
+
+    /**
+     * Receives compacted documents from native code and passes them to a listener.
+     */
+    private final class CompactionCallback {
+        private final DocCompactionListener listener;
+
+        CompactionCallback(DocCompactionListener listener) {
+            this.listener = listener;
+        }
+
+        @SuppressWarnings("unused") // Called from native code
+        void compacted(String name, long docPtr, byte[] state) {
+            try {
+                listener.onCompact(name, state);
+            } catch (Exception e) {
+                JniYDoc doc = storedDoc(docPtr);
+                if (doc != null) {
+                    doc.getObserverErrorHandler().handleError(e, doc);
+                }
+            }
+        }
+    }
+
+    private final int capacity;
+    private final Map<Long, StoredDoc> docs = new HashMap<>();
+    private volatile DocEvictionListener evictionListener;
+    private long nativePtr;
+
+    /**
+     * Creates a store without a capacity limit.
+     */
+    public JniDocStore() {
+        this(0);
+    }
+
+    /**
+     * Creates a store that keeps at most the given number of documents.
+     *
+     * @param capacity the largest number of documents kept, or 0 for no limit
+     * @throws IllegalArgumentException if capacity is negative
+     */
+    public JniDocStore(int capacity) {
+        if (capacity < 0) {
+            throw new IllegalArgumentException("Capacity cannot be negative");
+        }
+        this.capacity = capacity;
+        this.nativePtr = nativeCreate(capacity);
+        if (nativePtr == 0) {
+            throw new RuntimeException("Failed to create document store");
+        }
+    }
+
+    /**
+     * Returns the document stored under a name, creating it if there is none.
+     *
+     * <p>Creating a document beyond the capacity evicts the least recently used
+     * others before this method returns.</p>
+     *
+     * @param name the document name
+     * @return the stored document
+     * @throws IllegalArgumentException if name is null
+     * @throws IllegalStateException if the store has been closed
+     */
+    public JniYDoc getOrCreate(String name) {
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        JniYDoc doc;
+        List<StoredDoc> evicted = new ArrayList<>();
+        synchronized (this) {
+            checkClosed();
+            long[] result = nativeGetOrCreate(nativePtr, name);
+            long ptr = result[0];
+            if (result[1] != 0) {
+                doc = new JniYDoc(ptr, true);
+                StoredDoc stored = new StoredDoc(name, ptr, doc);
+                docs.put(ptr, stored);
+                doc.setCloseHook(() -> release(stored));
+            } else {
+                doc = docs.get(ptr).doc;
+            }
+            for (int i = 2; i < result.length; i++) {
+                evicted.add(docs.remove(result[i]));
+            }
+        }
+        evictAll(evicted);
+        return doc;
+    }
+
+    /**
+     * Returns the document stored under a name, marking it as recently used.
+     *
+     * @param name the document name
+     * @return the stored document, or null if there is none
+     * @throws IllegalArgumentException if name is null
+     * @throws IllegalStateException if the store has been closed
+     */
+    public synchronized JniYDoc get(String name) {
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        checkClosed();
+        StoredDoc stored = docs.get(nativeGet(nativePtr, name));
+        return stored == null ? null : stored.doc;
+    }
+
+    /**
+     * Removes the document stored under a name without closing it.
+     *
+     * <p>The caller becomes responsible for closing the returned document. A later
+     * {@link #getOrCreate(String)} for the same name creates a new document.</p>
+     *
+     * @param name the document name
+     * @return the removed document, or null if there was none
+     * @throws IllegalArgumentException if name is null
+     * @throws IllegalStateException if the store has been closed
+     */
+    public JniYDoc remove(String name) {
+        StoredDoc stored = detach(name);
+        return stored == null ? null : stored.doc;
+    }
+
+    /**
+     * Removes the document stored under a name, passes it to the eviction
+     * listener and closes it.
+     *
+     * @param name the document name
+     * @return true if a document was evicted
+     * @throws IllegalArgumentException if name is null
+     * @throws IllegalStateException if the store has been closed
+     */
+    public boolean evict(String name) {
+        StoredDoc stored = detach(name);
+        if (stored == null) {
+            return false;
+        }
+        evictAll(List.of(stored));
+        return true;
+    }
+
+    /**
+     * Returns the names of the stored documents.
+     *
+     * @return the names, sorted
+     * @throws IllegalStateException if the store has been closed
+     */
+    public synchronized List<String> names() {
+        checkClosed();
+        return Arrays.asList(nativeNames(nativePtr));
+    }
+
+    /**
+     * Returns the number of stored documents.
+     *
+     * @return the number of documents
+     * @throws IllegalStateException if the store has been closed
+     */
+    public synchronized int size() {
+        checkClosed();
+        return nativeSize(nativePtr);
+    }
+
+    /**
+     * Returns the largest number of documents this store keeps.
+     *
+     * @return the capacity, or 0 if there is no limit
+     */
+    public int getCapacity() {
+        return capacity;
+    }
+
+    /**
+     * Calls an action for every stored document, in order of name.
+     *
+     * <p>The action runs without the store's lock held, on the documents stored
+     * when this method was called, and does not mark them as used.</p>
+     *
+     * @param action the action to call with each name and document
+     * @throws IllegalArgumentException if action is null
+     * @throws IllegalStateException if the store has been closed
+     */
+    public void forEach(BiConsumer<String, JniYDoc> action) {
+        if (action == null) {
+            throw new IllegalArgumentException("Action cannot be null");
+        }
+        List<StoredDoc> snapshot;
+        synchronized (this) {
+            checkClosed();
+            snapshot = new ArrayList<>(docs.values());
+        }
+        snapshot.sort((a, b) -> a.name.compareTo(b.name));
+        for (StoredDoc stored : snapshot) {
+            action.accept(stored.name, stored.doc);
+        }
+    }
+
+    /**
+     * Compacts the idle documents on the calling thread.
+     *
+     * <p>Every document that changed since it was last compacted, and has not
+     * changed for at least {@code idleAfter}, is encoded and passed to the listener
+     * before this method returns. The listener runs with the store's lock held.</p>
+     *
+     * @param idleAfter how long a document must not have changed
+     * @param listener the listener to pass the compacted documents to
+     * @return the number of documents compacted
+     * @throws IllegalArgumentException if idleAfter or listener is null, or
+     *         idleAfter is negative
+     * @throws IllegalStateException if the store has been closed
+     */
+    public synchronized int compactIdle(Duration idleAfter, DocCompactionListener listener) {
+        checkDuration(idleAfter, "Idle time");
+        if (listener == null) {
+            throw new IllegalArgumentException("Listener cannot be null");
+        }
+        checkClosed();
+        return nativeCompactIdle(nativePtr, idleAfter.toMillis(), new CompactionCallback(listener));
+    }
+
+    /**
+     * Starts compacting idle documents periodically on a background thread.
+     *
+     * <p>Every {@code interval}, the thread compacts the documents that changed
+     * since they were last compacted and have not changed for at least
+     * {@code idleAfter}, and passes them to the listener on that thread. A
+     * document is noticed as changed by the first pass after the change, so it is
+     * compacted between {@code idleAfter} and {@code idleAfter + interval} after
+     * its last change. Starting compaction again replaces the running thread.
+     * Closing the store stops it.</p>
+     *
+     * @param interval the time between compaction passes
+     * @param idleAfter how long a document must not have changed
+     * @param listener the listener to pass the compacted documents to
+     * @throws IllegalArgumentException if an argument is null, interval is not
+     *         positive or idleAfter is negative
+     * @throws IllegalStateException if the store has been closed
+     */
+    public synchronized void startCompaction(Duration interval, Duration idleAfter,
+                                             DocCompactionListener listener) {
+        checkDuration(interval, "Interval");
+        if (interval.isZero()) {
+            throw new IllegalArgumentException("Interval must be positive");
+        }
+        checkDuration(idleAfter, "Idle time");
+        if (listener == null) {
+            throw new IllegalArgumentException("Listener cannot be null");
+        }
+        checkClosed();
+        nativeStartCompaction(nativePtr, interval.toMillis(), idleAfter.toMillis(),
+            new CompactionCallback(listener));
+    }
+
+    /**
+     * Stops the background compaction started by
+     * {@link #startCompaction(Duration, Duration, DocCompactionListener)}.
+     *
+     * <p>A pass in progress reports no further documents. Does nothing if
+     * compaction is not running.</p>
+     *
+     * @throws IllegalStateException if the store has been closed
+     */
+    public synchronized void stopCompaction() {
+        checkClosed();
+        nativeStopCompaction(nativePtr);
+    }
+
+    /**
+     * Sets the listener called before an evicted document is closed.
+     *
+     * @param listener the listener, or null for none
+     */
+    public void setEvictionListener(DocEvictionListener listener) {
+        this.evictionListener = listener;
+    }
+
+    /**
+     * Returns whether this store has been closed.
+     *
+     * @return true if the store has been closed
+     */
+    public synchronized boolean isClosed() {
+        return nativePtr == 0;
+    }
+
+    /**
+     * Closes the store, evicting every document it still holds.
+     *
+     * <p>The documents are passed to the eviction listener and closed least
+     * recently used first. This method is idempotent.</p>
+     */
+    @Override
+    public void close() {
+        List<StoredDoc> evicted = new ArrayList<>();
+        synchronized (this) {
+            if (nativePtr == 0) {
+                return;
+            }
+            nativeStopCompaction(nativePtr);
+            for (long ptr : nativeDrain(nativePtr)) {
+                evicted.add(docs.remove(ptr));
+            }
+            nativeDestroy(nativePtr);
+            nativePtr = 0;
+        }
+        evictAll(evicted);
+    }
+
+    private synchronized StoredDoc detach(String name) {
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        checkClosed();
+        long ptr = nativeGet(nativePtr, name);
+        if (ptr == 0 || !nativeRemove(nativePtr, name, ptr)) {
+            return null;
+        }
+        StoredDoc stored = docs.remove(ptr);
+        stored.doc.setCloseHook(null);
+        return stored;
+    }
+
+    /**
+     * Forgets a document that is being closed directly, before it is freed.
+     */
+    private synchronized void release(StoredDoc stored) {
+        if (nativePtr != 0 && nativeRemove(nativePtr, stored.name, stored.ptr)) {
+            docs.remove(stored.ptr);
+        }
+    }
+
+    private synchronized JniYDoc storedDoc(long ptr) {
+        StoredDoc stored = docs.get(ptr);
+        return stored == null ? null : stored.doc;
+    }
+
+    private static void checkDuration(Duration duration, String what) {
+        if (duration == null) {
+            throw new IllegalArgumentException(what + " cannot be null");
+        }
+        if (duration.isNegative()) {
+            throw new IllegalArgumentException(what + " cannot be negative");
+        }
+    }
+
+    private void evictAll(List<StoredDoc> evicted) {
+        RuntimeException failure = null;
+        for (StoredDoc stored : evicted) {
+            stored.doc.setCloseHook(null);
+            DocEvictionListener listener = evictionListener;
+            try {
+                if (listener != null) {
+                    listener.onEvict(stored.name, stored.doc);
+                }
+            } catch (RuntimeException e) {
+                if (failure == null) {
+                    failure = e;
+                } else {
+                    failure.addSuppressed(e);
+                }
+            } finally {
+                stored.doc.close();
+            }
+        }
+        if (failure != null) {
+            throw failure;
+        }
+    }
+
+    private void checkClosed() {
+        if (nativePtr == 0) {
+            throw new IllegalStateException("JniDocStore has been closed");
+        }
+    }
+
+    private static native long nativeCreate(int capacity);
+
+    private static native void nativeDestroy(long ptr);
+
+    private static native long[] nativeGetOrCreate(long ptr, String name);
+
+    private static native long nativeGet(long ptr, String name);
+
+    private static native boolean nativeRemove(long ptr, String name, long docPtr);
+
+    private static native String[] nativeNames(long ptr);
+
+    private static native int nativeSize(long ptr);
+
+    private static native long[] nativeDrain(long ptr);
+
+    private static native int nativeCompactIdle(long ptr, long idleMillis, Object callback);
+
+    private static native void nativeStartCompaction(long ptr, long intervalMillis,
+        long idleMillis, Object callback);
+
+    private static native void nativeStopCompaction(long ptr);
+}
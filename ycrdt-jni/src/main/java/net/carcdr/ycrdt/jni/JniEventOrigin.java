@@ -0,0 +1,111 @@
+package net.carcdr.ycrdt.jni;
+
+import java.nio.charset.StandardCharsets;
+import java.util.Arrays;
+
+/**
+ * Describes the transaction that produced an observer event.
+ *
+ * <p>A transaction is remote if it applied an update received from a peer, with
+ * {@link JniYDoc#applyUpdate(byte[])} or a similar method, and local otherwise.
+ * The origin is the value passed to {@link JniYDoc#beginTransaction(String)} or
+ * {@link JniYDoc#applyUpdate(byte[], String)}; yrs stores it as bytes, which are
+ * available as-is and decoded as UTF-8.</p>
+ *
+ * <p>Instances are immutable.</p>
+ *
+ * @see JniYEvent#getEventOrigin()
+ */
+public final class JniEventOrigin {
+
+    private final byte[] bytes;
+    private final long clientId;
+    private final boolean local;
+
+    /**
+     * Package-private constructor. Origins are created by the native layer.
+     *
+     * @param bytes the origin bytes, or null if the transaction had no origin
+     * @param clientId the client that made the changes, or -1 if unknown
+     * @param local whether the transaction was local
+     */
+    JniEventOrigin(byte[] bytes, long clientId, boolean local) {
+        this.bytes = bytes;
+        this.clientId = clientId;
+        this.local = local;
+    }
+
+    /**
+     * Checks if the transaction had an origin.
+     *
+     * @return true if an origin was set
+     */
+    public boolean hasOrigin() {
+        return bytes != null;
+    }
+
+    /**
+     * Gets the origin bytes.
+     *
+     * @return a copy of the origin bytes, or null if the transaction had no origin
+     */
+    public byte[] getBytes() {
+        return bytes == null ? null : bytes.clone();
+    }
+
+    /**
+     * Gets the origin decoded as UTF-8, replacing malformed input.
+     *
+     * @return the origin string, or null if the transaction had no origin
+     */
+    public String getString() {
+        return bytes == null ? null : new String(bytes, StandardCharsets.UTF_8);
+    }
+
+    /**
+     * Gets the client that made the changes.
+     *
+     * <p>For local transactions this is the document's client ID. For remote ones
+     * it is the client whose changes were applied, or -1 if the update carried
+     * changes from several clients or only deleted content.</p>
+     *
+     * @return the client ID, or -1 if unknown
+     */
+    public long getClientId() {
+        return clientId;
+    }
+
+    /**
+     * Checks if the transaction was local.
+     *
+     * @return true if the transaction did not apply an update from a peer
+     */
+    public boolean isLocal() {
+        return local;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (!(o instanceof JniEventOrigin)) {
+            return false;
+        }
+        JniEventOrigin other = (JniEventOrigin) o;
+        return clientId == other.clientId && local == other.local
+            && Arrays.equals(bytes, other.bytes);
+    }
+
+    @Override
+    public int hashCode() {
+        return 31 * (31 * Arrays.hashCode(bytes) + Long.hashCode(clientId)) + Boolean.hashCode(local);
+    }
+
+    @Override
+    public String toString() {
+        return "JniEventOrigin{origin=" + getString()
+             + ", clientId=" + clientId
+             + ", local=" + local + "}";
+    }
+}
@@ -0,0 +1,79 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.Arrays;
+import java.util.Collections;
+import java.util.LinkedHashSet;
+import java.util.Set;
+
+/**
+ * The outcome of {@link JniYDoc#applyUpdateFiltered(byte[], java.util.function.Predicate)}.
+ *
+ * <p>Lists the root types the update touched, split into those whose changes were
+ * applied and those whose changes were dropped.</p>
+ */
+public final class JniFilteredUpdate {
+
+    private final Set<String> allowedRoots;
+    private final Set<String> deniedRoots;
+    private final boolean droppedUnresolved;
+
+    /**
+     * Called from native code.
+     *
+     * @param allowedRoots names of roots whose changes were applied, in name order
+     * @param deniedRoots names of roots whose changes were dropped, in name order
+     * @param droppedUnresolved whether changes that could not be traced to a root were dropped
+     */
+    JniFilteredUpdate(String[] allowedRoots, String[] deniedRoots, boolean droppedUnresolved) {
+        this.allowedRoots = Collections.unmodifiableSet(
+            new LinkedHashSet<>(Arrays.asList(allowedRoots)));
+        this.deniedRoots = Collections.unmodifiableSet(
+            new LinkedHashSet<>(Arrays.asList(deniedRoots)));
+        this.droppedUnresolved = droppedUnresolved;
+    }
+
+    /**
+     * Returns the roots whose changes were applied.
+     *
+     * @return an unmodifiable set of root names, in name order
+     */
+    public Set<String> getAllowedRoots() {
+        return allowedRoots;
+    }
+
+    /**
+     * Returns the roots whose changes were dropped.
+     *
+     * @return an unmodifiable set of root names, in name order
+     */
+    public Set<String> getDeniedRoots() {
+        return deniedRoots;
+    }
+
+    /**
+     * Checks if changes were dropped because they could not be traced to a root.
+     *
+     * <p>This happens when the update depends on content this document has not
+     * received yet.</p>
+     *
+     * @return true if unresolved changes were dropped
+     */
+    public boolean hasDroppedUnresolved() {
+        return droppedUnresolved;
+    }
+
+    /**
+     * Checks if the whole update was applied.
+     *
+     * @return true if no changes were dropped
+     */
+    public boolean isFullyApplied() {
+        return deniedRoots.isEmpty() && !droppedUnresolved;
+    }
+
+    @Override
+    public String toString() {
+        return "JniFilteredUpdate{allowed=" + allowedRoots + ", denied=" + deniedRoots
+            + (droppedUnresolved ? ", droppedUnresolved" : "") + '}';
+    }
+}
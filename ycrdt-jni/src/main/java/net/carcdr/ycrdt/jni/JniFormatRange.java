@@ -0,0 +1,141 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.Collections;
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+import java.util.Objects;
+
+/**
+ * A range of text and the formatting attributes to apply to it.
+ *
+ * <p>Used with {@link JniYText#formatRanges(List)} and
+ * {@link JniYXmlText#formatRanges(List)} to apply many format operations in a
+ * single native call, for example when importing a styled document.</p>
+ */
+public final class JniFormatRange {
+
+    private final int index;
+    private final int length;
+    private final Map<String, Object> attributes;
+
+    /**
+     * Creates a format range.
+     *
+     * @param index the starting index of the range (0-based)
+     * @param length the number of characters to format
+     * @param attributes the attributes to apply; a null value removes that attribute
+     * @throws IllegalArgumentException if index or length is negative, or attributes is null
+     */
+    public JniFormatRange(int index, int length, Map<String, Object> attributes) {
+        if (index < 0) {
+            throw new IllegalArgumentException("Index cannot be negative: " + index);
+        }
+        if (length < 0) {
+            throw new IllegalArgumentException("Length cannot be negative: " + length);
+        }
+        if (attributes == null) {
+            throw new IllegalArgumentException("Attributes cannot be null");
+        }
+        this.index = index;
+        this.length = length;
+        this.attributes = Collections.unmodifiableMap(new HashMap<>(attributes));
+    }
+
+    /**
+     * Returns the starting index of the range.
+     *
+     * @return the index
+     */
+    public int getIndex() {
+        return index;
+    }
+
+    /**
+     * Returns the number of characters in the range.
+     *
+     * @return the length
+     */
+    public int getLength() {
+        return length;
+    }
+
+    /**
+     * Returns the attributes to apply.
+     *
+     * @return an unmodifiable map of attributes
+     */
+    public Map<String, Object> getAttributes() {
+        return attributes;
+    }
+
+    /**
+     * Flattens ranges into {@code (index, length)} pairs for native code.
+     *
+     * @param ranges the ranges
+     * @return an array of twice the size of ranges
+     */
+    static int[] toBounds(List<JniFormatRange> ranges) {
+        int[] bounds = new int[ranges.size() * 2];
+        for (int i = 0; i < ranges.size(); i++) {
+            JniFormatRange range = ranges.get(i);
+            bounds[i * 2] = range.index;
+            bounds[i * 2 + 1] = range.length;
+        }
+        return bounds;
+    }
+
+    /**
+     * Collects the attribute maps of ranges for native code.
+     *
+     * @param ranges the ranges
+     * @return one attribute map per range
+     */
+    static Object[] toAttributes(List<JniFormatRange> ranges) {
+        Object[] attributes = new Object[ranges.size()];
+        for (int i = 0; i < ranges.size(); i++) {
+            attributes[i] = ranges.get(i).attributes;
+        }
+        return attributes;
+    }
+
+    /**
+     * Checks that a list of ranges and its elements are not null.
+     *
+     * @param ranges the ranges
+     * @throws IllegalArgumentException if ranges or any element is null
+     */
+    static void requireValid(List<JniFormatRange> ranges) {
+        if (ranges == null) {
+            throw new IllegalArgumentException("Ranges cannot be null");
+        }
+        for (JniFormatRange range : ranges) {
+            if (range == null) {
+                throw new IllegalArgumentException("Ranges cannot contain null");
+            }
+        }
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (o == null || getClass() != o.getClass()) {
+            return false;
+        }
+        JniFormatRange that = (JniFormatRange) o;
+        return index == that.index && length == that.length && attributes.equals(that.attributes);
+    }
+
+    @Override
+    public int hashCode() {
+        return Objects.hash(index, length, attributes);
+    }
+
+    @Override
+    public String toString() {
+        return "JniFormatRange{index=" + index + ", length=" + length
+                + ", attributes=" + attributes + '}';
+    }
+}
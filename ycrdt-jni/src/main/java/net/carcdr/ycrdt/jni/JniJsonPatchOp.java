@@ -0,0 +1,110 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YChange;
+
+/**
+ * One JSON Patch (RFC 6902) operation describing a change to a {@link JniYJson}.
+ *
+ * <p>The operations of an event, applied in order to the JSON value before the
+ * transaction, give the value after it. Their types map onto {@link YChange.Type}:</p>
+ * <ul>
+ *   <li><b>INSERT:</b> {@code add}, a member or array element was added</li>
+ *   <li><b>ATTRIBUTE:</b> {@code replace}, a value was replaced</li>
+ *   <li><b>DELETE:</b> {@code remove}, a member or array element was removed</li>
+ * </ul>
+ *
+ * @see JniYJson#observe(net.carcdr.ycrdt.YObserver)
+ */
+public final class JniJsonPatchOp extends YChange {
+
+    private final String op;
+    private final String path;
+    private final String value;
+
+    /**
+     * Package-private constructor. Operations are created by the native layer.
+     *
+     * @param op the operation name: {@code add}, {@code replace} or {@code remove}
+     * @param path the JSON Pointer the operation applies to
+     * @param value the JSON text of the new value, or null for {@code remove}
+     */
+    JniJsonPatchOp(String op, String path, String value) {
+        this.op = op;
+        this.path = path;
+        this.value = value;
+    }
+
+    @Override
+    public Type getType() {
+        switch (op) {
+            case "add":
+                return Type.INSERT;
+            case "remove":
+                return Type.DELETE;
+            default:
+                return Type.ATTRIBUTE;
+        }
+    }
+
+    /**
+     * Returns the operation name.
+     *
+     * @return {@code add}, {@code replace} or {@code remove}
+     */
+    public String getOp() {
+        return op;
+    }
+
+    /**
+     * Returns the JSON Pointer the operation applies to.
+     *
+     * @return the path, such as {@code /users/0/name}
+     */
+    public String getPath() {
+        return path;
+    }
+
+    /**
+     * Returns the JSON text of the new value.
+     *
+     * @return the value, or null for {@code remove}
+     */
+    public String getValue() {
+        return value;
+    }
+
+    /**
+     * Returns the operation as a JSON Patch object, such as
+     * {@code {"op":"add","path":"/a","value":1}}.
+     *
+     * @return the JSON text of the operation
+     */
+    public String toJson() {
+        StringBuilder json = new StringBuilder("{\"op\":\"").append(op).append("\",\"path\":");
+        appendString(json, path);
+        if (value != null) {
+            json.append(",\"value\":").append(value);
+        }
+        return json.append('}').toString();
+    }
+
+    private static void appendString(StringBuilder json, String s) {
+        json.append('"');
+        for (int i = 0; i < s.length(); i++) {
+            char c = s.charAt(i);
+            if (c == '"' || c == '\\') {
+                json.append('\\').append(c);
+            } else if (c < 0x20) {
+                json.append(String.format("\\u%04x", (int) c));
+            } else {
+                json.append(c);
+            }
+        }
+        json.append('"');
+    }
+
+    @Override
+    public String toString() {
+        return toJson();
+    }
+}
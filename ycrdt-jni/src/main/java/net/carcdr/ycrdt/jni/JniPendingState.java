@@ -0,0 +1,72 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Content a document is holding back because it depends on updates that have not
+ * arrived yet.
+ *
+ * <p>yrs keeps the parts of an update it cannot integrate, and deletions of content
+ * it has not seen, until the missing updates are applied. The
+ * {@linkplain #getMissingStateVector() missing state vector} can be sent to a peer,
+ * which can answer with {@link JniYDoc#encodeDiff(byte[])} to fill the gap.</p>
+ *
+ * @see JniYDoc#getPendingState()
+ */
+public final class JniPendingState {
+
+    private final long structCount;
+    private final long byteSize;
+    private final long deletedLength;
+    private final byte[] missingStateVector;
+
+    JniPendingState(long structCount, long byteSize, long deletedLength,
+                    byte[] missingStateVector) {
+        this.structCount = structCount;
+        this.byteSize = byteSize;
+        this.deletedLength = deletedLength;
+        this.missingStateVector = missingStateVector;
+    }
+
+    /**
+     * Returns the number of structs waiting to be integrated.
+     *
+     * @return the pending struct count
+     */
+    public long getStructCount() {
+        return structCount;
+    }
+
+    /**
+     * Returns the size of the pending structs in v1 update encoding.
+     *
+     * @return the size in bytes
+     */
+    public long getByteSize() {
+        return byteSize;
+    }
+
+    /**
+     * Returns the number of clock units whose deletion is waiting for the deleted
+     * content to arrive.
+     *
+     * @return the pending deleted length
+     */
+    public long getDeletedLength() {
+        return deletedLength;
+    }
+
+    /**
+     * Returns the lowest clock per client that must arrive before the pending content
+     * can be integrated, as an encoded state vector.
+     *
+     * @return a copy of the encoded state vector
+     */
+    public byte[] getMissingStateVector() {
+        return missingStateVector.clone();
+    }
+
+    @Override
+    public String toString() {
+        return "JniPendingState{structs=" + structCount + ", bytes=" + byteSize
+            + ", deletedLength=" + deletedLength + '}';
+    }
+}
@@ -0,0 +1,246 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.ArrayList;
+import java.util.LinkedHashMap;
+import java.util.List;
+import java.util.Map;
+
+/**
+ * Content built before it is added to a document.
+ *
+ * <p>A prelim is a map, array, text or XML node that belongs to no document. It
+ * can hold plain values and other prelims, so a whole tree can be assembled
+ * without a transaction and then added to a root type in one call with
+ * {@link JniYDoc#commitPrelim(String, JniPrelim)}:</p>
+ * <pre>{@code
+ * JniPrelim page = JniPrelim.map()
+ *     .put("title", "Notes")
+ *     .put("tags", JniPrelim.array().add("draft").add("todo"))
+ *     .put("body", JniPrelim.text().append("Hello", Map.of("bold", true)));
+ * doc.commitPrelim("page", page);
+ * }</pre>
+ *
+ * <p>Plain values may be {@link String}, {@link Long}, {@link Integer},
+ * {@link Double}, {@link Float}, {@link Boolean} or {@code null}; they are
+ * checked when the prelim is committed. A prelim can be committed any number of
+ * times, and each commit creates new shared types. Prelims are not thread-safe.</p>
+ */
+public final class JniPrelim {
+
+    static final int MAP = 0;
+    static final int ARRAY = 1;
+    static final int TEXT = 2;
+    static final int XML_FRAGMENT = 3;
+    static final int XML_ELEMENT = 4;
+    static final int XML_TEXT = 5;
+
+    // Read by native code when the prelim is committed
+    final int kind;
+    final String tag;
+    /** Map entries, or XML attributes. */
+    final Map<String, Object> entries = new LinkedHashMap<>();
+    /** Array items, or XML children. */
+    final List<Object> items = new ArrayList<>();
+    /** Text chunks. */
+    final List<String> chunks = new ArrayList<>();
+    /** Formatting attributes of each chunk, or null for unformatted chunks. */
+    final List<Map<String, Object>> formats = new ArrayList<>();
+
+    private JniPrelim(int kind, String tag) {
+        this.kind = kind;
+        this.tag = tag;
+    }
+
+    /**
+     * Creates an empty map.
+     *
+     * @return the prelim
+     */
+    public static JniPrelim map() {
+        return new JniPrelim(MAP, null);
+    }
+
+    /**
+     * Creates an empty array.
+     *
+     * @return the prelim
+     */
+    public static JniPrelim array() {
+        return new JniPrelim(ARRAY, null);
+    }
+
+    /**
+     * Creates an empty text.
+     *
+     * @return the prelim
+     */
+    public static JniPrelim text() {
+        return new JniPrelim(TEXT, null);
+    }
+
+    /**
+     * Creates an empty XML fragment.
+     *
+     * @return the prelim
+     */
+    public static JniPrelim xmlFragment() {
+        return new JniPrelim(XML_FRAGMENT, null);
+    }
+
+    /**
+     * Creates an empty XML element.
+     *
+     * @param tag the element's tag name
+     * @return the prelim
+     * @throws IllegalArgumentException if tag is null
+     */
+    public static JniPrelim xmlElement(String tag) {
+        if (tag == null) {
+            throw new IllegalArgumentException("Tag cannot be null");
+        }
+        return new JniPrelim(XML_ELEMENT, tag);
+    }
+
+    /**
+     * Creates an empty XML text.
+     *
+     * @return the prelim
+     */
+    public static JniPrelim xmlText() {
+        return new JniPrelim(XML_TEXT, null);
+    }
+
+    /**
+     * Sets a key of this map. Setting a key again replaces its value.
+     *
+     * @param key the key
+     * @param value a plain value or a prelim
+     * @return this prelim
+     * @throws IllegalArgumentException if key is null
+     * @throws UnsupportedOperationException if this prelim is not a map
+     */
+    public JniPrelim put(String key, Object value) {
+        requireKind("put", MAP);
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        entries.put(key, value);
+        return this;
+    }
+
+    /**
+     * Appends an item to this array, or a child to this XML fragment or element.
+     *
+     * @param value a plain value or a prelim; XML nodes only accept XML element
+     *        and XML text prelims
+     * @return this prelim
+     * @throws IllegalArgumentException if an XML node is given anything but an XML
+     *         element or XML text prelim
+     * @throws UnsupportedOperationException if this prelim is not an array, XML
+     *         fragment or XML element
+     */
+    public JniPrelim add(Object value) {
+        requireKind("add", ARRAY, XML_FRAGMENT, XML_ELEMENT);
+        if (kind != ARRAY && !isXmlChild(value)) {
+            throw new IllegalArgumentException(
+                "XML nodes can only contain XML elements and XML text");
+        }
+        items.add(value);
+        return this;
+    }
+
+    /**
+     * Appends unformatted text to this text or XML text.
+     *
+     * @param chunk the text to append
+     * @return this prelim
+     * @throws IllegalArgumentException if chunk is null
+     * @throws UnsupportedOperationException if this prelim is not a text or XML text
+     */
+    public JniPrelim append(String chunk) {
+        return append(chunk, null);
+    }
+
+    /**
+     * Appends formatted text to this text or XML text.
+     *
+     * @param chunk the text to append
+     * @param attributes the formatting attributes, or null for none; checked against
+     *        the document's allowlist when the prelim is committed
+     * @return this prelim
+     * @throws IllegalArgumentException if chunk is null
+     * @throws UnsupportedOperationException if this prelim is not a text or XML text
+     */
+    public JniPrelim append(String chunk, Map<String, Object> attributes) {
+        requireKind("append", TEXT, XML_TEXT);
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
+        }
+        chunks.add(chunk);
+        formats.add(attributes);
+        return this;
+    }
+
+    /**
+     * Sets an attribute of this XML element or XML text.
+     *
+     * @param name the attribute name
+     * @param value a plain value
+     * @return this prelim
+     * @throws IllegalArgumentException if name is null
+     * @throws UnsupportedOperationException if this prelim is not an XML element or
+     *         XML text
+     */
+    public JniPrelim attribute(String name, Object value) {
+        requireKind("attribute", XML_ELEMENT, XML_TEXT);
+        if (name == null) {
+            throw new IllegalArgumentException("Attribute name cannot be null");
+        }
+        entries.put(name, value);
+        return this;
+    }
+
+    /**
+     * Returns whether this prelim can be committed to a root type.
+     *
+     * @return true for maps, arrays, texts and XML fragments
+     */
+    public boolean isRootKind() {
+        return kind == MAP || kind == ARRAY || kind == TEXT || kind == XML_FRAGMENT;
+    }
+
+    private static boolean isXmlChild(Object value) {
+        if (!(value instanceof JniPrelim)) {
+            return false;
+        }
+        int childKind = ((JniPrelim) value).kind;
+        return childKind == XML_ELEMENT || childKind == XML_TEXT;
+    }
+
+    private void requireKind(String operation, int... allowed) {
+        for (int k : allowed) {
+            if (kind == k) {
+                return;
+            }
+        }
+        throw new UnsupportedOperationException(
+            operation + " is not supported by " + kindName() + " prelims");
+    }
+
+    private String kindName() {
+        switch (kind) {
+            case MAP:
+                return "map";
+            case ARRAY:
+                return "array";
+            case TEXT:
+                return "text";
+            case XML_FRAGMENT:
+                return "XML fragment";
+            case XML_ELEMENT:
+                return "XML element";
+            default:
+                return "XML text";
+        }
+    }
+}
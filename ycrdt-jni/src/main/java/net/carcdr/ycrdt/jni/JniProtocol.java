@@ -0,0 +1,43 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.Collections;
+import java.util.LinkedHashMap;
+import java.util.Map;
+
+/**
+ * The integer tags exchanged with native code, as defined by the native library.
+ *
+ * <p>Kinds of operations, nodes and values cross the JNI boundary as plain ints:
+ * package constants such as {@link JniBatchOp} kinds, or ordinals of enums such as
+ * {@link JniTreeNode.Kind}. The native library exports its side of each tag here,
+ * named {@code Class.CONSTANT} after the Java declaration (nested classes as
+ * {@code Outer$Inner}), so tests can check that the two sides have not drifted.</p>
+ */
+final class JniProtocol {
+
+    static {
+        NativeLoader.loadLibrary();
+    }
+
+    private JniProtocol() {
+    }
+
+    /**
+     * Returns the tags defined by the native library.
+     *
+     * @return the tag values by name, in the order of the native table
+     */
+    static Map<String, Integer> constants() {
+        String[] names = nativeConstantNames();
+        int[] values = nativeConstantValues();
+        Map<String, Integer> constants = new LinkedHashMap<>();
+        for (int i = 0; i < names.length; i++) {
+            constants.put(names[i], values[i]);
+        }
+        return Collections.unmodifiableMap(constants);
+    }
+
+    private static native String[] nativeConstantNames();
+
+    private static native int[] nativeConstantValues();
+}
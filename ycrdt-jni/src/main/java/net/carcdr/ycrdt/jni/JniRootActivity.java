@@ -0,0 +1,61 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * The most recent recorded change to a root type of a document.
+ *
+ * <p>Changes are only recorded while root activity tracking is enabled; yrs keeps no
+ * timestamps, so earlier history cannot be attributed. A change counts against a root
+ * type when it modifies the type itself or any type nested inside it.</p>
+ *
+ * @see JniYDoc#setRootActivityTracking(boolean)
+ * @see JniYDoc#getRootActivity()
+ */
+public final class JniRootActivity {
+
+    private final String rootName;
+    private final long[] clientIds;
+    private final long modifiedAtMillis;
+
+    JniRootActivity(String rootName, long[] clientIds, long modifiedAtMillis) {
+        this.rootName = rootName;
+        this.clientIds = clientIds;
+        this.modifiedAtMillis = modifiedAtMillis;
+    }
+
+    /**
+     * Returns the name of the root type.
+     *
+     * @return the root name
+     */
+    public String getRootName() {
+        return rootName;
+    }
+
+    /**
+     * Returns the clients whose clocks the change advanced, in ascending order.
+     *
+     * <p>A local edit gives this document's client ID; an applied update gives the
+     * clients whose content it carried. The array is empty if the change only deleted
+     * content.</p>
+     *
+     * @return a copy of the client IDs
+     */
+    public long[] getLastModifiedBy() {
+        return clientIds.clone();
+    }
+
+    /**
+     * Returns when the change was committed on this document.
+     *
+     * @return milliseconds since the Unix epoch
+     */
+    public long getLastModifiedAt() {
+        return modifiedAtMillis;
+    }
+
+    @Override
+    public String toString() {
+        return "JniRootActivity{root=" + rootName + ", clients="
+            + java.util.Arrays.toString(clientIds) + ", at=" + modifiedAtMillis + '}';
+    }
+}
@@ -0,0 +1,122 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A rule about the shape of a document, checked by {@link JniYDoc#validate(java.util.List)}.
+ *
+ * <p>A server applying updates from untrusted clients can describe the roots it expects
+ * and check afterwards that a client has not written something else:</p>
+ * <pre>{@code
+ * List<JniSchemaRule> schema = List.of(
+ *     JniSchemaRule.root("settings", JniTreeNode.Kind.MAP),
+ *     JniSchemaRule.key("settings", "title", JniYMap.ValueType.STRING),
+ *     JniSchemaRule.optionalRoot("body", JniTreeNode.Kind.TEXT),
+ *     JniSchemaRule.noOtherRoots());
+ * doc.applyUpdate(update);
+ * List<JniSchemaViolation> violations = doc.validate(schema);
+ * }</pre>
+ *
+ * <p>A root created by an update has no kind until it is read as one, so it passes a
+ * kind rule if its content fits the kind: map entries for a map, and sequence content
+ * for an array, text or XML fragment. Rules only describe roots and the keys of root
+ * maps; nested content is not checked.</p>
+ */
+public final class JniSchemaRule {
+
+    static final int ROOT = 0;
+    static final int OPTIONAL_ROOT = 1;
+    static final int KEY = 2;
+    static final int NO_OTHER_ROOTS = 3;
+
+    final int kind;
+    final String root;
+    final String key;
+    final int type;
+
+    private JniSchemaRule(int kind, String root, String key, int type) {
+        this.kind = kind;
+        this.root = root;
+        this.key = key;
+        this.type = type;
+    }
+
+    /**
+     * Requires a root type of a kind.
+     *
+     * @param name the name of the root
+     * @param kind {@link JniTreeNode.Kind#MAP MAP}, {@link JniTreeNode.Kind#ARRAY ARRAY},
+     *        {@link JniTreeNode.Kind#TEXT TEXT} or
+     *        {@link JniTreeNode.Kind#XML_FRAGMENT XML_FRAGMENT}
+     * @return the rule
+     * @throws IllegalArgumentException if name or kind is null, or kind cannot be a root
+     */
+    public static JniSchemaRule root(String name, JniTreeNode.Kind kind) {
+        return new JniSchemaRule(ROOT, checkRoot(name), null, rootKind(kind));
+    }
+
+    /**
+     * Allows a root type, which must be of a kind if it exists.
+     *
+     * @param name the name of the root
+     * @param kind the kind, as for {@link #root(String, JniTreeNode.Kind)}
+     * @return the rule
+     * @throws IllegalArgumentException if name or kind is null, or kind cannot be a root
+     */
+    public static JniSchemaRule optionalRoot(String name, JniTreeNode.Kind kind) {
+        return new JniSchemaRule(OPTIONAL_ROOT, checkRoot(name), null, rootKind(kind));
+    }
+
+    /**
+     * Requires a key in a root map.
+     *
+     * <p>The rule is broken if the root is missing or not a map. It does not allow the
+     * root for {@link #noOtherRoots()}; add a root rule as well.</p>
+     *
+     * @param root the name of the root map
+     * @param key the key
+     * @param type the type the value must have, or null for any
+     * @return the rule
+     * @throws IllegalArgumentException if root or key is null, or type is
+     *         {@link JniYMap.ValueType#ABSENT ABSENT}
+     */
+    public static JniSchemaRule key(String root, String key, JniYMap.ValueType type) {
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        if (type == JniYMap.ValueType.ABSENT) {
+            throw new IllegalArgumentException("A required key cannot be ABSENT");
+        }
+        return new JniSchemaRule(KEY, checkRoot(root), key, type == null ? -1 : type.ordinal());
+    }
+
+    /**
+     * Rejects root types not named by a {@link #root root} or {@link #optionalRoot
+     * optionalRoot} rule.
+     *
+     * @return the rule
+     */
+    public static JniSchemaRule noOtherRoots() {
+        return new JniSchemaRule(NO_OTHER_ROOTS, null, null, -1);
+    }
+
+    private static String checkRoot(String name) {
+        if (name == null) {
+            throw new IllegalArgumentException("Root name cannot be null");
+        }
+        return name;
+    }
+
+    private static int rootKind(JniTreeNode.Kind kind) {
+        if (kind == null) {
+            throw new IllegalArgumentException("Kind cannot be null");
+        }
+        switch (kind) {
+            case MAP:
+            case ARRAY:
+            case TEXT:
+            case XML_FRAGMENT:
+                return kind.ordinal();
+            default:
+                throw new IllegalArgumentException(kind + " cannot be a root type");
+        }
+    }
+}
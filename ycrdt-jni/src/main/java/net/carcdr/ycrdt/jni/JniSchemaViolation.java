@@ -0,0 +1,39 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A {@link JniSchemaRule} a document breaks, as reported by
+ * {@link JniYDoc#validate(java.util.List)}.
+ */
+public final class JniSchemaViolation {
+
+    private final String path;
+    private final String message;
+
+    JniSchemaViolation(String path, String message) {
+        this.path = path;
+        this.message = message;
+    }
+
+    /**
+     * Returns where the rule is broken.
+     *
+     * @return the root name, or {@code root/key} for a key rule
+     */
+    public String getPath() {
+        return path;
+    }
+
+    /**
+     * Returns what is wrong, such as {@code expected STRING, found NUMBER}.
+     *
+     * @return the message
+     */
+    public String getMessage() {
+        return message;
+    }
+
+    @Override
+    public String toString() {
+        return path + ": " + message;
+    }
+}
@@ -0,0 +1,146 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.FormattingChunk;
+
+import java.util.Collections;
+import java.util.HashMap;
+import java.util.Map;
+import java.util.Objects;
+
+/**
+ * A formatted text chunk from a snapshot diff, with optional change attribution.
+ *
+ * <p>When text is diffed between two snapshots (see
+ * {@link JniYXmlText#diffSnapshots(byte[], byte[])}), content inserted or removed
+ * between them is reported with a {@link ChangeKind} and the ID (client ID and
+ * clock) of the item that introduced it. This is the information needed to
+ * render track changes.</p>
+ *
+ * @see JniYXmlText#diffSnapshots(byte[], byte[])
+ */
+public final class JniSnapshotChunk implements FormattingChunk {
+
+    /**
+     * The kind of change a chunk represents relative to the previous snapshot.
+     */
+    public enum ChangeKind {
+        /** The chunk was inserted after the previous snapshot. */
+        ADDED,
+        /** The chunk was removed after the previous snapshot. */
+        REMOVED
+    }
+
+    /** Change kind passed by native code for content that did not change. */
+    static final int UNCHANGED = -1;
+
+    private final String text;
+    private final Map<String, Object> attributes;
+    private final ChangeKind changeKind;
+    private final long changeClientId;
+    private final long changeClock;
+
+    /**
+     * Called from native code.
+     *
+     * @param text the text content of this chunk
+     * @param attributes the formatting attributes (may be null)
+     * @param kind ordinal of the change kind, or {@link #UNCHANGED}
+     * @param changeClientId the client ID of the item that introduced the change
+     * @param changeClock the clock of the item that introduced the change
+     */
+    JniSnapshotChunk(String text, Map<String, Object> attributes, int kind,
+                     long changeClientId, long changeClock) {
+        this.text = text;
+        this.attributes = attributes == null || attributes.isEmpty()
+                ? Collections.emptyMap()
+                : Collections.unmodifiableMap(new HashMap<>(attributes));
+        this.changeKind = kind == UNCHANGED ? null : ChangeKind.values()[kind];
+        this.changeClientId = changeClientId;
+        this.changeClock = changeClock;
+    }
+
+    @Override
+    public String getText() {
+        return text;
+    }
+
+    @Override
+    public Map<String, Object> getAttributes() {
+        return attributes;
+    }
+
+    @Override
+    public boolean hasAttributes() {
+        return !attributes.isEmpty();
+    }
+
+    /**
+     * Returns the kind of change, or null if the chunk is unchanged between the snapshots.
+     *
+     * @return the change kind, or null
+     */
+    public ChangeKind getChangeKind() {
+        return changeKind;
+    }
+
+    /**
+     * Checks if this chunk was added or removed between the snapshots.
+     *
+     * @return true if {@link #getChangeKind()} is not null
+     */
+    public boolean isChanged() {
+        return changeKind != null;
+    }
+
+    /**
+     * Returns the client ID of the item that introduced the change.
+     *
+     * @return the client ID, or 0 if the chunk is unchanged
+     */
+    public long getChangeClientId() {
+        return changeClientId;
+    }
+
+    /**
+     * Returns the clock of the item that introduced the change.
+     *
+     * @return the clock, or 0 if the chunk is unchanged
+     */
+    public long getChangeClock() {
+        return changeClock;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (o == null || getClass() != o.getClass()) {
+            return false;
+        }
+        JniSnapshotChunk that = (JniSnapshotChunk) o;
+        return changeClientId == that.changeClientId
+                && changeClock == that.changeClock
+                && text.equals(that.text)
+                && attributes.equals(that.attributes)
+                && changeKind == that.changeKind;
+    }
+
+    @Override
+    public int hashCode() {
+        return Objects.hash(text, attributes, changeKind, changeClientId, changeClock);
+    }
+
+    @Override
+    public String toString() {
+        StringBuilder sb = new StringBuilder("JniSnapshotChunk{text='").append(text).append('\'');
+        if (!attributes.isEmpty()) {
+            sb.append(", attributes=").append(attributes);
+        }
+        if (changeKind != null) {
+            sb.append(", change=").append(changeKind)
+              .append('@').append(changeClientId).append(':').append(changeClock);
+        }
+        return sb.append('}').toString();
+    }
+}
@@ -0,0 +1,145 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.Arrays;
+
+/**
+ * A position in a sequence that stays attached to the surrounding content as
+ * the document changes.
+ *
+ * <p>An absolute index shifts whenever content is inserted or deleted before it.
+ * A sticky index refers to the item next to the position instead, so it keeps
+ * pointing at the same place after local or remote edits. It is the Yjs
+ * equivalent of a relative position.</p>
+ *
+ * <p>Instances are immutable values holding the v1 binary encoding of the
+ * index. They do not reference native memory and can be stored or sent to other
+ * peers with {@link #encode()} and {@link #decode(byte[])}. The encoding is the
+ * one written by {@code Y.encodeRelativePosition}, and everything
+ * {@code Y.decodeRelativePosition} reads is accepted, including positions
+ * without an association written by Yjs releases before 13.5.</p>
+ *
+ * @see JniYText#stickyIndex(int, Assoc)
+ */
+public final class JniStickyIndex {
+
+    /**
+     * Which side of the position a sticky index is attached to.
+     */
+    public enum Assoc {
+        /** Attached to the item after the position; inserts at the position land before it. */
+        AFTER(0),
+        /** Attached to the item before the position; inserts at the position land after it. */
+        BEFORE(-1);
+
+        private final int nativeValue;
+
+        Assoc(int nativeValue) {
+            this.nativeValue = nativeValue;
+        }
+
+        int getNativeValue() {
+            return nativeValue;
+        }
+    }
+
+    private final byte[] encoded;
+
+    private JniStickyIndex(byte[] encoded) {
+        this.encoded = encoded;
+    }
+
+    /**
+     * Wraps an encoded sticky index.
+     *
+     * <p>The bytes are validated when the index is used, not here.</p>
+     *
+     * @param encoded the v1 encoding produced by {@link #encode()} or by Yjs
+     * @return the sticky index
+     * @throws IllegalArgumentException if encoded is null or empty
+     */
+    public static JniStickyIndex decode(byte[] encoded) {
+        if (encoded == null || encoded.length == 0) {
+            throw new IllegalArgumentException("Encoded sticky index cannot be null or empty");
+        }
+        return new JniStickyIndex(encoded.clone());
+    }
+
+    /**
+     * Reads a sticky index from the JSON form used by Yjs.
+     *
+     * @param json the output of {@code Y.relativePositionToJSON} or {@link #toJson()}
+     * @return the sticky index
+     * @throws IllegalArgumentException if json is null or not a valid position
+     */
+    public static JniStickyIndex fromJson(String json) {
+        if (json == null) {
+            throw new IllegalArgumentException("JSON cannot be null");
+        }
+        return new JniStickyIndex(nativeFromJson(json));
+    }
+
+    /**
+     * Returns the JSON form used by Yjs, as produced by {@code Y.relativePositionToJSON}.
+     *
+     * <p>Yjs clients read it with {@code Y.createRelativePositionFromJSON}.</p>
+     *
+     * @return the JSON string
+     * @throws IllegalArgumentException if the encoded bytes are malformed
+     */
+    public String toJson() {
+        return nativeToJson(encoded);
+    }
+
+    /**
+     * Returns the v1 binary encoding of this sticky index.
+     *
+     * @return a copy of the encoded bytes
+     */
+    public byte[] encode() {
+        return encoded.clone();
+    }
+
+    /**
+     * Returns the encoded bytes without copying, for passing to native code.
+     *
+     * @return the encoded bytes
+     */
+    byte[] getEncoded() {
+        return encoded;
+    }
+
+    /**
+     * Wraps bytes produced by native code without copying.
+     *
+     * @param encoded the encoded bytes
+     * @return the sticky index
+     */
+    static JniStickyIndex fromNative(byte[] encoded) {
+        return new JniStickyIndex(encoded);
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (o == null || getClass() != o.getClass()) {
+            return false;
+        }
+        return Arrays.equals(encoded, ((JniStickyIndex) o).encoded);
+    }
+
+    @Override
+    public int hashCode() {
+        return Arrays.hashCode(encoded);
+    }
+
+    @Override
+    public String toString() {
+        return "JniStickyIndex" + Arrays.toString(encoded);
+    }
+
+    private static native String nativeToJson(byte[] encoded);
+
+    private static native byte[] nativeFromJson(String json);
+}
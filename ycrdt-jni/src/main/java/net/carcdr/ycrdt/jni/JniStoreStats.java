@@ -0,0 +1,237 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.ArrayList;
+import java.util.Collections;
+import java.util.List;
+
+/**
+ * Block counts of a document's store.
+ *
+ * <p>Every insertion is kept as a block, and deleted blocks stay in the store as
+ * tombstones until garbage collection discards their content. A document whose
+ * block count grows much faster than its visible content is accumulating garbage,
+ * for example because it runs without garbage collection or receives many tiny
+ * edits. Collecting the counts costs about as much as encoding the document, so
+ * they are meant to be sampled, not read on every change.</p>
+ *
+ * @see JniYDoc#getStoreStats()
+ */
+public final class JniStoreStats {
+
+    private static final int HEADER = 5;
+    private static final int PER_CLIENT = 5;
+
+    private final long deletedLength;
+    private final long pendingStructCount;
+    private final long pendingByteSize;
+    private final long pendingDeletedLength;
+    private final long stagedUpdateCount;
+    private final List<Client> clients;
+
+    /**
+     * Creates stats from the array returned by native code.
+     *
+     * @param values {@code [deletedLength, pendingStructs, pendingBytes,
+     *        pendingDeletedLength, stagedUpdates]} followed by
+     *        {@code [client, clock, blocks, deletedBlocks, gcBlocks]} per client
+     */
+    JniStoreStats(long[] values) {
+        this.deletedLength = values[0];
+        this.pendingStructCount = values[1];
+        this.pendingByteSize = values[2];
+        this.pendingDeletedLength = values[3];
+        this.stagedUpdateCount = values[4];
+        List<Client> list = new ArrayList<>();
+        for (int i = HEADER; i + PER_CLIENT <= values.length; i += PER_CLIENT) {
+            list.add(new Client(values[i], values[i + 1], values[i + 2], values[i + 3],
+                values[i + 4]));
+        }
+        this.clients = Collections.unmodifiableList(list);
+    }
+
+    /**
+     * Returns the block counts of each client that wrote to the document.
+     *
+     * @return the clients, ordered by unsigned client ID
+     */
+    public List<Client> getClients() {
+        return clients;
+    }
+
+    /**
+     * Returns the number of blocks in the store, including tombstones and
+     * garbage-collected blocks.
+     *
+     * @return the block count
+     */
+    public long getBlockCount() {
+        long total = 0;
+        for (Client client : clients) {
+            total += client.getBlockCount();
+        }
+        return total;
+    }
+
+    /**
+     * Returns the number of deleted blocks that still hold their content.
+     *
+     * @return the tombstone count
+     */
+    public long getDeletedBlockCount() {
+        long total = 0;
+        for (Client client : clients) {
+            total += client.getDeletedBlockCount();
+        }
+        return total;
+    }
+
+    /**
+     * Returns the number of blocks whose content was garbage collected.
+     *
+     * @return the GC block count
+     */
+    public long getGcBlockCount() {
+        long total = 0;
+        for (Client client : clients) {
+            total += client.getGcBlockCount();
+        }
+        return total;
+    }
+
+    /**
+     * Returns the number of clock units the document has deleted.
+     *
+     * @return the deleted length
+     */
+    public long getDeletedLength() {
+        return deletedLength;
+    }
+
+    /**
+     * Returns the number of structs waiting for missing updates.
+     *
+     * @return the pending struct count
+     * @see JniYDoc#getPendingState()
+     */
+    public long getPendingStructCount() {
+        return pendingStructCount;
+    }
+
+    /**
+     * Returns the size of the pending structs in v1 update encoding.
+     *
+     * @return the size in bytes
+     */
+    public long getPendingByteSize() {
+        return pendingByteSize;
+    }
+
+    /**
+     * Returns the number of clock units whose deletion waits for missing updates.
+     *
+     * @return the pending deleted length
+     */
+    public long getPendingDeletedLength() {
+        return pendingDeletedLength;
+    }
+
+    /**
+     * Returns the number of updates held in the staging area by
+     * {@link JniYDoc#queueUpdate(byte[])}.
+     *
+     * @return the staged update count
+     */
+    public long getStagedUpdateCount() {
+        return stagedUpdateCount;
+    }
+
+    @Override
+    public String toString() {
+        return "JniStoreStats{clients=" + clients.size()
+                + ", blocks=" + getBlockCount()
+                + ", deletedBlocks=" + getDeletedBlockCount()
+                + ", gcBlocks=" + getGcBlockCount()
+                + ", deletedLength=" + deletedLength
+                + ", pendingStructs=" + pendingStructCount
+                + ", stagedUpdates=" + stagedUpdateCount + '}';
+    }
+
+    /**
+     * Block counts of one client.
+     */
+    public static final class Client {
+
+        private final long clientId;
+        private final long clock;
+        private final long blockCount;
+        private final long deletedBlockCount;
+        private final long gcBlockCount;
+
+        Client(long clientId, long clock, long blockCount, long deletedBlockCount,
+               long gcBlockCount) {
+            this.clientId = clientId;
+            this.clock = clock;
+            this.blockCount = blockCount;
+            this.deletedBlockCount = deletedBlockCount;
+            this.gcBlockCount = gcBlockCount;
+        }
+
+        /**
+         * Returns the client ID.
+         *
+         * @return the client ID
+         */
+        public long getClientId() {
+            return clientId;
+        }
+
+        /**
+         * Returns the next clock of the client, which is the number of clock units it
+         * has written.
+         *
+         * @return the clock
+         */
+        public long getClock() {
+            return clock;
+        }
+
+        /**
+         * Returns the number of blocks the client wrote that are still in the store,
+         * including tombstones and garbage-collected blocks.
+         *
+         * @return the block count
+         */
+        public long getBlockCount() {
+            return blockCount;
+        }
+
+        /**
+         * Returns the number of deleted blocks of the client that still hold their
+         * content.
+         *
+         * @return the tombstone count
+         */
+        public long getDeletedBlockCount() {
+            return deletedBlockCount;
+        }
+
+        /**
+         * Returns the number of blocks of the client whose content was garbage
+         * collected.
+         *
+         * @return the GC block count
+         */
+        public long getGcBlockCount() {
+            return gcBlockCount;
+        }
+
+        @Override
+        public String toString() {
+            return "JniStoreStats.Client{clientId=" + Long.toUnsignedString(clientId)
+                    + ", clock=" + clock
+                    + ", blocks=" + blockCount
+                    + ", deletedBlocks=" + deletedBlockCount
+                    + ", gcBlocks=" + gcBlockCount + '}';
+        }
+    }
+}
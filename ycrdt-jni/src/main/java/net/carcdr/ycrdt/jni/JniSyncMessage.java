@@ -0,0 +1,115 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A y-websocket protocol message handled by {@link JniYDoc#applySyncMessage(byte[])}.
+ *
+ * <p>Sync messages are handled by the document: step 1 produces a reply to send
+ * back to the peer, and step 2 and update messages are applied. Awareness, auth
+ * and custom messages do not touch the document; their content is returned here
+ * for the caller to handle.</p>
+ */
+public final class JniSyncMessage {
+
+    /**
+     * The type of a handled message.
+     */
+    public enum Kind {
+        /** Sync step 1: the peer's state vector; answered with a reply. */
+        SYNC_STEP_1,
+        /** Sync step 2: the changes the peer was missing; applied. */
+        SYNC_STEP_2,
+        /** Sync update: an incremental update; applied. */
+        UPDATE,
+        /** Awareness update; see {@link #getPayload()}. */
+        AWARENESS,
+        /** Request for the current awareness state. */
+        AWARENESS_QUERY,
+        /** Authentication result; see {@link #getDeniedReason()}. */
+        AUTH,
+        /** Message of a type this library does not know; see {@link #getCustomType()}. */
+        CUSTOM
+    }
+
+    private final Kind kind;
+    private final byte[] reply;
+    private final byte[] payload;
+    private final int customType;
+    private final String deniedReason;
+
+    /**
+     * Called from native code.
+     *
+     * @param kind ordinal of the message kind
+     * @param reply framed message to send back, or null
+     * @param payload awareness update or custom payload, or null
+     * @param customType message type of a custom message
+     * @param deniedReason reason given by an auth message that denied permission, or null
+     */
+    JniSyncMessage(int kind, byte[] reply, byte[] payload, int customType, String deniedReason) {
+        this.kind = Kind.values()[kind];
+        this.reply = reply;
+        this.payload = payload;
+        this.customType = customType;
+        this.deniedReason = deniedReason;
+    }
+
+    /**
+     * Returns the type of the message.
+     *
+     * @return the message kind
+     */
+    public Kind getKind() {
+        return kind;
+    }
+
+    /**
+     * Checks if a reply must be sent back to the peer.
+     *
+     * @return true for sync step 1 messages
+     */
+    public boolean hasReply() {
+        return reply != null;
+    }
+
+    /**
+     * Returns the framed message to send back to the peer.
+     *
+     * @return a copy of the reply, or null if there is none
+     */
+    public byte[] getReply() {
+        return reply != null ? reply.clone() : null;
+    }
+
+    /**
+     * Returns the encoded awareness update of an {@link Kind#AWARENESS} message, or
+     * the payload of a {@link Kind#CUSTOM} message.
+     *
+     * @return a copy of the payload, or null for other kinds
+     */
+    public byte[] getPayload() {
+        return payload != null ? payload.clone() : null;
+    }
+
+    /**
+     * Returns the message type of a {@link Kind#CUSTOM} message.
+     *
+     * @return the message type, or 0 for other kinds
+     */
+    public int getCustomType() {
+        return customType;
+    }
+
+    /**
+     * Returns the reason given by an {@link Kind#AUTH} message that denied permission.
+     *
+     * @return the reason, or null if permission was granted or this is another kind
+     */
+    public String getDeniedReason() {
+        return deniedReason;
+    }
+
+    @Override
+    public String toString() {
+        return "JniSyncMessage{kind=" + kind + ", hasReply=" + hasReply() + "}";
+    }
+}
@@ -0,0 +1,79 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A fixed-size block of a text, for rendering only the visible part of a large text.
+ *
+ * <p>Returned by {@link JniYText#blocks(YTransaction, int, int, int)}. The text is
+ * split into consecutive blocks of the requested size, measured in the same units
+ * as the text's indexes. A block is longer only when the size would split a
+ * character.</p>
+ *
+ * <p>The anchor is a sticky index attached to the first character of the block.
+ * After a change, resolving the anchors with
+ * {@link JniYText#resolveStickyIndex(JniStickyIndex)} shows where each cached
+ * block moved, so only blocks that contain the changed range need to be fetched
+ * again.</p>
+ */
+public final class JniTextBlock {
+
+    private final int index;
+    private final int length;
+    private final String text;
+    private final JniStickyIndex anchor;
+
+    /**
+     * Called from native code.
+     *
+     * @param index the index of the first character of the block
+     * @param length the length of the block
+     * @param text the content of the block
+     * @param anchor the v1-encoded sticky index of the first character
+     */
+    JniTextBlock(int index, int length, String text, byte[] anchor) {
+        this.index = index;
+        this.length = length;
+        this.text = text;
+        this.anchor = JniStickyIndex.fromNative(anchor);
+    }
+
+    /**
+     * Returns the index of the first character of the block when it was read.
+     *
+     * @return the start index
+     */
+    public int getIndex() {
+        return index;
+    }
+
+    /**
+     * Returns the length of the block.
+     *
+     * @return the length, in the text's index units
+     */
+    public int getLength() {
+        return length;
+    }
+
+    /**
+     * Returns the content of the block.
+     *
+     * @return the text of the block
+     */
+    public String getText() {
+        return text;
+    }
+
+    /**
+     * Returns the sticky index attached to the first character of the block.
+     *
+     * @return the anchor of the block
+     */
+    public JniStickyIndex getAnchor() {
+        return anchor;
+    }
+
+    @Override
+    public String toString() {
+        return "JniTextBlock{index=" + index + ", length=" + length + ", text='" + text + "'}";
+    }
+}
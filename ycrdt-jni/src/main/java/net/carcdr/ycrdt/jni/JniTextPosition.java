@@ -0,0 +1,65 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * A line and column in a text.
+ *
+ * <p>Returned by {@link JniYText#positionOf(int)}. Lines end after each {@code \n}
+ * and are numbered from 0. Columns are measured in the same units as the text's
+ * indexes.</p>
+ */
+public final class JniTextPosition {
+
+    private final int line;
+    private final int column;
+
+    /**
+     * Creates a position.
+     *
+     * @param line the 0-based line
+     * @param column the 0-based column
+     */
+    public JniTextPosition(int line, int column) {
+        this.line = line;
+        this.column = column;
+    }
+
+    /**
+     * Returns the line.
+     *
+     * @return the 0-based line
+     */
+    public int getLine() {
+        return line;
+    }
+
+    /**
+     * Returns the column.
+     *
+     * @return the 0-based column
+     */
+    public int getColumn() {
+        return column;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (!(o instanceof JniTextPosition)) {
+            return false;
+        }
+        JniTextPosition other = (JniTextPosition) o;
+        return line == other.line && column == other.column;
+    }
+
+    @Override
+    public int hashCode() {
+        return 31 * line + column;
+    }
+
+    @Override
+    public String toString() {
+        return line + ":" + column;
+    }
+}
@@ -0,0 +1,96 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Counts describing the content of a text, as shown in an editor's status bar.
+ *
+ * <p>Returned by {@link JniYText#stats()}, which computes every count natively in
+ * one pass instead of copying the text to Java. Words are maximal runs of
+ * non-whitespace characters, and lines are counted like
+ * {@link JniYText#lineCount()}.</p>
+ */
+public final class JniTextStats {
+
+    private final long length;
+    private final long codePoints;
+    private final long words;
+    private final long lines;
+
+    /**
+     * Creates text statistics.
+     *
+     * @param length the length in the document's offset units
+     * @param codePoints the number of Unicode code points
+     * @param words the number of words
+     * @param lines the number of lines
+     */
+    public JniTextStats(long length, long codePoints, long words, long lines) {
+        this.length = length;
+        this.codePoints = codePoints;
+        this.words = words;
+        this.lines = lines;
+    }
+
+    /**
+     * Returns the length of the text in the same units as its indexes, which
+     * depend on the document's offset kind.
+     *
+     * @return the length
+     */
+    public long getLength() {
+        return length;
+    }
+
+    /**
+     * Returns the number of Unicode code points, regardless of the offset kind.
+     *
+     * @return the number of code points
+     */
+    public long getCodePoints() {
+        return codePoints;
+    }
+
+    /**
+     * Returns the number of words.
+     *
+     * @return the number of maximal runs of non-whitespace characters
+     */
+    public long getWords() {
+        return words;
+    }
+
+    /**
+     * Returns the number of lines.
+     *
+     * @return the number of line breaks plus one
+     */
+    public long getLines() {
+        return lines;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (!(o instanceof JniTextStats)) {
+            return false;
+        }
+        JniTextStats other = (JniTextStats) o;
+        return length == other.length && codePoints == other.codePoints
+            && words == other.words && lines == other.lines;
+    }
+
+    @Override
+    public int hashCode() {
+        int result = Long.hashCode(length);
+        result = 31 * result + Long.hashCode(codePoints);
+        result = 31 * result + Long.hashCode(words);
+        return 31 * result + Long.hashCode(lines);
+    }
+
+    @Override
+    public String toString() {
+        return "JniTextStats{length=" + length + ", codePoints=" + codePoints
+            + ", words=" + words + ", lines=" + lines + '}';
+    }
+}
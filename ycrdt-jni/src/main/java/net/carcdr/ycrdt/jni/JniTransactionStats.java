@@ -0,0 +1,159 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Measurements of a single committed transaction.
+ *
+ * <p>Instances are produced when transaction statistics are enabled on a
+ * document (see {@link JniYDoc#setTransactionStatsEnabled(boolean)}) and are
+ * passed to a {@link SlowTransactionListener}. All values are measured by the
+ * native library.</p>
+ */
+public final class JniTransactionStats {
+
+    private final long durationNanos;
+    private final long operationCount;
+    private final long updateBytes;
+    private final String label;
+
+    /**
+     * Creates stats from the array returned by the native commit.
+     *
+     * @param values {@code [durationNanos, operationCount, updateBytes]}
+     * @param label the label of the transaction, or null
+     */
+    JniTransactionStats(long[] values, String label) {
+        this.durationNanos = values[0];
+        this.operationCount = values[1];
+        this.updateBytes = values[2];
+        this.label = label;
+    }
+
+    /**
+     * Returns the time from opening the transaction until its commit finished,
+     * including observer callbacks run by the commit.
+     *
+     * @return the duration in nanoseconds
+     */
+    public long getDurationNanos() {
+        return durationNanos;
+    }
+
+    /**
+     * Returns the number of operations in the transaction, counted as the
+     * number of inserted items (characters, array elements, map entries)
+     * plus the number of deleted ones.
+     *
+     * @return the operation count
+     */
+    public long getOperationCount() {
+        return operationCount;
+    }
+
+    /**
+     * Returns the size of the v1 update produced by the transaction.
+     *
+     * @return the update size in bytes
+     */
+    public long getUpdateBytes() {
+        return updateBytes;
+    }
+
+    /**
+     * Returns the label the transaction had when it was committed.
+     *
+     * @return the label, or null if the transaction had none
+     * @see JniYTransaction#setLabel(String)
+     */
+    public String getLabel() {
+        return label;
+    }
+
+    @Override
+    public String toString() {
+        return "JniTransactionStats{durationNanos=" + durationNanos
+                + ", operationCount=" + operationCount
+                + ", updateBytes=" + updateBytes
+                + (label != null ? ", label=" + label : "") + '}';
+    }
+
+    /**
+     * Running totals of all transactions measured on a document.
+     *
+     * @see JniYDoc#getTransactionStatsSummary()
+     */
+    public static final class Summary {
+
+        private final long transactionCount;
+        private final long totalDurationNanos;
+        private final long maxDurationNanos;
+        private final long totalOperationCount;
+        private final long totalUpdateBytes;
+
+        /**
+         * Creates a summary from the array returned by native code.
+         *
+         * @param values {@code [count, totalDurationNanos, maxDurationNanos, ops, updateBytes]}
+         */
+        Summary(long[] values) {
+            this.transactionCount = values[0];
+            this.totalDurationNanos = values[1];
+            this.maxDurationNanos = values[2];
+            this.totalOperationCount = values[3];
+            this.totalUpdateBytes = values[4];
+        }
+
+        /**
+         * Returns the number of measured transactions.
+         *
+         * @return the transaction count
+         */
+        public long getTransactionCount() {
+            return transactionCount;
+        }
+
+        /**
+         * Returns the summed duration of all measured transactions.
+         *
+         * @return the total duration in nanoseconds
+         */
+        public long getTotalDurationNanos() {
+            return totalDurationNanos;
+        }
+
+        /**
+         * Returns the duration of the slowest measured transaction.
+         *
+         * @return the maximum duration in nanoseconds
+         */
+        public long getMaxDurationNanos() {
+            return maxDurationNanos;
+        }
+
+        /**
+         * Returns the summed operation count of all measured transactions.
+         *
+         * @return the total operation count
+         */
+        public long getTotalOperationCount() {
+            return totalOperationCount;
+        }
+
+        /**
+         * Returns the summed update size of all measured transactions.
+         *
+         * @return the total update size in bytes
+         */
+        public long getTotalUpdateBytes() {
+            return totalUpdateBytes;
+        }
+
+        @Override
+        public String toString() {
+            return "JniTransactionStats.Summary{transactionCount=" + transactionCount
+                    + ", totalDurationNanos=" + totalDurationNanos
+                    + ", maxDurationNanos=" + maxDurationNanos
+                    + ", totalOperationCount=" + totalOperationCount
+                    + ", totalUpdateBytes=" + totalUpdateBytes + '}';
+        }
+    }
+}
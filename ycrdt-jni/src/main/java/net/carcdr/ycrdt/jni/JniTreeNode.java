@@ -0,0 +1,175 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.Collections;
+import java.util.List;
+import java.util.Map;
+
+/**
+ * A shared type exported by {@link JniYDoc#exportTree(int)}.
+ *
+ * <p>Which fields are set depends on the {@link Kind}:</p>
+ * <ul>
+ *   <li>{@link Kind#MAP}: {@link #getEntries()}</li>
+ *   <li>{@link Kind#ARRAY} and {@link Kind#XML_FRAGMENT}: {@link #getItems()}</li>
+ *   <li>{@link Kind#TEXT}: {@link #getText()}</li>
+ *   <li>{@link Kind#XML_ELEMENT}: {@link #getName()} (the tag),
+ *       {@link #getAttributes()} and {@link #getItems()}</li>
+ *   <li>{@link Kind#XML_TEXT}: {@link #getText()} and {@link #getAttributes()}</li>
+ *   <li>{@link Kind#DOC}: {@link #getName()} (the GUID)</li>
+ *   <li>{@link Kind#UNKNOWN}: {@link #getEntries()} and {@link #getItems()}</li>
+ *   <li>{@link Kind#LINK}: no content; the link is not followed</li>
+ * </ul>
+ *
+ * <p>Entries and items hold either nested nodes or plain values. A node below
+ * the requested depth is {@linkplain #isTruncated() truncated}: it keeps its
+ * kind and name, and all content is empty or null.</p>
+ */
+public final class JniTreeNode {
+
+    /**
+     * The kind of shared type a node represents.
+     */
+    public enum Kind {
+        /** A YMap. */
+        MAP,
+        /** A YArray. */
+        ARRAY,
+        /** A YText. */
+        TEXT,
+        /** A YXmlElement. */
+        XML_ELEMENT,
+        /** A YXmlFragment. */
+        XML_FRAGMENT,
+        /** A YXmlText. */
+        XML_TEXT,
+        /** A subdocument. */
+        DOC,
+        /** A root type that has not been accessed with a concrete type yet. */
+        UNKNOWN,
+        /** A weak link, read with {@link JniYMap#getLinked(String)}. */
+        LINK
+    }
+
+    private final Kind kind;
+    private final String name;
+    private final String text;
+    private final Map<String, Object> attributes;
+    private final Map<String, Object> entries;
+    private final List<Object> items;
+    private final boolean truncated;
+
+    /**
+     * Called from native code.
+     *
+     * @param kind the ordinal of the node's {@link Kind}
+     * @param name the tag or GUID (may be null)
+     * @param text the text content (may be null)
+     * @param attributes the XML attributes (may be null)
+     * @param entries the map entries (may be null)
+     * @param items the sequence items (may be null)
+     * @param truncated whether the content was omitted because of the depth limit
+     */
+    JniTreeNode(int kind, String name, String text, Map<String, Object> attributes,
+                Map<String, Object> entries, List<Object> items, boolean truncated) {
+        this.kind = Kind.values()[kind];
+        this.name = name;
+        this.text = text;
+        this.attributes = attributes == null
+                ? Collections.emptyMap()
+                : Collections.unmodifiableMap(attributes);
+        this.entries = entries == null
+                ? Collections.emptyMap()
+                : Collections.unmodifiableMap(entries);
+        this.items = items == null
+                ? Collections.emptyList()
+                : Collections.unmodifiableList(items);
+        this.truncated = truncated;
+    }
+
+    /**
+     * Returns the kind of shared type.
+     *
+     * @return the kind
+     */
+    public Kind getKind() {
+        return kind;
+    }
+
+    /**
+     * Returns the element tag for XML elements or the GUID for subdocuments.
+     *
+     * @return the name, or null for other kinds
+     */
+    public String getName() {
+        return name;
+    }
+
+    /**
+     * Returns the text content of a text or XML text node.
+     *
+     * @return the text, or null for other kinds or if truncated
+     */
+    public String getText() {
+        return text;
+    }
+
+    /**
+     * Returns the attributes of an XML element or XML text node, in key order.
+     *
+     * @return an unmodifiable map, empty for other kinds or if truncated
+     */
+    public Map<String, Object> getAttributes() {
+        return attributes;
+    }
+
+    /**
+     * Returns the entries of a map node, in key order.
+     *
+     * @return an unmodifiable map, empty for other kinds or if truncated
+     */
+    public Map<String, Object> getEntries() {
+        return entries;
+    }
+
+    /**
+     * Returns the items of an array node or the children of an XML node.
+     *
+     * @return an unmodifiable list, empty for other kinds or if truncated
+     */
+    public List<Object> getItems() {
+        return items;
+    }
+
+    /**
+     * Checks if the content of this node was omitted because of the depth limit.
+     *
+     * @return true if the node is truncated
+     */
+    public boolean isTruncated() {
+        return truncated;
+    }
+
+    @Override
+    public String toString() {
+        StringBuilder sb = new StringBuilder("JniTreeNode{").append(kind);
+        if (name != null) {
+            sb.append(", name=").append(name);
+        }
+        if (truncated) {
+            return sb.append(", truncated}").toString();
+        }
+        if (text != null) {
+            sb.append(", text='").append(text).append('\'');
+        }
+        if (!attributes.isEmpty()) {
+            sb.append(", attributes=").append(attributes);
+        }
+        if (!entries.isEmpty()) {
+            sb.append(", entries=").append(entries);
+        }
+        if (!items.isEmpty()) {
+            sb.append(", items=").append(items);
+        }
+        return sb.append('}').toString();
+    }
+}
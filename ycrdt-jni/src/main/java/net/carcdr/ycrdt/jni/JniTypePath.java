@@ -0,0 +1,128 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.Collections;
+import java.util.List;
+
+/**
+ * Where a shared type lives in its document, returned by {@code getPath()} on
+ * {@link JniYText}, {@link JniYArray}, {@link JniYMap}, {@link JniYXmlElement}
+ * and {@link JniYXmlText}, and by {@link JniYDoc#getEmbeddedPath()} for a
+ * subdocument.
+ *
+ * <p>A path is the name of the root the type belongs to, followed by one
+ * segment per level leading down to the type: a {@code String} key for an
+ * entry of a map, or an {@code Integer} index for an item of an array or a
+ * child of an XML node. A root type has no segments. Indexes describe the
+ * document at the time the path was taken and shift as items are inserted or
+ * removed before them.</p>
+ */
+public final class JniTypePath {
+
+    private final String rootName;
+    private final List<Object> segments;
+
+    private JniTypePath(String rootName, List<Object> segments) {
+        this.rootName = rootName;
+        this.segments = segments;
+    }
+
+    /**
+     * Builds a path from the array returned by native code.
+     *
+     * @param path the root name followed by the segments, or null
+     * @return the path, or null if {@code path} is null
+     */
+    static JniTypePath fromNative(Object[] path) {
+        if (path == null) {
+            return null;
+        }
+        List<Object> segments = new ArrayList<>(Arrays.asList(path).subList(1, path.length));
+        return new JniTypePath((String) path[0], Collections.unmodifiableList(segments));
+    }
+
+    /**
+     * Returns the name of the root the type belongs to.
+     *
+     * @return the root name
+     */
+    public String getRootName() {
+        return rootName;
+    }
+
+    /**
+     * Returns the segments leading from the root to the type.
+     *
+     * @return an unmodifiable list of {@code String} keys and {@code Integer} indexes
+     */
+    public List<Object> getSegments() {
+        return segments;
+    }
+
+    /**
+     * Returns how deeply the type is nested below its root.
+     *
+     * @return the number of segments, 0 for a root type
+     */
+    public int getDepth() {
+        return segments.size();
+    }
+
+    /**
+     * Returns whether the type is a root type.
+     *
+     * @return true if the path has no segments
+     */
+    public boolean isRoot() {
+        return segments.isEmpty();
+    }
+
+    /**
+     * Returns the path of the type containing this one.
+     *
+     * @return the parent's path, or null for a root type
+     */
+    public JniTypePath getParent() {
+        if (segments.isEmpty()) {
+            return null;
+        }
+        return new JniTypePath(rootName, segments.subList(0, segments.size() - 1));
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) {
+            return true;
+        }
+        if (!(o instanceof JniTypePath)) {
+            return false;
+        }
+        JniTypePath other = (JniTypePath) o;
+        return rootName.equals(other.rootName) && segments.equals(other.segments);
+    }
+
+    @Override
+    public int hashCode() {
+        return 31 * rootName.hashCode() + segments.hashCode();
+    }
+
+    /**
+     * Formats the path as the root name followed by {@code .key} and
+     * {@code [index]} segments, for example {@code doc.list[1].text}.
+     *
+     * @return the formatted path
+     */
+    @Override
+    public String toString() {
+        StringBuilder sb = new StringBuilder(rootName);
+        for (Object segment : segments) {
+            if (segment instanceof Integer) {
+                sb.append('[').append(segment).append(']');
+            } else {
+                sb.append('.').append(segment);
+            }
+        }
+        return sb.toString();
+    }
+}
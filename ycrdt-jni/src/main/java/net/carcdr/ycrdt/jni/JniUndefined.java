@@ -0,0 +1,24 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * The JavaScript {@code undefined} value.
+ *
+ * <p>Yjs keeps {@code undefined} and {@code null} apart, and documents written by
+ * JavaScript clients can contain either. Values read from a document return
+ * {@link #UNDEFINED} for {@code undefined} and Java {@code null} for
+ * {@code null}. Writing {@link #UNDEFINED} stores {@code undefined}, so such
+ * documents keep their values when edited from Java.</p>
+ */
+public final class JniUndefined {
+
+    /** The single {@code undefined} value. */
+    public static final JniUndefined UNDEFINED = new JniUndefined();
+
+    private JniUndefined() {
+    }
+
+    @Override
+    public String toString() {
+        return "undefined";
+    }
+}
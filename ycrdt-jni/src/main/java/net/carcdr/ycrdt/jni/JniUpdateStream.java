@@ -0,0 +1,236 @@
+package net.carcdr.ycrdt.jni;
+
+import java.io.OutputStream;
+
+import net.carcdr.ycrdt.YTransaction;
+
+/**
+ * Applies a v1 update to a document as its bytes arrive.
+ *
+ * <p>{@link JniYDoc#applyUpdate(byte[])} needs the whole update in memory, and
+ * decodes all of it before applying any. For a large initial sync that means
+ * holding the encoded update, its decoded form and the document at once. A stream
+ * applies the structs of the update as soon as their bytes have been written, and
+ * keeps only the bytes it has not applied yet. The deletions recorded at the end
+ * of the update are applied by {@link #finish()}:</p>
+ * <pre>{@code
+ * try (JniUpdateStream stream = new JniUpdateStream(doc)) {
+ *     in.transferTo(stream);
+ *     stream.finish();
+ * }
+ * }</pre>
+ *
+ * <p>Each write applies what it completes in the calling thread's transaction, or
+ * in a transaction of its own if there is none, so observers see the update in
+ * parts. Content that depends on parts not written yet waits in the document's
+ * pending state and is integrated once they arrive. Closing a stream that was not
+ * finished discards the bytes not applied yet but keeps what was applied.</p>
+ *
+ * <p>Write errors are reported as unchecked exceptions, like
+ * {@link JniYDoc#applyUpdate(byte[])}; an update that cannot be decoded leaves the
+ * parts applied before the error in the document.</p>
+ */
+public class JniUpdateStream extends OutputStream {
+
+    private final JniYDoc doc;
+    private long nativePtr;
+
+    /**
+     * Creates a stream that applies an update to a document.
+     *
+     * @param doc the document to apply the update to
+     * @throws IllegalArgumentException if doc is null
+     * @throws IllegalStateException if the document is closed
+     */
+    public JniUpdateStream(JniYDoc doc) {
+        if (doc == null) {
+            throw new IllegalArgumentException("Document cannot be null");
+        }
+        if (doc.isClosed()) {
+            throw new IllegalStateException("JniYDoc has been closed");
+        }
+        this.doc = doc;
+        this.nativePtr = nativeCreate();
+        if (nativePtr == 0) {
+            throw new RuntimeException("Failed to create update stream");
+        }
+    }
+
+    /**
+     * Writes one byte of the update.
+     *
+     * @param b the byte to write; only its low eight bits are used
+     * @throws IllegalStateException if the stream or the document has been closed
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    @Override
+    public void write(int b) {
+        write(new byte[] {(byte) b}, 0, 1);
+    }
+
+    /**
+     * Writes bytes of the update, applying the structs they complete (creates
+     * implicit transaction).
+     *
+     * @param chunk the bytes to write
+     * @param offset index of the first byte to write
+     * @param length number of bytes to write
+     * @throws IllegalArgumentException if chunk is null
+     * @throws IndexOutOfBoundsException if offset and length do not fit in chunk
+     * @throws IllegalStateException if the stream or the document has been closed,
+     *         or the document is read-only
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    @Override
+    public synchronized void write(byte[] chunk, int offset, int length) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            write(activeTxn, chunk, offset, length);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                write(txn, chunk, offset, length);
+            }
+        }
+    }
+
+    /**
+     * Writes bytes of the update within an existing transaction, applying the
+     * structs they complete.
+     *
+     * @param txn the transaction to use for this operation
+     * @param chunk the bytes to write
+     * @param offset index of the first byte to write
+     * @param length number of bytes to write
+     * @throws IllegalArgumentException if txn or chunk is null
+     * @throws IndexOutOfBoundsException if offset and length do not fit in chunk
+     * @throws IllegalStateException if the stream or the document has been closed,
+     *         or the document is read-only
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    public synchronized void write(YTransaction txn, byte[] chunk, int offset, int length) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
+        }
+        if (offset < 0 || length < 0 || length > chunk.length - offset) {
+            throw new IndexOutOfBoundsException(
+                "Range [" + offset + ", " + offset + " + " + length + ") out of bounds for length "
+                    + chunk.length);
+        }
+        nativeWriteWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), chunk, offset, length);
+    }
+
+    /**
+     * Applies the deletions that end the update (creates implicit transaction).
+     *
+     * <p>Finishing a stream again has no effect.</p>
+     *
+     * @throws IllegalStateException if the stream or the document has been closed,
+     *         or the document is read-only
+     * @throws RuntimeException if the update has not been written in full, has bytes
+     *         after its end, or cannot be applied
+     */
+    public synchronized void finish() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            finish(activeTxn);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                finish(txn);
+            }
+        }
+    }
+
+    /**
+     * Applies the deletions that end the update within an existing transaction.
+     *
+     * @param txn the transaction to use for this operation
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the stream or the document has been closed,
+     *         or the document is read-only
+     * @throws RuntimeException if the update has not been written in full, has bytes
+     *         after its end, or cannot be applied
+     */
+    public synchronized void finish(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        nativeFinishWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Returns the number of bytes written but not applied yet.
+     *
+     * <p>This is the memory the stream holds on to: the start of a struct whose end
+     * has not been written, and the deletions at the end of the update until the
+     * stream is finished.</p>
+     *
+     * @return the number of buffered bytes
+     * @throws IllegalStateException if the stream has been closed
+     */
+    public synchronized long getBufferedSize() {
+        checkClosed();
+        return nativeGetBufferedSize(nativePtr);
+    }
+
+    /**
+     * Returns the number of structs applied so far.
+     *
+     * @return the number of structs applied
+     * @throws IllegalStateException if the stream has been closed
+     */
+    public synchronized long getStructsApplied() {
+        checkClosed();
+        return nativeGetStructsApplied(nativePtr);
+    }
+
+    /**
+     * Checks if this stream has been closed.
+     *
+     * @return true if closed, false otherwise
+     */
+    public synchronized boolean isClosed() {
+        return nativePtr == 0;
+    }
+
+    /**
+     * Releases the stream, discarding the bytes it has not applied.
+     *
+     * <p>Does not finish the stream; call {@link #finish()} first to apply the
+     * deletions at the end of the update.</p>
+     */
+    @Override
+    public synchronized void close() {
+        if (nativePtr != 0) {
+            nativeDestroy(nativePtr);
+            nativePtr = 0;
+        }
+    }
+
+    private void checkClosed() {
+        if (nativePtr == 0) {
+            throw new IllegalStateException("JniUpdateStream has been closed");
+        }
+    }
+
+    // Native method declarations
+    private static native long nativeCreate();
+
+    private static native void nativeDestroy(long ptr);
+
+    private static native void nativeWriteWithTxn(long docPtr, long ptr, long txnPtr,
+            byte[] chunk, int offset, int length);
+
+    private static native void nativeFinishWithTxn(long docPtr, long ptr, long txnPtr);
+
+    private static native long nativeGetBufferedSize(long ptr);
+
+    private static native long nativeGetStructsApplied(long ptr);
+}
@@ -0,0 +1,177 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.FormattingChunk;
+
+import java.util.ArrayList;
+import java.util.Collections;
+import java.util.List;
+import java.util.Map;
+
+/**
+ * An immutable node of an XML tree, materialized by
+ * {@link JniYXmlFragment#snapshotTree(net.carcdr.ycrdt.YTransaction)}.
+ *
+ * <p>A snapshot holds no native resources and does not change when the document
+ * does, so it can be kept and read from any thread after the transaction it was
+ * taken in has ended. Elements have a tag, attributes and children; text nodes
+ * have attributes and formatted content; fragments only have children. Attribute
+ * values are plain values; attributes holding shared types are left out.</p>
+ */
+public final class JniXmlNode {
+
+    /**
+     * The type of an XML node.
+     */
+    public enum Kind {
+        /** A fragment: a list of children without a tag. */
+        FRAGMENT,
+        /** An element with a tag, attributes and children. */
+        ELEMENT,
+        /** A text node with formatted content. */
+        TEXT
+    }
+
+    private final Kind kind;
+    private final String tag;
+    private final Map<String, Object> attributes;
+    private final List<JniXmlNode> children;
+    private final List<FormattingChunk> chunks;
+
+    /**
+     * Called from native code.
+     *
+     * @param kind ordinal of the node kind
+     * @param tag the element tag, or null
+     * @param attributes the attributes, or null for a fragment
+     * @param children the child nodes, or null for a text node
+     * @param chunks the formatted content of a text node, or null
+     */
+    JniXmlNode(int kind, String tag, Map<String, Object> attributes, List<JniXmlNode> children,
+               List<FormattingChunk> chunks) {
+        this.kind = Kind.values()[kind];
+        this.tag = tag;
+        this.attributes = attributes == null || attributes.isEmpty()
+                ? Collections.emptyMap()
+                : Collections.unmodifiableMap(attributes);
+        this.children = children == null || children.isEmpty()
+                ? Collections.emptyList()
+                : Collections.unmodifiableList(children);
+        this.chunks = chunks == null || chunks.isEmpty()
+                ? Collections.emptyList()
+                : Collections.unmodifiableList(chunks);
+    }
+
+    /**
+     * Returns the type of this node.
+     *
+     * @return the node kind
+     */
+    public Kind getKind() {
+        return kind;
+    }
+
+    /**
+     * Returns the tag of an element.
+     *
+     * @return the tag, or null if this node is not an element
+     */
+    public String getTag() {
+        return tag;
+    }
+
+    /**
+     * Returns the attributes of an element or text node, ordered by name.
+     *
+     * @return an unmodifiable map of attributes, empty for a fragment
+     */
+    public Map<String, Object> getAttributes() {
+        return attributes;
+    }
+
+    /**
+     * Returns the value of an attribute.
+     *
+     * @param name the attribute name
+     * @return the value, or null if the node has no such attribute
+     */
+    public Object getAttribute(String name) {
+        return attributes.get(name);
+    }
+
+    /**
+     * Returns the children of an element or fragment, in document order.
+     *
+     * @return an unmodifiable list of children, empty for a text node
+     */
+    public List<JniXmlNode> getChildren() {
+        return children;
+    }
+
+    /**
+     * Returns the formatted content of a text node.
+     *
+     * @return an unmodifiable list of chunks, empty unless this is a text node
+     */
+    public List<FormattingChunk> getChunks() {
+        return chunks;
+    }
+
+    /**
+     * Returns the text of this node and all nodes below it, without formatting.
+     *
+     * @return the concatenated text content
+     */
+    public String getTextContent() {
+        StringBuilder sb = new StringBuilder();
+        appendText(sb);
+        return sb.toString();
+    }
+
+    private void appendText(StringBuilder sb) {
+        for (FormattingChunk chunk : chunks) {
+            sb.append(chunk.getText());
+        }
+        for (JniXmlNode child : children) {
+            child.appendText(sb);
+        }
+    }
+
+    /**
+     * Returns the elements below this node with the given tag, in document order.
+     *
+     * @param tag the tag to look for
+     * @return the matching elements
+     */
+    public List<JniXmlNode> getElementsByTag(String tag) {
+        List<JniXmlNode> result = new ArrayList<>();
+        collectElements(tag, result);
+        return result;
+    }
+
+    private void collectElements(String wanted, List<JniXmlNode> result) {
+        for (JniXmlNode child : children) {
+            if (child.kind == Kind.ELEMENT && child.tag.equals(wanted)) {
+                result.add(child);
+            }
+            child.collectElements(wanted, result);
+        }
+    }
+
+    @Override
+    public String toString() {
+        StringBuilder sb = new StringBuilder("JniXmlNode{").append(kind);
+        if (tag != null) {
+            sb.append(", tag=").append(tag);
+        }
+        if (!attributes.isEmpty()) {
+            sb.append(", attributes=").append(attributes);
+        }
+        if (!children.isEmpty()) {
+            sb.append(", children=").append(children.size());
+        }
+        if (!chunks.isEmpty()) {
+            sb.append(", text='").append(getTextContent()).append('\'');
+        }
+        return sb.append('}').toString();
+    }
+}
@@ -0,0 +1,270 @@
+package net.carcdr.ycrdt.jni;
+
+import java.util.Arrays;
+import java.util.Collections;
+import java.util.List;
+import java.util.concurrent.ConcurrentHashMap;
+
+import net.carcdr.ycrdt.YMap;
+import net.carcdr.ycrdt.YSubscription;
+import net.carcdr.ycrdt.YText;
+import net.carcdr.ycrdt.YTransaction;
+
+/**
+ * Named ranges over a text, such as the anchors of comments.
+ *
+ * <p>Each annotation is stored in a companion map under its ID, as a pair of
+ * sticky indexes attached to the first and last annotated characters. The range
+ * follows its content through local and remote edits: text typed at either
+ * boundary stays outside of it, and deleting annotated characters shrinks it.
+ * Once all annotated content is deleted the annotation is collapsed; it keeps
+ * its ID and position until it is removed. Because the annotations live in the
+ * document, they are shared with every peer:</p>
+ * <pre>{@code
+ * JniYAnnotations comments = new JniYAnnotations(doc, text, doc.getMap("comments"));
+ * comments.add("c1", 6, 11);
+ * try (YSubscription sub = comments.onCollapsed(a -> System.out.println(a.getId()))) {
+ *     text.delete(6, 5); // prints "c1"
+ * }
+ * }</pre>
+ *
+ * <p>The companion map should hold annotations only; entries that are not
+ * annotations of this text are ignored.</p>
+ */
+public class JniYAnnotations implements JniYObservable {
+
+    private final JniYDoc doc;
+    private final JniYText text;
+    private final JniYMap store;
+    private final ConcurrentHashMap<Long, AnnotationCollapseListener> listeners =
+        new ConcurrentHashMap<>();
+
+    /**
+     * Creates annotations over a text.
+     *
+     * @param doc the document containing the text and the map
+     * @param text the annotated text
+     * @param store the map in which annotations are stored
+     * @throws IllegalArgumentException if any argument is null
+     * @throws IllegalStateException if the document is closed
+     */
+    public JniYAnnotations(JniYDoc doc, YText text, YMap store) {
+        if (doc == null || text == null || store == null) {
+            throw new IllegalArgumentException("Document, text and store cannot be null");
+        }
+        if (doc.isClosed()) {
+            throw new IllegalStateException("JniYDoc has been closed");
+        }
+        this.doc = doc;
+        this.text = (JniYText) text;
+        this.store = (JniYMap) store;
+    }
+
+    /**
+     * Creates or replaces an annotation within an existing transaction.
+     *
+     * @param txn the transaction to use for this operation
+     * @param id the annotation ID
+     * @param start the index of the first annotated character
+     * @param end the index after the last annotated character
+     * @throws IllegalArgumentException if txn or id is null
+     * @throws IndexOutOfBoundsException if the range is empty or outside of the text
+     * @throws IllegalStateException if the document is read-only
+     */
+    public void add(YTransaction txn, String id, int start, int end) {
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (id == null) {
+            throw new IllegalArgumentException("ID cannot be null");
+        }
+        nativeAddWithTxn(doc.getNativePtr(), text.getNativePtr(), store.getNativePtr(),
+            ((JniYTransaction) txn).getNativePtr(), id, start, end);
+    }
+
+    /**
+     * Creates or replaces an annotation (creates implicit transaction).
+     *
+     * @param id the annotation ID
+     * @param start the index of the first annotated character
+     * @param end the index after the last annotated character
+     * @throws IllegalArgumentException if id is null
+     * @throws IndexOutOfBoundsException if the range is empty or outside of the text
+     * @throws IllegalStateException if the document is read-only
+     */
+    public void add(String id, int start, int end) {
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            add(activeTxn, id, start, end);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                add(txn, id, start, end);
+            }
+        }
+    }
+
+    /**
+     * Resolves an annotation within an existing transaction.
+     *
+     * @param txn the transaction to use for this operation
+     * @param id the annotation ID
+     * @return the current range, or null if there is no annotation with this ID
+     * @throws IllegalArgumentException if txn or id is null
+     */
+    public JniAnnotation get(YTransaction txn, String id) {
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (id == null) {
+            throw new IllegalArgumentException("ID cannot be null");
+        }
+        return nativeGetWithTxn(doc.getNativePtr(), text.getNativePtr(), store.getNativePtr(),
+            ((JniYTransaction) txn).getNativePtr(), id);
+    }
+
+    /**
+     * Resolves an annotation (creates implicit transaction).
+     *
+     * @param id the annotation ID
+     * @return the current range, or null if there is no annotation with this ID
+     * @throws IllegalArgumentException if id is null
+     */
+    public JniAnnotation get(String id) {
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return get(activeTxn, id);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return get(txn, id);
+        }
+    }
+
+    /**
+     * Lists all annotations within an existing transaction.
+     *
+     * @param txn the transaction to use for this operation
+     * @return the annotations ordered by start index and then ID
+     * @throws IllegalArgumentException if txn is null
+     */
+    public List<JniAnnotation> list(YTransaction txn) {
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        JniAnnotation[] annotations = nativeListWithTxn(doc.getNativePtr(), text.getNativePtr(),
+            store.getNativePtr(), ((JniYTransaction) txn).getNativePtr());
+        return Collections.unmodifiableList(Arrays.asList(annotations));
+    }
+
+    /**
+     * Lists all annotations (creates implicit transaction).
+     *
+     * @return the annotations ordered by start index and then ID
+     */
+    public List<JniAnnotation> list() {
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return list(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return list(txn);
+        }
+    }
+
+    /**
+     * Removes an annotation within an existing transaction.
+     *
+     * @param txn the transaction to use for this operation
+     * @param id the annotation ID
+     * @throws IllegalArgumentException if txn or id is null
+     */
+    public void remove(YTransaction txn, String id) {
+        store.remove(txn, id);
+    }
+
+    /**
+     * Removes an annotation (creates implicit transaction).
+     *
+     * @param id the annotation ID
+     * @throws IllegalArgumentException if id is null
+     */
+    public void remove(String id) {
+        store.remove(id);
+    }
+
+    /**
+     * Registers a listener for annotations whose range collapses.
+     *
+     * <p>The listener is called once per annotation when a change to the text
+     * deletes the last of its content. Annotations that are already collapsed
+     * when the listener is registered are not reported.</p>
+     *
+     * @param listener the listener to register
+     * @return a subscription that can be closed to unregister the listener
+     * @throws IllegalArgumentException if listener is null
+     * @see AnnotationCollapseListener
+     */
+    public YSubscription onCollapsed(AnnotationCollapseListener listener) {
+        if (listener == null) {
+            throw new IllegalArgumentException("Listener cannot be null");
+        }
+        long id = doc.allocateSubscriptionId();
+        listeners.put(id, listener);
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            observeCollapsed(activeTxn, id);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                observeCollapsed(txn, id);
+            }
+        }
+        return new JniYSubscription(id, null, this);
+    }
+
+    private void observeCollapsed(JniYTransaction txn, long subscriptionId) {
+        nativeObserveCollapsed(doc.getNativePtr(), text.getNativePtr(), store.getNativePtr(),
+            txn.getNativePtr(), subscriptionId, this);
+    }
+
+    /**
+     * Package-private method to unobserve by subscription ID.
+     * Called by YSubscription.close().
+     *
+     * @param subscriptionId the subscription ID to remove
+     */
+    @Override
+    public void unobserveById(long subscriptionId) {
+        if (listeners.remove(subscriptionId) != null && !doc.isClosed()) {
+            doc.deferNativeUnsubscribe(subscriptionId);
+        }
+    }
+
+    /**
+     * Package-private method called by JNI when an annotation collapses.
+     *
+     * @param subscriptionId the subscription ID
+     * @param annotation the collapsed annotation
+     */
+    void dispatchCollapsed(long subscriptionId, JniAnnotation annotation) {
+        AnnotationCollapseListener listener = listeners.get(subscriptionId);
+        if (listener != null) {
+            try {
+                listener.onCollapsed(annotation);
+            } catch (Exception e) {
+                doc.getObserverErrorHandler().handleError(e, this);
+            }
+        }
+    }
+
+    // Native method declarations
+    private static native void nativeAddWithTxn(long docPtr, long textPtr, long mapPtr, long txnPtr,
+            String id, int start, int end);
+
+    private static native JniAnnotation nativeGetWithTxn(long docPtr, long textPtr, long mapPtr,
+            long txnPtr, String id);
+
+    private static native JniAnnotation[] nativeListWithTxn(long docPtr, long textPtr, long mapPtr,
+            long txnPtr);
+
+    private static native void nativeObserveCollapsed(long docPtr, long textPtr, long mapPtr,
+            long txnPtr, long subscriptionId, JniYAnnotations annotations);
+}
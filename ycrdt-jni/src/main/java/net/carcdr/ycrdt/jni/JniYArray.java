@@ -7,8 +7,9 @@ import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YTransaction;
 
 import java.io.Closeable;
+import java.nio.ByteBuffer;
+import java.util.Collection;
 import java.util.concurrent.ConcurrentHashMap;
-import java.util.concurrent.atomic.AtomicLong;
 
 /**
  * YArray represents a collaborative array type in a Y-CRDT document.
@@ -38,8 +39,13 @@ public class JniYArray implements YArray, JniYObservable {
     private final JniYDoc doc;
     private long nativePtr;
     private volatile boolean closed = false;
+
+    /**
+     * Root generation of the document when this root was looked up by name, or
+     * -1 if the handle was not obtained by name.
+     */
+    private final int rootGeneration;
     private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
-    private final AtomicLong nextSubscriptionId = new AtomicLong(0);
 
     /**
      * Package-private constructor. Use {@link YDoc#getArray(String)} to create instances.
@@ -55,6 +61,7 @@ public class JniYArray implements YArray, JniYObservable {
             throw new IllegalArgumentException("Name cannot be null");
         }
         this.doc = doc;
+        this.rootGeneration = doc.getRootGeneration();
         this.nativePtr = nativeGetArray(doc.getNativePtr(), name);
         if (this.nativePtr == 0) {
             throw new RuntimeException("Failed to create YArray");
@@ -65,9 +72,36 @@ public class JniYArray implements YArray, JniYObservable {
      * Returns the length of the array.
      *
      * @return The number of elements in the array
+     * @throws ArithmeticException if the length does not fit in an int
      * @throws IllegalStateException if the array has been closed
      */
     public int length() {
+        return Sizes.toInt(longLength(), "longLength");
+    }
+
+    /**
+     * Returns the length of the array using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return The number of elements in the array
+     * @throws IllegalArgumentException if txn is null
+     * @throws ArithmeticException if the length does not fit in an int
+     * @throws IllegalStateException if the array has been closed
+     */
+    public int length(YTransaction txn) {
+        return Sizes.toInt(longLength(txn), "longLength");
+    }
+
+    /**
+     * Returns the length of the array.
+     *
+     * <p>Unlike {@link #length()}, works for arrays longer
+     * than {@link Integer#MAX_VALUE}.</p>
+     *
+     * @return The number of elements in the array
+     * @throws IllegalStateException if the array has been closed
+     */
+    public long longLength() {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
@@ -81,12 +115,15 @@ public class JniYArray implements YArray, JniYObservable {
     /**
      * Returns the length of the array using an existing transaction.
      *
+     * <p>Unlike {@link #length(YTransaction)}, works for arrays longer
+     * than {@link Integer#MAX_VALUE}.</p>
+     *
      * @param txn The transaction to use for this operation
      * @return The number of elements in the array
      * @throws IllegalArgumentException if txn is null
      * @throws IllegalStateException if the array has been closed
      */
-    public int length(YTransaction txn) {
+    public long longLength(YTransaction txn) {
         checkClosed();
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
@@ -94,6 +131,36 @@ public class JniYArray implements YArray, JniYObservable {
         return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
     }
 
+    /**
+     * Returns the root this array belongs to and the path leading to it.
+     *
+     * @return the path, or null if the array was deleted or is embedded in a text
+     * @throws IllegalStateException if the array has been closed
+     */
+    public JniTypePath getPath() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getPath(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getPath(txn);
+        }
+    }
+
+    /**
+     * Returns the root this array belongs to and the path leading to it.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the path, or null if the array was deleted or is embedded in a text
+     * @throws IllegalStateException if the array has been closed
+     */
+    public JniTypePath getPath(YTransaction txn) {
+        checkClosed();
+        return JniTypePath.fromNative(
+            nativeGetPathWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr()));
+    }
+
     /**
      * Gets a string value at the specified index.
      *
@@ -178,6 +245,87 @@ public class JniYArray implements YArray, JniYObservable {
         return nativeGetDoubleWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), index);
     }
 
+    /**
+     * Reads the whole array as doubles when it holds only numbers.
+     *
+     * <p>Much faster than calling {@link #getDouble(int)} for every index on large
+     * numeric series, since no value is boxed. Integers are converted to the nearest
+     * double.</p>
+     *
+     * @return the values in order, or null if the array holds anything other than
+     *         numbers
+     * @throws IllegalStateException if the array has been closed
+     */
+    public double[] toDoubleArray() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return toDoubleArray(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return toDoubleArray(txn);
+        }
+    }
+
+    /**
+     * Reads the whole array as doubles when it holds only numbers, using an
+     * existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the values in order, or null if the array holds anything other than
+     *         numbers
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #toDoubleArray()
+     */
+    public double[] toDoubleArray(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeToDoubleArrayWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Reads the whole array as longs when it holds only integers.
+     *
+     * <p>Doubles without a fractional part that fit in a long, such as those
+     * written by {@link #pushDouble(double)}, count as integers.</p>
+     *
+     * @return the values in order, or null if the array holds anything other than
+     *         integers
+     * @throws IllegalStateException if the array has been closed
+     */
+    public long[] toLongArray() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return toLongArray(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return toLongArray(txn);
+        }
+    }
+
+    /**
+     * Reads the whole array as longs when it holds only integers, using an
+     * existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the values in order, or null if the array holds anything other than
+     *         integers
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #toLongArray()
+     */
+    public long[] toLongArray(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeToLongArrayWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+    }
+
     /**
      * Inserts a string value at the specified index within an existing transaction.
      *
@@ -396,6 +544,387 @@ public class JniYArray implements YArray, JniYObservable {
         }
     }
 
+    /**
+     * Inserts all values of a collection at the specified index within an existing
+     * transaction.
+     *
+     * <p>The values are inserted in iteration order with a single native call, which
+     * is equivalent to inserting them one at a time but faster. Supported values are
+     * {@code String}, {@code Long}, {@code Integer}, {@code Double}, {@code Float},
+     * {@code Boolean} and {@code null}; if any value is of another type, nothing is
+     * inserted.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if txn or values is null, or a value has an
+     *         unsupported type
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    public void insertAll(YTransaction txn, int index, Collection<?> values) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (values == null) {
+            throw new IllegalArgumentException("Values cannot be null");
+        }
+        nativeInsertAllWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            index, values.toArray());
+    }
+
+    /**
+     * Inserts all values of a collection at the specified index (creates implicit
+     * transaction).
+     *
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if values is null or a value has an unsupported type
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     * @see #insertAll(YTransaction, int, Collection)
+     */
+    public void insertAll(int index, Collection<?> values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            insertAll(activeTxn, index, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                insertAll(txn, index, values);
+            }
+        }
+    }
+
+    /**
+     * Appends all values of a collection to the end of the array within an existing
+     * transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param values The values to append
+     * @throws IllegalArgumentException if txn or values is null, or a value has an
+     *         unsupported type
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, Collection)
+     */
+    public void pushAll(YTransaction txn, Collection<?> values) {
+        insertAll(txn, length(txn), values);
+    }
+
+    /**
+     * Appends all values of a collection to the end of the array (creates implicit
+     * transaction).
+     *
+     * @param values The values to append
+     * @throws IllegalArgumentException if values is null or a value has an unsupported type
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, Collection)
+     */
+    public void pushAll(Collection<?> values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            pushAll(activeTxn, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                pushAll(txn, values);
+            }
+        }
+    }
+
+    /**
+     * Inserts an array of doubles at the specified index within an existing transaction.
+     *
+     * <p>The values are copied to native code in one call without boxing or inspecting
+     * each element, which suits time series and other large numeric batches.
+     * Each value is stored as a number, as with {@link #pushDouble(double)}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if txn or values is null
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    public void insertAll(YTransaction txn, int index, double[] values) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (values == null) {
+            throw new IllegalArgumentException("Values cannot be null");
+        }
+        nativeInsertDoublesWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            index, values);
+    }
+
+    /**
+     * Inserts an array of doubles at the specified index (creates implicit transaction).
+     *
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if values is null
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     * @see #insertAll(YTransaction, int, double[])
+     */
+    public void insertAll(int index, double[] values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            insertAll(activeTxn, index, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                insertAll(txn, index, values);
+            }
+        }
+    }
+
+    /**
+     * Appends an array of doubles to the end of the array within an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param values The values to append
+     * @throws IllegalArgumentException if txn or values is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, double[])
+     */
+    public void pushAll(YTransaction txn, double[] values) {
+        insertAll(txn, length(txn), values);
+    }
+
+    /**
+     * Appends an array of doubles to the end of the array (creates implicit transaction).
+     *
+     * @param values The values to append
+     * @throws IllegalArgumentException if values is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, double[])
+     */
+    public void pushAll(double[] values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            pushAll(activeTxn, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                pushAll(txn, values);
+            }
+        }
+    }
+
+    /**
+     * Inserts an array of longs at the specified index within an existing transaction.
+     *
+     * <p>The values are copied to native code in one call without boxing or inspecting
+     * each element, which suits time series and other large numeric batches.
+     * Each value is stored as an integer, as a {@code Long} is by
+     * {@link #insertAll(YTransaction, int, Collection)}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if txn or values is null
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    public void insertAll(YTransaction txn, int index, long[] values) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (values == null) {
+            throw new IllegalArgumentException("Values cannot be null");
+        }
+        nativeInsertLongsWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            index, values);
+    }
+
+    /**
+     * Inserts an array of longs at the specified index (creates implicit transaction).
+     *
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if values is null
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     * @see #insertAll(YTransaction, int, long[])
+     */
+    public void insertAll(int index, long[] values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            insertAll(activeTxn, index, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                insertAll(txn, index, values);
+            }
+        }
+    }
+
+    /**
+     * Appends an array of longs to the end of the array within an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param values The values to append
+     * @throws IllegalArgumentException if txn or values is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, long[])
+     */
+    public void pushAll(YTransaction txn, long[] values) {
+        insertAll(txn, length(txn), values);
+    }
+
+    /**
+     * Appends an array of longs to the end of the array (creates implicit transaction).
+     *
+     * @param values The values to append
+     * @throws IllegalArgumentException if values is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, long[])
+     */
+    public void pushAll(long[] values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            pushAll(activeTxn, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                pushAll(txn, values);
+            }
+        }
+    }
+
+    /**
+     * Inserts an array of booleans at the specified index within an existing transaction.
+     *
+     * <p>The values are copied to native code in one call without boxing or inspecting
+     * each element, which suits time series and other large numeric batches.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if txn or values is null
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    public void insertAll(YTransaction txn, int index, boolean[] values) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (values == null) {
+            throw new IllegalArgumentException("Values cannot be null");
+        }
+        nativeInsertBooleansWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            index, values);
+    }
+
+    /**
+     * Inserts an array of booleans at the specified index (creates implicit transaction).
+     *
+     * @param index The position at which to insert (0-based)
+     * @param values The values to insert
+     * @throws IllegalArgumentException if values is null
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     * @see #insertAll(YTransaction, int, boolean[])
+     */
+    public void insertAll(int index, boolean[] values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            insertAll(activeTxn, index, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                insertAll(txn, index, values);
+            }
+        }
+    }
+
+    /**
+     * Appends an array of booleans to the end of the array within an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param values The values to append
+     * @throws IllegalArgumentException if txn or values is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, boolean[])
+     */
+    public void pushAll(YTransaction txn, boolean[] values) {
+        insertAll(txn, length(txn), values);
+    }
+
+    /**
+     * Appends an array of booleans to the end of the array (creates implicit transaction).
+     *
+     * @param values The values to append
+     * @throws IllegalArgumentException if values is null
+     * @throws IllegalStateException if the array has been closed
+     * @see #insertAll(YTransaction, int, boolean[])
+     */
+    public void pushAll(boolean[] values) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            pushAll(activeTxn, values);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                pushAll(txn, values);
+            }
+        }
+    }
+
+    /**
+     * Replaces the element at the specified index within an existing transaction.
+     *
+     * <p>The old element is removed and the new value inserted at the same index in
+     * one native call, so observers receive a single event for the replacement.
+     * Supported values are {@code String}, {@code Long}, {@code Integer},
+     * {@code Double}, {@code Float}, {@code Boolean} and {@code null}; the array is
+     * left unchanged if the value has another type.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position of the element to replace (0-based)
+     * @param value The new value
+     * @throws IllegalArgumentException if txn is null or value has an unsupported type
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or not less than the
+     *         current length
+     */
+    public void replace(YTransaction txn, int index, Object value) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        nativeReplaceWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            index, value);
+    }
+
+    /**
+     * Replaces the element at the specified index (creates implicit transaction).
+     *
+     * @param index The position of the element to replace (0-based)
+     * @param value The new value
+     * @throws IllegalArgumentException if value has an unsupported type
+     * @throws IllegalStateException if the array has been closed
+     * @throws IndexOutOfBoundsException if index is negative or not less than the
+     *         current length
+     * @see #replace(YTransaction, int, Object)
+     */
+    public void replace(int index, Object value) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            replace(activeTxn, index, value);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                replace(txn, index, value);
+            }
+        }
+    }
+
     /**
      * Removes a range of elements from the array within an existing transaction.
      *
@@ -727,6 +1256,104 @@ public class JniYArray implements YArray, JniYObservable {
         return nativeToJsonWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
     }
 
+    /**
+     * Writes the JSON form of the array as UTF-8 into a direct buffer within an existing transaction.
+     *
+     * <p>The bytes are copied from native memory into the buffer starting at its
+     * position, without creating a Java string, which keeps the heap flat when
+     * many large structures are exported at once. If they fit in the buffer's
+     * remaining space, its position is advanced past them. Otherwise nothing is
+     * written, and the returned size tells the caller how large a buffer to
+     * provide.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param target A direct buffer to write into
+     * @return The size of the output in bytes; greater than the buffer's remaining
+     *         space if nothing was written
+     * @throws IllegalArgumentException if txn or target is null, or target is not direct
+     * @throws java.nio.ReadOnlyBufferException if target is read-only
+     * @throws IllegalStateException if the array has been closed
+     */
+    public int writeJson(YTransaction txn, ByteBuffer target) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        DirectBuffers.checkWritable(target);
+        int size = nativeWriteJsonWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            target, target.position(), target.remaining());
+        return DirectBuffers.advance(target, size);
+    }
+
+    /**
+     * Writes the JSON form of the array as UTF-8 into a direct buffer (creates implicit transaction).
+     *
+     * @param target A direct buffer to write into
+     * @return The size of the output in bytes; greater than the buffer's remaining
+     *         space if nothing was written
+     * @throws IllegalArgumentException if target is null or not direct
+     * @throws java.nio.ReadOnlyBufferException if target is read-only
+     * @throws IllegalStateException if the array has been closed
+     * @see #writeJson(YTransaction, ByteBuffer)
+     */
+    public int writeJson(ByteBuffer target) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return writeJson(activeTxn, target);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return writeJson(txn, target);
+        }
+    }
+
+    /**
+     * Returns a JSON string representation of the element at the specified index.
+     *
+     * <p>Nested shared types are converted in full, as with {@link #toJson()}, but only
+     * the requested element is serialized.</p>
+     *
+     * @param index The index (0-based)
+     * @return A JSON string representation, or null if index is out of bounds
+     * @throws IllegalStateException if the array has been closed
+     */
+    public String getJsonAt(int index) {
+        checkClosed();
+        if (index < 0) {
+            return null;
+        }
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return nativeGetJsonAtWithTxn(doc.getNativePtr(), nativePtr,
+                activeTxn.getNativePtr(), index);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return nativeGetJsonAtWithTxn(doc.getNativePtr(), nativePtr, txn.getNativePtr(), index);
+        }
+    }
+
+    /**
+     * Returns a JSON string representation of the element at the specified index using
+     * an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The index (0-based)
+     * @return A JSON string representation, or null if index is out of bounds
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the array has been closed
+     */
+    public String getJsonAt(YTransaction txn, int index) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (index < 0) {
+            return null;
+        }
+        return nativeGetJsonAtWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), index);
+    }
+
     /**
      * Registers an observer to be notified when this array changes.
      *
@@ -758,7 +1385,7 @@ public class JniYArray implements YArray, JniYObservable {
         if (observer == null) {
             throw new IllegalArgumentException("Observer cannot be null");
         }
-        long id = nextSubscriptionId.incrementAndGet();
+        long id = doc.allocateSubscriptionId();
         observers.put(id, observer);
         nativeObserve(doc.getNativePtr(), nativePtr, id, this);
         return new JniYSubscription(id, observer, this);
@@ -833,7 +1460,8 @@ public class JniYArray implements YArray, JniYObservable {
                     }
                     observers.clear();
 
-                    if (nativePtr != 0) {
+                    // The document frees released handles itself
+                    if (nativePtr != 0 && !isReleased()) {
                         nativeDestroy(nativePtr);
                         nativePtr = 0;
                     }
@@ -852,6 +1480,18 @@ public class JniYArray implements YArray, JniYObservable {
         if (closed) {
             throw new IllegalStateException("YArray has been closed");
         }
+        if (isReleased()) {
+            throw new IllegalStateException("YArray was released by its YDoc");
+        }
+    }
+
+    /**
+     * Returns whether the document released the native handle of this root.
+     *
+     * @return true after {@link JniYDoc#releaseAllRoots()} or closing the document
+     */
+    private boolean isReleased() {
+        return rootGeneration >= 0 && doc.getRootGeneration() != rootGeneration;
     }
 
     /**
@@ -866,11 +1506,14 @@ public class JniYArray implements YArray, JniYObservable {
     // Native methods
     private static native long nativeGetArray(long docPtr, String name);
     private static native void nativeDestroy(long ptr);
-    private static native int nativeLengthWithTxn(long docPtr, long arrayPtr, long txnPtr);
+    private static native long nativeLengthWithTxn(long docPtr, long arrayPtr, long txnPtr);
+    private static native Object[] nativeGetPathWithTxn(long docPtr, long arrayPtr, long txnPtr);
     private static native String nativeGetStringWithTxn(long docPtr, long arrayPtr, long txnPtr,
                                                          int index);
     private static native double nativeGetDoubleWithTxn(long docPtr, long arrayPtr, long txnPtr,
                                                          int index);
+    private static native double[] nativeToDoubleArrayWithTxn(long docPtr, long arrayPtr, long txnPtr);
+    private static native long[] nativeToLongArrayWithTxn(long docPtr, long arrayPtr, long txnPtr);
     private static native void nativeInsertStringWithTxn(long docPtr, long arrayPtr, long txnPtr,
                                                           int index, String value);
     private static native void nativeInsertDoubleWithTxn(long docPtr, long arrayPtr, long txnPtr,
@@ -879,9 +1522,26 @@ public class JniYArray implements YArray, JniYObservable {
                                                         String value);
     private static native void nativePushDoubleWithTxn(long docPtr, long arrayPtr, long txnPtr,
                                                         double value);
+
+    private static native void nativeInsertAllWithTxn(long docPtr, long arrayPtr, long txnPtr,
+                                                      int index, Object[] values);
+    private static native void nativeInsertDoublesWithTxn(long docPtr, long arrayPtr, long txnPtr,
+                                                          int index, double[] values);
+    private static native void nativeInsertLongsWithTxn(long docPtr, long arrayPtr, long txnPtr,
+                                                        int index, long[] values);
+    private static native void nativeInsertBooleansWithTxn(long docPtr, long arrayPtr, long txnPtr,
+                                                           int index, boolean[] values);
+    private static native void nativeReplaceWithTxn(long docPtr, long arrayPtr, long txnPtr,
+                                                    int index, Object value);
+
     private static native void nativeRemoveWithTxn(long docPtr, long arrayPtr, long txnPtr,
                                                     int index, int length);
     private static native String nativeToJsonWithTxn(long docPtr, long arrayPtr, long txnPtr);
+
+    private static native int nativeWriteJsonWithTxn(long docPtr, long arrayPtr, long txnPtr,
+                                                     ByteBuffer buffer, int offset, int length);
+
+    private static native String nativeGetJsonAtWithTxn(long docPtr, long arrayPtr, long txnPtr, int index);
     private static native void nativeInsertDocWithTxn(long docPtr, long arrayPtr, long txnPtr,
                                                        int index, long subdocPtr);
     private static native void nativePushDocWithTxn(long docPtr, long arrayPtr, long txnPtr,
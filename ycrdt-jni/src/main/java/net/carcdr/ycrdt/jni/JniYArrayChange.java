@@ -15,6 +15,10 @@ import java.util.List;
  *   <li><b>RETAIN:</b> Position skipped (no change), used for context</li>
  * </ul>
  *
+ * <p>Inserted items are plain Java values (strings, numbers, booleans,
+ * {@code byte[]}, {@code List} and {@code Map}) or, for shared types, fully
+ * expanded {@link JniTreeNode}s read when the event is delivered.
+ *
  * @see JniYEvent
  */
 public final class JniYArrayChange extends YArrayChange {
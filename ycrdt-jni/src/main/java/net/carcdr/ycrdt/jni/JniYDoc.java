@@ -1,15 +1,28 @@
 package net.carcdr.ycrdt.jni;
 
 import java.lang.ref.Cleaner;
+import java.util.ArrayDeque;
+import java.util.ArrayList;
+import java.util.Collections;
+import java.util.HashSet;
+import java.util.List;
+import java.util.Map;
+import java.util.Set;
+import java.util.TreeMap;
+import java.util.UUID;
 import java.util.concurrent.ConcurrentHashMap;
 import java.util.concurrent.ConcurrentLinkedQueue;
+import java.util.concurrent.atomic.AtomicInteger;
 import java.util.concurrent.atomic.AtomicLong;
 import java.util.function.Consumer;
+import java.util.function.Predicate;
+import java.util.random.RandomGenerator;
 
 import net.carcdr.ycrdt.DefaultObserverErrorHandler;
 import net.carcdr.ycrdt.ObserverErrorHandler;
 import net.carcdr.ycrdt.UpdateObserver;
 import net.carcdr.ycrdt.YDoc;
+import net.carcdr.ycrdt.YObserver;
 import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YTransaction;
 
@@ -50,6 +63,38 @@ public class JniYDoc implements YDoc, JniYObservable {
         NativeLoader.loadLibrary();
     }
 
+    /**
+     * Which modifications a document accepts.
+     *
+     * @see #setAccessMode(AccessMode)
+     */
+    public enum AccessMode {
+        /** Local edits and applied updates are allowed. */
+        READ_WRITE,
+        /** Every modification throws {@link IllegalStateException}. */
+        READ_ONLY,
+        /**
+         * Local edits throw {@link IllegalStateException}, but updates from peers
+         * can still be applied with {@link #applyUpdate(byte[])}.
+         */
+        READ_ONLY_ALLOW_UPDATES
+    }
+
+    /**
+     * How strings passed to a document are converted when they are not valid UTF-16.
+     *
+     * <p>A Java string can hold an unpaired surrogate, for example after a string
+     * with an emoji was cut in the middle. Such a string has no UTF-8 form.</p>
+     *
+     * @see #setStringPolicy(StringPolicy)
+     */
+    public enum StringPolicy {
+        /** Each unpaired surrogate is replaced with U+FFFD; the rest of the string is kept. */
+        LOSSY,
+        /** Strings with unpaired surrogates are rejected with {@link IllegalArgumentException}. */
+        STRICT
+    }
+
     /**
      * Pointer to the native YDoc instance.
      */
@@ -65,6 +110,18 @@ public class JniYDoc implements YDoc, JniYObservable {
      */
     private final Cleaner.Cleanable cleanable;
 
+    /**
+     * The action registered with {@link #cleanable}, kept so {@link #close()} can
+     * run it directly and surface failures.
+     */
+    private final CleanupAction cleanupAction;
+
+    /**
+     * Run once by {@link #close()} before the native document is freed, so the
+     * {@link JniDocStore} holding this document forgets it first.
+     */
+    private volatile Runnable closeHook;
+
     /**
      * Cleanup action that releases native resources.
      * This is a static class to avoid preventing the JniYDoc from being garbage collected.
@@ -80,7 +137,14 @@ public class JniYDoc implements YDoc, JniYObservable {
         public void run() {
             long p = ptr.getAndSet(0);
             if (p != 0) {
-                nativeDestroy(p);
+                try {
+                    nativeDestroy(p);
+                } catch (RuntimeException e) {
+                    // Destroy was refused (e.g. called from an observer callback),
+                    // so the document is still live and can be closed later
+                    ptr.set(p);
+                    throw e;
+                }
             }
         }
     }
@@ -92,13 +156,27 @@ public class JniYDoc implements YDoc, JniYObservable {
      */
     private final ThreadLocal<JniYTransaction> activeTransaction = new ThreadLocal<>();
 
+    /**
+     * Work deferred by observers with {@link #runAfterTransaction(Runnable)}, per
+     * thread, run once the transaction that notified them has been committed.
+     */
+    private final ThreadLocal<ArrayDeque<Runnable>> deferredActions =
+        ThreadLocal.withInitial(ArrayDeque::new);
+
     /**
      * Map of active update observers by subscription ID.
      */
     private final ConcurrentHashMap<Long, UpdateObserver> updateObservers = new ConcurrentHashMap<>();
 
     /**
-     * Counter for generating unique subscription IDs.
+     * Map of active root observers by subscription ID.
+     */
+    private final ConcurrentHashMap<Long, YObserver> rootObservers = new ConcurrentHashMap<>();
+
+    /**
+     * Counter for generating subscription IDs. All subscriptions on this document,
+     * including those of its shared types, are stored natively in one table keyed
+     * by this ID, so it must be unique per document.
      */
     private final AtomicLong nextSubscriptionId = new AtomicLong(1);
 
@@ -107,6 +185,32 @@ public class JniYDoc implements YDoc, JniYObservable {
      */
     private ObserverErrorHandler observerErrorHandler = DefaultObserverErrorHandler.INSTANCE;
 
+    /**
+     * Listener for transactions slower than {@link #slowTransactionThresholdNanos}.
+     */
+    private volatile SlowTransactionListener slowTransactionListener;
+
+    /**
+     * Duration above which {@link #slowTransactionListener} is invoked.
+     */
+    private volatile long slowTransactionThresholdNanos;
+
+    /**
+     * Listener for staged updates applied by {@link #queueUpdate(YTransaction, byte[])}.
+     */
+    private volatile UpdateFlushListener updateFlushListener;
+
+    /**
+     * Copy of the native formatting attribute allowlist, or null if none is set.
+     */
+    private volatile Set<String> allowedFormattingAttributes;
+
+    /**
+     * Incremented whenever the native handles of root types obtained by name are
+     * released, so the Java objects holding them stop using them.
+     */
+    private final AtomicInteger rootGeneration = new AtomicInteger();
+
     /**
      * Queue of native subscription IDs whose Rust-side Subscription objects
      * need to be dropped. The Java observer map is updated immediately so
@@ -128,7 +232,8 @@ public class JniYDoc implements YDoc, JniYObservable {
         if (this.nativePtr == 0) {
             throw new RuntimeException("Failed to create JniYDoc: native pointer is null");
         }
-        this.cleanable = NativeCleaner.CLEANER.register(this, new CleanupAction(nativePtr));
+        this.cleanupAction = new CleanupAction(nativePtr);
+        this.cleanable = NativeCleaner.CLEANER.register(this, cleanupAction);
     }
 
     /**
@@ -149,7 +254,8 @@ public class JniYDoc implements YDoc, JniYObservable {
         if (this.nativePtr == 0) {
             throw new RuntimeException("Failed to create JniYDoc: native pointer is null");
         }
-        this.cleanable = NativeCleaner.CLEANER.register(this, new CleanupAction(nativePtr));
+        this.cleanupAction = new CleanupAction(nativePtr);
+        this.cleanable = NativeCleaner.CLEANER.register(this, cleanupAction);
     }
 
     /**
@@ -164,7 +270,104 @@ public class JniYDoc implements YDoc, JniYObservable {
         if (this.nativePtr == 0) {
             throw new RuntimeException("Invalid native pointer");
         }
-        this.cleanable = NativeCleaner.CLEANER.register(this, new CleanupAction(nativePtr));
+        this.cleanupAction = new CleanupAction(nativePtr);
+        this.cleanable = NativeCleaner.CLEANER.register(this, cleanupAction);
+    }
+
+    /**
+     * Creates a new JniYDoc whose client ID and GUID are drawn from the given
+     * random source.
+     *
+     * <p>Documents created from a seeded source, such as
+     * {@code new SplittableRandom(seed)}, get the same IDs on every run, so
+     * simulations and property-based tests can be replayed exactly. The client ID
+     * is drawn from the same range yrs uses for random client IDs.</p>
+     *
+     * @param random the source to draw the client ID and GUID from
+     * @return a new document
+     * @throws IllegalArgumentException if random is null
+     * @throws RuntimeException if native initialization fails
+     */
+    public static JniYDoc withRandom(RandomGenerator random) {
+        if (random == null) {
+            throw new IllegalArgumentException("Random source cannot be null");
+        }
+        long clientId = random.nextLong(0, 0xFFFFFFFFL);
+        long mostSigBits = (random.nextLong() & ~0xF000L) | 0x4000L;
+        long leastSigBits = (random.nextLong() & ~0xC000000000000000L) | 0x8000000000000000L;
+        String guid = new UUID(mostSigBits, leastSigBits).toString();
+        long ptr = nativeCreateWithIds(clientId, guid);
+        if (ptr == 0) {
+            throw new RuntimeException("Failed to create JniYDoc: native pointer is null");
+        }
+        return new JniYDoc(ptr, true);
+    }
+
+    /**
+     * Creates a new JniYDoc that keeps the content of deleted items.
+     *
+     * <p>Normally deleted content is garbage collected. Disabling that lets
+     * snapshots taken with {@link #encodeSnapshot()} be rendered and diffed
+     * later, including the removed text, at the cost of a larger document.</p>
+     *
+     * @return a new document with garbage collection disabled
+     * @throws RuntimeException if native initialization fails
+     */
+    public static JniYDoc withoutGarbageCollection() {
+        return withoutGarbageCollection(-1);
+    }
+
+    /**
+     * Creates a new JniYDoc with a specific client ID that keeps the content of
+     * deleted items.
+     *
+     * @param clientId the client ID to assign, or a negative value for a random one
+     * @return a new document with garbage collection disabled
+     * @throws RuntimeException if native initialization fails
+     * @see #withoutGarbageCollection()
+     */
+    public static JniYDoc withoutGarbageCollection(long clientId) {
+        long ptr = nativeCreateWithoutGc(clientId);
+        if (ptr == 0) {
+            throw new RuntimeException("Failed to create JniYDoc: native pointer is null");
+        }
+        return new JniYDoc(ptr, true);
+    }
+
+    /**
+     * Creates a new JniYDoc whose output is the same on every run, for tests that
+     * compare serialized output against stored files.
+     *
+     * <p>The document uses the given client ID, so encoded updates do not depend on a
+     * random one, and map keys are returned in sorted order by {@code toJson},
+     * {@code getJsonAt} and {@link JniYMap#keys()}. Regular documents return map keys
+     * in an order that changes between runs.</p>
+     *
+     * @param clientId the client ID to assign
+     * @return a new deterministic document
+     * @throws IllegalArgumentException if clientId is negative
+     * @throws RuntimeException if native initialization fails
+     */
+    public static JniYDoc deterministic(long clientId) {
+        if (clientId < 0) {
+            throw new IllegalArgumentException("Client ID must be non-negative");
+        }
+        long ptr = nativeCreateDeterministic(clientId);
+        if (ptr == 0) {
+            throw new RuntimeException("Failed to create JniYDoc: native pointer is null");
+        }
+        return new JniYDoc(ptr, true);
+    }
+
+    /**
+     * Checks if this document was created with {@link #deterministic(long)}.
+     *
+     * @return true if map keys are returned in sorted order
+     * @throws IllegalStateException if this document has been closed
+     */
+    public boolean isDeterministic() {
+        ensureNotClosed();
+        return nativeIsDeterministic(nativePtr);
     }
 
     /**
@@ -182,6 +385,23 @@ public class JniYDoc implements YDoc, JniYObservable {
         return nativeGetClientId(nativePtr);
     }
 
+    /**
+     * Gets the number of committed transactions that changed this document.
+     *
+     * <p>The counter grows by one with every transaction that changes the document,
+     * whether the change is local or applied from an update, and is read without
+     * encoding anything. A cache built from the document can store the counter and is
+     * stale once the counter differs. Transactions that change nothing leave it
+     * unchanged; {@link #reset()} counts as a change.</p>
+     *
+     * @return the change counter, starting at zero for a new document
+     * @throws IllegalStateException if this document has been closed
+     */
+    public long getChangeCounter() {
+        ensureNotClosed();
+        return nativeGetChangeCounter(nativePtr);
+    }
+
     /**
      * Gets the globally unique identifier (GUID) of this document.
      *
@@ -198,207 +418,1648 @@ public class JniYDoc implements YDoc, JniYObservable {
     }
 
     /**
-     * Encodes the current state of the document as a binary update within an existing transaction.
+     * Gets the GUID of the document this subdocument is embedded in.
      *
-     * <p>This method captures the entire state of the document and returns it
-     * as a byte array that can be transmitted to other clients or stored for
-     * later use.</p>
+     * <p>Together with {@link #getEmbeddedPath()}, this lets persistence layers
+     * record where each subdocument lives and rebuild the document tree.</p>
      *
-     * <p>The returned byte array can be applied to another YDoc instance using
-     * {@link #applyUpdate(byte[])} to synchronize their states.</p>
+     * @return the parent's GUID, or null if this document is not a subdocument
+     * @throws IllegalStateException if this document has been closed, or if the
+     *         current thread holds a transaction on it
+     */
+    public String getParentGuid() {
+        ensureNotClosed();
+        return nativeGetParentGuid(nativePtr);
+    }
+
+    /**
+     * Gets where this subdocument is embedded in its parent document.
      *
-     * @param txn The transaction to use for this operation
-     * @return a byte array containing the encoded document state
-     * @throws IllegalArgumentException if txn is null
-     * @throws IllegalStateException if this document has been closed
-     * @throws RuntimeException if encoding fails
+     * <p>The path names the parent's root followed by the map keys and array
+     * indexes leading to the subdocument, for example {@code docs.child} or
+     * {@code pages[2]}. The parent is read in a transaction of its own.</p>
+     *
+     * @return the path, or null if this document is not a subdocument or has been
+     *         removed from its parent
+     * @throws IllegalStateException if this document has been closed, or if the
+     *         current thread holds a transaction on this document or its parent
      */
-    @Override
-    public byte[] encodeStateAsUpdate(YTransaction txn) {
+    public JniTypePath getEmbeddedPath() {
         ensureNotClosed();
-        if (txn == null) {
-            throw new IllegalArgumentException("Transaction cannot be null");
-        }
-        byte[] result = nativeEncodeStateAsUpdateWithTxn(nativePtr,
-            ((JniYTransaction) txn).getNativePtr());
-        if (result == null) {
-            throw new RuntimeException("Failed to encode state as update");
-        }
-        return result;
+        return JniTypePath.fromNative(nativeGetEmbeddedPath(nativePtr));
     }
 
     /**
-     * Encodes the current state of the document as a binary update (creates implicit transaction).
+     * Enables or disables single-writer checks for transactions on this document.
      *
-     * <p>This method captures the entire state of the document and returns it
-     * as a byte array that can be transmitted to other clients or stored for
-     * later use.</p>
+     * <p>When enabled, each transaction opened afterwards records the thread that
+     * opened it. Using that transaction (including committing it) from any other
+     * thread throws {@link IllegalStateException} naming both threads, instead of
+     * racing on the native transaction. Transactions opened before the call are
+     * not affected.</p>
      *
-     * <p>The returned byte array can be applied to another YDoc instance using
-     * {@link #applyUpdate(byte[])} to synchronize their states.</p>
+     * <p>This is intended as a debugging aid and is disabled by default.</p>
      *
-     * @return a byte array containing the encoded document state
+     * @param enabled true to enforce single-writer access
      * @throws IllegalStateException if this document has been closed
-     * @throws RuntimeException if encoding fails
      */
-    @Override
-    public byte[] encodeStateAsUpdate() {
+    public void setSingleWriterCheck(boolean enabled) {
         ensureNotClosed();
-        JniYTransaction activeTxn = getActiveTransaction();
-        if (activeTxn != null) {
-            byte[] result = nativeEncodeStateAsUpdateWithTxn(nativePtr, activeTxn.getNativePtr());
-            if (result == null) {
-                throw new RuntimeException("Failed to encode state as update");
-            }
-            return result;
-        }
-        try (JniYTransaction txn = beginTransactionInternal()) {
-            byte[] result = nativeEncodeStateAsUpdateWithTxn(nativePtr, txn.getNativePtr());
-            if (result == null) {
-                throw new RuntimeException("Failed to encode state as update");
-            }
-            return result;
-        }
+        nativeSetSingleWriter(nativePtr, enabled);
     }
 
     /**
-     * Applies a binary update to this document within an existing transaction.
-     *
-     * <p>This method merges changes from another document into this one. The update
-     * is typically obtained by calling {@link #encodeStateAsUpdate()} on another
-     * YDoc instance.</p>
-     *
-     * <p>Updates are idempotent - applying the same update multiple times has the
-     * same effect as applying it once.</p>
+     * Returns whether single-writer checks are enabled for this document.
      *
-     * @param txn The transaction to use for this operation
-     * @param update the binary update to apply
-     * @throws IllegalArgumentException if txn or update is null
+     * @return true if transactions are restricted to the thread that opened them
      * @throws IllegalStateException if this document has been closed
-     * @throws RuntimeException if the update is invalid or cannot be applied
+     * @see #setSingleWriterCheck(boolean)
      */
-    @Override
-    public void applyUpdate(YTransaction txn, byte[] update) {
+    public boolean isSingleWriterCheckEnabled() {
         ensureNotClosed();
-        if (txn == null) {
-            throw new IllegalArgumentException("Transaction cannot be null");
-        }
-        if (update == null) {
-            throw new IllegalArgumentException("Update cannot be null");
-        }
-        nativeApplyUpdateWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr(), update);
+        return nativeIsSingleWriter(nativePtr);
     }
 
     /**
-     * Applies a binary update to this document (creates implicit transaction).
+     * Sets which modifications this document accepts.
      *
-     * <p>This method merges changes from another document into this one. The update
-     * is typically obtained by calling {@link #encodeStateAsUpdate()} on another
-     * YDoc instance.</p>
+     * <p>In a read-only mode every native operation that would modify the document
+     * (inserting, deleting, formatting, setting attributes or map entries) throws
+     * {@link IllegalStateException} before touching it, regardless of which shared
+     * type or transaction it is called through. This lets a document be served to
+     * viewers with the guarantee that a bug in application code cannot change it.
+     * {@link AccessMode#READ_ONLY_ALLOW_UPDATES} still applies updates from peers,
+     * so a read-only replica can stay in sync.</p>
      *
-     * <p>Updates are idempotent - applying the same update multiple times has the
-     * same effect as applying it once.</p>
+     * <p>The mode applies to operations started after the call, including those
+     * in transactions that are already open.</p>
      *
-     * @param update the binary update to apply
+     * @param mode the access mode
+     * @throws IllegalArgumentException if mode is null
      * @throws IllegalStateException if this document has been closed
-     * @throws IllegalArgumentException if update is null
-     * @throws RuntimeException if the update is invalid or cannot be applied
      */
-    @Override
-    public void applyUpdate(byte[] update) {
+    public void setAccessMode(AccessMode mode) {
         ensureNotClosed();
-        if (update == null) {
-            throw new IllegalArgumentException("Update cannot be null");
-        }
-        JniYTransaction activeTxn = getActiveTransaction();
-        if (activeTxn != null) {
-            nativeApplyUpdateWithTxn(nativePtr, activeTxn.getNativePtr(), update);
-        } else {
-            try (JniYTransaction txn = beginTransactionInternal()) {
-                nativeApplyUpdateWithTxn(nativePtr, txn.getNativePtr(), update);
-            }
+        if (mode == null) {
+            throw new IllegalArgumentException("Access mode cannot be null");
         }
+        nativeSetAccess(nativePtr, mode.ordinal());
     }
 
     /**
-     * Encodes the current state vector of this document within an existing transaction.
-     *
-     * <p>A state vector is a compact representation of all known blocks inserted and
-     * integrated into this document. It serves as a logical timestamp describing which
-     * updates this document has observed.</p>
-     *
-     * <p>State vectors are used to generate differential updates - by sending a state
-     * vector to a remote peer, they can determine which changes this document has not
-     * yet seen and send only those changes.</p>
+     * Returns which modifications this document accepts.
      *
-     * @param txn The transaction to use for this operation
-     * @return a byte array containing the encoded state vector
-     * @throws IllegalArgumentException if txn is null
+     * @return the access mode
      * @throws IllegalStateException if this document has been closed
-     * @throws RuntimeException if encoding fails
+     * @see #setAccessMode(AccessMode)
      */
-    @Override
-    public byte[] encodeStateVector(YTransaction txn) {
+    public AccessMode getAccessMode() {
         ensureNotClosed();
-        if (txn == null) {
-            throw new IllegalArgumentException("Transaction cannot be null");
-        }
-        byte[] result = nativeEncodeStateVectorWithTxn(nativePtr,
-            ((JniYTransaction) txn).getNativePtr());
-        if (result == null) {
-            throw new RuntimeException("Failed to encode state vector");
-        }
-        return result;
+        return AccessMode.values()[nativeGetAccess(nativePtr)];
     }
 
     /**
-     * Encodes the current state vector of this document (creates implicit transaction).
+     * Checks if local edits to this document are rejected.
      *
-     * <p>A state vector is a compact representation of all known blocks inserted and
-     * integrated into this document. It serves as a logical timestamp describing which
-     * updates this document has observed.</p>
+     * @return true if the access mode is not {@link AccessMode#READ_WRITE}
+     * @throws IllegalStateException if this document has been closed
+     */
+    public boolean isReadOnly() {
+        return getAccessMode() != AccessMode.READ_WRITE;
+    }
+
+    /**
+     * Sets how strings passed to this document are converted when they are not
+     * valid UTF-16.
      *
-     * <p>State vectors are used to generate differential updates - by sending a state
-     * vector to a remote peer, they can determine which changes this document has not
-     * yet seen and send only those changes.</p>
+     * <p>The policy applies to text content, map keys and values, XML tag and
+     * attribute names and values, and formatting attributes passed to any shared
+     * type of this document. The default is {@link StringPolicy#LOSSY}.</p>
      *
-     * @return a byte array containing the encoded state vector
+     * @param policy the string policy
+     * @throws IllegalArgumentException if policy is null
      * @throws IllegalStateException if this document has been closed
-     * @throws RuntimeException if encoding fails
      */
-    @Override
-    public byte[] encodeStateVector() {
+    public void setStringPolicy(StringPolicy policy) {
         ensureNotClosed();
-        JniYTransaction activeTxn = getActiveTransaction();
-        if (activeTxn != null) {
-            byte[] result = nativeEncodeStateVectorWithTxn(nativePtr, activeTxn.getNativePtr());
-            if (result == null) {
-                throw new RuntimeException("Failed to encode state vector");
-            }
-            return result;
-        }
-        try (JniYTransaction txn = beginTransactionInternal()) {
-            byte[] result = nativeEncodeStateVectorWithTxn(nativePtr, txn.getNativePtr());
-            if (result == null) {
-                throw new RuntimeException("Failed to encode state vector");
-            }
-            return result;
+        if (policy == null) {
+            throw new IllegalArgumentException("String policy cannot be null");
         }
+        nativeSetStringPolicy(nativePtr, policy.ordinal());
     }
 
     /**
-     * Encodes a differential update containing only changes not yet observed by the
-     * remote peer within an existing transaction.
+     * Returns how strings passed to this document are converted when they are not
+     * valid UTF-16.
      *
-     * <p>This method generates an update that includes only the changes that are present
-     * in this document but not reflected in the provided state vector. This is more
-     * efficient than sending the entire document state when synchronizing with a peer
-     * that already has some of the data.</p>
+     * @return the string policy
+     * @throws IllegalStateException if this document has been closed
+     * @see #setStringPolicy(StringPolicy)
+     */
+    public StringPolicy getStringPolicy() {
+        ensureNotClosed();
+        return StringPolicy.values()[nativeGetStringPolicy(nativePtr)];
+    }
+
+    /**
+     * Enables or disables recording when each root type is modified.
      *
-     * <p>Typical usage:</p>
-     * <pre>{@code
-     * // Remote peer sends their state vector
-     * byte[] remoteStateVector = ...;
+     * <p>While enabled, every committed transaction that changes a root type, directly
+     * or through a nested type, records the time and the clients whose clocks it
+     * advanced. Disabling keeps the entries recorded so far. Recording is off by
+     * default.</p>
+     *
+     * @param enabled whether to record changes
+     * @throws IllegalStateException if this document has been closed or a transaction
+     *         is open
+     * @see #getRootActivity()
+     */
+    public void setRootActivityTracking(boolean enabled) {
+        ensureNotClosed();
+        nativeSetRootActivityTracking(nativePtr, enabled);
+    }
+
+    /**
+     * Checks whether root type modifications are being recorded.
+     *
+     * @return true if root activity tracking is enabled
+     * @throws IllegalStateException if this document has been closed
+     */
+    public boolean isRootActivityTracking() {
+        ensureNotClosed();
+        return nativeIsRootActivityTracking(nativePtr);
+    }
+
+    /**
+     * Returns the last recorded modification of each root type.
+     *
+     * <p>Root types that have not changed while tracking was enabled are absent.</p>
+     *
+     * @return an unmodifiable map from root name to its last recorded change, sorted by
+     *         name
+     * @throws IllegalStateException if this document has been closed
+     * @see #setRootActivityTracking(boolean)
+     */
+    public Map<String, JniRootActivity> getRootActivity() {
+        ensureNotClosed();
+        Map<String, JniRootActivity> result = new TreeMap<>();
+        for (JniRootActivity activity : nativeGetRootActivity(nativePtr)) {
+            result.put(activity.getRootName(), activity);
+        }
+        return Collections.unmodifiableMap(result);
+    }
+
+    /**
+     * Enables or disables transaction statistics for this document.
+     *
+     * <p>When enabled, each transaction opened afterwards is timed natively, and on
+     * commit its operation count and the size of the update it produced are
+     * measured. The measurements are added to the totals returned by
+     * {@link #getTransactionStatsSummary()}. Encoding the update adds work to every
+     * commit, so this is disabled by default.</p>
+     *
+     * @param enabled true to measure transactions
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void setTransactionStatsEnabled(boolean enabled) {
+        ensureNotClosed();
+        nativeSetTransactionStats(nativePtr, enabled);
+    }
+
+    /**
+     * Returns whether transaction statistics are enabled for this document.
+     *
+     * @return true if transactions are measured
+     * @throws IllegalStateException if this document has been closed
+     * @see #setTransactionStatsEnabled(boolean)
+     */
+    public boolean isTransactionStatsEnabled() {
+        ensureNotClosed();
+        return nativeIsTransactionStats(nativePtr);
+    }
+
+    /**
+     * Enables or disables logging of updates that fail to decode.
+     *
+     * <p>The exception thrown for an undecodable update always names the byte
+     * offset where decoding failed and shows the bytes around it. When logging is
+     * enabled, the whole update is also written to standard error as hex, so a
+     * corrupt payload can be captured for a bug report. Disabled by default.</p>
+     *
+     * @param enabled true to log undecodable updates
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void setDecodeFailureLogging(boolean enabled) {
+        ensureNotClosed();
+        nativeSetDecodeFailureLogging(nativePtr, enabled);
+    }
+
+    /**
+     * Returns whether updates that fail to decode are logged.
+     *
+     * @return true if undecodable updates are logged
+     * @throws IllegalStateException if this document has been closed
+     * @see #setDecodeFailureLogging(boolean)
+     */
+    public boolean isDecodeFailureLogging() {
+        ensureNotClosed();
+        return nativeIsDecodeFailureLogging(nativePtr);
+    }
+
+    /**
+     * Limits how much inserted text is copied into text change events.
+     *
+     * <p>Observers of {@link JniYText} and {@link JniYXmlText} receive every
+     * inserted string as a {@link JniYTextChange}, so a transaction that inserts
+     * megabytes of text copies all of it into Java for each observer. With a limit
+     * set, an insertion longer than {@code maxLength} UTF-16 units is delivered as
+     * a summary instead: its content is cut to the first {@code maxLength} units,
+     * {@link JniYTextChange#getLength()} still reports the full length,
+     * {@link JniYTextChange#isTruncated()} returns true and
+     * {@link JniYTextChange#getContentHash()} returns the
+     * {@link String#hashCode()} of the full text. A limit of 0 reduces every
+     * insertion to its length and hash. Embeds are not affected. There is no limit
+     * by default.</p>
+     *
+     * @param maxLength the longest insertion delivered in full, in UTF-16 units,
+     *        or -1 for no limit
+     * @throws IllegalArgumentException if maxLength is less than -1
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void setEventContentLimit(int maxLength) {
+        if (maxLength < -1) {
+            throw new IllegalArgumentException("maxLength must be -1 or non-negative: "
+                + maxLength);
+        }
+        ensureNotClosed();
+        nativeSetEventContentLimit(nativePtr, maxLength);
+    }
+
+    /**
+     * Returns the longest insertion delivered in full to text observers.
+     *
+     * @return the limit in UTF-16 units, or -1 if there is no limit
+     * @throws IllegalStateException if this document has been closed
+     * @see #setEventContentLimit(int)
+     */
+    public int getEventContentLimit() {
+        ensureNotClosed();
+        return nativeGetEventContentLimit(nativePtr);
+    }
+
+    /**
+     * Returns the running totals of transactions measured on this document.
+     *
+     * @return the totals since statistics were enabled or last reset
+     * @throws IllegalStateException if this document has been closed
+     * @see #setTransactionStatsEnabled(boolean)
+     */
+    public JniTransactionStats.Summary getTransactionStatsSummary() {
+        ensureNotClosed();
+        long[] values = nativeGetTransactionStats(nativePtr);
+        if (values == null) {
+            throw new RuntimeException("Failed to read transaction statistics");
+        }
+        return new JniTransactionStats.Summary(values);
+    }
+
+    /**
+     * Resets the running totals of measured transactions to zero.
+     *
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void resetTransactionStats() {
+        ensureNotClosed();
+        nativeResetTransactionStats(nativePtr);
+    }
+
+    /**
+     * Sets a listener that is invoked when a transaction takes at least the given
+     * time from opening to the end of its commit.
+     *
+     * <p>Setting a listener enables transaction statistics. Passing null removes the
+     * listener but leaves statistics enabled; call
+     * {@link #setTransactionStatsEnabled(boolean)} to turn them off.</p>
+     *
+     * @param thresholdNanos the minimum duration in nanoseconds that is reported
+     * @param listener the listener, or null to remove it
+     * @throws IllegalArgumentException if thresholdNanos is negative
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void setSlowTransactionListener(long thresholdNanos, SlowTransactionListener listener) {
+        if (thresholdNanos < 0) {
+            throw new IllegalArgumentException("Threshold cannot be negative");
+        }
+        ensureNotClosed();
+        this.slowTransactionThresholdNanos = thresholdNanos;
+        this.slowTransactionListener = listener;
+        if (listener != null) {
+            nativeSetTransactionStats(nativePtr, true);
+        }
+    }
+
+    /**
+     * Called by {@link JniYTransaction} after a measured transaction commits.
+     *
+     * @param stats the measurements of the transaction
+     */
+    void onTransactionMeasured(JniTransactionStats stats) {
+        SlowTransactionListener listener = slowTransactionListener;
+        if (listener == null || stats.getDurationNanos() < slowTransactionThresholdNanos) {
+            return;
+        }
+        try {
+            listener.onSlowTransaction(this, stats);
+        } catch (Exception e) {
+            observerErrorHandler.handleError(e, this);
+        }
+    }
+
+    /**
+     * Restricts the formatting attributes that insert and format operations may set.
+     *
+     * <p>Once set, any operation on this document's {@link JniYText} or
+     * {@link JniYXmlText} instances that sets a formatting attribute whose key is not
+     * in {@code keys} throws {@link IllegalArgumentException} and is not applied.
+     * The check runs natively, before the change enters the document. Removing an
+     * attribute (passing a null value) is always allowed.</p>
+     *
+     * <p>The allowlist only applies to local operations. Updates received from other
+     * peers are not checked.</p>
+     *
+     * @param keys the allowed attribute keys, or null to allow any key
+     * @throws IllegalArgumentException if keys contains null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void setAllowedFormattingAttributes(Set<String> keys) {
+        ensureNotClosed();
+        if (keys == null) {
+            nativeSetAllowedAttributes(nativePtr, null);
+            allowedFormattingAttributes = null;
+            return;
+        }
+        Set<String> copy = Collections.unmodifiableSet(new HashSet<>(keys));
+        if (copy.contains(null)) {
+            throw new IllegalArgumentException("Attribute keys cannot contain null");
+        }
+        nativeSetAllowedAttributes(nativePtr, copy.toArray(new String[0]));
+        allowedFormattingAttributes = copy;
+    }
+
+    /**
+     * Returns the formatting attribute allowlist of this document.
+     *
+     * @return an unmodifiable set of allowed keys, or null if any key is allowed
+     * @see #setAllowedFormattingAttributes(Set)
+     */
+    public Set<String> getAllowedFormattingAttributes() {
+        return allowedFormattingAttributes;
+    }
+
+    /**
+     * Checks whether the native library was built with XML support.
+     *
+     * <p>The {@code xml} cargo feature is enabled by default. Builds without it
+     * are smaller but cannot create {@link JniYXmlText}, {@link JniYXmlElement}
+     * or {@link JniYXmlFragment} instances.</p>
+     *
+     * @return true if XML types are available
+     */
+    public static boolean hasXmlSupport() {
+        return XmlSupport.AVAILABLE;
+    }
+
+    /**
+     * Switches tracing of native calls on or off.
+     *
+     * <p>When the native library is built with the {@code trace-jni} cargo feature
+     * and tracing is on, every native method writes a line to standard error when
+     * it is entered, naming the method, the calling thread and its handle
+     * arguments, and another when it returns, with the time spent. This is meant
+     * for debugging the order of calls across threads. Tracing is off by default
+     * and affects all documents.</p>
+     *
+     * @param enabled whether to trace native calls
+     * @return true if tracing is now on; always false if the library was built
+     *         without the {@code trace-jni} feature
+     */
+    public static boolean setNativeTracing(boolean enabled) {
+        return nativeSetNativeTracing(enabled);
+    }
+
+    /**
+     * Checks whether native calls are being traced.
+     *
+     * @return true if tracing is on
+     * @see #setNativeTracing(boolean)
+     */
+    public static boolean isNativeTracing() {
+        return nativeIsNativeTracing();
+    }
+
+    /**
+     * Registers the class loader used to find the library's callback classes.
+     *
+     * <p>Observer callbacks can run on threads that native code attached to the
+     * JVM, where class lookups go through the system class loader. When this
+     * library is loaded by another class loader, as in OSGi bundles, application
+     * servers or Spring Boot fat jars, that lookup fails. The classes used by
+     * callbacks are resolved once when the native library loads, and any other
+     * class the JVM cannot find is loaded with the registered loader, then with
+     * the loader of this class. The setting affects all documents.</p>
+     *
+     * @param loader the class loader to use, or null to use only the loader of
+     *        this class
+     */
+    public static void setCallbackClassLoader(ClassLoader loader) {
+        nativeSetCallbackClassLoader(loader);
+    }
+
+    /**
+     * Returns build and capability information about the loaded native library.
+     *
+     * <p>The map contains:</p>
+     * <ul>
+     *   <li>{@code version} ({@code String}): version of the native crate</li>
+     *   <li>{@code yrsVersion} ({@code String}): version of yrs it was built against</li>
+     *   <li>{@code features} ({@code List<String>}): enabled cargo features, e.g. {@code xml}</li>
+     *   <li>{@code updateEncodings} ({@code List<String>}): supported update encodings,
+     *       e.g. {@code v1}</li>
+     * </ul>
+     *
+     * <p>Applications can check this at startup to detect a native library that does
+     * not match the Java classes, rather than failing later on a missing method.</p>
+     *
+     * @return an unmodifiable map of version and capability information
+     */
+    public static Map<String, Object> getNativeVersionInfo() {
+        return Collections.unmodifiableMap(nativeGetVersionInfo());
+    }
+
+    /**
+     * Throws if the native library was built without XML support.
+     *
+     * @throws UnsupportedOperationException if XML types are unavailable
+     */
+    private static void requireXmlSupport() {
+        if (!XmlSupport.AVAILABLE) {
+            throw new UnsupportedOperationException(
+                "XML types are not available: the native library was built without the 'xml' feature");
+        }
+    }
+
+    /**
+     * Holder for the XML capability flag, queried once on first use.
+     */
+    private static final class XmlSupport {
+        static final boolean AVAILABLE = nativeHasXmlSupport();
+    }
+
+    /**
+     * Encodes the current state of the document as a binary update within an existing transaction.
+     *
+     * <p>This method captures the entire state of the document and returns it
+     * as a byte array that can be transmitted to other clients or stored for
+     * later use.</p>
+     *
+     * <p>The returned byte array can be applied to another YDoc instance using
+     * {@link #applyUpdate(byte[])} to synchronize their states.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return a byte array containing the encoded document state
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if encoding fails
+     */
+    @Override
+    public byte[] encodeStateAsUpdate(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        byte[] result = nativeEncodeStateAsUpdateWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        if (result == null) {
+            throw new RuntimeException("Failed to encode state as update");
+        }
+        return result;
+    }
+
+    /**
+     * Encodes the current state of the document as a binary update (creates implicit transaction).
+     *
+     * <p>This method captures the entire state of the document and returns it
+     * as a byte array that can be transmitted to other clients or stored for
+     * later use.</p>
+     *
+     * <p>The returned byte array can be applied to another YDoc instance using
+     * {@link #applyUpdate(byte[])} to synchronize their states.</p>
+     *
+     * @return a byte array containing the encoded document state
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if encoding fails
+     */
+    @Override
+    public byte[] encodeStateAsUpdate() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            byte[] result = nativeEncodeStateAsUpdateWithTxn(nativePtr, activeTxn.getNativePtr());
+            if (result == null) {
+                throw new RuntimeException("Failed to encode state as update");
+            }
+            return result;
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            byte[] result = nativeEncodeStateAsUpdateWithTxn(nativePtr, txn.getNativePtr());
+            if (result == null) {
+                throw new RuntimeException("Failed to encode state as update");
+            }
+            return result;
+        }
+    }
+
+    /**
+     * Applies a binary update to this document within an existing transaction.
+     *
+     * <p>This method merges changes from another document into this one. The update
+     * is typically obtained by calling {@link #encodeStateAsUpdate()} on another
+     * YDoc instance.</p>
+     *
+     * <p>Updates are idempotent - applying the same update multiple times has the
+     * same effect as applying it once.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param update the binary update to apply
+     * @throws IllegalArgumentException if txn or update is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    @Override
+    public void applyUpdate(YTransaction txn, byte[] update) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        nativeApplyUpdateWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr(), update);
+    }
+
+    /**
+     * Applies a binary update to this document (creates implicit transaction).
+     *
+     * <p>This method merges changes from another document into this one. The update
+     * is typically obtained by calling {@link #encodeStateAsUpdate()} on another
+     * YDoc instance.</p>
+     *
+     * <p>Updates are idempotent - applying the same update multiple times has the
+     * same effect as applying it once.</p>
+     *
+     * @param update the binary update to apply
+     * @throws IllegalStateException if this document has been closed
+     * @throws IllegalArgumentException if update is null
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    @Override
+    public void applyUpdate(byte[] update) {
+        ensureNotClosed();
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            nativeApplyUpdateWithTxn(nativePtr, activeTxn.getNativePtr(), update);
+        } else {
+            try (JniYTransaction txn = beginTransactionInternal()) {
+                nativeApplyUpdateWithTxn(nativePtr, txn.getNativePtr(), update);
+            }
+        }
+    }
+
+    /**
+     * Applies a binary update to this document within an existing transaction and
+     * reports what it did.
+     *
+     * <p>Works like {@link #applyUpdate(YTransaction, byte[])}, and also tells
+     * whether the update was applied, is partly waiting for updates it depends on,
+     * or was already contained in the document.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param update the binary update to apply
+     * @return the outcome of applying the update
+     * @throws IllegalArgumentException if txn or update is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    public JniApplyResult applyUpdateWithResult(YTransaction txn, byte[] update) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        return nativeApplyUpdateWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            update);
+    }
+
+    /**
+     * Applies a binary update to this document and reports what it did (creates
+     * implicit transaction).
+     *
+     * @param update the binary update to apply
+     * @return the outcome of applying the update
+     * @throws IllegalStateException if this document has been closed
+     * @throws IllegalArgumentException if update is null
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     * @see #applyUpdateWithResult(YTransaction, byte[])
+     */
+    public JniApplyResult applyUpdateWithResult(byte[] update) {
+        ensureNotClosed();
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return applyUpdateWithResult(activeTxn, update);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return applyUpdateWithResult(txn, update);
+        }
+    }
+
+    /**
+     * Encodes the state of this document and of its subdocuments as one deep update
+     * within an existing transaction.
+     *
+     * <p>A regular update only records where subdocuments are, since their content
+     * is kept in their own stores. A deep update also carries the full state of
+     * every subdocument that should load, keyed by GUID, and of their subdocuments
+     * in turn, so a whole tree of documents can be stored as one blob and restored
+     * with {@link #applyUpdateDeep(byte[])}. It is not a Yjs update and cannot be
+     * passed to {@link #applyUpdate(byte[])}.</p>
+     *
+     * <p>The subdocuments are read in transactions of their own.</p>
+     *
+     * @param txn The transaction to use for this document
+     * @return the deep update
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed, or if this
+     *         thread holds a transaction on one of the subdocuments
+     */
+    public byte[] encodeStateAsUpdateDeep(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeEncodeStateAsUpdateDeepWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Encodes the state of this document and of its subdocuments as one deep update
+     * (creates implicit transaction).
+     *
+     * @return the deep update
+     * @throws IllegalStateException if this document has been closed, or if this
+     *         thread holds a transaction on one of the subdocuments
+     * @see #encodeStateAsUpdateDeep(YTransaction)
+     */
+    public byte[] encodeStateAsUpdateDeep() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return encodeStateAsUpdateDeep(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return encodeStateAsUpdateDeep(txn);
+        }
+    }
+
+    /**
+     * Applies a deep update produced by {@link #encodeStateAsUpdateDeep()} to this
+     * document and its subdocuments.
+     *
+     * <p>The document's own update is applied and committed first. Each subdocument
+     * in the deep update is then looked up by GUID among this document's
+     * subdocuments and updated in a transaction of its own, so this cannot be
+     * called while this thread holds a transaction on the document.</p>
+     *
+     * <p>Nothing is applied if the deep update cannot be decoded. If it names a
+     * subdocument this document does not have, the updates applied before that
+     * point are kept.</p>
+     *
+     * @param update the deep update
+     * @throws IllegalArgumentException if update is null
+     * @throws IllegalStateException if this document has been closed or is
+     *         read-only, or if this thread holds a transaction on the document or
+     *         one of its subdocuments
+     * @throws RuntimeException if the deep update is invalid or cannot be applied
+     */
+    public void applyUpdateDeep(byte[] update) {
+        ensureNotClosed();
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        nativeApplyUpdateDeep(nativePtr, update);
+    }
+
+    /**
+     * Applies a binary update to this document in a new transaction carrying an origin.
+     *
+     * <p>Use an origin naming the peer the update came from, so that a
+     * {@link JniYUndoManager} tracking local origins does not capture it.</p>
+     *
+     * @param update the binary update to apply
+     * @param origin the origin of the transaction
+     * @throws IllegalArgumentException if update or origin is null
+     * @throws IllegalStateException if this document has been closed, or if this
+     *         thread already has a transaction open, since its origin cannot change
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     * @see #beginTransaction(String)
+     */
+    public void applyUpdate(byte[] update, String origin) {
+        ensureNotClosed();
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        if (getActiveTransaction() != null) {
+            throw new IllegalStateException(
+                "Cannot apply an update with an origin while a transaction is open");
+        }
+        try (JniYTransaction txn = beginTransaction(origin)) {
+            nativeApplyUpdateWithTxn(nativePtr, txn.getNativePtr(), update);
+        }
+    }
+
+    /**
+     * Applies the parts of a binary update that a permission check allows, within an
+     * existing transaction.
+     *
+     * <p>The update is decoded natively and each change is traced to the root type it
+     * belongs to, including changes to nested types and deletions. {@code allowRoot}
+     * is called once with the name of each root the update touches. Changes under
+     * denied roots are dropped and the rest is applied, which allows field-level
+     * access control when each protected field is a separate root type.</p>
+     *
+     * <p>Dropped content is recorded as garbage collected, so later updates from the
+     * same peer still apply, but this document will not receive that content even if
+     * the permission changes later. Changes that cannot be traced to a root, because
+     * they depend on updates this document has not received, are also dropped; see
+     * {@link JniFilteredUpdate#hasDroppedUnresolved()}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param update the binary update to apply
+     * @param allowRoot returns true for root names whose changes may be applied
+     * @return which roots were applied and which were dropped
+     * @throws IllegalArgumentException if txn, update or allowRoot is null
+     * @throws IllegalStateException if this document has been closed or is read-only
+     * @throws RuntimeException if the update is invalid or cannot be applied, or
+     *         if allowRoot throws
+     */
+    public JniFilteredUpdate applyUpdateFiltered(YTransaction txn, byte[] update,
+                                                 Predicate<String> allowRoot) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        if (allowRoot == null) {
+            throw new IllegalArgumentException("Permission check cannot be null");
+        }
+        return nativeApplyUpdateFilteredWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), update, allowRoot);
+    }
+
+    /**
+     * Applies the parts of a binary update that a permission check allows (creates
+     * implicit transaction).
+     *
+     * @param update the binary update to apply
+     * @param allowRoot returns true for root names whose changes may be applied
+     * @return which roots were applied and which were dropped
+     * @throws IllegalArgumentException if update or allowRoot is null
+     * @throws IllegalStateException if this document has been closed or is read-only
+     * @throws RuntimeException if the update is invalid or cannot be applied, or
+     *         if allowRoot throws
+     * @see #applyUpdateFiltered(YTransaction, byte[], Predicate)
+     */
+    public JniFilteredUpdate applyUpdateFiltered(byte[] update, Predicate<String> allowRoot) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return applyUpdateFiltered(activeTxn, update, allowRoot);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return applyUpdateFiltered(txn, update, allowRoot);
+        }
+    }
+
+    /**
+     * Handles one y-websocket protocol message within an existing transaction.
+     *
+     * <p>The message is the binary payload of one websocket frame from a y-websocket
+     * (or compatible) server or client: a message type, and for sync messages a sync
+     * step, followed by the payload. Sync step 1 is answered with a reply carrying
+     * the changes the peer is missing, which the caller sends back unchanged. Sync
+     * step 2 and update messages are applied to this document. Awareness, auth and
+     * other messages are returned without touching the document.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param message the framed message
+     * @return the kind of message and any reply to send
+     * @throws IllegalArgumentException if txn or message is null
+     * @throws IllegalStateException if this document has been closed, or if the
+     *         message carries an update and this document is read-only
+     * @throws RuntimeException if the message or the update it carries is invalid
+     */
+    public JniSyncMessage applySyncMessage(YTransaction txn, byte[] message) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (message == null) {
+            throw new IllegalArgumentException("Message cannot be null");
+        }
+        return nativeApplySyncMessageWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), message);
+    }
+
+    /**
+     * Handles one y-websocket protocol message (creates implicit transaction).
+     *
+     * @param message the framed message
+     * @return the kind of message and any reply to send
+     * @throws IllegalArgumentException if message is null
+     * @throws IllegalStateException if this document has been closed, or if the
+     *         message carries an update and this document is read-only
+     * @throws RuntimeException if the message or the update it carries is invalid
+     * @see #applySyncMessage(YTransaction, byte[])
+     */
+    public JniSyncMessage applySyncMessage(byte[] message) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return applySyncMessage(activeTxn, message);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return applySyncMessage(txn, message);
+        }
+    }
+
+    /**
+     * Encodes a y-websocket sync step 1 message within an existing transaction.
+     *
+     * <p>Sending this message to a peer asks it for the changes this document is
+     * missing; the peer answers with sync step 2.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return the framed message carrying this document's state vector
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public byte[] encodeSyncStep1(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeEncodeSyncStep1WithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Encodes a y-websocket sync step 1 message (creates implicit transaction).
+     *
+     * @return the framed message carrying this document's state vector
+     * @throws IllegalStateException if this document has been closed
+     * @see #encodeSyncStep1(YTransaction)
+     */
+    public byte[] encodeSyncStep1() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return encodeSyncStep1(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return encodeSyncStep1(txn);
+        }
+    }
+
+    /**
+     * Frames an update as a y-websocket sync update message.
+     *
+     * <p>Use this to broadcast updates from {@link #observeUpdateV1} to peers.</p>
+     *
+     * @param update a v1 update
+     * @return the framed message
+     * @throws IllegalArgumentException if update is null
+     */
+    public static byte[] encodeSyncUpdate(byte[] update) {
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        return nativeEncodeSyncUpdate(update);
+    }
+
+    /**
+     * Returns the clock of each client known to this document within an existing
+     * transaction.
+     *
+     * <p>This is the decoded form of {@link #encodeStateVector(YTransaction)}: each
+     * clock is the number of clock units received from that client, so it only grows as
+     * the client makes changes.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return an unmodifiable map from client ID to clock, sorted by client ID
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public Map<Long, Long> getStateVectorMap(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        long[] entries = nativeGetStateVectorEntriesWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        Map<Long, Long> result = new TreeMap<>();
+        for (int i = 0; i + 1 < entries.length; i += 2) {
+            result.put(entries[i], entries[i + 1]);
+        }
+        return Collections.unmodifiableMap(result);
+    }
+
+    /**
+     * Returns the clock of each client known to this document (creates implicit
+     * transaction).
+     *
+     * @return an unmodifiable map from client ID to clock, sorted by client ID
+     * @throws IllegalStateException if this document has been closed
+     * @see #getStateVectorMap(YTransaction)
+     */
+    public Map<Long, Long> getStateVectorMap() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return getStateVectorMap(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return getStateVectorMap(txn);
+        }
+    }
+
+    /**
+     * Checks if this document is holding back updates within an existing transaction.
+     *
+     * <p>An update that refers to content this document has not received cannot be
+     * fully integrated. The remainder is kept until the missing updates are applied;
+     * see {@link #getPendingState(YTransaction)}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return true if some applied content is waiting for missing updates
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public boolean hasMissingUpdates(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeHasMissingUpdatesWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Checks if this document is holding back updates (creates implicit transaction).
+     *
+     * @return true if some applied content is waiting for missing updates
+     * @throws IllegalStateException if this document has been closed
+     * @see #hasMissingUpdates(YTransaction)
+     */
+    public boolean hasMissingUpdates() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return hasMissingUpdates(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return hasMissingUpdates(txn);
+        }
+    }
+
+    /**
+     * Describes the content this document is holding back within an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the pending content, or null if nothing is pending
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public JniPendingState getPendingState(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        long txnPtr = ((JniYTransaction) txn).getNativePtr();
+        long[] stats = nativeGetPendingStatsWithTxn(nativePtr, txnPtr);
+        if (stats == null) {
+            return null;
+        }
+        byte[] missing = nativeGetMissingStateVectorWithTxn(nativePtr, txnPtr);
+        return new JniPendingState(stats[0], stats[1], stats[2], missing);
+    }
+
+    /**
+     * Describes the content this document is holding back (creates implicit transaction).
+     *
+     * @return the pending content, or null if nothing is pending
+     * @throws IllegalStateException if this document has been closed
+     * @see #getPendingState(YTransaction)
+     */
+    public JniPendingState getPendingState() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return getPendingState(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return getPendingState(txn);
+        }
+    }
+
+    /**
+     * Collects block counts of this document's store within an existing transaction.
+     *
+     * <p>Use this to watch for documents that accumulate tombstones or blocks. The
+     * counts are derived from the full state of the document, so this costs about
+     * as much as {@link #encodeStateAsUpdate(YTransaction)}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return the store statistics
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public JniStoreStats getStoreStats(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return new JniStoreStats(
+            nativeGetStoreStatsWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr()));
+    }
+
+    /**
+     * Collects block counts of this document's store (creates implicit transaction).
+     *
+     * @return the store statistics
+     * @throws IllegalStateException if this document has been closed
+     * @see #getStoreStats(YTransaction)
+     */
+    public JniStoreStats getStoreStats() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return getStoreStats(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return getStoreStats(txn);
+        }
+    }
+
+    /**
+     * Applies an update once this document has everything it builds on, within an
+     * existing transaction.
+     *
+     * <p>Unlike {@link #applyUpdate(YTransaction, byte[])}, which integrates as much of
+     * an early update as it can and keeps the rest pending, this method holds the whole
+     * update back in a staging area until this document has every clock the update
+     * starts from. Transports can pass updates in the order they arrive.</p>
+     *
+     * <p>Whenever an update is applied, staged updates that became ready are applied
+     * as well, and the {@linkplain #setUpdateFlushListener(UpdateFlushListener) flush
+     * listener} is notified. Staged updates are kept in native memory until they are
+     * applied, {@linkplain #clearQueuedUpdates() cleared}, or the document is closed.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param update the update to apply (encoded as v1)
+     * @return true if the update was applied, false if it was staged
+     * @throws IllegalArgumentException if txn or update is null
+     * @throws IllegalStateException if this document has been closed or is read-only
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     */
+    public boolean queueUpdate(YTransaction txn, byte[] update) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        long[] outcome = queueUpdateInternal((JniYTransaction) txn, update);
+        notifyUpdateFlush(outcome);
+        return outcome[0] != 0;
+    }
+
+    /**
+     * Applies an update once this document has everything it builds on (creates
+     * implicit transaction).
+     *
+     * @param update the update to apply (encoded as v1)
+     * @return true if the update was applied, false if it was staged
+     * @throws IllegalArgumentException if update is null
+     * @throws IllegalStateException if this document has been closed or is read-only
+     * @throws RuntimeException if the update is invalid or cannot be applied
+     * @see #queueUpdate(YTransaction, byte[])
+     */
+    public boolean queueUpdate(byte[] update) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return queueUpdate(activeTxn, update);
+        }
+        long[] outcome;
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            outcome = queueUpdateInternal(txn, update);
+        }
+        notifyUpdateFlush(outcome);
+        return outcome[0] != 0;
+    }
+
+    private long[] queueUpdateInternal(JniYTransaction txn, byte[] update) {
+        if (update == null) {
+            throw new IllegalArgumentException("Update cannot be null");
+        }
+        return nativeQueueUpdateWithTxn(nativePtr, txn.getNativePtr(), update);
+    }
+
+    private void notifyUpdateFlush(long[] outcome) {
+        UpdateFlushListener listener = updateFlushListener;
+        if (listener == null || outcome[1] == 0) {
+            return;
+        }
+        try {
+            listener.onFlush(this, (int) outcome[1], (int) outcome[2]);
+        } catch (Exception e) {
+            observerErrorHandler.handleError(e, this);
+        }
+    }
+
+    /**
+     * Returns the number of updates staged by {@link #queueUpdate(byte[])}.
+     *
+     * @return the number of staged updates
+     * @throws IllegalStateException if this document has been closed
+     */
+    public int getQueuedUpdateCount() {
+        ensureNotClosed();
+        return nativeGetQueuedUpdateCount(nativePtr);
+    }
+
+    /**
+     * Discards the updates staged by {@link #queueUpdate(byte[])}.
+     *
+     * @return the number of updates discarded
+     * @throws IllegalStateException if this document has been closed
+     */
+    public int clearQueuedUpdates() {
+        ensureNotClosed();
+        return nativeClearQueuedUpdates(nativePtr);
+    }
+
+    /**
+     * Adds a prelim to a root type within an existing transaction.
+     *
+     * <p>The root type is chosen by the prelim: a map prelim's entries are set on
+     * the root map, an array prelim's items are appended to the root array, a text
+     * prelim's chunks are appended to the root text, and an XML fragment prelim's
+     * children are appended to the root XML fragment.</p>
+     *
+     * <p>The whole prelim is read and checked before the document is changed, so an
+     * invalid value or disallowed formatting attribute anywhere in the tree leaves
+     * the document as it was.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param rootName the name of the root type
+     * @param prelim the content to add
+     * @throws IllegalArgumentException if any argument is null, the prelim is not a
+     *         map, array, text or XML fragment, or the tree holds an unsupported
+     *         value or a formatting attribute the document does not allow
+     * @throws IllegalStateException if this document has been closed or is read-only
+     * @see JniPrelim
+     */
+    public void commitPrelim(YTransaction txn, String rootName, JniPrelim prelim) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (rootName == null) {
+            throw new IllegalArgumentException("Root name cannot be null");
+        }
+        if (prelim == null) {
+            throw new IllegalArgumentException("Prelim cannot be null");
+        }
+        if (!prelim.isRootKind()) {
+            throw new IllegalArgumentException(
+                "Only map, array, text and XML fragment prelims can be committed to a root");
+        }
+        nativeCommitPrelimWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr(), rootName, prelim);
+    }
+
+    /**
+     * Adds a prelim to a root type (creates implicit transaction).
+     *
+     * @param rootName the name of the root type
+     * @param prelim the content to add
+     * @throws IllegalArgumentException if any argument is null, the prelim is not a
+     *         map, array, text or XML fragment, or the tree holds an unsupported
+     *         value or a formatting attribute the document does not allow
+     * @throws IllegalStateException if this document has been closed or is read-only
+     * @see #commitPrelim(YTransaction, String, JniPrelim)
+     */
+    public void commitPrelim(String rootName, JniPrelim prelim) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            commitPrelim(activeTxn, rootName, prelim);
+            return;
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            commitPrelim(txn, rootName, prelim);
+        }
+    }
+
+    /**
+     * Queues a batch of operations to run in a single transaction on this document's
+     * batch worker thread.
+     *
+     * <p>The operations are converted to native form before this method returns, so
+     * many threads can submit batches without opening transactions themselves. Batches
+     * run one at a time in the order they were submitted; each waits for any
+     * transaction open on another thread to close. After the batch's transaction has
+     * been committed, the callback receives the update it produced.</p>
+     *
+     * <p>A thread must not wait for a batch to complete while it holds a transaction on
+     * this document, since the batch cannot start until that transaction closes.
+     * Batches already queued when the document is closed still run.</p>
+     *
+     * @param ops the operations to run, in order
+     * @param callback receives the result, or null if no result is needed
+     * @throws IllegalArgumentException if ops is null or contains null
+     * @throws IllegalStateException if this document has been closed or is read-only
+     */
+    public void submitBatch(List<JniBatchOp> ops, BatchCallback callback) {
+        ensureNotClosed();
+        if (ops == null) {
+            throw new IllegalArgumentException("Operations cannot be null");
+        }
+        int count = ops.size();
+        int[] kinds = new int[count];
+        String[] roots = new String[count];
+        int[] positions = new int[2 * count];
+        String[] strings = new String[count];
+        Object[] values = new Object[count];
+        for (int i = 0; i < count; i++) {
+            JniBatchOp op = ops.get(i);
+            if (op == null) {
+                throw new IllegalArgumentException("Operation cannot be null");
+            }
+            kinds[i] = op.kind;
+            roots[i] = op.root;
+            positions[2 * i] = op.index;
+            positions[2 * i + 1] = op.length;
+            strings[i] = op.string;
+            values[i] = op.value;
+        }
+        nativeSubmitBatch(nativePtr, kinds, roots, positions, strings, values,
+            new BatchCompletion(this, callback));
+    }
+
+    /**
+     * Receives the outcome of a batch from the native worker thread.
+     */
+    private static final class BatchCompletion {
+
+        private final JniYDoc doc;
+        private final BatchCallback callback;
+
+        BatchCompletion(JniYDoc doc, BatchCallback callback) {
+            this.doc = doc;
+            this.callback = callback;
+        }
+
+        @SuppressWarnings("unused") // Called from native code
+        void complete(byte[] update, int applied, String error) {
+            // The batch's observers ran on this thread while it committed
+            doc.runDeferredActions();
+            if (callback == null) {
+                return;
+            }
+            try {
+                callback.onComplete(doc, new JniBatchResult(update, applied, error));
+            } catch (Exception e) {
+                doc.observerErrorHandler.handleError(e, doc);
+            }
+        }
+    }
+
+    /**
+     * Sets a listener that is invoked when staged updates are applied.
+     *
+     * @param listener the listener, or null to remove it
+     * @throws IllegalStateException if this document has been closed
+     * @see #queueUpdate(YTransaction, byte[])
+     */
+    public void setUpdateFlushListener(UpdateFlushListener listener) {
+        ensureNotClosed();
+        this.updateFlushListener = listener;
+    }
+
+    /**
+     * Encodes the current state vector of this document within an existing transaction.
+     *
+     * <p>A state vector is a compact representation of all known blocks inserted and
+     * integrated into this document. It serves as a logical timestamp describing which
+     * updates this document has observed.</p>
+     *
+     * <p>State vectors are used to generate differential updates - by sending a state
+     * vector to a remote peer, they can determine which changes this document has not
+     * yet seen and send only those changes.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return a byte array containing the encoded state vector
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if encoding fails
+     */
+    @Override
+    public byte[] encodeStateVector(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        byte[] result = nativeEncodeStateVectorWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        if (result == null) {
+            throw new RuntimeException("Failed to encode state vector");
+        }
+        return result;
+    }
+
+    /**
+     * Encodes the current state vector of this document (creates implicit transaction).
+     *
+     * <p>A state vector is a compact representation of all known blocks inserted and
+     * integrated into this document. It serves as a logical timestamp describing which
+     * updates this document has observed.</p>
+     *
+     * <p>State vectors are used to generate differential updates - by sending a state
+     * vector to a remote peer, they can determine which changes this document has not
+     * yet seen and send only those changes.</p>
+     *
+     * @return a byte array containing the encoded state vector
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if encoding fails
+     */
+    @Override
+    public byte[] encodeStateVector() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            byte[] result = nativeEncodeStateVectorWithTxn(nativePtr, activeTxn.getNativePtr());
+            if (result == null) {
+                throw new RuntimeException("Failed to encode state vector");
+            }
+            return result;
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            byte[] result = nativeEncodeStateVectorWithTxn(nativePtr, txn.getNativePtr());
+            if (result == null) {
+                throw new RuntimeException("Failed to encode state vector");
+            }
+            return result;
+        }
+    }
+
+    /**
+     * Encodes a snapshot of this document within an existing transaction.
+     *
+     * <p>A snapshot records the state vector and delete set at a point in time.
+     * It can be passed to {@link JniYXmlText#diffSnapshots(byte[], byte[])} to
+     * render text as of that point or to attribute later changes.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return a byte array containing the encoded snapshot
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if encoding fails
+     */
+    public byte[] encodeSnapshot(YTransaction txn) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        byte[] result = nativeEncodeSnapshotWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        if (result == null) {
+            throw new RuntimeException("Failed to encode snapshot");
+        }
+        return result;
+    }
+
+    /**
+     * Encodes a snapshot of this document (creates implicit transaction).
+     *
+     * @return a byte array containing the encoded snapshot
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if encoding fails
+     * @see #encodeSnapshot(YTransaction)
+     */
+    public byte[] encodeSnapshot() {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return encodeSnapshot(activeTxn);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return encodeSnapshot(txn);
+        }
+    }
+
+    /**
+     * Encodes this document as it was at a snapshot, within an existing transaction.
+     *
+     * <p>The result is an update holding every change the snapshot includes.
+     * Applying it to an empty document reconstructs the version the snapshot was
+     * taken at, for example to serve a read-only view of an earlier version.</p>
+     *
+     * <p>Requires a document created with {@link #withoutGarbageCollection()}, since
+     * otherwise the content of items deleted after the snapshot is no longer
+     * available.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param snapshot a snapshot from {@link #encodeSnapshot()}
+     * @return the update reconstructing the document at the snapshot
+     * @throws IllegalArgumentException if txn or snapshot is null, or the snapshot
+     *         is invalid
+     * @throws IllegalStateException if this document has been closed or uses
+     *         garbage collection
+     */
+    public byte[] encodeStateFromSnapshot(YTransaction txn, byte[] snapshot) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (snapshot == null) {
+            throw new IllegalArgumentException("Snapshot cannot be null");
+        }
+        return nativeEncodeStateFromSnapshotWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), snapshot);
+    }
+
+    /**
+     * Encodes this document as it was at a snapshot (creates implicit transaction).
+     *
+     * @param snapshot a snapshot from {@link #encodeSnapshot()}
+     * @return the update reconstructing the document at the snapshot
+     * @throws IllegalArgumentException if snapshot is null or invalid
+     * @throws IllegalStateException if this document has been closed or uses
+     *         garbage collection
+     * @see #encodeStateFromSnapshot(YTransaction, byte[])
+     */
+    public byte[] encodeStateFromSnapshot(byte[] snapshot) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return encodeStateFromSnapshot(activeTxn, snapshot);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return encodeStateFromSnapshot(txn, snapshot);
+        }
+    }
+
+    /**
+     * Exports every root type of this document as a tree within an existing transaction.
+     *
+     * <p>Each shared type becomes a {@link JniTreeNode}; plain values become
+     * {@code String}, {@code Double}, {@code Long}, {@code Boolean}, {@code byte[]},
+     * {@code List} or {@code Map}. Shared types more than {@code maxDepth} levels
+     * below a root are returned as nodes with {@link JniTreeNode#isTruncated()} set
+     * and no content. A depth of 0 returns only the kind of each root.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param maxDepth the number of shared-type levels to expand below each root
+     * @return the root nodes keyed by root name, in name order
+     * @throws IllegalArgumentException if txn is null or maxDepth is negative
+     * @throws IllegalStateException if this document has been closed
+     */
+    public Map<String, JniTreeNode> exportTree(YTransaction txn, int maxDepth) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (maxDepth < 0) {
+            throw new IllegalArgumentException("maxDepth must not be negative");
+        }
+        return nativeExportTreeWithTxn(nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            maxDepth);
+    }
+
+    /**
+     * Exports every root type of this document as a tree (creates implicit transaction).
+     *
+     * @param maxDepth the number of shared-type levels to expand below each root
+     * @return the root nodes keyed by root name, in name order
+     * @throws IllegalArgumentException if maxDepth is negative
+     * @throws IllegalStateException if this document has been closed
+     * @see #exportTree(YTransaction, int)
+     */
+    public Map<String, JniTreeNode> exportTree(int maxDepth) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return exportTree(activeTxn, maxDepth);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return exportTree(txn, maxDepth);
+        }
+    }
+
+    /**
+     * Checks this document against schema rules within an existing transaction.
+     *
+     * <p>The check runs natively and reports every broken rule, so it can run after
+     * applying updates from untrusted clients; see {@link JniSchemaRule} for an
+     * example. The document is not changed, whatever the outcome.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param rules the rules to check
+     * @return the violations in rule order, or an empty list if the document conforms
+     * @throws IllegalArgumentException if txn or rules is null, or rules contains null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public List<JniSchemaViolation> validate(YTransaction txn, List<JniSchemaRule> rules) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (rules == null) {
+            throw new IllegalArgumentException("Rules cannot be null");
+        }
+        int count = rules.size();
+        int[] kinds = new int[count];
+        String[] roots = new String[count];
+        String[] keys = new String[count];
+        int[] types = new int[count];
+        for (int i = 0; i < count; i++) {
+            JniSchemaRule rule = rules.get(i);
+            if (rule == null) {
+                throw new IllegalArgumentException("Rule cannot be null");
+            }
+            kinds[i] = rule.kind;
+            roots[i] = rule.root;
+            keys[i] = rule.key;
+            types[i] = rule.type;
+        }
+        String[] parts = nativeValidateWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), kinds, roots, keys, types);
+        List<JniSchemaViolation> violations = new ArrayList<>(parts.length / 2);
+        for (int i = 0; i < parts.length; i += 2) {
+            violations.add(new JniSchemaViolation(parts[i], parts[i + 1]));
+        }
+        return violations;
+    }
+
+    /**
+     * Checks this document against schema rules (creates implicit transaction).
+     *
+     * @param rules the rules to check
+     * @return the violations in rule order, or an empty list if the document conforms
+     * @throws IllegalArgumentException if rules is null or contains null
+     * @throws IllegalStateException if this document has been closed
+     * @see #validate(YTransaction, List)
+     */
+    public List<JniSchemaViolation> validate(List<JniSchemaRule> rules) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return validate(activeTxn, rules);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return validate(txn, rules);
+        }
+    }
+
+    /**
+     * Encodes a differential update containing only changes not yet observed by the
+     * remote peer within an existing transaction.
+     *
+     * <p>This method generates an update that includes only the changes that are present
+     * in this document but not reflected in the provided state vector. This is more
+     * efficient than sending the entire document state when synchronizing with a peer
+     * that already has some of the data.</p>
+     *
+     * <p>Typical usage:</p>
+     * <pre>{@code
+     * // Remote peer sends their state vector
+     * byte[] remoteStateVector = ...;
      *
      * try (YTransaction txn = doc.beginTransaction()) {
      *     // Generate differential update
@@ -433,52 +2094,324 @@ public class JniYDoc implements YDoc, JniYObservable {
     }
 
     /**
-     * Encodes a differential update containing only changes not yet observed by the
-     * remote peer (creates implicit transaction).
-     *
-     * <p>This method generates an update that includes only the changes that are present
-     * in this document but not reflected in the provided state vector. This is more
-     * efficient than sending the entire document state when synchronizing with a peer
-     * that already has some of the data.</p>
-     *
-     * <p>Typical usage:</p>
-     * <pre>{@code
-     * // Remote peer sends their state vector
-     * byte[] remoteStateVector = ...;
+     * Encodes a differential update containing only changes not yet observed by the
+     * remote peer (creates implicit transaction).
+     *
+     * <p>This method generates an update that includes only the changes that are present
+     * in this document but not reflected in the provided state vector. This is more
+     * efficient than sending the entire document state when synchronizing with a peer
+     * that already has some of the data.</p>
+     *
+     * <p>Typical usage:</p>
+     * <pre>{@code
+     * // Remote peer sends their state vector
+     * byte[] remoteStateVector = ...;
+     *
+     * // Generate differential update
+     * byte[] diff = doc.encodeDiff(remoteStateVector);
+     *
+     * // Send diff to remote peer
+     * // Remote peer applies it with applyUpdate(diff)
+     * }</pre>
+     *
+     * @param stateVector the state vector from the remote peer
+     * @return a byte array containing the differential update
+     * @throws IllegalStateException if this document has been closed
+     * @throws IllegalArgumentException if stateVector is null
+     * @throws RuntimeException if encoding fails
+     */
+    @Override
+    public byte[] encodeDiff(byte[] stateVector) {
+        ensureNotClosed();
+        if (stateVector == null) {
+            throw new IllegalArgumentException("State vector cannot be null");
+        }
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            byte[] result = nativeEncodeDiffWithTxn(nativePtr, activeTxn.getNativePtr(), stateVector);
+            if (result == null) {
+                throw new RuntimeException("Failed to encode differential update");
+            }
+            return result;
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            byte[] result = nativeEncodeDiffWithTxn(nativePtr, txn.getNativePtr(), stateVector);
+            if (result == null) {
+                throw new RuntimeException("Failed to encode differential update");
+            }
+            return result;
+        }
+    }
+
+    /**
+     * Returns the size in bytes of the update {@link #encodeDiff(YTransaction, byte[])}
+     * would return, without building it.
+     *
+     * <p>The diff is encoded into a byte counter instead of a buffer, so the size is
+     * exact but nothing is allocated or copied to Java. A transport can use it to
+     * decide between sending a diff and asking the peer to load a snapshot.</p>
+     *
+     * @param txn the transaction to use for this operation
+     * @param stateVector the state vector from the remote peer
+     * @return the length of the differential update in bytes
+     * @throws IllegalStateException if this document has been closed
+     * @throws IllegalArgumentException if txn or stateVector is null
+     * @throws RuntimeException if the state vector cannot be decoded
+     */
+    public long estimateUpdateSize(YTransaction txn, byte[] stateVector) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (stateVector == null) {
+            throw new IllegalArgumentException("State vector cannot be null");
+        }
+        return nativeEstimateUpdateSizeWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), stateVector);
+    }
+
+    /**
+     * Returns the size in bytes of the update {@link #encodeDiff(byte[])} would return,
+     * without building it (creates implicit transaction).
+     *
+     * @param stateVector the state vector from the remote peer
+     * @return the length of the differential update in bytes
+     * @throws IllegalStateException if this document has been closed
+     * @throws IllegalArgumentException if stateVector is null
+     * @throws RuntimeException if the state vector cannot be decoded
+     * @see #estimateUpdateSize(YTransaction, byte[])
+     */
+    public long estimateUpdateSize(byte[] stateVector) {
+        ensureNotClosed();
+        if (stateVector == null) {
+            throw new IllegalArgumentException("State vector cannot be null");
+        }
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return nativeEstimateUpdateSizeWithTxn(nativePtr, activeTxn.getNativePtr(), stateVector);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return nativeEstimateUpdateSizeWithTxn(nativePtr, txn.getNativePtr(), stateVector);
+        }
+    }
+
+    /**
+     * Encodes the state of the given roots as an update within an existing
+     * transaction.
+     *
+     * <p>Lets a server replicate a large document with many roots to a client that
+     * only subscribes to some of them. Changes are traced to their roots the same way
+     * as by {@link #applyUpdateFiltered(YTransaction, byte[], Predicate)}, including
+     * changes to nested types and deletions.</p>
+     *
+     * <p>Content under other roots is encoded as garbage collected, so the update
+     * stays applicable and later updates apply on top of it, but the receiving
+     * document can never receive that content afterwards. A client that subscribes to
+     * another root later needs a fresh document.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param roots names of the roots to include
+     * @return the update
+     * @throws IllegalArgumentException if txn or roots is null, or roots contains null
+     * @throws IllegalStateException if this document has been closed
+     * @see #encodeDiffForRoots(YTransaction, byte[], Set)
+     */
+    public byte[] encodeStateAsUpdateForRoots(YTransaction txn, Set<String> roots) {
+        return encodeForRoots(txn, null, roots);
+    }
+
+    /**
+     * Encodes the state of the given roots as an update (creates implicit
+     * transaction).
+     *
+     * @param roots names of the roots to include
+     * @return the update
+     * @throws IllegalArgumentException if roots is null or contains null
+     * @throws IllegalStateException if this document has been closed
+     * @see #encodeStateAsUpdateForRoots(YTransaction, Set)
+     */
+    public byte[] encodeStateAsUpdateForRoots(Set<String> roots) {
+        return encodeForRoots(null, roots);
+    }
+
+    /**
+     * Encodes the changes to the given roots that a peer is missing within an
+     * existing transaction.
+     *
+     * <p>This is {@link #encodeDiff(YTransaction, byte[])} narrowed to the given
+     * roots; see {@link #encodeStateAsUpdateForRoots(YTransaction, Set)} for how
+     * content under other roots is encoded.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param stateVector the state vector from the remote peer
+     * @param roots names of the roots to include
+     * @return the differential update
+     * @throws IllegalArgumentException if any argument is null, or roots contains null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if the state vector cannot be decoded
+     */
+    public byte[] encodeDiffForRoots(YTransaction txn, byte[] stateVector, Set<String> roots) {
+        if (stateVector == null) {
+            throw new IllegalArgumentException("State vector cannot be null");
+        }
+        return encodeForRoots(txn, stateVector, roots);
+    }
+
+    /**
+     * Encodes the changes to the given roots that a peer is missing (creates implicit
+     * transaction).
+     *
+     * @param stateVector the state vector from the remote peer
+     * @param roots names of the roots to include
+     * @return the differential update
+     * @throws IllegalArgumentException if any argument is null, or roots contains null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if the state vector cannot be decoded
+     * @see #encodeDiffForRoots(YTransaction, byte[], Set)
+     */
+    public byte[] encodeDiffForRoots(byte[] stateVector, Set<String> roots) {
+        if (stateVector == null) {
+            throw new IllegalArgumentException("State vector cannot be null");
+        }
+        return encodeForRoots(stateVector, roots);
+    }
+
+    private byte[] encodeForRoots(byte[] stateVector, Set<String> roots) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return encodeForRoots(activeTxn, stateVector, roots);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return encodeForRoots(txn, stateVector, roots);
+        }
+    }
+
+    private byte[] encodeForRoots(YTransaction txn, byte[] stateVector, Set<String> roots) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (roots == null) {
+            throw new IllegalArgumentException("Roots cannot be null");
+        }
+        String[] names = roots.toArray(new String[0]);
+        for (String name : names) {
+            if (name == null) {
+                throw new IllegalArgumentException("Root names cannot be null");
+            }
+        }
+        byte[] result = nativeEncodeDiffForRootsWithTxn(nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), stateVector, names);
+        if (result == null) {
+            throw new RuntimeException("Failed to encode update");
+        }
+        return result;
+    }
+
+    /**
+     * Encodes the changes a peer is missing within an existing transaction, as the
+     * first half of a pairwise sync.
+     *
+     * <p>The result is the same as {@link #encodeDiff(YTransaction, byte[])}. The peer
+     * passes it to {@link #absorb(YTransaction, byte[])}, which applies it and returns
+     * the changes this document is missing:</p>
+     * <pre>{@code
+     * byte[] diff = docA.syncWith(docB.encodeStateVector());
+     * byte[] reply = docB.absorb(diff);
+     * docA.applyUpdate(reply);
+     * }</pre>
+     *
+     * @param txn The transaction to use for this operation
+     * @param remoteStateVector the state vector of the peer
+     * @return the diff to send to the peer
+     * @throws IllegalArgumentException if txn or remoteStateVector is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if the state vector cannot be decoded
+     */
+    public byte[] syncWith(YTransaction txn, byte[] remoteStateVector) {
+        ensureNotClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (remoteStateVector == null) {
+            throw new IllegalArgumentException("State vector cannot be null");
+        }
+        return nativeSyncWith(nativePtr, ((JniYTransaction) txn).getNativePtr(), remoteStateVector);
+    }
+
+    /**
+     * Encodes the changes a peer is missing (creates implicit transaction).
+     *
+     * @param remoteStateVector the state vector of the peer
+     * @return the diff to send to the peer
+     * @throws IllegalArgumentException if remoteStateVector is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if the state vector cannot be decoded
+     * @see #syncWith(YTransaction, byte[])
+     */
+    public byte[] syncWith(byte[] remoteStateVector) {
+        ensureNotClosed();
+        JniYTransaction activeTxn = getActiveTransaction();
+        if (activeTxn != null) {
+            return syncWith(activeTxn, remoteStateVector);
+        }
+        try (JniYTransaction txn = beginTransactionInternal()) {
+            return syncWith(txn, remoteStateVector);
+        }
+    }
+
+    /**
+     * Applies a diff from a peer within an existing transaction and returns the
+     * changes that peer is missing, as the second half of a pairwise sync.
      *
-     * // Generate differential update
-     * byte[] diff = doc.encodeDiff(remoteStateVector);
-     *
-     * // Send diff to remote peer
-     * // Remote peer applies it with applyUpdate(diff)
-     * }</pre>
+     * <p>The diff does not carry the peer's state vector, so the reply is computed
+     * against the clocks the diff reaches. Content from clients the diff does not
+     * mention is sent in full. Applying content a peer already has is harmless, so
+     * this may send more than needed but never too little. Exchange state vectors
+     * with {@link #encodeDiff(YTransaction, byte[])} in both directions when the
+     * size of the reply matters.</p>
      *
-     * @param stateVector the state vector from the remote peer
-     * @return a byte array containing the differential update
-     * @throws IllegalStateException if this document has been closed
-     * @throws IllegalArgumentException if stateVector is null
-     * @throws RuntimeException if encoding fails
+     * @param txn The transaction to use for this operation
+     * @param remoteDiff the diff received from the peer, as returned by
+     *        {@link #syncWith(YTransaction, byte[])}
+     * @return the diff to send back to the peer
+     * @throws IllegalArgumentException if txn or remoteDiff is null
+     * @throws IllegalStateException if this document has been closed or does not
+     *         accept updates
+     * @throws RuntimeException if the diff is invalid or cannot be applied
      */
-    @Override
-    public byte[] encodeDiff(byte[] stateVector) {
+    public byte[] absorb(YTransaction txn, byte[] remoteDiff) {
         ensureNotClosed();
-        if (stateVector == null) {
-            throw new IllegalArgumentException("State vector cannot be null");
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
         }
+        if (remoteDiff == null) {
+            throw new IllegalArgumentException("Diff cannot be null");
+        }
+        return nativeAbsorb(nativePtr, ((JniYTransaction) txn).getNativePtr(), remoteDiff);
+    }
+
+    /**
+     * Applies a diff from a peer and returns the changes that peer is missing
+     * (creates implicit transaction).
+     *
+     * @param remoteDiff the diff received from the peer
+     * @return the diff to send back to the peer
+     * @throws IllegalArgumentException if remoteDiff is null
+     * @throws IllegalStateException if this document has been closed or does not
+     *         accept updates
+     * @throws RuntimeException if the diff is invalid or cannot be applied
+     * @see #absorb(YTransaction, byte[])
+     */
+    public byte[] absorb(byte[] remoteDiff) {
+        ensureNotClosed();
         JniYTransaction activeTxn = getActiveTransaction();
         if (activeTxn != null) {
-            byte[] result = nativeEncodeDiffWithTxn(nativePtr, activeTxn.getNativePtr(), stateVector);
-            if (result == null) {
-                throw new RuntimeException("Failed to encode differential update");
-            }
-            return result;
+            return absorb(activeTxn, remoteDiff);
         }
         try (JniYTransaction txn = beginTransactionInternal()) {
-            byte[] result = nativeEncodeDiffWithTxn(nativePtr, txn.getNativePtr(), stateVector);
-            if (result == null) {
-                throw new RuntimeException("Failed to encode differential update");
-            }
-            return result;
+            return absorb(txn, remoteDiff);
         }
     }
 
@@ -664,6 +2597,26 @@ public class JniYDoc implements YDoc, JniYObservable {
         return new JniYMap(this, name);
     }
 
+    /**
+     * Gets a JSON value kept in the root map with the specified name.
+     *
+     * <p>The returned instance holds a handle to the root map and must be closed when
+     * no longer needed. Use try-with-resources for automatic cleanup.</p>
+     *
+     * @param name the name of the root map
+     * @return a JniYJson instance
+     * @throws IllegalStateException if this document has been closed
+     * @throws IllegalArgumentException if name is null
+     * @see JniYJson
+     */
+    public JniYJson getJson(String name) {
+        ensureNotClosed();
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        return new JniYJson(this, name);
+    }
+
     /**
      * Gets or creates a YXmlText instance with the specified name.
      *
@@ -688,10 +2641,12 @@ public class JniYDoc implements YDoc, JniYObservable {
      * @throws IllegalStateException if this document has been closed
      * @throws IllegalArgumentException if name is null
      * @throws RuntimeException if XML text creation fails
+     * @throws UnsupportedOperationException if the native library was built without XML support
      */
     @Override
     public JniYXmlText getXmlText(String name) {
         ensureNotClosed();
+        requireXmlSupport();
         if (name == null) {
             throw new IllegalArgumentException("Name cannot be null");
         }
@@ -722,10 +2677,12 @@ public class JniYDoc implements YDoc, JniYObservable {
      * @throws IllegalStateException if this document has been closed
      * @throws IllegalArgumentException if name is null
      * @throws RuntimeException if XML element creation fails
+     * @throws UnsupportedOperationException if the native library was built without XML support
      */
     @Override
     public JniYXmlElement getXmlElement(String name) {
         ensureNotClosed();
+        requireXmlSupport();
         if (name == null) {
             throw new IllegalArgumentException("Name cannot be null");
         }
@@ -757,17 +2714,120 @@ public class JniYDoc implements YDoc, JniYObservable {
      * @throws IllegalStateException if this document has been closed
      * @throws IllegalArgumentException if name is null
      * @throws RuntimeException if XML fragment creation fails
+     * @throws UnsupportedOperationException if the native library was built without XML support
      * @since 0.2.0
      */
     @Override
     public JniYXmlFragment getXmlFragment(String name) {
         ensureNotClosed();
+        requireXmlSupport();
         if (name == null) {
             throw new IllegalArgumentException("Name cannot be null");
         }
         return new JniYXmlFragment(this, name);
     }
 
+    /**
+     * Replaces the content of this document with a new, empty document that has
+     * a random client ID.
+     *
+     * @throws IllegalStateException if this document has been closed or a
+     *         transaction is open on it
+     * @see #reset(long)
+     */
+    public void reset() {
+        resetInternal(-1);
+    }
+
+    /**
+     * Replaces the content of this document with a new, empty document.
+     *
+     * <p>Lets a server keep a pool of documents and hand one to the next user
+     * instead of destroying and creating native documents. The new document has
+     * a new GUID and the given client ID, and is created with the options of the
+     * old one. Settings made on this object, such as the string policy, access
+     * mode and listeners, are kept.</p>
+     *
+     * <p>All observers are detached, and closing their subscriptions afterwards
+     * does nothing. Root types obtained by name are released as by
+     * {@link #releaseAllRoots()}. Staged updates, root activity and transaction
+     * statistics are discarded. The {@linkplain #getChangeCounter() change counter}
+     * keeps counting and counts the reset as a change. Other objects obtained from
+     * the old content, such as nested types, subdocuments and undo managers, must be
+     * closed before the reset and not used afterwards.</p>
+     *
+     * @param clientId the client ID of the new document
+     * @throws IllegalArgumentException if clientId is negative
+     * @throws IllegalStateException if this document has been closed or a
+     *         transaction is open on it
+     */
+    public void reset(long clientId) {
+        if (clientId < 0) {
+            throw new IllegalArgumentException("Client ID must be non-negative");
+        }
+        resetInternal(clientId);
+    }
+
+    private void resetInternal(long clientId) {
+        ensureNotClosed();
+        if (getActiveTransaction() != null) {
+            throw new IllegalStateException(
+                "Cannot reset a YDoc while a transaction is open on it");
+        }
+        drainPendingUnsubscribes();
+        nativeReset(nativePtr, clientId);
+        rootGeneration.incrementAndGet();
+        updateObservers.clear();
+        rootObservers.clear();
+    }
+
+    /**
+     * Releases the native handles of all root types obtained from this document
+     * by name.
+     *
+     * <p>Every call to {@link #getText(String)}, {@link #getMap(String)},
+     * {@link #getArray(String)} and the XML lookups creates a native handle that
+     * lives until the returned object is closed. A pooled document handed from
+     * one user to the next can drop the handles its previous user left open with
+     * this method. The objects holding them are invalidated: using them throws
+     * {@link IllegalStateException} and closing them does nothing. The document
+     * content is not changed, and the roots can be looked up again. Closing the
+     * document releases the handles as well.</p>
+     *
+     * @return the number of handles released
+     * @throws IllegalStateException if this document has been closed
+     * @see #getOpenRootNames()
+     */
+    public int releaseAllRoots() {
+        ensureNotClosed();
+        rootGeneration.incrementAndGet();
+        return nativeReleaseAllRoots(nativePtr);
+    }
+
+    /**
+     * Returns the names of the root types whose objects are open.
+     *
+     * <p>A root looked up several times appears once per open object.</p>
+     *
+     * @return the names, sorted
+     * @throws IllegalStateException if this document has been closed
+     * @see #releaseAllRoots()
+     */
+    public List<String> getOpenRootNames() {
+        ensureNotClosed();
+        return List.of(nativeGetOpenRootNames(nativePtr));
+    }
+
+    /**
+     * Returns the current root generation. Root-type objects record it when they
+     * are created and treat their handle as released once it changes.
+     *
+     * @return the root generation
+     */
+    int getRootGeneration() {
+        return rootGeneration.get();
+    }
+
     /**
      * Enqueues a native subscription ID for deferred unsubscription.
      * The Java-side observer map should already be updated so callbacks
@@ -826,21 +2886,103 @@ public class JniYDoc implements YDoc, JniYObservable {
         return beginTransactionInternal();
     }
 
+    /**
+     * Begin a new transaction carrying an origin.
+     *
+     * <p>The origin identifies where the changes in the transaction come from. It is
+     * passed to update observers, and a {@link JniYUndoManager} that tracks origins
+     * captures the transaction only if the origin is one of them. Open local edits
+     * with an origin the undo manager tracks, and apply updates from peers with a
+     * different origin, so that remote changes are never undone locally.</p>
+     *
+     * @param origin the origin of the transaction
+     * @return transaction handle (use with try-with-resources)
+     * @throws IllegalArgumentException if origin is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if transaction creation fails
+     * @see JniYUndoManager#trackOrigin(String)
+     */
+    public JniYTransaction beginTransaction(String origin) {
+        if (origin == null) {
+            throw new IllegalArgumentException("Origin cannot be null");
+        }
+        return beginTransactionInternal(origin);
+    }
+
+    /**
+     * Begin a transaction whose changes are not reported to observers of shared types.
+     *
+     * <p>Observers registered with {@code observe} on texts, arrays, maps, XML nodes
+     * and JSON views, with {@link #observeRoot}, and annotation observers skip the
+     * transaction. It is otherwise an ordinary transaction: its update is still passed
+     * to {@link #observeUpdateV1} observers and update streams, so peers receive it.
+     * This is meant for loading a document from storage, when observers would
+     * otherwise receive one event describing the whole document:</p>
+     * <pre>{@code
+     * try (JniYTransaction txn = doc.beginSilentTransaction()) {
+     *     doc.applyUpdate(txn, stored);
+     * }
+     * }</pre>
+     *
+     * @return transaction handle (use with try-with-resources)
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if transaction creation fails
+     * @see JniYTransaction#isSilent()
+     */
+    public JniYTransaction beginSilentTransaction() {
+        JniYTransaction txn = beginTransactionInternal();
+        txn.markSilent();
+        return txn;
+    }
+
+    /**
+     * Begin a silent transaction carrying an origin.
+     *
+     * @param origin the origin of the transaction
+     * @return transaction handle (use with try-with-resources)
+     * @throws IllegalArgumentException if origin is null
+     * @throws IllegalStateException if this document has been closed
+     * @throws RuntimeException if transaction creation fails
+     * @see #beginSilentTransaction()
+     * @see #beginTransaction(String)
+     */
+    public JniYTransaction beginSilentTransaction(String origin) {
+        JniYTransaction txn = beginTransaction(origin);
+        txn.markSilent();
+        return txn;
+    }
+
     /**
      * Internal method to begin a transaction, returning concrete type.
      */
     private JniYTransaction beginTransactionInternal() {
+        return beginTransactionInternal(null);
+    }
+
+    private JniYTransaction beginTransactionInternal(String origin) {
         ensureNotClosed();
         drainPendingUnsubscribes();
-        long txnPtr = nativeBeginTransaction(nativePtr);
+        long txnPtr = origin == null
+            ? nativeBeginTransaction(nativePtr)
+            : nativeBeginTransactionWithOrigin(nativePtr, origin);
         if (txnPtr == 0) {
             throw new RuntimeException("Failed to create transaction: native pointer is null");
         }
-        JniYTransaction txn = new JniYTransaction(this, txnPtr);
+        JniYTransaction txn = new JniYTransaction(this, txnPtr, origin);
         activeTransaction.set(txn);
         return txn;
     }
 
+    /**
+     * Allocates a subscription ID that is unique within this document.
+     * Package-private for use by the shared types when registering observers.
+     *
+     * @return a new subscription ID
+     */
+    long allocateSubscriptionId() {
+        return nextSubscriptionId.getAndIncrement();
+    }
+
     /**
      * Gets the currently active transaction for this thread, or null if none.
      * Package-private for internal use.
@@ -859,6 +3001,58 @@ public class JniYDoc implements YDoc, JniYObservable {
         activeTransaction.remove();
     }
 
+    /**
+     * Runs an action once the current transaction has finished.
+     *
+     * <p>Observers run while the transaction that notified them is committed, so
+     * they cannot change the document, apply updates to it or open a transaction on
+     * it; those calls throw {@link IllegalStateException}. An observer that needs to
+     * react with a change defers it with this method instead:</p>
+     * <pre>{@code
+     * text.observe(event -> doc.runAfterTransaction(() -> counter.increment()));
+     * }</pre>
+     *
+     * <p>Called from an observer of this document, the action is queued and runs on
+     * the same thread right after the commit, in its own transaction unless it opens
+     * one. Actions run in the order they were queued, and actions queued by the
+     * observers of a deferred action's changes run after it. Called anywhere else,
+     * the action runs immediately. Exceptions thrown by a deferred action are passed
+     * to the observer error handler.</p>
+     *
+     * @param action the action to run
+     * @throws IllegalArgumentException if action is null
+     * @throws IllegalStateException if this document has been closed
+     */
+    public void runAfterTransaction(Runnable action) {
+        ensureNotClosed();
+        if (action == null) {
+            throw new IllegalArgumentException("Action cannot be null");
+        }
+        if (nativeIsDispatching(nativePtr)) {
+            deferredActions.get().add(action);
+        } else {
+            action.run();
+        }
+    }
+
+    /**
+     * Runs the actions observers on this thread deferred with
+     * {@link #runAfterTransaction(Runnable)}. Package-private; called once the
+     * transaction that notified them has been committed.
+     */
+    void runDeferredActions() {
+        ArrayDeque<Runnable> actions = deferredActions.get();
+        Runnable action;
+        while (!closed && (action = actions.poll()) != null) {
+            try {
+                action.run();
+            } catch (Exception e) {
+                observerErrorHandler.handleError(e, this);
+            }
+        }
+        actions.clear();
+    }
+
     /**
      * Execute operations within a transaction using a callback.
      *
@@ -923,8 +3117,8 @@ public class JniYDoc implements YDoc, JniYObservable {
      * blocking document operations. For expensive operations, schedule them
      * asynchronously.</p>
      *
-     * <p><b>Reentrancy Warning:</b> Observers should NOT modify the same document
-     * that triggered the callback, as this may cause undefined behavior or deadlocks.</p>
+     * <p>The observer cannot change this document, apply updates to it or open a
+     * transaction on it; see {@link #runAfterTransaction(Runnable)}.</p>
      *
      * @param observer the observer to register
      * @return a subscription that can be closed to unregister the observer
@@ -939,7 +3133,7 @@ public class JniYDoc implements YDoc, JniYObservable {
             throw new IllegalArgumentException("Observer cannot be null");
         }
 
-        long subscriptionId = nextSubscriptionId.getAndIncrement();
+        long subscriptionId = allocateSubscriptionId();
         updateObservers.put(subscriptionId, observer);
 
         // Drain any pending unsubscribes before registering with native layer
@@ -950,7 +3144,63 @@ public class JniYDoc implements YDoc, JniYObservable {
     }
 
     /**
-     * Unregisters an update observer by subscription ID.
+     * Registers an observer for a root type, creating the root if needed.
+     *
+     * <p>This is equivalent to getting the root type and calling its
+     * {@code observe} method, but no handle to the root type is created, so
+     * nothing but the subscription has to be kept or closed. Events are the same
+     * as those of the type's own observers, except that their target is this
+     * document.</p>
+     *
+     * <pre>{@code
+     * try (YSubscription sub = doc.observeRoot("body", JniTreeNode.Kind.TEXT,
+     *         event -> System.out.println(event.getChanges()))) {
+     *     ...
+     * }
+     * }</pre>
+     *
+     * <p>XML element and XML text roots are created inside a fragment of the same
+     * name, as {@link #getXmlElement(String)} and {@link #getXmlText(String)} do.</p>
+     *
+     * @param name the name of the root type
+     * @param kind the kind of the root type; {@link JniTreeNode.Kind#DOC} and
+     *        {@link JniTreeNode.Kind#UNKNOWN} are not supported
+     * @param observer the observer to register
+     * @return a subscription that can be closed to unregister the observer
+     * @throws IllegalArgumentException if name, kind or observer is null, if kind is
+     *         not supported, or if an existing XML root does not hold a node of
+     *         that kind
+     * @throws IllegalStateException if this document has been closed, or if this
+     *         thread holds an open transaction on it
+     */
+    public YSubscription observeRoot(String name, JniTreeNode.Kind kind, YObserver observer) {
+        ensureNotClosed();
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        if (kind == null) {
+            throw new IllegalArgumentException("Kind cannot be null");
+        }
+        if (observer == null) {
+            throw new IllegalArgumentException("Observer cannot be null");
+        }
+
+        long subscriptionId = allocateSubscriptionId();
+        rootObservers.put(subscriptionId, observer);
+
+        drainPendingUnsubscribes();
+        try {
+            nativeObserveRoot(nativePtr, name, kind.ordinal(), subscriptionId, this);
+        } catch (RuntimeException e) {
+            rootObservers.remove(subscriptionId);
+            throw e;
+        }
+
+        return new JniYSubscription(subscriptionId, observer, this);
+    }
+
+    /**
+     * Unregisters an update or root observer by subscription ID.
      *
      * <p>This is called automatically when a YSubscription is closed.
      * You typically don't need to call this directly.</p>
@@ -959,7 +3209,8 @@ public class JniYDoc implements YDoc, JniYObservable {
      */
     @Override
     public void unobserveById(long subscriptionId) {
-        if (updateObservers.remove(subscriptionId) != null) {
+        if (updateObservers.remove(subscriptionId) != null
+                || rootObservers.remove(subscriptionId) != null) {
             if (!closed && nativePtr != 0) {
                 deferNativeUnsubscribe(subscriptionId);
             }
@@ -1019,21 +3270,60 @@ public class JniYDoc implements YDoc, JniYObservable {
         }
     }
 
+    /**
+     * Called from native code to dispatch an event of an observed root type.
+     *
+     * @param subscriptionId the subscription ID
+     * @param event the event to dispatch
+     */
+    void dispatchEvent(long subscriptionId, JniYEvent event) {
+        YObserver observer = rootObservers.get(subscriptionId);
+        if (observer != null) {
+            try {
+                observer.onChange(event);
+            } catch (Exception e) {
+                observerErrorHandler.handleError(e, this);
+            }
+        }
+    }
+
     /**
      * Closes this document and frees its native resources.
      *
      * <p>After calling this method, any further operations on this document
      * will throw {@link IllegalStateException}.</p>
      *
+     * <p>If observer callbacks are running on other threads, this method waits
+     * for them to return before freeing the document.</p>
+     *
      * <p>This method is idempotent - calling it multiple times is safe.</p>
+     *
+     * @throws IllegalStateException if called from inside one of this document's
+     *         observer callbacks; the document stays open in that case
      */
     @Override
     public void close() {
+        Runnable hook = closeHook;
+        if (hook != null) {
+            closeHook = null;
+            hook.run();
+        }
+        rootGeneration.incrementAndGet();
         drainPendingUnsubscribes();
+        cleanupAction.run();
         cleanable.clean();
         closed = true;
     }
 
+    /**
+     * Sets the action {@link #close()} runs before freeing the document.
+     *
+     * @param hook the action, or null for none
+     */
+    void setCloseHook(Runnable hook) {
+        this.closeHook = hook;
+    }
+
     /**
      * Checks if this document has been closed.
      *
@@ -1080,27 +3370,153 @@ public class JniYDoc implements YDoc, JniYObservable {
 
     private static native long nativeCreateWithClientId(long clientId);
 
+    private static native long nativeCreateWithIds(long clientId, String guid);
+
+    private static native long nativeCreateWithoutGc(long clientId);
+
+    private static native long nativeCreateDeterministic(long clientId);
+
+    private static native boolean nativeIsDeterministic(long ptr);
+
+    private static native boolean nativeIsDispatching(long ptr);
+
     private static native void nativeDestroy(long ptr);
 
+    private static native void nativeReset(long ptr, long clientId);
+
+    private static native int nativeReleaseAllRoots(long ptr);
+
+    private static native String[] nativeGetOpenRootNames(long ptr);
+
     private static native long nativeGetClientId(long ptr);
 
+    private static native long nativeGetChangeCounter(long ptr);
+
     private static native String nativeGetGuid(long ptr);
 
+    private static native String nativeGetParentGuid(long ptr);
+
+    private static native Object[] nativeGetEmbeddedPath(long ptr);
+
+    private static native void nativeSetSingleWriter(long ptr, boolean enabled);
+
+    private static native boolean nativeIsSingleWriter(long ptr);
+
+    private static native void nativeSetAccess(long ptr, int access);
+
+    private static native int nativeGetAccess(long ptr);
+
+    private static native void nativeSetStringPolicy(long ptr, int policy);
+
+    private static native int nativeGetStringPolicy(long ptr);
+
+    private static native void nativeSetTransactionStats(long ptr, boolean enabled);
+
+    private static native boolean nativeIsTransactionStats(long ptr);
+
+    private static native void nativeSetDecodeFailureLogging(long ptr, boolean enabled);
+
+    private static native boolean nativeIsDecodeFailureLogging(long ptr);
+
+    private static native void nativeSetEventContentLimit(long ptr, int maxLength);
+
+    private static native int nativeGetEventContentLimit(long ptr);
+
+    private static native long[] nativeGetTransactionStats(long ptr);
+
+    private static native void nativeResetTransactionStats(long ptr);
+
+    private static native void nativeSetAllowedAttributes(long ptr, String[] keys);
+
+    private static native boolean nativeHasXmlSupport();
+    private static native boolean nativeSetNativeTracing(boolean enabled);
+    private static native boolean nativeIsNativeTracing();
+    private static native void nativeSetCallbackClassLoader(ClassLoader loader);
+
+    private static native Map<String, Object> nativeGetVersionInfo();
+
     private static native byte[] nativeEncodeStateAsUpdateWithTxn(long ptr, long txnPtr);
 
-    private static native void nativeApplyUpdateWithTxn(long ptr, long txnPtr, byte[] update);
+    private static native JniApplyResult nativeApplyUpdateWithTxn(long ptr, long txnPtr,
+        byte[] update);
+
+    private static native JniSyncMessage nativeApplySyncMessageWithTxn(long ptr, long txnPtr,
+                                                                      byte[] message);
+
+    private static native byte[] nativeEncodeSyncStep1WithTxn(long ptr, long txnPtr);
+
+    private static native byte[] nativeEncodeSyncUpdate(byte[] update);
+
+    private static native JniFilteredUpdate nativeApplyUpdateFilteredWithTxn(long ptr,
+        long txnPtr, byte[] update, Predicate<String> allowRoot);
 
     private static native byte[] nativeEncodeStateVectorWithTxn(long ptr, long txnPtr);
 
+    private static native boolean nativeHasMissingUpdatesWithTxn(long ptr, long txnPtr);
+
+    private static native long[] nativeGetStateVectorEntriesWithTxn(long ptr, long txnPtr);
+
+    private static native void nativeSetRootActivityTracking(long ptr, boolean enabled);
+
+    private static native boolean nativeIsRootActivityTracking(long ptr);
+
+    private static native JniRootActivity[] nativeGetRootActivity(long ptr);
+
+    private static native long[] nativeGetPendingStatsWithTxn(long ptr, long txnPtr);
+    private static native long[] nativeGetStoreStatsWithTxn(long ptr, long txnPtr);
+
+    private static native byte[] nativeGetMissingStateVectorWithTxn(long ptr, long txnPtr);
+
+    private static native long[] nativeQueueUpdateWithTxn(long ptr, long txnPtr, byte[] update);
+
+    private static native String[] nativeValidateWithTxn(long ptr, long txnPtr, int[] kinds,
+        String[] roots, String[] keys, int[] types);
+
+    private static native void nativeSubmitBatch(long ptr, int[] kinds, String[] roots,
+        int[] positions, String[] strings, Object[] values, Object completion);
+
+    private static native int nativeGetQueuedUpdateCount(long ptr);
+
+    private static native int nativeClearQueuedUpdates(long ptr);
+
+    private static native byte[] nativeEncodeSnapshotWithTxn(long ptr, long txnPtr);
+
+    private static native byte[] nativeEncodeStateFromSnapshotWithTxn(long ptr, long txnPtr,
+            byte[] snapshot);
+
+    private static native Map<String, JniTreeNode> nativeExportTreeWithTxn(long ptr, long txnPtr,
+        int maxDepth);
+
     private static native byte[] nativeEncodeDiffWithTxn(long ptr, long txnPtr, byte[] stateVector);
 
+    private static native long nativeEstimateUpdateSizeWithTxn(long ptr, long txnPtr, byte[] stateVector);
+
+    private static native byte[] nativeEncodeDiffForRootsWithTxn(long ptr, long txnPtr,
+            byte[] stateVector, String[] roots);
+
+    private static native byte[] nativeSyncWith(long ptr, long txnPtr, byte[] stateVector);
+
+    private static native byte[] nativeAbsorb(long ptr, long txnPtr, byte[] diff);
+
+    private static native void nativeCommitPrelimWithTxn(long ptr, long txnPtr, String rootName,
+        JniPrelim prelim);
+
     private static native byte[] nativeMergeUpdates(byte[][] updates);
 
     private static native byte[] nativeEncodeStateVectorFromUpdate(byte[] update);
 
     private static native long nativeBeginTransaction(long ptr);
 
+    private static native long nativeBeginTransactionWithOrigin(long ptr, String origin);
+
     private static native void nativeObserveUpdateV1(long ptr, long subscriptionId, JniYDoc ydocObj);
 
     private static native void nativeUnobserveUpdateV1(long ptr, long subscriptionId);
+
+    private static native void nativeObserveRoot(long ptr, String name, int kind,
+        long subscriptionId, JniYDoc ydocObj);
+
+    private static native byte[] nativeEncodeStateAsUpdateDeepWithTxn(long ptr, long txnPtr);
+
+    private static native void nativeApplyUpdateDeep(long ptr, byte[] update);
 }
@@ -13,7 +13,7 @@ import java.util.List;
  * <ul>
  *   <li>The target object that changed</li>
  *   <li>A list of changes (deltas) describing the modifications</li>
- *   <li>Metadata about the change origin</li>
+ *   <li>Metadata about the change origin: see {@link #getEventOrigin()}</li>
  * </ul>
  *
  * <p>Events are immutable and thread-safe.</p>
@@ -25,6 +25,7 @@ public final class JniYEvent implements YEvent {
     private final Object target;
     private final List<? extends YChange> changes;
     private final String origin;
+    private final JniEventOrigin eventOrigin;
 
     /**
      * Package-private constructor. Events are created by the native layer.
@@ -37,6 +38,21 @@ public final class JniYEvent implements YEvent {
         this.target = target;
         this.changes = Collections.unmodifiableList(changes);
         this.origin = origin;
+        this.eventOrigin = null;
+    }
+
+    /**
+     * Package-private constructor. Events are created by the native layer.
+     *
+     * @param target the Y type that changed
+     * @param changes the list of changes
+     * @param eventOrigin the transaction that made the changes
+     */
+    JniYEvent(Object target, List<? extends YChange> changes, JniEventOrigin eventOrigin) {
+        this.target = target;
+        this.changes = Collections.unmodifiableList(changes);
+        this.origin = eventOrigin.getString();
+        this.eventOrigin = eventOrigin;
     }
 
     @Override
@@ -54,6 +70,17 @@ public final class JniYEvent implements YEvent {
         return origin;
     }
 
+    /**
+     * Gets the origin, client ID and locality of the transaction that made the
+     * changes.
+     *
+     * @return the event origin, or null if this event was not created by the
+     *         native layer
+     */
+    public JniEventOrigin getEventOrigin() {
+        return eventOrigin;
+    }
+
     @Override
     public String toString() {
         return "JniYEvent{target=" + target.getClass().getSimpleName()
@@ -0,0 +1,334 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YObserver;
+import net.carcdr.ycrdt.YSubscription;
+import net.carcdr.ycrdt.YTransaction;
+
+import java.io.Closeable;
+import java.util.concurrent.ConcurrentHashMap;
+
+/**
+ * A JSON value shared through a root map of a document.
+ *
+ * <p>For applications that want shared JSON state without working with the individual
+ * shared types. Objects are stored as nested maps and arrays as nested arrays, so
+ * concurrent changes to different members or elements merge. Values are addressed
+ * with JSON Pointers (RFC 6901), passed as JSON text, and changed with the
+ * {@code add}, {@code replace} and {@code remove} operations of JSON Patch
+ * (RFC 6902). Observers receive the changes of each transaction, local or remote, as
+ * a list of {@link JniJsonPatchOp}s:</p>
+ * <pre>{@code
+ * try (JniYJson state = doc.getJson("state")) {
+ *     state.add("/users", "[]");
+ *     state.add("/users/-", "{\"name\":\"Ann\"}");
+ *     state.replace("/users/0/name", "\"Anne\"");
+ *     state.get("/users/0");   // {"name":"Anne"}
+ *     YSubscription sub = state.observe(event -> {
+ *         for (YChange change : event.getChanges()) {
+ *             System.out.println(((JniJsonPatchOp) change).toJson());
+ *         }
+ *     });
+ * }
+ * }</pre>
+ *
+ * <p>The root is always an object. Objects and arrays stored as plain values by
+ * other APIs, such as {@link JniYMap#compareAndSet}, can be read but not changed in
+ * place; replace them instead. A text nested in the value is read as a string and its
+ * changes are reported as a {@code replace} of the whole string.</p>
+ *
+ * <p>Pointer and JSON errors throw {@link IllegalArgumentException}, including
+ * {@code replace} and {@code remove} of a value that does not exist.</p>
+ */
+public class JniYJson implements Closeable, JniYObservable {
+
+    // Write operations
+    private static final int OP_ADD = 0;
+    private static final int OP_REPLACE = 1;
+    private static final int OP_REMOVE = 2;
+
+    private final JniYDoc doc;
+    private final JniYMap map;
+    private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
+
+    /**
+     * Package-private constructor. Use {@link JniYDoc#getJson(String)} to create instances.
+     *
+     * @param doc The parent YDoc instance
+     * @param name The name of the root map holding the value
+     */
+    JniYJson(JniYDoc doc, String name) {
+        this.doc = doc;
+        this.map = doc.getMap(name);
+    }
+
+    /**
+     * Returns the JSON text of the value at a pointer (creates implicit transaction).
+     *
+     * @param pointer the JSON Pointer; the empty string is the whole value
+     * @return the JSON text, or null if there is no value at the pointer
+     * @throws IllegalArgumentException if pointer is null or not a JSON Pointer
+     * @throws IllegalStateException if this value has been closed
+     */
+    public String get(String pointer) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return get(activeTxn, pointer);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return get(txn, pointer);
+        }
+    }
+
+    /**
+     * Returns the JSON text of the value at a pointer using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param pointer the JSON Pointer; the empty string is the whole value
+     * @return the JSON text, or null if there is no value at the pointer
+     * @throws IllegalArgumentException if txn or pointer is null, or pointer is not a
+     *         JSON Pointer
+     * @throws IllegalStateException if this value has been closed
+     */
+    public String get(YTransaction txn, String pointer) {
+        checkClosed();
+        checkArgs(txn, pointer);
+        return nativeGetWithTxn(doc.getNativePtr(), map.getNativePtr(),
+            ((JniYTransaction) txn).getNativePtr(), pointer);
+    }
+
+    /**
+     * Returns the JSON text of the whole value (creates implicit transaction).
+     *
+     * @return the JSON text of the root object
+     * @throws IllegalStateException if this value has been closed
+     */
+    public String toJson() {
+        return get("");
+    }
+
+    /**
+     * Adds a value (creates implicit transaction).
+     *
+     * <p>As the JSON Patch {@code add} operation: sets an object member, inserts into an
+     * array at an index, or appends to an array if the last token is {@code -}.</p>
+     *
+     * @param pointer the JSON Pointer
+     * @param json the JSON text of the value
+     * @throws IllegalArgumentException if an argument is null, json is not valid JSON,
+     *         or the pointer's parent does not exist or is not an object or array
+     * @throws IllegalStateException if this value has been closed
+     */
+    public void add(String pointer, String json) {
+        write(OP_ADD, pointer, json);
+    }
+
+    /**
+     * Adds a value using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param pointer the JSON Pointer
+     * @param json the JSON text of the value
+     * @throws IllegalArgumentException if an argument is null, json is not valid JSON,
+     *         or the pointer's parent does not exist or is not an object or array
+     * @throws IllegalStateException if this value has been closed
+     * @see #add(String, String)
+     */
+    public void add(YTransaction txn, String pointer, String json) {
+        write(txn, OP_ADD, pointer, json);
+    }
+
+    /**
+     * Replaces an existing value (creates implicit transaction).
+     *
+     * <p>Replacing the empty pointer replaces the whole value, which must be an
+     * object.</p>
+     *
+     * @param pointer the JSON Pointer
+     * @param json the JSON text of the value
+     * @throws IllegalArgumentException if an argument is null, json is not valid JSON,
+     *         or there is no value at the pointer
+     * @throws IllegalStateException if this value has been closed
+     */
+    public void replace(String pointer, String json) {
+        write(OP_REPLACE, pointer, json);
+    }
+
+    /**
+     * Replaces an existing value using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param pointer the JSON Pointer
+     * @param json the JSON text of the value
+     * @throws IllegalArgumentException if an argument is null, json is not valid JSON,
+     *         or there is no value at the pointer
+     * @throws IllegalStateException if this value has been closed
+     * @see #replace(String, String)
+     */
+    public void replace(YTransaction txn, String pointer, String json) {
+        write(txn, OP_REPLACE, pointer, json);
+    }
+
+    /**
+     * Removes an existing value (creates implicit transaction).
+     *
+     * @param pointer the JSON Pointer
+     * @throws IllegalArgumentException if pointer is null, empty or there is no value at it
+     * @throws IllegalStateException if this value has been closed
+     */
+    public void remove(String pointer) {
+        write(OP_REMOVE, pointer, null);
+    }
+
+    /**
+     * Removes an existing value using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param pointer the JSON Pointer
+     * @throws IllegalArgumentException if txn or pointer is null, pointer is empty or
+     *         there is no value at it
+     * @throws IllegalStateException if this value has been closed
+     */
+    public void remove(YTransaction txn, String pointer) {
+        write(txn, OP_REMOVE, pointer, null);
+    }
+
+    private void write(int op, String pointer, String json) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            write(activeTxn, op, pointer, json);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                write(txn, op, pointer, json);
+            }
+        }
+    }
+
+    private void write(YTransaction txn, int op, String pointer, String json) {
+        checkClosed();
+        checkArgs(txn, pointer);
+        if (op != OP_REMOVE && json == null) {
+            throw new IllegalArgumentException("JSON cannot be null");
+        }
+        long txnPtr = ((JniYTransaction) txn).getNativePtr();
+        switch (op) {
+            case OP_ADD:
+                nativeAddWithTxn(doc.getNativePtr(), map.getNativePtr(), txnPtr, pointer, json);
+                break;
+            case OP_REPLACE:
+                nativeReplaceWithTxn(doc.getNativePtr(), map.getNativePtr(), txnPtr, pointer, json);
+                break;
+            default:
+                nativeRemoveWithTxn(doc.getNativePtr(), map.getNativePtr(), txnPtr, pointer);
+                break;
+        }
+    }
+
+    private static void checkArgs(YTransaction txn, String pointer) {
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (pointer == null) {
+            throw new IllegalArgumentException("Pointer cannot be null");
+        }
+    }
+
+    /**
+     * Registers an observer notified of changes anywhere in the value.
+     *
+     * <p>Each transaction that changes the value produces one event whose changes are
+     * {@link JniJsonPatchOp}s. Applied in order to the value before the transaction,
+     * they give the value after it.</p>
+     *
+     * @param observer The observer to register (must not be null)
+     * @return A subscription handle that can be used to unregister the observer
+     * @throws IllegalArgumentException if observer is null
+     * @throws IllegalStateException if this value has been closed
+     */
+    public YSubscription observe(YObserver observer) {
+        checkClosed();
+        if (observer == null) {
+            throw new IllegalArgumentException("Observer cannot be null");
+        }
+        long id = doc.allocateSubscriptionId();
+        observers.put(id, observer);
+        nativeObserve(doc.getNativePtr(), map.getNativePtr(), id, this);
+        return new JniYSubscription(id, observer, this);
+    }
+
+    /**
+     * Unregisters an observer by its subscription ID.
+     *
+     * @param subscriptionId The ID of the subscription to remove
+     */
+    @Override
+    public void unobserveById(long subscriptionId) {
+        if (observers.remove(subscriptionId) != null && !map.isClosed()) {
+            doc.deferNativeUnsubscribe(subscriptionId);
+        }
+    }
+
+    /**
+     * Dispatches an event to the observer registered with the given subscription ID.
+     * Called from native code.
+     *
+     * @param subscriptionId The subscription ID
+     * @param event The event to dispatch
+     */
+    void dispatchEvent(long subscriptionId, JniYEvent event) {
+        YObserver observer = observers.get(subscriptionId);
+        if (observer != null) {
+            try {
+                observer.onChange(event);
+            } catch (Exception e) {
+                doc.getObserverErrorHandler().handleError(e, this);
+            }
+        }
+    }
+
+    /**
+     * Checks if this value has been closed.
+     *
+     * @return true if closed, false otherwise
+     */
+    public boolean isClosed() {
+        return map.isClosed();
+    }
+
+    /**
+     * Closes this value, removing its observers and releasing its root map handle.
+     */
+    @Override
+    public synchronized void close() {
+        if (!map.isClosed()) {
+            for (Long subscriptionId : observers.keySet()) {
+                doc.deferNativeUnsubscribe(subscriptionId);
+            }
+            observers.clear();
+            map.close();
+        }
+    }
+
+    private void checkClosed() {
+        if (map.isClosed()) {
+            throw new IllegalStateException("JniYJson has been closed");
+        }
+    }
+
+    // Native method declarations
+    private static native String nativeGetWithTxn(long docPtr, long mapPtr, long txnPtr,
+            String pointer);
+
+    private static native void nativeAddWithTxn(long docPtr, long mapPtr, long txnPtr,
+            String pointer, String json);
+
+    private static native void nativeReplaceWithTxn(long docPtr, long mapPtr, long txnPtr,
+            String pointer, String json);
+
+    private static native void nativeRemoveWithTxn(long docPtr, long mapPtr, long txnPtr,
+            String pointer);
+
+    private static native void nativeObserve(long docPtr, long mapPtr, long subscriptionId,
+            JniYJson json);
+}
@@ -7,8 +7,8 @@ import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YTransaction;
 
 import java.io.Closeable;
+import java.nio.ByteBuffer;
 import java.util.concurrent.ConcurrentHashMap;
-import java.util.concurrent.atomic.AtomicLong;
 
 /**
  * YMap represents a collaborative map type in a Y-CRDT document.
@@ -34,11 +34,48 @@ import java.util.concurrent.atomic.AtomicLong;
  */
 public class JniYMap implements YMap, JniYObservable {
 
+    /**
+     * The kind of value stored under a key, as reported by {@link #getValueType(String)}.
+     */
+    public enum ValueType {
+        /** The key is not present. */
+        ABSENT,
+        /** The key holds null. */
+        NULL,
+        /** The key holds a string. */
+        STRING,
+        /** The key holds a number (including big integers). */
+        NUMBER,
+        /** The key holds a boolean. */
+        BOOL,
+        /** The key holds a byte array. */
+        BYTES,
+        /** The key holds a YMap or a plain JSON object. */
+        MAP,
+        /** The key holds a YArray or a plain JSON array. */
+        ARRAY,
+        /** The key holds a YText. */
+        TEXT,
+        /** The key holds a subdocument. */
+        DOC,
+        /** The key holds an XML element, fragment or text. */
+        XML,
+        /** The key holds {@link JniUndefined#UNDEFINED}. */
+        UNDEFINED,
+        /** The key holds a weak link, read with {@link #getLinked(String)}. */
+        LINK
+    }
+
     private final JniYDoc doc;
     private long nativePtr;
     private volatile boolean closed = false;
+
+    /**
+     * Root generation of the document when this root was looked up by name, or
+     * -1 if the handle was not obtained by name.
+     */
+    private final int rootGeneration;
     private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
-    private final AtomicLong nextSubscriptionId = new AtomicLong(0);
 
     /**
      * Package-private constructor. Use {@link YDoc#getMap(String)} to create instances.
@@ -54,6 +91,7 @@ public class JniYMap implements YMap, JniYObservable {
             throw new IllegalArgumentException("Name cannot be null");
         }
         this.doc = doc;
+        this.rootGeneration = doc.getRootGeneration();
         this.nativePtr = nativeGetMap(doc.getNativePtr(), name);
         if (this.nativePtr == 0) {
             throw new RuntimeException("Failed to create YMap");
@@ -64,33 +102,93 @@ public class JniYMap implements YMap, JniYObservable {
      * Returns the number of entries in the map.
      *
      * @return The size of the map
+     * @throws ArithmeticException if the size does not fit in an int
      * @throws IllegalStateException if the map has been closed
      */
     public int size() {
+        return Sizes.toInt(longSize(), "longSize");
+    }
+
+    /**
+     * Returns the number of entries in the map using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return The size of the map
+     * @throws IllegalArgumentException if txn is null
+     * @throws ArithmeticException if the size does not fit in an int
+     * @throws IllegalStateException if the map has been closed
+     */
+    public int size(YTransaction txn) {
+        return Sizes.toInt(longSize(txn), "longSize");
+    }
+
+    /**
+     * Returns the root this map belongs to and the path leading to it.
+     *
+     * @return the path, or null if the map was deleted or is embedded in a text
+     * @throws IllegalStateException if the map has been closed
+     */
+    public JniTypePath getPath() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getPath(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getPath(txn);
+        }
+    }
+
+    /**
+     * Returns the root this map belongs to and the path leading to it.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the path, or null if the map was deleted or is embedded in a text
+     * @throws IllegalStateException if the map has been closed
+     */
+    public JniTypePath getPath(YTransaction txn) {
+        checkClosed();
+        return JniTypePath.fromNative(
+            nativeGetPathWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr()));
+    }
+
+    /**
+     * Returns the number of entries in the map.
+     *
+     * <p>Unlike {@link #size()}, works for maps with more
+     * than {@link Integer#MAX_VALUE} entries.</p>
+     *
+     * @return The size of the map
+     * @throws IllegalStateException if the map has been closed
+     */
+    public long longSize() {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
-            return (int) nativeSizeWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
+            return nativeSizeWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
         }
         try (JniYTransaction txn = doc.beginTransaction()) {
-            return (int) nativeSizeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+            return nativeSizeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
         }
     }
 
     /**
      * Returns the number of entries in the map using an existing transaction.
      *
+     * <p>Unlike {@link #size(YTransaction)}, works for maps with more
+     * than {@link Integer#MAX_VALUE} entries.</p>
+     *
      * @param txn The transaction to use for this operation
      * @return The size of the map
      * @throws IllegalArgumentException if txn is null
      * @throws IllegalStateException if the map has been closed
      */
-    public int size(YTransaction txn) {
+    public long longSize(YTransaction txn) {
         checkClosed();
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
         }
-        return (int) nativeSizeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+        return nativeSizeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
     }
 
     /**
@@ -189,6 +287,56 @@ public class JniYMap implements YMap, JniYObservable {
         return nativeGetDoubleWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), key);
     }
 
+    /**
+     * Gets the values of several keys in one call.
+     *
+     * <p>Values are converted as in map events: strings, numbers and booleans become
+     * their Java types, nested plain arrays and maps become {@link java.util.List} and
+     * {@link java.util.Map}, and shared types become {@link JniTreeNode}s.</p>
+     *
+     * @param keys The keys to look up
+     * @return the value of each key at the same index, or null where the key is absent
+     * @throws IllegalArgumentException if keys or any key is null
+     * @throws IllegalStateException if the map has been closed
+     */
+    public Object[] getAll(String... keys) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getAll(activeTxn, keys);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getAll(txn, keys);
+        }
+    }
+
+    /**
+     * Gets the values of several keys in one call using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param keys The keys to look up
+     * @return the value of each key at the same index, or null where the key is absent
+     * @throws IllegalArgumentException if txn, keys or any key is null
+     * @throws IllegalStateException if the map has been closed
+     * @see #getAll(String...)
+     */
+    public Object[] getAll(YTransaction txn, String... keys) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (keys == null) {
+            throw new IllegalArgumentException("Keys cannot be null");
+        }
+        for (String key : keys) {
+            if (key == null) {
+                throw new IllegalArgumentException("Key cannot be null");
+            }
+        }
+        return nativeGetAllWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), keys);
+    }
+
     /**
      * Sets a string value in the map.
      *
@@ -350,6 +498,75 @@ public class JniYMap implements YMap, JniYObservable {
         nativeRemoveWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), key);
     }
 
+    /**
+     * Replaces the value of a key only if it currently equals an expected value.
+     *
+     * <p>The comparison and the write are done in one native call inside the
+     * transaction, so no other writer can change the key in between. This suits
+     * coordination fields such as a lock owner:</p>
+     * <pre>{@code
+     * if (map.compareAndSet("owner", null, clientName)) {
+     *     // acquired
+     * }
+     * map.compareAndSet("owner", clientName, null); // release
+     * }</pre>
+     *
+     * <p>Values may be {@link String}, {@link Long}, {@link Integer}, {@link Double},
+     * {@link Float}, {@link Boolean}, {@link JniUndefined#UNDEFINED} or null. Null
+     * does not match a stored {@code undefined}. Numbers compare by value, so
+     * {@code 1} matches a value set with {@code setDouble(key, 1.0)}. A key holding
+     * a shared type or subdocument never matches.</p>
+     *
+     * @param key The key to update
+     * @param expected The value the key must hold, or null to require that the key
+     *        is missing or holds null
+     * @param newValue The value to store, or null to remove the key
+     * @return true if the value matched and was replaced
+     * @throws IllegalArgumentException if key is null, or expected or newValue has an
+     *         unsupported type
+     * @throws IllegalStateException if the map has been closed
+     */
+    public boolean compareAndSet(String key, Object expected, Object newValue) {
+        checkClosed();
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return compareAndSet(activeTxn, key, expected, newValue);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return compareAndSet(txn, key, expected, newValue);
+        }
+    }
+
+    /**
+     * Replaces the value of a key only if it currently equals an expected value,
+     * using an existing transaction.
+     *
+     * @param txn The transaction to use
+     * @param key The key to update
+     * @param expected The value the key must hold, or null to require that the key
+     *        is missing or holds null
+     * @param newValue The value to store, or null to remove the key
+     * @return true if the value matched and was replaced
+     * @throws IllegalArgumentException if txn or key is null, or expected or newValue
+     *         has an unsupported type
+     * @throws IllegalStateException if the map or transaction has been closed
+     * @see #compareAndSet(String, Object, Object)
+     */
+    public boolean compareAndSet(YTransaction txn, String key, Object expected, Object newValue) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        return nativeCompareAndSetWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), key, expected, newValue);
+    }
+
     /**
      * Checks if a key exists in the map.
      *
@@ -393,6 +610,51 @@ public class JniYMap implements YMap, JniYObservable {
         return nativeContainsKeyWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), key);
     }
 
+    /**
+     * Returns whether a key is present and what kind of value it holds.
+     *
+     * <p>This replaces probing with several typed getters: one call tells the
+     * caller which getter applies.</p>
+     *
+     * @param key The key to look up
+     * @return the value type, or {@link ValueType#ABSENT} if the key is not present
+     * @throws IllegalArgumentException if key is null
+     * @throws IllegalStateException if the map has been closed
+     */
+    public ValueType getValueType(String key) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getValueType(activeTxn, key);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getValueType(txn, key);
+        }
+    }
+
+    /**
+     * Returns whether a key is present and what kind of value it holds, using an
+     * existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param key The key to look up
+     * @return the value type, or {@link ValueType#ABSENT} if the key is not present
+     * @throws IllegalArgumentException if txn or key is null
+     * @throws IllegalStateException if the map has been closed
+     */
+    public ValueType getValueType(YTransaction txn, String key) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        int tag = nativeGetValueTypeWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), key);
+        return ValueType.values()[tag];
+    }
+
     /**
      * Gets all keys from the map.
      *
@@ -436,6 +698,67 @@ public class JniYMap implements YMap, JniYObservable {
         return (String[]) result;
     }
 
+    /**
+     * Gets one page of the keys that start with a prefix.
+     *
+     * <p>Matching is done in native code and only the requested page is copied to
+     * Java, so maps used as indexes can be walked without fetching every key.
+     * Matching keys are sorted by Unicode code point, so consecutive pages do not
+     * overlap as long as the map is not changed between calls.</p>
+     *
+     * @param prefix The prefix keys must start with; the empty string matches every key
+     * @param limit The maximum number of keys to return
+     * @param offset The number of matching keys to skip
+     * @return The matching keys, in sorted order
+     * @throws IllegalArgumentException if prefix is null, or limit or offset is negative
+     * @throws IllegalStateException if the map has been closed
+     */
+    public String[] keysWithPrefix(String prefix, int limit, int offset) {
+        checkClosed();
+        checkPage(prefix, limit, offset);
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return keysWithPrefix(activeTxn, prefix, limit, offset);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return keysWithPrefix(txn, prefix, limit, offset);
+        }
+    }
+
+    /**
+     * Gets one page of the keys that start with a prefix using an existing
+     * transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param prefix The prefix keys must start with; the empty string matches every key
+     * @param limit The maximum number of keys to return
+     * @param offset The number of matching keys to skip
+     * @return The matching keys, in sorted order
+     * @throws IllegalArgumentException if txn or prefix is null, or limit or offset is
+     *         negative
+     * @throws IllegalStateException if the map has been closed
+     * @see #keysWithPrefix(String, int, int)
+     */
+    public String[] keysWithPrefix(YTransaction txn, String prefix, int limit, int offset) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        checkPage(prefix, limit, offset);
+        return nativeKeysWithPrefixWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), prefix, limit, offset);
+    }
+
+    private static void checkPage(String prefix, int limit, int offset) {
+        if (prefix == null) {
+            throw new IllegalArgumentException("Prefix cannot be null");
+        }
+        if (limit < 0 || offset < 0) {
+            throw new IllegalArgumentException(
+                "Limit and offset cannot be negative: limit " + limit + ", offset " + offset);
+        }
+    }
+
     /**
      * Removes all entries from the map.
      *
@@ -624,6 +947,122 @@ public class JniYMap implements YMap, JniYObservable {
         return new JniYDoc(subdocPtr, true);
     }
 
+    /**
+     * Stores a weak link to an entry of a map under a key (creates implicit transaction).
+     *
+     * <p>The link refers to whatever is stored under {@code sourceKey} in {@code source}
+     * and is resolved when read, so it follows later writes to that entry. This lets one
+     * part of a document refer to another without copying it:</p>
+     * <pre>{@code
+     * users.setString("u1", "Ann");
+     * task.setLink("assignee", users, "u1");
+     * users.setString("u1", "Anne");
+     * task.getLinked("assignee");   // "Anne"
+     * }</pre>
+     *
+     * <p>While the entry is removed, the link resolves to null; setting the key again
+     * makes it resolve to the new value. The source map may be this map.</p>
+     *
+     * @param key The key to store the link under
+     * @param source The map holding the linked entry
+     * @param sourceKey The key of the linked entry
+     * @throws IllegalArgumentException if an argument is null, source belongs to another
+     *         document, or source has no entry for sourceKey
+     * @throws IllegalStateException if this map or source has been closed
+     */
+    public void setLink(String key, JniYMap source, String sourceKey) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            setLink(activeTxn, key, source, sourceKey);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                setLink(txn, key, source, sourceKey);
+            }
+        }
+    }
+
+    /**
+     * Stores a weak link to an entry of a map under a key using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param key The key to store the link under
+     * @param source The map holding the linked entry
+     * @param sourceKey The key of the linked entry
+     * @throws IllegalArgumentException if an argument is null, source belongs to another
+     *         document, or source has no entry for sourceKey
+     * @throws IllegalStateException if this map or source has been closed
+     * @see #setLink(String, JniYMap, String)
+     */
+    public void setLink(YTransaction txn, String key, JniYMap source, String sourceKey) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (key == null || sourceKey == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        if (source == null) {
+            throw new IllegalArgumentException("Source map cannot be null");
+        }
+        if (source.doc != doc) {
+            throw new IllegalArgumentException("Source map belongs to a different document");
+        }
+        source.checkClosed();
+        nativeSetLinkWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            key, source.nativePtr, sourceKey);
+    }
+
+    /**
+     * Reads the value a weak link stored under a key refers to (creates implicit
+     * transaction).
+     *
+     * <p>Plain values are returned as by {@link JniYDoc#exportTree(int)}: strings,
+     * numbers, booleans, {@code byte[]}, lists and maps. A linked shared type is returned
+     * as a {@link JniTreeNode} with its whole content.</p>
+     *
+     * @param key The key the link is stored under
+     * @return The linked value, or null if the key does not hold a link or the linked
+     *         entry has been removed
+     * @throws IllegalArgumentException if key is null
+     * @throws IllegalStateException if the map has been closed
+     * @see #setLink(String, JniYMap, String)
+     */
+    public Object getLinked(String key) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getLinked(activeTxn, key);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getLinked(txn, key);
+        }
+    }
+
+    /**
+     * Reads the value a weak link stored under a key refers to using an existing
+     * transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param key The key the link is stored under
+     * @return The linked value, or null if the key does not hold a link or the linked
+     *         entry has been removed
+     * @throws IllegalArgumentException if txn or key is null
+     * @throws IllegalStateException if the map has been closed
+     * @see #getLinked(String)
+     */
+    public Object getLinked(YTransaction txn, String key) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (key == null) {
+            throw new IllegalArgumentException("Key cannot be null");
+        }
+        return nativeGetLinkedWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), key);
+    }
+
     /**
      * Returns a JSON string representation of the map.
      *
@@ -657,6 +1096,57 @@ public class JniYMap implements YMap, JniYObservable {
         return nativeToJsonWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
     }
 
+    /**
+     * Writes the JSON form of the map as UTF-8 into a direct buffer within an existing transaction.
+     *
+     * <p>The bytes are copied from native memory into the buffer starting at its
+     * position, without creating a Java string, which keeps the heap flat when
+     * many large structures are exported at once. If they fit in the buffer's
+     * remaining space, its position is advanced past them. Otherwise nothing is
+     * written, and the returned size tells the caller how large a buffer to
+     * provide.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param target A direct buffer to write into
+     * @return The size of the output in bytes; greater than the buffer's remaining
+     *         space if nothing was written
+     * @throws IllegalArgumentException if txn or target is null, or target is not direct
+     * @throws java.nio.ReadOnlyBufferException if target is read-only
+     * @throws IllegalStateException if the map has been closed
+     */
+    public int writeJson(YTransaction txn, ByteBuffer target) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        DirectBuffers.checkWritable(target);
+        int size = nativeWriteJsonWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            target, target.position(), target.remaining());
+        return DirectBuffers.advance(target, size);
+    }
+
+    /**
+     * Writes the JSON form of the map as UTF-8 into a direct buffer (creates implicit transaction).
+     *
+     * @param target A direct buffer to write into
+     * @return The size of the output in bytes; greater than the buffer's remaining
+     *         space if nothing was written
+     * @throws IllegalArgumentException if target is null or not direct
+     * @throws java.nio.ReadOnlyBufferException if target is read-only
+     * @throws IllegalStateException if the map has been closed
+     * @see #writeJson(YTransaction, ByteBuffer)
+     */
+    public int writeJson(ByteBuffer target) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return writeJson(activeTxn, target);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return writeJson(txn, target);
+        }
+    }
+
     /**
      * Registers an observer to be notified when this map changes.
      *
@@ -688,9 +1178,34 @@ public class JniYMap implements YMap, JniYObservable {
         if (observer == null) {
             throw new IllegalArgumentException("Observer cannot be null");
         }
-        long id = nextSubscriptionId.incrementAndGet();
+        long id = doc.allocateSubscriptionId();
+        observers.put(id, observer);
+        nativeObserve(doc.getNativePtr(), nativePtr, id, this, false);
+        return new JniYSubscription(id, observer, this);
+    }
+
+    /**
+     * Registers an observer that is told which keys changed, without their values.
+     *
+     * <p>Each change in the events has its type and key, but {@link JniYMapChange#getNewValue()}
+     * and {@link JniYMapChange#getOldValue()} return null. Observers that only invalidate
+     * caches can use this to skip converting the values, which is expensive for large
+     * values and shared types.</p>
+     *
+     * @param observer The observer to register (must not be null)
+     * @return A subscription handle that can be used to unregister the observer
+     * @throws IllegalArgumentException if observer is null
+     * @throws IllegalStateException if this map has been closed
+     * @see #observe(YObserver)
+     */
+    public YSubscription observeKeys(YObserver observer) {
+        checkClosed();
+        if (observer == null) {
+            throw new IllegalArgumentException("Observer cannot be null");
+        }
+        long id = doc.allocateSubscriptionId();
         observers.put(id, observer);
-        nativeObserve(doc.getNativePtr(), nativePtr, id, this);
+        nativeObserve(doc.getNativePtr(), nativePtr, id, this, true);
         return new JniYSubscription(id, observer, this);
     }
 
@@ -763,7 +1278,8 @@ public class JniYMap implements YMap, JniYObservable {
                     }
                     observers.clear();
 
-                    if (nativePtr != 0) {
+                    // The document frees released handles itself
+                    if (nativePtr != 0 && !isReleased()) {
                         nativeDestroy(nativePtr);
                         nativePtr = 0;
                     }
@@ -782,6 +1298,18 @@ public class JniYMap implements YMap, JniYObservable {
         if (closed) {
             throw new IllegalStateException("YMap has been closed");
         }
+        if (isReleased()) {
+            throw new IllegalStateException("YMap was released by its YDoc");
+        }
+    }
+
+    /**
+     * Returns whether the document released the native handle of this root.
+     *
+     * @return true after {@link JniYDoc#releaseAllRoots()} or closing the document
+     */
+    private boolean isReleased() {
+        return rootGeneration >= 0 && doc.getRootGeneration() != rootGeneration;
     }
 
     /**
@@ -797,26 +1325,43 @@ public class JniYMap implements YMap, JniYObservable {
     private static native long nativeGetMap(long docPtr, String name);
     private static native void nativeDestroy(long ptr);
     private static native long nativeSizeWithTxn(long docPtr, long mapPtr, long txnPtr);
+    private static native Object[] nativeGetPathWithTxn(long docPtr, long mapPtr, long txnPtr);
     private static native String nativeGetStringWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                          String key);
     private static native double nativeGetDoubleWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                          String key);
+
+    private static native Object[] nativeGetAllWithTxn(long docPtr, long mapPtr, long txnPtr,
+                                                        String[] keys);
+
     private static native void nativeSetStringWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                        String key, String value);
     private static native void nativeSetDoubleWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                        String key, double value);
     private static native void nativeRemoveWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                     String key);
+    private static native boolean nativeCompareAndSetWithTxn(long docPtr, long mapPtr, long txnPtr,
+                                                             String key, Object expected, Object newValue);
     private static native boolean nativeContainsKeyWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                             String key);
+    private static native int nativeGetValueTypeWithTxn(long docPtr, long mapPtr, long txnPtr,
+        String key);
     private static native Object nativeKeysWithTxn(long docPtr, long mapPtr, long txnPtr);
+    private static native String[] nativeKeysWithPrefixWithTxn(long docPtr, long mapPtr, long txnPtr,
+                                                               String prefix, int limit, int offset);
     private static native void nativeClearWithTxn(long docPtr, long mapPtr, long txnPtr);
     private static native String nativeToJsonWithTxn(long docPtr, long mapPtr, long txnPtr);
+    private static native int nativeWriteJsonWithTxn(long docPtr, long mapPtr, long txnPtr,
+                                                     ByteBuffer buffer, int offset, int length);
     private static native void nativeSetDocWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                     String key, long subdocPtr);
     private static native long nativeGetDocWithTxn(long docPtr, long mapPtr, long txnPtr,
                                                     String key);
+    private static native void nativeSetLinkWithTxn(long docPtr, long mapPtr, long txnPtr,
+        String key, long sourcePtr, String sourceKey);
+    private static native Object nativeGetLinkedWithTxn(long docPtr, long mapPtr, long txnPtr,
+        String key);
     private static native void nativeObserve(long docPtr, long mapPtr, long subscriptionId,
-                                              YMap ymapObj);
+                                              YMap ymapObj, boolean keysOnly);
     private static native void nativeUnobserve(long docPtr, long mapPtr, long subscriptionId);
 }
@@ -12,6 +12,14 @@ import net.carcdr.ycrdt.YMapChange;
  *   <li><b>ATTRIBUTE:</b> A key's value was modified</li>
  * </ul>
  *
+ * <p>Values are plain Java values (strings, numbers, booleans, {@code byte[]},
+ * {@code List} and {@code Map}) or, for shared types, fully expanded
+ * {@link JniTreeNode}s read when the event is delivered. A shared type removed
+ * by the change has lost its content by then, so its node is empty.
+ *
+ * <p>Changes delivered to observers registered with {@link JniYMap#observeKeys}
+ * carry no values.</p>
+ *
  * @see JniYEvent
  */
 public final class JniYMapChange extends YMapChange {
@@ -6,8 +6,13 @@ import net.carcdr.ycrdt.YText;
 import net.carcdr.ycrdt.YTransaction;
 
 import java.io.Closeable;
+import java.nio.ByteBuffer;
+import java.nio.ByteOrder;
+import java.nio.CharBuffer;
+import java.util.List;
+import java.util.Map;
 import java.util.concurrent.ConcurrentHashMap;
-import java.util.concurrent.atomic.AtomicLong;
+import java.util.function.Consumer;
 
 /**
  * YText represents a collaborative text type in a Y-CRDT document.
@@ -36,190 +41,1158 @@ public class JniYText implements YText, JniYObservable {
     private final JniYDoc doc;
     private long nativePtr;
     private volatile boolean closed = false;
+
+    /**
+     * Root generation of the document when this root was looked up by name, or
+     * -1 if the handle was not obtained by name.
+     */
+    private final int rootGeneration;
     private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
-    private final AtomicLong nextSubscriptionId = new AtomicLong(0);
 
     /**
-     * Package-private constructor. Use {@link YDoc#getText(String)} to create instances.
+     * Package-private constructor. Use {@link YDoc#getText(String)} to create instances.
+     *
+     * @param doc The parent YDoc instance
+     * @param name The name of this text object in the document
+     */
+    JniYText(JniYDoc doc, String name) {
+        if (doc == null) {
+            throw new IllegalArgumentException("YDoc cannot be null");
+        }
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        this.doc = doc;
+        this.rootGeneration = doc.getRootGeneration();
+        this.nativePtr = nativeGetText(doc.getNativePtr(), name);
+        if (this.nativePtr == 0) {
+            throw new RuntimeException("Failed to create YText");
+        }
+    }
+
+    /**
+     * Returns the length of the text.
+     *
+     * @return The number of characters in the text
+     * @throws ArithmeticException if the length does not fit in an int
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int length() {
+        return Sizes.toInt(longLength(), "longLength");
+    }
+
+    /**
+     * Returns the length of the text.
+     *
+     * @param txn The transaction to use for this operation
+     * @return The number of characters in the text
+     * @throws ArithmeticException if the length does not fit in an int
+     * @throws IllegalStateException if the text has been closed
+     */
+    @Override
+    public int length(YTransaction txn) {
+        return Sizes.toInt(longLength(txn), "longLength");
+    }
+
+    /**
+     * Returns the length of the text.
+     *
+     * <p>Unlike {@link #length()}, works for texts longer
+     * than {@link Integer#MAX_VALUE}.</p>
+     *
+     * @return The number of characters in the text
+     * @throws IllegalStateException if the text has been closed
+     */
+    public long longLength() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+        }
+    }
+
+    /**
+     * Returns the length of the text.
+     *
+     * <p>Unlike {@link #length(YTransaction)}, works for texts longer
+     * than {@link Integer#MAX_VALUE}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return The number of characters in the text
+     * @throws IllegalStateException if the text has been closed
+     */
+    public long longLength(YTransaction txn) {
+        checkClosed();
+        return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Returns the root this text belongs to and the path leading to it.
+     *
+     * @return the path, or null if the text was deleted or is embedded in a text
+     * @throws IllegalStateException if the text has been closed
+     */
+    public JniTypePath getPath() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getPath(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getPath(txn);
+        }
+    }
+
+    /**
+     * Returns the root this text belongs to and the path leading to it.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the path, or null if the text was deleted or is embedded in a text
+     * @throws IllegalStateException if the text has been closed
+     */
+    public JniTypePath getPath(YTransaction txn) {
+        checkClosed();
+        return JniTypePath.fromNative(
+            nativeGetPathWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr()));
+    }
+
+    /**
+     * Returns the text content as a string.
+     *
+     * @return The current text content
+     * @throws IllegalStateException if the text has been closed
+     */
+    @Override
+    public String toString() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return nativeToStringWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return nativeToStringWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+        }
+    }
+
+    /**
+     * Writes the text content as UTF-8 into a direct buffer within an existing transaction.
+     *
+     * <p>The bytes are copied from native memory into the buffer starting at its
+     * position, without creating a Java string. If they fit in the buffer's
+     * remaining space, its position is advanced past them. Otherwise nothing is
+     * written, and the returned size tells the caller how large a buffer to
+     * provide.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param target A direct buffer to write into
+     * @return The size of the content in bytes; greater than the buffer's remaining
+     *         space if nothing was written
+     * @throws IllegalArgumentException if txn or target is null, or target is not direct
+     * @throws java.nio.ReadOnlyBufferException if target is read-only
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int writeString(YTransaction txn, ByteBuffer target) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        DirectBuffers.checkWritable(target);
+        int size = nativeWriteStringWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            target, target.position(), target.remaining());
+        return DirectBuffers.advance(target, size);
+    }
+
+    /**
+     * Writes the text content as UTF-8 into a direct buffer (creates implicit transaction).
+     *
+     * @param target A direct buffer to write into
+     * @return The size of the content in bytes; greater than the buffer's remaining
+     *         space if nothing was written
+     * @throws IllegalArgumentException if target is null or not direct
+     * @throws java.nio.ReadOnlyBufferException if target is read-only
+     * @throws IllegalStateException if the text has been closed
+     * @see #writeString(YTransaction, ByteBuffer)
+     */
+    public int writeString(ByteBuffer target) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return writeString(activeTxn, target);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return writeString(txn, target);
+        }
+    }
+
+    /**
+     * Returns a piece of the text content within an existing transaction.
+     *
+     * <p>Offsets and sizes count bytes of the UTF-8 encoding of {@link #toString()}, and
+     * a chunk never ends inside a character. The chunk is at most {@code maxBytes} long
+     * unless the character at {@code offset} is longer, in which case the chunk holds
+     * just that character. The next chunk starts at {@code offset} plus the UTF-8
+     * length of this one.</p>
+     *
+     * <p>Each call builds the full content natively. To read all of a large text,
+     * use {@link #readChunks(YTransaction, int, Consumer)}, which does so once.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param offset the UTF-8 byte offset to start at
+     * @param maxBytes the maximum UTF-8 length of the chunk
+     * @return the chunk, or an empty string if offset is at the end of the content
+     * @throws IllegalArgumentException if txn is null, maxBytes is not positive, or
+     *         offset is negative, past the end or inside a character
+     * @throws IllegalStateException if the text has been closed
+     */
+    public String getStringChunk(YTransaction txn, int offset, int maxBytes) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeGetStringChunkWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), offset, maxBytes);
+    }
+
+    /**
+     * Returns a piece of the text content (creates implicit transaction).
+     *
+     * @param offset the UTF-8 byte offset to start at
+     * @param maxBytes the maximum UTF-8 length of the chunk
+     * @return the chunk, or an empty string if offset is at the end of the content
+     * @throws IllegalArgumentException if maxBytes is not positive, or offset is
+     *         negative, past the end or inside a character
+     * @throws IllegalStateException if the text has been closed
+     * @see #getStringChunk(YTransaction, int, int)
+     */
+    public String getStringChunk(int offset, int maxBytes) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getStringChunk(activeTxn, offset, maxBytes);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getStringChunk(txn, offset, maxBytes);
+        }
+    }
+
+    /**
+     * Passes the text content to a consumer in chunks within an existing transaction.
+     *
+     * <p>Concatenating the chunks gives {@link #toString()}, but no single Java string
+     * larger than a chunk is created. Chunks follow the rules of
+     * {@link #getStringChunk(YTransaction, int, int)}. An exception thrown by the
+     * consumer stops the read and is rethrown.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param maxBytes the maximum UTF-8 length of each chunk
+     * @param consumer receives the chunks in order
+     * @throws IllegalArgumentException if txn or consumer is null, or maxBytes is not
+     *         positive
+     * @throws IllegalStateException if the text has been closed
+     */
+    public void readChunks(YTransaction txn, int maxBytes, Consumer<String> consumer) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (consumer == null) {
+            throw new IllegalArgumentException("Consumer cannot be null");
+        }
+        nativeReadChunksWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), maxBytes, consumer);
+    }
+
+    /**
+     * Passes the text content to a consumer in chunks (creates implicit transaction).
+     *
+     * @param maxBytes the maximum UTF-8 length of each chunk
+     * @param consumer receives the chunks in order
+     * @throws IllegalArgumentException if consumer is null or maxBytes is not positive
+     * @throws IllegalStateException if the text has been closed
+     * @see #readChunks(YTransaction, int, Consumer)
+     */
+    public void readChunks(int maxBytes, Consumer<String> consumer) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            readChunks(activeTxn, maxBytes, consumer);
+            return;
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            readChunks(txn, maxBytes, consumer);
+        }
+    }
+
+    /**
+     * Returns the offsets at which the lines of this text start within an existing
+     * transaction.
+     *
+     * <p>Lines end after each {@code \n}, so a {@code \r} before it belongs to the
+     * line it ends. Offsets are in the same units as the text's indexes. The lines
+     * are found natively, so an editor gutter can be refreshed without copying the
+     * text to Java.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return the start offsets, one per line; the first is always 0
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int[] lineStarts(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeLineStartsWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Returns the offsets at which the lines of this text start (creates implicit
+     * transaction).
+     *
+     * @return the start offsets, one per line; the first is always 0
+     * @throws IllegalStateException if the text has been closed
+     * @see #lineStarts(YTransaction)
+     */
+    public int[] lineStarts() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return lineStarts(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return lineStarts(txn);
+        }
+    }
+
+    /**
+     * Returns a range of fixed-size blocks of this text within an existing transaction.
+     *
+     * <p>The text is split into consecutive blocks of {@code blockSize} units, and
+     * blocks {@code firstBlock} to {@code firstBlock + maxBlocks - 1} are returned,
+     * fewer at the end of the text. Only those blocks are copied to Java, so a
+     * virtualized view of a huge text can fetch what is on screen. Each block
+     * carries a sticky index on its first character; see {@link JniTextBlock}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param blockSize The length of a block, in the text's index units
+     * @param firstBlock The number of the first block to return
+     * @param maxBlocks The maximum number of blocks to return
+     * @return the blocks, in order
+     * @throws IllegalArgumentException if txn is null, blockSize is not positive, or
+     *         firstBlock or maxBlocks is negative
+     * @throws IllegalStateException if the text has been closed
+     */
+    public List<JniTextBlock> blocks(YTransaction txn, int blockSize, int firstBlock,
+                                     int maxBlocks) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (blockSize <= 0) {
+            throw new IllegalArgumentException("Block size must be positive: " + blockSize);
+        }
+        if (firstBlock < 0 || maxBlocks < 0) {
+            throw new IllegalArgumentException(
+                "Block range cannot be negative: " + firstBlock + ", " + maxBlocks);
+        }
+        return nativeBlocksWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), blockSize, firstBlock, maxBlocks);
+    }
+
+    /**
+     * Returns a range of fixed-size blocks of this text (creates implicit transaction).
+     *
+     * @param blockSize The length of a block, in the text's index units
+     * @param firstBlock The number of the first block to return
+     * @param maxBlocks The maximum number of blocks to return
+     * @return the blocks, in order
+     * @throws IllegalArgumentException if blockSize is not positive, or firstBlock or
+     *         maxBlocks is negative
+     * @throws IllegalStateException if the text has been closed
+     * @see #blocks(YTransaction, int, int, int)
+     */
+    public List<JniTextBlock> blocks(int blockSize, int firstBlock, int maxBlocks) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return blocks(activeTxn, blockSize, firstBlock, maxBlocks);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return blocks(txn, blockSize, firstBlock, maxBlocks);
+        }
+    }
+
+    /**
+     * Returns the number of lines of this text within an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the number of line breaks plus one
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int lineCount(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeLineCountWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Returns the number of lines of this text (creates implicit transaction).
+     *
+     * @return the number of line breaks plus one
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int lineCount() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return lineCount(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return lineCount(txn);
+        }
+    }
+
+    /**
+     * Returns the length, code point, word and line counts of this text within an
+     * existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the counts, computed natively in one pass
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the text has been closed
+     */
+    public JniTextStats stats(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        long[] stats = nativeStatsWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        return new JniTextStats(stats[0], stats[1], stats[2], stats[3]);
+    }
+
+    /**
+     * Returns the length, code point, word and line counts of this text (creates
+     * implicit transaction).
+     *
+     * <p>Cheap enough to call after every keystroke to refresh a status bar, since
+     * the text is not copied to Java.</p>
+     *
+     * @return the counts, computed natively in one pass
+     * @throws IllegalStateException if the text has been closed
+     */
+    public JniTextStats stats() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return stats(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return stats(txn);
+        }
+    }
+
+    /**
+     * Returns the line and column of an offset within an existing transaction.
+     *
+     * <p>The offset of a line break is the last column of the line it ends.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param offset the offset, from 0 to the length of the text
+     * @return the position of the offset
+     * @throws IllegalArgumentException if txn is null
+     * @throws IndexOutOfBoundsException if offset is negative or past the end
+     * @throws IllegalStateException if the text has been closed
+     */
+    public JniTextPosition positionOf(YTransaction txn, int offset) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        int[] position = nativePositionWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), offset);
+        return new JniTextPosition(position[0], position[1]);
+    }
+
+    /**
+     * Returns the line and column of an offset (creates implicit transaction).
+     *
+     * @param offset the offset, from 0 to the length of the text
+     * @return the position of the offset
+     * @throws IndexOutOfBoundsException if offset is negative or past the end
+     * @throws IllegalStateException if the text has been closed
+     * @see #positionOf(YTransaction, int)
+     */
+    public JniTextPosition positionOf(int offset) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return positionOf(activeTxn, offset);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return positionOf(txn, offset);
+        }
+    }
+
+    /**
+     * Returns the offset of a line and column within an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param line the 0-based line
+     * @param column the 0-based column, at most the length of the line without its
+     *        line break
+     * @return the offset of the position
+     * @throws IllegalArgumentException if txn is null
+     * @throws IndexOutOfBoundsException if there is no such line or column
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int offsetOf(YTransaction txn, int line, int column) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeOffsetWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), line, column);
+    }
+
+    /**
+     * Returns the offset of a line and column (creates implicit transaction).
+     *
+     * @param line the 0-based line
+     * @param column the 0-based column, at most the length of the line without its
+     *        line break
+     * @return the offset of the position
+     * @throws IndexOutOfBoundsException if there is no such line or column
+     * @throws IllegalStateException if the text has been closed
+     * @see #offsetOf(YTransaction, int, int)
+     */
+    public int offsetOf(int line, int column) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return offsetOf(activeTxn, line, column);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return offsetOf(txn, line, column);
+        }
+    }
+
+    /**
+     * Fills this text from a Quill document delta within an existing transaction.
+     *
+     * <p>The delta is JSON, either {@code {"ops":[...]}} or the bare operation array,
+     * and may only contain inserts of a string or an embed object, each with optional
+     * attributes. All runs are inserted in the given transaction, so observers see
+     * one change. Attributes must be in the document's allowed attributes, if set.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param deltaJson the delta to import
+     * @throws IllegalArgumentException if txn or deltaJson is null, or the delta is
+     *         malformed or contains operations other than inserts
+     * @throws IllegalStateException if the text has been closed or is not empty
+     */
+    public void importDelta(YTransaction txn, String deltaJson) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (deltaJson == null) {
+            throw new IllegalArgumentException("Delta cannot be null");
+        }
+        nativeImportDeltaWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), deltaJson);
+    }
+
+    /**
+     * Fills this text from a Quill document delta (creates implicit transaction).
+     *
+     * @param deltaJson the delta to import
+     * @throws IllegalArgumentException if deltaJson is null, or the delta is malformed
+     *         or contains operations other than inserts
+     * @throws IllegalStateException if the text has been closed or is not empty
+     * @see #importDelta(YTransaction, String)
+     */
+    public void importDelta(String deltaJson) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            importDelta(activeTxn, deltaJson);
+            return;
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            importDelta(txn, deltaJson);
+        }
+    }
+
+    /**
+     * Returns the content of this text as a Quill document delta within an existing
+     * transaction.
+     *
+     * <p>The result has the form {@code {"ops":[...]}} with one insert per formatting
+     * run. Attribute keys are sorted, so equal content gives equal JSON.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return the delta as JSON
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the text has been closed
+     */
+    public String exportDelta(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeExportDeltaWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Returns the content of this text as a Quill document delta (creates implicit
+     * transaction).
+     *
+     * @return the delta as JSON
+     * @throws IllegalStateException if the text has been closed
+     * @see #exportDelta(YTransaction)
+     */
+    public String exportDelta() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return exportDelta(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return exportDelta(txn);
+        }
+    }
+
+    /**
+     * Inserts text at the specified index within an existing transaction.
+     *
+     * <p>Use this method to batch multiple operations:
+     * <pre>{@code
+     * try (JniYTransaction txn = doc.beginTransaction()) {
+     *     text.insert(txn, 0, "Hello");
+     *     text.insert(txn, 5, " World");
+     * }
+     * }</pre>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert the text (0-based)
+     * @param chunk The text to insert
+     * @throws IllegalArgumentException if txn or chunk is null
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    @Override
+    public void insert(YTransaction txn, int index, String chunk) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
+        }
+        if (index < 0 || index > length(txn)) {
+            throw new IndexOutOfBoundsException(
+                "Index " + index + " out of bounds for length " + length(txn));
+        }
+        nativeInsertWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), index, chunk);
+    }
+
+    /**
+     * Inserts text at the specified index (creates implicit transaction).
+     *
+     * @param index The position at which to insert the text (0-based)
+     * @param chunk The text to insert
+     * @throws IllegalArgumentException if chunk is null
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    public void insert(int index, String chunk) {
+        checkClosed();
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
+        }
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            if (index < 0 || index > length(activeTxn)) {
+                throw new IndexOutOfBoundsException(
+                    "Index " + index + " out of bounds for length " + length(activeTxn));
+            }
+            nativeInsertWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr(), index, chunk);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                if (index < 0 || index > length(txn)) {
+                    throw new IndexOutOfBoundsException(
+                        "Index " + index + " out of bounds for length " + length(txn));
+                }
+                nativeInsertWithTxn(doc.getNativePtr(), nativePtr,
+                    ((JniYTransaction) txn).getNativePtr(), index, chunk);
+            }
+        }
+    }
+
+    /**
+     * Inserts a range of a char array at the specified index within an existing
+     * transaction, without creating an intermediate {@link String}.
+     *
+     * <p>The chars are read as UTF-16 code units. Unpaired surrogates are handled
+     * according to the document's {@link JniYDoc.StringPolicy}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert the text (0-based)
+     * @param chars The array holding the text to insert
+     * @param offset The index of the first char to insert
+     * @param length The number of chars to insert
+     * @throws IllegalArgumentException if txn or chars is null, or if the range
+     *         holds an unpaired surrogate and the document uses
+     *         {@link JniYDoc.StringPolicy#STRICT}
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the
+     *         current length, or if the range is outside the array
+     */
+    public void insert(YTransaction txn, int index, char[] chars, int offset, int length) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        checkCharRange(chars, offset, length);
+        if (index < 0 || index > length(txn)) {
+            throw new IndexOutOfBoundsException(
+                "Index " + index + " out of bounds for length " + length(txn));
+        }
+        nativeInsertCharsWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), index, chars, offset, length);
+    }
+
+    /**
+     * Inserts a range of a char array at the specified index (creates implicit
+     * transaction).
+     *
+     * @param index The position at which to insert the text (0-based)
+     * @param chars The array holding the text to insert
+     * @param offset The index of the first char to insert
+     * @param length The number of chars to insert
+     * @throws IllegalArgumentException if chars is null, or if the range holds an
+     *         unpaired surrogate and the document uses {@link JniYDoc.StringPolicy#STRICT}
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the
+     *         current length, or if the range is outside the array
+     * @see #insert(YTransaction, int, char[], int, int)
+     */
+    public void insert(int index, char[] chars, int offset, int length) {
+        checkClosed();
+        checkCharRange(chars, offset, length);
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            insert(activeTxn, index, chars, offset, length);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                insert(txn, index, chars, offset, length);
+            }
+        }
+    }
+
+    /**
+     * Inserts the remaining chars of a buffer at the specified index within an
+     * existing transaction, without creating an intermediate {@link String}.
+     *
+     * <p>Heap buffers and direct buffers in the platform's native byte order are
+     * read in place; other buffers are copied first. On success the buffer's
+     * position is advanced to its limit. Unpaired surrogates are handled
+     * according to the document's {@link JniYDoc.StringPolicy}.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index The position at which to insert the text (0-based)
+     * @param chars The buffer holding the text to insert
+     * @throws IllegalArgumentException if txn or chars is null, or if the buffer
+     *         holds an unpaired surrogate and the document uses
+     *         {@link JniYDoc.StringPolicy#STRICT}
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the
+     *         current length
+     */
+    public void insert(YTransaction txn, int index, CharBuffer chars) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (chars == null) {
+            throw new IllegalArgumentException("Chars cannot be null");
+        }
+        if (index < 0 || index > length(txn)) {
+            throw new IndexOutOfBoundsException(
+                "Index " + index + " out of bounds for length " + length(txn));
+        }
+        long txnPtr = ((JniYTransaction) txn).getNativePtr();
+        int position = chars.position();
+        int remaining = chars.remaining();
+        if (chars.hasArray()) {
+            nativeInsertCharsWithTxn(doc.getNativePtr(), nativePtr, txnPtr, index,
+                chars.array(), chars.arrayOffset() + position, remaining);
+        } else if (chars.isDirect() && chars.order() == ByteOrder.nativeOrder()) {
+            nativeInsertDirectCharsWithTxn(doc.getNativePtr(), nativePtr, txnPtr, index,
+                chars, position, remaining);
+        } else {
+            char[] copy = new char[remaining];
+            chars.duplicate().get(copy);
+            nativeInsertCharsWithTxn(doc.getNativePtr(), nativePtr, txnPtr, index,
+                copy, 0, remaining);
+        }
+        chars.position(position + remaining);
+    }
+
+    /**
+     * Inserts the remaining chars of a buffer at the specified index (creates
+     * implicit transaction).
+     *
+     * @param index The position at which to insert the text (0-based)
+     * @param chars The buffer holding the text to insert
+     * @throws IllegalArgumentException if chars is null, or if the buffer holds an
+     *         unpaired surrogate and the document uses {@link JniYDoc.StringPolicy#STRICT}
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the
+     *         current length
+     * @see #insert(YTransaction, int, CharBuffer)
+     */
+    public void insert(int index, CharBuffer chars) {
+        checkClosed();
+        if (chars == null) {
+            throw new IllegalArgumentException("Chars cannot be null");
+        }
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            insert(activeTxn, index, chars);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                insert(txn, index, chars);
+            }
+        }
+    }
+
+    private static void checkCharRange(char[] chars, int offset, int length) {
+        if (chars == null) {
+            throw new IllegalArgumentException("Chars cannot be null");
+        }
+        if (offset < 0 || length < 0 || offset > chars.length - length) {
+            throw new IndexOutOfBoundsException(
+                "Range [" + offset + ", " + offset + " + " + length
+                    + ") out of bounds for length " + chars.length);
+        }
+    }
+
+    /**
+     * Appends text to the end within an existing transaction.
      *
-     * @param doc The parent YDoc instance
-     * @param name The name of this text object in the document
+     * <p>Use this method to batch multiple operations:
+     * <pre>{@code
+     * try (JniYTransaction txn = doc.beginTransaction()) {
+     *     text.push(txn, "Hello");
+     *     text.push(txn, " World");
+     * }
+     * }</pre>
+     *
+     * @param txn The transaction to use for this operation
+     * @param chunk The text to append
+     * @throws IllegalArgumentException if txn or chunk is null
+     * @throws IllegalStateException if the text has been closed
      */
-    JniYText(JniYDoc doc, String name) {
-        if (doc == null) {
-            throw new IllegalArgumentException("YDoc cannot be null");
+    @Override
+    public void push(YTransaction txn, String chunk) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
         }
-        if (name == null) {
-            throw new IllegalArgumentException("Name cannot be null");
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
         }
-        this.doc = doc;
-        this.nativePtr = nativeGetText(doc.getNativePtr(), name);
-        if (this.nativePtr == 0) {
-            throw new RuntimeException("Failed to create YText");
+        nativePushWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), chunk);
+    }
+
+    /**
+     * Appends text to the end (creates implicit transaction).
+     *
+     * @param chunk The text to append
+     * @throws IllegalArgumentException if chunk is null
+     * @throws IllegalStateException if the text has been closed
+     */
+    public void push(String chunk) {
+        checkClosed();
+        if (chunk == null) {
+            throw new IllegalArgumentException("Chunk cannot be null");
+        }
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            nativePushWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr(), chunk);
+        } else {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                nativePushWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), chunk);
+            }
         }
     }
 
     /**
-     * Returns the length of the text.
+     * Creates a sticky index for a position within an existing transaction.
      *
-     * @return The number of characters in the text
+     * @param txn The transaction to use for this operation
+     * @param index The position to track (0-based, may equal the length)
+     * @param assoc Which side of the position the index is attached to
+     * @return the sticky index
+     * @throws IllegalArgumentException if txn or assoc is null
      * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
      */
-    public int length() {
+    public JniStickyIndex stickyIndex(YTransaction txn, int index, JniStickyIndex.Assoc assoc) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (assoc == null) {
+            throw new IllegalArgumentException("Assoc cannot be null");
+        }
+        if (index < 0 || index > length(txn)) {
+            throw new IndexOutOfBoundsException(
+                "Index " + index + " out of bounds for length " + length(txn));
+        }
+        byte[] encoded = nativeStickyIndexWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), index, assoc.getNativeValue());
+        if (encoded == null) {
+            throw new IndexOutOfBoundsException(
+                "Index " + index + " out of bounds for length " + length(txn));
+        }
+        return JniStickyIndex.fromNative(encoded);
+    }
+
+    /**
+     * Creates a sticky index for a position (creates implicit transaction).
+     *
+     * @param index The position to track (0-based, may equal the length)
+     * @param assoc Which side of the position the index is attached to
+     * @return the sticky index
+     * @throws IllegalArgumentException if assoc is null
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     */
+    public JniStickyIndex stickyIndex(int index, JniStickyIndex.Assoc assoc) {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
-            return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
+            return stickyIndex(activeTxn, index, assoc);
         }
         try (JniYTransaction txn = doc.beginTransaction()) {
-            return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+            return stickyIndex(txn, index, assoc);
         }
     }
 
     /**
-     * Returns the length of the text.
+     * Resolves a sticky index to its current position within an existing transaction.
      *
      * @param txn The transaction to use for this operation
-     * @return The number of characters in the text
+     * @param sticky The sticky index to resolve
+     * @return the current index, or -1 if the sticky index does not point into this text
+     * @throws IllegalArgumentException if txn or sticky is null, or sticky is malformed
      * @throws IllegalStateException if the text has been closed
      */
-    @Override
-    public int length(YTransaction txn) {
+    public int resolveStickyIndex(YTransaction txn, JniStickyIndex sticky) {
         checkClosed();
-        return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (sticky == null) {
+            throw new IllegalArgumentException("Sticky index cannot be null");
+        }
+        return nativeResolveStickyIndexWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), sticky.getEncoded());
     }
 
     /**
-     * Returns the text content as a string.
+     * Resolves a sticky index to its current position (creates implicit transaction).
      *
-     * @return The current text content
+     * @param sticky The sticky index to resolve
+     * @return the current index, or -1 if the sticky index does not point into this text
+     * @throws IllegalArgumentException if sticky is null or malformed
      * @throws IllegalStateException if the text has been closed
      */
-    @Override
-    public String toString() {
+    public int resolveStickyIndex(JniStickyIndex sticky) {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
-            return nativeToStringWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
+            return resolveStickyIndex(activeTxn, sticky);
         }
         try (JniYTransaction txn = doc.beginTransaction()) {
-            return nativeToStringWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+            return resolveStickyIndex(txn, sticky);
         }
     }
 
     /**
-     * Inserts text at the specified index within an existing transaction.
+     * Inserts text at the current position of a sticky index within an existing transaction.
      *
-     * <p>Use this method to batch multiple operations:
-     * <pre>{@code
-     * try (JniYTransaction txn = doc.beginTransaction()) {
-     *     text.insert(txn, 0, "Hello");
-     *     text.insert(txn, 5, " World");
-     * }
-     * }</pre>
+     * <p>The sticky index is resolved natively inside the transaction, so the
+     * position cannot move between resolving it and inserting. This is the safe
+     * way to insert at a collaborator's cursor.</p>
      *
      * @param txn The transaction to use for this operation
-     * @param index The position at which to insert the text (0-based)
+     * @param sticky The position to insert at
      * @param chunk The text to insert
-     * @throws IllegalArgumentException if txn or chunk is null
+     * @return the index the text was inserted at
+     * @throws IllegalArgumentException if any argument is null, or sticky is malformed
+     *         or does not point into this text
      * @throws IllegalStateException if the text has been closed
-     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
      */
-    @Override
-    public void insert(YTransaction txn, int index, String chunk) {
+    public int insert(YTransaction txn, JniStickyIndex sticky, String chunk) {
         checkClosed();
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
         }
+        if (sticky == null) {
+            throw new IllegalArgumentException("Sticky index cannot be null");
+        }
         if (chunk == null) {
             throw new IllegalArgumentException("Chunk cannot be null");
         }
-        if (index < 0 || index > length(txn)) {
-            throw new IndexOutOfBoundsException(
-                "Index " + index + " out of bounds for length " + length(txn));
-        }
-        nativeInsertWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), index, chunk);
+        return nativeInsertAtStickyIndexWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), sticky.getEncoded(), chunk);
     }
 
     /**
-     * Inserts text at the specified index (creates implicit transaction).
+     * Inserts text at the current position of a sticky index (creates implicit transaction).
      *
-     * @param index The position at which to insert the text (0-based)
+     * @param sticky The position to insert at
      * @param chunk The text to insert
-     * @throws IllegalArgumentException if chunk is null
+     * @return the index the text was inserted at
+     * @throws IllegalArgumentException if sticky or chunk is null, or sticky is malformed
+     *         or does not point into this text
      * @throws IllegalStateException if the text has been closed
-     * @throws IndexOutOfBoundsException if index is negative or greater than the current length
+     * @see #insert(YTransaction, JniStickyIndex, String)
      */
-    public void insert(int index, String chunk) {
+    public int insert(JniStickyIndex sticky, String chunk) {
         checkClosed();
-        if (chunk == null) {
-            throw new IllegalArgumentException("Chunk cannot be null");
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return insert(activeTxn, sticky, chunk);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return insert(txn, sticky, chunk);
         }
+    }
+
+    /**
+     * Applies formatting to several ranges within an existing transaction.
+     *
+     * <p>All ranges are applied in one native call. They are validated before any is
+     * applied, so an out-of-bounds range leaves the text unchanged. Ranges are
+     * applied in list order, so later ranges win where they overlap.</p>
+     *
+     * @param txn Transaction handle
+     * @param ranges The ranges to format and their attributes
+     * @throws IllegalArgumentException if txn or ranges is null, or ranges contains null
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if any range extends past the end of the text
+     */
+    public void formatRanges(YTransaction txn, List<JniFormatRange> ranges) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        JniFormatRange.requireValid(ranges);
+        if (ranges.isEmpty()) {
+            return;
+        }
+        nativeFormatRangesWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            JniFormatRange.toBounds(ranges), JniFormatRange.toAttributes(ranges));
+    }
+
+    /**
+     * Applies formatting to several ranges (creates implicit transaction).
+     *
+     * @param ranges The ranges to format and their attributes
+     * @throws IllegalArgumentException if ranges is null or contains null
+     * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if any range extends past the end of the text
+     * @see #formatRanges(YTransaction, List)
+     */
+    public void formatRanges(List<JniFormatRange> ranges) {
+        checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
-            if (index < 0 || index > length(activeTxn)) {
-                throw new IndexOutOfBoundsException(
-                    "Index " + index + " out of bounds for length " + length(activeTxn));
-            }
-            nativeInsertWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr(), index, chunk);
+            formatRanges(activeTxn, ranges);
         } else {
             try (JniYTransaction txn = doc.beginTransaction()) {
-                if (index < 0 || index > length(txn)) {
-                    throw new IndexOutOfBoundsException(
-                        "Index " + index + " out of bounds for length " + length(txn));
-                }
-                nativeInsertWithTxn(doc.getNativePtr(), nativePtr,
-                    ((JniYTransaction) txn).getNativePtr(), index, chunk);
+                formatRanges(txn, ranges);
             }
         }
     }
 
     /**
-     * Appends text to the end within an existing transaction.
+     * Replaces a range of text within an existing transaction.
      *
-     * <p>Use this method to batch multiple operations:
-     * <pre>{@code
-     * try (JniYTransaction txn = doc.beginTransaction()) {
-     *     text.push(txn, "Hello");
-     *     text.push(txn, " World");
-     * }
-     * }</pre>
+     * <p>Deletes {@code length} characters at {@code index} and inserts
+     * {@code replacement} in their place in one native call. This is the edit an
+     * input method produces when it commits or updates a composition.</p>
      *
      * @param txn The transaction to use for this operation
-     * @param chunk The text to append
-     * @throws IllegalArgumentException if txn or chunk is null
+     * @param index The start of the range to replace (0-based)
+     * @param length The number of characters to replace; 0 inserts without deleting
+     * @param replacement The text to insert; an empty string only deletes
+     * @param attributes Formatting attributes for the inserted text, or null to take
+     *        the formatting at {@code index}; checked against the document's allowlist
+     * @throws IllegalArgumentException if txn or replacement is null, or an attribute
+     *         is not allowed or has an unsupported value
      * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if the range is invalid
      */
-    @Override
-    public void push(YTransaction txn, String chunk) {
+    public void replaceRange(YTransaction txn, int index, int length, String replacement,
+                             Map<String, Object> attributes) {
         checkClosed();
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
         }
-        if (chunk == null) {
-            throw new IllegalArgumentException("Chunk cannot be null");
+        if (replacement == null) {
+            throw new IllegalArgumentException("Replacement cannot be null");
         }
-        nativePushWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), chunk);
+        if (index < 0 || length < 0) {
+            throw new IndexOutOfBoundsException(
+                "Index and length must be non-negative");
+        }
+        int currentLength = length(txn);
+        if (index + length > currentLength) {
+            throw new IndexOutOfBoundsException(
+                "Range [" + index + ", " + (index + length) + ") out of bounds for length "
+                + currentLength);
+        }
+        nativeReplaceRangeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            index, length, replacement, attributes);
     }
 
     /**
-     * Appends text to the end (creates implicit transaction).
+     * Replaces a range of text (creates implicit transaction).
      *
-     * @param chunk The text to append
-     * @throws IllegalArgumentException if chunk is null
+     * @param index The start of the range to replace (0-based)
+     * @param length The number of characters to replace; 0 inserts without deleting
+     * @param replacement The text to insert; an empty string only deletes
+     * @param attributes Formatting attributes for the inserted text, or null to take
+     *        the formatting at {@code index}
+     * @throws IllegalArgumentException if replacement is null, or an attribute is not
+     *         allowed or has an unsupported value
      * @throws IllegalStateException if the text has been closed
+     * @throws IndexOutOfBoundsException if the range is invalid
+     * @see #replaceRange(YTransaction, int, int, String, Map)
      */
-    public void push(String chunk) {
+    public void replaceRange(int index, int length, String replacement, Map<String, Object> attributes) {
         checkClosed();
-        if (chunk == null) {
-            throw new IllegalArgumentException("Chunk cannot be null");
-        }
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
-            nativePushWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr(), chunk);
+            replaceRange(activeTxn, index, length, replacement, attributes);
         } else {
             try (JniYTransaction txn = doc.beginTransaction()) {
-                nativePushWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), chunk);
+                replaceRange(txn, index, length, replacement, attributes);
             }
         }
     }
@@ -298,6 +1271,64 @@ public class JniYText implements YText, JniYObservable {
         }
     }
 
+    /**
+     * Deletes whole grapheme clusters next to an index within an existing transaction.
+     *
+     * <p>A grapheme cluster is what a user sees as one character, which may be many
+     * {@code char}s: an emoji with a skin tone, a flag, a family joined with zero
+     * width joiners, or a letter with combining marks. Deleting clusters instead of
+     * chars or code points never leaves part of one in the text. For backspace,
+     * delete one cluster before the cursor; for forward delete, one cluster from
+     * it:</p>
+     * <pre>{@code
+     * cursor = text.deleteGraphemes(txn, cursor, -1); // backspace
+     * cursor = text.deleteGraphemes(txn, cursor, 1);  // delete
+     * }</pre>
+     *
+     * <p>A positive count deletes from the start of the cluster containing index
+     * onwards, a negative count the clusters before index, including the one it is
+     * inside of. Deletion stops at the start or end of the text. Clusters follow the
+     * extended grapheme cluster rules of Unicode Standard Annex #29.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param index the index, from 0 to the length of the text
+     * @param count the number of clusters to delete; negative to delete backwards
+     * @return the index at which the deleted range started, where the cursor belongs
+     *         after the deletion
+     * @throws IllegalArgumentException if txn is null
+     * @throws IndexOutOfBoundsException if index is negative or past the end
+     * @throws IllegalStateException if the text has been closed
+     */
+    public int deleteGraphemes(YTransaction txn, int index, int count) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeDeleteGraphemesWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), index, count);
+    }
+
+    /**
+     * Deletes whole grapheme clusters next to an index (creates implicit transaction).
+     *
+     * @param index the index, from 0 to the length of the text
+     * @param count the number of clusters to delete; negative to delete backwards
+     * @return the index at which the deleted range started
+     * @throws IndexOutOfBoundsException if index is negative or past the end
+     * @throws IllegalStateException if the text has been closed
+     * @see #deleteGraphemes(YTransaction, int, int)
+     */
+    public int deleteGraphemes(int index, int count) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return deleteGraphemes(activeTxn, index, count);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return deleteGraphemes(txn, index, count);
+        }
+    }
+
     /**
      * Checks if this YText has been closed.
      *
@@ -313,6 +1344,10 @@ public class JniYText implements YText, JniYObservable {
      * <p>The observer will be called whenever this text is modified.
      * Multiple observers can be registered on the same text.</p>
      *
+     * <p>DELETE and RETAIN changes carry their UTF-16 length as well as their
+     * length in bytes. To measure them, the native side keeps one copy of this
+     * text for all of its observers, which is freed with the last of them.</p>
+     *
      * <p>Example:</p>
      * <pre>{@code
      * try (YSubscription sub = text.observe(event -> {
@@ -335,9 +1370,11 @@ public class JniYText implements YText, JniYObservable {
         if (observer == null) {
             throw new IllegalArgumentException("Observer cannot be null");
         }
-        long id = nextSubscriptionId.incrementAndGet();
+        long id = doc.allocateSubscriptionId();
         observers.put(id, observer);
-        nativeObserve(doc.getNativePtr(), nativePtr, id, this);
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        long txnPtr = activeTxn != null ? activeTxn.getNativePtr() : 0;
+        nativeObserve(doc.getNativePtr(), nativePtr, txnPtr, id, this);
         return new JniYSubscription(id, observer, this);
     }
 
@@ -389,7 +1426,8 @@ public class JniYText implements YText, JniYObservable {
                 if (!closed) {
                     // Clear all observers
                     observers.clear();
-                    if (nativePtr != 0) {
+                    // The document frees released handles itself
+                    if (nativePtr != 0 && !isReleased()) {
                         nativeDestroy(nativePtr);
                         nativePtr = 0;
                     }
@@ -408,6 +1446,18 @@ public class JniYText implements YText, JniYObservable {
         if (closed) {
             throw new IllegalStateException("YText has been closed");
         }
+        if (isReleased()) {
+            throw new IllegalStateException("YText was released by its YDoc");
+        }
+    }
+
+    /**
+     * Returns whether the document released the native handle of this root.
+     *
+     * @return true after {@link JniYDoc#releaseAllRoots()} or closing the document
+     */
+    private boolean isReleased() {
+        return rootGeneration >= 0 && doc.getRootGeneration() != rootGeneration;
     }
 
     /**
@@ -422,11 +1472,58 @@ public class JniYText implements YText, JniYObservable {
     // Native methods
     private static native long nativeGetText(long docPtr, String name);
     private static native void nativeDestroy(long ptr);
-    private static native int nativeLengthWithTxn(long docPtr, long textPtr, long txnPtr);
+    private static native long nativeLengthWithTxn(long docPtr, long textPtr, long txnPtr);
+    private static native Object[] nativeGetPathWithTxn(long docPtr, long textPtr, long txnPtr);
     private static native String nativeToStringWithTxn(long docPtr, long textPtr, long txnPtr);
+
+    private static native int nativeWriteStringWithTxn(long docPtr, long textPtr, long txnPtr,
+        ByteBuffer buffer, int offset, int length);
+
+    private static native String nativeGetStringChunkWithTxn(long docPtr, long textPtr, long txnPtr,
+        int offset, int maxBytes);
+
+    private static native void nativeReadChunksWithTxn(long docPtr, long textPtr, long txnPtr,
+        int maxBytes, Consumer<String> consumer);
+
+    private static native int[] nativeLineStartsWithTxn(long docPtr, long textPtr, long txnPtr);
+
+    private static native List<JniTextBlock> nativeBlocksWithTxn(long docPtr, long textPtr,
+            long txnPtr, int blockSize, int firstBlock, int maxBlocks);
+
+    private static native int nativeLineCountWithTxn(long docPtr, long textPtr, long txnPtr);
+    private static native long[] nativeStatsWithTxn(long docPtr, long textPtr, long txnPtr);
+
+    private static native int[] nativePositionWithTxn(long docPtr, long textPtr, long txnPtr,
+            int offset);
+
+    private static native int nativeOffsetWithTxn(long docPtr, long textPtr, long txnPtr,
+            int line, int column);
+
+    private static native void nativeImportDeltaWithTxn(long docPtr, long textPtr, long txnPtr,
+        String deltaJson);
+
+    private static native String nativeExportDeltaWithTxn(long docPtr, long textPtr, long txnPtr);
     private static native void nativeInsertWithTxn(long docPtr, long textPtr, long txnPtr, int index, String chunk);
+    private static native void nativeInsertCharsWithTxn(long docPtr, long textPtr, long txnPtr, int index,
+                                                        char[] chars, int offset, int length);
+    private static native void nativeInsertDirectCharsWithTxn(long docPtr, long textPtr, long txnPtr, int index,
+                                                              CharBuffer chars, int offset, int length);
+    private static native byte[] nativeStickyIndexWithTxn(long docPtr, long textPtr, long txnPtr,
+        int index, int assoc);
+    private static native int nativeResolveStickyIndexWithTxn(long docPtr, long textPtr, long txnPtr,
+        byte[] sticky);
+    private static native int nativeInsertAtStickyIndexWithTxn(long docPtr, long textPtr, long txnPtr,
+        byte[] sticky, String chunk);
     private static native void nativePushWithTxn(long docPtr, long textPtr, long txnPtr, String chunk);
+    private static native void nativeFormatRangesWithTxn(long docPtr, long textPtr, long txnPtr,
+        int[] ranges, Object[] attributes);
     private static native void nativeDeleteWithTxn(long docPtr, long textPtr, long txnPtr, int index, int length);
-    private static native void nativeObserve(long docPtr, long textPtr, long subscriptionId, YText ytextObj);
+    private static native int nativeDeleteGraphemesWithTxn(long docPtr, long textPtr, long txnPtr,
+        int index, int count);
+    private static native void nativeReplaceRangeWithTxn(long docPtr, long textPtr, long txnPtr, int index,
+                                                         int length, String replacement,
+                                                         Map<String, Object> attributes);
+    private static native void nativeObserve(long docPtr, long textPtr, long txnPtr,
+        long subscriptionId, YText ytextObj);
     private static native void nativeUnobserve(long docPtr, long textPtr, long subscriptionId);
 }
@@ -28,7 +28,11 @@ public final class JniYTextChange extends YTextChange {
     private final Type type;
     private final String content;
     private final int length;
+    private final int utf16Length;
     private final Map<String, Object> attributes;
+    private final Object embed;
+    private final boolean truncated;
+    private final int contentHash;
 
     /**
      * Package-private constructor for INSERT changes.
@@ -37,9 +41,51 @@ public final class JniYTextChange extends YTextChange {
      * @param attributes the formatting attributes (may be null)
      */
     JniYTextChange(String content, Map<String, Object> attributes) {
+        this(content, null, attributes);
+    }
+
+    /**
+     * Package-private constructor for INSERT changes that may be embeds.
+     *
+     * <p>An embed occupies a single position in the text; its content is the
+     * string form of the embedded value.
+     *
+     * @param content the inserted text, or the string form of the embed
+     * @param embed the embedded value, or null for inserted text
+     * @param attributes the formatting attributes (may be null)
+     */
+    JniYTextChange(String content, Object embed, Map<String, Object> attributes) {
         this.type = Type.INSERT;
         this.content = content;
-        this.length = content.length();
+        this.length = embed != null ? 1 : content.length();
+        this.utf16Length = length;
+        this.embed = embed;
+        this.truncated = false;
+        this.contentHash = embed != null ? 0 : content.hashCode();
+        this.attributes = attributes != null
+            ? Collections.unmodifiableMap(attributes)
+            : Collections.emptyMap();
+    }
+
+    /**
+     * Package-private constructor for INSERT changes whose text was cut to the
+     * document's event content limit.
+     *
+     * @param prefix the leading part of the inserted text
+     * @param length the length of the full inserted text
+     * @param contentHash the {@link String#hashCode()} of the full inserted text
+     * @param attributes the formatting attributes (may be null)
+     * @see JniYDoc#setEventContentLimit(int)
+     */
+    JniYTextChange(String prefix, int length, int contentHash,
+                   Map<String, Object> attributes) {
+        this.type = Type.INSERT;
+        this.content = prefix;
+        this.length = length;
+        this.utf16Length = length;
+        this.embed = null;
+        this.truncated = true;
+        this.contentHash = contentHash;
         this.attributes = attributes != null
             ? Collections.unmodifiableMap(attributes)
             : Collections.emptyMap();
@@ -63,12 +109,30 @@ public final class JniYTextChange extends YTextChange {
      * @param attributes the formatting attributes (may be null, only for RETAIN)
      */
     JniYTextChange(Type type, int length, Map<String, Object> attributes) {
+        this(type, length, length, attributes);
+    }
+
+    /**
+     * Package-private constructor for DELETE and RETAIN changes measured in both
+     * bytes and UTF-16 units.
+     *
+     * @param type the change type (DELETE or RETAIN)
+     * @param length the number of UTF-8 bytes deleted or retained
+     * @param utf16Length the number of UTF-16 units deleted or retained, or -1 if
+     *        not known
+     * @param attributes the formatting attributes (may be null, only for RETAIN)
+     */
+    JniYTextChange(Type type, int length, int utf16Length, Map<String, Object> attributes) {
         if (type != Type.DELETE && type != Type.RETAIN) {
             throw new IllegalArgumentException("Type must be DELETE or RETAIN");
         }
         this.type = type;
         this.content = null;
         this.length = length;
+        this.utf16Length = utf16Length;
+        this.embed = null;
+        this.truncated = false;
+        this.contentHash = 0;
         this.attributes = attributes != null
             ? Collections.unmodifiableMap(attributes)
             : Collections.emptyMap();
@@ -89,15 +153,79 @@ public final class JniYTextChange extends YTextChange {
         return length;
     }
 
+    /**
+     * Returns the length of this change in UTF-16 units, the unit of Java string
+     * indices.
+     *
+     * <p>{@link #getLength()} measures DELETE and RETAIN spans in UTF-8 bytes, as
+     * the native document does. This method measures them in UTF-16 units, so the
+     * spans can be applied to a Java copy of the text without reading the
+     * document again. For INSERT changes both methods return the same value.</p>
+     *
+     * @return the UTF-16 length, or -1 if it is not known, which happens only
+     *         when the text's first observer was registered inside the
+     *         transaction that made the change
+     */
+    public int getUtf16Length() {
+        return utf16Length;
+    }
+
     @Override
     public Map<String, Object> getAttributes() {
         return attributes;
     }
 
+    /**
+     * Returns the value embedded by an INSERT change.
+     *
+     * <p>Plain values are returned as strings, numbers, booleans, {@code byte[]},
+     * {@code List} and {@code Map}; shared types as fully expanded
+     * {@link JniTreeNode}s.
+     *
+     * @return the embedded value, or null if the change inserted text or is
+     *         not an INSERT
+     */
+    public Object getEmbed() {
+        return embed;
+    }
+
+    /**
+     * Returns whether the content of an INSERT change was cut to the document's
+     * event content limit.
+     *
+     * <p>A truncated change carries only the leading part of the inserted text in
+     * {@link #getContent()}; {@link #getLength()} and {@link #getContentHash()}
+     * describe the full text.</p>
+     *
+     * @return true if the content is incomplete
+     * @see JniYDoc#setEventContentLimit(int)
+     */
+    public boolean isTruncated() {
+        return truncated;
+    }
+
+    /**
+     * Returns the {@link String#hashCode()} of the full text inserted by an INSERT
+     * change.
+     *
+     * <p>Lets observers that receive truncated content compare an insertion with
+     * text they already hold.</p>
+     *
+     * @return the hash of the inserted text, or 0 for embeds and for DELETE and
+     *         RETAIN changes
+     */
+    public int getContentHash() {
+        return contentHash;
+    }
+
     @Override
     public String toString() {
         switch (type) {
             case INSERT:
+                if (truncated) {
+                    return "INSERT(\"" + content + "\"..., length=" + length + ", "
+                           + attributes + ")";
+                }
                 return "INSERT(\"" + content + "\", " + attributes + ")";
             case DELETE:
                 return "DELETE(" + length + ")";
@@ -33,6 +33,21 @@ public class JniYTransaction implements YTransaction {
      */
     private final long nativePtr;
 
+    /**
+     * The origin the transaction was opened with, or null.
+     */
+    private final String origin;
+
+    /**
+     * The label set with {@link #setLabel(String)}, or null.
+     */
+    private volatile String label;
+
+    /**
+     * Whether observers of shared types skip this transaction.
+     */
+    private volatile boolean silent;
+
     /**
      * Flag to track if this transaction has been closed.
      */
@@ -43,8 +58,9 @@ public class JniYTransaction implements YTransaction {
      *
      * @param doc the document this transaction belongs to
      * @param nativePtr the native transaction pointer
+     * @param origin the origin the transaction was opened with, or null
      */
-    JniYTransaction(JniYDoc doc, long nativePtr) {
+    JniYTransaction(JniYDoc doc, long nativePtr, String origin) {
         if (doc == null) {
             throw new IllegalArgumentException("Document cannot be null");
         }
@@ -53,6 +69,7 @@ public class JniYTransaction implements YTransaction {
         }
         this.doc = doc;
         this.nativePtr = nativePtr;
+        this.origin = origin;
     }
 
     @Override
@@ -60,11 +77,15 @@ public class JniYTransaction implements YTransaction {
         if (!closed) {
             synchronized (this) {
                 if (!closed) {
-                    nativeCommit(doc.getNativePtr(), nativePtr);
+                    long[] stats = nativeCommit(doc.getNativePtr(), nativePtr);
                     doc.clearActiveTransaction();
                     closed = true;
+                    if (stats != null) {
+                        doc.onTransactionMeasured(new JniTransactionStats(stats, label));
+                    }
                 }
             }
+            doc.runDeferredActions();
         }
     }
 
@@ -78,6 +99,73 @@ public class JniYTransaction implements YTransaction {
         return closed;
     }
 
+    /**
+     * Gets the origin this transaction was opened with.
+     *
+     * @return the origin, or null if the transaction has none
+     * @see JniYDoc#beginTransaction(String)
+     */
+    public String getOrigin() {
+        return origin;
+    }
+
+    /**
+     * Labels this transaction with the name of the code path that opened it.
+     *
+     * <p>Unlike the origin, the label is not part of the document and is not seen by
+     * observers or other peers. It is reported with the
+     * {@linkplain JniTransactionStats#getLabel() statistics} of the transaction, in the
+     * exception thrown when this thread opens another transaction on the document
+     * while this one is open, and on stderr if native code panics while it is open:</p>
+     * <pre>{@code
+     * try (JniYTransaction txn = doc.beginTransaction().setLabel("DraftService.save")) {
+     *     text.insert(txn, 0, draft);
+     * }
+     * }</pre>
+     *
+     * @param label the label, replacing any earlier one
+     * @return this transaction
+     * @throws IllegalArgumentException if label is null
+     * @throws IllegalStateException if this transaction has been closed
+     */
+    public JniYTransaction setLabel(String label) {
+        if (label == null) {
+            throw new IllegalArgumentException("Label cannot be null");
+        }
+        nativeSetLabel(doc.getNativePtr(), getNativePtr(), label);
+        this.label = label;
+        return this;
+    }
+
+    /**
+     * Gets the label of this transaction.
+     *
+     * @return the label, or null if none has been set
+     * @see #setLabel(String)
+     */
+    public String getLabel() {
+        return label;
+    }
+
+    /**
+     * Hides the changes in this transaction from observers of shared types.
+     * Package-private for use by {@link JniYDoc#beginSilentTransaction()}.
+     */
+    void markSilent() {
+        nativeSetSilent(doc.getNativePtr(), getNativePtr());
+        silent = true;
+    }
+
+    /**
+     * Gets whether this transaction is silent.
+     *
+     * @return true if observers of shared types do not receive its changes
+     * @see JniYDoc#beginSilentTransaction()
+     */
+    public boolean isSilent() {
+        return silent;
+    }
+
     /**
      * Gets the native pointer for internal use by operation methods.
      *
@@ -101,6 +189,8 @@ public class JniYTransaction implements YTransaction {
     }
 
     // Native method declarations
-    private static native void nativeCommit(long docPtr, long txnPtr);
+    private static native long[] nativeCommit(long docPtr, long txnPtr);
     private static native void nativeRollback(long docPtr, long txnPtr);
+    private static native void nativeSetSilent(long docPtr, long txnPtr);
+    private static native void nativeSetLabel(long docPtr, long txnPtr, String label);
 }
@@ -0,0 +1,362 @@
+package net.carcdr.ycrdt.jni;
+
+import java.io.Closeable;
+import java.util.function.LongSupplier;
+
+import net.carcdr.ycrdt.YArray;
+import net.carcdr.ycrdt.YMap;
+import net.carcdr.ycrdt.YText;
+import net.carcdr.ycrdt.YXmlElement;
+import net.carcdr.ycrdt.YXmlFragment;
+import net.carcdr.ycrdt.YXmlText;
+
+/**
+ * Undoes and redoes local changes to a set of shared types.
+ *
+ * <p>The manager captures changes to the shared types added with {@code addToScope}
+ * made by the transactions it tracks. Changes made within the capture timeout of
+ * each other are undone together; {@link #stopCapturing()} starts a new group.</p>
+ *
+ * <p>Until an origin is tracked, the manager captures transactions without an
+ * origin, which includes updates applied with {@link JniYDoc#applyUpdate(byte[])}.
+ * To keep changes received from peers out of the undo history, open local
+ * transactions with {@link JniYDoc#beginTransaction(String)} and track that origin;
+ * the manager then captures only transactions carrying a tracked origin:</p>
+ * <pre>{@code
+ * try (JniYUndoManager undo = new JniYUndoManager(doc)) {
+ *     undo.addToScope(text);
+ *     undo.trackOrigin("local");
+ *     try (JniYTransaction txn = doc.beginTransaction("local")) {
+ *         text.insert(txn, 0, "Hello");
+ *     }
+ *     doc.applyUpdate(remoteUpdate, "remote"); // not captured
+ *     undo.undo(); // removes "Hello" only
+ * }
+ * }</pre>
+ *
+ * <p>Creating, closing, undoing, redoing and clearing need exclusive access to the
+ * document: they throw {@link IllegalStateException} when the calling thread has a
+ * transaction open, and wait for transactions on other threads to finish.</p>
+ */
+public class JniYUndoManager implements Closeable {
+
+    /** Default time window in which consecutive changes are undone together. */
+    public static final long DEFAULT_CAPTURE_TIMEOUT_MILLIS = 500;
+
+    // Scope kinds, matching the UNDO_SCOPE_* constants in undo.rs
+    private static final int SCOPE_TEXT = 0;
+    private static final int SCOPE_ARRAY = 1;
+    private static final int SCOPE_MAP = 2;
+    private static final int SCOPE_XML_FRAGMENT = 3;
+    private static final int SCOPE_XML_ELEMENT = 4;
+    private static final int SCOPE_XML_TEXT = 5;
+
+    private final JniYDoc doc;
+    private long nativePtr;
+
+    /**
+     * Creates an undo manager with the default capture timeout and an empty scope.
+     *
+     * @param doc the document whose changes are undone
+     * @throws IllegalArgumentException if doc is null
+     * @throws IllegalStateException if the document is closed or the calling thread
+     *         has a transaction open
+     */
+    public JniYUndoManager(JniYDoc doc) {
+        this(doc, DEFAULT_CAPTURE_TIMEOUT_MILLIS);
+    }
+
+    /**
+     * Creates an undo manager with an empty scope.
+     *
+     * @param doc the document whose changes are undone
+     * @param captureTimeoutMillis changes made within this many milliseconds of each
+     *        other are undone together; 0 undoes every transaction separately
+     * @throws IllegalArgumentException if doc is null or the timeout is negative
+     * @throws IllegalStateException if the document is closed or the calling thread
+     *         has a transaction open
+     */
+    public JniYUndoManager(JniYDoc doc, long captureTimeoutMillis) {
+        if (doc == null) {
+            throw new IllegalArgumentException("Document cannot be null");
+        }
+        if (captureTimeoutMillis < 0) {
+            throw new IllegalArgumentException("Capture timeout cannot be negative");
+        }
+        if (doc.isClosed()) {
+            throw new IllegalStateException("JniYDoc has been closed");
+        }
+        this.doc = doc;
+        ensureNoActiveTransaction();
+        this.nativePtr = nativeCreate(doc.getNativePtr(), captureTimeoutMillis);
+        if (nativePtr == 0) {
+            throw new RuntimeException("Failed to create undo manager");
+        }
+    }
+
+    /**
+     * Creates an undo manager with an empty scope that measures the capture timeout
+     * with the given clock instead of the system time.
+     *
+     * <p>A manual clock makes grouping independent of how fast a test or simulation
+     * runs. The clock is read on the committing thread after every tracked
+     * transaction. If it throws or returns a negative time, its last reading (or 0
+     * before the first) is used for that transaction; the system time is never
+     * mixed in, since it would not be comparable with the clock's own epoch.</p>
+     *
+     * @param doc the document whose changes are undone
+     * @param captureTimeoutMillis changes made within this many milliseconds of each
+     *        other, according to the clock, are undone together
+     * @param clock returns the current time in milliseconds
+     * @throws IllegalArgumentException if doc or clock is null or the timeout is negative
+     * @throws IllegalStateException if the document is closed or the calling thread
+     *         has a transaction open
+     */
+    public JniYUndoManager(JniYDoc doc, long captureTimeoutMillis, LongSupplier clock) {
+        if (doc == null) {
+            throw new IllegalArgumentException("Document cannot be null");
+        }
+        if (clock == null) {
+            throw new IllegalArgumentException("Clock cannot be null");
+        }
+        if (captureTimeoutMillis < 0) {
+            throw new IllegalArgumentException("Capture timeout cannot be negative");
+        }
+        if (doc.isClosed()) {
+            throw new IllegalStateException("JniYDoc has been closed");
+        }
+        this.doc = doc;
+        ensureNoActiveTransaction();
+        this.nativePtr = nativeCreateWithClock(doc.getNativePtr(), captureTimeoutMillis, clock);
+        if (nativePtr == 0) {
+            throw new RuntimeException("Failed to create undo manager");
+        }
+    }
+
+    /**
+     * Captures changes to a text.
+     *
+     * @param text the text to add to the scope
+     */
+    public synchronized void addToScope(YText text) {
+        addToScope(SCOPE_TEXT, ((JniYText) text).getNativePtr());
+    }
+
+    /**
+     * Captures changes to an array and its nested types.
+     *
+     * @param array the array to add to the scope
+     */
+    public synchronized void addToScope(YArray array) {
+        addToScope(SCOPE_ARRAY, ((JniYArray) array).getNativePtr());
+    }
+
+    /**
+     * Captures changes to a map and its nested types.
+     *
+     * @param map the map to add to the scope
+     */
+    public synchronized void addToScope(YMap map) {
+        addToScope(SCOPE_MAP, ((JniYMap) map).getNativePtr());
+    }
+
+    /**
+     * Captures changes to an XML fragment and its descendants.
+     *
+     * @param fragment the fragment to add to the scope
+     */
+    public synchronized void addToScope(YXmlFragment fragment) {
+        addToScope(SCOPE_XML_FRAGMENT, ((JniYXmlFragment) fragment).getNativePtr());
+    }
+
+    /**
+     * Captures changes to an XML element and its descendants.
+     *
+     * @param element the element to add to the scope
+     */
+    public synchronized void addToScope(YXmlElement element) {
+        addToScope(SCOPE_XML_ELEMENT, ((JniYXmlElement) element).getNativePtr());
+    }
+
+    /**
+     * Captures changes to an XML text.
+     *
+     * @param text the text to add to the scope
+     */
+    public synchronized void addToScope(YXmlText text) {
+        addToScope(SCOPE_XML_TEXT, ((JniYXmlText) text).getNativePtr());
+    }
+
+    private void addToScope(int kind, long typePtr) {
+        checkClosed();
+        nativeExpandScope(nativePtr, kind, typePtr);
+    }
+
+    /**
+     * Captures transactions opened with the given origin.
+     *
+     * <p>Once an origin is tracked, transactions without an origin are no longer
+     * captured.</p>
+     *
+     * @param origin the origin passed to {@link JniYDoc#beginTransaction(String)}
+     * @throws IllegalArgumentException if origin is null
+     */
+    public synchronized void trackOrigin(String origin) {
+        setOriginTracked(origin, true);
+    }
+
+    /**
+     * Stops capturing transactions opened with the given origin.
+     *
+     * @param origin an origin previously passed to {@link #trackOrigin(String)}
+     * @throws IllegalArgumentException if origin is null
+     */
+    public synchronized void untrackOrigin(String origin) {
+        setOriginTracked(origin, false);
+    }
+
+    private void setOriginTracked(String origin, boolean tracked) {
+        checkClosed();
+        if (origin == null) {
+            throw new IllegalArgumentException("Origin cannot be null");
+        }
+        ensureNoActiveTransaction();
+        nativeSetOriginTracked(doc.getNativePtr(), nativePtr, origin, tracked);
+    }
+
+    /**
+     * Reverts the last group of captured changes.
+     *
+     * @return true if anything was undone
+     * @throws IllegalStateException if closed, if the calling thread has a transaction
+     *         open, or if the document is read-only
+     */
+    public synchronized boolean undo() {
+        checkClosed();
+        ensureNoActiveTransaction();
+        boolean changed = nativeUndo(doc.getNativePtr(), nativePtr);
+        doc.runDeferredActions();
+        return changed;
+    }
+
+    /**
+     * Reapplies the last group of undone changes.
+     *
+     * @return true if anything was redone
+     * @throws IllegalStateException if closed, if the calling thread has a transaction
+     *         open, or if the document is read-only
+     */
+    public synchronized boolean redo() {
+        checkClosed();
+        ensureNoActiveTransaction();
+        boolean changed = nativeRedo(doc.getNativePtr(), nativePtr);
+        doc.runDeferredActions();
+        return changed;
+    }
+
+    /**
+     * Checks if there are changes to undo.
+     *
+     * @return true if {@link #undo()} would change the document
+     */
+    public synchronized boolean canUndo() {
+        checkClosed();
+        return nativeCanUndo(nativePtr);
+    }
+
+    /**
+     * Checks if there are changes to redo.
+     *
+     * @return true if {@link #redo()} would change the document
+     */
+    public synchronized boolean canRedo() {
+        checkClosed();
+        return nativeCanRedo(nativePtr);
+    }
+
+    /**
+     * Discards all undo and redo history.
+     *
+     * @throws IllegalStateException if closed or if the calling thread has a
+     *         transaction open
+     */
+    public synchronized void clear() {
+        checkClosed();
+        ensureNoActiveTransaction();
+        nativeClear(nativePtr);
+    }
+
+    /**
+     * Ends the current group of changes, so the next change is undone separately
+     * even if it falls within the capture timeout.
+     *
+     * @throws IllegalStateException if closed or if the calling thread has a
+     *         transaction open
+     */
+    public synchronized void stopCapturing() {
+        checkClosed();
+        ensureNoActiveTransaction();
+        nativeStopCapturing(doc.getNativePtr(), nativePtr);
+    }
+
+    /**
+     * Checks if this undo manager has been closed.
+     *
+     * @return true if closed
+     */
+    public synchronized boolean isClosed() {
+        return nativePtr == 0;
+    }
+
+    /**
+     * Stops capturing changes and releases the history.
+     *
+     * @throws IllegalStateException if the calling thread has a transaction open
+     */
+    @Override
+    public synchronized void close() {
+        if (nativePtr != 0) {
+            ensureNoActiveTransaction();
+            nativeDestroy(doc.getNativePtr(), nativePtr);
+            nativePtr = 0;
+        }
+    }
+
+    private void checkClosed() {
+        if (nativePtr == 0) {
+            throw new IllegalStateException("JniYUndoManager has been closed");
+        }
+    }
+
+    private void ensureNoActiveTransaction() {
+        if (doc.getActiveTransaction() != null) {
+            throw new IllegalStateException(
+                "Cannot use an undo manager while this thread has a transaction open");
+        }
+    }
+
+    // Native method declarations
+    private static native long nativeCreate(long docPtr, long captureTimeoutMillis);
+
+    private static native long nativeCreateWithClock(long docPtr, long captureTimeoutMillis,
+                                                     Object clock);
+
+    private static native void nativeDestroy(long docPtr, long ptr);
+
+    private static native void nativeExpandScope(long ptr, int kind, long typePtr);
+
+    private static native void nativeSetOriginTracked(long docPtr, long ptr, String origin,
+            boolean tracked);
+
+    private static native boolean nativeUndo(long docPtr, long ptr);
+
+    private static native boolean nativeRedo(long docPtr, long ptr);
+
+    private static native boolean nativeCanUndo(long ptr);
+
+    private static native boolean nativeCanRedo(long ptr);
+
+    private static native void nativeClear(long ptr);
+
+    private static native void nativeStopCapturing(long docPtr, long ptr);
+}
@@ -4,10 +4,13 @@ import net.carcdr.ycrdt.YObserver;
 import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YTransaction;
 import net.carcdr.ycrdt.YXmlElement;
+import net.carcdr.ycrdt.YXmlNode;
 
 import java.io.Closeable;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.Map;
 import java.util.concurrent.ConcurrentHashMap;
-import java.util.concurrent.atomic.AtomicLong;
 
 /**
  * YXmlElement represents a collaborative XML element type in a Y-CRDT document.
@@ -37,8 +40,13 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
     private final JniYDoc doc;
     private long nativePtr;
     private volatile boolean closed = false;
+
+    /**
+     * Root generation of the document when this root was looked up by name, or
+     * -1 if the handle was not obtained by name.
+     */
+    private final int rootGeneration;
     private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
-    private final AtomicLong nextSubscriptionId = new AtomicLong(0);
 
     /**
      * Package-private constructor. Use {@link YDoc#getXmlElement(String)} to create instances.
@@ -54,6 +62,7 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
             throw new IllegalArgumentException("Name cannot be null");
         }
         this.doc = doc;
+        this.rootGeneration = doc.getRootGeneration();
         this.nativePtr = nativeGetXmlElement(doc.getNativePtr(), name);
         if (this.nativePtr == 0) {
             throw new RuntimeException("Failed to create YXmlElement");
@@ -76,6 +85,7 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         }
         this.doc = doc;
         this.nativePtr = nativeHandle;
+        this.rootGeneration = -1;
     }
 
     @Override
@@ -117,6 +127,36 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         return result != null ? result : "";
     }
 
+    /**
+     * Returns the root this XML element belongs to and the path leading to it.
+     *
+     * @return the path, or null if the element was deleted
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public JniTypePath getPath() {
+        checkClosed();
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return getPath(txn);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return getPath(autoTxn);
+        }
+    }
+
+    /**
+     * Returns the root this XML element belongs to and the path leading to it.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the path, or null if the element was deleted
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public JniTypePath getPath(YTransaction txn) {
+        checkClosed();
+        return JniTypePath.fromNative(
+            nativeGetPathWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr()));
+    }
+
     /**
      * Gets an attribute value by name.
      *
@@ -225,6 +265,7 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
 
     private static void validateAttributeValue(Object value) {
         if (value == null
+                || value == JniUndefined.UNDEFINED
                 || value instanceof String
                 || value instanceof Long
                 || value instanceof Integer
@@ -235,7 +276,65 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         }
         throw new IllegalArgumentException(
             "Unsupported attribute value type: " + value.getClass().getName()
-                + ". Expected String, Long, Integer, Double, Float, Boolean, or null.");
+                + ". Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, or null.");
+    }
+
+    /**
+     * Sets several attribute values at once.
+     *
+     * <p>All values are passed to the native layer in one call and applied in one
+     * transaction, so observers see a single change. Values follow the rules of
+     * {@link #setAttribute(String, Object)}.</p>
+     *
+     * @param attributes The attribute names and values to set
+     * @throws IllegalArgumentException if attributes is null, contains a null name, or
+     *         contains a value that is not a supported type
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public void setAttributes(Map<String, ?> attributes) {
+        checkClosed();
+        validateAttributes(attributes);
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            setAttributes(txn, attributes);
+            return;
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            setAttributes(autoTxn, attributes);
+        }
+    }
+
+    /**
+     * Sets several attribute values within an existing transaction.
+     *
+     * <p>If any entry is invalid, no attribute is changed.</p>
+     *
+     * @param txn Transaction handle
+     * @param attributes The attribute names and values to set
+     * @throws IllegalArgumentException if txn or attributes is null, attributes contains
+     *         a null name, or contains a value that is not a supported type
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public void setAttributes(YTransaction txn, Map<String, ?> attributes) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        validateAttributes(attributes);
+        nativeSetAttributesWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), attributes);
+    }
+
+    private static void validateAttributes(Map<String, ?> attributes) {
+        if (attributes == null) {
+            throw new IllegalArgumentException("Attributes cannot be null");
+        }
+        for (Map.Entry<String, ?> entry : attributes.entrySet()) {
+            if (entry.getKey() == null) {
+                throw new IllegalArgumentException("Attribute name cannot be null");
+            }
+            validateAttributeValue(entry.getValue());
+        }
     }
 
     /**
@@ -287,6 +386,57 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         nativeRemoveAttributeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), name);
     }
 
+    /**
+     * Removes several attributes at once.
+     *
+     * <p>Names that are not set are ignored.</p>
+     *
+     * @param names The attribute names to remove
+     * @throws IllegalArgumentException if names is null or contains null
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public void removeAttributes(String... names) {
+        checkClosed();
+        validateAttributeNames(names);
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            removeAttributes(txn, names);
+            return;
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            removeAttributes(autoTxn, names);
+        }
+    }
+
+    /**
+     * Removes several attributes within an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @param names The attribute names to remove
+     * @throws IllegalArgumentException if txn or names is null, or names contains null
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public void removeAttributes(YTransaction txn, String... names) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        validateAttributeNames(names);
+        nativeRemoveAttributesWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), names);
+    }
+
+    private static void validateAttributeNames(String[] names) {
+        if (names == null) {
+            throw new IllegalArgumentException("Names cannot be null");
+        }
+        for (String name : names) {
+            if (name == null) {
+                throw new IllegalArgumentException("Attribute name cannot be null");
+            }
+        }
+    }
+
     /**
      * Gets all attribute names.
      *
@@ -363,6 +513,10 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
     /**
      * Gets the number of child nodes in this element.
      *
+     * <p>Without an active transaction, this reads the document under a
+     * short-lived read transaction, which concurrent readers share, instead of
+     * opening a write transaction.</p>
+     *
      * @return The number of child nodes
      * @throws IllegalStateException if the XML element has been closed
      */
@@ -372,9 +526,7 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         if (txn != null) {
             return childCount(txn);
         }
-        try (YTransaction autoTxn = doc.beginTransaction()) {
-            return childCount(autoTxn);
-        }
+        return nativeChildCount(doc.getNativePtr(), nativePtr);
     }
 
     /**
@@ -514,12 +666,66 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         return new JniYXmlText(doc, childPtr);
     }
 
+    /**
+     * Inserts an empty nested XML fragment at the specified index.
+     *
+     * <p>Nested fragments group children without adding an element of their own,
+     * which is how editors embed one XML tree inside another.</p>
+     *
+     * @param index The index at which to insert the fragment
+     * @return The new fragment
+     * @throws IndexOutOfBoundsException if index is negative
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public JniYXmlFragment insertFragment(int index) {
+        checkClosed();
+        if (index < 0) {
+            throw new IndexOutOfBoundsException("Index cannot be negative: " + index);
+        }
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return insertFragment(txn, index);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return insertFragment(autoTxn, index);
+        }
+    }
+
+    /**
+     * Inserts an empty nested XML fragment at the specified index within an
+     * existing transaction.
+     *
+     * @param txn Transaction handle
+     * @param index The index at which to insert the fragment
+     * @return The new fragment
+     * @throws IllegalArgumentException if txn is null
+     * @throws IndexOutOfBoundsException if index is negative
+     * @throws IllegalStateException if the XML element has been closed
+     * @see #insertFragment(int)
+     */
+    public JniYXmlFragment insertFragment(YTransaction txn, int index) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (index < 0) {
+            throw new IndexOutOfBoundsException("Index cannot be negative: " + index);
+        }
+        long childPtr = nativeInsertFragmentWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), index);
+        if (childPtr == 0) {
+            throw new RuntimeException("Failed to insert fragment child");
+        }
+        return new JniYXmlFragment(doc, childPtr);
+    }
+
     /**
      * Gets the child node at the specified index.
-     * The returned object can be either YXmlElement or YXmlText.
+     * The returned object can be a YXmlElement, a YXmlText or a nested
+     * JniYXmlFragment.
      *
      * @param index The index of the child to retrieve
-     * @return The child node, or null if not found
+     * @return The child node, or null if index is past the last child
      * @throws IndexOutOfBoundsException if index is negative
      * @throws IllegalStateException if the XML element has been closed
      */
@@ -539,11 +745,12 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
 
     /**
      * Gets the child node at the specified index using an existing transaction.
-     * The returned object can be either YXmlElement or YXmlText.
+     * The returned object can be a YXmlElement, a YXmlText or a nested
+     * JniYXmlFragment.
      *
      * @param txn Transaction handle
      * @param index The index of the child to retrieve
-     * @return The child node, or null if not found
+     * @return The child node, or null if index is past the last child
      * @throws IllegalArgumentException if txn is null
      * @throws IndexOutOfBoundsException if index is negative
      * @throws IllegalStateException if the XML element has been closed
@@ -556,26 +763,175 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         if (index < 0) {
             throw new IndexOutOfBoundsException("Index cannot be negative: " + index);
         }
-        Object result = nativeGetChildWithTxn(doc.getNativePtr(), nativePtr,
+        YNativeRef ref = nativeGetChildWithTxn(doc.getNativePtr(), nativePtr,
             ((JniYTransaction) txn).getNativePtr(), index);
-        if (result == null) {
+        if (ref == null) {
             return null;
         }
+        return ref.toXmlNode(doc);
+    }
 
-        // Result is Object[2] where [0] = Integer type, [1] = Long pointer
-        Object[] array = (Object[]) result;
-        int type = ((Integer) array[0]).intValue();
-        long pointer = ((Long) array[1]).longValue();
-
-        if (type == 0) {
-            // Element
-            return new JniYXmlElement(doc, pointer);
-        } else if (type == 1) {
-            // Text
-            return new JniYXmlText(doc, pointer);
+    /**
+     * Returns the index of a child node of this element.
+     *
+     * <p>The child is located natively in a single pass over the children, so
+     * this is cheaper than comparing the result of {@link #getChild(int)} for
+     * each index.</p>
+     *
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return the index of the child, or -1 if it is not a child of this element or
+     *         is not an XML node
+     * @throws IllegalArgumentException if child is null
+     * @throws IllegalStateException if this element or the child has been closed
+     */
+    public int indexOf(Object child) {
+        checkClosed();
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return indexOf(txn, child);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return indexOf(autoTxn, child);
+        }
+    }
+
+    /**
+     * Returns the index of a child node of this element using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return the index of the child, or -1 if it is not a child of this element or
+     *         is not an XML node
+     * @throws IllegalArgumentException if txn or child is null
+     * @throws IllegalStateException if this element or the child has been closed
+     * @see #indexOf(Object)
+     */
+    public int indexOf(YTransaction txn, Object child) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (child == null) {
+            throw new IllegalArgumentException("Child cannot be null");
+        }
+        long childPtr;
+        int childType;
+        if (child instanceof JniYXmlElement) {
+            JniYXmlElement element = (JniYXmlElement) child;
+            if (element.isClosed()) {
+                throw new IllegalStateException("Child YXmlElement has been closed");
+            }
+            childPtr = element.getNativePtr();
+            childType = YNativeRef.TYPE_ELEMENT;
+        } else if (child instanceof JniYXmlText) {
+            JniYXmlText text = (JniYXmlText) child;
+            if (text.isClosed()) {
+                throw new IllegalStateException("Child YXmlText has been closed");
+            }
+            childPtr = text.getNativePtr();
+            childType = YNativeRef.TYPE_TEXT;
+        } else if (child instanceof JniYXmlFragment) {
+            JniYXmlFragment fragment = (JniYXmlFragment) child;
+            if (fragment.isClosed()) {
+                throw new IllegalStateException("Child YXmlFragment has been closed");
+            }
+            childPtr = fragment.getNativePtr();
+            childType = YNativeRef.TYPE_FRAGMENT;
         } else {
-            throw new RuntimeException("Unknown child type: " + type);
+            return -1;
         }
+        return nativeIndexOfWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), childPtr, childType);
+    }
+
+    /**
+     * Returns whether a node is a child of this element.
+     *
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return true if the node is a child of this element
+     * @throws IllegalArgumentException if child is null
+     * @throws IllegalStateException if this element or the child has been closed
+     * @see #indexOf(Object)
+     */
+    public boolean contains(Object child) {
+        return indexOf(child) >= 0;
+    }
+
+    /**
+     * Returns whether a node is a child of this element using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return true if the node is a child of this element
+     * @throws IllegalArgumentException if txn or child is null
+     * @throws IllegalStateException if this element or the child has been closed
+     * @see #indexOf(YTransaction, Object)
+     */
+    public boolean contains(YTransaction txn, Object child) {
+        return indexOf(txn, child) >= 0;
+    }
+
+    /**
+     * Returns all child nodes of this element.
+     *
+     * <p>All children are fetched in a single native call, which is cheaper than
+     * calling {@link #getChild(int)} for each index.</p>
+     *
+     * @return The child nodes in document order (YXmlElement, YXmlText or nested
+     *         JniYXmlFragment)
+     * @throws IllegalStateException if the XML element has been closed
+     */
+    public List<YXmlNode> getChildren() {
+        checkClosed();
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return getChildren(txn);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return getChildren(autoTxn);
+        }
+    }
+
+    /**
+     * Returns all child nodes of this element using an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @return The child nodes in document order (YXmlElement, YXmlText or nested
+     *         JniYXmlFragment)
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the XML element has been closed
+     * @see #getChildren()
+     */
+    public List<YXmlNode> getChildren(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        Object result = nativeGetChildrenWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        if (result == null) {
+            return new ArrayList<>();
+        }
+
+        // Result is Object[3] where [0] = int[] types, [1] = long[] pointers,
+        // [2] = String[] tag-or-text previews
+        Object[] array = (Object[]) result;
+        int[] types = (int[]) array[0];
+        long[] pointers = (long[]) array[1];
+
+        List<YXmlNode> children = new ArrayList<>(types.length);
+        for (int i = 0; i < types.length; i++) {
+            if (types[i] == YNativeRef.TYPE_ELEMENT) {
+                children.add(new JniYXmlElement(doc, pointers[i]));
+            } else if (types[i] == YNativeRef.TYPE_TEXT) {
+                children.add(new JniYXmlText(doc, pointers[i]));
+            } else if (types[i] == YNativeRef.TYPE_FRAGMENT) {
+                children.add(new JniYXmlFragment(doc, pointers[i]));
+            } else {
+                throw new RuntimeException("Unknown child type: " + types[i]);
+            }
+        }
+        return children;
     }
 
     /**
@@ -660,25 +1016,55 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
         }
-        Object result = nativeGetParentWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
-        if (result == null) {
+        YNativeRef ref = nativeGetParentWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+        if (ref == null) {
             return null;
         }
+        return ref.toXmlNode(doc);
+    }
 
-        // Result is Object[2] where [0] = Integer type, [1] = Long pointer
-        Object[] array = (Object[]) result;
-        int type = ((Integer) array[0]).intValue();
-        long pointer = ((Long) array[1]).longValue();
-
-        if (type == 0) {
-            // Element
-            return new JniYXmlElement(doc, pointer);
-        } else if (type == 1) {
-            // Fragment
-            return new JniYXmlFragment(doc, pointer);
-        } else {
-            throw new RuntimeException("Unknown parent type: " + type);
+    /**
+     * Gets the nearest fragment containing this element.
+     *
+     * <p>Parent elements are skipped until a fragment is reached. For an element
+     * obtained from {@link JniYDoc#getXmlElement(String)} this is the root fragment of
+     * the same name, which holds the element as its only child.</p>
+     *
+     * @return The containing fragment, or null if no fragment contains this element
+     * @throws IllegalStateException if the XML element has been closed
+     * @see JniYXmlFragment#getWrappedElement()
+     */
+    public JniYXmlFragment getOwningFragment() {
+        checkClosed();
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return getOwningFragment(txn);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return getOwningFragment(autoTxn);
+        }
+    }
+
+    /**
+     * Gets the nearest fragment containing this element using an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @return The containing fragment, or null if no fragment contains this element
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the XML element has been closed
+     * @see #getOwningFragment()
+     */
+    public JniYXmlFragment getOwningFragment(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        long fragmentPtr = nativeGetOwningFragmentWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        if (fragmentPtr == 0) {
+            return null;
         }
+        return new JniYXmlFragment(doc, fragmentPtr);
     }
 
     /**
@@ -745,7 +1131,7 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         if (observer == null) {
             throw new IllegalArgumentException("Observer cannot be null");
         }
-        long id = nextSubscriptionId.incrementAndGet();
+        long id = doc.allocateSubscriptionId();
         observers.put(id, observer);
         nativeObserve(doc.getNativePtr(), nativePtr, id, this);
         return new JniYSubscription(id, observer, this);
@@ -820,7 +1206,8 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
                     }
                     observers.clear();
 
-                    if (nativePtr != 0) {
+                    // The document frees released handles itself
+                    if (nativePtr != 0 && !isReleased()) {
                         nativeDestroy(nativePtr);
                         nativePtr = 0;
                     }
@@ -839,6 +1226,18 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
         if (closed) {
             throw new IllegalStateException("YXmlElement has been closed");
         }
+        if (isReleased()) {
+            throw new IllegalStateException("YXmlElement was released by its YDoc");
+        }
+    }
+
+    /**
+     * Returns whether the document released the native handle of this root.
+     *
+     * @return true after {@link JniYDoc#releaseAllRoots()} or closing the document
+     */
+    private boolean isReleased() {
+        return rootGeneration >= 0 && doc.getRootGeneration() != rootGeneration;
     }
 
     /**
@@ -854,20 +1253,32 @@ public class JniYXmlElement implements YXmlElement, JniYObservable {
     private static native long nativeGetXmlElement(long docPtr, String name);
     private static native void nativeDestroy(long ptr);
     private static native String nativeGetTagWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
+    private static native Object[] nativeGetPathWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
     private static native Object nativeGetAttributeWithTxn(long docPtr, long xmlElementPtr, long txnPtr, String name);
     private static native void nativeSetAttributeWithTxn(
             long docPtr, long xmlElementPtr, long txnPtr, String name, Object value);
     private static native void nativeRemoveAttributeWithTxn(
             long docPtr, long xmlElementPtr, long txnPtr, String name);
+    private static native void nativeSetAttributesWithTxn(
+            long docPtr, long xmlElementPtr, long txnPtr, Map<String, ?> attributes);
+    private static native void nativeRemoveAttributesWithTxn(
+            long docPtr, long xmlElementPtr, long txnPtr, String[] names);
     private static native Object nativeGetAttributeNamesWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
     private static native String nativeToStringWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
     private static native int nativeChildCountWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
+    private static native int nativeChildCount(long docPtr, long xmlElementPtr);
     private static native long nativeInsertElementWithTxn(
             long docPtr, long xmlElementPtr, long txnPtr, int index, String tag);
     private static native long nativeInsertTextWithTxn(long docPtr, long xmlElementPtr, long txnPtr, int index);
-    private static native Object nativeGetChildWithTxn(long docPtr, long xmlElementPtr, long txnPtr, int index);
+    private static native long nativeInsertFragmentWithTxn(long docPtr, long xmlElementPtr, long txnPtr,
+        int index);
+    private static native YNativeRef nativeGetChildWithTxn(long docPtr, long xmlElementPtr, long txnPtr, int index);
+    private static native int nativeIndexOfWithTxn(long docPtr, long xmlElementPtr, long txnPtr,
+        long childPtr, int childType);
+    private static native Object nativeGetChildrenWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
     private static native void nativeRemoveChildWithTxn(long docPtr, long xmlElementPtr, long txnPtr, int index);
-    private static native Object nativeGetParentWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
+    private static native YNativeRef nativeGetParentWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
+    private static native long nativeGetOwningFragmentWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
     private static native int nativeGetIndexInParentWithTxn(long docPtr, long xmlElementPtr, long txnPtr);
     private static native void nativeObserve(long docPtr, long xmlElementPtr, long subscriptionId,
                                               YXmlElement xmlElementObj);
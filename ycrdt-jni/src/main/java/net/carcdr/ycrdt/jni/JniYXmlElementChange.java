@@ -12,6 +12,10 @@ import net.carcdr.ycrdt.YXmlElementChange;
  *   <li><b>ATTRIBUTE:</b> An attribute's value was modified</li>
  * </ul>
  *
+ * <p>Values are available as strings, and with the type they were set with from
+ * {@link #getNewTypedValue()} and {@link #getOldTypedValue()}. Each change also
+ * carries the origin of the transaction that made it.</p>
+ *
  * <p>Note: Changes to children are reported as YArrayChange events.</p>
  *
  * @see JniYEvent
@@ -22,20 +26,30 @@ public final class JniYXmlElementChange extends YXmlElementChange {
     private final String attributeName;
     private final String newValue;
     private final String oldValue;
+    private final Object newTypedValue;
+    private final Object oldTypedValue;
+    private final JniEventOrigin eventOrigin;
 
     /**
-     * Package-private constructor.
+     * Package-private constructor used by the native layer.
      *
      * @param type the change type
      * @param attributeName the attribute name that changed
-     * @param newValue the new value (may be null)
-     * @param oldValue the old value (may be null)
+     * @param newValue the new value as a string (may be null)
+     * @param newTypedValue the new value as converted from native code (may be null)
+     * @param oldValue the old value as a string (may be null)
+     * @param oldTypedValue the old value as converted from native code (may be null)
+     * @param eventOrigin the transaction that made the change (may be null)
      */
-    JniYXmlElementChange(Type type, String attributeName, String newValue, String oldValue) {
+    JniYXmlElementChange(Type type, String attributeName, String newValue, Object newTypedValue,
+                         String oldValue, Object oldTypedValue, JniEventOrigin eventOrigin) {
         this.type = type;
         this.attributeName = attributeName;
         this.newValue = newValue;
         this.oldValue = oldValue;
+        this.newTypedValue = newTypedValue;
+        this.oldTypedValue = oldTypedValue;
+        this.eventOrigin = eventOrigin;
     }
 
     @Override
@@ -58,6 +72,34 @@ public final class JniYXmlElementChange extends YXmlElementChange {
         return oldValue;
     }
 
+    /**
+     * Returns the new value with the type it was set with, such as a Long,
+     * Boolean or Map.
+     *
+     * @return the new value, or null if the attribute was removed
+     */
+    public Object getNewTypedValue() {
+        return newTypedValue;
+    }
+
+    /**
+     * Returns the previous value with the type it was set with.
+     *
+     * @return the old value, or null if the attribute was newly added
+     */
+    public Object getOldTypedValue() {
+        return oldTypedValue;
+    }
+
+    /**
+     * Returns the origin of the transaction that changed the attribute.
+     *
+     * @return the origin, or null if the change was not created by native code
+     */
+    public JniEventOrigin getEventOrigin() {
+        return eventOrigin;
+    }
+
     @Override
     public String toString() {
         switch (type) {
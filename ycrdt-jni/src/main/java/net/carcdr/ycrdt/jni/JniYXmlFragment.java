@@ -9,7 +9,6 @@ import net.carcdr.ycrdt.YXmlNode;
 import net.carcdr.ycrdt.YXmlText;
 
 import java.util.concurrent.ConcurrentHashMap;
-import java.util.concurrent.atomic.AtomicLong;
 
 /**
  * Represents a fragment of XML content in a Y-CRDT document.
@@ -44,13 +43,18 @@ import java.util.concurrent.atomic.AtomicLong;
  *
  * @since 0.2.0
  */
-public class JniYXmlFragment implements YXmlFragment, JniYObservable {
+public class JniYXmlFragment implements YXmlFragment, YXmlNode, JniYObservable {
 
     private final JniYDoc doc;
     private long nativeHandle;
     private volatile boolean closed = false;
+
+    /**
+     * Root generation of the document when this root was looked up by name, or
+     * -1 if the handle was not obtained by name.
+     */
+    private final int rootGeneration;
     private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
-    private final AtomicLong nextSubscriptionId = new AtomicLong(0);
 
     /**
      * Package-private constructor. Use {@link YDoc#getXmlFragment(String)} to create instances.
@@ -66,6 +70,7 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
             throw new IllegalArgumentException("Name cannot be null");
         }
         this.doc = doc;
+        this.rootGeneration = doc.getRootGeneration();
         this.nativeHandle = nativeGetFragment(doc.getNativeHandle(), name);
     }
 
@@ -85,34 +90,71 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         }
         this.doc = doc;
         this.nativeHandle = nativeHandle;
+        this.rootGeneration = -1;
     }
 
     /**
      * Returns the number of children in this fragment.
      *
+     * <p>Without an active transaction, this reads the document under a
+     * short-lived read transaction, which concurrent readers share, instead of
+     * opening a write transaction.</p>
+     *
      * @return the number of child nodes
+     * @throws ArithmeticException if the length does not fit in an int
      * @throws IllegalStateException if this fragment has been closed
      */
     public int length() {
+        return Sizes.toInt(longLength(), "longLength");
+    }
+
+    /**
+     * Returns the number of children in this fragment using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the number of child nodes
+     * @throws IllegalArgumentException if txn is null
+     * @throws ArithmeticException if the length does not fit in an int
+     * @throws IllegalStateException if this fragment has been closed
+     */
+    public int length(YTransaction txn) {
+        return Sizes.toInt(longLength(txn), "longLength");
+    }
+
+    /**
+     * Returns the number of children in this fragment.
+     *
+     * <p>Without an active transaction, this reads the document under a
+     * short-lived read transaction, which concurrent readers share, instead of
+     * opening a write transaction.</p>
+     *
+     * <p>Unlike {@link #length()}, works for fragments with more
+     * than {@link Integer#MAX_VALUE} children.</p>
+     *
+     * @return the number of child nodes
+     * @throws IllegalStateException if this fragment has been closed
+     */
+    public long longLength() {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
             return nativeLengthWithTxn(doc.getNativeHandle(), nativeHandle, activeTxn.getNativePtr());
         }
-        try (JniYTransaction txn = doc.beginTransaction()) {
-            return nativeLengthWithTxn(doc.getNativeHandle(), nativeHandle, ((JniYTransaction) txn).getNativePtr());
-        }
+        return nativeLength(doc.getNativeHandle(), nativeHandle);
     }
 
     /**
      * Returns the number of children in this fragment using an existing transaction.
      *
+     * <p>Unlike {@link #length(YTransaction)}, works for fragments with more
+     * than {@link Integer#MAX_VALUE} children.</p>
+     *
      * @param txn The transaction to use for this operation
      * @return the number of child nodes
      * @throws IllegalArgumentException if txn is null
      * @throws IllegalStateException if this fragment has been closed
      */
-    public int length(YTransaction txn) {
+    public long longLength(YTransaction txn) {
         checkClosed();
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
@@ -250,6 +292,53 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
             ((JniYTransaction) txn).getNativePtr(), index, content);
     }
 
+    /**
+     * Inserts an empty nested XML fragment as a child at the specified index.
+     *
+     * @param index the index at which to insert (0-based)
+     * @return the new fragment
+     * @throws IllegalStateException if this fragment has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than length()
+     */
+    public JniYXmlFragment insertFragment(int index) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return insertFragment(activeTxn, index);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return insertFragment(txn, index);
+        }
+    }
+
+    /**
+     * Inserts an empty nested XML fragment as a child at the specified index within
+     * an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @param index the index at which to insert (0-based)
+     * @return the new fragment
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this fragment has been closed
+     * @throws IndexOutOfBoundsException if index is negative or greater than length()
+     * @see #insertFragment(int)
+     */
+    public JniYXmlFragment insertFragment(YTransaction txn, int index) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (index < 0 || index > length(txn)) {
+            throw new IndexOutOfBoundsException("Index: " + index + ", Length: " + length(txn));
+        }
+        long childPtr = nativeInsertFragmentWithTxn(doc.getNativeHandle(), nativeHandle,
+            ((JniYTransaction) txn).getNativePtr(), index);
+        if (childPtr == 0) {
+            throw new RuntimeException("Failed to insert fragment child");
+        }
+        return new JniYXmlFragment(doc, childPtr);
+    }
+
     /**
      * Removes children from this fragment.
      *
@@ -324,6 +413,16 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         nativeRemoveWithTxn(doc.getNativeHandle(), nativeHandle, ((JniYTransaction) txn).getNativePtr(), index, length);
     }
 
+    /**
+     * Returns the node type of this fragment, as seen when it is nested in an element.
+     *
+     * @return {@link YXmlNode.NodeType#FRAGMENT}
+     */
+    @Override
+    public YXmlNode.NodeType getNodeType() {
+        return YXmlNode.NodeType.FRAGMENT;
+    }
+
     /**
      * Gets the type of the child node at the specified index.
      *
@@ -344,12 +443,7 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
                     ((JniYTransaction) txn).getNativePtr(), index);
             }
         }
-        if (type == 0) {
-            return YXmlNode.NodeType.ELEMENT;
-        } else if (type == 1) {
-            return YXmlNode.NodeType.TEXT;
-        }
-        return null;
+        return YNativeRef.nodeType(type);
     }
 
     /**
@@ -368,12 +462,7 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         }
         int type = nativeGetNodeTypeWithTxn(doc.getNativeHandle(), nativeHandle,
             ((JniYTransaction) txn).getNativePtr(), index);
-        if (type == 0) {
-            return YXmlNode.NodeType.ELEMENT;
-        } else if (type == 1) {
-            return YXmlNode.NodeType.TEXT;
-        }
-        return null;
+        return YNativeRef.nodeType(type);
     }
 
     /**
@@ -403,7 +492,7 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
      * }</pre>
      *
      * @param index the index of the child node (0-based)
-     * @return a YXmlElement or YXmlText depending on the child type,
+     * @return a YXmlElement, YXmlText or nested YXmlFragment depending on the child type,
      *         or null if the index is out of bounds
      * @throws IllegalStateException if this fragment has been closed
      * @throws IndexOutOfBoundsException if index is negative
@@ -414,19 +503,141 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
             throw new IndexOutOfBoundsException("Index cannot be negative: " + index);
         }
 
-        YXmlNode.NodeType type = getNodeType(index);
-        if (type == null) {
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getChild(activeTxn, index);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getChild(txn, index);
+        }
+    }
+
+    /**
+     * Retrieves a child node at the specified index using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param index the index of the child node (0-based)
+     * @return a YXmlElement, YXmlText or nested YXmlFragment depending on the child type,
+     *         or null if the index is out of bounds
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this fragment has been closed
+     * @throws IndexOutOfBoundsException if index is negative
+     * @see #getChild(int)
+     */
+    public Object getChild(YTransaction txn, int index) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (index < 0) {
+            throw new IndexOutOfBoundsException("Index cannot be negative: " + index);
+        }
+        YNativeRef ref = nativeGetChildWithTxn(doc.getNativeHandle(), nativeHandle,
+            ((JniYTransaction) txn).getNativePtr(), index);
+        if (ref == null) {
             return null;
         }
+        return ref.toXmlNode(doc);
+    }
 
-        switch (type) {
-            case ELEMENT:
-                return getElement(index);
-            case TEXT:
-                return getText(index);
-            default:
-                return null;
+    /**
+     * Returns the index of a child node of this fragment.
+     *
+     * <p>The child is located natively in a single pass over the children, so
+     * this is cheaper than comparing the result of {@link #getChild(int)} for
+     * each index.</p>
+     *
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return the index of the child, or -1 if it is not a child of this fragment or
+     *         is not an XML node
+     * @throws IllegalArgumentException if child is null
+     * @throws IllegalStateException if this fragment or the child has been closed
+     */
+    public int indexOf(Object child) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return indexOf(activeTxn, child);
         }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return indexOf(txn, child);
+        }
+    }
+
+    /**
+     * Returns the index of a child node of this fragment using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return the index of the child, or -1 if it is not a child of this fragment or
+     *         is not an XML node
+     * @throws IllegalArgumentException if txn or child is null
+     * @throws IllegalStateException if this fragment or the child has been closed
+     * @see #indexOf(Object)
+     */
+    public int indexOf(YTransaction txn, Object child) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (child == null) {
+            throw new IllegalArgumentException("Child cannot be null");
+        }
+        long childPtr;
+        int childType;
+        if (child instanceof JniYXmlElement) {
+            JniYXmlElement element = (JniYXmlElement) child;
+            if (element.isClosed()) {
+                throw new IllegalStateException("Child YXmlElement has been closed");
+            }
+            childPtr = element.getNativePtr();
+            childType = YNativeRef.TYPE_ELEMENT;
+        } else if (child instanceof JniYXmlText) {
+            JniYXmlText text = (JniYXmlText) child;
+            if (text.isClosed()) {
+                throw new IllegalStateException("Child YXmlText has been closed");
+            }
+            childPtr = text.getNativePtr();
+            childType = YNativeRef.TYPE_TEXT;
+        } else if (child instanceof JniYXmlFragment) {
+            JniYXmlFragment nested = (JniYXmlFragment) child;
+            if (nested.isClosed()) {
+                throw new IllegalStateException("Child YXmlFragment has been closed");
+            }
+            childPtr = nested.getNativePtr();
+            childType = YNativeRef.TYPE_FRAGMENT;
+        } else {
+            return -1;
+        }
+        return nativeIndexOfWithTxn(doc.getNativeHandle(), nativeHandle,
+            ((JniYTransaction) txn).getNativePtr(), childPtr, childType);
+    }
+
+    /**
+     * Returns whether a node is a child of this fragment.
+     *
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return true if the node is a child of this fragment
+     * @throws IllegalArgumentException if child is null
+     * @throws IllegalStateException if this fragment or the child has been closed
+     * @see #indexOf(Object)
+     */
+    public boolean contains(Object child) {
+        return indexOf(child) >= 0;
+    }
+
+    /**
+     * Returns whether a node is a child of this fragment using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param child a YXmlElement, YXmlText or nested JniYXmlFragment
+     * @return true if the node is a child of this fragment
+     * @throws IllegalArgumentException if txn or child is null
+     * @throws IllegalStateException if this fragment or the child has been closed
+     * @see #indexOf(YTransaction, Object)
+     */
+    public boolean contains(YTransaction txn, Object child) {
+        return indexOf(txn, child) >= 0;
     }
 
     /**
@@ -505,6 +716,52 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         return new JniYXmlElement(doc, elementPtr);
     }
 
+    /**
+     * Returns the element this fragment wraps.
+     *
+     * <p>A fragment wraps an element when the element is its only child. Root-level
+     * elements from {@link JniYDoc#getXmlElement(String)} are stored this way, so this
+     * gives the same element for a fragment of the same name.</p>
+     *
+     * @return the wrapped element, or null if this fragment does not have exactly one
+     *         child or that child is not an element
+     * @throws IllegalStateException if this fragment has been closed
+     * @see JniYXmlElement#getOwningFragment()
+     */
+    public JniYXmlElement getWrappedElement() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getWrappedElement(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getWrappedElement(txn);
+        }
+    }
+
+    /**
+     * Returns the element this fragment wraps using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the wrapped element, or null if this fragment does not have exactly one
+     *         child or that child is not an element
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this fragment has been closed
+     * @see #getWrappedElement()
+     */
+    public JniYXmlElement getWrappedElement(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        long elementPtr = nativeGetWrappedElementWithTxn(doc.getNativeHandle(), nativeHandle,
+            ((JniYTransaction) txn).getNativePtr());
+        if (elementPtr == 0) {
+            return null;
+        }
+        return new JniYXmlElement(doc, elementPtr);
+    }
+
     /**
      * Retrieves a child text node at the specified index.
      *
@@ -617,6 +874,47 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         return nativeToXmlStringWithTxn(doc.getNativeHandle(), nativeHandle, ((JniYTransaction) txn).getNativePtr());
     }
 
+    /**
+     * Materializes this fragment and everything below it as an immutable tree within
+     * an existing transaction.
+     *
+     * <p>The whole tree is read in one native call, and the returned nodes hold no
+     * native resources. This suits renderers and other read-mostly consumers that
+     * would otherwise open a handle per node.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @return a node of kind {@link JniXmlNode.Kind#FRAGMENT} holding the tree
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if this fragment has been closed
+     */
+    public JniXmlNode snapshotTree(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeSnapshotTreeWithTxn(doc.getNativeHandle(), nativeHandle,
+            ((JniYTransaction) txn).getNativePtr());
+    }
+
+    /**
+     * Materializes this fragment and everything below it as an immutable tree
+     * (creates implicit transaction).
+     *
+     * @return a node of kind {@link JniXmlNode.Kind#FRAGMENT} holding the tree
+     * @throws IllegalStateException if this fragment has been closed
+     * @see #snapshotTree(YTransaction)
+     */
+    public JniXmlNode snapshotTree() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return snapshotTree(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return snapshotTree(txn);
+        }
+    }
+
     /**
      * Returns the XML string representation of this fragment.
      * Equivalent to {@link #toXmlString()}.
@@ -662,7 +960,7 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         if (observer == null) {
             throw new IllegalArgumentException("Observer cannot be null");
         }
-        long id = nextSubscriptionId.incrementAndGet();
+        long id = doc.allocateSubscriptionId();
         observers.put(id, observer);
         nativeObserve(doc.getNativeHandle(), nativeHandle, id, this);
         return new JniYSubscription(id, observer, this);
@@ -723,7 +1021,8 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
                     }
                     observers.clear();
 
-                    if (nativeHandle != 0) {
+                    // The document frees released handles itself
+                    if (nativeHandle != 0 && !isReleased()) {
                         nativeDestroy(nativeHandle);
                         nativeHandle = 0;
                     }
@@ -742,6 +1041,15 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         return closed;
     }
 
+    /**
+     * Gets the native handle for internal use.
+     *
+     * @return the native handle value
+     */
+    long getNativePtr() {
+        return nativeHandle;
+    }
+
     /**
      * Throws an exception if this fragment has been closed.
      *
@@ -751,6 +1059,18 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
         if (closed) {
             throw new IllegalStateException("YXmlFragment has been closed");
         }
+        if (isReleased()) {
+            throw new IllegalStateException("YXmlFragment was released by its YDoc");
+        }
+    }
+
+    /**
+     * Returns whether the document released the native handle of this root.
+     *
+     * @return true after {@link JniYDoc#releaseAllRoots()} or closing the document
+     */
+    private boolean isReleased() {
+        return rootGeneration >= 0 && doc.getRootGeneration() != rootGeneration;
     }
 
     // Native methods
@@ -758,13 +1078,17 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
 
     private static native void nativeDestroy(long ptr);
 
-    private static native int nativeLengthWithTxn(long docPtr, long fragmentPtr, long txnPtr);
+    private static native long nativeLengthWithTxn(long docPtr, long fragmentPtr, long txnPtr);
+
+    private static native long nativeLength(long docPtr, long fragmentPtr);
 
     private static native void nativeInsertElementWithTxn(long docPtr, long fragmentPtr, long txnPtr,
             int index, String tag);
 
     private static native void nativeInsertTextWithTxn(long docPtr, long fragmentPtr, long txnPtr,
             int index, String content);
+    private static native long nativeInsertFragmentWithTxn(long docPtr, long fragmentPtr, long txnPtr,
+            int index);
 
     private static native void nativeRemoveWithTxn(long docPtr, long fragmentPtr, long txnPtr,
             int index, int length);
@@ -775,11 +1099,23 @@ public class JniYXmlFragment implements YXmlFragment, JniYObservable {
     private static native long nativeGetElementWithTxn(long docPtr, long fragmentPtr, long txnPtr,
             int index);
 
+    private static native long nativeGetWrappedElementWithTxn(long docPtr, long fragmentPtr,
+        long txnPtr);
+
     private static native long nativeGetTextWithTxn(long docPtr, long fragmentPtr, long txnPtr,
             int index);
 
+    private static native YNativeRef nativeGetChildWithTxn(long docPtr, long fragmentPtr, long txnPtr,
+            int index);
+
+    private static native int nativeIndexOfWithTxn(long docPtr, long fragmentPtr, long txnPtr,
+        long childPtr, int childType);
+
     private static native String nativeToXmlStringWithTxn(long docPtr, long fragmentPtr, long txnPtr);
 
+    private static native JniXmlNode nativeSnapshotTreeWithTxn(long docPtr, long fragmentPtr,
+            long txnPtr);
+
     private static native void nativeObserve(long docPtr, long fragmentPtr, long subscriptionId,
                                               YXmlFragment fragmentObj);
 
@@ -10,7 +10,7 @@ import java.io.Closeable;
 import java.util.List;
 import java.util.Map;
 import java.util.concurrent.ConcurrentHashMap;
-import java.util.concurrent.atomic.AtomicLong;
+import java.util.function.Consumer;
 
 /**
  * YXmlText represents a collaborative XML text type in a Y-CRDT document.
@@ -39,8 +39,13 @@ public class JniYXmlText implements YXmlText, JniYObservable {
     private final JniYDoc doc;
     private long nativePtr;
     private volatile boolean closed = false;
+
+    /**
+     * Root generation of the document when this root was looked up by name, or
+     * -1 if the handle was not obtained by name.
+     */
+    private final int rootGeneration;
     private final ConcurrentHashMap<Long, YObserver> observers = new ConcurrentHashMap<>();
-    private final AtomicLong nextSubscriptionId = new AtomicLong(0);
 
     /**
      * Package-private constructor. Use {@link YDoc#getXmlText(String)} to create instances.
@@ -56,6 +61,7 @@ public class JniYXmlText implements YXmlText, JniYObservable {
             throw new IllegalArgumentException("Name cannot be null");
         }
         this.doc = doc;
+        this.rootGeneration = doc.getRootGeneration();
         this.nativePtr = nativeGetXmlText(doc.getNativePtr(), name);
         if (this.nativePtr == 0) {
             throw new RuntimeException("Failed to create YXmlText");
@@ -78,6 +84,7 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         }
         this.doc = doc;
         this.nativePtr = nativeHandle;
+        this.rootGeneration = -1;
     }
 
     @Override
@@ -88,29 +95,65 @@ public class JniYXmlText implements YXmlText, JniYObservable {
     /**
      * Returns the length of the text in characters.
      *
+     * <p>Without an active transaction, this reads the document under a
+     * short-lived read transaction, which concurrent readers share, instead of
+     * opening a write transaction.</p>
+     *
      * @return The text length
+     * @throws ArithmeticException if the length does not fit in an int
      * @throws IllegalStateException if the XML text has been closed
      */
     public int length() {
+        return Sizes.toInt(longLength(), "longLength");
+    }
+
+    /**
+     * Returns the length of the text in characters using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @return The text length
+     * @throws IllegalArgumentException if txn is null
+     * @throws ArithmeticException if the length does not fit in an int
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public int length(YTransaction txn) {
+        return Sizes.toInt(longLength(txn), "longLength");
+    }
+
+    /**
+     * Returns the length of the text in characters.
+     *
+     * <p>Without an active transaction, this reads the document under a
+     * short-lived read transaction, which concurrent readers share, instead of
+     * opening a write transaction.</p>
+     *
+     * <p>Unlike {@link #length()}, works for texts longer
+     * than {@link Integer#MAX_VALUE}.</p>
+     *
+     * @return The text length
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public long longLength() {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
         if (activeTxn != null) {
             return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, activeTxn.getNativePtr());
         }
-        try (JniYTransaction txn = doc.beginTransaction()) {
-            return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
-        }
+        return nativeLength(doc.getNativePtr(), nativePtr);
     }
 
     /**
      * Returns the length of the text in characters using an existing transaction.
      *
+     * <p>Unlike {@link #length(YTransaction)}, works for texts longer
+     * than {@link Integer#MAX_VALUE}.</p>
+     *
      * @param txn The transaction to use for this operation
      * @return The text length
      * @throws IllegalArgumentException if txn is null
      * @throws IllegalStateException if the XML text has been closed
      */
-    public int length(YTransaction txn) {
+    public long longLength(YTransaction txn) {
         checkClosed();
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
@@ -118,6 +161,36 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         return nativeLengthWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
     }
 
+    /**
+     * Returns the root this XML text belongs to and the path leading to it.
+     *
+     * @return the path, or null if the text was deleted
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public JniTypePath getPath() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getPath(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getPath(txn);
+        }
+    }
+
+    /**
+     * Returns the root this XML text belongs to and the path leading to it.
+     *
+     * @param txn The transaction to use for this operation
+     * @return the path, or null if the text was deleted
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public JniTypePath getPath(YTransaction txn) {
+        checkClosed();
+        return JniTypePath.fromNative(
+            nativeGetPathWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr()));
+    }
+
     /**
      * Returns the string representation of the XML text content.
      *
@@ -157,6 +230,105 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         return result != null ? result : "";
     }
 
+    /**
+     * Returns a piece of the XML text content within an existing transaction.
+     *
+     * <p>Offsets and sizes count bytes of the UTF-8 encoding of {@link #toString()}, and
+     * a chunk never ends inside a character. The chunk is at most {@code maxBytes} long
+     * unless the character at {@code offset} is longer, in which case the chunk holds
+     * just that character. The next chunk starts at {@code offset} plus the UTF-8
+     * length of this one.</p>
+     *
+     * <p>Each call builds the full content natively. To read all of a large XML text,
+     * use {@link #readChunks(YTransaction, int, Consumer)}, which does so once.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param offset the UTF-8 byte offset to start at
+     * @param maxBytes the maximum UTF-8 length of the chunk
+     * @return the chunk, or an empty string if offset is at the end of the content
+     * @throws IllegalArgumentException if txn is null, maxBytes is not positive, or
+     *         offset is negative, past the end or inside a character
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public String getStringChunk(YTransaction txn, int offset, int maxBytes) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeGetStringChunkWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), offset, maxBytes);
+    }
+
+    /**
+     * Returns a piece of the XML text content (creates implicit transaction).
+     *
+     * @param offset the UTF-8 byte offset to start at
+     * @param maxBytes the maximum UTF-8 length of the chunk
+     * @return the chunk, or an empty string if offset is at the end of the content
+     * @throws IllegalArgumentException if maxBytes is not positive, or offset is
+     *         negative, past the end or inside a character
+     * @throws IllegalStateException if the XML text has been closed
+     * @see #getStringChunk(YTransaction, int, int)
+     */
+    public String getStringChunk(int offset, int maxBytes) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getStringChunk(activeTxn, offset, maxBytes);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getStringChunk(txn, offset, maxBytes);
+        }
+    }
+
+    /**
+     * Passes the XML text content to a consumer in chunks within an existing transaction.
+     *
+     * <p>Concatenating the chunks gives {@link #toString()}, but no single Java string
+     * larger than a chunk is created. Chunks follow the rules of
+     * {@link #getStringChunk(YTransaction, int, int)}. An exception thrown by the
+     * consumer stops the read and is rethrown.</p>
+     *
+     * @param txn The transaction to use for this operation
+     * @param maxBytes the maximum UTF-8 length of each chunk
+     * @param consumer receives the chunks in order
+     * @throws IllegalArgumentException if txn or consumer is null, or maxBytes is not
+     *         positive
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public void readChunks(YTransaction txn, int maxBytes, Consumer<String> consumer) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (consumer == null) {
+            throw new IllegalArgumentException("Consumer cannot be null");
+        }
+        nativeReadChunksWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), maxBytes, consumer);
+    }
+
+    /**
+     * Passes the XML text content to a consumer in chunks (creates implicit transaction).
+     *
+     * @param maxBytes the maximum UTF-8 length of each chunk
+     * @param consumer receives the chunks in order
+     * @throws IllegalArgumentException if consumer is null or maxBytes is not positive
+     * @throws IllegalStateException if the XML text has been closed
+     * @see #readChunks(YTransaction, int, Consumer)
+     */
+    public void readChunks(int maxBytes, Consumer<String> consumer) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            readChunks(activeTxn, maxBytes, consumer);
+            return;
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            readChunks(txn, maxBytes, consumer);
+        }
+    }
+
     /**
      * Inserts text at the specified index within an existing transaction.
      *
@@ -506,42 +678,265 @@ public class JniYXmlText implements YXmlText, JniYObservable {
     }
 
     /**
-     * Gets the parent of this XML text node.
-     * The parent can be either a YXmlElement or YXmlFragment.
+     * Applies formatting to several ranges within an existing transaction.
      *
-     * @return The parent node (YXmlElement or YXmlFragment), or null if this text node has no parent
+     * <p>All ranges are applied in one native call. They are validated before any is
+     * applied, so an out-of-bounds range leaves the XML text unchanged. Ranges are
+     * applied in list order, so later ranges win where they overlap.</p>
+     *
+     * @param txn Transaction handle
+     * @param ranges The ranges to format and their attributes
+     * @throws IllegalArgumentException if txn or ranges is null, or ranges contains null
      * @throws IllegalStateException if the XML text has been closed
+     * @throws IndexOutOfBoundsException if any range extends past the end of the XML text
      */
-    public Object getParent() {
+    public void formatRanges(YTransaction txn, List<JniFormatRange> ranges) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        JniFormatRange.requireValid(ranges);
+        if (ranges.isEmpty()) {
+            return;
+        }
+        nativeFormatRangesWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(),
+            JniFormatRange.toBounds(ranges), JniFormatRange.toAttributes(ranges));
+    }
+
+    /**
+     * Applies formatting to several ranges (creates implicit transaction).
+     *
+     * @param ranges The ranges to format and their attributes
+     * @throws IllegalArgumentException if ranges is null or contains null
+     * @throws IllegalStateException if the XML text has been closed
+     * @throws IndexOutOfBoundsException if any range extends past the end of the XML text
+     * @see #formatRanges(YTransaction, List)
+     */
+    public void formatRanges(List<JniFormatRange> ranges) {
         checkClosed();
         JniYTransaction activeTxn = doc.getActiveTransaction();
-        Object result;
         if (activeTxn != null) {
-            result = nativeGetParentWithTxn(doc.getNativePtr(), nativePtr,
-                activeTxn.getNativePtr());
+            formatRanges(activeTxn, ranges);
         } else {
             try (JniYTransaction txn = doc.beginTransaction()) {
-                result = nativeGetParentWithTxn(doc.getNativePtr(), nativePtr,
-                    ((JniYTransaction) txn).getNativePtr());
+                formatRanges(txn, ranges);
             }
         }
-        if (result == null) {
-            return null;
+    }
+
+    /**
+     * Gets a node-level attribute value by name.
+     *
+     * <p>Node attributes belong to the text node itself (for example
+     * {@code data-id} on an inline {@code <span>}) and are separate from the
+     * formatting attributes applied to text runs via {@link #format}.</p>
+     *
+     * <p>Return types are {@link String}, {@link Long}, {@link Double},
+     * {@link Boolean}, or {@code null} (when the attribute is absent or stored
+     * as null). A shared type stored as an attribute by another peer is returned
+     * as a {@link JniTreeNode}.
+     *
+     * @param name The attribute name
+     * @return The attribute value, or {@code null} if not found
+     * @throws IllegalArgumentException if name is null
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public Object getAttribute(String name) {
+        checkClosed();
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return getAttribute(txn, name);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return getAttribute(autoTxn, name);
+        }
+    }
+
+    /**
+     * Gets a node-level attribute value by name using an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @param name The attribute name
+     * @return The attribute value, or {@code null} if not found
+     * @throws IllegalArgumentException if txn or name is null
+     * @throws IllegalStateException if the XML text has been closed
+     * @see #getAttribute(String)
+     */
+    public Object getAttribute(YTransaction txn, String name) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
         }
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        return nativeGetAttributeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), name);
+    }
 
-        // Result is Object[2] where [0] = Integer type, [1] = Long pointer
-        Object[] array = (Object[]) result;
-        int type = ((Integer) array[0]).intValue();
-        long pointer = ((Long) array[1]).longValue();
+    /**
+     * Sets a node-level attribute value.
+     *
+     * <p>Supported value types: {@link String}, {@link Long}, {@link Integer},
+     * {@link Double}, {@link Float}, {@link Boolean}, or {@code null}.
+     * {@code Integer} and {@code Float} are widened to {@code Long} and
+     * {@code Double} respectively when stored.
+     *
+     * @param name The attribute name
+     * @param value The attribute value (may be {@code null})
+     * @throws IllegalArgumentException if name is null or value is not a supported type
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public void setAttribute(String name, Object value) {
+        checkClosed();
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        validateAttributeValue(value);
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            setAttribute(txn, name, value);
+            return;
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            setAttribute(autoTxn, name, value);
+        }
+    }
 
-        if (type == 0) {
-            // Element
-            return new JniYXmlElement(doc, pointer);
-        } else if (type == 1) {
-            // Fragment
-            return new JniYXmlFragment(doc, pointer);
-        } else {
-            throw new RuntimeException("Unknown parent type: " + type);
+    /**
+     * Sets a node-level attribute value within an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @param name The attribute name
+     * @param value The attribute value (may be {@code null})
+     * @throws IllegalArgumentException if txn or name is null, or value is not a supported type
+     * @throws IllegalStateException if the XML text has been closed
+     * @see #setAttribute(String, Object)
+     */
+    public void setAttribute(YTransaction txn, String name, Object value) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        validateAttributeValue(value);
+        nativeSetAttributeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), name, value);
+    }
+
+    private static void validateAttributeValue(Object value) {
+        if (value == null
+                || value == JniUndefined.UNDEFINED
+                || value instanceof String
+                || value instanceof Long
+                || value instanceof Integer
+                || value instanceof Double
+                || value instanceof Float
+                || value instanceof Boolean) {
+            return;
+        }
+        throw new IllegalArgumentException(
+            "Unsupported attribute value type: " + value.getClass().getName()
+                + ". Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, or null.");
+    }
+
+    /**
+     * Removes a node-level attribute.
+     *
+     * @param name The attribute name to remove
+     * @throws IllegalArgumentException if name is null
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public void removeAttribute(String name) {
+        checkClosed();
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            removeAttribute(txn, name);
+            return;
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            removeAttribute(autoTxn, name);
+        }
+    }
+
+    /**
+     * Removes a node-level attribute within an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @param name The attribute name to remove
+     * @throws IllegalArgumentException if txn or name is null
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public void removeAttribute(YTransaction txn, String name) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        if (name == null) {
+            throw new IllegalArgumentException("Name cannot be null");
+        }
+        nativeRemoveAttributeWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr(), name);
+    }
+
+    /**
+     * Gets all node-level attribute names.
+     *
+     * @return An array of all attribute names
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public String[] getAttributeNames() {
+        checkClosed();
+        YTransaction txn = doc.getActiveTransaction();
+        if (txn != null) {
+            return getAttributeNames(txn);
+        }
+        try (YTransaction autoTxn = doc.beginTransaction()) {
+            return getAttributeNames(autoTxn);
+        }
+    }
+
+    /**
+     * Gets all node-level attribute names using an existing transaction.
+     *
+     * @param txn Transaction handle
+     * @return An array of all attribute names
+     * @throws IllegalArgumentException if txn is null
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public String[] getAttributeNames(YTransaction txn) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        Object result = nativeGetAttributeNamesWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr());
+        if (result == null) {
+            return new String[0];
+        }
+        return (String[]) result;
+    }
+
+    /**
+     * Gets the parent of this XML text node.
+     * The parent can be either a YXmlElement or YXmlFragment.
+     *
+     * @return The parent node (YXmlElement or YXmlFragment), or null if this text node has no parent
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public Object getParent() {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return getParent(activeTxn);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return getParent(txn);
         }
     }
 
@@ -559,25 +954,11 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         if (txn == null) {
             throw new IllegalArgumentException("Transaction cannot be null");
         }
-        Object result = nativeGetParentWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
-        if (result == null) {
+        YNativeRef ref = nativeGetParentWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
+        if (ref == null) {
             return null;
         }
-
-        // Result is Object[2] where [0] = Integer type, [1] = Long pointer
-        Object[] array = (Object[]) result;
-        int type = ((Integer) array[0]).intValue();
-        long pointer = ((Long) array[1]).longValue();
-
-        if (type == 0) {
-            // Element
-            return new JniYXmlElement(doc, pointer);
-        } else if (type == 1) {
-            // Fragment
-            return new JniYXmlFragment(doc, pointer);
-        } else {
-            throw new RuntimeException("Unknown parent type: " + type);
-        }
+        return ref.toXmlNode(doc);
     }
 
     /**
@@ -677,6 +1058,65 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         return nativeGetFormattingChunksWithTxn(doc.getNativePtr(), nativePtr, ((JniYTransaction) txn).getNativePtr());
     }
 
+    /**
+     * Returns the formatted text as of a snapshot, attributing changes made since
+     * an earlier snapshot.
+     *
+     * <p>Content inserted between {@code previousSnapshot} and {@code snapshot} is
+     * reported as {@link JniSnapshotChunk.ChangeKind#ADDED}, and content deleted in
+     * that range as {@link JniSnapshotChunk.ChangeKind#REMOVED}. Removed content is
+     * only available if the document was created with
+     * {@link JniYDoc#withoutGarbageCollection()}.</p>
+     *
+     * <p>Example usage:</p>
+     * <pre>{@code
+     * byte[] before = doc.encodeSnapshot();
+     * text.insert(0, "new ");
+     * for (JniSnapshotChunk chunk : text.diffSnapshots(null, before)) {
+     *     System.out.println(chunk.getText() + " " + chunk.getChangeKind());
+     * }
+     * }</pre>
+     *
+     * @param snapshot the snapshot to render (from {@link JniYDoc#encodeSnapshot()}),
+     *                 or null for the current state
+     * @param previousSnapshot the snapshot to compare against, or null for no attribution
+     * @return the text chunks with formatting attributes and change attribution
+     * @throws IllegalArgumentException if a snapshot cannot be decoded
+     * @throws IllegalStateException if the XML text has been closed
+     */
+    public List<JniSnapshotChunk> diffSnapshots(byte[] snapshot, byte[] previousSnapshot) {
+        checkClosed();
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        if (activeTxn != null) {
+            return diffSnapshots(activeTxn, snapshot, previousSnapshot);
+        }
+        try (JniYTransaction txn = doc.beginTransaction()) {
+            return diffSnapshots(txn, snapshot, previousSnapshot);
+        }
+    }
+
+    /**
+     * Returns the formatted text as of a snapshot, attributing changes made since
+     * an earlier snapshot, using an existing transaction.
+     *
+     * @param txn The transaction to use for this operation
+     * @param snapshot the snapshot to render, or null for the current state
+     * @param previousSnapshot the snapshot to compare against, or null for no attribution
+     * @return the text chunks with formatting attributes and change attribution
+     * @throws IllegalArgumentException if txn is null or a snapshot cannot be decoded
+     * @throws IllegalStateException if the XML text has been closed
+     * @see #diffSnapshots(byte[], byte[])
+     */
+    public List<JniSnapshotChunk> diffSnapshots(YTransaction txn, byte[] snapshot,
+                                                byte[] previousSnapshot) {
+        checkClosed();
+        if (txn == null) {
+            throw new IllegalArgumentException("Transaction cannot be null");
+        }
+        return nativeDiffSnapshotsWithTxn(doc.getNativePtr(), nativePtr,
+            ((JniYTransaction) txn).getNativePtr(), snapshot, previousSnapshot);
+    }
+
     /**
      * Checks if this YXmlText has been closed.
      *
@@ -692,6 +1132,10 @@ public class JniYXmlText implements YXmlText, JniYObservable {
      * <p>The observer will be called whenever this XML text is modified.
      * Multiple observers can be registered on the same XML text.</p>
      *
+     * <p>DELETE and RETAIN changes carry their UTF-16 length as well as their
+     * length in bytes. To measure them, the native side keeps one copy of this
+     * text for all of its observers, which is freed with the last of them.</p>
+     *
      * <p>Example:</p>
      * <pre>{@code
      * try (YSubscription sub = xmlText.observe(event -> {
@@ -714,9 +1158,11 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         if (observer == null) {
             throw new IllegalArgumentException("Observer cannot be null");
         }
-        long id = nextSubscriptionId.incrementAndGet();
+        long id = doc.allocateSubscriptionId();
         observers.put(id, observer);
-        nativeObserve(doc.getNativePtr(), nativePtr, id, this);
+        JniYTransaction activeTxn = doc.getActiveTransaction();
+        long txnPtr = activeTxn != null ? activeTxn.getNativePtr() : 0;
+        nativeObserve(doc.getNativePtr(), nativePtr, txnPtr, id, this);
         return new JniYSubscription(id, observer, this);
     }
 
@@ -773,7 +1219,8 @@ public class JniYXmlText implements YXmlText, JniYObservable {
                         }
                     }
                     observers.clear();
-                    if (nativePtr != 0) {
+                    // The document frees released handles itself
+                    if (nativePtr != 0 && !isReleased()) {
                         nativeDestroy(nativePtr);
                         nativePtr = 0;
                     }
@@ -792,6 +1239,18 @@ public class JniYXmlText implements YXmlText, JniYObservable {
         if (closed) {
             throw new IllegalStateException("YXmlText has been closed");
         }
+        if (isReleased()) {
+            throw new IllegalStateException("YXmlText was released by its YDoc");
+        }
+    }
+
+    /**
+     * Returns whether the document released the native handle of this root.
+     *
+     * @return true after {@link JniYDoc#releaseAllRoots()} or closing the document
+     */
+    private boolean isReleased() {
+        return rootGeneration >= 0 && doc.getRootGeneration() != rootGeneration;
     }
 
     /**
@@ -806,8 +1265,16 @@ public class JniYXmlText implements YXmlText, JniYObservable {
     // Native methods
     private static native long nativeGetXmlText(long docPtr, String name);
     private static native void nativeDestroy(long ptr);
-    private static native int nativeLengthWithTxn(long docPtr, long xmlTextPtr, long txnPtr);
+    private static native long nativeLengthWithTxn(long docPtr, long xmlTextPtr, long txnPtr);
+    private static native Object[] nativeGetPathWithTxn(long docPtr, long xmlTextPtr, long txnPtr);
+    private static native long nativeLength(long docPtr, long xmlTextPtr);
     private static native String nativeToStringWithTxn(long docPtr, long xmlTextPtr, long txnPtr);
+
+    private static native String nativeGetStringChunkWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
+        int offset, int maxBytes);
+
+    private static native void nativeReadChunksWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
+        int maxBytes, Consumer<String> consumer);
     private static native void nativeInsertWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
                                                      int index, String chunk);
     private static native void nativePushWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
@@ -820,12 +1287,25 @@ public class JniYXmlText implements YXmlText, JniYObservable {
     private static native void nativeFormatWithTxn(
             long docPtr, long xmlTextPtr, long txnPtr, int index, int length,
             Map<String, Object> attributes);
-    private static native Object nativeGetParentWithTxn(long docPtr, long xmlTextPtr, long txnPtr);
+    private static native void nativeFormatRangesWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
+        int[] ranges, Object[] attributes);
+    private static native Object nativeGetAttributeWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
+                                                           String name);
+    private static native void nativeSetAttributeWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
+                                                         String name, Object value);
+    private static native void nativeRemoveAttributeWithTxn(long docPtr, long xmlTextPtr, long txnPtr,
+                                                            String name);
+    private static native Object nativeGetAttributeNamesWithTxn(long docPtr, long xmlTextPtr,
+                                                                long txnPtr);
+    private static native YNativeRef nativeGetParentWithTxn(long docPtr, long xmlTextPtr, long txnPtr);
     private static native int nativeGetIndexInParentWithTxn(long docPtr, long xmlTextPtr,
                                                              long txnPtr);
-    private static native void nativeObserve(long docPtr, long xmlTextPtr, long subscriptionId,
-                                              YXmlText yxmlTextObj);
+    private static native void nativeObserve(long docPtr, long xmlTextPtr, long txnPtr,
+        long subscriptionId, YXmlText yxmlTextObj);
     private static native void nativeUnobserve(long docPtr, long xmlTextPtr, long subscriptionId);
     private static native List<FormattingChunk> nativeGetFormattingChunksWithTxn(
             long docPtr, long xmlTextPtr, long txnPtr);
+
+    private static native List<JniSnapshotChunk> nativeDiffSnapshotsWithTxn(
+            long docPtr, long xmlTextPtr, long txnPtr, byte[] snapshot, byte[] previousSnapshot);
 }
@@ -0,0 +1,32 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Narrowing of the 64-bit sizes reported by native code for the {@code int}
+ * methods of the shared types.
+ *
+ * <p>Native code counts lengths in unsigned 32-bit integers, so a very large
+ * text or array can be longer than {@link Integer#MAX_VALUE}. The {@code int}
+ * methods then throw instead of returning a truncated value, and the
+ * {@code long} methods report the size.</p>
+ */
+final class Sizes {
+
+    private Sizes() {
+    }
+
+    /**
+     * Converts a size to an {@code int}.
+     *
+     * @param size the size reported by native code
+     * @param longMethod the name of the method returning the size as a {@code long}
+     * @return size
+     * @throws ArithmeticException if size does not fit in an {@code int}
+     */
+    static int toInt(long size, String longMethod) {
+        if (size > Integer.MAX_VALUE) {
+            throw new ArithmeticException(
+                "Size " + size + " does not fit in an int; use " + longMethod + "()");
+        }
+        return (int) size;
+    }
+}
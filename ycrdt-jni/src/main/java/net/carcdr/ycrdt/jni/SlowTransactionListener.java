@@ -0,0 +1,22 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Callback invoked when a transaction takes longer than a configured threshold.
+ *
+ * <p>The listener runs on the thread that committed the transaction, after the
+ * commit has finished. Exceptions thrown by the listener are passed to the
+ * document's observer error handler.</p>
+ *
+ * @see JniYDoc#setSlowTransactionListener(long, SlowTransactionListener)
+ */
+@FunctionalInterface
+public interface SlowTransactionListener {
+
+    /**
+     * Called after a slow transaction has been committed.
+     *
+     * @param doc the document the transaction belonged to
+     * @param stats the measurements of the transaction
+     */
+    void onSlowTransaction(JniYDoc doc, JniTransactionStats stats);
+}
@@ -0,0 +1,26 @@
+package net.carcdr.ycrdt.jni;
+
+/**
+ * Callback invoked when updates staged by {@link JniYDoc#queueUpdate(byte[])} are
+ * applied.
+ *
+ * <p>The listener runs on the thread that queued the update that made the staged
+ * updates ready. When the update was queued without an explicit transaction, the
+ * listener runs after that transaction has been committed; otherwise it runs
+ * before the caller's transaction is committed. Exceptions thrown by the listener
+ * are passed to the document's observer error handler.</p>
+ *
+ * @see JniYDoc#setUpdateFlushListener(UpdateFlushListener)
+ */
+@FunctionalInterface
+public interface UpdateFlushListener {
+
+    /**
+     * Called after staged updates have been applied.
+     *
+     * @param doc the document the updates were applied to
+     * @param flushed the number of staged updates that were applied
+     * @param remaining the number of updates that are still staged
+     */
+    void onFlush(JniYDoc doc, int flushed, int remaining);
+}
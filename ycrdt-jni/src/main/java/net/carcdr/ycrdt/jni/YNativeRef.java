@@ -0,0 +1,94 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YXmlNode;
+
+/**
+ * A type-tagged native handle returned by the native layer for XML node lookups.
+ *
+ * <p>Instances are created from native code (via a cached constructor ID) for
+ * methods such as {@code getChild} and {@code getParent}, where the returned
+ * node can be one of several XML types. The handle is an owned native pointer
+ * that must be wrapped (see {@link #toXmlNode(JniYDoc)}) so it is eventually
+ * freed.</p>
+ */
+final class YNativeRef {
+
+    /** Type tag for an XML element handle. */
+    static final int TYPE_ELEMENT = 0;
+    /** Type tag for an XML text handle. */
+    static final int TYPE_TEXT = 1;
+    /** Type tag for an XML fragment handle. */
+    static final int TYPE_FRAGMENT = 2;
+
+    private final int type;
+    private final long handle;
+
+    /**
+     * Called from native code.
+     *
+     * @param type The type tag
+     * @param handle The native pointer
+     */
+    YNativeRef(int type, long handle) {
+        this.type = type;
+        this.handle = handle;
+    }
+
+    /**
+     * Returns the type tag.
+     *
+     * @return One of {@link #TYPE_ELEMENT}, {@link #TYPE_TEXT}, {@link #TYPE_FRAGMENT}
+     */
+    int getType() {
+        return type;
+    }
+
+    /**
+     * Returns the native pointer.
+     *
+     * @return The native pointer
+     */
+    long getHandle() {
+        return handle;
+    }
+
+    /**
+     * Maps a type tag to a node type.
+     *
+     * @param type A type tag, or -1 for no node
+     * @return The node type, or null for no node
+     */
+    static YXmlNode.NodeType nodeType(int type) {
+        switch (type) {
+            case TYPE_ELEMENT:
+                return YXmlNode.NodeType.ELEMENT;
+            case TYPE_TEXT:
+                return YXmlNode.NodeType.TEXT;
+            case TYPE_FRAGMENT:
+                return YXmlNode.NodeType.FRAGMENT;
+            default:
+                return null;
+        }
+    }
+
+    /**
+     * Wraps the handle in the matching Java type, transferring ownership of the
+     * native pointer to the returned object.
+     *
+     * @param doc The document the node belongs to
+     * @return A JniYXmlElement, JniYXmlText, or JniYXmlFragment
+     * @throws IllegalStateException if the type tag is unknown
+     */
+    Object toXmlNode(JniYDoc doc) {
+        switch (type) {
+            case TYPE_ELEMENT:
+                return new JniYXmlElement(doc, handle);
+            case TYPE_TEXT:
+                return new JniYXmlText(doc, handle);
+            case TYPE_FRAGMENT:
+                return new JniYXmlFragment(doc, handle);
+            default:
+                throw new IllegalStateException("Unknown native ref type: " + type);
+        }
+    }
+}
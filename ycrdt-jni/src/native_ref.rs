@@ -0,0 +1,116 @@
+//! Type-tagged native handles returned to Java.
+//!
+//! XML lookups such as `getChild` and `getParent` can return several node
+//! types. Rather than a positional `Object[]` of boxed values, they return a
+//! `YNativeRef` object built from a cached class and constructor ID.
+//!
+//! All XML natives follow the same result contract. A node that is absent, such
+//! as a child past the end or the parent of a root, is returned as null (or -1
+//! for indexes). A negative index throws `IndexOutOfBoundsException`, and a node
+//! whose type cannot appear where it was found, such as a fragment among the
+//! children of an element, throws `IllegalStateException`.
+
+use crate::{find_callback_class, to_java_ptr, JniResult};
+use jni::objects::{GlobalRef, JClass, JMethodID, JObject, JValue};
+use jni::sys::{jint, jlong};
+use jni::JNIEnv;
+use std::sync::OnceLock;
+use yrs::{XmlElementRef, XmlFragmentRef, XmlOut, XmlTextRef};
+
+/// Throws `IndexOutOfBoundsException` for a negative child index
+pub fn throw_negative_index(env: &mut JNIEnv, index: jint) {
+    let _ = env.throw_new(
+        "java/lang/IndexOutOfBoundsException",
+        format!("Index cannot be negative: {index}"),
+    );
+}
+
+/// Throws `IllegalStateException` for a node whose type cannot appear where it
+/// was found
+pub fn throw_unexpected_node(env: &mut JNIEnv, message: &str) {
+    let _ = env.throw_new("java/lang/IllegalStateException", message);
+}
+
+/// Type tag for an XmlElement reference
+pub const NATIVE_REF_ELEMENT: jint = 0;
+/// Type tag for an XmlText reference
+pub const NATIVE_REF_TEXT: jint = 1;
+/// Type tag for an XmlFragment reference
+pub const NATIVE_REF_FRAGMENT: jint = 2;
+
+/// The `NATIVE_REF_*` type tag of an XML node
+pub fn native_ref_type(node: &XmlOut) -> jint {
+    match node {
+        XmlOut::Element(_) => NATIVE_REF_ELEMENT,
+        XmlOut::Text(_) => NATIVE_REF_TEXT,
+        XmlOut::Fragment(_) => NATIVE_REF_FRAGMENT,
+    }
+}
+
+const NATIVE_REF_CLASS: &str = "net/carcdr/ycrdt/jni/YNativeRef";
+
+/// Cached class and constructor for `YNativeRef`, resolved in `JNI_OnLoad`
+/// (or on first use if the library was loaded without it)
+struct NativeRefClass {
+    class: GlobalRef,
+    ctor: JMethodID,
+}
+
+static NATIVE_REF: OnceLock<NativeRefClass> = OnceLock::new();
+
+fn native_ref_class(env: &mut JNIEnv) -> JniResult<&'static NativeRefClass> {
+    if let Some(cached) = NATIVE_REF.get() {
+        return Ok(cached);
+    }
+    let class = find_callback_class(env, NATIVE_REF_CLASS)?;
+    let ctor = env.get_method_id(&class, "<init>", "(IJ)V")?;
+    let class = env.new_global_ref(class)?;
+    Ok(NATIVE_REF.get_or_init(|| NativeRefClass { class, ctor }))
+}
+
+/// Resolves the `YNativeRef` class and constructor ahead of first use
+pub(crate) fn init_native_ref_cache(env: &mut JNIEnv) -> JniResult<()> {
+    native_ref_class(env).map(|_| ())
+}
+
+/// Creates a `YNativeRef` Java object holding a type tag and native handle
+///
+/// # Parameters
+/// - `type_tag`: One of the `NATIVE_REF_*` constants
+/// - `ptr`: The native pointer to wrap
+pub fn new_native_ref<'a>(
+    env: &mut JNIEnv<'a>,
+    type_tag: jint,
+    ptr: jlong,
+) -> JniResult<JObject<'a>> {
+    let cached = native_ref_class(env)?;
+    let class: &JClass = cached.class.as_obj().into();
+    // SAFETY: the constructor ID was resolved against this class with the
+    // signature (IJ)V, and the arguments match that signature.
+    let obj = unsafe {
+        env.new_object_unchecked(
+            class,
+            cached.ctor,
+            &[JValue::Int(type_tag).as_jni(), JValue::Long(ptr).as_jni()],
+        )?
+    };
+    Ok(obj)
+}
+
+/// Boxes an XML node and wraps the resulting pointer in a `YNativeRef`
+///
+/// The boxed pointer is freed again if the Java object cannot be created.
+pub fn xml_out_to_native_ref<'a>(env: &mut JNIEnv<'a>, node: XmlOut) -> JniResult<JObject<'a>> {
+    let (type_tag, ptr) = match node {
+        XmlOut::Element(elem) => (NATIVE_REF_ELEMENT, to_java_ptr(elem)),
+        XmlOut::Text(text) => (NATIVE_REF_TEXT, to_java_ptr(text)),
+        XmlOut::Fragment(frag) => (NATIVE_REF_FRAGMENT, to_java_ptr(frag)),
+    };
+    new_native_ref(env, type_tag, ptr).inspect_err(|_| unsafe {
+        match type_tag {
+            NATIVE_REF_ELEMENT => crate::free_java_ptr::<XmlElementRef>(ptr),
+            NATIVE_REF_TEXT => crate::free_java_ptr::<XmlTextRef>(ptr),
+            _ => crate::free_java_ptr::<XmlFragmentRef>(ptr),
+        }
+    })
+}
@@ -0,0 +1,197 @@
+//! Inspection of updates that are waiting for missing dependencies.
+//!
+//! When an update refers to content the document has not seen, yrs keeps the
+//! parts it cannot integrate in a pending update (and deletions of unknown
+//! content in a pending delete set) until the missing updates arrive.
+//!
+//! Comparing the document before and after applying an update tells a sync
+//! layer what the update did: it was integrated, part of it is waiting for
+//! missing updates, or everything in it was already known.
+
+use crate::count_structs_v1;
+use jni::sys::jint;
+use yrs::updates::encoder::Encode;
+use yrs::{ReadTxn, StateVector, TransactionMut};
+
+/// Update outcomes, matching the declaration order of `JniApplyResult.Status`
+pub const APPLY_APPLIED: jint = 0;
+pub const APPLY_PENDING: jint = 1;
+pub const APPLY_DUPLICATE: jint = 2;
+
+/// Summary of the content a document is holding back
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PendingState {
+    /// Number of structs in the pending update
+    pub structs: u64,
+    /// Size of the pending update in v1 encoding
+    pub bytes: u64,
+    /// Clock units deleted by the pending delete set
+    pub deleted: u64,
+    /// For each client, the lowest clock that must arrive before the pending
+    /// content can be integrated
+    pub missing: StateVector,
+}
+
+/// Returns whether the document is holding back updates or deletions
+pub fn has_pending<T: ReadTxn>(txn: &T) -> bool {
+    let store = txn.store();
+    store.pending_update().is_some() || store.pending_ds().is_some_and(|ds| !ds.is_empty())
+}
+
+/// Describes the pending content of a document, or `None` if there is none
+pub fn pending_state<T: ReadTxn>(txn: &T) -> Option<PendingState> {
+    if !has_pending(txn) {
+        return None;
+    }
+    let store = txn.store();
+    let mut state = PendingState::default();
+    if let Some(pending) = store.pending_update() {
+        let encoded = pending.update.encode_v1();
+        state.structs = count_structs_v1(&encoded).unwrap_or(0) as u64;
+        state.bytes = encoded.len() as u64;
+        state.missing = pending.missing.clone();
+    }
+    if let Some(ds) = store.pending_ds() {
+        let local = txn.state_vector();
+        for (client, ranges) in ds.iter() {
+            let mut end = 0;
+            for range in ranges.iter() {
+                state.deleted += u64::from(range.end - range.start);
+                end = end.max(range.end);
+            }
+            // Deletions wait for every clock up to the deleted range
+            let have = local.get(client);
+            if end > have {
+                state.missing.set_min(*client, have);
+            }
+        }
+    }
+    Some(state)
+}
+
+/// State of a transaction before an update, to classify what the update did
+pub struct ApplyProbe {
+    state: StateVector,
+    deleted: u64,
+    pending: Option<PendingState>,
+}
+
+impl ApplyProbe {
+    /// Records the state of `txn` before an update is applied to it
+    pub fn new(txn: &TransactionMut) -> Self {
+        ApplyProbe {
+            state: txn.state_vector(),
+            deleted: deleted_len(txn),
+            pending: pending_state(txn),
+        }
+    }
+
+    /// Classifies the update applied since the probe was taken, as one of the
+    /// `APPLY_*` constants, with the clocks still missing if it is pending
+    ///
+    /// An update is pending when it added content the document holds back; it
+    /// may also have integrated the parts that did not depend on missing
+    /// updates. It is a duplicate when it changed neither the document nor
+    /// the held back content.
+    pub fn outcome(self, txn: &TransactionMut) -> (jint, StateVector) {
+        let pending = pending_state(txn);
+        if pending != self.pending {
+            if let Some(pending) = pending {
+                return (APPLY_PENDING, pending.missing);
+            }
+        }
+        let changed = pending != self.pending
+            || txn.state_vector() != self.state
+            || deleted_len(txn) != self.deleted;
+        if changed {
+            (APPLY_APPLIED, StateVector::default())
+        } else {
+            (APPLY_DUPLICATE, StateVector::default())
+        }
+    }
+}
+
+/// Clock units deleted so far by a transaction
+fn deleted_len(txn: &TransactionMut) -> u64 {
+    txn.delete_set()
+        .iter()
+        .flat_map(|(_, ranges)| ranges.iter())
+        .map(|range| u64::from(range.end - range.start))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::decoder::Decode;
+    use yrs::{Doc, GetString, Text, Transact, Update};
+
+    #[test]
+    fn test_apply_probe_classifies_updates() {
+        let source = Doc::with_client_id(7);
+        let text = source.get_or_insert_text("t");
+        text.insert(&mut source.transact_mut(), 0, "a");
+        let first = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let sv = source.transact().state_vector();
+        text.insert(&mut source.transact_mut(), 1, "bc");
+        let second = source.transact().encode_diff_v1(&sv);
+        let sv = source.transact().state_vector();
+        text.remove_range(&mut source.transact_mut(), 0, 1);
+        let deletion = source.transact().encode_diff_v1(&sv);
+
+        let target = Doc::new();
+        let apply = |update: &[u8]| {
+            let mut txn = target.transact_mut();
+            let probe = ApplyProbe::new(&txn);
+            txn.apply_update(Update::decode_v1(update).unwrap())
+                .unwrap();
+            probe.outcome(&txn)
+        };
+        let (status, missing) = apply(&second);
+        assert_eq!(status, APPLY_PENDING);
+        assert_eq!(missing.get(&7), 0);
+        assert_eq!(apply(&second).0, APPLY_DUPLICATE);
+        assert_eq!(apply(&first).0, APPLY_APPLIED);
+        assert_eq!(apply(&first).0, APPLY_DUPLICATE);
+        assert_eq!(apply(&deletion).0, APPLY_APPLIED);
+        assert_eq!(apply(&deletion).0, APPLY_DUPLICATE);
+    }
+
+    #[test]
+    fn test_pending_state_reports_missing_clock() {
+        let source = Doc::with_client_id(7);
+        let text = source.get_or_insert_text("t");
+        text.insert(&mut source.transact_mut(), 0, "a");
+        let first = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let sv = source.transact().state_vector();
+        text.insert(&mut source.transact_mut(), 1, "bc");
+        let second = source.transact().encode_diff_v1(&sv);
+
+        let target = Doc::new();
+        {
+            let mut txn = target.transact_mut();
+            txn.apply_update(Update::decode_v1(&second).unwrap())
+                .unwrap();
+        }
+        let txn = target.transact();
+        assert!(has_pending(&txn));
+        let state = pending_state(&txn).unwrap();
+        assert_eq!(state.structs, 1);
+        assert!(state.bytes > 0);
+        assert_eq!(state.missing.get(&7), 0);
+        drop(txn);
+
+        target
+            .transact_mut()
+            .apply_update(Update::decode_v1(&first).unwrap())
+            .unwrap();
+        let txn = target.transact();
+        assert!(!has_pending(&txn));
+        assert_eq!(pending_state(&txn), None);
+        assert_eq!(txn.get_text("t").unwrap().get_string(&txn), "abc");
+    }
+}
@@ -0,0 +1,552 @@
+//! Content built in Java before it is added to a document.
+//!
+//! A `JniPrelim` is a tree of maps, arrays, texts and XML nodes that belongs to
+//! no document. Committing it first converts the whole tree to [`PrelimNode`]s,
+//! so unsupported values and disallowed formatting are reported before the
+//! document is touched, and then adds it to a root type in one transaction.
+
+use crate::{
+    check_writable_or_throw, find_callback_class, get_mut_or_throw, get_ref_or_throw,
+    get_string_or_throw, java_map_to_attrs, jobject_to_any, throw_jni_error, trace_native,
+    AnyConversionError, AttributeSchema, DocPtr, JniEnvExt, JniError, JniResult, StringPolicy,
+    TxnPtr,
+};
+use jni::objects::{JClass, JList, JMap, JObject, JString};
+use jni::sys::jlong;
+use jni::JNIEnv;
+use yrs::block::Prelim;
+use yrs::types::Attrs;
+use yrs::{
+    Any, Array, ArrayPrelim, ArrayRef, Map, MapPrelim, MapRef, Text, TextPrelim, TransactionMut,
+    WriteTxn, Xml, XmlElementPrelim, XmlFragment, XmlFragmentPrelim, XmlTextPrelim,
+};
+
+/// Node kinds, matching the `JniPrelim` kind constants
+pub const PRELIM_MAP: i32 = 0;
+pub const PRELIM_ARRAY: i32 = 1;
+pub const PRELIM_TEXT: i32 = 2;
+pub const PRELIM_XML_FRAGMENT: i32 = 3;
+pub const PRELIM_XML_ELEMENT: i32 = 4;
+pub const PRELIM_XML_TEXT: i32 = 5;
+
+/// Nesting limit for converted trees, which also stops a `JniPrelim` that
+/// contains itself
+const MAX_DEPTH: usize = 512;
+
+/// A chunk of text and the formatting attributes it is inserted with
+pub type TextRun = (String, Option<Attrs>);
+
+/// Content that is not yet part of a document
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrelimNode {
+    Value(Any),
+    Map(Vec<(String, PrelimNode)>),
+    Array(Vec<PrelimNode>),
+    Text(Vec<TextRun>),
+    XmlFragment(Vec<PrelimNode>),
+    XmlElement {
+        tag: String,
+        attributes: Vec<(String, Any)>,
+        children: Vec<PrelimNode>,
+    },
+    XmlText {
+        attributes: Vec<(String, Any)>,
+        runs: Vec<TextRun>,
+    },
+}
+
+impl PrelimNode {
+    fn is_xml_child(&self) -> bool {
+        matches!(
+            self,
+            PrelimNode::XmlElement { .. } | PrelimNode::XmlText { .. }
+        )
+    }
+}
+
+/// Adds `node` to the root type named `root`
+///
+/// Map entries are set on the root map, array items and XML children are
+/// appended, and text runs are appended to the root text. Other node kinds
+/// cannot be roots.
+pub fn commit_prelim(txn: &mut TransactionMut, root: &str, node: PrelimNode) -> Result<(), String> {
+    match node {
+        PrelimNode::Map(entries) => {
+            let map = txn.get_or_insert_map(root);
+            for (key, value) in entries {
+                insert_node(txn, Slot::Map(&map, &key), value);
+            }
+        }
+        PrelimNode::Array(items) => {
+            let array = txn.get_or_insert_array(root);
+            let len = array.len(txn);
+            fill_array(txn, &array, len, items);
+        }
+        PrelimNode::Text(runs) => {
+            let text = txn.get_or_insert_text(root);
+            fill_text(txn, &text, runs);
+        }
+        PrelimNode::XmlFragment(children) => {
+            let fragment = txn.get_or_insert_xml_fragment(root);
+            fill_xml(txn, &fragment, children);
+        }
+        _ => {
+            return Err(
+                "Only map, array, text and XML fragment prelims can be committed to a root"
+                    .to_string(),
+            )
+        }
+    }
+    Ok(())
+}
+
+/// Where a nested node is inserted
+enum Slot<'a> {
+    Map(&'a MapRef, &'a str),
+    Array(&'a ArrayRef, u32),
+}
+
+impl Slot<'_> {
+    fn insert<P: Prelim>(&self, txn: &mut TransactionMut, value: P) -> P::Return {
+        match self {
+            Slot::Map(map, key) => map.insert(txn, *key, value),
+            Slot::Array(array, index) => array.insert(txn, *index, value),
+        }
+    }
+}
+
+fn insert_node(txn: &mut TransactionMut, slot: Slot, node: PrelimNode) {
+    match node {
+        PrelimNode::Value(value) => {
+            slot.insert(txn, value);
+        }
+        PrelimNode::Map(entries) => {
+            let map = slot.insert(txn, MapPrelim::default());
+            for (key, value) in entries {
+                insert_node(txn, Slot::Map(&map, &key), value);
+            }
+        }
+        PrelimNode::Array(items) => {
+            let array = slot.insert(txn, ArrayPrelim::default());
+            fill_array(txn, &array, 0, items);
+        }
+        PrelimNode::Text(runs) => {
+            let text = slot.insert(txn, TextPrelim::new(""));
+            fill_text(txn, &text, runs);
+        }
+        PrelimNode::XmlFragment(children) => {
+            let fragment = slot.insert(txn, XmlFragmentPrelim::default());
+            fill_xml(txn, &fragment, children);
+        }
+        PrelimNode::XmlElement {
+            tag,
+            attributes,
+            children,
+        } => {
+            let element = slot.insert(txn, XmlElementPrelim::empty(tag));
+            for (name, value) in attributes {
+                element.insert_attribute(txn, name, value);
+            }
+            fill_xml(txn, &element, children);
+        }
+        PrelimNode::XmlText { attributes, runs } => {
+            let text = slot.insert(txn, XmlTextPrelim::new(""));
+            for (name, value) in attributes {
+                text.insert_attribute(txn, name, value);
+            }
+            fill_text(txn, &text, runs);
+        }
+    }
+}
+
+/// Inserts `items` starting at `index`, writing runs of plain values with a
+/// single `insert_range`
+fn fill_array(txn: &mut TransactionMut, array: &ArrayRef, mut index: u32, items: Vec<PrelimNode>) {
+    let mut values = Vec::new();
+    for item in items {
+        match item {
+            PrelimNode::Value(value) => values.push(value),
+            nested => {
+                if !values.is_empty() {
+                    let count = values.len() as u32;
+                    array.insert_range(txn, index, std::mem::take(&mut values));
+                    index += count;
+                }
+                insert_node(txn, Slot::Array(array, index), nested);
+                index += 1;
+            }
+        }
+    }
+    if !values.is_empty() {
+        array.insert_range(txn, index, values);
+    }
+}
+
+fn fill_text<T: Text>(txn: &mut TransactionMut, text: &T, runs: Vec<TextRun>) {
+    for (chunk, attrs) in runs {
+        let index = text.len(txn);
+        // An empty set of attributes ends formatting inherited from the previous run
+        text.insert_with_attributes(txn, index, &chunk, attrs.unwrap_or_default());
+    }
+}
+
+fn fill_xml<F: XmlFragment>(txn: &mut TransactionMut, parent: &F, children: Vec<PrelimNode>) {
+    for child in children {
+        match child {
+            PrelimNode::XmlElement {
+                tag,
+                attributes,
+                children,
+            } => {
+                let element = parent.push_back(txn, XmlElementPrelim::empty(tag));
+                for (name, value) in attributes {
+                    element.insert_attribute(txn, name, value);
+                }
+                fill_xml(txn, &element, children);
+            }
+            PrelimNode::XmlText { attributes, runs } => {
+                let text = parent.push_back(txn, XmlTextPrelim::new(""));
+                for (name, value) in attributes {
+                    text.insert_attribute(txn, name, value);
+                }
+                fill_text(txn, &text, runs);
+            }
+            // Rejected when the tree is read from Java
+            _ => unreachable!("non-XML child of an XML node"),
+        }
+    }
+}
+
+/// Reads a `JniPrelim` tree
+///
+/// Values are converted like `jobject_to_any` and formatting attributes are
+/// checked against `schema`. Errors in the tree are reported as
+/// `JniError::InvalidString`, which is thrown as `IllegalArgumentException`.
+pub fn prelim_from_java(
+    env: &mut JNIEnv,
+    prelim: &JObject,
+    policy: StringPolicy,
+    schema: &AttributeSchema,
+) -> JniResult<PrelimNode> {
+    let mut reader = PrelimReader { policy, schema };
+    reader.node(env, prelim, 0)
+}
+
+struct PrelimReader<'a> {
+    policy: StringPolicy,
+    schema: &'a AttributeSchema,
+}
+
+impl PrelimReader<'_> {
+    fn node(&mut self, env: &mut JNIEnv, prelim: &JObject, depth: usize) -> JniResult<PrelimNode> {
+        if depth > MAX_DEPTH {
+            return Err(invalid(format!(
+                "Prelim is nested more than {} levels deep or contains itself",
+                MAX_DEPTH
+            )));
+        }
+        env.with_local_frame(16, |env| {
+            let kind = env.get_field(prelim, "kind", "I")?.i()?;
+            match kind {
+                PRELIM_MAP => {
+                    let mut entries = Vec::new();
+                    self.entries(env, prelim, |reader, env, key, value| {
+                        entries.push((key, reader.child(env, value, depth)?));
+                        Ok(())
+                    })?;
+                    Ok(PrelimNode::Map(entries))
+                }
+                PRELIM_ARRAY => Ok(PrelimNode::Array(
+                    self.children(env, prelim, "items", depth)?,
+                )),
+                PRELIM_TEXT => Ok(PrelimNode::Text(self.runs(env, prelim)?)),
+                PRELIM_XML_FRAGMENT => Ok(PrelimNode::XmlFragment(
+                    self.xml_children(env, prelim, depth)?,
+                )),
+                PRELIM_XML_ELEMENT => {
+                    let tag = env.get_field(prelim, "tag", "Ljava/lang/String;")?.l()?;
+                    let tag = env.get_rust_string(&JString::from(tag), self.policy)?;
+                    Ok(PrelimNode::XmlElement {
+                        tag,
+                        attributes: self.attributes(env, prelim)?,
+                        children: self.xml_children(env, prelim, depth)?,
+                    })
+                }
+                PRELIM_XML_TEXT => Ok(PrelimNode::XmlText {
+                    attributes: self.attributes(env, prelim)?,
+                    runs: self.runs(env, prelim)?,
+                }),
+                other => Err(invalid(format!("Unknown prelim kind {}", other))),
+            }
+        })
+    }
+
+    /// Reads a `List` field, calling `read` with each element
+    fn list<F>(
+        &mut self,
+        env: &mut JNIEnv,
+        prelim: &JObject,
+        field: &str,
+        mut read: F,
+    ) -> JniResult<()>
+    where
+        F: FnMut(&mut Self, &mut JNIEnv, JObject) -> JniResult<()>,
+    {
+        let list = env.get_field(prelim, field, "Ljava/util/List;")?.l()?;
+        let list = JList::from_env(env, &list)?;
+        let size = list.size(env)?;
+        for i in 0..size {
+            let element = list.get(env, i)?.unwrap_or_default();
+            read(self, env, element)?;
+        }
+        Ok(())
+    }
+
+    fn strings(
+        &mut self,
+        env: &mut JNIEnv,
+        prelim: &JObject,
+        field: &str,
+    ) -> JniResult<Vec<String>> {
+        let mut strings = Vec::new();
+        self.list(env, prelim, field, |reader, env, element| {
+            let string = JString::from(element);
+            strings.push(env.get_rust_string(&string, reader.policy)?);
+            env.delete_local_ref(string)?;
+            Ok(())
+        })?;
+        Ok(strings)
+    }
+
+    fn children(
+        &mut self,
+        env: &mut JNIEnv,
+        prelim: &JObject,
+        field: &str,
+        depth: usize,
+    ) -> JniResult<Vec<PrelimNode>> {
+        let mut children = Vec::new();
+        self.list(env, prelim, field, |reader, env, element| {
+            children.push(reader.child(env, element, depth)?);
+            Ok(())
+        })?;
+        Ok(children)
+    }
+
+    /// Reads a plain value or nested prelim, releasing its local reference
+    fn child(&mut self, env: &mut JNIEnv, element: JObject, depth: usize) -> JniResult<PrelimNode> {
+        let prelim_class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniPrelim")?;
+        let child = if env.is_instance_of(&element, &prelim_class)? {
+            self.node(env, &element, depth + 1)?
+        } else {
+            PrelimNode::Value(self.value(env, &element)?)
+        };
+        env.delete_local_ref(element)?;
+        Ok(child)
+    }
+
+    /// Reads the `entries` map field, calling `read` with each key and value
+    fn entries<F>(&mut self, env: &mut JNIEnv, prelim: &JObject, mut read: F) -> JniResult<()>
+    where
+        F: FnMut(&mut Self, &mut JNIEnv, String, JObject) -> JniResult<()>,
+    {
+        let map = env.get_field(prelim, "entries", "Ljava/util/Map;")?.l()?;
+        let map = JMap::from_env(env, &map)?;
+        let mut iter = map.iter(env)?;
+        while let Some((key, value)) = iter.next(env)? {
+            let key = JString::from(key);
+            let name = env.get_rust_string(&key, self.policy)?;
+            env.delete_local_ref(key)?;
+            read(self, env, name, value)?;
+        }
+        Ok(())
+    }
+
+    fn xml_children(
+        &mut self,
+        env: &mut JNIEnv,
+        prelim: &JObject,
+        depth: usize,
+    ) -> JniResult<Vec<PrelimNode>> {
+        let children = self.children(env, prelim, "items", depth)?;
+        if !children.iter().all(PrelimNode::is_xml_child) {
+            return Err(invalid(
+                "XML nodes can only contain XML elements and XML text".to_string(),
+            ));
+        }
+        Ok(children)
+    }
+
+    fn attributes(&mut self, env: &mut JNIEnv, prelim: &JObject) -> JniResult<Vec<(String, Any)>> {
+        let mut attributes = Vec::new();
+        self.entries(env, prelim, |reader, env, name, value| {
+            attributes.push((name, reader.value(env, &value)?));
+            env.delete_local_ref(value)?;
+            Ok(())
+        })?;
+        Ok(attributes)
+    }
+
+    fn runs(&mut self, env: &mut JNIEnv, prelim: &JObject) -> JniResult<Vec<TextRun>> {
+        let chunks = self.strings(env, prelim, "chunks")?;
+        let mut formats = Vec::new();
+        self.list(env, prelim, "formats", |reader, env, element| {
+            if element.is_null() {
+                formats.push(None);
+                return Ok(());
+            }
+            let attrs = java_map_to_attrs(env, &element, reader.policy)?;
+            if let Some(key) = reader.schema.first_rejected(&attrs) {
+                return Err(invalid(format!(
+                    "Formatting attribute '{}' is not allowed",
+                    key
+                )));
+            }
+            formats.push(Some(attrs));
+            env.delete_local_ref(element)?;
+            Ok(())
+        })?;
+        Ok(chunks.into_iter().zip(formats).collect())
+    }
+
+    fn value(&mut self, env: &mut JNIEnv, value: &JObject) -> JniResult<Any> {
+        jobject_to_any(env, value, self.policy).map_err(|e| match e {
+            AnyConversionError::Unsupported(class_name) => invalid(format!(
+                "Unsupported prelim value type: {}. Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, null or JniPrelim.",
+                class_name
+            )),
+            AnyConversionError::InvalidString(msg) => JniError::InvalidString(msg),
+            AnyConversionError::Jni(e) => JniError::Jni(e),
+        })
+    }
+}
+
+fn invalid(message: String) -> JniError {
+    JniError::InvalidString(message)
+}
+
+/// Adds a `JniPrelim` tree to a root type using an existing transaction
+///
+/// The tree is read in full before the document is changed, so an invalid
+/// tree throws `IllegalArgumentException` and leaves the document as it was.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `root`: The name of the root type
+/// - `prelim`: The `JniPrelim` to add
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCommitPrelimWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    root: JString,
+    prelim: JObject,
+) {
+    trace_native!("JniYDoc.nativeCommitPrelimWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let root = get_string_or_throw!(&mut env, wrapper, root);
+
+    let node = match prelim_from_java(
+        &mut env,
+        &prelim,
+        wrapper.string_policy(),
+        wrapper.attribute_schema(),
+    ) {
+        Ok(node) => node,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return;
+        }
+    };
+    if let Err(e) = commit_prelim(txn, &root, node) {
+        let _ = env.throw_new("java/lang/IllegalArgumentException", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::types::ToJson;
+    use yrs::{Doc, GetString, Transact};
+
+    #[test]
+    fn test_commit_builds_nested_content() {
+        let doc = Doc::new();
+        let node = PrelimNode::Map(vec![
+            ("title".to_string(), PrelimNode::Value(Any::from("Notes"))),
+            (
+                "items".to_string(),
+                PrelimNode::Array(vec![
+                    PrelimNode::Value(Any::BigInt(1)),
+                    PrelimNode::Value(Any::BigInt(2)),
+                    PrelimNode::Map(vec![("k".to_string(), PrelimNode::Value(Any::Bool(true)))]),
+                    PrelimNode::Value(Any::BigInt(3)),
+                ]),
+            ),
+            (
+                "body".to_string(),
+                PrelimNode::Text(vec![
+                    (
+                        "bold".to_string(),
+                        Some(Attrs::from([("b".into(), Any::Bool(true))])),
+                    ),
+                    (" plain".to_string(), None),
+                ]),
+            ),
+        ]);
+        let mut txn = doc.transact_mut();
+        commit_prelim(&mut txn, "root", node).unwrap();
+
+        let root = txn.get_or_insert_map("root");
+        let json = root.to_json(&txn);
+        let Any::Map(fields) = json else {
+            panic!("root is not a map")
+        };
+        assert_eq!(fields["title"], Any::from("Notes"));
+        let Any::Array(items) = &fields["items"] else {
+            panic!("items is not an array")
+        };
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[3], Any::BigInt(3));
+        let Some(yrs::Out::YText(body)) = root.get(&txn, "body") else {
+            panic!("body is not a text")
+        };
+        assert_eq!(body.get_string(&txn), "bold plain");
+        assert_eq!(
+            body.diff(&txn, yrs::types::text::YChange::identity).len(),
+            2
+        );
+
+        assert!(commit_prelim(&mut txn, "x", PrelimNode::Value(Any::Null)).is_err());
+    }
+
+    #[test]
+    fn test_commit_appends_xml_children() {
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let paragraph = PrelimNode::XmlElement {
+            tag: "p".to_string(),
+            attributes: vec![("class".to_string(), Any::from("lead"))],
+            children: vec![PrelimNode::XmlText {
+                attributes: Vec::new(),
+                runs: vec![("hi".to_string(), None)],
+            }],
+        };
+        commit_prelim(
+            &mut txn,
+            "xml",
+            PrelimNode::XmlFragment(vec![paragraph.clone()]),
+        )
+        .unwrap();
+        commit_prelim(&mut txn, "xml", PrelimNode::XmlFragment(vec![paragraph])).unwrap();
+        let fragment = txn.get_or_insert_xml_fragment("xml");
+        assert_eq!(
+            fragment.get_string(&txn),
+            "<p class=\"lead\">hi</p><p class=\"lead\">hi</p>"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! The integer tags shared with Java, in one table.
+//!
+//! Natives and Java exchange kinds of operations, nodes and values as plain
+//! ints: Java constants, or ordinals of Java enums. Each tag is defined once
+//! on each side, so `JniProtocol` reads this table at runtime and the Java
+//! tests compare it against the Java declarations, failing the build when a
+//! tag is added or reordered on one side only.
+//!
+//! Entries are named `Class.CONSTANT` after the Java side, with nested
+//! classes written `Outer$Inner` and enum constants standing for their
+//! ordinal.
+
+use crate::{
+    strings_to_java, throw_exception, trace_native, JniResultExt, StringPolicy, ACCESS_READ_ONLY,
+    ACCESS_READ_ONLY_ALLOW_UPDATES, ACCESS_READ_WRITE, APPLY_APPLIED, APPLY_DUPLICATE,
+    APPLY_PENDING, BATCH_OP_ARRAY_DELETE, BATCH_OP_ARRAY_INSERT, BATCH_OP_MAP_REMOVE,
+    BATCH_OP_MAP_SET, BATCH_OP_TEXT_DELETE, BATCH_OP_TEXT_INSERT, PRELIM_ARRAY, PRELIM_MAP,
+    PRELIM_TEXT, PRELIM_XML_ELEMENT, PRELIM_XML_FRAGMENT, PRELIM_XML_TEXT, SCHEMA_RULE_KEY,
+    SCHEMA_RULE_NO_OTHER_ROOTS, SCHEMA_RULE_OPTIONAL_ROOT, SCHEMA_RULE_ROOT, SYNC_KIND_AUTH,
+    SYNC_KIND_AWARENESS, SYNC_KIND_AWARENESS_QUERY, SYNC_KIND_CUSTOM, SYNC_KIND_STEP_1,
+    SYNC_KIND_STEP_2, SYNC_KIND_UPDATE, TREE_KIND_ARRAY, TREE_KIND_DOC, TREE_KIND_LINK,
+    TREE_KIND_MAP, TREE_KIND_TEXT, TREE_KIND_UNKNOWN, TREE_KIND_XML_ELEMENT,
+    TREE_KIND_XML_FRAGMENT, TREE_KIND_XML_TEXT, UNDO_SCOPE_ARRAY, UNDO_SCOPE_MAP, UNDO_SCOPE_TEXT,
+    UNDO_SCOPE_XML_ELEMENT, UNDO_SCOPE_XML_FRAGMENT, UNDO_SCOPE_XML_TEXT, VALUE_TYPE_ABSENT,
+    VALUE_TYPE_ARRAY, VALUE_TYPE_BOOL, VALUE_TYPE_BYTES, VALUE_TYPE_DOC, VALUE_TYPE_LINK,
+    VALUE_TYPE_MAP, VALUE_TYPE_NULL, VALUE_TYPE_NUMBER, VALUE_TYPE_STRING, VALUE_TYPE_TEXT,
+    VALUE_TYPE_UNDEFINED, VALUE_TYPE_XML,
+};
+#[cfg(feature = "xml")]
+use crate::{
+    CHANGE_KIND_ADDED, CHANGE_KIND_REMOVED, CHANGE_KIND_UNCHANGED, NATIVE_REF_ELEMENT,
+    NATIVE_REF_FRAGMENT, NATIVE_REF_TEXT, XML_NODE_ELEMENT, XML_NODE_FRAGMENT, XML_NODE_TEXT,
+};
+use jni::objects::{JClass, JObjectArray};
+use jni::sys::{jint, jintArray, jobjectArray};
+use jni::JNIEnv;
+
+/// Tags shared with Java, by the name of their Java declaration
+pub const PROTOCOL_CONSTANTS: &[(&str, jint)] = &[
+    ("JniApplyResult$Status.APPLIED", APPLY_APPLIED),
+    ("JniApplyResult$Status.PENDING", APPLY_PENDING),
+    ("JniApplyResult$Status.DUPLICATE", APPLY_DUPLICATE),
+    ("JniBatchOp.TEXT_INSERT", BATCH_OP_TEXT_INSERT),
+    ("JniBatchOp.TEXT_DELETE", BATCH_OP_TEXT_DELETE),
+    ("JniBatchOp.MAP_SET", BATCH_OP_MAP_SET),
+    ("JniBatchOp.MAP_REMOVE", BATCH_OP_MAP_REMOVE),
+    ("JniBatchOp.ARRAY_INSERT", BATCH_OP_ARRAY_INSERT),
+    ("JniBatchOp.ARRAY_DELETE", BATCH_OP_ARRAY_DELETE),
+    ("JniPrelim.MAP", PRELIM_MAP),
+    ("JniPrelim.ARRAY", PRELIM_ARRAY),
+    ("JniPrelim.TEXT", PRELIM_TEXT),
+    ("JniPrelim.XML_FRAGMENT", PRELIM_XML_FRAGMENT),
+    ("JniPrelim.XML_ELEMENT", PRELIM_XML_ELEMENT),
+    ("JniPrelim.XML_TEXT", PRELIM_XML_TEXT),
+    ("JniSchemaRule.ROOT", SCHEMA_RULE_ROOT),
+    ("JniSchemaRule.OPTIONAL_ROOT", SCHEMA_RULE_OPTIONAL_ROOT),
+    ("JniSchemaRule.KEY", SCHEMA_RULE_KEY),
+    ("JniSchemaRule.NO_OTHER_ROOTS", SCHEMA_RULE_NO_OTHER_ROOTS),
+    #[cfg(feature = "xml")]
+    ("JniSnapshotChunk.UNCHANGED", CHANGE_KIND_UNCHANGED),
+    #[cfg(feature = "xml")]
+    ("JniSnapshotChunk$ChangeKind.ADDED", CHANGE_KIND_ADDED),
+    #[cfg(feature = "xml")]
+    ("JniSnapshotChunk$ChangeKind.REMOVED", CHANGE_KIND_REMOVED),
+    ("JniSyncMessage$Kind.SYNC_STEP_1", SYNC_KIND_STEP_1),
+    ("JniSyncMessage$Kind.SYNC_STEP_2", SYNC_KIND_STEP_2),
+    ("JniSyncMessage$Kind.UPDATE", SYNC_KIND_UPDATE),
+    ("JniSyncMessage$Kind.AWARENESS", SYNC_KIND_AWARENESS),
+    (
+        "JniSyncMessage$Kind.AWARENESS_QUERY",
+        SYNC_KIND_AWARENESS_QUERY,
+    ),
+    ("JniSyncMessage$Kind.AUTH", SYNC_KIND_AUTH),
+    ("JniSyncMessage$Kind.CUSTOM", SYNC_KIND_CUSTOM),
+    ("JniTreeNode$Kind.MAP", TREE_KIND_MAP),
+    ("JniTreeNode$Kind.ARRAY", TREE_KIND_ARRAY),
+    ("JniTreeNode$Kind.TEXT", TREE_KIND_TEXT),
+    ("JniTreeNode$Kind.XML_ELEMENT", TREE_KIND_XML_ELEMENT),
+    ("JniTreeNode$Kind.XML_FRAGMENT", TREE_KIND_XML_FRAGMENT),
+    ("JniTreeNode$Kind.XML_TEXT", TREE_KIND_XML_TEXT),
+    ("JniTreeNode$Kind.DOC", TREE_KIND_DOC),
+    ("JniTreeNode$Kind.UNKNOWN", TREE_KIND_UNKNOWN),
+    ("JniTreeNode$Kind.LINK", TREE_KIND_LINK),
+    #[cfg(feature = "xml")]
+    ("JniXmlNode$Kind.FRAGMENT", XML_NODE_FRAGMENT),
+    #[cfg(feature = "xml")]
+    ("JniXmlNode$Kind.ELEMENT", XML_NODE_ELEMENT),
+    #[cfg(feature = "xml")]
+    ("JniXmlNode$Kind.TEXT", XML_NODE_TEXT),
+    ("JniYDoc$AccessMode.READ_WRITE", ACCESS_READ_WRITE as jint),
+    ("JniYDoc$AccessMode.READ_ONLY", ACCESS_READ_ONLY as jint),
+    (
+        "JniYDoc$AccessMode.READ_ONLY_ALLOW_UPDATES",
+        ACCESS_READ_ONLY_ALLOW_UPDATES as jint,
+    ),
+    ("JniYDoc$StringPolicy.LOSSY", StringPolicy::Lossy.ordinal()),
+    (
+        "JniYDoc$StringPolicy.STRICT",
+        StringPolicy::Strict.ordinal(),
+    ),
+    ("JniYMap$ValueType.ABSENT", VALUE_TYPE_ABSENT),
+    ("JniYMap$ValueType.NULL", VALUE_TYPE_NULL),
+    ("JniYMap$ValueType.STRING", VALUE_TYPE_STRING),
+    ("JniYMap$ValueType.NUMBER", VALUE_TYPE_NUMBER),
+    ("JniYMap$ValueType.BOOL", VALUE_TYPE_BOOL),
+    ("JniYMap$ValueType.BYTES", VALUE_TYPE_BYTES),
+    ("JniYMap$ValueType.MAP", VALUE_TYPE_MAP),
+    ("JniYMap$ValueType.ARRAY", VALUE_TYPE_ARRAY),
+    ("JniYMap$ValueType.TEXT", VALUE_TYPE_TEXT),
+    ("JniYMap$ValueType.DOC", VALUE_TYPE_DOC),
+    ("JniYMap$ValueType.XML", VALUE_TYPE_XML),
+    ("JniYMap$ValueType.UNDEFINED", VALUE_TYPE_UNDEFINED),
+    ("JniYMap$ValueType.LINK", VALUE_TYPE_LINK),
+    ("JniYUndoManager.SCOPE_TEXT", UNDO_SCOPE_TEXT),
+    ("JniYUndoManager.SCOPE_ARRAY", UNDO_SCOPE_ARRAY),
+    ("JniYUndoManager.SCOPE_MAP", UNDO_SCOPE_MAP),
+    (
+        "JniYUndoManager.SCOPE_XML_FRAGMENT",
+        UNDO_SCOPE_XML_FRAGMENT,
+    ),
+    ("JniYUndoManager.SCOPE_XML_ELEMENT", UNDO_SCOPE_XML_ELEMENT),
+    ("JniYUndoManager.SCOPE_XML_TEXT", UNDO_SCOPE_XML_TEXT),
+    #[cfg(feature = "xml")]
+    ("YNativeRef.TYPE_ELEMENT", NATIVE_REF_ELEMENT),
+    #[cfg(feature = "xml")]
+    ("YNativeRef.TYPE_TEXT", NATIVE_REF_TEXT),
+    #[cfg(feature = "xml")]
+    ("YNativeRef.TYPE_FRAGMENT", NATIVE_REF_FRAGMENT),
+];
+
+/// Returns the names of the shared tags
+///
+/// # Returns
+/// The names of `PROTOCOL_CONSTANTS`, in table order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniProtocol_nativeConstantNames(
+    mut env: JNIEnv,
+    _class: JClass,
+) -> jobjectArray {
+    trace_native!("JniProtocol.nativeConstantNames");
+    strings_to_java(&mut env, PROTOCOL_CONSTANTS.iter().map(|(name, _)| *name))
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Returns the values of the shared tags
+///
+/// # Returns
+/// The values of `PROTOCOL_CONSTANTS`, in table order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniProtocol_nativeConstantValues(
+    mut env: JNIEnv,
+    _class: JClass,
+) -> jintArray {
+    trace_native!("JniProtocol.nativeConstantValues");
+    let values: Vec<jint> = PROTOCOL_CONSTANTS.iter().map(|(_, value)| *value).collect();
+    let array = match env.new_int_array(values.len() as jint) {
+        Ok(array) => array,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create int array");
+            return std::ptr::null_mut();
+        }
+    };
+    if env.set_int_array_region(&array, 0, &values).is_err() {
+        throw_exception(&mut env, "Failed to fill constant array");
+        return std::ptr::null_mut();
+    }
+    array.into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_names_are_unique_and_values_distinct_per_class() {
+        let names: HashSet<&str> = PROTOCOL_CONSTANTS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names.len(), PROTOCOL_CONSTANTS.len());
+
+        let mut by_class: HashMap<&str, HashSet<jint>> = HashMap::new();
+        for (name, value) in PROTOCOL_CONSTANTS {
+            let (class, _) = name.rsplit_once('.').unwrap();
+            assert!(
+                by_class.entry(class).or_default().insert(*value),
+                "{name} reuses a tag of {class}"
+            );
+        }
+    }
+}
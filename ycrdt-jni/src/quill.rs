@@ -0,0 +1,142 @@
+//! Conversion between Quill Delta JSON and the content of a text.
+//!
+//! A document delta is a list of insert operations, each holding either a
+//! string or an embed object and optional formatting attributes:
+//!
+//! ```json
+//! {"ops":[{"insert":"Hello "},{"insert":"world","attributes":{"bold":true}},
+//!         {"insert":{"image":"a.png"}},{"insert":"\n"}]}
+//! ```
+//!
+//! Import accepts this object or the bare `ops` array and builds the whole
+//! content in one `apply_delta` call. Export produces the object form, with
+//! attribute and embed keys in sorted order so equal content gives equal JSON.
+
+use crate::write_json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use yrs::types::text::YChange;
+use yrs::types::{Attrs, Delta, ToJson};
+use yrs::{Any, ReadTxn, Text};
+
+/// Parses a Quill document delta into insert operations
+///
+/// Fails if the JSON is malformed, if an operation is not an insert, or if an
+/// insert is empty. Null attribute values are dropped, since there is no
+/// earlier formatting for them to remove.
+pub fn parse_quill_delta(json: &str) -> Result<Vec<Delta<Any>>, String> {
+    let parsed = Any::from_json(json).map_err(|e| format!("Invalid delta JSON: {}", e))?;
+    let ops = match parsed {
+        Any::Array(ops) => ops,
+        Any::Map(mut doc) => match Arc::make_mut(&mut doc).remove("ops") {
+            Some(Any::Array(ops)) => ops,
+            _ => return Err("Delta object must have an \"ops\" array".to_string()),
+        },
+        _ => return Err("Delta must be an object or an array of operations".to_string()),
+    };
+
+    let mut deltas = Vec::with_capacity(ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        let Any::Map(op) = op else {
+            return Err(format!("Operation {} is not an object", i));
+        };
+        let insert = match op.get("insert") {
+            Some(Any::String(s)) if s.is_empty() => {
+                return Err(format!("Operation {} inserts an empty string", i))
+            }
+            Some(insert @ (Any::String(_) | Any::Map(_))) => insert.clone(),
+            Some(_) => {
+                return Err(format!(
+                    "Operation {} must insert a string or an embed object",
+                    i
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "Operation {} is not an insert; document deltas only contain inserts",
+                    i
+                ))
+            }
+        };
+        let attrs = match op.get("attributes") {
+            None | Some(Any::Null) => None,
+            Some(Any::Map(attributes)) => {
+                let attrs: Attrs = attributes
+                    .iter()
+                    .filter(|(_, value)| !matches!(value, Any::Null))
+                    .map(|(key, value)| (key.as_str().into(), value.clone()))
+                    .collect();
+                (!attrs.is_empty()).then(|| Box::new(attrs))
+            }
+            Some(_) => return Err(format!("Attributes of operation {} must be an object", i)),
+        };
+        deltas.push(Delta::Inserted(insert, attrs));
+    }
+    Ok(deltas)
+}
+
+/// Returns the attributes used by a parsed delta, merged into one map for
+/// validation against an attribute allowlist
+pub fn delta_attributes(deltas: &[Delta<Any>]) -> Attrs {
+    let mut all = Attrs::new();
+    for delta in deltas {
+        if let Delta::Inserted(_, Some(attrs)) = delta {
+            all.extend(attrs.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+    all
+}
+
+/// Writes the content of `text` as a Quill document delta
+pub fn export_quill_delta<T: Text, R: ReadTxn>(text: &T, txn: &R) -> String {
+    let mut json = String::from("{\"ops\":[");
+    for (i, diff) in text.diff(txn, YChange::identity).iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str("{\"insert\":");
+        write_json(&mut json, &diff.insert.to_json(txn), true);
+        if let Some(attrs) = diff.attributes.as_deref().filter(|a| !a.is_empty()) {
+            json.push_str(",\"attributes\":{");
+            let sorted: BTreeMap<_, _> = attrs.iter().collect();
+            for (j, (key, value)) in sorted.into_iter().enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                write_json(&mut json, &Any::from(key.as_ref()), true);
+                json.push(':');
+                write_json(&mut json, value, true);
+            }
+            json.push('}');
+        }
+        json.push('}');
+    }
+    json.push_str("]}");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, GetString, Transact};
+
+    #[test]
+    fn test_delta_round_trip() {
+        let json = r#"{"ops":[{"insert":"Hello "},{"insert":"world","attributes":{"bold":true,"italic":true}},{"insert":{"image":"a.png"}},{"insert":"\n"}]}"#;
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("t");
+        let mut txn = doc.transact_mut();
+        text.apply_delta(&mut txn, parse_quill_delta(json).unwrap());
+        assert_eq!(text.get_string(&txn), "Hello world\n");
+        assert_eq!(export_quill_delta(&text, &txn), json);
+    }
+
+    #[test]
+    fn test_parse_rejects_non_insert_ops() {
+        assert!(parse_quill_delta(r#"[{"retain":3}]"#).is_err());
+        assert!(parse_quill_delta(r#"[{"insert":""}]"#).is_err());
+        assert!(parse_quill_delta(r#"{"ops":3}"#).is_err());
+        assert!(parse_quill_delta("not json").is_err());
+        assert_eq!(parse_quill_delta(r#"[{"insert":"a"}]"#).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,1516 @@
+//! Native method registration.
+//!
+//! `JNI_OnLoad` binds every native method with `RegisterNatives` instead of
+//! relying on JNI's name-mangled symbol lookup. This keeps the library working
+//! when the Java classes are shrunk by ProGuard/R8 (as long as the native
+//! methods themselves are kept), and a signature that does not match its Java
+//! declaration fails `System.loadLibrary` with `NoSuchMethodError` instead of
+//! surfacing as `UnsatisfiedLinkError` on first call.
+//!
+//! The `Java_*` exports are kept, so the tables below must list every native
+//! declared on the Java side with its exact JNI signature.
+
+#[cfg(feature = "xml")]
+use crate::native_ref::init_native_ref_cache;
+#[cfg(feature = "xml")]
+use crate::xml_snapshot::init_xml_snapshot_cache;
+use crate::*;
+use jni::sys::{jint, JNI_ERR};
+use jni::{JNIEnv, JavaVM, NativeMethod};
+use std::ffi::c_void;
+
+/// JNI version reported from `JNI_OnLoad`. ART only accepts versions up to
+/// 1.6, while desktop JVMs get 1.8.
+#[cfg(target_os = "android")]
+const JNI_VERSION: jint = jni::sys::JNI_VERSION_1_6;
+#[cfg(not(target_os = "android"))]
+const JNI_VERSION: jint = jni::sys::JNI_VERSION_1_8;
+
+/// Called by the JVM when the library is loaded
+///
+/// Registers all native methods and resolves cached classes. On failure the
+/// pending Java exception (e.g. `NoSuchMethodError` naming the offending
+/// method) is left in place and `JNI_ERR` is returned, which aborts the load.
+#[no_mangle]
+pub extern "system" fn JNI_OnLoad(vm: JavaVM, _reserved: *mut c_void) -> jint {
+    let mut env = match vm.get_env() {
+        Ok(env) => env,
+        Err(_) => return JNI_ERR,
+    };
+    match register_natives(&mut env).and_then(|_| init_class_caches(&mut env)) {
+        Ok(()) => JNI_VERSION,
+        Err(_) => JNI_ERR,
+    }
+}
+
+/// Resolves cached class and method IDs while the loading class loader is current
+fn init_class_caches(env: &mut JNIEnv) -> JniResult<()> {
+    init_callback_classes(env)?;
+    #[cfg(feature = "xml")]
+    init_native_ref_cache(env)?;
+    #[cfg(feature = "xml")]
+    init_xml_snapshot_cache(env)?;
+    Ok(())
+}
+
+/// Registers the native method tables for every class in `NATIVE_CLASSES`
+fn register_natives(env: &mut JNIEnv) -> JniResult<()> {
+    for (class_name, methods) in NATIVE_CLASSES {
+        let class = env.find_class(class_name)?;
+        env.register_native_methods(&class, &methods())?;
+        env.delete_local_ref(class)?;
+    }
+    Ok(())
+}
+
+fn native(name: &str, sig: &str, fn_ptr: *mut c_void) -> NativeMethod {
+    NativeMethod {
+        name: name.into(),
+        sig: sig.into(),
+        fn_ptr,
+    }
+}
+
+/// Builds the native method table for one class
+type MethodTable = fn() -> Vec<NativeMethod>;
+
+/// Classes whose native methods are registered on load, with their method tables
+const NATIVE_CLASSES: &[(&str, MethodTable)] = &[
+    ("net/carcdr/ycrdt/jni/JniYDoc", ydoc_methods),
+    ("net/carcdr/ycrdt/jni/JniYTransaction", ytransaction_methods),
+    ("net/carcdr/ycrdt/jni/JniYUndoManager", yundomanager_methods),
+    ("net/carcdr/ycrdt/jni/JniUpdateStream", updatestream_methods),
+    ("net/carcdr/ycrdt/jni/JniStickyIndex", stickyindex_methods),
+    ("net/carcdr/ycrdt/jni/JniCursor", cursor_methods),
+    ("net/carcdr/ycrdt/jni/JniYText", ytext_methods),
+    ("net/carcdr/ycrdt/jni/JniYAnnotations", yannotations_methods),
+    ("net/carcdr/ycrdt/jni/JniYArray", yarray_methods),
+    ("net/carcdr/ycrdt/jni/JniYMap", ymap_methods),
+    ("net/carcdr/ycrdt/jni/JniYJson", yjson_methods),
+    ("net/carcdr/ycrdt/jni/JniProtocol", protocol_methods),
+    ("net/carcdr/ycrdt/jni/JniDocStore", docstore_methods),
+    #[cfg(feature = "xml")]
+    ("net/carcdr/ycrdt/jni/JniYXmlText", yxmltext_methods),
+    #[cfg(feature = "xml")]
+    ("net/carcdr/ycrdt/jni/JniYXmlElement", yxmlelement_methods),
+    #[cfg(feature = "xml")]
+    ("net/carcdr/ycrdt/jni/JniYXmlFragment", yxmlfragment_methods),
+];
+
+fn ydoc_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeCreate",
+            "()J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreate as *mut c_void,
+        ),
+        native(
+            "nativeCreateWithClientId",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateWithClientId as *mut c_void,
+        ),
+        native(
+            "nativeCreateWithIds",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateWithIds as *mut c_void,
+        ),
+        native(
+            "nativeCreateWithoutGc",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateWithoutGc as *mut c_void,
+        ),
+        native(
+            "nativeCreateDeterministic",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateDeterministic as *mut c_void,
+        ),
+        native(
+            "nativeIsDeterministic",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsDeterministic as *mut c_void,
+        ),
+        native(
+            "nativeIsDispatching",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsDispatching as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeReset",
+            "(JJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeReset as *mut c_void,
+        ),
+        native(
+            "nativeReleaseAllRoots",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeReleaseAllRoots as *mut c_void,
+        ),
+        native(
+            "nativeGetOpenRootNames",
+            "(J)[Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetOpenRootNames as *mut c_void,
+        ),
+        native(
+            "nativeGetClientId",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetClientId as *mut c_void,
+        ),
+        native(
+            "nativeGetChangeCounter",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetChangeCounter as *mut c_void,
+        ),
+        native(
+            "nativeGetGuid",
+            "(J)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetGuid as *mut c_void,
+        ),
+        native(
+            "nativeGetParentGuid",
+            "(J)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetParentGuid as *mut c_void,
+        ),
+        native(
+            "nativeGetEmbeddedPath",
+            "(J)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetEmbeddedPath as *mut c_void,
+        ),
+        native(
+            "nativeSetSingleWriter",
+            "(JZ)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetSingleWriter as *mut c_void,
+        ),
+        native(
+            "nativeIsSingleWriter",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsSingleWriter as *mut c_void,
+        ),
+        native(
+            "nativeSetAccess",
+            "(JI)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetAccess as *mut c_void,
+        ),
+        native(
+            "nativeGetAccess",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetAccess as *mut c_void,
+        ),
+        native(
+            "nativeSetStringPolicy",
+            "(JI)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetStringPolicy as *mut c_void,
+        ),
+        native(
+            "nativeGetStringPolicy",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetStringPolicy as *mut c_void,
+        ),
+        native(
+            "nativeSetTransactionStats",
+            "(JZ)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetTransactionStats as *mut c_void,
+        ),
+        native(
+            "nativeIsTransactionStats",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsTransactionStats as *mut c_void,
+        ),
+        native(
+            "nativeSetDecodeFailureLogging",
+            "(JZ)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetDecodeFailureLogging as *mut c_void,
+        ),
+        native(
+            "nativeIsDecodeFailureLogging",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsDecodeFailureLogging as *mut c_void,
+        ),
+        native(
+            "nativeSetEventContentLimit",
+            "(JI)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetEventContentLimit as *mut c_void,
+        ),
+        native(
+            "nativeGetEventContentLimit",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetEventContentLimit as *mut c_void,
+        ),
+        native(
+            "nativeGetTransactionStats",
+            "(J)[J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetTransactionStats as *mut c_void,
+        ),
+        native(
+            "nativeResetTransactionStats",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeResetTransactionStats as *mut c_void,
+        ),
+        native(
+            "nativeSetAllowedAttributes",
+            "(J[Ljava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetAllowedAttributes as *mut c_void,
+        ),
+        native(
+            "nativeHasXmlSupport",
+            "()Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeHasXmlSupport as *mut c_void,
+        ),
+        native(
+            "nativeSetNativeTracing",
+            "(Z)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetNativeTracing as *mut c_void,
+        ),
+        native(
+            "nativeIsNativeTracing",
+            "()Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsNativeTracing as *mut c_void,
+        ),
+        native(
+            "nativeSetCallbackClassLoader",
+            "(Ljava/lang/ClassLoader;)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetCallbackClassLoader as *mut c_void,
+        ),
+        native(
+            "nativeGetVersionInfo",
+            "()Ljava/util/Map;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetVersionInfo as *mut c_void,
+        ),
+        native(
+            "nativeEncodeStateAsUpdateWithTxn",
+            "(JJ)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateAsUpdateWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeApplyUpdateWithTxn",
+            "(JJ[B)Lnet/carcdr/ycrdt/jni/JniApplyResult;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeApplySyncMessageWithTxn",
+            "(JJ[B)Lnet/carcdr/ycrdt/jni/JniSyncMessage;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplySyncMessageWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEncodeSyncStep1WithTxn",
+            "(JJ)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeSyncStep1WithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEncodeSyncUpdate",
+            "([B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeSyncUpdate as *mut c_void,
+        ),
+        native(
+            "nativeApplyUpdateFilteredWithTxn",
+            "(JJ[BLjava/util/function/Predicate;)Lnet/carcdr/ycrdt/jni/JniFilteredUpdate;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateFilteredWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEncodeStateVectorWithTxn",
+            "(JJ)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateVectorWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeHasMissingUpdatesWithTxn",
+            "(JJ)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeHasMissingUpdatesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetStateVectorEntriesWithTxn",
+            "(JJ)[J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetStateVectorEntriesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetRootActivityTracking",
+            "(JZ)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetRootActivityTracking as *mut c_void,
+        ),
+        native(
+            "nativeIsRootActivityTracking",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsRootActivityTracking as *mut c_void,
+        ),
+        native(
+            "nativeGetRootActivity",
+            "(J)[Lnet/carcdr/ycrdt/jni/JniRootActivity;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetRootActivity as *mut c_void,
+        ),
+        native(
+            "nativeGetPendingStatsWithTxn",
+            "(JJ)[J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetPendingStatsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetStoreStatsWithTxn",
+            "(JJ)[J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetStoreStatsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetMissingStateVectorWithTxn",
+            "(JJ)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetMissingStateVectorWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeQueueUpdateWithTxn",
+            "(JJ[B)[J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeQueueUpdateWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeValidateWithTxn",
+            "(JJ[I[Ljava/lang/String;[Ljava/lang/String;[I)[Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeValidateWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSubmitBatch",
+            "(J[I[Ljava/lang/String;[I[Ljava/lang/String;[Ljava/lang/Object;Ljava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSubmitBatch as *mut c_void,
+        ),
+        native(
+            "nativeGetQueuedUpdateCount",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetQueuedUpdateCount as *mut c_void,
+        ),
+        native(
+            "nativeClearQueuedUpdates",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeClearQueuedUpdates as *mut c_void,
+        ),
+        native(
+            "nativeEncodeSnapshotWithTxn",
+            "(JJ)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeSnapshotWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEncodeStateFromSnapshotWithTxn",
+            "(JJ[B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateFromSnapshotWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeExportTreeWithTxn",
+            "(JJI)Ljava/util/Map;",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeExportTreeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEncodeDiffWithTxn",
+            "(JJ[B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiffWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEstimateUpdateSizeWithTxn",
+            "(JJ[B)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEstimateUpdateSizeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeEncodeDiffForRootsWithTxn",
+            "(JJ[B[Ljava/lang/String;)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiffForRootsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSyncWith",
+            "(JJ[B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSyncWith as *mut c_void,
+        ),
+        native(
+            "nativeAbsorb",
+            "(JJ[B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeAbsorb as *mut c_void,
+        ),
+        native(
+            "nativeCommitPrelimWithTxn",
+            "(JJLjava/lang/String;Lnet/carcdr/ycrdt/jni/JniPrelim;)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCommitPrelimWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeMergeUpdates",
+            "([[B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeMergeUpdates as *mut c_void,
+        ),
+        native(
+            "nativeEncodeStateVectorFromUpdate",
+            "([B)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateVectorFromUpdate as *mut c_void,
+        ),
+        native(
+            "nativeBeginTransaction",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeBeginTransaction as *mut c_void,
+        ),
+        native(
+            "nativeBeginTransactionWithOrigin",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeBeginTransactionWithOrigin as *mut c_void,
+        ),
+        native(
+            "nativeObserveUpdateV1",
+            "(JJLnet/carcdr/ycrdt/jni/JniYDoc;)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeObserveUpdateV1 as *mut c_void,
+        ),
+        native(
+            "nativeUnobserveUpdateV1",
+            "(JJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeUnobserveUpdateV1 as *mut c_void,
+        ),
+        native(
+            "nativeObserveRoot",
+            "(JLjava/lang/String;IJLnet/carcdr/ycrdt/jni/JniYDoc;)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeObserveRoot as *mut c_void,
+        ),
+        native(
+            "nativeEncodeStateAsUpdateDeepWithTxn",
+            "(JJ)[B",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateAsUpdateDeepWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeApplyUpdateDeep",
+            "(J[B)V",
+            Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateDeep as *mut c_void,
+        ),
+    ]
+}
+
+fn ytransaction_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeCommit",
+            "(JJ)[J",
+            Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeCommit as *mut c_void,
+        ),
+        native(
+            "nativeRollback",
+            "(JJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeRollback as *mut c_void,
+        ),
+        native(
+            "nativeSetSilent",
+            "(JJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeSetSilent as *mut c_void,
+        ),
+        native(
+            "nativeSetLabel",
+            "(JJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeSetLabel as *mut c_void,
+        ),
+    ]
+}
+
+fn yundomanager_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeCreate",
+            "(JJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCreate as *mut c_void,
+        ),
+        native(
+            "nativeCreateWithClock",
+            "(JJLjava/lang/Object;)J",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCreateWithClock as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(JJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeExpandScope",
+            "(JIJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeExpandScope as *mut c_void,
+        ),
+        native(
+            "nativeSetOriginTracked",
+            "(JJLjava/lang/String;Z)V",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeSetOriginTracked as *mut c_void,
+        ),
+        native(
+            "nativeUndo",
+            "(JJ)Z",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeUndo as *mut c_void,
+        ),
+        native(
+            "nativeRedo",
+            "(JJ)Z",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeRedo as *mut c_void,
+        ),
+        native(
+            "nativeCanUndo",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCanUndo as *mut c_void,
+        ),
+        native(
+            "nativeCanRedo",
+            "(J)Z",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCanRedo as *mut c_void,
+        ),
+        native(
+            "nativeClear",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeClear as *mut c_void,
+        ),
+        native(
+            "nativeStopCapturing",
+            "(JJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeStopCapturing as *mut c_void,
+        ),
+    ]
+}
+
+fn updatestream_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeCreate",
+            "()J",
+            Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeCreate as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeWriteWithTxn",
+            "(JJJ[BII)V",
+            Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeWriteWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeFinishWithTxn",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeFinishWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetBufferedSize",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeGetBufferedSize as *mut c_void,
+        ),
+        native(
+            "nativeGetStructsApplied",
+            "(J)J",
+            Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeGetStructsApplied as *mut c_void,
+        ),
+    ]
+}
+
+fn stickyindex_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeToJson",
+            "([B)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniStickyIndex_nativeToJson as *mut c_void,
+        ),
+        native(
+            "nativeFromJson",
+            "(Ljava/lang/String;)[B",
+            Java_net_carcdr_ycrdt_jni_JniStickyIndex_nativeFromJson as *mut c_void,
+        ),
+    ]
+}
+
+fn cursor_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeToJson",
+            "([B[B)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniCursor_nativeToJson as *mut c_void,
+        ),
+        native(
+            "nativeFromJson",
+            "(Ljava/lang/String;)[[B",
+            Java_net_carcdr_ycrdt_jni_JniCursor_nativeFromJson as *mut c_void,
+        ),
+    ]
+}
+
+fn ytext_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetText",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeGetText as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeLengthWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeLengthWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetPathWithTxn",
+            "(JJJ)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeGetPathWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToStringWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeToStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeWriteStringWithTxn",
+            "(JJJLjava/nio/ByteBuffer;II)I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeWriteStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetStringChunkWithTxn",
+            "(JJJII)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeGetStringChunkWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeReadChunksWithTxn",
+            "(JJJILjava/util/function/Consumer;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeReadChunksWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeLineStartsWithTxn",
+            "(JJJ)[I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeLineStartsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeBlocksWithTxn",
+            "(JJJIII)Ljava/util/List;",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeBlocksWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeLineCountWithTxn",
+            "(JJJ)I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeLineCountWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeStatsWithTxn",
+            "(JJJ)[J",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeStatsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativePositionWithTxn",
+            "(JJJI)[I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativePositionWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeOffsetWithTxn",
+            "(JJJII)I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeOffsetWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeImportDeltaWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeImportDeltaWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeExportDeltaWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeExportDeltaWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertWithTxn",
+            "(JJJILjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertCharsWithTxn",
+            "(JJJI[CII)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertCharsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertDirectCharsWithTxn",
+            "(JJJILjava/nio/CharBuffer;II)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertDirectCharsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeStickyIndexWithTxn",
+            "(JJJII)[B",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeStickyIndexWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeResolveStickyIndexWithTxn",
+            "(JJJ[B)I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeResolveStickyIndexWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertAtStickyIndexWithTxn",
+            "(JJJ[BLjava/lang/String;)I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertAtStickyIndexWithTxn as *mut c_void,
+        ),
+        native(
+            "nativePushWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativePushWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeFormatRangesWithTxn",
+            "(JJJ[I[Ljava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeFormatRangesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeDeleteWithTxn",
+            "(JJJII)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeDeleteWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeDeleteGraphemesWithTxn",
+            "(JJJII)I",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeDeleteGraphemesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeReplaceRangeWithTxn",
+            "(JJJIILjava/lang/String;Ljava/util/Map;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeReplaceRangeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJJLnet/carcdr/ycrdt/YText;)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeObserve as *mut c_void,
+        ),
+        native(
+            "nativeUnobserve",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYText_nativeUnobserve as *mut c_void,
+        ),
+    ]
+}
+
+fn yannotations_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeAddWithTxn",
+            "(JJJJLjava/lang/String;II)V",
+            Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeAddWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetWithTxn",
+            "(JJJJLjava/lang/String;)Lnet/carcdr/ycrdt/jni/JniAnnotation;",
+            Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeGetWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeListWithTxn",
+            "(JJJJ)[Lnet/carcdr/ycrdt/jni/JniAnnotation;",
+            Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeListWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserveCollapsed",
+            "(JJJJJLnet/carcdr/ycrdt/jni/JniYAnnotations;)V",
+            Java_net_carcdr_ycrdt_jni_JniYAnnotations_nativeObserveCollapsed as *mut c_void,
+        ),
+    ]
+}
+
+fn yarray_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetArray",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetArray as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeLengthWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeLengthWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetPathWithTxn",
+            "(JJJ)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetPathWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetStringWithTxn",
+            "(JJJI)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetDoubleWithTxn",
+            "(JJJI)D",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetDoubleWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToDoubleArrayWithTxn",
+            "(JJJ)[D",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeToDoubleArrayWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToLongArrayWithTxn",
+            "(JJJ)[J",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeToLongArrayWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertStringWithTxn",
+            "(JJJILjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertDoubleWithTxn",
+            "(JJJID)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertDoubleWithTxn as *mut c_void,
+        ),
+        native(
+            "nativePushStringWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativePushStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativePushDoubleWithTxn",
+            "(JJJD)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativePushDoubleWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertAllWithTxn",
+            "(JJJI[Ljava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertAllWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertDoublesWithTxn",
+            "(JJJI[D)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertDoublesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertLongsWithTxn",
+            "(JJJI[J)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertLongsWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertBooleansWithTxn",
+            "(JJJI[Z)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertBooleansWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeReplaceWithTxn",
+            "(JJJILjava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeReplaceWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveWithTxn",
+            "(JJJII)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeRemoveWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToJsonWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeToJsonWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeWriteJsonWithTxn",
+            "(JJJLjava/nio/ByteBuffer;II)I",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeWriteJsonWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetJsonAtWithTxn",
+            "(JJJI)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetJsonAtWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertDocWithTxn",
+            "(JJJIJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertDocWithTxn as *mut c_void,
+        ),
+        native(
+            "nativePushDocWithTxn",
+            "(JJJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativePushDocWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetDocWithTxn",
+            "(JJJI)J",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetDocWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJLnet/carcdr/ycrdt/YArray;)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeObserve as *mut c_void,
+        ),
+        native(
+            "nativeUnobserve",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYArray_nativeUnobserve as *mut c_void,
+        ),
+    ]
+}
+
+fn ymap_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetMap",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetMap as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeSizeWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeSizeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetPathWithTxn",
+            "(JJJ)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetPathWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetStringWithTxn",
+            "(JJJLjava/lang/String;)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetDoubleWithTxn",
+            "(JJJLjava/lang/String;)D",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetDoubleWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetAllWithTxn",
+            "(JJJ[Ljava/lang/String;)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetAllWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetStringWithTxn",
+            "(JJJLjava/lang/String;Ljava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetDoubleWithTxn",
+            "(JJJLjava/lang/String;D)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetDoubleWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeRemoveWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeCompareAndSetWithTxn",
+            "(JJJLjava/lang/String;Ljava/lang/Object;Ljava/lang/Object;)Z",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeCompareAndSetWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeContainsKeyWithTxn",
+            "(JJJLjava/lang/String;)Z",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeContainsKeyWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetValueTypeWithTxn",
+            "(JJJLjava/lang/String;)I",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetValueTypeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeKeysWithTxn",
+            "(JJJ)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeKeysWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeKeysWithPrefixWithTxn",
+            "(JJJLjava/lang/String;II)[Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeKeysWithPrefixWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeClearWithTxn",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeClearWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToJsonWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeToJsonWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeWriteJsonWithTxn",
+            "(JJJLjava/nio/ByteBuffer;II)I",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeWriteJsonWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetDocWithTxn",
+            "(JJJLjava/lang/String;J)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetDocWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetDocWithTxn",
+            "(JJJLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetDocWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetLinkWithTxn",
+            "(JJJLjava/lang/String;JLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetLinkWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetLinkedWithTxn",
+            "(JJJLjava/lang/String;)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetLinkedWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJLnet/carcdr/ycrdt/YMap;Z)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeObserve as *mut c_void,
+        ),
+        native(
+            "nativeUnobserve",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYMap_nativeUnobserve as *mut c_void,
+        ),
+    ]
+}
+
+fn yjson_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetWithTxn",
+            "(JJJLjava/lang/String;)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYJson_nativeGetWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeAddWithTxn",
+            "(JJJLjava/lang/String;Ljava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYJson_nativeAddWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeReplaceWithTxn",
+            "(JJJLjava/lang/String;Ljava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYJson_nativeReplaceWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYJson_nativeRemoveWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJLnet/carcdr/ycrdt/jni/JniYJson;)V",
+            Java_net_carcdr_ycrdt_jni_JniYJson_nativeObserve as *mut c_void,
+        ),
+    ]
+}
+
+fn protocol_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeConstantNames",
+            "()[Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniProtocol_nativeConstantNames as *mut c_void,
+        ),
+        native(
+            "nativeConstantValues",
+            "()[I",
+            Java_net_carcdr_ycrdt_jni_JniProtocol_nativeConstantValues as *mut c_void,
+        ),
+    ]
+}
+
+fn docstore_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeCreate",
+            "(I)J",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeCreate as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeGetOrCreate",
+            "(JLjava/lang/String;)[J",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeGetOrCreate as *mut c_void,
+        ),
+        native(
+            "nativeGet",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeGet as *mut c_void,
+        ),
+        native(
+            "nativeRemove",
+            "(JLjava/lang/String;J)Z",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeRemove as *mut c_void,
+        ),
+        native(
+            "nativeNames",
+            "(J)[Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeNames as *mut c_void,
+        ),
+        native(
+            "nativeSize",
+            "(J)I",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeSize as *mut c_void,
+        ),
+        native(
+            "nativeDrain",
+            "(J)[J",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeDrain as *mut c_void,
+        ),
+        native(
+            "nativeCompactIdle",
+            "(JJLjava/lang/Object;)I",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeCompactIdle as *mut c_void,
+        ),
+        native(
+            "nativeStartCompaction",
+            "(JJJLjava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeStartCompaction as *mut c_void,
+        ),
+        native(
+            "nativeStopCompaction",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniDocStore_nativeStopCompaction as *mut c_void,
+        ),
+    ]
+}
+
+#[cfg(feature = "xml")]
+fn yxmltext_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetXmlText",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetXmlText as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeLengthWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeLengthWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetPathWithTxn",
+            "(JJJ)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetPathWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeLength",
+            "(JJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeLength as *mut c_void,
+        ),
+        native(
+            "nativeToStringWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeToStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetStringChunkWithTxn",
+            "(JJJII)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetStringChunkWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeReadChunksWithTxn",
+            "(JJJILjava/util/function/Consumer;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeReadChunksWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertWithTxn",
+            "(JJJILjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeInsertWithTxn as *mut c_void,
+        ),
+        native(
+            "nativePushWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativePushWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeDeleteWithTxn",
+            "(JJJII)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDeleteWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertWithAttributesWithTxn",
+            "(JJJILjava/lang/String;Ljava/util/Map;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeInsertWithAttributesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeFormatWithTxn",
+            "(JJJIILjava/util/Map;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeFormatWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeFormatRangesWithTxn",
+            "(JJJ[I[Ljava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeFormatRangesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetAttributeWithTxn",
+            "(JJJLjava/lang/String;)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetAttributeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetAttributeWithTxn",
+            "(JJJLjava/lang/String;Ljava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeSetAttributeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveAttributeWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeRemoveAttributeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetAttributeNamesWithTxn",
+            "(JJJ)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetAttributeNamesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetParentWithTxn",
+            "(JJJ)Lnet/carcdr/ycrdt/jni/YNativeRef;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetParentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetIndexInParentWithTxn",
+            "(JJJ)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetIndexInParentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJJLnet/carcdr/ycrdt/YXmlText;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeObserve as *mut c_void,
+        ),
+        native(
+            "nativeUnobserve",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeUnobserve as *mut c_void,
+        ),
+        native(
+            "nativeGetFormattingChunksWithTxn",
+            "(JJJ)Ljava/util/List;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetFormattingChunksWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeDiffSnapshotsWithTxn",
+            "(JJJ[B[B)Ljava/util/List;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDiffSnapshotsWithTxn as *mut c_void,
+        ),
+    ]
+}
+
+#[cfg(feature = "xml")]
+fn yxmlelement_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetXmlElement",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetXmlElement as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeGetTagWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetTagWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetPathWithTxn",
+            "(JJJ)[Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetPathWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetAttributeWithTxn",
+            "(JJJLjava/lang/String;)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetAttributeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetAttributeWithTxn",
+            "(JJJLjava/lang/String;Ljava/lang/Object;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeSetAttributeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveAttributeWithTxn",
+            "(JJJLjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveAttributeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSetAttributesWithTxn",
+            "(JJJLjava/util/Map;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeSetAttributesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveAttributesWithTxn",
+            "(JJJ[Ljava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveAttributesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetAttributeNamesWithTxn",
+            "(JJJ)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetAttributeNamesWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToStringWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeToStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeChildCountWithTxn",
+            "(JJJ)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeChildCountWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeChildCount",
+            "(JJ)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeChildCount as *mut c_void,
+        ),
+        native(
+            "nativeInsertElementWithTxn",
+            "(JJJILjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertElementWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertTextWithTxn",
+            "(JJJI)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertTextWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertFragmentWithTxn",
+            "(JJJI)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertFragmentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetChildWithTxn",
+            "(JJJI)Lnet/carcdr/ycrdt/jni/YNativeRef;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetChildWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeIndexOfWithTxn",
+            "(JJJJI)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeIndexOfWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetChildrenWithTxn",
+            "(JJJ)Ljava/lang/Object;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetChildrenWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveChildWithTxn",
+            "(JJJI)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveChildWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetParentWithTxn",
+            "(JJJ)Lnet/carcdr/ycrdt/jni/YNativeRef;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetParentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetOwningFragmentWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetOwningFragmentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetIndexInParentWithTxn",
+            "(JJJ)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetIndexInParentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJLnet/carcdr/ycrdt/YXmlElement;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeObserve as *mut c_void,
+        ),
+        native(
+            "nativeUnobserve",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeUnobserve as *mut c_void,
+        ),
+    ]
+}
+
+#[cfg(feature = "xml")]
+fn yxmlfragment_methods() -> Vec<NativeMethod> {
+    vec![
+        native(
+            "nativeGetFragment",
+            "(JLjava/lang/String;)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetFragment as *mut c_void,
+        ),
+        native(
+            "nativeDestroy",
+            "(J)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeDestroy as *mut c_void,
+        ),
+        native(
+            "nativeLengthWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLengthWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeLength",
+            "(JJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLength as *mut c_void,
+        ),
+        native(
+            "nativeInsertElementWithTxn",
+            "(JJJILjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertElementWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertTextWithTxn",
+            "(JJJILjava/lang/String;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertTextWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeInsertFragmentWithTxn",
+            "(JJJI)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertFragmentWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeRemoveWithTxn",
+            "(JJJII)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeRemoveWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetNodeTypeWithTxn",
+            "(JJJI)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetNodeTypeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetElementWithTxn",
+            "(JJJI)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetElementWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetWrappedElementWithTxn",
+            "(JJJ)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetWrappedElementWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetTextWithTxn",
+            "(JJJI)J",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetTextWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeGetChildWithTxn",
+            "(JJJI)Lnet/carcdr/ycrdt/jni/YNativeRef;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetChildWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeIndexOfWithTxn",
+            "(JJJJI)I",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeIndexOfWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeToXmlStringWithTxn",
+            "(JJJ)Ljava/lang/String;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeToXmlStringWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeSnapshotTreeWithTxn",
+            "(JJJ)Lnet/carcdr/ycrdt/jni/JniXmlNode;",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeSnapshotTreeWithTxn as *mut c_void,
+        ),
+        native(
+            "nativeObserve",
+            "(JJJLnet/carcdr/ycrdt/YXmlFragment;)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeObserve as *mut c_void,
+        ),
+        native(
+            "nativeUnobserve",
+            "(JJJ)V",
+            Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeUnobserve as *mut c_void,
+        ),
+    ]
+}
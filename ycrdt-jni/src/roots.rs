@@ -0,0 +1,263 @@
+//! Root-type handles registered with the document that returned them.
+//!
+//! Looking up a root type by name (`nativeGetText`, `nativeGetMap` and the
+//! like) returns a boxed branch reference. The handle is cached per root name
+//! and type, so looking the same root up again returns the same handle without
+//! another `get_or_insert_*` or allocation; the handle counts the Java objects
+//! holding it and is freed when the last of them is closed. Each handle is
+//! recorded with its document, so the open handles can be listed, released
+//! together when a pooled document is reused, and released when the document
+//! is destroyed. A handle destroyed on its own is removed from its document
+//! first.
+//!
+//! Releasing a handle goes through the handle registry, so a root that still
+//! has observers is freed once the last of them is removed.
+
+use crate::{release_handle, to_java_ptr, DocPtr, DocWrapper};
+use dashmap::DashMap;
+use jni::sys::jlong;
+use lazy_static::lazy_static;
+use std::any::type_name;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+lazy_static! {
+    /// Document of every registered root handle, keyed by the handle. An entry
+    /// is removed by whoever releases the handle, so it is released only once.
+    static ref ROOT_OWNERS: DashMap<jlong, jlong> = DashMap::new();
+}
+
+/// A root handle held by a document
+struct RootEntry {
+    /// Name of the root type
+    name: String,
+    /// Type of the boxed branch reference, as reported by `std::any::type_name`
+    type_name: &'static str,
+    /// Number of Java objects holding the handle
+    refs: usize,
+    /// Releases the boxed branch reference
+    release: unsafe fn(jlong),
+}
+
+#[derive(Default)]
+struct RootTable {
+    entries: HashMap<jlong, RootEntry>,
+    /// Handle of each cached root, keyed by type and name
+    by_name: HashMap<(&'static str, String), jlong>,
+}
+
+impl RootTable {
+    fn insert<T>(&mut self, doc_ptr: jlong, name: &str, root: T) -> jlong {
+        let ptr = to_java_ptr(root);
+        self.entries.insert(
+            ptr,
+            RootEntry {
+                name: name.to_string(),
+                type_name: type_name::<T>(),
+                refs: 1,
+                release: release_handle::<T>,
+            },
+        );
+        ROOT_OWNERS.insert(ptr, doc_ptr);
+        ptr
+    }
+
+    fn remove(&mut self, ptr: jlong) -> Option<RootEntry> {
+        let entry = self.entries.remove(&ptr)?;
+        let key = (entry.type_name, entry.name.clone());
+        if self.by_name.get(&key) == Some(&ptr) {
+            self.by_name.remove(&key);
+        }
+        Some(entry)
+    }
+}
+
+/// The root-type handles a document has handed to Java
+#[derive(Default)]
+pub struct RootHandles {
+    table: Mutex<RootTable>,
+}
+
+impl RootHandles {
+    fn table(&self) -> MutexGuard<'_, RootTable> {
+        self.table.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Names of the open root handles, sorted, with one entry per Java object
+    /// holding a handle
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .table()
+            .entries
+            .values()
+            .flat_map(|entry| std::iter::repeat_n(entry.name.clone(), entry.refs))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Number of open root handles
+    pub fn len(&self) -> usize {
+        self.table().entries.len()
+    }
+
+    /// Whether no root handles are open
+    pub fn is_empty(&self) -> bool {
+        self.table().entries.is_empty()
+    }
+
+    /// Returns the cached handle of a root, counting one more holder
+    fn acquire(&self, type_name: &'static str, name: &str) -> Option<jlong> {
+        let mut table = self.table();
+        let ptr = *table.by_name.get(&(type_name, name.to_string()))?;
+        if let Some(entry) = table.entries.get_mut(&ptr) {
+            entry.refs += 1;
+        }
+        Some(ptr)
+    }
+}
+
+/// Returns the handle of the root type `name` of the document at `doc_ptr`,
+/// calling `lookup` and registering a new handle if none is cached
+///
+/// `lookup` runs without the document's root table locked, since it may wait
+/// for a transaction held by a thread that is closing a root handle.
+pub fn root_to_java_ptr<T>(
+    doc_ptr: jlong,
+    wrapper: &DocWrapper,
+    name: &str,
+    lookup: impl FnOnce() -> T,
+) -> jlong {
+    let roots = wrapper.roots();
+    if let Some(ptr) = roots.acquire(type_name::<T>(), name) {
+        return ptr;
+    }
+    let root = lookup();
+    let mut table = roots.table();
+    let key = (type_name::<T>(), name.to_string());
+    if let Some(&ptr) = table.by_name.get(&key) {
+        // Another thread registered the root while it was looked up
+        if let Some(entry) = table.entries.get_mut(&ptr) {
+            entry.refs += 1;
+        }
+        return ptr;
+    }
+    let ptr = table.insert(doc_ptr, name, root);
+    table.by_name.insert(key, ptr);
+    ptr
+}
+
+/// Boxes a type found under the root `name` for Java and registers the handle
+/// with the document at `doc_ptr`, without caching it
+///
+/// For types that are looked up within a root, such as the element an XML
+/// root holds, which can be replaced between lookups.
+pub fn register_root_handle<T>(doc_ptr: jlong, wrapper: &DocWrapper, name: &str, root: T) -> jlong {
+    wrapper.roots().table().insert(doc_ptr, name, root)
+}
+
+/// Drops one holder of a handle that Java is about to destroy
+///
+/// Returns whether the handle should be freed now: it is not a registered
+/// root, or it was the last holder of a root, which is then removed from its
+/// document.
+pub fn forget_root(ptr: jlong) -> bool {
+    let Some(doc_ptr) = ROOT_OWNERS.get(&ptr).map(|owner| *owner) else {
+        return true;
+    };
+    let Some(wrapper) = (unsafe { DocPtr::from_raw(doc_ptr).as_ref() }) else {
+        ROOT_OWNERS.remove(&ptr);
+        return true;
+    };
+    let mut table = wrapper.roots().table();
+    match table.entries.get_mut(&ptr) {
+        Some(entry) if entry.refs > 1 => {
+            entry.refs -= 1;
+            false
+        }
+        Some(_) => {
+            table.remove(ptr);
+            ROOT_OWNERS.remove(&ptr);
+            true
+        }
+        // Released with the other roots of the document already
+        None => false,
+    }
+}
+
+/// Releases every root handle registered with `wrapper`
+///
+/// Returns the number of handles released.
+///
+/// # Safety
+/// No registered handle may be in use on another thread.
+pub unsafe fn release_all_roots(wrapper: &DocWrapper) -> usize {
+    let entries: Vec<(jlong, RootEntry)> = {
+        let mut table = wrapper.roots().table();
+        table.by_name.clear();
+        table.entries.drain().collect()
+    };
+    let mut released = 0;
+    for (ptr, entry) in entries {
+        if ROOT_OWNERS.remove(&ptr).is_some() {
+            (entry.release)(ptr);
+            released += 1;
+        }
+    }
+    released
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_live_handle;
+    use yrs::{MapRef, TextRef};
+
+    #[test]
+    fn test_roots_are_released_as_a_group() {
+        let doc_ptr = to_java_ptr(DocWrapper::new());
+        let wrapper = unsafe { DocPtr::from_raw(doc_ptr).as_ref() }.unwrap();
+        let doc = wrapper.doc.clone();
+        let text_ptr =
+            root_to_java_ptr(doc_ptr, wrapper, "text", || doc.get_or_insert_text("text"));
+        let map_ptr = root_to_java_ptr(doc_ptr, wrapper, "map", || doc.get_or_insert_map("map"));
+        let other_ptr = root_to_java_ptr(doc_ptr, wrapper, "text", || -> TextRef {
+            unreachable!("cached roots are not looked up again")
+        });
+        assert_eq!(other_ptr, text_ptr);
+        assert_eq!(wrapper.roots().names(), vec!["map", "text", "text"]);
+
+        assert!(!forget_root(other_ptr));
+        assert_eq!(wrapper.roots().names(), vec!["map", "text"]);
+        assert_eq!(wrapper.roots().len(), 2);
+
+        assert_eq!(unsafe { release_all_roots(wrapper) }, 2);
+        assert!(wrapper.roots().is_empty());
+        assert!(!is_live_handle(text_ptr));
+        assert!(!is_live_handle(map_ptr));
+        unsafe { crate::free_java_ptr::<DocWrapper>(doc_ptr) };
+    }
+
+    #[test]
+    fn test_last_holder_frees_root() {
+        let doc_ptr = to_java_ptr(DocWrapper::new());
+        let wrapper = unsafe { DocPtr::from_raw(doc_ptr).as_ref() }.unwrap();
+        let doc = wrapper.doc.clone();
+        let map_ptr = root_to_java_ptr(doc_ptr, wrapper, "root", || doc.get_or_insert_map("root"));
+        let text_ptr =
+            root_to_java_ptr(doc_ptr, wrapper, "root", || doc.get_or_insert_text("other"));
+        // Roots of different types are cached separately
+        assert_ne!(map_ptr, text_ptr);
+
+        assert!(forget_root(map_ptr));
+        unsafe { crate::free_java_ptr::<MapRef>(map_ptr) };
+        let again = root_to_java_ptr(doc_ptr, wrapper, "root", || doc.get_or_insert_map("root"));
+        assert_eq!(wrapper.roots().len(), 2);
+        assert!(forget_root(again));
+        unsafe { crate::free_java_ptr::<MapRef>(again) };
+        assert!(forget_root(text_ptr));
+        unsafe { crate::free_java_ptr::<TextRef>(text_ptr) };
+        assert!(wrapper.roots().is_empty());
+        unsafe { crate::free_java_ptr::<DocWrapper>(doc_ptr) };
+    }
+}
@@ -0,0 +1,297 @@
+//! Structural validation of a document against a list of rules.
+//!
+//! A server that applies updates from untrusted clients can check afterwards
+//! that the document still has the shape it expects: which root types exist
+//! and of which kind, and which keys a root map holds. Rules are checked
+//! natively so the document does not have to be exported to Java first.
+//!
+//! Roots created by a remote update have no kind until they are read as one,
+//! so such a root passes a kind check if its content fits the kind: map
+//! entries for a map, sequence content for an array, text or XML fragment.
+
+use crate::{
+    value_type_tag, TREE_KIND_ARRAY, TREE_KIND_MAP, TREE_KIND_TEXT, TREE_KIND_XML_FRAGMENT,
+};
+use jni::sys::jint;
+use std::collections::HashSet;
+use yrs::{Map, MapRef, Out, ReadTxn};
+
+/// Rule kinds, matching the constants of `JniSchemaRule`
+pub const SCHEMA_RULE_ROOT: jint = 0;
+pub const SCHEMA_RULE_OPTIONAL_ROOT: jint = 1;
+pub const SCHEMA_RULE_KEY: jint = 2;
+pub const SCHEMA_RULE_NO_OTHER_ROOTS: jint = 3;
+
+/// Names of `JniTreeNode.Kind`, by ordinal
+const KIND_NAMES: [&str; 9] = [
+    "MAP",
+    "ARRAY",
+    "TEXT",
+    "XML_ELEMENT",
+    "XML_FRAGMENT",
+    "XML_TEXT",
+    "DOC",
+    "UNKNOWN",
+    "LINK",
+];
+
+/// Names of `JniYMap.ValueType`, by ordinal
+const VALUE_TYPE_NAMES: [&str; 13] = [
+    "ABSENT",
+    "NULL",
+    "STRING",
+    "NUMBER",
+    "BOOL",
+    "BYTES",
+    "MAP",
+    "ARRAY",
+    "TEXT",
+    "DOC",
+    "XML",
+    "UNDEFINED",
+    "LINK",
+];
+
+/// One rule of a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaRule {
+    /// A root type of the given `JniTreeNode.Kind` ordinal, which must exist
+    /// if `required`
+    Root {
+        name: String,
+        kind: jint,
+        required: bool,
+    },
+    /// A key a root map must hold, with the `JniYMap.ValueType` ordinal its
+    /// value must have, if any
+    Key {
+        root: String,
+        key: String,
+        value_type: Option<jint>,
+    },
+    /// Every root type must be named by a root rule
+    NoOtherRoots,
+}
+
+/// A rule the document breaks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The root name, or `root/key` for key rules
+    pub path: String,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks the document read by `txn` against `rules`, returning the
+/// violations in the order of the rules
+pub fn validate_schema<T: ReadTxn>(txn: &T, rules: &[SchemaRule]) -> Vec<Violation> {
+    let roots: Vec<(&str, Out)> = txn.root_refs().collect();
+    let find = |name: &str| roots.iter().find(|(n, _)| *n == name).map(|(_, v)| v);
+    let mut violations = Vec::new();
+    for rule in rules {
+        match rule {
+            SchemaRule::Root {
+                name,
+                kind,
+                required,
+            } => match find(name) {
+                None if *required => {
+                    violations.push(Violation::new(name.as_str(), "required root is missing"))
+                }
+                None => {}
+                Some(value) => {
+                    if let Some(found) = kind_mismatch(txn, value, *kind) {
+                        violations.push(Violation::new(
+                            name.as_str(),
+                            format!("expected {}, found {}", kind_name(*kind), found),
+                        ));
+                    }
+                }
+            },
+            SchemaRule::Key {
+                root,
+                key,
+                value_type,
+            } => {
+                let path = format!("{}/{}", root, key);
+                let map = match find(root) {
+                    Some(Out::YMap(map)) => map.clone(),
+                    Some(Out::UndefinedRef(branch)) => MapRef::from(*branch),
+                    _ => {
+                        violations.push(Violation::new(path, "required key is missing"));
+                        continue;
+                    }
+                };
+                let Some(value) = map.get(txn, key) else {
+                    violations.push(Violation::new(path, "required key is missing"));
+                    continue;
+                };
+                let found = value_type_tag(Some(&value));
+                if let Some(expected) = value_type.filter(|expected| *expected != found) {
+                    violations.push(Violation::new(
+                        path,
+                        format!(
+                            "expected {}, found {}",
+                            value_type_name(expected),
+                            value_type_name(found)
+                        ),
+                    ));
+                }
+            }
+            SchemaRule::NoOtherRoots => {
+                let known: HashSet<&str> = rules
+                    .iter()
+                    .filter_map(|rule| match rule {
+                        SchemaRule::Root { name, .. } => Some(name.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                let mut unknown: Vec<&str> = roots
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .filter(|name| !known.contains(name))
+                    .collect();
+                unknown.sort_unstable();
+                for name in unknown {
+                    violations.push(Violation::new(name, "root is not in the schema"));
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// The name of the kind found if `value` is not of the `expected` kind
+fn kind_mismatch<T: ReadTxn>(txn: &T, value: &Out, expected: jint) -> Option<&'static str> {
+    let found = match value {
+        Out::YMap(_) => TREE_KIND_MAP,
+        Out::YArray(_) => TREE_KIND_ARRAY,
+        Out::YText(_) => TREE_KIND_TEXT,
+        Out::YXmlFragment(_) => TREE_KIND_XML_FRAGMENT,
+        Out::UndefinedRef(branch) => {
+            let has_entries = MapRef::from(*branch).len(txn) > 0;
+            let has_sequence = branch.content_len() > 0;
+            let fits = if expected == TREE_KIND_MAP {
+                !has_sequence
+            } else {
+                !has_entries
+            };
+            return match (fits, has_entries) {
+                (true, _) => None,
+                (false, true) => Some("map entries"),
+                (false, false) => Some("sequence content"),
+            };
+        }
+        _ => return Some("another type"),
+    };
+    (found != expected).then(|| kind_name(found))
+}
+
+fn kind_name(kind: jint) -> &'static str {
+    usize::try_from(kind)
+        .ok()
+        .and_then(|i| KIND_NAMES.get(i))
+        .unwrap_or(&"UNKNOWN")
+}
+
+fn value_type_name(value_type: jint) -> &'static str {
+    usize::try_from(value_type)
+        .ok()
+        .and_then(|i| VALUE_TYPE_NAMES.get(i))
+        .unwrap_or(&"UNKNOWN")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::decoder::Decode;
+    use yrs::{Array, Doc, Text, Transact, Update};
+
+    fn root(name: &str, kind: jint) -> SchemaRule {
+        SchemaRule::Root {
+            name: name.to_string(),
+            kind,
+            required: true,
+        }
+    }
+
+    fn key(root: &str, key: &str, value_type: Option<jint>) -> SchemaRule {
+        SchemaRule::Key {
+            root: root.to_string(),
+            key: key.to_string(),
+            value_type,
+        }
+    }
+
+    #[test]
+    fn test_reports_missing_and_mismatched_roots_and_keys() {
+        let doc = Doc::new();
+        let users = doc.get_or_insert_map("users");
+        let tags = doc.get_or_insert_array("tags");
+        {
+            let mut txn = doc.transact_mut();
+            users.insert(&mut txn, "admin", 7.0);
+            tags.push_back(&mut txn, "a");
+        }
+        let rules = [
+            root("users", TREE_KIND_MAP),
+            key("users", "admin", Some(2)),
+            key("users", "owner", None),
+            root("tags", TREE_KIND_TEXT),
+            root("body", TREE_KIND_TEXT),
+            SchemaRule::Root {
+                name: "notes".to_string(),
+                kind: TREE_KIND_MAP,
+                required: false,
+            },
+            SchemaRule::NoOtherRoots,
+        ];
+        let violations = validate_schema(&doc.transact(), &rules);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::new("users/admin", "expected STRING, found NUMBER"),
+                Violation::new("users/owner", "required key is missing"),
+                Violation::new("tags", "expected TEXT, found ARRAY"),
+                Violation::new("body", "required root is missing"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_roots_from_updates_are_checked_by_content() {
+        let source = Doc::new();
+        source
+            .get_or_insert_text("body")
+            .insert(&mut source.transact_mut(), 0, "hi");
+        source
+            .get_or_insert_map("extra")
+            .insert(&mut source.transact_mut(), "k", 1.0);
+        let update = source
+            .transact()
+            .encode_state_as_update_v1(&Default::default());
+
+        let doc = Doc::new();
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        let rules = [
+            root("body", TREE_KIND_TEXT),
+            root("extra", TREE_KIND_ARRAY),
+            key("extra", "k", Some(3)),
+            SchemaRule::NoOtherRoots,
+        ];
+        assert_eq!(
+            validate_schema(&doc.transact(), &rules),
+            vec![Violation::new("extra", "expected ARRAY, found map entries")]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Transactions whose changes are hidden from observers of shared types.
+//!
+//! Hydrating a document from storage applies its whole history in one
+//! transaction, and every observer attached to the document would otherwise
+//! receive an event describing all of it. A silent transaction still commits
+//! normally and reaches update observers and update streams, so peers stay in
+//! sync, but the observers of texts, arrays, maps, XML nodes and JSON views
+//! skip it. Marks are keyed by the transaction's address, like the remote
+//! marks of `event_origin`, and are removed when the transaction is freed.
+
+use dashmap::DashSet;
+use jni::sys::jlong;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use yrs::TransactionMut;
+
+lazy_static! {
+    /// Transactions whose events are not dispatched to observers
+    static ref SILENT_TXNS: DashSet<jlong> = DashSet::new();
+}
+
+/// Number of entries in `SILENT_TXNS`, so observers skip the set when no
+/// silent transaction is open.
+static SILENT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Hides the events of the open transaction at `txn_ptr` from observers
+pub fn mark_silent(txn_ptr: jlong) {
+    if SILENT_TXNS.insert(txn_ptr) {
+        SILENT_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Whether observers of shared types should skip the events of `txn`
+pub fn is_silent(txn: &TransactionMut) -> bool {
+    SILENT_COUNT.load(Ordering::Acquire) != 0
+        && SILENT_TXNS.contains(&(txn as *const TransactionMut as jlong))
+}
+
+/// Drops the mark of a transaction that is being freed
+pub fn forget_silent(txn_ptr: jlong) {
+    if SILENT_COUNT.load(Ordering::Acquire) != 0 && SILENT_TXNS.remove(&txn_ptr).is_some() {
+        SILENT_COUNT.fetch_sub(1, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, Transact};
+
+    #[test]
+    fn test_marks_are_kept_until_forgotten() {
+        let doc = Doc::new();
+        let txn = doc.transact_mut();
+        let key = &txn as *const TransactionMut as jlong;
+        // Marks left behind by other tests' transactions at the same address
+        forget_silent(key);
+        assert!(!is_silent(&txn));
+        mark_silent(key);
+        assert!(is_silent(&txn));
+        forget_silent(key);
+        assert!(!is_silent(&txn));
+    }
+}
@@ -0,0 +1,94 @@
+//! Optional single-writer enforcement for transactions.
+//!
+//! When a document has single-writer checks enabled, the thread that opens a
+//! transaction is recorded. Any later use of that transaction from a different
+//! thread throws `IllegalStateException` naming both threads, instead of
+//! silently racing on the underlying `TransactionMut`.
+
+use dashmap::DashMap;
+use jni::objects::JString;
+use jni::sys::jlong;
+use jni::JNIEnv;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, ThreadId};
+
+/// The thread that opened a transaction
+struct TxnOwner {
+    thread: ThreadId,
+    name: String,
+}
+
+lazy_static! {
+    /// Owners of transactions opened on documents with single-writer checks enabled
+    static ref TXN_OWNERS: DashMap<jlong, TxnOwner> = DashMap::new();
+}
+
+/// Number of entries in `TXN_OWNERS`, so the common case (no checked
+/// transactions open) skips the map lookup entirely.
+static OWNER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the name of the current Java thread, or a placeholder if it cannot be read
+fn current_thread_name(env: &mut JNIEnv) -> String {
+    let name = env
+        .call_static_method(
+            "java/lang/Thread",
+            "currentThread",
+            "()Ljava/lang/Thread;",
+            &[],
+        )
+        .and_then(|t| t.l())
+        .and_then(|t| env.call_method(&t, "getName", "()Ljava/lang/String;", &[]))
+        .and_then(|n| n.l())
+        .and_then(|n| env.get_string(&JString::from(n)).map(String::from));
+    match name {
+        Ok(name) => name,
+        Err(_) => {
+            let _ = env.exception_clear();
+            String::from("<unknown>")
+        }
+    }
+}
+
+/// Records the current thread as the owner of a transaction
+pub fn register_txn_owner(env: &mut JNIEnv, txn_ptr: jlong) {
+    let owner = TxnOwner {
+        thread: thread::current().id(),
+        name: current_thread_name(env),
+    };
+    if TXN_OWNERS.insert(txn_ptr, owner).is_none() {
+        OWNER_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Forgets the owner of a transaction (on commit or rollback)
+pub fn unregister_txn_owner(txn_ptr: jlong) {
+    if OWNER_COUNT.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    if TXN_OWNERS.remove(&txn_ptr).is_some() {
+        OWNER_COUNT.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Checks that the current thread owns the given transaction.
+///
+/// Returns `true` if access is allowed. Otherwise throws
+/// `IllegalStateException` naming both threads and returns `false`.
+/// Pointers that were never registered are always allowed.
+pub fn check_txn_owner(env: &mut JNIEnv, txn_ptr: jlong) -> bool {
+    if OWNER_COUNT.load(Ordering::Acquire) == 0 {
+        return true;
+    }
+    let owner_name = match TXN_OWNERS.get(&txn_ptr) {
+        Some(owner) if owner.thread != thread::current().id() => owner.name.clone(),
+        _ => return true,
+    };
+    let current = current_thread_name(env);
+    let msg = format!(
+        "YTransaction opened on thread '{}' was used from thread '{}'; transactions must stay on the thread that opened them",
+        owner_name, current
+    );
+    let _ = env.throw_new("java/lang/IllegalStateException", msg);
+    false
+}
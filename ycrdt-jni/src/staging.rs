@@ -0,0 +1,196 @@
+//! Per-document staging area for updates that arrive out of order.
+//!
+//! yrs integrates whatever part of an update it can and keeps the rest
+//! pending, so an update applied too early is split between the document and
+//! the pending queue. The staging area instead holds whole updates back until
+//! the document has every clock they start from, then applies them in one go.
+//! Each time an update is applied, staged updates that became ready are
+//! applied as well (a flush), repeating until none are left ready.
+//!
+//! Readiness only looks at the lowest clock per client in the update. An
+//! update that also references content from a client it does not start with
+//! may still leave a remainder in yrs' pending queue; yrs integrates that
+//! remainder once the referenced content arrives.
+
+use crate::{decode_update_v1, mark_remote, UpdateDecodeError};
+use std::sync::Mutex;
+use yrs::error::UpdateError;
+use yrs::{ReadTxn, StateVector, TransactionMut};
+
+/// An update waiting for earlier clocks to arrive
+struct StagedUpdate {
+    /// The v1-encoded update
+    bytes: Vec<u8>,
+    /// Lowest clock per client the update contains
+    lower: StateVector,
+}
+
+/// What happened to an update passed to [`UpdateStaging::apply_or_stage`]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StagingOutcome {
+    /// Whether the update was applied rather than staged
+    pub applied: bool,
+    /// Number of previously staged updates applied as a result
+    pub flushed: usize,
+    /// Number of updates still staged
+    pub remaining: usize,
+}
+
+/// Reasons an update could not be staged or applied
+#[derive(Debug)]
+pub enum StagingError {
+    /// The update is not valid v1 encoding
+    Decode(UpdateDecodeError),
+    /// yrs rejected the update
+    Apply(UpdateError),
+}
+
+/// Updates held back until the document has the clocks they depend on
+#[derive(Default)]
+pub struct UpdateStaging {
+    queue: Mutex<Vec<StagedUpdate>>,
+}
+
+impl UpdateStaging {
+    /// Applies `bytes` if the document has every clock it starts from and
+    /// stages it otherwise, then flushes staged updates that became ready
+    pub fn apply_or_stage(
+        &self,
+        txn: &mut TransactionMut,
+        bytes: Vec<u8>,
+    ) -> Result<StagingOutcome, StagingError> {
+        let update = decode_update_v1(&bytes).map_err(StagingError::Decode)?;
+        let lower = update.state_vector_lower();
+        let mut outcome = StagingOutcome::default();
+        if is_ready(&lower, &txn.state_vector()) {
+            mark_remote(txn);
+            txn.apply_update(update).map_err(StagingError::Apply)?;
+            outcome.applied = true;
+            outcome.flushed = self.flush(txn)?;
+        } else {
+            self.lock().push(StagedUpdate { bytes, lower });
+        }
+        outcome.remaining = self.len();
+        Ok(outcome)
+    }
+
+    /// Applies staged updates until none of the remaining ones are ready,
+    /// returning how many were applied
+    pub fn flush(&self, txn: &mut TransactionMut) -> Result<usize, StagingError> {
+        let mut flushed = 0;
+        loop {
+            let ready = self.take_ready(&txn.state_vector());
+            if ready.is_empty() {
+                return Ok(flushed);
+            }
+            for staged in ready {
+                let update = decode_update_v1(&staged.bytes).map_err(StagingError::Decode)?;
+                txn.apply_update(update).map_err(StagingError::Apply)?;
+                flushed += 1;
+            }
+        }
+    }
+
+    /// Number of staged updates
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no updates are staged
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Discards all staged updates, returning how many there were
+    pub fn clear(&self) -> usize {
+        let mut queue = self.lock();
+        let count = queue.len();
+        queue.clear();
+        count
+    }
+
+    /// Removes and returns the staged updates that are ready for `local`, in
+    /// the order they were staged. The lock is released before they are
+    /// applied.
+    fn take_ready(&self, local: &StateVector) -> Vec<StagedUpdate> {
+        let mut queue = self.lock();
+        let (ready, waiting) = queue
+            .drain(..)
+            .partition(|staged| is_ready(&staged.lower, local));
+        *queue = waiting;
+        ready
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<StagedUpdate>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether a document at `local` has every clock an update starting at `lower` needs
+fn is_ready(lower: &StateVector, local: &StateVector) -> bool {
+    lower
+        .iter()
+        .all(|(client, clock)| *clock <= local.get(client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, GetString, Text, Transact};
+
+    /// Three consecutive updates from one client, each appending a letter
+    fn updates() -> Vec<Vec<u8>> {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("t");
+        let mut out = Vec::new();
+        for (i, s) in ["a", "b", "c"].iter().enumerate() {
+            let sv = doc.transact().state_vector();
+            text.insert(&mut doc.transact_mut(), i as u32, s);
+            out.push(doc.transact().encode_diff_v1(&sv));
+        }
+        out
+    }
+
+    #[test]
+    fn test_staged_updates_flush_in_dependency_order() {
+        let updates = updates();
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("t");
+        let staging = UpdateStaging::default();
+        let mut txn = doc.transact_mut();
+
+        let outcome = staging
+            .apply_or_stage(&mut txn, updates[2].clone())
+            .unwrap();
+        assert!(!outcome.applied);
+        assert_eq!(outcome.remaining, 1);
+        let outcome = staging
+            .apply_or_stage(&mut txn, updates[1].clone())
+            .unwrap();
+        assert!(!outcome.applied);
+        assert_eq!(outcome.remaining, 2);
+        assert!(txn.store().pending_update().is_none());
+
+        let outcome = staging
+            .apply_or_stage(&mut txn, updates[0].clone())
+            .unwrap();
+        assert_eq!(
+            outcome,
+            StagingOutcome {
+                applied: true,
+                flushed: 2,
+                remaining: 0
+            }
+        );
+        assert_eq!(text.get_string(&txn), "abc");
+    }
+
+    #[test]
+    fn test_invalid_update_is_not_staged() {
+        let doc = Doc::new();
+        let staging = UpdateStaging::default();
+        let result = staging.apply_or_stage(&mut doc.transact_mut(), vec![0xff, 0xff]);
+        assert!(matches!(result, Err(StagingError::Decode(_))));
+        assert!(staging.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Helpers for passing sticky indexes across the JNI boundary.
+//!
+//! Sticky indexes travel between Java and Rust in their v1 binary encoding
+//! (the same format as Yjs relative positions), so Java can store and send
+//! them without holding a native handle.
+//!
+//! yrs writes the format exactly as `Y.encodeRelativePosition` does, but reads
+//! it more strictly than `Y.decodeRelativePosition`: Yjs releases before 13.5
+//! did not write the association, which Yjs reads as 0, and Yjs treats any
+//! negative association as "before". [`decode_sticky_index_v1`] reads the
+//! format the way Yjs does.
+
+use jni::objects::JByteArray;
+use jni::sys::jint;
+use jni::JNIEnv;
+use yrs::block::ID;
+use yrs::branch::{Branch, BranchPtr};
+use yrs::encoding::read::{Cursor, Error, Read};
+use yrs::{Assoc, IndexScope, ReadTxn, StickyIndex};
+
+/// Maps the Java association value (0 = after, -1 = before) to `Assoc`
+pub fn assoc_from_jint(value: jint) -> Assoc {
+    if value < 0 {
+        Assoc::Before
+    } else {
+        Assoc::After
+    }
+}
+
+/// Decodes a v1-encoded sticky index, accepting everything
+/// `Y.decodeRelativePosition` accepts
+///
+/// A missing association means [`Assoc::After`], any negative one
+/// [`Assoc::Before`]. Bytes after the association are ignored, as in Yjs.
+pub fn decode_sticky_index_v1(data: &[u8]) -> Result<StickyIndex, Error> {
+    let mut cursor = Cursor::new(data);
+    let tag: u32 = cursor.read_var()?;
+    let scope = match tag {
+        0 => IndexScope::Relative(ID::new(cursor.read_var()?, cursor.read_var()?)),
+        1 => IndexScope::Root(cursor.read_string()?.into()),
+        2 => IndexScope::Nested(ID::new(cursor.read_var()?, cursor.read_var()?)),
+        _ => return Err(Error::UnexpectedValue),
+    };
+    let assoc = if cursor.has_content() {
+        let value: i64 = cursor.read_var()?;
+        if value < 0 {
+            Assoc::Before
+        } else {
+            Assoc::After
+        }
+    } else {
+        Assoc::After
+    };
+    Ok(StickyIndex::new(scope, assoc))
+}
+
+/// Decodes a sticky index argument, throwing `IllegalArgumentException` on
+/// null or malformed input
+pub fn decode_sticky_index(env: &mut JNIEnv, bytes: &JByteArray) -> Option<StickyIndex> {
+    if bytes.is_null() {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Sticky index cannot be null",
+        );
+        return None;
+    }
+    let decoded = env
+        .convert_byte_array(bytes)
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|data| decode_sticky_index_v1(&data).map_err(|e| format!("{:?}", e)));
+    match decoded {
+        Ok(index) => Some(index),
+        Err(e) => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Invalid sticky index: {}", e),
+            );
+            None
+        }
+    }
+}
+
+/// Resolves a sticky index to an offset in `branch`.
+///
+/// Returns `None` if the index cannot be resolved in the current document
+/// state or if it points into a different shared type.
+pub fn resolve_in<T: ReadTxn, B: AsRef<Branch>>(
+    txn: &T,
+    sticky: &StickyIndex,
+    branch: &B,
+) -> Option<u32> {
+    let offset = sticky.get_offset(txn)?;
+    if offset.branch == BranchPtr::from(branch.as_ref()) {
+        Some(offset.index)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::encoder::Encode;
+
+    // The positions created by scripts/yjs-sticky-fixtures.mjs, as encoded by
+    // Y.encodeRelativePosition of yjs 13.6.27; rerun the script to refresh
+    // them. Each is a tag, then the item or type ID as two var-uints or the
+    // root name as a var-string, then the association as a lib0 var-int
+    const ITEM_BEFORE: &[u8] = &[0x00, 0x2a, 0x02, 0x41];
+    const ROOT_AFTER: &[u8] = &[0x01, 0x04, 0x74, 0x65, 0x78, 0x74, 0x00];
+    const NESTED_AFTER: &[u8] = &[0x02, 0x80, 0xbc, 0xc1, 0x96, 0x0b, 0x07, 0x00];
+    // ITEM_BEFORE as written by Yjs releases that did not encode the association
+    const ITEM_LEGACY: &[u8] = &[0x00, 0x2a, 0x02];
+
+    #[test]
+    fn test_yjs_encoding_round_trips() {
+        let cases = [
+            (
+                ITEM_BEFORE,
+                StickyIndex::new(IndexScope::Relative(ID::new(42, 2)), Assoc::Before),
+            ),
+            (
+                ROOT_AFTER,
+                StickyIndex::new(IndexScope::Root("text".into()), Assoc::After),
+            ),
+            (
+                NESTED_AFTER,
+                StickyIndex::new(IndexScope::Nested(ID::new(3_000_000_000, 7)), Assoc::After),
+            ),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(decode_sticky_index_v1(bytes).unwrap(), expected);
+            assert_eq!(expected.encode_v1(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_decode_accepts_what_yjs_accepts() {
+        let legacy = decode_sticky_index_v1(ITEM_LEGACY).unwrap();
+        assert_eq!(legacy.assoc, Assoc::After);
+        assert_eq!(legacy.scope(), &IndexScope::Relative(ID::new(42, 2)));
+
+        // -2 as a lib0 var-int; only the sign matters
+        let before = decode_sticky_index_v1(&[0x00, 0x2a, 0x02, 0x42]).unwrap();
+        assert_eq!(before.assoc, Assoc::Before);
+
+        assert!(decode_sticky_index_v1(&[0x03, 0x00]).is_err());
+        assert!(decode_sticky_index_v1(&[0x00, 0x2a]).is_err());
+    }
+}
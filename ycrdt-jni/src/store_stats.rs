@@ -0,0 +1,102 @@
+//! Statistics about the blocks a document keeps in its store.
+//!
+//! Every insertion becomes a block, and deleted blocks stay in the store as
+//! tombstones until garbage collection replaces their content with GC blocks.
+//! Documents that see many small edits, or that run without garbage
+//! collection, can accumulate a large number of blocks; these counts make that
+//! visible. They are taken from the document's full state update, so
+//! computing them costs about as much as encoding the document.
+
+use crate::{count_structs_by_client_v1, pending_state, PendingState, UpdateDecodeError};
+use yrs::{ReadTxn, StateVector};
+
+/// Block counts of one client
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClientStats {
+    /// The client ID
+    pub client: u64,
+    /// The next clock of the client, i.e. the number of clock units it wrote
+    pub clock: u32,
+    /// Number of blocks, including tombstones and GC blocks
+    pub blocks: u64,
+    /// Number of deleted blocks that still hold their content
+    pub deleted_blocks: u64,
+    /// Number of blocks whose content was garbage collected
+    pub gc_blocks: u64,
+}
+
+/// Block counts of a document's store
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    /// Per-client counts, ordered by client ID
+    pub clients: Vec<ClientStats>,
+    /// Clock units covered by the document's delete set
+    pub deleted_length: u64,
+    /// Content held back until missing updates arrive
+    pub pending: Option<PendingState>,
+}
+
+/// Collects the block counts of the document read by `txn`
+pub fn store_stats<T: ReadTxn>(txn: &T) -> Result<StoreStats, UpdateDecodeError> {
+    // Unlike encode_state_as_update_v1, the diff leaves out pending structs
+    let update = txn.encode_diff_v1(&StateVector::default());
+    let counts = count_structs_by_client_v1(&update)?;
+    let state_vector = txn.state_vector();
+    let clients = counts
+        .into_iter()
+        .map(|(client, counts)| ClientStats {
+            client,
+            clock: state_vector.get(&client),
+            blocks: counts.items + counts.gc,
+            deleted_blocks: counts.deleted_items,
+            gc_blocks: counts.gc,
+        })
+        .collect();
+    let deleted_length = txn
+        .snapshot()
+        .delete_set
+        .iter()
+        .flat_map(|(_, ranges)| ranges.iter())
+        .map(|r| u64::from(r.end - r.start))
+        .sum();
+    Ok(StoreStats {
+        clients,
+        deleted_length,
+        pending: pending_state(txn),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, Options, Text, Transact};
+
+    #[test]
+    fn test_store_stats_counts_tombstones() {
+        let doc = Doc::with_options(Options {
+            client_id: 7,
+            skip_gc: true,
+            ..Options::default()
+        });
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        text.insert(&mut doc.transact_mut(), 5, " world");
+        text.remove_range(&mut doc.transact_mut(), 0, 6);
+
+        // Neighbouring blocks of the same state are merged when a
+        // transaction commits: "hello " is deleted and "world" is not
+        let stats = store_stats(&doc.transact()).unwrap();
+        assert_eq!(
+            stats.clients,
+            vec![ClientStats {
+                client: 7,
+                clock: 11,
+                blocks: 2,
+                deleted_blocks: 1,
+                gc_blocks: 0,
+            }]
+        );
+        assert_eq!(stats.deleted_length, 6);
+        assert_eq!(stats.pending, None);
+    }
+}
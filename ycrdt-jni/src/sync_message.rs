@@ -0,0 +1,237 @@
+//! Handling of y-websocket sync protocol messages.
+//!
+//! A message starts with a varint message type. Sync messages (type 0) carry a
+//! second varint for the sync step followed by a length-prefixed payload:
+//!
+//! - step 1 holds the sender's state vector and is answered with step 2
+//! - step 2 and update hold a v1 update, which is applied to the document
+//!
+//! Awareness (type 1), auth (type 2) and awareness query (type 3) messages do
+//! not touch the document; they are decoded and handed back to the caller,
+//! as are messages of unknown type.
+//!
+//! [`absorb`] is a frameless shortcut for pairwise sync: it applies a diff
+//! from a peer and answers with the changes that peer is missing.
+
+use crate::{decode_update_v1, mark_remote, UpdateDecodeError};
+use yrs::encoding::read::Error as DecodeError;
+use yrs::error::UpdateError;
+use yrs::sync::{Message, SyncMessage};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{DeleteSet, ReadTxn, StateVector, TransactionMut, Update};
+
+/// Message kinds, matching the declaration order of `JniSyncMessage.Kind`
+pub const SYNC_KIND_STEP_1: i32 = 0;
+pub const SYNC_KIND_STEP_2: i32 = 1;
+pub const SYNC_KIND_UPDATE: i32 = 2;
+pub const SYNC_KIND_AWARENESS: i32 = 3;
+pub const SYNC_KIND_AWARENESS_QUERY: i32 = 4;
+pub const SYNC_KIND_AUTH: i32 = 5;
+pub const SYNC_KIND_CUSTOM: i32 = 6;
+
+/// What a handled message was and what the caller should do with it
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncOutcome {
+    /// One of the `SYNC_KIND_*` constants
+    pub kind: i32,
+    /// Message to send back to the peer
+    pub reply: Option<Vec<u8>>,
+    /// Awareness update, or the payload of a message of unknown type
+    pub payload: Option<Vec<u8>>,
+    /// Message type of a message of unknown type
+    pub custom_type: u8,
+    /// Reason given by an auth message that denied permission
+    pub denied_reason: Option<String>,
+}
+
+/// Reasons a sync message could not be handled
+#[derive(Debug)]
+pub enum SyncMessageError {
+    /// The message framing is invalid
+    Decode(DecodeError),
+    /// The update carried by the message is invalid
+    Update(UpdateDecodeError),
+    /// yrs rejected the update
+    Apply(UpdateError),
+    /// The message carries an update but the document does not accept updates
+    ReadOnly,
+}
+
+/// Handles one y-websocket message, applying any update it carries to `txn`
+pub fn handle_sync_message(
+    txn: &mut TransactionMut,
+    message: &[u8],
+    accepts_updates: bool,
+) -> Result<SyncOutcome, SyncMessageError> {
+    let message = Message::decode_v1(message).map_err(SyncMessageError::Decode)?;
+    let mut outcome = SyncOutcome::default();
+    match message {
+        Message::Sync(SyncMessage::SyncStep1(remote)) => {
+            outcome.kind = SYNC_KIND_STEP_1;
+            let diff = txn.encode_diff_v1(&remote);
+            outcome.reply = Some(Message::Sync(SyncMessage::SyncStep2(diff)).encode_v1());
+        }
+        Message::Sync(SyncMessage::SyncStep2(update)) => {
+            outcome.kind = SYNC_KIND_STEP_2;
+            apply(txn, &update, accepts_updates)?;
+        }
+        Message::Sync(SyncMessage::Update(update)) => {
+            outcome.kind = SYNC_KIND_UPDATE;
+            apply(txn, &update, accepts_updates)?;
+        }
+        Message::Awareness(update) => {
+            outcome.kind = SYNC_KIND_AWARENESS;
+            outcome.payload = Some(update.encode_v1());
+        }
+        Message::AwarenessQuery => outcome.kind = SYNC_KIND_AWARENESS_QUERY,
+        Message::Auth(reason) => {
+            outcome.kind = SYNC_KIND_AUTH;
+            outcome.denied_reason = reason;
+        }
+        Message::Custom(tag, data) => {
+            outcome.kind = SYNC_KIND_CUSTOM;
+            outcome.custom_type = tag;
+            outcome.payload = Some(data);
+        }
+    }
+    Ok(outcome)
+}
+
+fn apply(
+    txn: &mut TransactionMut,
+    update: &[u8],
+    accepts_updates: bool,
+) -> Result<(), SyncMessageError> {
+    if !accepts_updates {
+        return Err(SyncMessageError::ReadOnly);
+    }
+    let update = decode_update_v1(update).map_err(SyncMessageError::Update)?;
+    mark_remote(txn);
+    txn.apply_update(update).map_err(SyncMessageError::Apply)
+}
+
+/// Applies a diff received from a peer and returns the diff to send back
+///
+/// The peer's state vector is not part of the diff, so it is estimated from
+/// the clocks the diff reaches; see [`covered_state`]. Clients the diff has no
+/// content from are sent in full. Applying content a peer already has is
+/// harmless, so this costs bandwidth but not correctness.
+pub fn absorb(
+    txn: &mut TransactionMut,
+    diff: &[u8],
+    accepts_updates: bool,
+) -> Result<Vec<u8>, SyncMessageError> {
+    if !accepts_updates {
+        return Err(SyncMessageError::ReadOnly);
+    }
+    let update = decode_update_v1(diff).map_err(SyncMessageError::Update)?;
+    let remote = covered_state(&update);
+    mark_remote(txn);
+    txn.apply_update(update).map_err(SyncMessageError::Apply)?;
+    Ok(txn.encode_diff_v1(&remote))
+}
+
+/// The clock each client reaches in `update`
+///
+/// Unlike `Update::state_vector`, this counts clients whose content does not
+/// start at clock 0, which is the case for every client in a diff against a
+/// non-empty state vector.
+pub fn covered_state(update: &Update) -> StateVector {
+    let mut state = StateVector::default();
+    for (client, range) in DeleteSet::from(update.insertions(true)).iter() {
+        if let Some(last) = range.iter().next_back() {
+            state.set_max(*client, last.end);
+        }
+    }
+    state
+}
+
+/// Encodes a sync step 1 message announcing the state vector of `txn`
+pub fn encode_sync_step1<T: ReadTxn>(txn: &T) -> Vec<u8> {
+    Message::Sync(SyncMessage::SyncStep1(txn.state_vector())).encode_v1()
+}
+
+/// Encodes a sync update message carrying a v1 update
+pub fn encode_sync_update(update: Vec<u8>) -> Vec<u8> {
+    Message::Sync(SyncMessage::Update(update)).encode_v1()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, GetString, Text, Transact};
+
+    #[test]
+    fn test_step1_reply_syncs_peer() {
+        let server = Doc::new();
+        let text = server.get_or_insert_text("t");
+        text.insert(&mut server.transact_mut(), 0, "hello");
+        let client = Doc::new();
+        let client_text = client.get_or_insert_text("t");
+
+        let step1 = encode_sync_step1(&client.transact());
+        let outcome = handle_sync_message(&mut server.transact_mut(), &step1, true).unwrap();
+        assert_eq!(outcome.kind, SYNC_KIND_STEP_1);
+
+        let step2 = outcome.reply.unwrap();
+        let outcome = handle_sync_message(&mut client.transact_mut(), &step2, true).unwrap();
+        assert_eq!(outcome.kind, SYNC_KIND_STEP_2);
+        assert_eq!(outcome.reply, None);
+        assert_eq!(client_text.get_string(&client.transact()), "hello");
+    }
+
+    #[test]
+    fn test_absorb_answers_with_missing_changes() {
+        let a = Doc::with_options(yrs::Options::with_client_id(1));
+        let b = Doc::with_options(yrs::Options::with_client_id(2));
+        let a_text = a.get_or_insert_text("t");
+        let b_text = b.get_or_insert_text("t");
+        a_text.insert(&mut a.transact_mut(), 0, "shared ");
+        let base = a
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        b.transact_mut()
+            .apply_update(Update::decode_v1(&base).unwrap())
+            .unwrap();
+        a_text.insert(&mut a.transact_mut(), 7, "a");
+        b_text.insert(&mut b.transact_mut(), 0, "b");
+
+        let diff = a.transact().encode_diff_v1(&b.transact().state_vector());
+        let update = Update::decode_v1(&diff).unwrap();
+        assert_eq!(update.state_vector().get(&1), 0);
+        assert_eq!(covered_state(&update).get(&1), 8);
+
+        let reply = absorb(&mut b.transact_mut(), &diff, true).unwrap();
+        let reply = Update::decode_v1(&reply).unwrap();
+        assert_eq!(covered_state(&reply).get(&1), 0);
+        a.transact_mut().apply_update(reply).unwrap();
+        assert_eq!(
+            a_text.get_string(&a.transact()),
+            b_text.get_string(&b.transact())
+        );
+    }
+
+    #[test]
+    fn test_non_document_messages_are_returned() {
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let outcome = handle_sync_message(&mut txn, &[3], true).unwrap();
+        assert_eq!(outcome.kind, SYNC_KIND_AWARENESS_QUERY);
+
+        let outcome = handle_sync_message(&mut txn, &[9, 2, 0xab, 0xcd], true).unwrap();
+        assert_eq!(outcome.kind, SYNC_KIND_CUSTOM);
+        assert_eq!(outcome.custom_type, 9);
+        assert_eq!(outcome.payload, Some(vec![0xab, 0xcd]));
+
+        let update = encode_sync_update(txn.encode_update_v1());
+        assert!(matches!(
+            handle_sync_message(&mut txn, &update, false),
+            Err(SyncMessageError::ReadOnly)
+        ));
+        assert!(matches!(
+            handle_sync_message(&mut txn, &[0, 7], true),
+            Err(SyncMessageError::Decode(_))
+        ));
+    }
+}
@@ -685,6 +685,72 @@ public class ConcurrentAccessTest {
         }
     }
 
+    /**
+     * Test that closing a document waits for an observer running on another thread.
+     */
+    @Test
+    public void testCloseWaitsForInFlightObserver() throws Exception {
+        JniYDoc doc = new JniYDoc();
+        CountDownLatch entered = new CountDownLatch(1);
+        CountDownLatch release = new CountDownLatch(1);
+        AtomicBoolean observerFinished = new AtomicBoolean(false);
+
+        doc.observeUpdateV1((update, origin) -> {
+            entered.countDown();
+            try {
+                release.await(TIMEOUT_SECONDS, TimeUnit.SECONDS);
+            } catch (InterruptedException e) {
+                Thread.currentThread().interrupt();
+            }
+            observerFinished.set(true);
+        });
+
+        Thread writer = new Thread(() -> {
+            try (YText text = doc.getText("text")) {
+                text.push("hello");
+            }
+        });
+        writer.start();
+        assertTrue("Observer did not start", entered.await(TIMEOUT_SECONDS, TimeUnit.SECONDS));
+
+        Thread closer = new Thread(doc::close);
+        closer.start();
+        closer.join(100);
+        assertTrue("close() returned while an observer was running", closer.isAlive());
+
+        release.countDown();
+        closer.join(TimeUnit.SECONDS.toMillis(TIMEOUT_SECONDS));
+        writer.join(TimeUnit.SECONDS.toMillis(TIMEOUT_SECONDS));
+        assertFalse("close() did not return", closer.isAlive());
+        assertTrue(observerFinished.get());
+        assertTrue(doc.isClosed());
+    }
+
+    /**
+     * Test that closing a document from inside its own observer is rejected.
+     */
+    @Test
+    public void testCloseFromObserverIsRejected() {
+        JniYDoc doc = new JniYDoc();
+        AtomicReference<Exception> error = new AtomicReference<>();
+        doc.observeUpdateV1((update, origin) -> {
+            try {
+                doc.close();
+            } catch (IllegalStateException e) {
+                error.set(e);
+            }
+        });
+
+        try (YText text = doc.getText("text")) {
+            text.push("hello");
+        }
+
+        assertNotNull(error.get());
+        assertFalse("Document was closed from its own observer", doc.isClosed());
+        doc.close();
+        assertTrue(doc.isClosed());
+    }
+
     /**
      * Test subscribing and unsubscribing observers concurrently.
      */
@@ -0,0 +1,175 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YText;
+
+import org.junit.Test;
+
+import java.time.Duration;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.concurrent.ArrayBlockingQueue;
+import java.util.concurrent.BlockingQueue;
+import java.util.concurrent.TimeUnit;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertFalse;
+import static org.junit.Assert.assertNotNull;
+import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertSame;
+import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
+
+/**
+ * Unit tests for JniDocStore.
+ */
+public class DocStoreTest {
+
+    @Test
+    public void testGetOrCreateReturnsSameDocument() {
+        try (JniDocStore store = new JniDocStore()) {
+            JniYDoc doc = store.getOrCreate("room");
+            assertSame(doc, store.getOrCreate("room"));
+            assertSame(doc, store.get("room"));
+            assertNull(store.get("other"));
+            assertEquals("room", doc.getGuid());
+            assertEquals(1, store.size());
+        }
+    }
+
+    @Test
+    public void testCapacityEvictsLeastRecentlyUsed() {
+        List<String> evicted = new ArrayList<>();
+        try (JniDocStore store = new JniDocStore(2)) {
+            store.setEvictionListener((name, doc) -> {
+                assertFalse(doc.isClosed());
+                try (YText text = doc.getText("text")) {
+                    evicted.add(name + ":" + text.toString());
+                }
+            });
+            JniYDoc a = store.getOrCreate("a");
+            JniYDoc b = store.getOrCreate("b");
+            try (YText text = b.getText("text")) {
+                text.insert(0, "hello");
+            }
+            store.get("a");
+            store.getOrCreate("c");
+
+            assertEquals(List.of("b:hello"), evicted);
+            assertTrue(b.isClosed());
+            assertFalse(a.isClosed());
+            assertEquals(List.of("a", "c"), store.names());
+            assertEquals(2, store.getCapacity());
+        }
+        assertEquals(List.of("b:hello", "a:", "c:"), evicted);
+    }
+
+    @Test
+    public void testRemoveAndEvict() {
+        List<String> evicted = new ArrayList<>();
+        try (JniDocStore store = new JniDocStore()) {
+            store.setEvictionListener((name, doc) -> evicted.add(name));
+            JniYDoc removed = store.getOrCreate("a");
+            JniYDoc closed = store.getOrCreate("b");
+            store.getOrCreate("c");
+
+            assertSame(removed, store.remove("a"));
+            assertFalse(removed.isClosed());
+            assertNull(store.remove("a"));
+            assertTrue(store.evict("b"));
+            assertTrue(closed.isClosed());
+            assertFalse(store.evict("b"));
+            assertEquals(List.of("b"), evicted);
+
+            assertEquals(List.of("c"), store.names());
+            removed.close();
+        }
+    }
+
+    @Test
+    public void testClosingDocumentRemovesItFromStore() {
+        try (JniDocStore store = new JniDocStore()) {
+            JniYDoc doc = store.getOrCreate("room");
+            doc.close();
+            assertEquals(0, store.size());
+            JniYDoc recreated = store.getOrCreate("room");
+            assertFalse(recreated.isClosed());
+            assertEquals(1, store.size());
+        }
+    }
+
+    @Test
+    public void testForEachAndClose() {
+        JniDocStore store = new JniDocStore();
+        JniYDoc b = store.getOrCreate("b");
+        JniYDoc a = store.getOrCreate("a");
+        List<String> seen = new ArrayList<>();
+        store.forEach((name, doc) -> seen.add(name));
+        assertEquals(List.of("a", "b"), seen);
+
+        store.close();
+        store.close();
+        assertTrue(store.isClosed());
+        assertTrue(a.isClosed());
+        assertTrue(b.isClosed());
+        try {
+            store.getOrCreate("a");
+            fail("Expected IllegalStateException");
+        } catch (IllegalStateException e) {
+            // expected
+        }
+    }
+
+    @Test
+    public void testCompactIdleEncodesChangedDocuments() {
+        try (JniDocStore store = new JniDocStore()) {
+            JniYDoc doc = store.getOrCreate("a");
+            store.getOrCreate("b");
+            List<String> names = new ArrayList<>();
+            List<byte[]> states = new ArrayList<>();
+            DocCompactionListener listener = (name, state) -> {
+                names.add(name);
+                states.add(state);
+            };
+            assertEquals(0, store.compactIdle(Duration.ZERO, listener));
+
+            try (YText text = doc.getText("text")) {
+                text.insert(0, "hello");
+            }
+            assertEquals(0, store.compactIdle(Duration.ofHours(1), listener));
+            assertEquals(1, store.compactIdle(Duration.ZERO, listener));
+            assertEquals(List.of("a"), names);
+            assertEquals(0, store.compactIdle(Duration.ZERO, listener));
+
+            try (JniYDoc restored = new JniYDoc();
+                 YText text = restored.getText("text")) {
+                restored.applyUpdate(states.get(0));
+                assertEquals("hello", text.toString());
+            }
+        }
+    }
+
+    @Test
+    public void testBackgroundCompaction() throws InterruptedException {
+        BlockingQueue<String> compacted = new ArrayBlockingQueue<>(10);
+        try (JniDocStore store = new JniDocStore()) {
+            JniYDoc doc = store.getOrCreate("room");
+            store.startCompaction(Duration.ofMillis(10), Duration.ofMillis(20),
+                (name, state) -> compacted.add(name + ":" + state.length));
+            try (YText text = doc.getText("text")) {
+                text.insert(0, "hello");
+            }
+            String result = compacted.poll(10, TimeUnit.SECONDS);
+            assertNotNull(result);
+            assertTrue(result.startsWith("room:"));
+            store.stopCompaction();
+
+            try {
+                store.startCompaction(Duration.ZERO, Duration.ZERO, (name, state) -> { });
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+        assertTrue(compacted.isEmpty());
+    }
+}
@@ -0,0 +1,108 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YXmlNode;
+
+import org.junit.Test;
+
+import java.lang.reflect.Field;
+import java.lang.reflect.Modifier;
+import java.util.HashSet;
+import java.util.List;
+import java.util.Map;
+import java.util.Set;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertTrue;
+
+/**
+ * Checks the integer tags declared in Java against the table exported by the
+ * native library.
+ */
+public class ProtocolTest {
+
+    @Test
+    public void testJavaTagsMatchNativeTags() throws Exception {
+        Map<String, Integer> constants = JniProtocol.constants();
+        assertTrue(constants.containsKey("JniBatchOp.TEXT_INSERT"));
+        assertTrue(constants.containsKey("JniTreeNode$Kind.LINK"));
+
+        Set<Class<?>> classes = new HashSet<>();
+        for (Map.Entry<String, Integer> entry : constants.entrySet()) {
+            String name = entry.getKey();
+            int dot = name.lastIndexOf('.');
+            Class<?> type = Class.forName("net.carcdr.ycrdt.jni." + name.substring(0, dot));
+            classes.add(type);
+            assertEquals(name, (int) entry.getValue(), javaTag(type, name.substring(dot + 1)));
+        }
+
+        // Every tag of a class in the table must be exported, so a tag added in
+        // Java only is caught as well
+        for (Class<?> type : classes) {
+            for (String tag : javaTagNames(type)) {
+                String name = type.getName().substring("net.carcdr.ycrdt.jni.".length())
+                    + "." + tag;
+                assertTrue("Missing native tag " + name, constants.containsKey(name));
+            }
+        }
+    }
+
+    @Test
+    public void testXmlNodeTagsMatchNativeRefTags() throws Exception {
+        Map<String, Integer> constants = JniProtocol.constants();
+        assertEquals(YNativeRef.TYPE_ELEMENT, (int) constants.get("YNativeRef.TYPE_ELEMENT"));
+        assertEquals(YNativeRef.TYPE_TEXT, (int) constants.get("YNativeRef.TYPE_TEXT"));
+        assertEquals(YNativeRef.TYPE_FRAGMENT, (int) constants.get("YNativeRef.TYPE_FRAGMENT"));
+
+        // The tags emitted by node type and children lookups are read with the
+        // same constants
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("xml")) {
+            fragment.insertElement(0, "div");
+            fragment.insertText(1, "text");
+            assertEquals(YXmlNode.NodeType.ELEMENT, fragment.getNodeType(0));
+            assertEquals(YXmlNode.NodeType.TEXT, fragment.getNodeType(1));
+            assertNull(fragment.getNodeType(2));
+
+            try (JniYXmlElement div = (JniYXmlElement) fragment.getChild(0)) {
+                div.insertElement(0, "span").close();
+                div.insertText(1).close();
+                List<YXmlNode> children = div.getChildren();
+                assertEquals(YXmlNode.NodeType.ELEMENT, children.get(0).getNodeType());
+                assertEquals(YXmlNode.NodeType.TEXT, children.get(1).getNodeType());
+            }
+        }
+    }
+
+    private static int javaTag(Class<?> type, String tag) throws ReflectiveOperationException {
+        if (type.isEnum()) {
+            for (Object constant : type.getEnumConstants()) {
+                if (((Enum<?>) constant).name().equals(tag)) {
+                    return ((Enum<?>) constant).ordinal();
+                }
+            }
+            throw new NoSuchFieldException(type.getName() + "." + tag);
+        }
+        Field field = type.getDeclaredField(tag);
+        field.setAccessible(true);
+        return field.getInt(null);
+    }
+
+    private static Set<String> javaTagNames(Class<?> type) {
+        Set<String> names = new HashSet<>();
+        if (type.isEnum()) {
+            for (Object constant : type.getEnumConstants()) {
+                names.add(((Enum<?>) constant).name());
+            }
+            return names;
+        }
+        for (Field field : type.getDeclaredFields()) {
+            int modifiers = field.getModifiers();
+            if (Modifier.isStatic(modifiers) && Modifier.isFinal(modifiers)
+                    && field.getType() == int.class) {
+                names.add(field.getName());
+            }
+        }
+        return names;
+    }
+}
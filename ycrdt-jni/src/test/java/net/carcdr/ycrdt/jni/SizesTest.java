@@ -0,0 +1,53 @@
+package net.carcdr.ycrdt.jni;
+
+import org.junit.Test;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
+
+/**
+ * Tests for the 64-bit size methods and their narrowing to int.
+ */
+public class SizesTest {
+
+    @Test
+    public void testLongSizesMatchIntSizes() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYArray array = doc.getArray("array");
+             JniYMap map = doc.getMap("map");
+             JniYXmlText xmlText = doc.getXmlText("xmlText");
+             JniYXmlFragment fragment = doc.getXmlFragment("fragment")) {
+            text.insert(0, "Hello");
+            array.pushString("a");
+            map.setString("k", "v");
+            xmlText.insert(0, "Hi");
+            fragment.insertText(0, "x");
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                assertEquals(5L, text.longLength(txn));
+                assertEquals(1L, array.longLength(txn));
+                assertEquals(1L, map.longSize(txn));
+                assertEquals(2L, xmlText.longLength(txn));
+                assertEquals(1L, fragment.longLength(txn));
+            }
+            assertEquals(text.length(), text.longLength());
+            assertEquals(array.length(), array.longLength());
+            assertEquals(map.size(), map.longSize());
+            assertEquals(xmlText.length(), xmlText.longLength());
+            assertEquals(fragment.length(), fragment.longLength());
+        }
+    }
+
+    @Test
+    public void testSizesPastIntRangeThrow() {
+        assertEquals(Integer.MAX_VALUE, Sizes.toInt(Integer.MAX_VALUE, "longLength"));
+        try {
+            Sizes.toInt(1L << 32, "longLength");
+            fail("Expected ArithmeticException");
+        } catch (ArithmeticException e) {
+            assertTrue(e.getMessage().contains("4294967296"));
+            assertTrue(e.getMessage().contains("longLength()"));
+        }
+    }
+}
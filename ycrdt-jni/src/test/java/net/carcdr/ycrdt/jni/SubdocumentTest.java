@@ -6,6 +6,8 @@ import net.carcdr.ycrdt.YArray;
 import net.carcdr.ycrdt.YMap;
 import net.carcdr.ycrdt.YXmlElement;
 
+import java.util.Arrays;
+
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertNotNull;
 import static org.junit.Assert.assertNull;
@@ -389,4 +391,90 @@ public class SubdocumentTest {
             }
         }
     }
+
+    // ==================== Deep Updates ====================
+
+    @Test
+    public void testDeepUpdateCarriesSubdocumentContent() {
+        byte[] deep;
+        byte[] shallow;
+        try (JniYDoc parent = new JniYDoc();
+             JniYDoc child = new JniYDoc();
+             JniYDoc grandchild = new JniYDoc();
+             YMap map = parent.getMap("map")) {
+            try (YText text = child.getText("content");
+                 YText nested = grandchild.getText("content")) {
+                text.push("child");
+                nested.push("grandchild");
+            }
+            try (YMap childMap = child.getMap("map")) {
+                childMap.setDoc("nested", grandchild);
+            }
+            map.setDoc("child", child);
+            deep = parent.encodeStateAsUpdateDeep();
+            shallow = parent.encodeStateAsUpdate();
+        }
+
+        try (JniYDoc restored = new JniYDoc();
+             YMap map = restored.getMap("map")) {
+            restored.applyUpdateDeep(deep);
+            try (YDoc child = map.getDoc("child");
+                 YText text = child.getText("content");
+                 YMap childMap = child.getMap("map");
+                 YDoc grandchild = childMap.getDoc("nested");
+                 YText nested = grandchild.getText("content")) {
+                assertEquals("child", text.toString());
+                assertEquals("grandchild", nested.toString());
+            }
+        }
+
+        try (JniYDoc restored = new JniYDoc();
+             YMap map = restored.getMap("map")) {
+            restored.applyUpdate(shallow);
+            try (YDoc child = map.getDoc("child");
+                 YText text = child.getText("content")) {
+                assertEquals("", text.toString());
+            }
+        }
+    }
+
+    @Test(expected = RuntimeException.class)
+    public void testApplyUpdateDeepRejectsPlainUpdate() {
+        try (JniYDoc source = new JniYDoc();
+             JniYDoc target = new JniYDoc();
+             YText text = source.getText("text")) {
+            text.push("Hello");
+            target.applyUpdateDeep(source.encodeStateAsUpdate());
+        }
+    }
+
+    // ==================== Parent Linkage Tests ====================
+
+    @Test
+    public void testSubdocumentParentGuidAndPath() {
+        try (JniYDoc parent = new JniYDoc();
+             YDoc child = new JniYDoc();
+             YDoc page = new JniYDoc();
+             YMap map = parent.getMap("docs");
+             YArray array = parent.getArray("pages")) {
+
+            assertNull(parent.getParentGuid());
+            assertNull(parent.getEmbeddedPath());
+
+            map.setDoc("child", child);
+            array.pushDoc(new JniYDoc());
+            array.pushDoc(page);
+
+            try (JniYDoc retrieved = (JniYDoc) map.getDoc("child")) {
+                assertEquals(parent.getGuid(), retrieved.getParentGuid());
+                JniTypePath path = retrieved.getEmbeddedPath();
+                assertEquals("docs", path.getRootName());
+                assertEquals(Arrays.asList("child"), path.getSegments());
+            }
+            try (JniYDoc retrieved = (JniYDoc) array.getDoc(1)) {
+                assertEquals(parent.getGuid(), retrieved.getParentGuid());
+                assertEquals("pages[1]", retrieved.getEmbeddedPath().toString());
+            }
+        }
+    }
 }
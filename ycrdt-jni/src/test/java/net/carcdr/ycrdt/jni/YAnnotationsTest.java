@@ -0,0 +1,100 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YMap;
+import net.carcdr.ycrdt.YSubscription;
+import net.carcdr.ycrdt.YText;
+
+import org.junit.Test;
+
+import java.util.ArrayList;
+import java.util.List;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertFalse;
+import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
+
+/**
+ * Unit tests for JniYAnnotations.
+ */
+public class YAnnotationsTest {
+
+    @Test
+    public void testAnnotationsFollowEdits() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             YMap store = doc.getMap("comments")) {
+            text.insert(0, "hello world");
+            JniYAnnotations comments = new JniYAnnotations(doc, text, store);
+            comments.add("world", 6, 11);
+            comments.add("hello", 0, 5);
+
+            text.insert(11, "!");
+            text.insert(0, "oh ");
+            assertEquals(new JniAnnotation("world", 9, 14), comments.get("world"));
+            List<JniAnnotation> all = comments.list();
+            assertEquals(2, all.size());
+            assertEquals("hello", all.get(0).getId());
+            assertEquals("oh ", text.toString().substring(0, all.get(0).getStart()));
+
+            comments.remove("hello");
+            assertNull(comments.get("hello"));
+            assertEquals(1, comments.list().size());
+
+            try {
+                comments.add("empty", 2, 2);
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testAnnotationsSyncToPeers() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             YMap store = doc.getMap("comments");
+             JniYDoc peer = new JniYDoc();
+             YText peerText = peer.getText("text");
+             YMap peerStore = peer.getMap("comments")) {
+            text.insert(0, "abcdef");
+            new JniYAnnotations(doc, text, store).add("c", 2, 4);
+            peer.applyUpdate(doc.encodeStateAsUpdate());
+            peerText.insert(0, "xyz");
+            assertEquals(new JniAnnotation("c", 5, 7),
+                new JniYAnnotations(peer, peerText, peerStore).get("c"));
+        }
+    }
+
+    @Test
+    public void testCollapseNotification() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             YMap store = doc.getMap("comments")) {
+            text.insert(0, "hello world");
+            JniYAnnotations comments = new JniYAnnotations(doc, text, store);
+            comments.add("a", 0, 5);
+            comments.add("b", 6, 11);
+
+            List<JniAnnotation> collapsed = new ArrayList<>();
+            try (YSubscription sub = comments.onCollapsed(collapsed::add)) {
+                text.delete(1, 3);
+                assertTrue(collapsed.isEmpty());
+                text.delete(0, 2);
+                assertEquals(1, collapsed.size());
+                assertEquals("a", collapsed.get(0).getId());
+                assertTrue(collapsed.get(0).isCollapsed());
+
+                // Reported only once
+                text.insert(0, "x");
+                assertEquals(1, collapsed.size());
+                assertFalse(comments.get("b").isCollapsed());
+            }
+            text.delete(0, text.length());
+            assertEquals(1, collapsed.size());
+            assertTrue(comments.get("b").isCollapsed());
+        }
+    }
+}
@@ -2,9 +2,13 @@ package net.carcdr.ycrdt.jni;
 
 import net.carcdr.ycrdt.YDoc;
 import net.carcdr.ycrdt.YArray;
+import net.carcdr.ycrdt.YEvent;
+import net.carcdr.ycrdt.YSubscription;
+import net.carcdr.ycrdt.YTransaction;
 
 import org.junit.Test;
 
+import static org.junit.Assert.assertArrayEquals;
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
@@ -50,6 +54,27 @@ public class YArrayTest {
         }
     }
 
+    @Test
+    public void testToPrimitiveArrays() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = doc.getArray("test")) {
+            assertTrue(java.util.Arrays.equals(new double[0], array.toDoubleArray()));
+            array.pushAll(java.util.Arrays.asList(1.0, 2L, 3));
+            assertTrue(java.util.Arrays.equals(new double[] {1.0, 2.0, 3.0}, array.toDoubleArray()));
+            assertArrayEquals(new long[] {1, 2, 3}, array.toLongArray());
+
+            array.pushDouble(4.5);
+            assertTrue(java.util.Arrays.equals(new double[] {1.0, 2.0, 3.0, 4.5}, array.toDoubleArray()));
+            assertNull(array.toLongArray());
+
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                array.pushString(txn, "five");
+                assertNull(array.toDoubleArray(txn));
+            }
+            assertNull(array.toDoubleArray());
+        }
+    }
+
     @Test
     public void testMixedTypes() {
         try (YDoc doc = new JniYDoc();
@@ -136,6 +161,19 @@ public class YArrayTest {
         }
     }
 
+    @Test
+    public void testGetJsonAt() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = doc.getArray("test")) {
+            array.pushString("Hello \"World\"");
+            array.pushDouble(42.5);
+            assertEquals("\"Hello \\\"World\\\"\"", array.getJsonAt(0));
+            assertEquals("42.5", array.getJsonAt(1));
+            assertNull(array.getJsonAt(2));
+            assertNull(array.getJsonAt(-1));
+        }
+    }
+
     @Test
     public void testGetOutOfBounds() {
         try (YDoc doc = new JniYDoc();
@@ -392,4 +430,94 @@ public class YArrayTest {
             assertTrue(json.contains("D"));
         }
     }
+
+    @Test
+    public void testInsertAll() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = (JniYArray) doc.getArray("test")) {
+            array.pushString("end");
+            array.insertAll(0, java.util.Arrays.asList("a", 1.5, true, null));
+            array.pushAll(java.util.Collections.singletonList("last"));
+            assertEquals("[\"a\",1.5,true,null,\"end\",\"last\"]", array.toJson());
+
+            try {
+                array.insertAll(0, java.util.Arrays.asList("b", new Object()));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected: nothing is inserted when any value is unsupported
+            }
+            assertEquals(6, array.length());
+
+            try {
+                array.insertAll(7, java.util.Collections.singletonList("c"));
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testInsertPrimitiveArrays() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = doc.getArray("test")) {
+            array.pushAll(new long[] {1, 2});
+            array.insertAll(1, new double[] {1.5});
+            array.pushAll(new boolean[] {true, false});
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                array.insertAll(txn, 0, new long[0]);
+                array.pushAll(txn, new double[] {3.25});
+            }
+            assertEquals("[1,1.5,2,true,false,3.25]", array.toJson());
+            assertNull(array.toDoubleArray());
+
+            try {
+                array.insertAll(7, new double[] {1});
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+            try {
+                array.pushAll((long[]) null);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+            assertEquals(6, array.length());
+        }
+    }
+
+    @Test
+    public void testReplace() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = (JniYArray) doc.getArray("test")) {
+            array.pushAll(java.util.Arrays.asList("a", "b", "c"));
+            java.util.List<YEvent> events = new java.util.ArrayList<>();
+            try (YSubscription sub = array.observe(events::add)) {
+                array.replace(1, 2L);
+            }
+            assertEquals("[\"a\",2,\"c\"]", array.toJson());
+            assertEquals(1, events.size());
+
+            try (YTransaction txn = doc.beginTransaction()) {
+                array.replace(txn, 2, true);
+                array.replace(txn, 0, null);
+            }
+            assertEquals("[null,2,true]", array.toJson());
+
+            try {
+                array.replace(0, new Object());
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected: the old element is kept
+            }
+            try {
+                array.replace(3, "d");
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+            assertEquals("[null,2,true]", array.toJson());
+        }
+    }
 }
@@ -1,13 +1,30 @@
 package net.carcdr.ycrdt.jni;
 
 import net.carcdr.ycrdt.YDoc;
+import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YText;
+import net.carcdr.ycrdt.YTransaction;
 
 import org.junit.Test;
 
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.Collections;
+import java.util.List;
+import java.util.Map;
+import java.util.Set;
+import java.util.SplittableRandom;
+import java.util.UUID;
+import java.util.concurrent.CountDownLatch;
+import java.util.concurrent.TimeUnit;
+import java.util.concurrent.atomic.AtomicReference;
+
+import static org.junit.Assert.assertArrayEquals;
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
+import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertThrows;
 import static org.junit.Assert.assertTrue;
 
 /**
@@ -112,6 +129,53 @@ public class YDocTest {
         }
     }
 
+    @Test
+    public void testHasXmlSupport() {
+        // The default build enables the xml cargo feature
+        assertTrue(JniYDoc.hasXmlSupport());
+    }
+
+    @Test
+    public void testNativeVersionInfo() {
+        Map<String, Object> info = JniYDoc.getNativeVersionInfo();
+        assertNotNull(info.get("version"));
+        assertNotNull(info.get("yrsVersion"));
+        assertTrue(((List<?>) info.get("features")).contains("xml"));
+        assertTrue(((List<?>) info.get("updateEncodings")).contains("v1"));
+    }
+
+    @Test
+    public void testNativeTracingFollowsFeature() {
+        List<?> features = (List<?>) JniYDoc.getNativeVersionInfo().get("features");
+        boolean available = features.contains("trace-jni");
+        try {
+            assertEquals(available, JniYDoc.setNativeTracing(true));
+            assertEquals(available, JniYDoc.isNativeTracing());
+        } finally {
+            assertFalse(JniYDoc.setNativeTracing(false));
+        }
+        assertFalse(JniYDoc.isNativeTracing());
+    }
+
+    @Test
+    public void testCallbackClassLoader() throws Exception {
+        JniYDoc.setCallbackClassLoader(getClass().getClassLoader());
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            List<Object> events = new ArrayList<>();
+            try (YSubscription sub = text.observe(events::add)) {
+                // Commit on a thread without a context class loader
+                Thread writer = new Thread(() -> text.insert(0, "abc"));
+                writer.setContextClassLoader(null);
+                writer.start();
+                writer.join();
+            }
+            assertEquals(1, events.size());
+        } finally {
+            JniYDoc.setCallbackClassLoader(null);
+        }
+    }
+
     @Test
     public void testClose() {
         YDoc doc = new JniYDoc();
@@ -255,6 +319,25 @@ public class YDocTest {
         doc.encodeDiff(sv);
     }
 
+    @Test
+    public void testEstimateUpdateSize() {
+        try (JniYDoc doc1 = new JniYDoc();
+             JniYDoc doc2 = new JniYDoc();
+             YText text1 = doc1.getText("test")) {
+            text1.insert(0, "Hello");
+            doc2.applyUpdate(doc1.encodeStateAsUpdate());
+            text1.insert(5, " World");
+
+            byte[] empty = new byte[] {0};
+            byte[] partial = doc2.encodeStateVector();
+            byte[] full = doc1.encodeStateVector();
+            for (byte[] sv : new byte[][] {empty, partial, full}) {
+                assertEquals(doc1.encodeDiff(sv).length, doc1.estimateUpdateSize(sv));
+            }
+            assertTrue(doc1.estimateUpdateSize(partial) < doc1.estimateUpdateSize(empty));
+        }
+    }
+
     @Test
     public void testMergeUpdates() {
         try (YDoc doc1 = new JniYDoc();
@@ -428,6 +511,26 @@ public class YDocTest {
         }
     }
 
+    @Test
+    public void testSyncWithAndAbsorb() {
+        try (JniYDoc a = new JniYDoc(1);
+             JniYDoc b = new JniYDoc(2);
+             JniYText aText = a.getText("content");
+             JniYText bText = b.getText("content")) {
+            aText.insert(0, "shared");
+            b.applyUpdate(a.encodeStateAsUpdate());
+            aText.push(" a");
+            bText.insert(0, "b ");
+
+            byte[] diff = a.syncWith(b.encodeStateVector());
+            byte[] reply = b.absorb(diff);
+            a.applyUpdate(reply);
+
+            assertEquals(aText.toString(), bText.toString());
+            assertEquals(a.getStateVectorMap(), b.getStateVectorMap());
+        }
+    }
+
     @Test
     public void testMergeMultipleUpdatesReducesSize() {
         try (YDoc doc = new JniYDoc();
@@ -458,4 +561,771 @@ public class YDocTest {
             }
         }
     }
+
+    @Test
+    public void testExportTreeRoots() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = doc.getMap("settings");
+             JniYArray array = doc.getArray("list");
+             JniYText text = doc.getText("body");
+             JniYDoc subdoc = new JniYDoc()) {
+            map.setString("title", "Notes");
+            map.setDouble("count", 2);
+            map.setDoc("child", subdoc);
+            array.pushString("a");
+            array.pushDouble(1.5);
+            text.insert(0, "hello");
+
+            Map<String, JniTreeNode> roots = doc.exportTree(1);
+            assertEquals(List.of("body", "list", "settings"), List.copyOf(roots.keySet()));
+
+            JniTreeNode body = roots.get("body");
+            assertEquals(JniTreeNode.Kind.TEXT, body.getKind());
+            assertEquals("hello", body.getText());
+            assertFalse(body.isTruncated());
+
+            JniTreeNode list = roots.get("list");
+            assertEquals(JniTreeNode.Kind.ARRAY, list.getKind());
+            assertEquals(List.of("a", 1.5), list.getItems());
+
+            JniTreeNode settings = roots.get("settings");
+            assertEquals(JniTreeNode.Kind.MAP, settings.getKind());
+            assertEquals(List.of("child", "count", "title"),
+                List.copyOf(settings.getEntries().keySet()));
+            assertEquals("Notes", settings.getEntries().get("title"));
+            assertEquals(2.0, settings.getEntries().get("count"));
+            JniTreeNode child = (JniTreeNode) settings.getEntries().get("child");
+            assertEquals(JniTreeNode.Kind.DOC, child.getKind());
+            assertEquals(subdoc.getGuid(), child.getName());
+        }
+    }
+
+    @Test
+    public void testExportTreeDepthLimit() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("doc")) {
+            fragment.insertElement(0, "p");
+            try (JniYXmlElement p = fragment.getElement(0)) {
+                p.setAttribute("class", "intro");
+                try (JniYXmlText t = p.insertText(0)) {
+                    t.insert(0, "hi");
+                }
+            }
+
+            JniTreeNode root = doc.exportTree(0).get("doc");
+            assertEquals(JniTreeNode.Kind.XML_FRAGMENT, root.getKind());
+            assertTrue(root.isTruncated());
+            assertTrue(root.getItems().isEmpty());
+
+            JniTreeNode p = (JniTreeNode) doc.exportTree(1).get("doc").getItems().get(0);
+            assertEquals(JniTreeNode.Kind.XML_ELEMENT, p.getKind());
+            assertEquals("p", p.getName());
+            assertTrue(p.isTruncated());
+            assertTrue(p.getAttributes().isEmpty());
+
+            p = (JniTreeNode) doc.exportTree(2).get("doc").getItems().get(0);
+            assertFalse(p.isTruncated());
+            assertEquals(Map.of("class", "intro"), p.getAttributes());
+            JniTreeNode t = (JniTreeNode) p.getItems().get(0);
+            assertEquals(JniTreeNode.Kind.XML_TEXT, t.getKind());
+            assertTrue(t.isTruncated());
+            assertNull(t.getText());
+
+            t = (JniTreeNode) ((JniTreeNode) doc.exportTree(3).get("doc").getItems().get(0))
+                .getItems().get(0);
+            assertEquals("hi", t.getText());
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testExportTreeNegativeDepth() {
+        try (JniYDoc doc = new JniYDoc()) {
+            doc.exportTree(-1);
+        }
+    }
+
+    @Test
+    public void testReadOnlyRejectsLocalEdits() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYMap map = doc.getMap("map");
+             JniYArray array = doc.getArray("array");
+             JniYXmlFragment fragment = doc.getXmlFragment("xml")) {
+            text.insert(0, "hello");
+            doc.setAccessMode(JniYDoc.AccessMode.READ_ONLY);
+            assertTrue(doc.isReadOnly());
+
+            assertThrows(IllegalStateException.class, () -> text.insert(0, "x"));
+            assertThrows(IllegalStateException.class, () -> text.delete(0, 1));
+            assertThrows(IllegalStateException.class, () -> map.setString("k", "v"));
+            assertThrows(IllegalStateException.class, () -> map.clear());
+            assertThrows(IllegalStateException.class, () -> array.pushDouble(1));
+            assertThrows(IllegalStateException.class, () -> fragment.insertElement(0, "p"));
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                assertThrows(IllegalStateException.class, () -> text.insert(txn, 0, "x"));
+            }
+
+            assertEquals("hello", text.toString());
+            assertEquals(0, map.size());
+
+            doc.setAccessMode(JniYDoc.AccessMode.READ_WRITE);
+            assertFalse(doc.isReadOnly());
+            text.insert(5, "!");
+            assertEquals("hello!", text.toString());
+        }
+    }
+
+    @Test
+    public void testReadOnlyApplyUpdate() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText sourceText = source.getText("text");
+             JniYDoc replica = new JniYDoc();
+             JniYText replicaText = replica.getText("text")) {
+            sourceText.insert(0, "shared");
+            byte[] update = source.encodeStateAsUpdate();
+
+            replica.setAccessMode(JniYDoc.AccessMode.READ_ONLY);
+            assertThrows(IllegalStateException.class, () -> replica.applyUpdate(update));
+            assertEquals("", replicaText.toString());
+
+            replica.setAccessMode(JniYDoc.AccessMode.READ_ONLY_ALLOW_UPDATES);
+            assertEquals(JniYDoc.AccessMode.READ_ONLY_ALLOW_UPDATES, replica.getAccessMode());
+            replica.applyUpdate(update);
+            assertEquals("shared", replicaText.toString());
+            assertThrows(IllegalStateException.class, () -> replicaText.insert(0, "x"));
+        }
+    }
+
+    @Test
+    public void testApplyUpdateFiltered() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText notes = source.getText("notes");
+             JniYMap salary = source.getMap("salary");
+             JniYDoc target = new JniYDoc();
+             JniYText targetNotes = target.getText("notes");
+             JniYMap targetSalary = target.getMap("salary")) {
+            notes.insert(0, "public");
+            salary.setDouble("amount", 100);
+            List<String> asked = new java.util.ArrayList<>();
+
+            JniFilteredUpdate result = target.applyUpdateFiltered(source.encodeStateAsUpdate(),
+                root -> {
+                    asked.add(root);
+                    return !root.equals("salary");
+                });
+
+            assertEquals(List.of("notes", "salary"), asked);
+            assertEquals(Set.of("notes"), result.getAllowedRoots());
+            assertEquals(Set.of("salary"), result.getDeniedRoots());
+            assertFalse(result.isFullyApplied());
+            assertEquals("public", targetNotes.toString());
+            assertEquals(0, targetSalary.size());
+
+            // Later allowed changes from the same peer still apply
+            byte[] before = target.encodeStateVector();
+            notes.insert(6, "!");
+            result = target.applyUpdateFiltered(source.encodeDiff(before), root -> true);
+            assertTrue(result.isFullyApplied());
+            assertEquals("public!", targetNotes.toString());
+        }
+    }
+
+    @Test
+    public void testApplyUpdateFilteredPropagatesPredicateException() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("text");
+             JniYDoc target = new JniYDoc();
+             JniYText targetText = target.getText("text")) {
+            text.insert(0, "hello");
+            byte[] update = source.encodeStateAsUpdate();
+            IllegalStateException e = assertThrows(IllegalStateException.class,
+                () -> target.applyUpdateFiltered(update, root -> {
+                    throw new IllegalStateException("denied");
+                }));
+            assertEquals("denied", e.getMessage());
+            assertEquals("", targetText.toString());
+        }
+    }
+
+    @Test
+    public void testEncodeForRoots() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText notes = source.getText("notes");
+             JniYMap salary = source.getMap("salary");
+             JniYDoc target = new JniYDoc();
+             JniYText targetNotes = target.getText("notes");
+             JniYMap targetSalary = target.getMap("salary")) {
+            notes.insert(0, "public");
+            salary.setDouble("amount", 100);
+
+            target.applyUpdate(source.encodeStateAsUpdateForRoots(Set.of("notes")));
+            assertEquals("public", targetNotes.toString());
+            assertEquals(0, targetSalary.size());
+
+            byte[] before = target.encodeStateVector();
+            notes.insert(6, "!");
+            salary.setDouble("bonus", 10);
+            target.applyUpdate(source.encodeDiffForRoots(before, Set.of("notes")));
+            assertEquals("public!", targetNotes.toString());
+            assertEquals(0, targetSalary.size());
+            assertArrayEquals(source.encodeStateVector(), target.encodeStateVector());
+        }
+    }
+
+    @Test
+    public void testPendingStateForOutOfOrderUpdate() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("text");
+             JniYDoc target = new JniYDoc();
+             JniYText targetText = target.getText("text")) {
+            text.insert(0, "a");
+            byte[] first = source.encodeStateAsUpdate();
+            byte[] afterFirst = source.encodeStateVector();
+            text.insert(1, "bc");
+            byte[] second = source.encodeDiff(afterFirst);
+
+            assertFalse(target.hasMissingUpdates());
+            assertNull(target.getPendingState());
+
+            target.applyUpdate(second);
+            assertTrue(target.hasMissingUpdates());
+            JniPendingState pending = target.getPendingState();
+            assertNotNull(pending);
+            assertEquals(1, pending.getStructCount());
+            assertTrue(pending.getByteSize() > 0);
+            assertEquals("", targetText.toString());
+
+            // The peer can answer the missing state vector with exactly the gap
+            target.applyUpdate(source.encodeDiff(pending.getMissingStateVector()));
+            assertFalse(target.hasMissingUpdates());
+            assertEquals("abc", targetText.toString());
+        }
+    }
+
+    @Test
+    public void testApplyUpdateWithResult() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("text");
+             JniYDoc target = new JniYDoc();
+             JniYText targetText = target.getText("text")) {
+            text.insert(0, "a");
+            byte[] first = source.encodeStateAsUpdate();
+            byte[] afterFirst = source.encodeStateVector();
+            text.insert(1, "bc");
+            byte[] second = source.encodeDiff(afterFirst);
+
+            JniApplyResult pending = target.applyUpdateWithResult(second);
+            assertEquals(JniApplyResult.Status.PENDING, pending.getStatus());
+            assertTrue(pending.isPending());
+            assertTrue(pending.getMissingStateVector().length > 0);
+            assertEquals(JniApplyResult.Status.DUPLICATE,
+                target.applyUpdateWithResult(second).getStatus());
+
+            JniApplyResult applied = target.applyUpdateWithResult(first);
+            assertEquals(JniApplyResult.Status.APPLIED, applied.getStatus());
+            assertEquals("abc", targetText.toString());
+            assertEquals(JniApplyResult.Status.DUPLICATE,
+                target.applyUpdateWithResult(source.encodeStateAsUpdate()).getStatus());
+
+            byte[] beforeDelete = source.encodeStateVector();
+            text.delete(0, 1);
+            try (JniYTransaction txn = target.beginTransaction()) {
+                assertEquals(JniApplyResult.Status.APPLIED,
+                    target.applyUpdateWithResult(txn, source.encodeDiff(beforeDelete))
+                        .getStatus());
+            }
+            assertEquals("bc", targetText.toString());
+        }
+    }
+
+    @Test
+    public void testStoreStats() {
+        try (JniYDoc source = JniYDoc.withoutGarbageCollection(7);
+             JniYText text = source.getText("text");
+             JniYDoc target = new JniYDoc()) {
+            JniStoreStats empty = source.getStoreStats();
+            assertEquals(0, empty.getBlockCount());
+            assertTrue(empty.getClients().isEmpty());
+
+            text.insert(0, "hello");
+            text.insert(5, " world");
+            text.delete(0, 6);
+            JniStoreStats stats = source.getStoreStats();
+            assertEquals(1, stats.getClients().size());
+            JniStoreStats.Client client = stats.getClients().get(0);
+            assertEquals(7, client.getClientId());
+            assertEquals(11, client.getClock());
+            assertEquals(2, client.getBlockCount());
+            assertEquals(1, client.getDeletedBlockCount());
+            assertEquals(0, stats.getGcBlockCount());
+            assertEquals(6, stats.getDeletedLength());
+            assertEquals(0, stats.getPendingStructCount());
+
+            byte[] sv = source.encodeStateVector();
+            text.insert(0, "!");
+            target.applyUpdate(source.encodeDiff(sv));
+            target.queueUpdate(source.encodeDiff(sv));
+            JniStoreStats targetStats = target.getStoreStats();
+            assertEquals(1, targetStats.getPendingStructCount());
+            assertEquals(1, targetStats.getStagedUpdateCount());
+            assertEquals(0, targetStats.getBlockCount());
+        }
+    }
+
+    @Test
+    public void testQueueUpdateStagesUntilDependenciesArrive() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("text");
+             JniYDoc target = new JniYDoc();
+             JniYText targetText = target.getText("text")) {
+            List<byte[]> updates = new ArrayList<>();
+            for (String s : new String[] {"a", "b", "c"}) {
+                byte[] sv = source.encodeStateVector();
+                text.insert(text.length(), s);
+                updates.add(source.encodeDiff(sv));
+            }
+            List<int[]> flushes = new ArrayList<>();
+            target.setUpdateFlushListener(
+                (doc, flushed, remaining) -> flushes.add(new int[] {flushed, remaining}));
+
+            assertFalse(target.queueUpdate(updates.get(2)));
+            assertFalse(target.queueUpdate(updates.get(1)));
+            assertEquals(2, target.getQueuedUpdateCount());
+            assertFalse(target.hasMissingUpdates());
+            assertTrue(flushes.isEmpty());
+
+            assertTrue(target.queueUpdate(updates.get(0)));
+            assertEquals("abc", targetText.toString());
+            assertEquals(0, target.getQueuedUpdateCount());
+            assertEquals(1, flushes.size());
+            assertArrayEquals(new int[] {2, 0}, flushes.get(0));
+        }
+    }
+
+    @Test
+    public void testClearQueuedUpdates() {
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("text");
+             JniYDoc target = new JniYDoc()) {
+            text.insert(0, "a");
+            byte[] sv = source.encodeStateVector();
+            text.insert(1, "b");
+            assertFalse(target.queueUpdate(source.encodeDiff(sv)));
+            assertEquals(1, target.clearQueuedUpdates());
+            assertEquals(0, target.getQueuedUpdateCount());
+        }
+    }
+
+    @Test
+    public void testStringPolicy() {
+        // An emoji followed by a lone high surrogate
+        String invalid = "a\uD83D\uDE00\uD83D";
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYMap map = doc.getMap("map")) {
+            assertEquals(JniYDoc.StringPolicy.LOSSY, doc.getStringPolicy());
+            text.insert(0, invalid);
+            assertEquals("a\uD83D\uDE00\uFFFD", text.toString());
+
+            doc.setStringPolicy(JniYDoc.StringPolicy.STRICT);
+            assertEquals(JniYDoc.StringPolicy.STRICT, doc.getStringPolicy());
+            assertThrows(IllegalArgumentException.class, () -> text.insert(0, invalid));
+            assertThrows(IllegalArgumentException.class, () -> map.setString(invalid, "v"));
+            text.insert(0, "ok \uD83D\uDE00 ");
+            assertEquals("ok \uD83D\uDE00 a\uD83D\uDE00\uFFFD", text.toString());
+        }
+    }
+
+    @Test
+    public void testStateVectorMap() {
+        try (JniYDoc doc1 = new JniYDoc(1);
+             JniYDoc doc2 = new JniYDoc(2);
+             JniYText text1 = doc1.getText("text");
+             JniYText text2 = doc2.getText("text")) {
+            assertTrue(doc1.getStateVectorMap().isEmpty());
+            text1.insert(0, "abc");
+            text2.insert(0, "de");
+            doc1.applyUpdate(doc2.encodeStateAsUpdate());
+
+            Map<Long, Long> stateVector = doc1.getStateVectorMap();
+            assertEquals(2, stateVector.size());
+            assertEquals(Long.valueOf(3), stateVector.get(1L));
+            assertEquals(Long.valueOf(2), stateVector.get(2L));
+        }
+    }
+
+    @Test
+    public void testRootActivity() {
+        try (JniYDoc doc = new JniYDoc(5);
+             JniYDoc remote = new JniYDoc(6);
+             JniYText text = doc.getText("text");
+             JniYMap map = doc.getMap("map");
+             JniYMap remoteMap = remote.getMap("map")) {
+            text.insert(0, "untracked");
+            assertFalse(doc.isRootActivityTracking());
+            doc.setRootActivityTracking(true);
+            assertTrue(doc.isRootActivityTracking());
+
+            long before = System.currentTimeMillis();
+            remoteMap.setString("k", "v");
+            doc.applyUpdate(remote.encodeStateAsUpdate());
+
+            Map<String, JniRootActivity> activity = doc.getRootActivity();
+            assertEquals(1, activity.size());
+            JniRootActivity mapActivity = activity.get("map");
+            assertArrayEquals(new long[] {6}, mapActivity.getLastModifiedBy());
+            assertTrue(mapActivity.getLastModifiedAt() >= before);
+
+            text.delete(0, 2);
+            assertEquals(0, doc.getRootActivity().get("text").getLastModifiedBy().length);
+
+            doc.setRootActivityTracking(false);
+            text.insert(0, "x");
+            assertEquals(2, doc.getRootActivity().size());
+        }
+    }
+
+    @Test
+    public void testSubmitBatch() throws Exception {
+        try (JniYDoc doc = new JniYDoc();
+             JniYDoc replica = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYMap map = doc.getMap("map")) {
+            int threads = 4;
+            CountDownLatch done = new CountDownLatch(threads);
+            List<byte[]> updates = Collections.synchronizedList(new ArrayList<>());
+            for (int t = 0; t < threads; t++) {
+                String key = "k" + t;
+                new Thread(() -> doc.submitBatch(Arrays.asList(
+                    JniBatchOp.textInsert("text", 0, "ab"),
+                    JniBatchOp.mapSet("map", key, 1L)), (d, result) -> {
+                        assertTrue(result.isSuccess());
+                        assertEquals(2, result.getAppliedCount());
+                        updates.add(result.getUpdate());
+                        done.countDown();
+                    })).start();
+            }
+            assertTrue(done.await(10, TimeUnit.SECONDS));
+            assertEquals(8, text.length());
+            assertEquals(4, map.size());
+
+            for (byte[] update : updates) {
+                replica.applyUpdate(update);
+            }
+            try (JniYText replicaText = replica.getText("text")) {
+                assertEquals(text.toString(), replicaText.toString());
+            }
+        }
+    }
+
+    @Test
+    public void testSubmitBatchStopsAtInvalidOp() throws Exception {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = doc.getArray("array")) {
+            AtomicReference<JniBatchResult> result = new AtomicReference<>();
+            CountDownLatch done = new CountDownLatch(1);
+            doc.submitBatch(Arrays.asList(
+                JniBatchOp.arrayInsert("array", 0, "x"),
+                JniBatchOp.arrayDelete("array", 0, 5),
+                JniBatchOp.arrayInsert("array", 1, "y")), (d, r) -> {
+                    result.set(r);
+                    done.countDown();
+                });
+            assertTrue(done.await(10, TimeUnit.SECONDS));
+            assertFalse(result.get().isSuccess());
+            assertEquals(1, result.get().getAppliedCount());
+            assertNotNull(result.get().getError());
+            assertEquals(1, array.length());
+        }
+    }
+
+    @Test
+    public void testDecodeErrorNamesOffset() {
+        byte[] update;
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("text")) {
+            text.insert(0, "hello world");
+            update = source.encodeStateAsUpdate();
+        }
+        byte[] truncated = Arrays.copyOf(update, update.length - 4);
+        try (JniYDoc doc = new JniYDoc()) {
+            assertFalse(doc.isDecodeFailureLogging());
+            doc.setDecodeFailureLogging(true);
+            assertTrue(doc.isDecodeFailureLogging());
+            RuntimeException e = assertThrows(RuntimeException.class,
+                () -> doc.applyUpdate(truncated));
+            assertTrue(e.getMessage(), e.getMessage().contains("of " + truncated.length));
+            assertTrue(e.getMessage(), e.getMessage().contains("["));
+        }
+    }
+
+    @Test
+    public void testApplySyncMessage() {
+        try (JniYDoc server = new JniYDoc();
+             JniYDoc client = new JniYDoc();
+             JniYText serverText = server.getText("text");
+             JniYText clientText = client.getText("text")) {
+            serverText.insert(0, "hello");
+
+            JniSyncMessage step1 = server.applySyncMessage(client.encodeSyncStep1());
+            assertEquals(JniSyncMessage.Kind.SYNC_STEP_1, step1.getKind());
+            assertTrue(step1.hasReply());
+
+            JniSyncMessage step2 = client.applySyncMessage(step1.getReply());
+            assertEquals(JniSyncMessage.Kind.SYNC_STEP_2, step2.getKind());
+            assertFalse(step2.hasReply());
+            assertEquals("hello", clientText.toString());
+
+            byte[] before = server.encodeStateVector();
+            serverText.insert(5, " world");
+            byte[] update = server.encodeDiff(before);
+            JniSyncMessage applied = client.applySyncMessage(JniYDoc.encodeSyncUpdate(update));
+            assertEquals(JniSyncMessage.Kind.UPDATE, applied.getKind());
+            assertEquals("hello world", clientText.toString());
+
+            JniSyncMessage query = client.applySyncMessage(new byte[] {3});
+            assertEquals(JniSyncMessage.Kind.AWARENESS_QUERY, query.getKind());
+            assertNull(query.getPayload());
+        }
+    }
+
+    @Test
+    public void testDeterministicDocument() {
+        byte[] first = null;
+        for (int run = 0; run < 2; run++) {
+            try (JniYDoc doc = JniYDoc.deterministic(7);
+                 JniYMap map = doc.getMap("map")) {
+                assertTrue(doc.isDeterministic());
+                assertEquals(7, doc.getClientId());
+                for (String key : new String[] {"zeta", "alpha", "mid", "beta", "omega"}) {
+                    map.setString(key, key.toUpperCase());
+                }
+                assertArrayEquals(new String[] {"alpha", "beta", "mid", "omega", "zeta"}, map.keys());
+                assertEquals("{\"alpha\":\"ALPHA\",\"beta\":\"BETA\",\"mid\":\"MID\","
+                    + "\"omega\":\"OMEGA\",\"zeta\":\"ZETA\"}", map.toJson());
+                byte[] update = doc.encodeStateAsUpdate();
+                if (first == null) {
+                    first = update;
+                } else {
+                    assertArrayEquals(first, update);
+                }
+            }
+        }
+        try (JniYDoc doc = new JniYDoc()) {
+            assertFalse(doc.isDeterministic());
+        }
+    }
+
+    @Test
+    public void testEncodeStateFromSnapshot() {
+        try (JniYDoc doc = JniYDoc.withoutGarbageCollection();
+             JniYText text = doc.getText("text")) {
+            text.insert(0, "version one");
+            byte[] snapshot = doc.encodeSnapshot();
+            text.delete(0, 8);
+            text.insert(0, "two: ");
+
+            byte[] update = doc.encodeStateFromSnapshot(snapshot);
+            try (JniYDoc view = new JniYDoc();
+                 JniYText viewText = view.getText("text")) {
+                view.applyUpdate(update);
+                assertEquals("version one", viewText.toString());
+            }
+            assertThrows(IllegalArgumentException.class,
+                () -> doc.encodeStateFromSnapshot(new byte[] {(byte) 0xff}));
+        }
+        try (JniYDoc doc = new JniYDoc()) {
+            byte[] snapshot = doc.encodeSnapshot();
+            assertThrows(IllegalStateException.class, () -> doc.encodeStateFromSnapshot(snapshot));
+        }
+    }
+
+    @Test
+    public void testReleaseAllRoots() {
+        try (JniYDoc doc = new JniYDoc()) {
+            JniYText text = doc.getText("text");
+            JniYMap map = doc.getMap("map");
+            JniYText closedText = doc.getText("text");
+            closedText.close();
+            text.insert(0, "kept");
+            assertEquals(List.of("map", "text"), doc.getOpenRootNames());
+
+            assertEquals(2, doc.releaseAllRoots());
+            assertEquals(List.of(), doc.getOpenRootNames());
+            assertThrows(IllegalStateException.class, text::length);
+            assertThrows(IllegalStateException.class, map::size);
+            text.close();
+            map.close();
+
+            try (JniYText again = doc.getText("text")) {
+                assertEquals("kept", again.toString());
+                assertEquals(List.of("text"), doc.getOpenRootNames());
+            }
+            assertEquals(List.of(), doc.getOpenRootNames());
+        }
+    }
+
+    @Test
+    public void testRootLookupsShareHandle() {
+        try (JniYDoc doc = new JniYDoc()) {
+            JniYText first = doc.getText("content");
+            JniYText second = doc.getText("content");
+            assertEquals(first.getNativePtr(), second.getNativePtr());
+            assertEquals(List.of("content", "content"), doc.getOpenRootNames());
+
+            first.close();
+            second.insert(0, "still open");
+            assertEquals(List.of("content"), doc.getOpenRootNames());
+            second.close();
+            assertEquals(List.of(), doc.getOpenRootNames());
+
+            try (JniYText again = doc.getText("content")) {
+                assertEquals("still open", again.toString());
+            }
+        }
+    }
+
+    @Test
+    public void testWithRandomIsReproducible() {
+        try (JniYDoc first = JniYDoc.withRandom(new SplittableRandom(42));
+             JniYDoc second = JniYDoc.withRandom(new SplittableRandom(42));
+             JniYDoc other = JniYDoc.withRandom(new SplittableRandom(7))) {
+            assertEquals(first.getClientId(), second.getClientId());
+            assertEquals(first.getGuid(), second.getGuid());
+            assertFalse(first.getGuid().equals(other.getGuid()));
+            assertEquals(4, UUID.fromString(first.getGuid()).version());
+
+            for (JniYDoc doc : List.of(first, second)) {
+                try (JniYText text = doc.getText("text")) {
+                    text.insert(0, "same");
+                }
+            }
+            assertArrayEquals(first.encodeStateAsUpdate(), second.encodeStateAsUpdate());
+        }
+    }
+
+    @Test
+    public void testRootPaths() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("content");
+             JniYArray array = doc.getArray("items");
+             JniYMap map = doc.getMap("meta")) {
+            JniTypePath path = text.getPath();
+            assertEquals("content", path.getRootName());
+            assertTrue(path.isRoot());
+            assertNull(path.getParent());
+            assertEquals("items", array.getPath().toString());
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                assertEquals(List.of(), map.getPath(txn).getSegments());
+            }
+        }
+    }
+
+    @Test
+    public void testReset() {
+        try (JniYDoc doc = new JniYDoc(7)) {
+            doc.setStringPolicy(JniYDoc.StringPolicy.STRICT);
+            JniYText text = doc.getText("text");
+            text.insert(0, "old content");
+            List<byte[]> updates = new ArrayList<>();
+            YSubscription sub = doc.observeUpdateV1((update, origin) -> updates.add(update));
+            String guid = doc.getGuid();
+
+            doc.reset(9);
+            assertEquals(9, doc.getClientId());
+            assertFalse(guid.equals(doc.getGuid()));
+            assertEquals(JniYDoc.StringPolicy.STRICT, doc.getStringPolicy());
+            assertThrows(IllegalStateException.class, text::length);
+            sub.close();
+
+            try (JniYText fresh = doc.getText("text")) {
+                assertEquals("", fresh.toString());
+                fresh.insert(0, "new");
+                assertEquals("new", fresh.toString());
+            }
+            assertTrue(updates.isEmpty());
+
+            try (YTransaction txn = doc.beginTransaction()) {
+                assertThrows(IllegalStateException.class, doc::reset);
+            }
+            assertThrows(IllegalArgumentException.class, () -> doc.reset(-1));
+        }
+    }
+
+    @Test
+    public void testChangeCounter() {
+        try (JniYDoc doc = new JniYDoc(); JniYDoc remote = new JniYDoc()) {
+            assertEquals(0, doc.getChangeCounter());
+            try (JniYText text = doc.getText("text")) {
+                text.insert(0, "hello");
+                assertEquals(1, doc.getChangeCounter());
+
+                try (JniYTransaction txn = doc.beginTransaction()) {
+                    text.insert(txn, 5, " world");
+                    text.delete(txn, 0, 1);
+                }
+                assertEquals(2, doc.getChangeCounter());
+
+                doc.beginTransaction().close();
+                assertEquals("ello world", text.toString());
+                assertEquals(2, doc.getChangeCounter());
+
+                try (JniYMap map = remote.getMap("map")) {
+                    map.setString("key", "value");
+                }
+                doc.applyUpdate(remote.encodeStateAsUpdate());
+                assertEquals(3, doc.getChangeCounter());
+                doc.applyUpdate(remote.encodeStateAsUpdate());
+                assertEquals(3, doc.getChangeCounter());
+            }
+
+            doc.reset();
+            assertEquals(4, doc.getChangeCounter());
+            try (JniYText text = doc.getText("text")) {
+                text.insert(0, "again");
+            }
+            assertEquals(5, doc.getChangeCounter());
+        }
+    }
+
+    @Test
+    public void testValidateSchema() {
+        List<JniSchemaRule> schema = List.of(
+            JniSchemaRule.root("settings", JniTreeNode.Kind.MAP),
+            JniSchemaRule.key("settings", "title", JniYMap.ValueType.STRING),
+            JniSchemaRule.key("settings", "owner", null),
+            JniSchemaRule.optionalRoot("body", JniTreeNode.Kind.TEXT),
+            JniSchemaRule.noOtherRoots());
+        try (JniYDoc client = new JniYDoc(); JniYDoc server = new JniYDoc()) {
+            try (JniYMap settings = client.getMap("settings")) {
+                settings.setString("title", "Plan");
+                settings.setString("owner", "ann");
+            }
+            server.applyUpdate(client.encodeStateAsUpdate());
+            assertEquals(List.of(), server.validate(schema));
+
+            try (JniYMap settings = client.getMap("settings");
+                 JniYMap body = client.getMap("body");
+                 JniYArray junk = client.getArray("junk")) {
+                settings.setDouble("title", 3);
+                settings.remove("owner");
+                body.setString("k", "v");
+                junk.pushString("x");
+            }
+            server.applyUpdate(client.encodeStateAsUpdate());
+            List<String> violations = new ArrayList<>();
+            for (JniSchemaViolation violation : server.validate(schema)) {
+                violations.add(violation.toString());
+            }
+            assertEquals(List.of(
+                "settings/title: expected STRING, found NUMBER",
+                "settings/owner: required key is missing",
+                "body: expected TEXT, found map entries",
+                "junk: root is not in the schema"), violations);
+        }
+        assertThrows(IllegalArgumentException.class,
+            () -> JniSchemaRule.root("x", JniTreeNode.Kind.XML_TEXT));
+        assertThrows(IllegalArgumentException.class,
+            () -> JniSchemaRule.key("x", "k", JniYMap.ValueType.ABSENT));
+    }
 }
@@ -0,0 +1,93 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YChange;
+import net.carcdr.ycrdt.YSubscription;
+
+import org.junit.Test;
+
+import java.util.ArrayList;
+import java.util.List;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertThrows;
+import static org.junit.Assert.assertTrue;
+
+/**
+ * Unit tests for JniYJson.
+ */
+public class YJsonTest {
+
+    @Test
+    public void testPathOperations() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYJson state = doc.getJson("state")) {
+            state.add("/users", "[{\"name\":\"Ann\"}]");
+            state.add("/users/-", "{\"name\":\"Bob\",\"tags\":[]}");
+            state.add("/users/1/tags/0", "\"admin\"");
+            state.replace("/users/0/name", "\"Anne\"");
+            state.remove("/users/1/name");
+
+            assertEquals("{\"name\":\"Anne\"}", state.get("/users/0"));
+            assertEquals("[\"admin\"]", state.get("/users/1/tags"));
+            assertNull(state.get("/users/2"));
+            assertEquals("{\"users\":[{\"name\":\"Anne\"},{\"tags\":[\"admin\"]}]}",
+                state.toJson());
+
+            assertThrows(IllegalArgumentException.class, () -> state.replace("/missing", "1"));
+            assertThrows(IllegalArgumentException.class, () -> state.add("/users/9", "1"));
+            assertThrows(IllegalArgumentException.class, () -> state.add("users", "1"));
+            assertThrows(IllegalArgumentException.class, () -> state.add("/x", "{oops"));
+            assertThrows(IllegalArgumentException.class, () -> state.remove(""));
+        }
+    }
+
+    @Test
+    public void testConcurrentChangesMerge() {
+        try (JniYDoc a = new JniYDoc();
+             JniYDoc b = new JniYDoc();
+             JniYJson stateA = a.getJson("state");
+             JniYJson stateB = b.getJson("state")) {
+            stateA.add("/list", "[]");
+            b.applyUpdate(a.encodeStateAsUpdate());
+
+            stateA.add("/list/-", "1");
+            stateB.add("/list/-", "2");
+            byte[] fromA = a.encodeStateAsUpdate();
+            a.applyUpdate(b.encodeStateAsUpdate());
+            b.applyUpdate(fromA);
+
+            assertEquals(stateA.toJson(), stateB.toJson());
+            assertEquals(2, stateA.get("/list").split(",").length);
+        }
+    }
+
+    @Test
+    public void testObserversReceiveJsonPatch() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYJson state = doc.getJson("state")) {
+            state.add("/list", "[1,2]");
+            List<String> ops = new ArrayList<>();
+            List<YChange.Type> types = new ArrayList<>();
+            try (YSubscription sub = state.observe(event -> {
+                for (YChange change : event.getChanges()) {
+                    ops.add(((JniJsonPatchOp) change).toJson());
+                    types.add(change.getType());
+                }
+            })) {
+                try (JniYTransaction txn = doc.beginTransaction()) {
+                    state.remove(txn, "/list/0");
+                    state.add(txn, "/a~b", "\"x\"");
+                }
+            }
+            assertEquals(List.of(
+                "{\"op\":\"add\",\"path\":\"/a~0b\",\"value\":\"x\"}",
+                "{\"op\":\"remove\",\"path\":\"/list/0\"}"), ops);
+            assertEquals(List.of(YChange.Type.INSERT, YChange.Type.DELETE), types);
+
+            ops.clear();
+            state.add("/b", "1");
+            assertTrue(ops.isEmpty());
+        }
+    }
+}
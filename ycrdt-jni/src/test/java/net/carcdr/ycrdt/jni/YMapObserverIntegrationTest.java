@@ -10,6 +10,7 @@ import net.carcdr.ycrdt.YMapChange;
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
+import static org.junit.Assert.assertNull;
 import static org.junit.Assert.assertTrue;
 
 import java.util.ArrayList;
@@ -242,6 +243,35 @@ public class YMapObserverIntegrationTest {
         }
     }
 
+    @Test
+    public void testObserveKeysOmitsValues() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = doc.getMap("test")) {
+            map.setString("kept", "initial");
+            map.setString("gone", "soon");
+
+            List<YEvent> capturedEvents = new ArrayList<>();
+            try (YSubscription sub = map.observeKeys(capturedEvents::add)) {
+                try (JniYTransaction txn = doc.beginTransaction()) {
+                    map.setString(txn, "kept", "updated");
+                    map.remove(txn, "gone");
+                    map.setDouble(txn, "added", 1.5);
+                }
+            }
+
+            assertEquals(1, capturedEvents.size());
+            List<String> keys = new ArrayList<>();
+            for (YChange change : capturedEvents.get(0).getChanges()) {
+                YMapChange mapChange = (YMapChange) change;
+                keys.add(mapChange.getKey() + ":" + mapChange.getType());
+                assertNull(mapChange.getNewValue());
+                assertNull(mapChange.getOldValue());
+            }
+            keys.sort(null);
+            assertEquals(List.of("added:INSERT", "gone:DELETE", "kept:ATTRIBUTE"), keys);
+        }
+    }
+
     @Test
     public void testRemoveChange() {
         try (YDoc doc = new JniYDoc();
@@ -280,4 +310,29 @@ public class YMapObserverIntegrationTest {
             }
         }
     }
+
+    @Test
+    public void testNestedValuesAreTyped() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYDoc subdoc = new JniYDoc();
+             YMap map = doc.getMap("test")) {
+
+            map.setDoc("key1", subdoc);
+
+            List<YEvent> capturedEvents = new ArrayList<>();
+
+            try (YSubscription sub = map.observe(capturedEvents::add)) {
+                map.setDouble("key1", 2.5);
+
+                assertEquals(1, capturedEvents.size());
+                YMapChange change = (YMapChange) capturedEvents.get(0).getChanges().get(0);
+                assertEquals(YChange.Type.ATTRIBUTE, change.getType());
+                assertEquals(2.5, change.getNewValue());
+
+                JniTreeNode old = (JniTreeNode) change.getOldValue();
+                assertEquals(JniTreeNode.Kind.DOC, old.getKind());
+                assertEquals(subdoc.getGuid(), old.getName());
+            }
+        }
+    }
 }
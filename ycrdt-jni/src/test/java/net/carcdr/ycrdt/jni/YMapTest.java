@@ -6,10 +6,15 @@ import net.carcdr.ycrdt.YTransaction;
 
 import org.junit.Test;
 
+import java.nio.ByteBuffer;
+import java.nio.charset.StandardCharsets;
+
+import static org.junit.Assert.assertArrayEquals;
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
 import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertThrows;
 import static org.junit.Assert.assertTrue;
 import static org.junit.Assert.fail;
 
@@ -166,6 +171,122 @@ public class YMapTest {
         }
     }
 
+    @Test
+    public void testWriteJsonToDirectBuffer() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = doc.getMap("test")) {
+            map.setString("name", "Alice");
+            String json = map.toJson();
+            int size = json.getBytes(StandardCharsets.UTF_8).length;
+
+            ByteBuffer small = ByteBuffer.allocateDirect(size - 1);
+            assertEquals(size, map.writeJson(small));
+            assertEquals(0, small.position());
+
+            ByteBuffer buffer = ByteBuffer.allocateDirect(size);
+            assertEquals(size, map.writeJson(buffer));
+            assertFalse(buffer.hasRemaining());
+            buffer.flip();
+            assertEquals(json, StandardCharsets.UTF_8.decode(buffer).toString());
+        }
+    }
+
+    @Test
+    public void testGetAll() {
+        try (YDoc doc = new JniYDoc();
+             JniYMap map = (JniYMap) doc.getMap("config")) {
+            map.setString("host", "localhost");
+            map.setDouble("port", 8080.0);
+            map.setString("mode", "dev");
+
+            Object[] values = map.getAll("port", "missing", "host");
+            assertEquals(3, values.length);
+            assertEquals(8080.0, (Double) values[0], 0.0);
+            assertNull(values[1]);
+            assertEquals("localhost", values[2]);
+            assertEquals(0, map.getAll().length);
+
+            try {
+                map.getAll("host", null);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testCompareAndSet() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = (JniYMap) doc.getMap("test")) {
+            assertTrue(map.compareAndSet("owner", null, "alice"));
+            assertFalse(map.compareAndSet("owner", null, "bob"));
+            assertEquals("alice", map.getString("owner"));
+
+            assertFalse(map.compareAndSet("owner", "bob", null));
+            assertTrue(map.compareAndSet("owner", "alice", null));
+            assertFalse(map.containsKey("owner"));
+
+            map.setDouble("count", 2);
+            assertTrue(map.compareAndSet("count", 2, 3L));
+            assertFalse(map.compareAndSet("count", 2.0, 4.0));
+            try (YTransaction txn = doc.beginTransaction()) {
+                assertTrue(map.compareAndSet(txn, "count", 3.0, true));
+            }
+            assertEquals(Boolean.TRUE, map.getAll("count")[0]);
+        }
+    }
+
+    @Test
+    public void testCompareAndSetRejectsUnsupportedValue() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = (JniYMap) doc.getMap("test")) {
+            try {
+                map.compareAndSet("key", null, new Object());
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // Expected
+            }
+            assertFalse(map.containsKey("key"));
+        }
+    }
+
+    @Test
+    public void testKeysWithPrefix() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = (JniYMap) doc.getMap("test")) {
+            try (YTransaction txn = doc.beginTransaction()) {
+                for (int i = 0; i < 25; i++) {
+                    map.setDouble(txn, String.format("user:%02d", i), i);
+                }
+                map.setString(txn, "group:admins", "1");
+            }
+
+            assertArrayEquals(new String[] {"user:00", "user:01", "user:02"},
+                map.keysWithPrefix("user:", 3, 0));
+            assertArrayEquals(new String[] {"user:10", "user:11"},
+                map.keysWithPrefix("user:", 2, 10));
+            assertArrayEquals(new String[] {"user:24"}, map.keysWithPrefix("user:", 10, 24));
+            assertEquals(0, map.keysWithPrefix("user:", 10, 25).length);
+            assertEquals(0, map.keysWithPrefix("user:", 0, 0).length);
+            assertEquals(0, map.keysWithPrefix("missing:", 10, 0).length);
+            assertEquals(26, map.keysWithPrefix("", 100, 0).length);
+
+            try (YTransaction txn = doc.beginTransaction()) {
+                assertArrayEquals(new String[] {"group:admins"},
+                    map.keysWithPrefix(txn, "group:", 10, 0));
+            }
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testKeysWithPrefixNegativeOffset() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = (JniYMap) doc.getMap("test")) {
+            map.keysWithPrefix("user:", 10, -1);
+        }
+    }
+
     @Test
     public void testGetNonExistentKey() {
         try (YDoc doc = new JniYDoc();
@@ -645,4 +766,88 @@ public class YMapTest {
             map.setDoc(null, "key", child);
         }
     }
+
+    @Test
+    public void testGetValueType() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYMap map = doc.getMap("test");
+             JniYDoc subdoc = new JniYDoc()) {
+            map.setString("name", "Alice");
+            map.setDouble("age", 30.0);
+            map.setDoc("child", subdoc);
+
+            assertEquals(JniYMap.ValueType.STRING, map.getValueType("name"));
+            assertEquals(JniYMap.ValueType.NUMBER, map.getValueType("age"));
+            assertEquals(JniYMap.ValueType.DOC, map.getValueType("child"));
+            assertEquals(JniYMap.ValueType.ABSENT, map.getValueType("missing"));
+
+            try (YTransaction txn = doc.beginTransaction()) {
+                map.remove(txn, "name");
+                assertEquals(JniYMap.ValueType.ABSENT, map.getValueType(txn, "name"));
+            }
+        }
+    }
+
+    @Test
+    public void testUndefinedIsKeptApartFromNull() {
+        try (JniYDoc doc1 = new JniYDoc();
+             JniYDoc doc2 = new JniYDoc();
+             JniYMap map1 = doc1.getMap("test");
+             JniYMap map2 = doc2.getMap("test")) {
+            assertTrue(map1.compareAndSet("u", null, JniUndefined.UNDEFINED));
+            doc2.applyUpdate(doc1.encodeStateAsUpdate());
+
+            assertEquals(JniYMap.ValueType.UNDEFINED, map2.getValueType("u"));
+            assertArrayEquals(new Object[] {JniUndefined.UNDEFINED, null},
+                map2.getAll("u", "missing"));
+            // null does not match a stored undefined
+            assertFalse(map2.compareAndSet("u", null, "x"));
+            assertTrue(map2.compareAndSet("u", JniUndefined.UNDEFINED, "x"));
+        }
+    }
+
+    @Test
+    public void testWeakLinkToEntry() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYDoc remote = new JniYDoc();
+             JniYMap users = doc.getMap("users");
+             JniYMap task = doc.getMap("task")) {
+            users.setString("u1", "Ann");
+            task.setLink("assignee", users, "u1");
+            assertEquals("Ann", task.getLinked("assignee"));
+            assertEquals(JniYMap.ValueType.LINK, task.getValueType("assignee"));
+
+            users.setString("u1", "Anne");
+            assertEquals("Anne", task.getLinked("assignee"));
+            users.setLink("self", users, "u1");
+            assertEquals("Anne", users.getLinked("self"));
+            assertNull(users.getLinked("u1"));
+            assertNull(task.getLinked("missing"));
+
+            remote.applyUpdate(doc.encodeStateAsUpdate());
+            try (JniYMap remoteTask = remote.getMap("task")) {
+                assertEquals("Anne", remoteTask.getLinked("assignee"));
+            }
+            JniTreeNode tree = doc.exportTree(2).get("task");
+            assertEquals(JniTreeNode.Kind.LINK,
+                ((JniTreeNode) tree.getEntries().get("assignee")).getKind());
+
+            users.remove("u1");
+            assertNull(task.getLinked("assignee"));
+            users.setString("u1", "Bob");
+            assertEquals("Bob", task.getLinked("assignee"));
+
+            try {
+                task.setLink("reviewer", users, "u2");
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                assertTrue(e.getMessage().contains("u2"));
+            }
+            assertFalse(task.containsKey("reviewer"));
+            try (JniYMap other = remote.getMap("users")) {
+                assertThrows(IllegalArgumentException.class,
+                    () -> task.setLink("reviewer", other, "u1"));
+            }
+        }
+    }
 }
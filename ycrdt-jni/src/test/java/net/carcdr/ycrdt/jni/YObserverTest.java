@@ -143,4 +143,147 @@ public class YObserverTest {
         // Should throw
         text.observe(event -> { });
     }
+
+    @Test
+    public void testObserveAndUnobserveEveryType() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYArray array = doc.getArray("array");
+             JniYMap map = doc.getMap("map");
+             JniYXmlText xmlText = doc.getXmlText("xmlText");
+             JniYXmlElement xmlElement = doc.getXmlElement("xmlElement");
+             JniYXmlFragment xmlFragment = doc.getXmlFragment("xmlFragment")) {
+            List<Runnable> mutations = new ArrayList<>();
+            List<YSubscription> subscriptions = new ArrayList<>();
+            List<String> events = new ArrayList<>();
+
+            subscriptions.add(text.observe(e -> events.add("text")));
+            mutations.add(() -> text.push("a"));
+            subscriptions.add(array.observe(e -> events.add("array")));
+            mutations.add(() -> array.pushString("a"));
+            subscriptions.add(map.observe(e -> events.add("map")));
+            mutations.add(() -> map.setString("k", "v"));
+            subscriptions.add(xmlText.observe(e -> events.add("xmlText")));
+            mutations.add(() -> xmlText.push("a"));
+            subscriptions.add(xmlElement.observe(e -> events.add("xmlElement")));
+            mutations.add(() -> xmlElement.setAttribute("k", "v"));
+            subscriptions.add(xmlFragment.observe(e -> events.add("xmlFragment")));
+            mutations.add(() -> xmlFragment.insertElement(0, "p"));
+
+            mutations.forEach(Runnable::run);
+            assertEquals(List.of("text", "array", "map", "xmlText", "xmlElement", "xmlFragment"),
+                events);
+
+            subscriptions.forEach(YSubscription::close);
+            events.clear();
+            mutations.forEach(Runnable::run);
+            assertTrue(events.isEmpty());
+        }
+    }
+
+    @Test
+    public void testObserveRoot() {
+        try (JniYDoc doc = new JniYDoc()) {
+            List<YEvent> events = new ArrayList<>();
+            try (YSubscription sub = doc.observeRoot("body", JniTreeNode.Kind.TEXT, events::add)) {
+                try (YText text = doc.getText("body")) {
+                    text.insert(0, "Hello");
+                }
+                assertEquals(1, events.size());
+                assertTrue(events.get(0).getTarget() == doc);
+                YTextChange change = (YTextChange) events.get(0).getChanges().get(0);
+                assertEquals(YChange.Type.INSERT, change.getType());
+                assertEquals("Hello", change.getContent());
+            }
+
+            try (YText text = doc.getText("body")) {
+                text.insert(5, "!");
+            }
+            assertEquals(1, events.size());
+        }
+    }
+
+    @Test
+    public void testObserveRootOfEveryKind() {
+        try (JniYDoc doc = new JniYDoc()) {
+            List<YEvent> events = new ArrayList<>();
+            List<YSubscription> subs = new ArrayList<>();
+            subs.add(doc.observeRoot("map", JniTreeNode.Kind.MAP, events::add));
+            subs.add(doc.observeRoot("array", JniTreeNode.Kind.ARRAY, events::add));
+            subs.add(doc.observeRoot("fragment", JniTreeNode.Kind.XML_FRAGMENT, events::add));
+            subs.add(doc.observeRoot("element", JniTreeNode.Kind.XML_ELEMENT, events::add));
+            subs.add(doc.observeRoot("xmltext", JniTreeNode.Kind.XML_TEXT, events::add));
+
+            try (JniYMap map = doc.getMap("map");
+                 JniYArray array = doc.getArray("array");
+                 JniYXmlFragment fragment = doc.getXmlFragment("fragment");
+                 JniYXmlElement element = doc.getXmlElement("element");
+                 JniYXmlText xmlText = doc.getXmlText("xmltext")) {
+                map.setString("key", "value");
+                array.pushString("item");
+                fragment.insertElement(0, "p");
+                element.setAttribute("class", "title");
+                xmlText.insert(0, "text");
+            }
+            assertEquals(5, events.size());
+
+            subs.forEach(YSubscription::close);
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testObserveRootRejectsDocKind() {
+        try (JniYDoc doc = new JniYDoc()) {
+            doc.observeRoot("sub", JniTreeNode.Kind.DOC, event -> { });
+        }
+    }
+
+    @Test
+    public void testObserverCannotChangeItsDocument() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYText other = doc.getText("other")) {
+            List<Exception> errors = new ArrayList<>();
+            doc.setObserverErrorHandler((e, source) -> errors.add(e));
+            try (YSubscription sub = text.observe(event -> other.insert(0, "x"));
+                 YSubscription updates = doc.observeUpdateV1((update, origin) ->
+                     doc.applyUpdate(update))) {
+                text.insert(0, "Hello");
+            }
+            assertEquals(2, errors.size());
+            for (Exception e : errors) {
+                assertTrue(e instanceof IllegalStateException);
+                assertTrue(e.getMessage().contains("runAfterTransaction"));
+            }
+            assertEquals("", other.toString());
+            assertEquals("Hello", text.toString());
+        }
+    }
+
+    @Test
+    public void testRunAfterTransactionDefersChanges() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text");
+             JniYArray log = doc.getArray("log")) {
+            try (YSubscription sub = text.observe(event ->
+                     doc.runAfterTransaction(() -> log.pushString(text.toString())));
+                 YSubscription logSub = log.observe(event -> doc.runAfterTransaction(() -> {
+                     if (!text.toString().endsWith("!")) {
+                         text.insert(text.length(), "!");
+                     }
+                 }))) {
+                text.insert(0, "Hi");
+            }
+            // The text change is logged, logging appends "!", which is logged too
+            assertEquals("Hi!", text.toString());
+            assertEquals(2, log.length());
+            assertEquals("Hi", log.getString(0));
+            assertEquals("Hi!", log.getString(1));
+
+            // Outside of observers the action runs immediately
+            List<String> ran = new ArrayList<>();
+            doc.runAfterTransaction(() -> ran.add(text.toString()));
+            assertEquals(List.of("Hi!"), ran);
+        }
+    }
 }
@@ -0,0 +1,92 @@
+package net.carcdr.ycrdt.jni;
+
+import org.junit.Test;
+
+import java.util.Collections;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
+
+/**
+ * Unit tests for JniPrelim.
+ */
+public class YPrelimTest {
+
+    @Test
+    public void testCommitNestedMap() {
+        try (JniYDoc doc = JniYDoc.deterministic(1);
+             JniYMap page = doc.getMap("page")) {
+            JniPrelim prelim = JniPrelim.map()
+                .put("title", "Notes")
+                .put("tags", JniPrelim.array().add("draft").add(2L).add(JniPrelim.map().put("k", true)))
+                .put("body", JniPrelim.text().append("Hello ").append("world"));
+            doc.commitPrelim("page", prelim);
+
+            assertEquals("{\"body\":\"Hello world\",\"tags\":[\"draft\",2,{\"k\":true}],\"title\":\"Notes\"}",
+                page.toJson());
+        }
+    }
+
+    @Test
+    public void testCommitFormattedTextAppends() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            text.insert(0, "> ");
+            doc.commitPrelim("text", JniPrelim.text()
+                .append("bold", Collections.singletonMap("bold", true))
+                .append(" plain"));
+            assertEquals("> bold plain", text.toString());
+            assertEquals("{\"ops\":[{\"insert\":\"> \"},{\"insert\":\"bold\",\"attributes\":{\"bold\":true}},"
+                + "{\"insert\":\" plain\"}]}", text.exportDelta());
+        }
+    }
+
+    @Test
+    public void testCommitXmlFragment() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("xml")) {
+            doc.commitPrelim("xml", JniPrelim.xmlFragment()
+                .add(JniPrelim.xmlElement("p")
+                    .attribute("class", "lead")
+                    .add(JniPrelim.xmlText().append("hi"))));
+            assertEquals("<p class=\"lead\">hi</p>", fragment.toString());
+        }
+    }
+
+    @Test
+    public void testInvalidPrelimLeavesDocumentUnchanged() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYArray array = doc.getArray("array")) {
+            JniPrelim prelim = JniPrelim.array().add("ok").add(JniPrelim.array().add(new Object()));
+            try {
+                doc.commitPrelim("array", prelim);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                assertTrue(e.getMessage().contains("java.lang.Object"));
+            }
+            assertEquals(0, array.length());
+
+            doc.setAllowedFormattingAttributes(Collections.singleton("bold"));
+            try {
+                doc.commitPrelim("text", JniPrelim.text().append("x", Collections.singletonMap("color", "red")));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+
+            try {
+                JniPrelim.xmlFragment().add("text");
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+            try {
+                JniPrelim.array().put("k", 1);
+                fail("Expected UnsupportedOperationException");
+            } catch (UnsupportedOperationException e) {
+                // expected
+            }
+        }
+    }
+}
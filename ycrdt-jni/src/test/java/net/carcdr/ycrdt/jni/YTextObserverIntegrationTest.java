@@ -7,12 +7,14 @@ import net.carcdr.ycrdt.YChange;
 import net.carcdr.ycrdt.YEvent;
 import net.carcdr.ycrdt.YTextChange;
 
+import static org.junit.Assert.assertArrayEquals;
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
 import static org.junit.Assert.assertTrue;
 
 import java.util.ArrayList;
+import java.util.Arrays;
 import java.util.List;
 import java.util.concurrent.atomic.AtomicInteger;
 import org.junit.Test;
@@ -166,4 +168,125 @@ public class YTextObserverIntegrationTest {
             }
         }
     }
+
+    @Test
+    public void testEventOrigin() {
+        byte[] remoteUpdate;
+        try (JniYDoc remote = new JniYDoc(7);
+             YText remoteText = remote.getText("test")) {
+            remoteText.insert(0, "remote");
+            remoteUpdate = remote.encodeStateAsUpdate();
+        }
+
+        try (JniYDoc doc = new JniYDoc(1);
+             YText text = doc.getText("test")) {
+            List<JniEventOrigin> origins = new ArrayList<>();
+            try (YSubscription sub = text.observe(event ->
+                    origins.add(((JniYEvent) event).getEventOrigin()))) {
+                text.insert(0, "a");
+                try (JniYTransaction txn = doc.beginTransaction("editor")) {
+                    text.insert(txn, 0, "b");
+                }
+                doc.applyUpdate(remoteUpdate, "peer");
+            }
+
+            assertEquals(3, origins.size());
+            JniEventOrigin plain = origins.get(0);
+            assertFalse(plain.hasOrigin());
+            assertEquals(null, plain.getString());
+            assertEquals(1, plain.getClientId());
+            assertTrue(plain.isLocal());
+
+            JniEventOrigin editor = origins.get(1);
+            assertEquals("editor", editor.getString());
+            assertEquals(1, editor.getClientId());
+            assertTrue(editor.isLocal());
+
+            JniEventOrigin peer = origins.get(2);
+            assertArrayEquals("peer".getBytes(java.nio.charset.StandardCharsets.UTF_8), peer.getBytes());
+            assertEquals(7, peer.getClientId());
+            assertFalse(peer.isLocal());
+        }
+    }
+
+    @Test
+    public void testSpansCarryUtf16Lengths() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            // "\uD83D\uDE00" is 4 bytes and 2 UTF-16 units, "\u00E9" 2 bytes and 1 unit
+            text.insert(0, "a\uD83D\uDE00b\u00E9 c");
+            List<YEvent> events = new ArrayList<>();
+            List<YEvent> others = new ArrayList<>();
+            // Both observers measure their events against the same copy
+            try (YSubscription sub = text.observe(events::add);
+                 YSubscription other = text.observe(others::add)) {
+                text.delete(1, 5);
+                text.delete(3, 1);
+            }
+
+            for (List<YEvent> received : Arrays.asList(events, others)) {
+                List<? extends YChange> first = received.get(0).getChanges();
+                assertEquals(2, first.size());
+                assertSpan(first.get(0), YChange.Type.RETAIN, 1, 1);
+                assertSpan(first.get(1), YChange.Type.DELETE, 5, 3);
+                List<? extends YChange> second = received.get(1).getChanges();
+                assertSpan(second.get(0), YChange.Type.RETAIN, 3, 2);
+                assertSpan(second.get(1), YChange.Type.DELETE, 1, 1);
+            }
+        }
+    }
+
+    @Test
+    public void testLaterObserverMeasuresAgainstCurrentText() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.insert(0, "a\uD83D\uDE00b");
+            List<YEvent> events = new ArrayList<>();
+            List<YEvent> later = new ArrayList<>();
+            try (YSubscription sub = text.observe(events::add)) {
+                text.delete(1, 4);
+                try (YSubscription laterSub = text.observe(later::add)) {
+                    text.insert(0, "\u00E9");
+                    text.delete(2, 1);
+                }
+            }
+
+            assertEquals(3, events.size());
+            assertEquals(2, later.size());
+            List<? extends YChange> changes = events.get(0).getChanges();
+            assertSpan(changes.get(0), YChange.Type.RETAIN, 1, 1);
+            assertSpan(changes.get(1), YChange.Type.DELETE, 4, 2);
+            for (YEvent event : Arrays.asList(events.get(2), later.get(1))) {
+                changes = event.getChanges();
+                assertSpan(changes.get(0), YChange.Type.RETAIN, 2, 1);
+                assertSpan(changes.get(1), YChange.Type.DELETE, 1, 1);
+            }
+        }
+    }
+
+    @Test
+    public void testObserverRegistersInsideTransaction() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.insert(0, "\u00E9\u00E9");
+            List<YEvent> events = new ArrayList<>();
+            YSubscription sub;
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                sub = text.observe(events::add);
+            }
+            text.delete(2, 2);
+            sub.close();
+
+            List<? extends YChange> changes = events.get(0).getChanges();
+            assertSpan(changes.get(0), YChange.Type.RETAIN, 2, 1);
+            assertSpan(changes.get(1), YChange.Type.DELETE, 2, 1);
+        }
+    }
+
+    private static void assertSpan(YChange change, YChange.Type type, int length, int utf16Length) {
+        JniYTextChange span = (JniYTextChange) change;
+        assertEquals(type, span.getType());
+        assertEquals(length, span.getLength());
+        assertEquals(utf16Length, span.getUtf16Length());
+    }
 }
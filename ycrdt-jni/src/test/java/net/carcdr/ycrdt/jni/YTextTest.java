@@ -1,12 +1,26 @@
 package net.carcdr.ycrdt.jni;
 
+import net.carcdr.ycrdt.YChange;
 import net.carcdr.ycrdt.YDoc;
+import net.carcdr.ycrdt.YEvent;
 import net.carcdr.ycrdt.YText;
 import net.carcdr.ycrdt.YTransaction;
 import net.carcdr.ycrdt.YSubscription;
+import net.carcdr.ycrdt.YTextChange;
 
 import org.junit.Test;
 
+import java.nio.ByteBuffer;
+import java.nio.ByteOrder;
+import java.nio.CharBuffer;
+import java.nio.charset.StandardCharsets;
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.List;
+import java.util.Map;
+import java.util.Set;
+
+import static org.junit.Assert.assertArrayEquals;
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
@@ -231,6 +245,80 @@ public class YTextTest {
         }
     }
 
+    @Test
+    public void testInsertCharArrayRange() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = (JniYText) doc.getText("test")) {
+            char[] rope = "xxHello \uD83D\uDE00 Worldxx".toCharArray();
+            text.insert(0, rope, 2, rope.length - 4);
+            assertEquals("Hello \uD83D\uDE00 World", text.toString());
+
+            text.insert(5, rope, 0, 0);
+            assertEquals("Hello \uD83D\uDE00 World", text.toString());
+
+            try {
+                text.insert(0, rope, 2, rope.length);
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // Expected
+            }
+            try {
+                text.insert(0, (char[]) null, 0, 0);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // Expected
+            }
+        }
+    }
+
+    @Test
+    public void testInsertCharBuffer() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = (JniYText) doc.getText("test")) {
+            CharBuffer heap = CharBuffer.wrap("--World--".toCharArray(), 2, 5);
+            text.insert(0, heap);
+            assertEquals("World", text.toString());
+            assertEquals(0, heap.remaining());
+
+            CharBuffer direct = ByteBuffer.allocateDirect(32)
+                .order(ByteOrder.nativeOrder()).asCharBuffer();
+            direct.put("Hello ").flip();
+            text.insert(0, direct);
+            assertEquals("Hello World", text.toString());
+            assertEquals(0, direct.remaining());
+
+            CharBuffer swapped = ByteBuffer.allocateDirect(4)
+                .order(ByteOrder.nativeOrder() == ByteOrder.BIG_ENDIAN
+                    ? ByteOrder.LITTLE_ENDIAN : ByteOrder.BIG_ENDIAN)
+                .asCharBuffer();
+            swapped.put("!").flip();
+            text.insert(11, swapped);
+            assertEquals("Hello World!", text.toString());
+
+            text.insert(5, CharBuffer.wrap(","));
+            assertEquals("Hello, World!", text.toString());
+        }
+    }
+
+    @Test
+    public void testInsertCharsWithUnpairedSurrogate() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = (JniYText) doc.getText("test")) {
+            char[] chars = {'a', '\uD83D', 'b'};
+            text.insert(0, chars, 0, chars.length);
+            assertEquals("a\uFFFDb", text.toString());
+
+            doc.setStringPolicy(JniYDoc.StringPolicy.STRICT);
+            try {
+                text.insert(0, CharBuffer.wrap(chars));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // Expected
+            }
+            assertEquals("a\uFFFDb", text.toString());
+        }
+    }
+
     @Test(expected = IndexOutOfBoundsException.class)
     public void testDeleteNegativeIndex() {
         try (YDoc doc = new JniYDoc();
@@ -506,4 +594,450 @@ public class YTextTest {
             text.push(txn, "Hello");
         }
     }
+
+    @Test
+    public void testInsertAtStickyIndexAfterRemoteEdit() {
+        try (JniYDoc doc1 = new JniYDoc();
+             JniYDoc doc2 = new JniYDoc();
+             JniYText text1 = doc1.getText("shared");
+             JniYText text2 = doc2.getText("shared")) {
+            text1.push("Hello World");
+            doc2.applyUpdate(doc1.encodeStateAsUpdate());
+
+            JniStickyIndex cursor = text1.stickyIndex(5, JniStickyIndex.Assoc.AFTER);
+            assertEquals(5, text1.resolveStickyIndex(cursor));
+
+            text2.insert(0, ">> ");
+            doc1.applyUpdate(doc2.encodeStateAsUpdate());
+
+            assertEquals(8, text1.resolveStickyIndex(cursor));
+            assertEquals(8, text1.insert(cursor, ","));
+            assertEquals(">> Hello, World", text1.toString());
+        }
+    }
+
+    @Test
+    public void testCursorJsonMatchesYjs() {
+        try (JniYDoc doc = new JniYDoc(42);
+             JniYText text = doc.getText("test")) {
+            text.push("hello");
+            JniCursor cursor = new JniCursor(
+                text.stickyIndex(1, JniStickyIndex.Assoc.AFTER),
+                text.stickyIndex(3, JniStickyIndex.Assoc.BEFORE));
+            String json = cursor.toJson();
+            assertEquals("{\"anchor\":{\"assoc\":0,\"item\":{\"client\":42,\"clock\":1}},"
+                + "\"head\":{\"assoc\":-1,\"item\":{\"client\":42,\"clock\":2}}}", json);
+            assertEquals(cursor, JniCursor.fromJson(json));
+
+            // As published by a Yjs client, with null fields and no assoc
+            JniStickyIndex anchor = JniStickyIndex.fromJson(
+                "{\"type\":null,\"tname\":null,\"item\":{\"client\":42,\"clock\":4}}");
+            assertEquals(4, text.resolveStickyIndex(anchor));
+            assertEquals(anchor, JniStickyIndex.fromJson(anchor.toJson()));
+
+            try {
+                JniCursor.fromJson("{\"anchor\":{}}");
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testWriteStringToDirectBuffer() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.push("caf\u00e9");
+            ByteBuffer small = ByteBuffer.allocateDirect(3);
+            assertEquals(5, text.writeString(small));
+            assertEquals(0, small.position());
+
+            ByteBuffer buffer = ByteBuffer.allocateDirect(16);
+            buffer.put((byte) '>');
+            assertEquals(5, text.writeString(buffer));
+            assertEquals(6, buffer.position());
+            buffer.flip();
+            assertEquals(">caf\u00e9", StandardCharsets.UTF_8.decode(buffer).toString());
+
+            try {
+                text.writeString(ByteBuffer.allocate(16));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testStickyIndexRoundTripsThroughEncoding() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.push("abc");
+            JniStickyIndex end = text.stickyIndex(3, JniStickyIndex.Assoc.BEFORE);
+            JniStickyIndex decoded = JniStickyIndex.decode(end.encode());
+            assertEquals(end, decoded);
+
+            text.push("def");
+            assertEquals(3, text.resolveStickyIndex(decoded));
+            assertEquals(3, text.insert(decoded, "-"));
+            assertEquals("abc-def", text.toString());
+        }
+    }
+
+    @Test
+    public void testStickyIndexUsesYjsEncoding() {
+        try (JniYDoc doc = new JniYDoc(42);
+             JniYText text = doc.getText("test")) {
+            text.push("hello");
+            // Y.encodeRelativePosition: tag 0, client, clock, assoc -1 as a var-int
+            byte[] yjsBefore = {0x00, 0x2a, 0x03, 0x41};
+            assertArrayEquals(yjsBefore,
+                text.stickyIndex(4, JniStickyIndex.Assoc.BEFORE).encode());
+            assertEquals(4, text.resolveStickyIndex(JniStickyIndex.decode(yjsBefore)));
+
+            // Older Yjs releases wrote no assoc
+            JniStickyIndex legacy = JniStickyIndex.decode(new byte[] {0x00, 0x2a, 0x01});
+            assertEquals(1, text.resolveStickyIndex(legacy));
+            assertEquals("{\"assoc\":0,\"item\":{\"client\":42,\"clock\":1}}",
+                legacy.toJson());
+
+            // A position in the type itself follows its end, as in Yjs
+            JniStickyIndex root = JniStickyIndex.decode(
+                new byte[] {0x01, 0x04, 't', 'e', 's', 't', 0x00});
+            assertEquals(5, text.resolveStickyIndex(root));
+        }
+    }
+
+    @Test
+    public void testStickyIndexFromOtherTextIsRejected() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("a");
+             JniYText other = doc.getText("b")) {
+            text.push("abc");
+            other.push("xyz");
+            JniStickyIndex sticky = other.stickyIndex(1, JniStickyIndex.Assoc.AFTER);
+
+            assertEquals(-1, text.resolveStickyIndex(sticky));
+            try {
+                text.insert(sticky, "!");
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+            assertEquals("abc", text.toString());
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testMalformedStickyIndexIsRejected() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.insert(JniStickyIndex.decode(new byte[] {(byte) 0xff}), "x");
+        }
+    }
+
+    @Test(expected = IndexOutOfBoundsException.class)
+    public void testStickyIndexOutOfBounds() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.stickyIndex(1, JniStickyIndex.Assoc.AFTER);
+        }
+    }
+
+    @Test
+    public void testFormatRanges() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.push("Hello World");
+            List<YTextChange> changes = new ArrayList<>();
+            try (YSubscription sub = text.observe(event -> {
+                for (YChange change : event.getChanges()) {
+                    changes.add((YTextChange) change);
+                }
+            })) {
+                text.formatRanges(List.of(
+                    new JniFormatRange(0, 5, Map.of("b", true)),
+                    new JniFormatRange(6, 5, Map.of("b", true))));
+            }
+
+            assertEquals(3, changes.size());
+            assertEquals(YChange.Type.RETAIN, changes.get(0).getType());
+            assertEquals(5, changes.get(0).getLength());
+            assertEquals(Map.of("b", true), changes.get(0).getAttributes());
+            assertTrue(changes.get(1).getAttributes().isEmpty());
+            assertEquals(Map.of("b", true), changes.get(2).getAttributes());
+            assertEquals("Hello World", text.toString());
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testFormatRangesRejectsNullElement() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("test")) {
+            text.push("Hello");
+            text.formatRanges(Arrays.asList(new JniFormatRange(0, 1, Map.of()), null));
+        }
+    }
+
+    @Test
+    public void testGetStringChunk() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            // "\u00e9" is two bytes in UTF-8
+            text.insert(0, "a\u00e9b");
+            assertEquals("a", text.getStringChunk(0, 2));
+            assertEquals("\u00e9b", text.getStringChunk(1, 3));
+            assertEquals("\u00e9", text.getStringChunk(1, 1));
+            assertEquals("", text.getStringChunk(4, 2));
+            try {
+                text.getStringChunk(2, 2);
+                fail("Expected IllegalArgumentException for offset inside a character");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testReadChunks() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            StringBuilder expected = new StringBuilder();
+            for (int i = 0; i < 100; i++) {
+                expected.append("line ").append(i).append(" \u00e9\n");
+            }
+            text.insert(0, expected.toString());
+
+            List<String> chunks = new ArrayList<>();
+            text.readChunks(64, chunks::add);
+            assertTrue(chunks.size() > 1);
+            StringBuilder actual = new StringBuilder();
+            for (String chunk : chunks) {
+                assertTrue(chunk.getBytes(StandardCharsets.UTF_8).length <= 64);
+                actual.append(chunk);
+            }
+            assertEquals(expected.toString(), actual.toString());
+        }
+    }
+
+    @Test
+    public void testReplaceRange() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            text.insert(0, "Hello wrld");
+            List<YEvent> events = new ArrayList<>();
+            try (YSubscription sub = text.observe(events::add)) {
+                text.replaceRange(6, 4, "world", Map.of("bold", true));
+            }
+            assertEquals(1, events.size());
+            assertEquals("{\"ops\":[{\"insert\":\"Hello \"},"
+                + "{\"insert\":\"world\",\"attributes\":{\"bold\":true}}]}", text.exportDelta());
+
+            try (YTransaction txn = doc.beginTransaction()) {
+                text.replaceRange(txn, 0, 5, "Hi", null);
+                text.replaceRange(txn, 2, 0, "!", null);
+                text.replaceRange(txn, 3, 1, "", null);
+            }
+            assertEquals("Hi!world", text.toString());
+
+            doc.setAllowedFormattingAttributes(Set.of("italic"));
+            try {
+                text.replaceRange(0, 2, "Hey", Map.of("bold", true));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected: nothing is deleted when the attributes are rejected
+            }
+            try {
+                text.replaceRange(4, 5, "x", null);
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+            assertEquals("Hi!world", text.toString());
+        }
+    }
+
+    @Test
+    public void testImportExportDelta() {
+        String delta = "{\"ops\":[{\"insert\":\"Hello \"},"
+            + "{\"insert\":\"world\",\"attributes\":{\"bold\":true}},"
+            + "{\"insert\":\"\\n\"}]}";
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            text.importDelta(delta);
+            assertEquals("Hello world\n", text.toString());
+            assertEquals(delta, text.exportDelta());
+
+            try {
+                text.importDelta("[{\"insert\":\"more\"}]");
+                fail("Expected IllegalStateException");
+            } catch (IllegalStateException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testImportDeltaRejectsRetain() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            try {
+                text.importDelta("[{\"retain\":3}]");
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+            assertEquals(0, text.length());
+        }
+    }
+
+    @Test
+    public void testEventContentLimit() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            assertEquals(-1, doc.getEventContentLimit());
+            doc.setEventContentLimit(4);
+            assertEquals(4, doc.getEventContentLimit());
+            List<YEvent> events = new ArrayList<>();
+            try (YSubscription sub = text.observe(events::add)) {
+                text.insert(0, "abcd");
+                text.insert(4, "Hello, world");
+            }
+            JniYTextChange full = (JniYTextChange) events.get(0).getChanges().get(0);
+            assertFalse(full.isTruncated());
+            assertEquals("abcd", full.getContent());
+            assertEquals("abcd".hashCode(), full.getContentHash());
+
+            JniYTextChange summary = (JniYTextChange) events.get(1).getChanges().get(1);
+            assertTrue(summary.isTruncated());
+            assertEquals("Hell", summary.getContent());
+            assertEquals(12, summary.getLength());
+            assertEquals("Hello, world".hashCode(), summary.getContentHash());
+
+            doc.setEventContentLimit(-1);
+            try {
+                doc.setEventContentLimit(-2);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testLineIndex() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            assertArrayEquals(new int[] {0}, text.lineStarts());
+            assertEquals(1, text.lineCount());
+
+            text.insert(0, "ab\r\nc\u00e9\n\nd");
+            assertArrayEquals(new int[] {0, 4, 8, 9}, text.lineStarts());
+            assertEquals(4, text.lineCount());
+            assertEquals(new JniTextPosition(0, 0), text.positionOf(0));
+            assertEquals(new JniTextPosition(1, 3), text.positionOf(7));
+            assertEquals(new JniTextPosition(3, 1), text.positionOf(10));
+            assertEquals(7, text.offsetOf(1, 3));
+            assertEquals(10, text.offsetOf(3, 1));
+
+            try {
+                text.positionOf(11);
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+            try {
+                text.offsetOf(1, 4);
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testStats() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            assertEquals(new JniTextStats(0, 0, 0, 1), text.stats());
+
+            text.insert(0, "Hello, w\u00f6rld!\n\n  two\twords \ud83d\ude00\n");
+            JniTextStats stats = text.stats();
+            assertEquals(text.longLength(), stats.getLength());
+            assertEquals(33, stats.getLength());
+            assertEquals(29, stats.getCodePoints());
+            assertEquals(5, stats.getWords());
+            assertEquals(text.lineCount(), stats.getLines());
+
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                text.delete(txn, 0, 7);
+                assertEquals(4, text.stats(txn).getWords());
+            }
+        }
+    }
+
+    @Test
+    public void testBlocks() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            assertTrue(text.blocks(4, 0, 10).isEmpty());
+
+            // Indexes count UTF-8 bytes by default, so the emoji extends the first block
+            text.insert(0, "abc\uD83D\uDE00defghij");
+            List<JniTextBlock> blocks = text.blocks(4, 0, 10);
+            assertEquals(3, blocks.size());
+            assertEquals("abc\uD83D\uDE00", blocks.get(0).getText());
+            assertEquals(0, blocks.get(0).getIndex());
+            assertEquals(7, blocks.get(0).getLength());
+            assertEquals("defg", blocks.get(1).getText());
+            assertEquals(7, blocks.get(1).getIndex());
+            assertEquals("hij", blocks.get(2).getText());
+            assertEquals(11, blocks.get(2).getIndex());
+
+            List<JniTextBlock> middle = text.blocks(4, 1, 1);
+            assertEquals(1, middle.size());
+            assertEquals("defg", middle.get(0).getText());
+
+            // Anchors follow their blocks through edits before them
+            text.insert(0, "XY");
+            assertEquals(9, text.resolveStickyIndex(blocks.get(1).getAnchor()));
+            assertEquals(13, text.resolveStickyIndex(blocks.get(2).getAnchor()));
+
+            try {
+                text.blocks(0, 0, 1);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testDeleteGraphemes() {
+        String family = "\uD83D\uDC68\u200D\uD83D\uDC69\u200D\uD83D\uDC67";
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("text")) {
+            text.insert(0, "a" + family + "e\u0301b");
+
+            int cursor = text.deleteGraphemes(text.length(), -1);
+            assertEquals("a" + family + "e\u0301", text.toString());
+            cursor = text.deleteGraphemes(cursor, -2);
+            assertEquals("a", text.toString());
+            assertEquals(1, cursor);
+
+            text.insert(1, family + "c");
+            assertEquals(1, text.deleteGraphemes(1, 1));
+            assertEquals("ac", text.toString());
+            assertEquals(2, text.deleteGraphemes(2, 1));
+            assertEquals("ac", text.toString());
+
+            try {
+                text.deleteGraphemes(3, 1);
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+        }
+    }
 }
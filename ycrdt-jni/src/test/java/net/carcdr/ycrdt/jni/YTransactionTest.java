@@ -1,16 +1,25 @@
 package net.carcdr.ycrdt.jni;
 
+import net.carcdr.ycrdt.YChange;
 import net.carcdr.ycrdt.YDoc;
+import net.carcdr.ycrdt.YEvent;
+import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YText;
 import net.carcdr.ycrdt.YTransaction;
 
-import org.junit.Ignore;
 import org.junit.Test;
 
+import java.util.ArrayList;
+import java.util.List;
+import java.util.concurrent.atomic.AtomicReference;
+
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
+import static org.junit.Assert.assertNull;
+import static org.junit.Assert.assertThrows;
 import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
 
 /**
  * Tests for the YTransaction class.
@@ -229,14 +238,12 @@ public class YTransactionTest {
     }
 
     /**
-     * Documents known limitation: getText/getArray/getMap cannot be called
-     * inside an explicit transaction.
+     * getText/getArray/getMap cannot be called inside an explicit transaction.
      *
      * <p>The underlying yrs library's get_or_insert_* methods internally call
-     * transact_mut() to create a new write transaction. Since yrs uses
-     * async_lock::RwLock which does NOT support recursive write locking,
-     * calling these methods while already holding a write lock (from an
-     * explicit transaction) causes a deadlock.</p>
+     * transact_mut() to create a new write transaction, which would wait on the
+     * transaction this thread already holds. The native layer detects this and
+     * throws instead of deadlocking.</p>
      *
      * <p>WORKAROUND: Get shared types BEFORE starting the transaction:</p>
      * <pre>{@code
@@ -247,17 +254,236 @@ public class YTransactionTest {
      * }
      * }</pre>
      */
-    @Ignore("Known limitation: getText inside transaction deadlocks due to yrs RwLock")
     @Test
-    public void testGetTextInsideTransactionDeadlocks() {
+    public void testGetTextInsideTransactionThrows() {
         try (YDoc doc = new JniYDoc()) {
+            AtomicReference<Throwable> error = new AtomicReference<>();
             doc.transaction(txn -> {
-                // DEADLOCK: getText creates internal transaction, conflicts with outer txn
-                try (YText text = doc.getText("test")) {
-                    text.push("Hello");
-                    assertEquals("Hello", text.toString());
+                try {
+                    doc.getText("test");
+                } catch (IllegalStateException e) {
+                    error.set(e);
                 }
             });
+            assertTrue(error.get() instanceof IllegalStateException);
+            assertTrue(error.get().getMessage().contains("deadlock"));
+
+            try (YText text = doc.getText("test")) {
+                text.push("Hello");
+                assertEquals("Hello", text.toString());
+            }
+        }
+    }
+
+    @Test
+    public void testBeginTransactionWhileHoldingOneThrows() {
+        try (YDoc doc = new JniYDoc();
+             YText text = doc.getText("test")) {
+            try (YTransaction txn = doc.beginTransaction()) {
+                text.insert(txn, 0, "Hello");
+                try {
+                    doc.beginTransaction();
+                    fail("Expected IllegalStateException");
+                } catch (IllegalStateException e) {
+                    assertTrue(e.getMessage().contains("already holds a transaction"));
+                }
+            }
+            assertEquals("Hello", text.toString());
+
+            // Other threads wait instead, and this thread can open one again
+            try (YTransaction txn = doc.beginTransaction()) {
+                text.insert(txn, 5, "!");
+            }
+            assertEquals("Hello!", text.toString());
+        }
+    }
+
+    @Test
+    public void testApplyUpdateWithOtherDocTransactionThrows() {
+        try (YDoc source = new JniYDoc();
+             YDoc target = new JniYDoc();
+             YDoc other = new JniYDoc();
+             YText text = source.getText("test")) {
+            text.push("Hello");
+            byte[] update = source.encodeStateAsUpdate();
+
+            try (YTransaction txn = other.beginTransaction()) {
+                target.applyUpdate(txn, update);
+                fail("Expected IllegalStateException");
+            } catch (IllegalStateException e) {
+                assertTrue(e.getMessage().contains("different YDoc"));
+            }
+
+            target.applyUpdate(update);
+            try (YText applied = target.getText("test")) {
+                assertEquals("Hello", applied.toString());
+            }
+        }
+    }
+
+    @Test
+    public void testSingleWriterCheckRejectsOtherThread() throws Exception {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("test")) {
+            assertFalse(doc.isSingleWriterCheckEnabled());
+            doc.setSingleWriterCheck(true);
+            assertTrue(doc.isSingleWriterCheckEnabled());
+
+            JniYTransaction txn = doc.beginTransaction();
+            text.insert(txn, 0, "Hello");
+
+            AtomicReference<Throwable> error = new AtomicReference<>();
+            Thread other = new Thread(() -> {
+                try {
+                    text.insert(txn, 5, " World");
+                } catch (Throwable t) {
+                    error.set(t);
+                }
+            }, "single-writer-test");
+            other.start();
+            other.join();
+
+            txn.close();
+
+            assertTrue(error.get() instanceof IllegalStateException);
+            assertTrue(error.get().getMessage().contains("single-writer-test"));
+            assertEquals("Hello", text.toString());
+        }
+    }
+
+    @Test
+    public void testTransactionStats() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("test")) {
+            assertFalse(doc.isTransactionStatsEnabled());
+            text.insert(0, "ignored");
+            assertEquals(0, doc.getTransactionStatsSummary().getTransactionCount());
+
+            doc.setTransactionStatsEnabled(true);
+            assertTrue(doc.isTransactionStatsEnabled());
+            try (YTransaction txn = doc.beginTransaction()) {
+                text.insert(txn, 0, "Hello");
+                text.delete(txn, 5, 7);
+            }
+            text.insert(0, ">");
+
+            JniTransactionStats.Summary summary = doc.getTransactionStatsSummary();
+            assertEquals(2, summary.getTransactionCount());
+            assertEquals(13, summary.getTotalOperationCount());
+            assertTrue(summary.getTotalUpdateBytes() > 0);
+            assertTrue(summary.getMaxDurationNanos() > 0);
+            assertTrue(summary.getTotalDurationNanos() >= summary.getMaxDurationNanos());
+
+            doc.resetTransactionStats();
+            assertEquals(0, doc.getTransactionStatsSummary().getTransactionCount());
+        }
+    }
+
+    @Test
+    public void testSlowTransactionListener() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("test")) {
+            List<JniTransactionStats> slow = new ArrayList<>();
+            doc.setSlowTransactionListener(0, (d, stats) -> slow.add(stats));
+            assertTrue(doc.isTransactionStatsEnabled());
+
+            text.insert(0, "abc");
+            assertEquals(1, slow.size());
+            assertEquals(3, slow.get(0).getOperationCount());
+            assertTrue(slow.get(0).getUpdateBytes() > 0);
+
+            doc.setSlowTransactionListener(Long.MAX_VALUE, (d, stats) -> slow.add(stats));
+            text.insert(0, "def");
+            assertEquals(1, slow.size());
+            assertEquals(2, doc.getTransactionStatsSummary().getTransactionCount());
+        }
+    }
+
+    @Test
+    public void testTransactionLabel() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("test")) {
+            List<JniTransactionStats> slow = new ArrayList<>();
+            doc.setSlowTransactionListener(0, (d, stats) -> slow.add(stats));
+
+            try (JniYTransaction txn = doc.beginTransaction().setLabel("first")) {
+                assertEquals("first", txn.getLabel());
+                txn.setLabel("Editor.save");
+                text.insert(txn, 0, "abc");
+                try {
+                    doc.beginTransaction();
+                    fail("Expected IllegalStateException");
+                } catch (IllegalStateException e) {
+                    assertTrue(e.getMessage().contains("labelled \"Editor.save\""));
+                }
+                assertThrows(IllegalArgumentException.class, () -> txn.setLabel(null));
+            }
+            text.insert(0, "x");
+
+            assertEquals(2, slow.size());
+            assertEquals("Editor.save", slow.get(0).getLabel());
+            assertTrue(slow.get(0).toString().contains("label=Editor.save"));
+            assertNull(slow.get(1).getLabel());
+        }
+    }
+
+    @Test
+    public void testSlowTransactionListenerErrorsGoToHandler() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("test")) {
+            List<Exception> errors = new ArrayList<>();
+            doc.setObserverErrorHandler((e, source) -> errors.add(e));
+            doc.setSlowTransactionListener(0, (d, stats) -> {
+                throw new IllegalStateException("listener failed");
+            });
+
+            text.insert(0, "abc");
+            assertEquals(1, errors.size());
+            assertEquals("listener failed", errors.get(0).getMessage());
+            assertEquals("abc", text.toString());
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testSlowTransactionListenerRejectsNegativeThreshold() {
+        try (JniYDoc doc = new JniYDoc()) {
+            doc.setSlowTransactionListener(-1, (d, stats) -> { });
+        }
+    }
+
+    @Test
+    public void testSilentTransactionSkipsTypeObservers() {
+        byte[] stored;
+        try (JniYDoc source = new JniYDoc();
+             JniYText text = source.getText("body")) {
+            text.insert(0, "\uD83D\uDE00ab");
+            stored = source.encodeStateAsUpdate();
+        }
+        try (JniYDoc doc = new JniYDoc();
+             JniYText text = doc.getText("body")) {
+            List<YEvent> events = new ArrayList<>();
+            List<byte[]> updates = new ArrayList<>();
+            try (YSubscription sub = text.observe(events::add);
+                 YSubscription updateSub = doc.observeUpdateV1((update, origin) -> updates.add(update))) {
+                try (JniYTransaction txn = doc.beginSilentTransaction()) {
+                    assertTrue(txn.isSilent());
+                    doc.applyUpdate(txn, stored);
+                }
+                assertEquals("\uD83D\uDE00ab", text.toString());
+                assertTrue(events.isEmpty());
+                assertEquals(1, updates.size());
+
+                // Later events are measured against the loaded content
+                text.delete(5, 1);
+                assertEquals(1, events.size());
+                JniYTextChange retain = (JniYTextChange) events.get(0).getChanges().get(0);
+                assertEquals(YChange.Type.RETAIN, retain.getType());
+                assertEquals(5, retain.getLength());
+                assertEquals(3, retain.getUtf16Length());
+            }
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                assertFalse(txn.isSilent());
+            }
         }
     }
 }
@@ -0,0 +1,170 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YText;
+import net.carcdr.ycrdt.YSubscription;
+
+import org.junit.Test;
+
+import java.util.ArrayList;
+import java.util.List;
+import java.util.concurrent.atomic.AtomicBoolean;
+import java.util.concurrent.atomic.AtomicLong;
+
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertFalse;
+import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
+
+/**
+ * Unit tests for JniYUndoManager and transaction origins.
+ */
+public class YUndoManagerTest {
+
+    private static byte[] remoteInsert(String content) {
+        try (JniYDoc remote = new JniYDoc();
+             YText text = remote.getText("text")) {
+            text.insert(0, content);
+            return remote.encodeStateAsUpdate();
+        }
+    }
+
+    @Test
+    public void testUndoRedoWithoutOrigins() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             JniYUndoManager undo = new JniYUndoManager(doc, 0)) {
+            undo.addToScope(text);
+            text.insert(0, "Hello");
+            text.insert(5, " World");
+            assertTrue(undo.canUndo());
+
+            assertTrue(undo.undo());
+            assertEquals("Hello", text.toString());
+            assertTrue(undo.canRedo());
+            assertTrue(undo.redo());
+            assertEquals("Hello World", text.toString());
+
+            undo.clear();
+            assertFalse(undo.canUndo());
+            assertFalse(undo.undo());
+        }
+    }
+
+    @Test
+    public void testCaptureTimeoutUsesClock() {
+        AtomicLong now = new AtomicLong(1_000);
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             JniYUndoManager undo = new JniYUndoManager(doc, 100, now::get)) {
+            undo.addToScope(text);
+            text.insert(0, "a");
+            text.insert(1, "b");
+            now.addAndGet(1_000);
+            text.insert(2, "c");
+
+            assertTrue(undo.undo());
+            assertEquals("ab", text.toString());
+            assertTrue(undo.undo());
+            assertEquals("", text.toString());
+        }
+    }
+
+    @Test
+    public void testFailingClockRepeatsItsLastReading() {
+        AtomicLong now = new AtomicLong(1_000);
+        AtomicBoolean failing = new AtomicBoolean();
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             JniYUndoManager undo = new JniYUndoManager(doc, 100, () -> {
+                 if (failing.get()) {
+                     throw new IllegalStateException("no time");
+                 }
+                 return now.get();
+             })) {
+            undo.addToScope(text);
+            text.insert(0, "a");
+            // Grouped with "a" at 1000 instead of being stamped with the system time
+            failing.set(true);
+            text.insert(1, "b");
+            failing.set(false);
+            now.addAndGet(1_000);
+            text.insert(2, "c");
+
+            assertTrue(undo.undo());
+            assertEquals("ab", text.toString());
+            assertTrue(undo.undo());
+            assertEquals("", text.toString());
+        }
+    }
+
+    @Test
+    public void testUpdatesWithOtherOriginsAreNotUndone() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             JniYUndoManager undo = new JniYUndoManager(doc)) {
+            undo.addToScope(text);
+            undo.trackOrigin("local");
+
+            try (JniYTransaction txn = doc.beginTransaction("local")) {
+                assertEquals("local", txn.getOrigin());
+                text.insert(txn, 0, "mine");
+            }
+            doc.applyUpdate(remoteInsert("theirs"), "remote");
+            // Without an origin the transaction is not tracked either
+            text.insert(0, "untracked");
+
+            assertTrue(undo.undo());
+            String remaining = text.toString();
+            assertFalse(remaining.contains("mine"));
+            assertTrue(remaining.contains("theirs"));
+            assertTrue(remaining.contains("untracked"));
+            assertFalse(undo.canUndo());
+        }
+    }
+
+    @Test
+    public void testUpdateObserverReceivesOrigin() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text")) {
+            List<String> origins = new ArrayList<>();
+            try (YSubscription sub = doc.observeUpdateV1((update, origin) -> origins.add(origin))) {
+                try (JniYTransaction txn = doc.beginTransaction("editor")) {
+                    text.insert(txn, 0, "a");
+                }
+                text.insert(1, "b");
+                doc.applyUpdate(remoteInsert("c"), "peer-1");
+            }
+            List<String> expected = new ArrayList<>();
+            expected.add("editor");
+            expected.add(null);
+            expected.add("peer-1");
+            assertEquals(expected, origins);
+        }
+    }
+
+    @Test
+    public void testRejectsUseInsideTransaction() {
+        try (JniYDoc doc = new JniYDoc();
+             YText text = doc.getText("text");
+             JniYUndoManager undo = new JniYUndoManager(doc)) {
+            undo.addToScope(text);
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                text.insert(txn, 0, "a");
+                try {
+                    undo.undo();
+                    fail("Expected IllegalStateException");
+                } catch (IllegalStateException e) {
+                    // expected: undoing needs the document lock this thread holds
+                }
+                try {
+                    doc.applyUpdate(new byte[0], "remote");
+                    fail("Expected IllegalStateException");
+                } catch (IllegalStateException e) {
+                    // expected: the open transaction's origin cannot change
+                }
+            }
+            undo.close();
+            assertTrue(undo.isClosed());
+        }
+    }
+}
@@ -0,0 +1,129 @@
+package net.carcdr.ycrdt.jni;
+
+import net.carcdr.ycrdt.YArray;
+import net.carcdr.ycrdt.YText;
+
+import org.junit.Test;
+
+import java.io.ByteArrayInputStream;
+import java.io.IOException;
+import java.util.Arrays;
+
+import static org.junit.Assert.assertArrayEquals;
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertTrue;
+import static org.junit.Assert.fail;
+
+/**
+ * Unit tests for JniUpdateStream.
+ */
+public class YUpdateStreamTest {
+
+    private static byte[] largeUpdate() {
+        try (JniYDoc source = new JniYDoc();
+             YText text = source.getText("text");
+             YArray array = source.getArray("array")) {
+            for (int i = 0; i < 300; i++) {
+                text.insert(text.length() / 2, "line " + i + " é😀\n");
+                array.pushDouble(i);
+            }
+            text.delete(5, 100);
+            return source.encodeStateAsUpdate();
+        }
+    }
+
+    @Test
+    public void testChunkedWritesMatchApplyUpdate() {
+        byte[] update = largeUpdate();
+        try (JniYDoc expected = new JniYDoc();
+             JniYDoc doc = new JniYDoc();
+             JniUpdateStream stream = new JniUpdateStream(doc)) {
+            expected.applyUpdate(update);
+            for (int offset = 0; offset < update.length; offset += 13) {
+                stream.write(update, offset, Math.min(13, update.length - offset));
+            }
+            assertTrue(stream.getStructsApplied() > 0);
+            stream.finish();
+            assertEquals(0, stream.getBufferedSize());
+
+            try (YText text = doc.getText("text");
+                 YText expectedText = expected.getText("text");
+                 YArray array = doc.getArray("array")) {
+                assertEquals(expectedText.toString(), text.toString());
+                assertEquals(300, array.length());
+            }
+            assertArrayEquals(expected.encodeStateVector(), doc.encodeStateVector());
+        }
+    }
+
+    @Test
+    public void testTransferFromInputStream() throws IOException {
+        byte[] update = largeUpdate();
+        try (JniYDoc doc = new JniYDoc();
+             JniUpdateStream stream = new JniUpdateStream(doc)) {
+            new ByteArrayInputStream(update).transferTo(stream);
+            stream.finish();
+            assertArrayEquals(update, doc.encodeStateAsUpdate());
+        }
+    }
+
+    @Test
+    public void testWritesJoinActiveTransaction() {
+        byte[] update = largeUpdate();
+        try (JniYDoc doc = new JniYDoc();
+             JniUpdateStream stream = new JniUpdateStream(doc)) {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                stream.write(update, 0, update.length);
+                stream.finish();
+            }
+            try (YText text = doc.getText("text")) {
+                assertTrue(text.length() > 0);
+            }
+        }
+    }
+
+    @Test
+    public void testFinishBeforeEndOfUpdateFails() {
+        byte[] update = largeUpdate();
+        try (JniYDoc doc = new JniYDoc();
+             JniUpdateStream stream = new JniUpdateStream(doc)) {
+            stream.write(Arrays.copyOf(update, update.length / 2), 0, update.length / 2);
+            try {
+                stream.finish();
+                fail("Expected RuntimeException");
+            } catch (RuntimeException e) {
+                assertTrue(e.getMessage().contains("ended before the end of the update"));
+            }
+        }
+    }
+
+    @Test
+    public void testInvalidBytesFail() {
+        try (JniYDoc doc = new JniYDoc();
+             JniUpdateStream stream = new JniUpdateStream(doc)) {
+            byte[] garbage = {1, 1, 1, 0, 0x1f, 1, 1, 'a', 0};
+            stream.write(garbage, 0, garbage.length);
+            stream.finish();
+            fail("Expected RuntimeException");
+        } catch (RuntimeException e) {
+            assertTrue(e.getMessage(), e.getMessage().contains("Failed to decode update"));
+        }
+    }
+
+    @Test(expected = IndexOutOfBoundsException.class)
+    public void testWriteOutOfBounds() {
+        try (JniYDoc doc = new JniYDoc();
+             JniUpdateStream stream = new JniUpdateStream(doc)) {
+            stream.write(new byte[4], 2, 3);
+        }
+    }
+
+    @Test(expected = IllegalStateException.class)
+    public void testWriteAfterClose() {
+        try (JniYDoc doc = new JniYDoc()) {
+            JniUpdateStream stream = new JniUpdateStream(doc);
+            stream.close();
+            stream.write(new byte[] {0}, 0, 1);
+        }
+    }
+}
@@ -243,6 +243,43 @@ public class YXmlElementObserverIntegrationTest {
         }
     }
 
+    @Test
+    public void testAttributeChangeTypedValuesAndOrigin() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement element = (JniYXmlElement) doc.getXmlElement("div")) {
+            element.setAttribute("width", 10);
+
+            List<YEvent> capturedEvents = new ArrayList<>();
+            try (YSubscription sub = element.observe(capturedEvents::add)) {
+                try (JniYTransaction txn = doc.beginTransaction("editor")) {
+                    element.setAttribute(txn, "width", 20);
+                    element.setAttribute(txn, "hidden", true);
+                }
+            }
+
+            assertEquals(1, capturedEvents.size());
+            int seen = 0;
+            for (YChange change : capturedEvents.get(0).getChanges()) {
+                JniYXmlElementChange attrChange = (JniYXmlElementChange) change;
+                assertEquals("editor", attrChange.getEventOrigin().getString());
+                assertTrue(attrChange.getEventOrigin().isLocal());
+                if ("width".equals(attrChange.getAttributeName())) {
+                    assertEquals(YChange.Type.ATTRIBUTE, attrChange.getType());
+                    assertEquals("20", attrChange.getNewValue());
+                    assertEquals(20, ((Number) attrChange.getNewTypedValue()).intValue());
+                    assertEquals(10, ((Number) attrChange.getOldTypedValue()).intValue());
+                    seen++;
+                } else if ("hidden".equals(attrChange.getAttributeName())) {
+                    assertEquals(YChange.Type.INSERT, attrChange.getType());
+                    assertEquals(Boolean.TRUE, attrChange.getNewTypedValue());
+                    assertEquals(null, attrChange.getOldTypedValue());
+                    seen++;
+                }
+            }
+            assertEquals(2, seen);
+        }
+    }
+
     @Test
     public void testMultipleObservers() {
         try (YDoc doc = new JniYDoc();
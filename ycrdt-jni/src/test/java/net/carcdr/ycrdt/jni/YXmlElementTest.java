@@ -1,13 +1,23 @@
 package net.carcdr.ycrdt.jni;
 
 import net.carcdr.ycrdt.YDoc;
+import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YTransaction;
 import net.carcdr.ycrdt.YXmlElement;
 import net.carcdr.ycrdt.YXmlFragment;
+import net.carcdr.ycrdt.YXmlNode;
 import net.carcdr.ycrdt.YXmlText;
 
 import org.junit.Test;
 
+import java.util.Arrays;
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+import java.util.concurrent.CountDownLatch;
+import java.util.concurrent.TimeUnit;
+import java.util.concurrent.atomic.AtomicReference;
+
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
 import static org.junit.Assert.assertNotNull;
@@ -402,6 +412,34 @@ public class YXmlElementTest {
         }
     }
 
+    @Test
+    public void testGetChildren() {
+        try (YDoc doc = new JniYDoc();
+             JniYXmlElement div = (JniYXmlElement) doc.getXmlElement("div")) {
+            YXmlElement span = div.insertElement(0, "span");
+            YXmlText text = div.insertText(1);
+            text.push("Hello");
+
+            List<YXmlNode> children = div.getChildren();
+            assertEquals(2, children.size());
+            assertEquals("span", children.get(0).asElement().getTag());
+            assertEquals("Hello", children.get(1).asText().toString());
+
+            children.get(0).asElement().close();
+            children.get(1).asText().close();
+            span.close();
+            text.close();
+        }
+    }
+
+    @Test
+    public void testGetChildrenEmpty() {
+        try (YDoc doc = new JniYDoc();
+             JniYXmlElement div = (JniYXmlElement) doc.getXmlElement("div")) {
+            assertTrue(div.getChildren().isEmpty());
+        }
+    }
+
     @Test
     public void testInsertText() {
         try (YDoc doc = new JniYDoc();
@@ -645,6 +683,54 @@ public class YXmlElementTest {
         }
     }
 
+    @Test
+    public void testAbsentChildReturnsNull() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement div = (JniYXmlElement) doc.getXmlElement("div")) {
+            try (JniYTransaction txn = doc.beginTransaction()) {
+                assertNull(div.getChild(txn, 0));
+                try {
+                    div.getChild(txn, -1);
+                    fail("Expected IndexOutOfBoundsException");
+                } catch (IndexOutOfBoundsException e) {
+                    // expected
+                }
+            }
+            YXmlElement span = div.insertElement(0, "span");
+            assertNull(div.getChild(1));
+            assertNull(span.getChild(0));
+            span.close();
+        }
+    }
+
+    @Test
+    public void testNestedFragmentChild() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement div = (JniYXmlElement) doc.getXmlElement("div")) {
+            div.insertText(0);
+            try (JniYXmlFragment nested = div.insertFragment(1)) {
+                nested.insertElement(0, "span");
+                assertEquals(1, div.indexOf(nested));
+            }
+            assertEquals(2, div.childCount());
+
+            Object child = div.getChild(1);
+            assertTrue(child instanceof JniYXmlFragment);
+            try (JniYXmlFragment nested = (JniYXmlFragment) child) {
+                assertEquals("span", nested.getElement(0).getTag());
+            }
+            List<YXmlNode> children = div.getChildren();
+            assertEquals(2, children.size());
+            assertEquals(YXmlNode.NodeType.TEXT, children.get(0).getNodeType());
+            assertEquals(YXmlNode.NodeType.FRAGMENT, children.get(1).getNodeType());
+            try (JniYXmlFragment nested = (JniYXmlFragment) children.get(1)) {
+                assertEquals("span", nested.getElement(0).getTag());
+                assertEquals(1, div.indexOf(nested));
+            }
+            children.get(0).asText().close();
+        }
+    }
+
     @Test(expected = IndexOutOfBoundsException.class)
     public void testRemoveChildNegativeIndex() {
         try (YDoc doc = new JniYDoc();
@@ -791,6 +877,29 @@ public class YXmlElementTest {
         }
     }
 
+    @Test
+    public void testGetPathOfNestedNodes() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement div = doc.getXmlElement("div");
+             JniYXmlElement h1 = div.insertElement(0, "h1");
+             JniYXmlElement p = div.insertElement(1, "p");
+             JniYXmlText text = p.insertText(0)) {
+            assertEquals(List.of(0), div.getPath().getSegments());
+            assertEquals("div[0][1][0]", text.getPath().toString());
+            JniTypePath path = p.getPath();
+            assertEquals("div", path.getRootName());
+            assertEquals(List.of(0, 1), path.getSegments());
+            assertEquals(path, text.getPath().getParent());
+            assertEquals(2, path.getDepth());
+            assertFalse(path.isRoot());
+
+            div.removeChild(1);
+            assertNull(p.getPath());
+            assertNull(text.getPath());
+            assertEquals("div[0][0]", h1.getPath().toString());
+        }
+    }
+
     @Test
     public void testGetIndexInParentSingleChild() {
         try (YDoc doc = new JniYDoc();
@@ -1172,4 +1281,101 @@ public class YXmlElementTest {
             element.setAttribute("bad", new Object());
         }
     }
+
+    @Test
+    public void testSetAndRemoveAttributes() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement element = doc.getXmlElement("div")) {
+            Map<String, Object> style = new HashMap<>();
+            style.put("color", "red");
+            style.put("width", 42L);
+            style.put("bold", true);
+            element.setAttributes(style);
+            assertEquals("red", element.getAttribute("color"));
+            assertEquals(42L, element.getAttribute("width"));
+            assertEquals(true, element.getAttribute("bold"));
+
+            element.removeAttributes("color", "bold", "missing");
+            assertNull(element.getAttribute("color"));
+            assertNull(element.getAttribute("bold"));
+            assertEquals(42L, element.getAttribute("width"));
+        }
+    }
+
+    @Test
+    public void testSetAttributesRejectsInvalidEntry() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement element = doc.getXmlElement("div")) {
+            Map<String, Object> attributes = new HashMap<>();
+            attributes.put("ok", "value");
+            attributes.put("bad", new Object());
+            try {
+                element.setAttributes(attributes);
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                // expected
+            }
+            assertNull(element.getAttribute("ok"));
+        }
+    }
+
+    @Test
+    public void testIndexOfChild() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement root = doc.getXmlElement("root");
+             JniYXmlElement first = root.insertElement(0, "a");
+             JniYXmlText second = root.insertText(1);
+             JniYXmlElement grandchild = first.insertElement(0, "b")) {
+            assertEquals(0, root.indexOf(first));
+            assertEquals(1, root.indexOf(second));
+            assertEquals(-1, root.indexOf(grandchild));
+            assertEquals(0, first.indexOf(grandchild));
+            assertTrue(root.contains(second));
+            assertFalse(root.contains(root));
+            assertEquals(1, second.getIndexInParent());
+            assertEquals(0, grandchild.getIndexInParent());
+        }
+    }
+
+    @Test
+    public void testChildCountWithoutTransaction() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement div = doc.getXmlElement("div")) {
+            div.insertElement(0, "span");
+            div.insertText(1);
+
+            assertEquals(2, div.childCount());
+            // The count is read without leaving a transaction open
+            assertEquals(null, doc.getActiveTransaction());
+            try (YTransaction txn = doc.beginTransaction()) {
+                assertEquals(2, div.childCount(txn));
+            }
+        }
+    }
+
+    @Test
+    public void testChildCountThrowsWhileThreadHoldsTransaction() throws Exception {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement div = doc.getXmlElement("div");
+             JniYText text = doc.getText("text")) {
+            AtomicReference<Exception> error = new AtomicReference<>();
+            CountDownLatch done = new CountDownLatch(1);
+            // Batches are applied on a native thread that holds the write
+            // transaction while observers run, with no Java transaction open
+            try (YSubscription sub = text.observe(event -> {
+                try {
+                    div.childCount();
+                } catch (IllegalStateException e) {
+                    error.set(e);
+                }
+            })) {
+                doc.submitBatch(Arrays.asList(JniBatchOp.textInsert("text", 0, "x")),
+                    (d, result) -> done.countDown());
+                assertTrue(done.await(10, TimeUnit.SECONDS));
+            }
+            assertNotNull(error.get());
+            assertTrue(error.get().getMessage().contains("already holds a transaction"));
+            assertEquals(0, div.childCount());
+        }
+    }
 }
@@ -238,4 +238,26 @@ public class YXmlFragmentObserverIntegrationTest {
             }
         }
     }
+
+    @Test
+    public void testInsertedItemsAreTreeNodes() {
+        try (YDoc doc = new JniYDoc();
+             YXmlFragment fragment = doc.getXmlFragment("test")) {
+
+            List<YEvent> capturedEvents = new ArrayList<>();
+
+            try (YSubscription sub = fragment.observe(capturedEvents::add)) {
+                fragment.insertElement(0, "div");
+
+                assertEquals(1, capturedEvents.size());
+                YArrayChange change = (YArrayChange) capturedEvents.get(0).getChanges().get(0);
+                assertEquals(YChange.Type.INSERT, change.getType());
+
+                JniTreeNode node = (JniTreeNode) change.getItems().get(0);
+                assertEquals(JniTreeNode.Kind.XML_ELEMENT, node.getKind());
+                assertEquals("div", node.getName());
+                assertTrue(node.getItems().isEmpty());
+            }
+        }
+    }
 }
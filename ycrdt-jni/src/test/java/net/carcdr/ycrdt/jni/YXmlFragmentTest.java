@@ -1,6 +1,7 @@
 package net.carcdr.ycrdt.jni;
 
 import net.carcdr.ycrdt.YDoc;
+import net.carcdr.ycrdt.YSubscription;
 import net.carcdr.ycrdt.YXmlNode;
 import net.carcdr.ycrdt.YTransaction;
 import net.carcdr.ycrdt.YXmlElement;
@@ -9,6 +10,12 @@ import net.carcdr.ycrdt.YXmlText;
 
 import org.junit.Test;
 
+import java.util.Arrays;
+import java.util.Map;
+import java.util.concurrent.CountDownLatch;
+import java.util.concurrent.TimeUnit;
+import java.util.concurrent.atomic.AtomicReference;
+
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertNotNull;
 import static org.junit.Assert.assertFalse;
@@ -498,4 +505,222 @@ public class YXmlFragmentTest {
             assertEquals(YXmlNode.NodeType.ELEMENT, fragment.getNodeType(3)); // p
         }
     }
+
+    @Test
+    public void testGetChildWithTransaction() {
+        try (YDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = (JniYXmlFragment) doc.getXmlFragment("doc")) {
+            fragment.insertElement(0, "div");
+            fragment.insertText(1, "Hello");
+
+            try (YTransaction txn = doc.beginTransaction()) {
+                Object child0 = fragment.getChild(txn, 0);
+                Object child1 = fragment.getChild(txn, 1);
+                assertTrue(child0 instanceof YXmlElement);
+                assertTrue(child1 instanceof YXmlText);
+                assertEquals(null, fragment.getChild(txn, 2));
+                ((YXmlElement) child0).close();
+                ((YXmlText) child1).close();
+            }
+        }
+    }
+
+    @Test
+    public void testElementFragmentConversion() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlElement root = doc.getXmlElement("page");
+             JniYXmlFragment fragment = doc.getXmlFragment("page");
+             JniYXmlElement wrapped = fragment.getWrappedElement();
+             JniYXmlFragment owning = root.getOwningFragment()) {
+            assertNotNull(wrapped);
+            assertEquals("page", wrapped.getTag());
+            wrapped.setAttribute("id", "main");
+            assertEquals("main", root.getAttribute("id"));
+
+            assertNotNull(owning);
+            assertEquals(1, owning.length());
+
+            try (JniYXmlElement child = root.insertElement(0, "span");
+                 JniYXmlFragment childOwner = child.getOwningFragment()) {
+                assertEquals(1, childOwner.length());
+            }
+
+            fragment.insertText(1, "tail");
+            assertEquals(null, fragment.getWrappedElement());
+        }
+    }
+
+    @Test
+    public void testWrappedElementRequiresElementChild() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("doc")) {
+            assertEquals(null, fragment.getWrappedElement());
+            fragment.insertText(0, "text");
+            assertEquals(null, fragment.getWrappedElement());
+        }
+    }
+
+    @Test
+    public void testIndexOfAndContains() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("doc");
+             JniYXmlFragment other = doc.getXmlFragment("other")) {
+            fragment.insertElement(0, "p");
+            fragment.insertText(1, "tail");
+            fragment.insertElement(2, "div");
+            other.insertElement(0, "p");
+
+            try (JniYXmlElement p = fragment.getElement(0);
+                 JniYXmlText tail = fragment.getText(1);
+                 JniYXmlElement div = fragment.getElement(2);
+                 JniYXmlElement foreign = other.getElement(0);
+                 JniYXmlText nested = div.insertText(0)) {
+                assertEquals(0, fragment.indexOf(p));
+                assertEquals(1, fragment.indexOf(tail));
+                assertEquals(2, fragment.indexOf(div));
+                assertEquals(-1, fragment.indexOf(foreign));
+                assertEquals(-1, fragment.indexOf(nested));
+                assertEquals(-1, fragment.indexOf("p"));
+                assertTrue(fragment.contains(div));
+                assertFalse(fragment.contains(foreign));
+
+                fragment.remove(0, 1);
+                assertEquals(-1, fragment.indexOf(p));
+                try (YTransaction txn = doc.beginTransaction()) {
+                    assertEquals(1, fragment.indexOf(txn, div));
+                    assertTrue(fragment.contains(txn, tail));
+                }
+            }
+        }
+    }
+
+    @Test
+    public void testNestedFragment() {
+        try (JniYDoc doc1 = new JniYDoc();
+             JniYDoc doc2 = new JniYDoc();
+             JniYXmlFragment fragment = doc1.getXmlFragment("doc")) {
+            fragment.insertElement(0, "p");
+            try (JniYXmlFragment nested = fragment.insertFragment(1)) {
+                nested.insertElement(0, "embed");
+                nested.insertText(1, "inner");
+                assertEquals(1, fragment.indexOf(nested));
+                assertTrue(fragment.contains(nested));
+            }
+            assertEquals(2, fragment.length());
+            assertEquals(YXmlNode.NodeType.FRAGMENT, fragment.getNodeType(1));
+
+            doc2.applyUpdate(doc1.encodeStateAsUpdate());
+            try (JniYXmlFragment remote = doc2.getXmlFragment("doc")) {
+                Object child = remote.getChild(1);
+                assertTrue(child instanceof JniYXmlFragment);
+                try (JniYXmlFragment nested = (JniYXmlFragment) child) {
+                    assertEquals(2, nested.length());
+                    assertEquals("embed", nested.getElement(0).getTag());
+                    assertEquals("inner", nested.getText(1).toString());
+                }
+            }
+        }
+    }
+
+    @Test
+    public void testIndexOfClosedChild() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("doc")) {
+            fragment.insertElement(0, "p");
+            JniYXmlElement p = fragment.getElement(0);
+            p.close();
+            try {
+                fragment.indexOf(p);
+                fail("Expected IllegalStateException");
+            } catch (IllegalStateException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testSnapshotTree() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("doc")) {
+            fragment.insertElement(0, "p");
+            fragment.insertText(1, "tail");
+            try (JniYXmlElement p = fragment.getElement(0)) {
+                p.setAttribute("class", "intro");
+                try (JniYXmlText text = p.insertText(0)) {
+                    text.insert(0, "Hello world");
+                    text.format(0, 5, Map.of("bold", true));
+                }
+                p.insertElement(1, "br").close();
+            }
+
+            JniXmlNode root = fragment.snapshotTree();
+            assertEquals(JniXmlNode.Kind.FRAGMENT, root.getKind());
+            assertEquals(2, root.getChildren().size());
+            assertEquals("Hello worldtail", root.getTextContent());
+
+            JniXmlNode p = root.getChildren().get(0);
+            assertEquals(JniXmlNode.Kind.ELEMENT, p.getKind());
+            assertEquals("p", p.getTag());
+            assertEquals("intro", p.getAttribute("class"));
+            assertEquals(1, root.getElementsByTag("br").size());
+
+            JniXmlNode text = p.getChildren().get(0);
+            assertEquals(JniXmlNode.Kind.TEXT, text.getKind());
+            assertEquals(2, text.getChunks().size());
+            assertEquals("Hello", text.getChunks().get(0).getText());
+            assertEquals(true, text.getChunks().get(0).getAttributes().get("bold"));
+
+            // The snapshot does not follow later changes
+            fragment.remove(0, 2);
+            assertEquals(2, root.getChildren().size());
+            try {
+                root.getChildren().clear();
+                fail("Expected UnsupportedOperationException");
+            } catch (UnsupportedOperationException e) {
+                // expected
+            }
+        }
+    }
+
+    @Test
+    public void testLengthWithoutTransaction() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("test")) {
+            fragment.insertElement(0, "div");
+            fragment.insertText(1, "Hello");
+
+            assertEquals(2, fragment.length());
+            // The length is read without leaving a transaction open
+            assertEquals(null, doc.getActiveTransaction());
+            try (YTransaction txn = doc.beginTransaction()) {
+                assertEquals(2, fragment.length(txn));
+            }
+        }
+    }
+
+    @Test
+    public void testLengthThrowsWhileThreadHoldsTransaction() throws Exception {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlFragment fragment = doc.getXmlFragment("test");
+             JniYText text = doc.getText("text")) {
+            AtomicReference<Exception> error = new AtomicReference<>();
+            CountDownLatch done = new CountDownLatch(1);
+            // Batches are applied on a native thread that holds the write
+            // transaction while observers run, with no Java transaction open
+            try (YSubscription sub = text.observe(event -> {
+                try {
+                    fragment.length();
+                } catch (IllegalStateException e) {
+                    error.set(e);
+                }
+            })) {
+                doc.submitBatch(Arrays.asList(JniBatchOp.textInsert("text", 0, "x")),
+                    (d, result) -> done.countDown());
+                assertTrue(done.await(10, TimeUnit.SECONDS));
+            }
+            assertNotNull(error.get());
+            assertTrue(error.get().getMessage().contains("already holds a transaction"));
+            assertEquals(0, fragment.length());
+        }
+    }
 }
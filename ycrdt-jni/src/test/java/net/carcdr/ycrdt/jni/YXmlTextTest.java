@@ -1,5 +1,6 @@
 package net.carcdr.ycrdt.jni;
 
+import net.carcdr.ycrdt.FormattingChunk;
 import net.carcdr.ycrdt.YDoc;
 import net.carcdr.ycrdt.YTransaction;
 import net.carcdr.ycrdt.YXmlElement;
@@ -9,7 +10,9 @@ import net.carcdr.ycrdt.YXmlText;
 import org.junit.Test;
 
 import java.util.HashMap;
+import java.util.List;
 import java.util.Map;
+import java.util.Set;
 
 import static org.junit.Assert.assertEquals;
 import static org.junit.Assert.assertFalse;
@@ -775,4 +778,189 @@ public class YXmlTextTest {
             assertTrue(xmlText.toString().startsWith("x"));
         }
     }
+
+    @Test
+    public void testNodeAttributes() {
+        try (YDoc doc = new JniYDoc();
+             JniYXmlText xmlText = (JniYXmlText) doc.getXmlText("test")) {
+            xmlText.push("hello");
+            xmlText.setAttribute("data-id", "span-1");
+            xmlText.setAttribute("level", 2);
+
+            assertEquals("span-1", xmlText.getAttribute("data-id"));
+            assertEquals(2L, xmlText.getAttribute("level"));
+            assertEquals(2, xmlText.getAttributeNames().length);
+
+            xmlText.removeAttribute("level");
+            assertEquals(null, xmlText.getAttribute("level"));
+            assertEquals(1, xmlText.getAttributeNames().length);
+            assertEquals(5, xmlText.length());
+        }
+    }
+
+    @Test
+    public void testNodeAttributeHoldingSharedType() {
+        // Encoded by yrs: XML text "hi" at index 0 of the fragment "test", with
+        // the attribute "meta" set to a map {k: "v"}
+        byte[] update = {1, 4, 1, 0, 7, 1, 4, 116, 101, 115, 116, 6, 4, 0, 1, 0, 2, 104, 105,
+            39, 0, 1, 0, 4, 109, 101, 116, 97, 1, 40, 0, 1, 3, 1, 107, 1, 119, 1, 118, 0};
+        try (JniYDoc doc = new JniYDoc()) {
+            doc.applyUpdate(update);
+            try (JniYXmlText xmlText = (JniYXmlText) doc.getXmlText("test")) {
+                assertEquals("hi", xmlText.toString());
+                JniTreeNode meta = (JniTreeNode) xmlText.getAttribute("meta");
+                assertEquals(JniTreeNode.Kind.MAP, meta.getKind());
+                assertEquals("v", meta.getEntries().get("k"));
+            }
+        }
+    }
+
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testNodeAttributeUnsupportedValue() {
+        try (YDoc doc = new JniYDoc();
+             JniYXmlText xmlText = (JniYXmlText) doc.getXmlText("test")) {
+            xmlText.setAttribute("data", new Object());
+        }
+    }
+
+    @Test
+    public void testDiffSnapshots() {
+        try (JniYDoc doc = JniYDoc.withoutGarbageCollection();
+             JniYXmlText xmlText = (JniYXmlText) doc.getXmlText("test")) {
+            xmlText.insert(0, "hello world");
+            byte[] before = doc.encodeSnapshot();
+
+            xmlText.delete(5, 6);
+            xmlText.push("!");
+
+            List<JniSnapshotChunk> chunks = xmlText.diffSnapshots(null, before);
+            assertEquals(3, chunks.size());
+            assertEquals("hello", chunks.get(0).getText());
+            assertFalse(chunks.get(0).isChanged());
+            assertEquals(" world", chunks.get(1).getText());
+            assertEquals(JniSnapshotChunk.ChangeKind.REMOVED, chunks.get(1).getChangeKind());
+            assertEquals("!", chunks.get(2).getText());
+            assertEquals(JniSnapshotChunk.ChangeKind.ADDED, chunks.get(2).getChangeKind());
+            assertEquals(doc.getClientId(), chunks.get(2).getChangeClientId());
+
+            // Rendering the earlier snapshot without attribution
+            List<JniSnapshotChunk> old = xmlText.diffSnapshots(before, null);
+            assertEquals(1, old.size());
+            assertEquals("hello world", old.get(0).getText());
+        }
+    }
+
+    @Test(expected = IllegalArgumentException.class)
+    public void testDiffSnapshotsInvalidSnapshot() {
+        try (YDoc doc = new JniYDoc();
+             JniYXmlText xmlText = (JniYXmlText) doc.getXmlText("test")) {
+            xmlText.diffSnapshots(new byte[] {(byte) 0xff}, null);
+        }
+    }
+
+    @Test
+    public void testFormatRanges() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlText text = doc.getXmlText("test")) {
+            text.push("Hello World");
+            text.formatRanges(List.of(
+                new JniFormatRange(0, 5, Map.of("b", true)),
+                new JniFormatRange(6, 5, Map.of("i", true))));
+
+            List<FormattingChunk> chunks = text.getFormattingChunks();
+            assertEquals(3, chunks.size());
+            assertEquals("Hello", chunks.get(0).getText());
+            assertEquals(Map.of("b", true), chunks.get(0).getAttributes());
+            assertFalse(chunks.get(1).hasAttributes());
+            assertEquals("World", chunks.get(2).getText());
+            assertEquals(Map.of("i", true), chunks.get(2).getAttributes());
+        }
+    }
+
+    @Test
+    public void testFormatRangesOutOfBoundsAppliesNothing() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlText text = doc.getXmlText("test")) {
+            text.push("Hello");
+            try {
+                text.formatRanges(List.of(
+                    new JniFormatRange(0, 2, Map.of("b", true)),
+                    new JniFormatRange(3, 10, Map.of("b", true))));
+                fail("Expected IndexOutOfBoundsException");
+            } catch (IndexOutOfBoundsException e) {
+                // expected
+            }
+            assertEquals(1, text.getFormattingChunks().size());
+            assertFalse(text.getFormattingChunks().get(0).hasAttributes());
+        }
+    }
+
+    @Test
+    public void testAllowedFormattingAttributes() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlText text = doc.getXmlText("test")) {
+            assertEquals(null, doc.getAllowedFormattingAttributes());
+            text.insertWithAttributes(0, "Hello", Map.of("font", "serif"));
+
+            doc.setAllowedFormattingAttributes(Set.of("b", "i"));
+            assertEquals(Set.of("b", "i"), doc.getAllowedFormattingAttributes());
+
+            text.format(0, 5, Map.of("b", true));
+            try {
+                text.format(0, 5, Map.of("color", "red"));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                assertTrue(e.getMessage().contains("color"));
+            }
+            try {
+                text.insertWithAttributes(5, " World", Map.of("u", true));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                assertTrue(e.getMessage().contains("'u'"));
+            }
+            try {
+                text.formatRanges(List.of(
+                    new JniFormatRange(0, 1, Map.of("i", true)),
+                    new JniFormatRange(1, 1, Map.of("size", 12))));
+                fail("Expected IllegalArgumentException");
+            } catch (IllegalArgumentException e) {
+                assertTrue(e.getMessage().contains("size"));
+            }
+            assertEquals(5, text.length());
+
+            // Removing an attribute that is not allowed still works
+            Map<String, Object> removeFont = new HashMap<>();
+            removeFont.put("font", null);
+            text.format(0, 5, removeFont);
+            List<FormattingChunk> chunks = text.getFormattingChunks();
+            assertEquals(1, chunks.size());
+            assertEquals("Hello", chunks.get(0).getText());
+            assertEquals(Map.of("b", true), chunks.get(0).getAttributes());
+
+            doc.setAllowedFormattingAttributes(null);
+            text.format(0, 5, Map.of("color", "red"));
+        }
+    }
+
+    @Test
+    public void testReadChunksStopsOnConsumerException() {
+        try (JniYDoc doc = new JniYDoc();
+             JniYXmlText text = doc.getXmlText("text")) {
+            text.insert(0, "abcdef");
+            StringBuilder seen = new StringBuilder();
+            try {
+                text.readChunks(2, chunk -> {
+                    seen.append(chunk);
+                    if (seen.length() >= 4) {
+                        throw new IllegalStateException("stop");
+                    }
+                });
+                fail("Expected consumer exception to propagate");
+            } catch (IllegalStateException e) {
+                assertEquals("stop", e.getMessage());
+            }
+            assertEquals("abcd", seen.toString());
+        }
+    }
 }
@@ -0,0 +1,98 @@
+//! Fixed-size blocks of text content for virtualized rendering.
+//!
+//! A UI showing a huge text only needs the part on screen. The text is split
+//! into consecutive blocks of a fixed number of units, counted in the
+//! document's offset kind, and only the requested blocks cross the JNI
+//! boundary. A block is longer than the block size only when the size would
+//! split a character, so every block holds whole characters and the block
+//! boundaries are the same for every caller using the same size.
+//!
+//! Each block comes with a sticky index attached to its first character.
+//! Resolving the anchors after a change tells which blocks moved, and a
+//! change event whose range lies between two anchors only touches the block
+//! of the first.
+
+use yrs::OffsetKind;
+
+/// A block of text, positioned in the units of the document's offset kind
+#[derive(Debug, PartialEq, Eq)]
+pub struct TextBlock {
+    pub start: u32,
+    pub len: u32,
+    pub content: String,
+}
+
+/// Splits `content` into blocks of `block_size` units and returns at most
+/// `max_blocks` of them, starting with block number `first_block`
+pub fn text_blocks(
+    content: &str,
+    kind: OffsetKind,
+    block_size: u32,
+    first_block: u32,
+    max_blocks: u32,
+) -> Vec<TextBlock> {
+    let mut blocks = Vec::new();
+    let mut number = 0;
+    let mut block_start = 0;
+    let mut start = 0;
+    let mut len = 0;
+    let mut chars = content.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        len += match kind {
+            OffsetKind::Bytes => c.len_utf8() as u32,
+            OffsetKind::Utf16 => c.len_utf16() as u32,
+        };
+        let end = offset + c.len_utf8();
+        if len < block_size && chars.peek().is_some() {
+            continue;
+        }
+        if number >= first_block {
+            blocks.push(TextBlock {
+                start,
+                len,
+                content: content[block_start..end].to_string(),
+            });
+            if blocks.len() as u32 >= max_blocks {
+                break;
+            }
+        }
+        number += 1;
+        block_start = end;
+        start += len;
+        len = 0;
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(start: u32, len: u32, content: &str) -> TextBlock {
+        TextBlock {
+            start,
+            len,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_blocks_cover_the_text_without_splitting_characters() {
+        // The emoji is two UTF-16 units and four bytes
+        let content = "abc😀defg";
+        assert_eq!(
+            text_blocks(content, OffsetKind::Utf16, 4, 0, u32::MAX),
+            vec![block(0, 5, "abc😀"), block(5, 4, "defg")]
+        );
+        assert_eq!(
+            text_blocks(content, OffsetKind::Utf16, 3, 1, 1),
+            vec![block(3, 3, "😀d")]
+        );
+        assert_eq!(
+            text_blocks(content, OffsetKind::Bytes, 6, 1, 5),
+            vec![block(7, 4, "defg")]
+        );
+        assert!(text_blocks(content, OffsetKind::Utf16, 4, 2, 1).is_empty());
+        assert!(text_blocks("", OffsetKind::Utf16, 4, 0, 1).is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! UTF-16 lengths of the spans of text events.
+//!
+//! yrs measures the retained and deleted spans of a text delta in UTF-8 bytes,
+//! while Java indexes strings in UTF-16 units. Deleted text is gone from the
+//! document by the time an observer runs, so the UTF-16 length of a deleted
+//! span cannot be read back from it, and yrs does not expose the content of
+//! deleted items. Observers therefore measure spans against a copy of the text
+//! as it was before the event, applying every delta to it once. The copy is
+//! shared by all observers of the same text, so observing a text keeps one
+//! copy of it however many observers it has. No copy is kept for documents
+//! that measure text in UTF-16, whose spans already have the right length.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use yrs::types::text::YChange;
+use yrs::types::Delta;
+use yrs::{Any, BranchID, DeleteSet, OffsetKind, Out, ReadTxn, StateVector, Text, TransactionMut};
+
+/// Stands in for an embed, which counts as one byte and one UTF-16 unit
+const EMBED: char = '\0';
+
+/// The content of an observed text, as of the last event
+pub struct TextShadow {
+    state: Mutex<ShadowState>,
+}
+
+struct ShadowState {
+    content: String,
+    /// State vector at the start of a transaction that changed the document
+    /// before the copy was taken; its event cannot be applied to the copy
+    stale_before: Option<StateVector>,
+    /// The last event applied to the copy, so observers sharing it apply each
+    /// event once
+    last_event: Option<EventKey>,
+    /// Lengths measured for the last event
+    last_lengths: Vec<Option<u32>>,
+}
+
+/// Identifies the commit that raised an event: every commit that changes the
+/// document either advances its state vector or deletes items no earlier
+/// commit deleted
+#[derive(PartialEq)]
+struct EventKey {
+    after_state: StateVector,
+    delete_set: DeleteSet,
+}
+
+impl EventKey {
+    fn of(txn: &TransactionMut) -> Self {
+        EventKey {
+            after_state: txn.after_state().clone(),
+            delete_set: txn.delete_set().clone(),
+        }
+    }
+}
+
+impl TextShadow {
+    /// Copies the current content of `text`
+    pub fn new<T: ReadTxn, X: Text>(txn: &T, text: &X) -> Self {
+        TextShadow {
+            state: Mutex::new(ShadowState {
+                content: read_content(txn, text),
+                stale_before: None,
+                last_event: None,
+                last_lengths: Vec::new(),
+            }),
+        }
+    }
+
+    /// Copies the content of `text` as seen by `txn`, which may already have
+    /// changed the document
+    pub fn in_transaction<X: Text>(txn: &TransactionMut, text: &X) -> Self {
+        let shadow = Self::new(txn, text);
+        if txn.before_state() != &txn.state_vector() || !txn.delete_set().is_empty() {
+            shadow.lock().stale_before = Some(txn.before_state().clone());
+        }
+        shadow
+    }
+
+    /// Applies an event's delta and returns the UTF-16 length of each of its
+    /// entries
+    ///
+    /// An event already applied by another observer of the text is not
+    /// applied again; the lengths measured then are returned.
+    ///
+    /// Every entry is `None` if the delta cannot be applied to the copy, which
+    /// happens for the event of a transaction that changed the document before
+    /// the copy was taken. The copy is then read again from `text`, so later
+    /// events are measured.
+    pub fn apply<X: Text>(
+        &self,
+        txn: &TransactionMut,
+        text: &X,
+        delta: &[Delta],
+    ) -> Vec<Option<u32>> {
+        let mut state = self.lock();
+        let key = EventKey::of(txn);
+        if state.last_event.as_ref() == Some(&key) {
+            return state.last_lengths.clone();
+        }
+        let stale = state
+            .stale_before
+            .take()
+            .is_some_and(|before| &before == txn.before_state());
+        let lengths = if stale {
+            None
+        } else {
+            apply_delta(&mut state.content, delta)
+        };
+        if lengths.is_none() || state.content.len() != text.len(txn) as usize {
+            state.content = read_content(txn, text);
+        }
+        state.last_event = Some(key);
+        state.last_lengths = lengths.unwrap_or_else(|| vec![None; delta.len()]);
+        state.last_lengths.clone()
+    }
+
+    /// Copies the content of `text` again, after an event that was not
+    /// applied to the copy
+    pub fn reset<X: Text>(&self, txn: &TransactionMut, text: &X) {
+        let mut state = self.lock();
+        state.content = read_content(txn, text);
+        state.stale_before = None;
+        state.last_event = Some(EventKey::of(txn));
+        state.last_lengths.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ShadowState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The copies of the texts of one document that have observers, keyed by text
+#[derive(Default)]
+pub struct TextShadows {
+    shadows: Mutex<HashMap<BranchID, Weak<TextShadow>>>,
+}
+
+impl TextShadows {
+    /// Returns the copy shared by the observers of `text`, or makes one with
+    /// `copy` if it has none. Documents that measure text in UTF-16 get none.
+    ///
+    /// The copy is freed with the last observer holding it.
+    pub fn share<X: Text>(
+        &self,
+        text: &X,
+        offset_kind: OffsetKind,
+        copy: impl FnOnce() -> TextShadow,
+    ) -> Option<Arc<TextShadow>> {
+        if offset_kind == OffsetKind::Utf16 {
+            return None;
+        }
+        let id = text.as_ref().id();
+        if let Some(shadow) = self.get(&id) {
+            return Some(shadow);
+        }
+        // Copied without the lock held: reading the text waits for the
+        // document, whose observers may be registering observers themselves
+        let copied = Arc::new(copy());
+        let mut shadows = self.lock();
+        if let Some(shadow) = shadows.get(&id).and_then(Weak::upgrade) {
+            return Some(shadow);
+        }
+        shadows.retain(|_, shadow| shadow.strong_count() > 0);
+        shadows.insert(id, Arc::downgrade(&copied));
+        Some(copied)
+    }
+
+    fn get(&self, id: &BranchID) -> Option<Arc<TextShadow>> {
+        self.lock().get(id).and_then(Weak::upgrade)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<BranchID, Weak<TextShadow>>> {
+        self.shadows.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The UTF-16 length of each entry of an event's delta, measured with the
+/// observer's copy of the text if it keeps one
+///
+/// Without a copy, spans are measured only if the document already measures
+/// text in UTF-16; otherwise every entry is `None`.
+pub fn span_utf16_lens<X: Text>(
+    shadow: Option<&TextShadow>,
+    txn: &TransactionMut,
+    text: &X,
+    delta: &[Delta],
+) -> Vec<Option<u32>> {
+    if let Some(shadow) = shadow {
+        return shadow.apply(txn, text, delta);
+    }
+    if txn.doc().offset_kind() != OffsetKind::Utf16 {
+        return vec![None; delta.len()];
+    }
+    delta
+        .iter()
+        .map(|d| match d {
+            Delta::Retain(len, _) | Delta::Deleted(len) => Some(*len),
+            Delta::Inserted(..) => None,
+        })
+        .collect()
+}
+
+fn read_content<T: ReadTxn, X: Text>(txn: &T, text: &X) -> String {
+    let mut content = String::new();
+    for chunk in text.diff(txn, YChange::identity) {
+        match chunk.insert {
+            Out::Any(Any::String(s)) => content.push_str(&s),
+            _ => content.push(EMBED),
+        }
+    }
+    content
+}
+
+/// Applies `delta` to `content`, or returns `None` if a span does not fit
+fn apply_delta(content: &mut String, delta: &[Delta]) -> Option<Vec<Option<u32>>> {
+    let mut lengths = Vec::with_capacity(delta.len());
+    let mut pos = 0;
+    for d in delta {
+        let length = match d {
+            Delta::Inserted(Out::Any(Any::String(s)), _) => {
+                content.insert_str(pos, s);
+                pos += s.len();
+                utf16_len(s)
+            }
+            Delta::Inserted(_, _) => {
+                content.insert(pos, EMBED);
+                pos += 1;
+                1
+            }
+            Delta::Retain(len, _) => {
+                let end = pos + *len as usize;
+                let length = utf16_len(content.get(pos..end)?);
+                pos = end;
+                length
+            }
+            Delta::Deleted(len) => {
+                let end = pos + *len as usize;
+                let length = utf16_len(content.get(pos..end)?);
+                content.replace_range(pos..end, "");
+                length
+            }
+        };
+        lengths.push(Some(length));
+    }
+    Some(lengths)
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.encode_utf16().count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use yrs::{Doc, GetString, Observable, Transact};
+
+    #[test]
+    fn test_spans_are_measured_in_utf16() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "a😀bé c");
+        let shadow = Arc::new(TextShadow::new(&doc.transact(), &text));
+
+        let lengths = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let (shadow, lengths) = (shadow.clone(), lengths.clone());
+            text.observe(move |txn, event| {
+                let lengths_of_event = shadow.apply(txn, event.target(), event.delta(txn));
+                lengths.lock().unwrap().push(lengths_of_event);
+            })
+        };
+        // Delete "😀b" (5 bytes, 3 UTF-16 units) after "a" and insert "x"
+        {
+            let mut txn = doc.transact_mut();
+            text.remove_range(&mut txn, 1, 5);
+            text.insert(&mut txn, 1, "x");
+        }
+        // Retain "axé" (4 bytes) and delete " " (1 byte)
+        text.remove_range(&mut doc.transact_mut(), 4, 1);
+
+        let lengths = lengths.lock().unwrap();
+        assert_eq!(lengths[0], vec![Some(1), Some(3), Some(1)]);
+        assert_eq!(lengths[1], vec![Some(3), Some(1)]);
+        assert_eq!(shadow.lock().content, text.get_string(&doc.transact()));
+    }
+
+    #[test]
+    fn test_observers_sharing_a_copy_apply_each_event_once() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "é😀");
+        let shadows = TextShadows::default();
+        let copy = || TextShadow::new(&doc.transact(), &text);
+        let shadow = shadows.share(&text, OffsetKind::Bytes, copy).unwrap();
+        let again = shadows.share(&text, OffsetKind::Bytes, || unreachable!());
+        assert!(Arc::ptr_eq(&shadow, again.as_ref().unwrap()));
+
+        let lengths = Arc::new(Mutex::new(Vec::new()));
+        let _subs: Vec<_> = [shadow.clone(), again.unwrap()]
+            .into_iter()
+            .map(|shadow| {
+                let lengths = lengths.clone();
+                text.observe(move |txn, event| {
+                    let lengths_of_event = shadow.apply(txn, event.target(), event.delta(txn));
+                    lengths.lock().unwrap().push(lengths_of_event);
+                })
+            })
+            .collect();
+        // Delete "😀" (4 bytes, 2 UTF-16 units) after "é"
+        text.remove_range(&mut doc.transact_mut(), 2, 4);
+
+        let lengths = lengths.lock().unwrap();
+        assert_eq!(*lengths, vec![vec![Some(1), Some(2)]; 2]);
+        assert_eq!(shadow.lock().content, "é");
+    }
+
+    #[test]
+    fn test_utf16_documents_share_no_copy() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        let shadows = TextShadows::default();
+        assert!(shadows
+            .share(&text, OffsetKind::Utf16, || unreachable!())
+            .is_none());
+    }
+
+    #[test]
+    fn test_spans_are_measured_without_a_copy_in_utf16_documents() {
+        let delta = vec![Delta::Retain(2, None), Delta::Deleted(3)];
+        for (kind, expected) in [
+            (OffsetKind::Utf16, vec![Some(2), Some(3)]),
+            (OffsetKind::Bytes, vec![None, None]),
+        ] {
+            let doc = Doc::with_options(yrs::Options {
+                offset_kind: kind,
+                ..Default::default()
+            });
+            let text = doc.get_or_insert_text("text");
+            let txn = doc.transact_mut();
+            assert_eq!(span_utf16_lens(None, &txn, &text, &delta), expected);
+        }
+    }
+
+    #[test]
+    fn test_event_of_the_copying_transaction_is_not_measured() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "héllo");
+
+        let mut txn = doc.transact_mut();
+        text.remove_range(&mut txn, 0, 3);
+        let shadow = TextShadow::in_transaction(&txn, &text);
+        let delta = vec![Delta::Deleted(3)];
+        assert_eq!(shadow.apply(&txn, &text, &delta), vec![None]);
+        assert_eq!(shadow.lock().content, "llo");
+    }
+}
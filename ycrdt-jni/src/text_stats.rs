@@ -0,0 +1,83 @@
+//! Counts shown in editor status bars.
+//!
+//! Editors refresh the length, word count and line count of a text after
+//! every keystroke. Computing them natively in a single pass over the content
+//! avoids copying the whole text to Java each time. Words are maximal runs of
+//! non-whitespace characters, and lines are counted like `lineCount`: the
+//! number of `\n` plus one.
+
+use yrs::OffsetKind;
+
+/// Counts describing the content of a text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextStats {
+    /// Length in the units of the document's offset kind
+    pub length: u64,
+    /// Number of Unicode code points
+    pub code_points: u64,
+    /// Number of maximal runs of non-whitespace characters
+    pub words: u64,
+    /// Number of line breaks plus one
+    pub lines: u64,
+}
+
+impl TextStats {
+    /// Counts `content`, measuring its length in the units of `kind`
+    pub fn new(content: &str, kind: OffsetKind) -> Self {
+        let mut stats = TextStats {
+            lines: 1,
+            ..Default::default()
+        };
+        let mut in_word = false;
+        for c in content.chars() {
+            stats.code_points += 1;
+            stats.length += match kind {
+                OffsetKind::Bytes => c.len_utf8() as u64,
+                OffsetKind::Utf16 => c.len_utf16() as u64,
+            };
+            if c == '\n' {
+                stats.lines += 1;
+            }
+            if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                stats.words += 1;
+            }
+        }
+        stats
+    }
+
+    /// The counts in the order `JniTextStats` reads them
+    pub fn to_array(self) -> [i64; 4] {
+        [self.length, self.code_points, self.words, self.lines].map(|n| n as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let content = "Hello, wörld!\n\n  two\twords 😀\n";
+        let utf16 = TextStats::new(content, OffsetKind::Utf16);
+        assert_eq!(
+            utf16,
+            TextStats {
+                length: 30,
+                code_points: 29,
+                words: 5,
+                lines: 4,
+            }
+        );
+        assert_eq!(TextStats::new(content, OffsetKind::Bytes).length, 33);
+        assert_eq!(
+            TextStats::new("", OffsetKind::Bytes),
+            TextStats {
+                lines: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
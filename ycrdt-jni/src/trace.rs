@@ -0,0 +1,148 @@
+//! Tracing of native method calls.
+//!
+//! Built with the `trace-jni` feature, every native entry point starts with
+//! [`trace_native!`], which writes a line to stderr when the native is entered
+//! and another when it returns, naming the calling thread, the handle
+//! arguments and the time spent. Tracing is off until it is switched on with
+//! `JniYDoc.setNativeTracing(true)`. Without the feature the macro expands to
+//! nothing.
+
+use jni::objects::JClass;
+use jni::sys::{jboolean, JNI_FALSE, JNI_TRUE};
+use jni::JNIEnv;
+
+#[cfg(feature = "trace-jni")]
+use jni::sys::jlong;
+#[cfg(feature = "trace-jni")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "trace-jni")]
+use std::time::Instant;
+
+/// Whether native calls are traced
+#[cfg(feature = "trace-jni")]
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "trace-jni")]
+fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "trace-jni"))]
+fn is_enabled() -> bool {
+    false
+}
+
+/// Traces the native method that invokes it until the end of its body
+///
+/// Takes the `Class.method` name of the native followed by its handle
+/// arguments, which are logged as hexadecimal addresses.
+#[macro_export]
+macro_rules! trace_native {
+    ($name:literal $(, $arg:ident)* $(,)?) => {
+        #[cfg(feature = "trace-jni")]
+        let _trace = $crate::NativeTrace::enter($name, &[$((stringify!($arg), $arg)),*]);
+    };
+}
+
+/// An open native call, logged as returned when dropped
+#[cfg(feature = "trace-jni")]
+pub struct NativeTrace {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+#[cfg(feature = "trace-jni")]
+impl NativeTrace {
+    /// Logs entry into the native `name` if tracing is on
+    pub fn enter(name: &'static str, args: &[(&str, jlong)]) -> NativeTrace {
+        if !is_enabled() {
+            return NativeTrace { name, start: None };
+        }
+        eprintln!(
+            "ycrdt-jni: > {} [{:?}]{}",
+            name,
+            std::thread::current().id(),
+            describe_args(args)
+        );
+        NativeTrace {
+            name,
+            start: Some(Instant::now()),
+        }
+    }
+}
+
+#[cfg(feature = "trace-jni")]
+impl Drop for NativeTrace {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            eprintln!(
+                "ycrdt-jni: < {} [{:?}] {:?}",
+                self.name,
+                std::thread::current().id(),
+                start.elapsed()
+            );
+        }
+    }
+}
+
+/// Formats handle arguments as ` name=0x...`, without the leading underscore
+/// of unused parameters
+#[cfg(feature = "trace-jni")]
+fn describe_args(args: &[(&str, jlong)]) -> String {
+    args.iter()
+        .map(|(name, value)| format!(" {}={:#x}", name.trim_start_matches('_'), value))
+        .collect()
+}
+
+/// Switches tracing of native calls on or off
+///
+/// # Parameters
+/// - `enabled`: Whether to trace
+///
+/// # Returns
+/// Whether tracing is now on; always `false` without the `trace-jni` feature
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetNativeTracing(
+    _env: JNIEnv,
+    _class: JClass,
+    enabled: jboolean,
+) -> jboolean {
+    #[cfg(feature = "trace-jni")]
+    ENABLED.store(enabled != JNI_FALSE, Ordering::Relaxed);
+    #[cfg(not(feature = "trace-jni"))]
+    let _ = enabled;
+    to_jboolean(is_enabled())
+}
+
+/// Whether native calls are being traced
+///
+/// # Returns
+/// `true` if tracing is on; always `false` without the `trace-jni` feature
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsNativeTracing(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jboolean {
+    to_jboolean(is_enabled())
+}
+
+fn to_jboolean(value: bool) -> jboolean {
+    if value {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+#[cfg(all(test, feature = "trace-jni"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_args() {
+        assert_eq!(
+            describe_args(&[("doc_ptr", 0x1f), ("_txn_ptr", 0)]),
+            " doc_ptr=0x1f txn_ptr=0x0"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Detection of transactions a thread would wait on itself.
+//!
+//! yrs allows one read-write transaction per document, and asking for any
+//! other transaction blocks until it is dropped. A thread that asks while it
+//! holds the write transaction itself, for example by opening a second
+//! transaction, or by applying an update from an observer that runs while
+//! the transaction commits, would wait forever. Write transactions are
+//! recorded here while they are open, and natives that open a transaction of
+//! their own check first and throw `IllegalStateException` instead.
+//!
+//! Observers of a document run while the transaction that notified them
+//! commits, and a change made from one would either wait on that transaction
+//! or be written into it while it is being committed. Natives that change a
+//! document, apply updates to it or open a transaction on it throw
+//! `IllegalStateException` when called from one of its observers; Java defers
+//! such work with `JniYDoc.runAfterTransaction` instead.
+
+use crate::{txn_label, DispatchGate};
+use dashmap::DashMap;
+use jni::sys::jlong;
+use jni::JNIEnv;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, ThreadId};
+use yrs::{Doc, TransactionMut};
+
+/// The document and thread of an open write transaction
+struct Holder {
+    doc: Doc,
+    thread: ThreadId,
+}
+
+lazy_static! {
+    /// Open write transactions, keyed by the transaction's address
+    static ref HOLDERS: DashMap<jlong, Holder> = DashMap::new();
+}
+
+/// Number of entries in `HOLDERS`, so freeing a transaction skips the map
+/// when none is recorded.
+static HOLDER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Records that the current thread holds the write transaction at `txn_key`
+pub fn hold_transaction(txn_key: jlong, doc: &Doc) {
+    let holder = Holder {
+        doc: doc.clone(),
+        thread: thread::current().id(),
+    };
+    if HOLDERS.insert(txn_key, holder).is_none() {
+        HOLDER_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Forgets a write transaction once it has been committed
+pub fn release_transaction(txn_key: jlong) {
+    if HOLDER_COUNT.load(Ordering::Acquire) != 0 && HOLDERS.remove(&txn_key).is_some() {
+        HOLDER_COUNT.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Whether the current thread holds a write transaction on `doc`
+pub fn held_by_current_thread(doc: &Doc) -> bool {
+    held_key_on_current_thread(doc).is_some()
+}
+
+/// Key of the write transaction the current thread holds on `doc`, if any
+fn held_key_on_current_thread(doc: &Doc) -> Option<jlong> {
+    if HOLDER_COUNT.load(Ordering::Acquire) == 0 {
+        return None;
+    }
+    let current = thread::current().id();
+    HOLDERS
+        .iter()
+        .find(|holder| holder.thread == current && Doc::ptr_eq(&holder.doc, doc))
+        .map(|holder| *holder.key())
+}
+
+/// Checks that opening a transaction on `doc` cannot wait on the current
+/// thread.
+///
+/// Returns `true` if it is safe. Otherwise throws `IllegalStateException`,
+/// naming the held transaction if it is labelled, and returns `false`.
+pub fn check_not_held_or_throw(env: &mut JNIEnv, doc: &Doc) -> bool {
+    let Some(key) = held_key_on_current_thread(doc) else {
+        return true;
+    };
+    let held = match txn_label(key) {
+        Some(label) => format!("a transaction labelled {:?}", label),
+        None => "a transaction".to_string(),
+    };
+    let _ = env.throw_new(
+        "java/lang/IllegalStateException",
+        format!(
+            "This thread already holds {} on this YDoc, and opening another would \
+             deadlock; use the open transaction, or wait until it has been committed",
+            held
+        ),
+    );
+    false
+}
+
+/// Checks that the current thread is not running an observer of the document
+/// whose dispatch gate is `gate`.
+///
+/// Returns `true` if it is not. Otherwise throws `IllegalStateException` and
+/// returns `false`.
+pub fn check_not_dispatching_or_throw(env: &mut JNIEnv, gate: &DispatchGate) -> bool {
+    if !gate.dispatching_on_current_thread() {
+        return true;
+    }
+    let _ = env.throw_new(
+        "java/lang/IllegalStateException",
+        "An observer cannot change its YDoc or open a transaction on it while the \
+         transaction that notified it commits; defer the change with \
+         JniYDoc.runAfterTransaction",
+    );
+    false
+}
+
+/// Checks that `txn` belongs to `doc`.
+///
+/// Returns `true` if it does. Otherwise throws `IllegalStateException` and
+/// returns `false`, since applying an update through another document's
+/// transaction would change that document instead.
+pub fn check_txn_doc_or_throw(env: &mut JNIEnv, doc: &Doc, txn: &TransactionMut) -> bool {
+    if Doc::ptr_eq(txn.doc(), doc) {
+        return true;
+    }
+    let _ = env.throw_new(
+        "java/lang/IllegalStateException",
+        "YTransaction belongs to a different YDoc",
+    );
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::Transact;
+
+    #[test]
+    fn test_hold_is_per_document_and_thread() {
+        let doc = Doc::new();
+        let other = Doc::new();
+        let txn = doc.transact_mut();
+        let key = &txn as *const TransactionMut as jlong;
+        assert!(!held_by_current_thread(&doc));
+
+        hold_transaction(key, &doc);
+        assert!(held_by_current_thread(&doc));
+        assert!(!held_by_current_thread(&other));
+        let doc_clone = doc.clone();
+        assert!(!thread::spawn(move || held_by_current_thread(&doc_clone))
+            .join()
+            .unwrap());
+
+        release_transaction(key);
+        assert!(!held_by_current_thread(&doc));
+    }
+}
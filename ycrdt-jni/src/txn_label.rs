@@ -0,0 +1,115 @@
+//! Labels naming the code path that opened a transaction.
+//!
+//! Origins are part of the document's data and reach observers and undo
+//! managers, so they are a poor place to name Java call sites. A label is only
+//! kept here while its transaction is open. Java reports it with the
+//! statistics of the transaction, and a panic in native code prints the labels
+//! of the transactions open on the panicking thread before the default panic
+//! message, since the process usually aborts before anything else is logged.
+
+use dashmap::DashMap;
+use jni::sys::jlong;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once};
+use std::thread::{self, ThreadId};
+
+/// The label of an open transaction and the thread that set it
+struct Label {
+    text: Arc<str>,
+    thread: ThreadId,
+}
+
+lazy_static! {
+    /// Labels of open transactions, keyed by the transaction's address
+    static ref LABELS: DashMap<jlong, Label> = DashMap::new();
+}
+
+/// Number of entries in `LABELS`, so freeing a transaction skips the map when
+/// none is labelled.
+static LABEL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Labels the open transaction at `txn_ptr`, replacing any earlier label
+pub fn set_txn_label(txn_ptr: jlong, text: &str) {
+    PANIC_HOOK.call_once(install_panic_hook);
+    let label = Label {
+        text: Arc::from(text),
+        thread: thread::current().id(),
+    };
+    if LABELS.insert(txn_ptr, label).is_none() {
+        LABEL_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// The label of the transaction at `txn_ptr`, if it has one
+pub fn txn_label(txn_ptr: jlong) -> Option<Arc<str>> {
+    if LABEL_COUNT.load(Ordering::Acquire) == 0 {
+        return None;
+    }
+    LABELS.get(&txn_ptr).map(|label| Arc::clone(&label.text))
+}
+
+/// Forgets the label of a transaction once it has been committed
+pub fn forget_txn_label(txn_ptr: jlong) {
+    if LABEL_COUNT.load(Ordering::Acquire) != 0 && LABELS.remove(&txn_ptr).is_some() {
+        LABEL_COUNT.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Labels of the transactions labelled by the current thread, sorted
+fn labels_on_current_thread() -> Vec<Arc<str>> {
+    if LABEL_COUNT.load(Ordering::Acquire) == 0 {
+        return Vec::new();
+    }
+    let current = thread::current().id();
+    let mut labels: Vec<Arc<str>> = LABELS
+        .iter()
+        .filter(|label| label.thread == current)
+        .map(|label| Arc::clone(&label.text))
+        .collect();
+    labels.sort();
+    labels
+}
+
+/// Chains a hook in front of the current panic hook that names the open
+/// labelled transactions of the panicking thread
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let labels = labels_on_current_thread();
+        if !labels.is_empty() {
+            eprintln!(
+                "ycrdt-jni: panic with open transactions labelled {:?}",
+                labels
+            );
+        }
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_are_kept_until_forgotten() {
+        set_txn_label(-11, "save");
+        set_txn_label(-12, "load");
+        set_txn_label(-11, "save draft");
+        assert_eq!(txn_label(-11).as_deref(), Some("save draft"));
+        assert_eq!(txn_label(-13), None);
+
+        let on_thread = labels_on_current_thread();
+        assert!(on_thread.contains(&Arc::from("load")));
+        assert!(on_thread.contains(&Arc::from("save draft")));
+        let other = thread::spawn(labels_on_current_thread).join().unwrap();
+        assert!(!other.contains(&Arc::from("load")));
+
+        forget_txn_label(-11);
+        forget_txn_label(-12);
+        assert_eq!(txn_label(-11), None);
+        assert_eq!(txn_label(-12), None);
+    }
+}
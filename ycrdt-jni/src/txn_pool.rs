@@ -0,0 +1,96 @@
+//! Reuse of the allocations that hold transactions.
+//!
+//! Every transaction handed to Java lives in a box of its own, and code that
+//! opens thousands of short implicit transactions a second spends a noticeable
+//! part of its time allocating and freeing those boxes. Freed boxes are kept in
+//! a small pool per thread and reused by the next transaction opened on that
+//! thread. Only the memory is reused: a transaction is dropped, and so
+//! committed, before its box goes back to the pool.
+//!
+//! A reused box gets a new generation in its handle header, so the handle of
+//! the transaction it held before is rejected rather than reaching the new one.
+
+use crate::handles::Tagged;
+use jni::sys::jlong;
+use std::cell::RefCell;
+use std::mem::MaybeUninit;
+use yrs::TransactionMut;
+
+/// Memory for one transaction, behind the header that tags it as one
+type Slot = Box<Tagged<MaybeUninit<TransactionMut<'static>>>>;
+
+/// Number of freed boxes kept per thread
+const POOL_CAPACITY: usize = 4;
+
+thread_local! {
+    static POOL: RefCell<Vec<Slot>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Moves `txn` to the heap, reusing a pooled box if the current thread has one,
+/// and returns its handle
+pub fn box_transaction(txn: TransactionMut<'static>) -> jlong {
+    let slot = POOL
+        .try_with(|pool| pool.borrow_mut().pop())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| Box::new(Tagged::new_as::<TransactionMut>(MaybeUninit::uninit())));
+    let slot = Box::into_raw(slot);
+    // SAFETY: the slot is uninitialized memory owned by this function
+    unsafe {
+        (*slot).value_mut().write(txn);
+        Tagged::handle(slot)
+    }
+}
+
+/// Returns the box of a transaction that has already been dropped to the
+/// current thread's pool, or frees it if the pool is full
+///
+/// # Safety
+/// `txn_ptr` must come from [`box_transaction`], its transaction must have
+/// been dropped in place, and it must not be used afterwards.
+pub unsafe fn recycle_transaction_box(txn_ptr: jlong) {
+    let slot: Slot = Box::from_raw(Tagged::from_handle(txn_ptr));
+    slot.retire();
+    let _ = POOL.try_with(move |pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < POOL_CAPACITY {
+            pool.push(slot);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handle_address, is_handle_of};
+    use yrs::{Doc, GetString, Text, Transact};
+
+    #[test]
+    fn test_freed_box_is_reused() {
+        // Transactions handed to Java borrow a document that outlives them
+        let doc: &'static Doc = Box::leak(Box::new(Doc::new()));
+        let text = doc.get_or_insert_text("text");
+
+        let first = box_transaction(doc.transact_mut());
+        unsafe {
+            let txn = handle_address(first) as *mut TransactionMut;
+            text.insert(&mut *txn, 0, "a");
+            std::ptr::drop_in_place(txn);
+            recycle_transaction_box(first);
+        }
+        let second = box_transaction(doc.transact_mut());
+        assert_eq!(handle_address(first), handle_address(second));
+        // The handle of the dropped transaction does not reach the new one
+        assert_ne!(first, second);
+        assert!(!unsafe { is_handle_of::<TransactionMut>(first) });
+        assert!(unsafe { is_handle_of::<TransactionMut>(second) });
+        unsafe {
+            // The reused box holds the new transaction, which sees the first commit
+            let txn = handle_address(second) as *mut TransactionMut;
+            text.insert(&mut *txn, 1, "b");
+            std::ptr::drop_in_place(txn);
+            recycle_transaction_box(second);
+        }
+        assert_eq!(text.get_string(&doc.transact()), "ab");
+    }
+}
@@ -0,0 +1,159 @@
+//! Optional per-transaction statistics.
+//!
+//! When a document has transaction statistics enabled, the time each
+//! transaction is opened is recorded. On commit the duration, the number of
+//! operations (inserted and deleted clock units) and the size of the v1 update
+//! it produced are measured and added to the document's running totals.
+
+use dashmap::DashMap;
+use jni::sys::jlong;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+use yrs::{ReadTxn, TransactionMut};
+
+lazy_static! {
+    /// Open times of transactions on documents with statistics enabled
+    static ref TXN_STARTS: DashMap<jlong, Instant> = DashMap::new();
+}
+
+/// Number of entries in `TXN_STARTS`, so the common case (no measured
+/// transactions open) skips the map lookup entirely.
+static START_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the current time as the start of a transaction
+pub fn record_txn_start(txn_ptr: jlong) {
+    if TXN_STARTS.insert(txn_ptr, Instant::now()).is_none() {
+        START_COUNT.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Removes and returns the start time of a transaction, if it was recorded
+pub fn take_txn_start(txn_ptr: jlong) -> Option<Instant> {
+    if START_COUNT.load(Ordering::Acquire) == 0 {
+        return None;
+    }
+    let (_, started) = TXN_STARTS.remove(&txn_ptr)?;
+    START_COUNT.fetch_sub(1, Ordering::Release);
+    Some(started)
+}
+
+/// Measurements for a single committed transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxnStats {
+    /// Time from opening the transaction until its commit finished
+    pub duration_nanos: u64,
+    /// Clock units inserted plus clock units deleted
+    pub ops: u64,
+    /// Length of the v1 update produced by the transaction
+    pub update_bytes: u64,
+}
+
+/// Counts the clock units inserted and deleted by a transaction that has not
+/// been committed yet
+pub fn count_ops(txn: &TransactionMut) -> u64 {
+    let before = txn.before_state();
+    let inserted: u64 = txn
+        .state_vector()
+        .iter()
+        .map(|(client, clock)| u64::from(clock.saturating_sub(before.get(client))))
+        .sum();
+    let deleted: u64 = txn
+        .delete_set()
+        .iter()
+        .flat_map(|(_, range)| range.iter())
+        .map(|r| u64::from(r.end - r.start))
+        .sum();
+    inserted + deleted
+}
+
+/// Running totals of transaction statistics for one document
+#[derive(Default)]
+pub struct TxnStatsTotals {
+    count: AtomicU64,
+    total_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+    ops: AtomicU64,
+    update_bytes: AtomicU64,
+}
+
+impl TxnStatsTotals {
+    /// Adds one transaction to the totals
+    pub fn record(&self, stats: &TxnStats) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos
+            .fetch_add(stats.duration_nanos, Ordering::Relaxed);
+        self.max_nanos
+            .fetch_max(stats.duration_nanos, Ordering::Relaxed);
+        self.ops.fetch_add(stats.ops, Ordering::Relaxed);
+        self.update_bytes
+            .fetch_add(stats.update_bytes, Ordering::Relaxed);
+    }
+
+    /// Returns `[count, total_nanos, max_nanos, ops, update_bytes]`
+    pub fn snapshot(&self) -> [u64; 5] {
+        [
+            self.count.load(Ordering::Relaxed),
+            self.total_nanos.load(Ordering::Relaxed),
+            self.max_nanos.load(Ordering::Relaxed),
+            self.ops.load(Ordering::Relaxed),
+            self.update_bytes.load(Ordering::Relaxed),
+        ]
+    }
+
+    /// Resets all totals to zero
+    pub fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total_nanos.store(0, Ordering::Relaxed);
+        self.max_nanos.store(0, Ordering::Relaxed);
+        self.ops.store(0, Ordering::Relaxed);
+        self.update_bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, GetString, Text, Transact};
+
+    #[test]
+    fn test_count_ops_inserts_and_deletes() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("t");
+        {
+            let mut txn = doc.transact_mut();
+            text.insert(&mut txn, 0, "hello");
+            assert_eq!(count_ops(&txn), 5);
+        }
+        let mut txn = doc.transact_mut();
+        text.remove_range(&mut txn, 1, 3);
+        text.insert(&mut txn, 0, "x");
+        assert_eq!(count_ops(&txn), 4);
+        assert_eq!(text.get_string(&txn), "xho");
+    }
+
+    #[test]
+    fn test_totals_record_and_reset() {
+        let totals = TxnStatsTotals::default();
+        totals.record(&TxnStats {
+            duration_nanos: 10,
+            ops: 2,
+            update_bytes: 30,
+        });
+        totals.record(&TxnStats {
+            duration_nanos: 25,
+            ops: 1,
+            update_bytes: 12,
+        });
+        assert_eq!(totals.snapshot(), [2, 35, 25, 3, 42]);
+        totals.reset();
+        assert_eq!(totals.snapshot(), [0; 5]);
+    }
+
+    #[test]
+    fn test_take_txn_start_only_once() {
+        record_txn_start(-42);
+        assert!(take_txn_start(-42).is_some());
+        assert!(take_txn_start(-42).is_none());
+    }
+}
@@ -0,0 +1,255 @@
+//! Location of a shared type within its document.
+//!
+//! A handle to a nested map, array or text does not say where the type lives,
+//! which debugging tools and persistence code keyed by root need. yrs keeps
+//! the parent of a nested type private, so the location is found by walking
+//! the document from its roots down to the type: the root name, then a map
+//! key or sequence index per level. Types embedded in text, and types reached
+//! only through a weak link, are not found. Subdocuments are located in their
+//! parent document the same way.
+
+use crate::JniResult;
+use crate::JniResultExt;
+use jni::objects::{JObject, JObjectArray, JValue};
+use jni::sys::jobjectArray;
+use jni::JNIEnv;
+use std::sync::Arc;
+use yrs::block::ID;
+use yrs::branch::{Branch, BranchID};
+use yrs::{Array, ArrayRef, Doc, Map, MapRef, Out, ReadTxn, XmlFragment, XmlOut};
+
+/// One step from a type to one of its children
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathStep {
+    /// Entry of a map
+    Key(Arc<str>),
+    /// Item of an array or child of an XML node
+    Index(u32),
+}
+
+/// The root a shared type belongs to and the steps leading from it to the type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypePath {
+    pub root: String,
+    pub steps: Vec<PathStep>,
+}
+
+/// Finds where `branch` lives in the document read by `txn`, or `None` if it
+/// was deleted or cannot be reached from a root
+pub fn type_path<T: ReadTxn>(txn: &T, branch: &Branch) -> Option<TypePath> {
+    let id = match branch.id() {
+        BranchID::Root(name) => {
+            return Some(TypePath {
+                root: name.to_string(),
+                steps: Vec::new(),
+            })
+        }
+        BranchID::Nested(_) if branch.is_deleted() => return None,
+        BranchID::Nested(id) => id,
+    };
+    search(txn, &|value| is_type(value, &id))
+}
+
+/// Finds where the subdocument `subdoc` is embedded in the document read by
+/// `txn`, or `None` if it was removed or belongs to another document
+pub fn subdoc_path<T: ReadTxn>(txn: &T, subdoc: &Doc) -> Option<TypePath> {
+    search(
+        txn,
+        &|value| matches!(value, Out::YDoc(doc) if Doc::ptr_eq(doc, subdoc)),
+    )
+}
+
+/// Searches the document from its roots, in name order, for the value
+/// accepted by `target`
+fn search<T: ReadTxn>(txn: &T, target: &Target) -> Option<TypePath> {
+    let mut roots: Vec<(&str, Out)> = txn.root_refs().collect();
+    roots.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in roots {
+        let mut steps = Vec::new();
+        if find(txn, &value, target, &mut steps) {
+            return Some(TypePath {
+                root: name.to_string(),
+                steps,
+            });
+        }
+    }
+    None
+}
+
+/// Accepts the value being searched for
+type Target<'a> = dyn Fn(&Out) -> bool + 'a;
+
+/// Searches the children of `value` for the value accepted by `target`,
+/// appending the steps to it to `steps`
+fn find<T: ReadTxn>(txn: &T, value: &Out, target: &Target, steps: &mut Vec<PathStep>) -> bool {
+    match value {
+        Out::YMap(map) => find_in_map(txn, map, target, steps),
+        Out::YArray(array) => find_in_array(txn, array, target, steps),
+        Out::YXmlElement(element) => find_in_xml(txn, element, target, steps),
+        Out::YXmlFragment(fragment) => find_in_xml(txn, fragment, target, steps),
+        Out::UndefinedRef(branch) => {
+            find_in_map(txn, &MapRef::from(*branch), target, steps)
+                || find_in_array(txn, &ArrayRef::from(*branch), target, steps)
+        }
+        _ => false,
+    }
+}
+
+fn find_in_map<T: ReadTxn>(
+    txn: &T,
+    map: &MapRef,
+    target: &Target,
+    steps: &mut Vec<PathStep>,
+) -> bool {
+    for (key, value) in map.iter(txn) {
+        steps.push(PathStep::Key(key.into()));
+        if target(&value) || find(txn, &value, target, steps) {
+            return true;
+        }
+        steps.pop();
+    }
+    false
+}
+
+fn find_in_array<T: ReadTxn>(
+    txn: &T,
+    array: &ArrayRef,
+    target: &Target,
+    steps: &mut Vec<PathStep>,
+) -> bool {
+    for (index, value) in array.iter(txn).enumerate() {
+        steps.push(PathStep::Index(index as u32));
+        if target(&value) || find(txn, &value, target, steps) {
+            return true;
+        }
+        steps.pop();
+    }
+    false
+}
+
+fn find_in_xml<T: ReadTxn, F: XmlFragment>(
+    txn: &T,
+    parent: &F,
+    target: &Target,
+    steps: &mut Vec<PathStep>,
+) -> bool {
+    for (index, child) in parent.children(txn).enumerate() {
+        let child = Out::from(child as XmlOut);
+        steps.push(PathStep::Index(index as u32));
+        if target(&child) || find(txn, &child, target, steps) {
+            return true;
+        }
+        steps.pop();
+    }
+    false
+}
+
+/// Whether `value` is the nested type with item `target`
+fn is_type(value: &Out, target: &ID) -> bool {
+    let branch: &Branch = match value {
+        Out::YMap(map) => map.as_ref(),
+        Out::YArray(array) => array.as_ref(),
+        Out::YText(text) => text.as_ref(),
+        Out::YXmlElement(element) => element.as_ref(),
+        Out::YXmlFragment(fragment) => fragment.as_ref(),
+        Out::YXmlText(text) => text.as_ref(),
+        Out::UndefinedRef(branch) => branch,
+        _ => return false,
+    };
+    matches!(branch.id(), BranchID::Nested(id) if id == *target)
+}
+
+/// Converts a path to a Java `Object[]`: the root name, then a `String` per
+/// map key and an `Integer` per index
+pub fn type_path_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    path: &TypePath,
+) -> JniResult<JObjectArray<'local>> {
+    let array = env.new_object_array(
+        (path.steps.len() + 1) as i32,
+        "java/lang/Object",
+        JObject::null(),
+    )?;
+    let root = env.new_string(&path.root)?;
+    env.set_object_array_element(&array, 0, &root)?;
+    env.delete_local_ref(root)?;
+    for (i, step) in path.steps.iter().enumerate() {
+        let value = match step {
+            PathStep::Key(key) => JObject::from(env.new_string(key.as_ref())?),
+            PathStep::Index(index) => {
+                env.new_object("java/lang/Integer", "(I)V", &[JValue::Int(*index as i32)])?
+            }
+        };
+        env.set_object_array_element(&array, (i + 1) as i32, &value)?;
+        env.delete_local_ref(value)?;
+    }
+    Ok(array)
+}
+
+/// Finds where `branch` lives and returns it as a Java `Object[]`, or null if
+/// it cannot be reached from a root
+pub fn type_path_or_null<T: ReadTxn>(env: &mut JNIEnv, txn: &T, branch: &Branch) -> jobjectArray {
+    match type_path(txn, branch) {
+        Some(path) => type_path_to_java(env, &path)
+            .map(JObjectArray::into_raw)
+            .unwrap_or_throw(env),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{ArrayPrelim, Doc, MapPrelim, TextPrelim, Transact};
+
+    #[test]
+    fn test_finds_nested_types() {
+        let doc = Doc::new();
+        let root = doc.get_or_insert_map("root");
+        let mut txn = doc.transact_mut();
+        let list = root.insert(&mut txn, "list", ArrayPrelim::default());
+        list.push_back(&mut txn, 1);
+        let inner = list.push_back(&mut txn, MapPrelim::default());
+        let text = inner.insert(&mut txn, "text", TextPrelim::new("hi"));
+
+        assert_eq!(
+            type_path(&txn, root.as_ref()),
+            Some(TypePath {
+                root: "root".to_string(),
+                steps: vec![]
+            })
+        );
+        assert_eq!(
+            type_path(&txn, text.as_ref()),
+            Some(TypePath {
+                root: "root".to_string(),
+                steps: vec![
+                    PathStep::Key("list".into()),
+                    PathStep::Index(1),
+                    PathStep::Key("text".into()),
+                ]
+            })
+        );
+
+        root.remove(&mut txn, "list");
+        assert_eq!(type_path(&txn, text.as_ref()), None);
+    }
+
+    #[test]
+    fn test_finds_subdocs() {
+        let doc = Doc::new();
+        let pages = doc.get_or_insert_array("pages");
+        let mut txn = doc.transact_mut();
+        pages.push_back(&mut txn, 1);
+        let page = pages.push_back(&mut txn, Doc::new());
+
+        assert_eq!(
+            subdoc_path(&txn, &page),
+            Some(TypePath {
+                root: "pages".to_string(),
+                steps: vec![PathStep::Index(1)]
+            })
+        );
+        assert_eq!(subdoc_path(&txn, &Doc::new()), None);
+    }
+}
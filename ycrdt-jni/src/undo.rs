@@ -0,0 +1,426 @@
+//! Undo and redo of local changes through yrs' `UndoManager`.
+//!
+//! A manager captures changes to the shared types in its scope made by
+//! transactions it tracks. Until an origin is included, it tracks only
+//! transactions without an origin. Once origins are included, it tracks only
+//! transactions carrying one of them, so updates applied under a different
+//! origin (for example one naming a remote peer) are never undone locally.
+//! Origins given from Java are the UTF-8 bytes of the string, the same bytes
+//! `nativeBeginTransactionWithOrigin` attaches to a transaction.
+//!
+//! yrs registers and removes the manager's document observers with a
+//! non-blocking write lock and panics if it is not available, so creation
+//! and destruction wait for open transactions to finish first. The Java side
+//! rejects those calls on a thread that holds a transaction itself, and the
+//! natives that wait check `txn_guard` as well.
+
+use crate::{
+    check_not_held_or_throw, check_writable_or_throw, free_if_valid, get_mut_or_throw,
+    get_ref_or_throw, get_string_or_throw, throw_jni_error, to_java_ptr, trace_native, ArrayPtr,
+    DocPtr, DocWrapper, JavaPtr, JniEnvExt, JniResult, MapPtr, TextPtr,
+};
+#[cfg(feature = "xml")]
+use crate::{XmlElementPtr, XmlFragmentPtr, XmlTextPtr};
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::{jboolean, jint, jlong, JNI_FALSE, JNI_TRUE};
+use jni::JNIEnv;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use yrs::branch::{Branch, BranchPtr};
+use yrs::sync::time::Clock;
+use yrs::undo::Options;
+use yrs::{Transact, UndoManager};
+
+/// Scope kinds, matching the `JniYUndoManager` scope constants
+pub const UNDO_SCOPE_TEXT: i32 = 0;
+pub const UNDO_SCOPE_ARRAY: i32 = 1;
+pub const UNDO_SCOPE_MAP: i32 = 2;
+pub const UNDO_SCOPE_XML_FRAGMENT: i32 = 3;
+pub const UNDO_SCOPE_XML_ELEMENT: i32 = 4;
+pub const UNDO_SCOPE_XML_TEXT: i32 = 5;
+
+pub type UndoManagerPtr = JavaPtr<UndoManager>;
+
+/// Blocks until no transaction is open on the document
+///
+/// yrs acquires its observer lock without blocking, so this narrows the
+/// window in which another thread could hold a transaction.
+fn wait_for_transactions(wrapper: &DocWrapper) {
+    drop(wrapper.doc.transact_mut());
+}
+
+/// Creates an undo manager with an empty scope
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `capture_timeout_millis`: Changes made within this many milliseconds of
+///   each other are undone together
+///
+/// # Returns
+/// A pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCreate(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    capture_timeout_millis: jlong,
+) -> jlong {
+    trace_native!("JniYUndoManager.nativeCreate", doc_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let options = Options {
+        capture_timeout_millis: u64::try_from(capture_timeout_millis).unwrap_or(0),
+        ..Options::default()
+    };
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+    wait_for_transactions(wrapper);
+    to_java_ptr(UndoManager::with_options(&wrapper.doc, options))
+}
+
+/// Creates an undo manager with an empty scope whose capture timeout is
+/// measured with a clock supplied from Java
+///
+/// The clock is read on the committing thread after every tracked
+/// transaction. If it throws or returns a negative time, the exception is
+/// cleared and its last reading is used for that transaction, so timestamps
+/// from the clock are never compared with the system time.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `capture_timeout_millis`: Changes made within this many milliseconds of
+///   each other, according to the clock, are undone together
+/// - `clock`: A `java.util.function.LongSupplier` returning milliseconds
+///
+/// # Returns
+/// A pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCreateWithClock(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    capture_timeout_millis: jlong,
+    clock: JObject,
+) -> jlong {
+    trace_native!("JniYUndoManager.nativeCreateWithClock", doc_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let clock = match java_clock(&mut env, wrapper, &clock) {
+        Ok(clock) => clock,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return 0;
+        }
+    };
+    let options = Options {
+        capture_timeout_millis: u64::try_from(capture_timeout_millis).unwrap_or(0),
+        timestamp: clock,
+        ..Options::default()
+    };
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+    wait_for_transactions(wrapper);
+    to_java_ptr(UndoManager::with_options(&wrapper.doc, options))
+}
+
+/// Wraps a Java `LongSupplier` as a yrs clock that repeats its last reading,
+/// or 0 before the first, when the supplier fails
+fn java_clock(
+    env: &mut JNIEnv,
+    wrapper: &DocWrapper,
+    clock: &JObject,
+) -> JniResult<Arc<dyn Clock>> {
+    let executor = wrapper.executor(env)?;
+    let clock = env.new_global_ref(clock)?;
+    let last = AtomicU64::new(0);
+    Ok(Arc::new(move || {
+        let now = executor
+            .with_attached(|env| {
+                match env
+                    .call_method(&clock, "getAsLong", "()J", &[])
+                    .and_then(|v| v.j())
+                {
+                    Ok(millis) => Ok(u64::try_from(millis).ok()),
+                    Err(_) => {
+                        env.exception_clear()?;
+                        Ok::<_, jni::errors::Error>(None)
+                    }
+                }
+            })
+            .ok()
+            .flatten();
+        match now {
+            Some(now) => {
+                last.store(now, Ordering::Relaxed);
+                now
+            }
+            None => last.load(Ordering::Relaxed),
+        }
+    }))
+}
+
+/// Destroys an undo manager, waiting for open transactions to finish first
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `ptr`: Pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeDestroy(
+    _env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+) {
+    trace_native!("JniYUndoManager.nativeDestroy", doc_ptr, ptr);
+    if let Some(wrapper) = unsafe { DocPtr::from_raw(doc_ptr).as_ref() } {
+        wait_for_transactions(wrapper);
+    }
+    free_if_valid!(UndoManagerPtr::from_raw(ptr), UndoManager);
+}
+
+/// Adds a shared type to the scope of an undo manager
+///
+/// # Parameters
+/// - `ptr`: Pointer to the undo manager
+/// - `kind`: One of the `UNDO_SCOPE_*` constants
+/// - `type_ptr`: Pointer to the shared type
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeExpandScope(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    kind: jint,
+    type_ptr: jlong,
+) {
+    trace_native!("JniYUndoManager.nativeExpandScope", ptr, type_ptr);
+    let manager = get_mut_or_throw!(&mut env, UndoManagerPtr::from_raw(ptr), "YUndoManager");
+    let branch: &Branch = match kind {
+        UNDO_SCOPE_TEXT => {
+            get_ref_or_throw!(&mut env, TextPtr::from_raw(type_ptr), "YText").as_ref()
+        }
+        UNDO_SCOPE_ARRAY => {
+            get_ref_or_throw!(&mut env, ArrayPtr::from_raw(type_ptr), "YArray").as_ref()
+        }
+        UNDO_SCOPE_MAP => get_ref_or_throw!(&mut env, MapPtr::from_raw(type_ptr), "YMap").as_ref(),
+        #[cfg(feature = "xml")]
+        UNDO_SCOPE_XML_FRAGMENT => {
+            get_ref_or_throw!(&mut env, XmlFragmentPtr::from_raw(type_ptr), "YXmlFragment").as_ref()
+        }
+        #[cfg(feature = "xml")]
+        UNDO_SCOPE_XML_ELEMENT => {
+            get_ref_or_throw!(&mut env, XmlElementPtr::from_raw(type_ptr), "YXmlElement").as_ref()
+        }
+        #[cfg(feature = "xml")]
+        UNDO_SCOPE_XML_TEXT => {
+            get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(type_ptr), "YXmlText").as_ref()
+        }
+        _ => {
+            crate::throw_exception(&mut env, &format!("Unsupported scope kind: {}", kind));
+            return;
+        }
+    };
+    manager.expand_scope(&BranchPtr::from(branch));
+}
+
+/// Starts or stops tracking transactions with the given origin
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance whose string policy applies
+/// - `ptr`: Pointer to the undo manager
+/// - `origin`: The origin
+/// - `tracked`: Whether to include or exclude the origin
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeSetOriginTracked(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+    origin: JString,
+    tracked: jboolean,
+) {
+    trace_native!("JniYUndoManager.nativeSetOriginTracked", doc_ptr, ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let manager = get_mut_or_throw!(&mut env, UndoManagerPtr::from_raw(ptr), "YUndoManager");
+    let origin = get_string_or_throw!(&mut env, wrapper, origin);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return;
+    }
+    // Commits read the tracked origins, so hold off writers while changing them
+    let _txn = wrapper.doc.transact();
+    if tracked != 0 {
+        manager.include_origin(origin.as_str());
+    } else {
+        manager.exclude_origin(origin.as_str());
+    }
+}
+
+/// Undoes the last captured change
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `ptr`: Pointer to the undo manager
+///
+/// # Returns
+/// True if anything was undone
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeUndo(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYUndoManager.nativeUndo", doc_ptr, ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JNI_FALSE);
+    check_writable_or_throw!(&mut env, wrapper, JNI_FALSE);
+    let manager = get_mut_or_throw!(
+        &mut env,
+        UndoManagerPtr::from_raw(ptr),
+        "YUndoManager",
+        JNI_FALSE
+    );
+    to_jboolean(manager.undo_blocking())
+}
+
+/// Redoes the last undone change
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `ptr`: Pointer to the undo manager
+///
+/// # Returns
+/// True if anything was redone
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeRedo(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYUndoManager.nativeRedo", doc_ptr, ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JNI_FALSE);
+    check_writable_or_throw!(&mut env, wrapper, JNI_FALSE);
+    let manager = get_mut_or_throw!(
+        &mut env,
+        UndoManagerPtr::from_raw(ptr),
+        "YUndoManager",
+        JNI_FALSE
+    );
+    to_jboolean(manager.redo_blocking())
+}
+
+/// Checks if there is a change to undo
+///
+/// # Parameters
+/// - `ptr`: Pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCanUndo(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYUndoManager.nativeCanUndo", ptr);
+    let manager = get_ref_or_throw!(
+        &mut env,
+        UndoManagerPtr::from_raw(ptr),
+        "YUndoManager",
+        JNI_FALSE
+    );
+    to_jboolean(manager.can_undo())
+}
+
+/// Checks if there is a change to redo
+///
+/// # Parameters
+/// - `ptr`: Pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeCanRedo(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYUndoManager.nativeCanRedo", ptr);
+    let manager = get_ref_or_throw!(
+        &mut env,
+        UndoManagerPtr::from_raw(ptr),
+        "YUndoManager",
+        JNI_FALSE
+    );
+    to_jboolean(manager.can_redo())
+}
+
+/// Drops every captured change
+///
+/// # Parameters
+/// - `ptr`: Pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeClear(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) {
+    trace_native!("JniYUndoManager.nativeClear", ptr);
+    let manager = get_mut_or_throw!(&mut env, UndoManagerPtr::from_raw(ptr), "YUndoManager");
+    manager.clear();
+}
+
+/// Ends the current capture group, so the next change is undone separately
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `ptr`: Pointer to the undo manager
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYUndoManager_nativeStopCapturing(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+) {
+    trace_native!("JniYUndoManager.nativeStopCapturing", doc_ptr, ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let manager = get_mut_or_throw!(&mut env, UndoManagerPtr::from_raw(ptr), "YUndoManager");
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return;
+    }
+    // Commits update the capture group, so hold off writers while ending it
+    let _txn = wrapper.doc.transact();
+    manager.reset();
+}
+
+fn to_jboolean(value: bool) -> jboolean {
+    if value {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::decoder::Decode;
+    use yrs::{GetString, ReadTxn, Text, Update};
+
+    #[test]
+    fn test_other_origins_are_not_undone() {
+        let doc = yrs::Doc::new();
+        let text = doc.get_or_insert_text("t");
+        let mut manager = UndoManager::with_options(&doc, Options::default());
+        manager.expand_scope(&text);
+        manager.include_origin("local");
+
+        text.insert(&mut doc.transact_mut_with("local"), 0, "a");
+        let remote = yrs::Doc::new();
+        let remote_text = remote.get_or_insert_text("t");
+        remote_text.insert(&mut remote.transact_mut(), 0, "b");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&Default::default());
+        doc.transact_mut_with("remote")
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+
+        assert!(manager.undo_blocking());
+        assert_eq!(text.get_string(&doc.transact()), "b");
+        assert!(!manager.can_undo());
+    }
+}
@@ -0,0 +1,693 @@
+//! Filtering of incoming updates by root type.
+//!
+//! An update is decoded struct by struct and every item is traced back to the
+//! root type it belongs to, either directly (items that name their root), via
+//! the nested type they were inserted into, or via their left/right origin.
+//! References that point outside the update are resolved against the current
+//! state of the document. Each distinct root is passed to a permission check
+//! once; items under denied roots are rewritten as GC structs of the same
+//! length, so clocks stay contiguous and later updates from the same client
+//! still integrate. Deletions are split along item boundaries and kept only
+//! for allowed roots.
+//!
+//! Content that cannot be traced to a root (because it depends on structs the
+//! document has not received, or on content that was garbage collected) is
+//! treated as denied.
+//!
+//! The same filter narrows an outgoing diff to the roots a peer subscribes to.
+//! The peer receives the other roots' content as GC structs, so it keeps the
+//! clocks of that content without the content itself and cannot receive it
+//! later.
+
+use crate::UpdateDecodeError;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::convert::Infallible;
+use std::ops::Range;
+use std::sync::Arc;
+use yrs::any::Any;
+use yrs::block::{
+    ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB,
+    HAS_RIGHT_ORIGIN,
+};
+use yrs::encoding::read::{Cursor, Error as DecodeError, Read};
+use yrs::encoding::write::Write;
+use yrs::updates::decoder::{Decode, Decoder};
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::{DeleteSet, OffsetKind, ReadTxn, StateVector, ID};
+
+/// Error returned by [`filter_update_v1`]
+#[derive(Debug)]
+pub enum FilterError<E> {
+    /// The update could not be decoded
+    Decode(UpdateDecodeError),
+    /// The permission check failed
+    Check(E),
+}
+
+/// Result of filtering an update
+#[derive(Debug, Default)]
+pub struct FilteredUpdate {
+    /// The rewritten v1 update, containing only content under allowed roots
+    pub update: Vec<u8>,
+    /// Roots touched by the update that were allowed
+    pub allowed: BTreeSet<String>,
+    /// Roots touched by the update that were denied
+    pub denied: BTreeSet<String>,
+    /// Whether content that could not be traced to a root was dropped
+    pub dropped_unresolved: bool,
+}
+
+/// Filters a v1 update so that only changes under roots accepted by `allow`
+/// remain. `allow` is called once per distinct root, in name order.
+pub fn filter_update_v1<T, E, F>(
+    txn: &T,
+    update: &[u8],
+    mut allow: F,
+) -> Result<FilteredUpdate, FilterError<E>>
+where
+    T: ReadTxn,
+    F: FnMut(&str) -> Result<bool, E>,
+{
+    let incoming = ParsedUpdate::parse(update).map_err(FilterError::Decode)?;
+    let mut index = RootIndex::default();
+    let item_nodes: Vec<usize> = incoming.structs.iter().map(|s| index.add(s)).collect();
+
+    let needs_local =
+        !incoming.delete_set.is_empty() || item_nodes.iter().any(|&n| index.resolve(n).is_none());
+    if needs_local {
+        // Resolve references to existing content against the document state
+        let local = txn.encode_state_as_update_v1(&StateVector::default());
+        let local = ParsedUpdate::parse(&local).map_err(FilterError::Decode)?;
+        index.reset_unresolved();
+        for s in &local.structs {
+            index.add(s);
+        }
+    }
+
+    let item_roots: Vec<Option<usize>> = item_nodes.iter().map(|&n| index.resolve(n)).collect();
+    let deletions = index.split_deletions(&incoming.delete_set);
+
+    let mut touched: BTreeSet<usize> = item_roots.iter().flatten().copied().collect();
+    touched.extend(deletions.iter().filter_map(|d| d.root));
+
+    let mut result = FilteredUpdate::default();
+    let mut allowed_roots = vec![false; index.roots.len()];
+    let mut names: Vec<(&str, usize)> = touched
+        .iter()
+        .map(|&r| (index.roots[r].as_ref(), r))
+        .collect();
+    names.sort();
+    for (name, root) in names {
+        if allow(name).map_err(FilterError::Check)? {
+            allowed_roots[root] = true;
+            result.allowed.insert(name.to_string());
+        } else {
+            result.denied.insert(name.to_string());
+        }
+    }
+    let is_allowed = |root: Option<usize>| root.is_some_and(|r| allowed_roots[r]);
+
+    let mut out = Vec::with_capacity(update.len());
+    let mut copied = 0;
+    for (s, root) in incoming.structs.iter().zip(&item_roots) {
+        if !s.is_item() || s.len == 0 || is_allowed(*root) {
+            continue;
+        }
+        if root.is_none() {
+            result.dropped_unresolved = true;
+        }
+        out.extend_from_slice(&update[copied..s.span.start]);
+        out.write_u8(BLOCK_GC_REF_NUMBER);
+        out.write_var(s.len);
+        copied = s.span.end;
+    }
+    out.extend_from_slice(&update[copied..incoming.delete_set_start]);
+
+    let mut delete_set = DeleteSet::new();
+    for d in &deletions {
+        if is_allowed(d.root) {
+            delete_set.insert(ID::new(d.client, d.clocks.start), d.clocks.len() as u32);
+        } else if d.root.is_none() {
+            result.dropped_unresolved = true;
+        }
+    }
+    delete_set.squash();
+    let mut encoder = EncoderV1::new();
+    delete_set.encode(&mut encoder);
+    out.extend_from_slice(&encoder.to_vec());
+
+    result.update = out;
+    Ok(result)
+}
+
+/// Encodes the changes missing from `state_vector`, keeping only content under
+/// the roots in `roots`
+pub fn encode_roots_diff_v1<T: ReadTxn>(
+    txn: &T,
+    state_vector: &StateVector,
+    roots: &HashSet<String>,
+) -> Result<Vec<u8>, UpdateDecodeError> {
+    let diff = txn.encode_diff_v1(state_vector);
+    filter_update_v1(txn, &diff, |root| Ok::<_, Infallible>(roots.contains(root)))
+        .map(|filtered| filtered.update)
+        .map_err(|e| match e {
+            FilterError::Decode(e) => e,
+            FilterError::Check(e) => match e {},
+        })
+}
+
+/// Counts the structs (items, GC and skip blocks) in a v1 update
+pub fn count_structs_v1(update: &[u8]) -> Result<usize, UpdateDecodeError> {
+    Ok(ParsedUpdate::parse(update)?.structs.len())
+}
+
+/// Numbers of structs of one client in an update
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StructCounts {
+    /// Items, deleted or not
+    pub items: u64,
+    /// Items covered by the update's delete set
+    pub deleted_items: u64,
+    /// GC and skip blocks
+    pub gc: u64,
+}
+
+/// Counts the structs in a v1 update per client
+pub fn count_structs_by_client_v1(
+    update: &[u8],
+) -> Result<BTreeMap<u64, StructCounts>, UpdateDecodeError> {
+    let parsed = ParsedUpdate::parse(update)?;
+    let mut counts: BTreeMap<u64, StructCounts> = BTreeMap::new();
+    for s in &parsed.structs {
+        let entry = counts.entry(s.id.client).or_default();
+        if !s.is_item() {
+            entry.gc += 1;
+        } else {
+            entry.items += 1;
+            // Blocks are split where deletions start and end, so an item is
+            // either wholly deleted or not at all
+            if parsed.delete_set.is_deleted(&s.id) {
+                entry.deleted_items += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// How a struct is linked to its root
+#[derive(Debug, Clone)]
+enum Link {
+    /// The item names its root type directly
+    Root(Arc<str>),
+    /// The root is that of another item: the nested type the item was
+    /// inserted into, or one of its origins
+    Via(ID),
+    /// GC and skip structs carry no parent
+    None,
+}
+
+/// A struct decoded from an update
+#[derive(Debug)]
+struct ParsedStruct {
+    id: ID,
+    len: u32,
+    link: Link,
+    span: Range<usize>,
+}
+
+impl ParsedStruct {
+    fn is_item(&self) -> bool {
+        !matches!(self.link, Link::None)
+    }
+}
+
+/// Structs and delete set of a v1 update, with the byte span of each struct
+struct ParsedUpdate {
+    structs: Vec<ParsedStruct>,
+    delete_set: DeleteSet,
+    delete_set_start: usize,
+}
+
+impl ParsedUpdate {
+    fn parse(update: &[u8]) -> Result<Self, UpdateDecodeError> {
+        let mut decoder = SpanDecoder::new(update);
+        Self::parse_from(&mut decoder)
+            .map_err(|e| UpdateDecodeError::new(e, update, decoder.cursor.next))
+    }
+
+    fn parse_from(decoder: &mut SpanDecoder) -> Result<Self, DecodeError> {
+        let mut structs = Vec::new();
+        let clients: u32 = decoder.read_var()?;
+        for _ in 0..clients {
+            let count: u32 = decoder.read_var()?;
+            let client = decoder.read_client()?;
+            let mut clock: u32 = decoder.read_var()?;
+            for _ in 0..count {
+                let start = decoder.cursor.next;
+                let (len, link) = decoder.read_struct()?;
+                structs.push(ParsedStruct {
+                    id: ID::new(client, clock),
+                    len,
+                    link,
+                    span: start..decoder.cursor.next,
+                });
+                // A crafted update can claim clocks past the end of the range
+                clock = clock.checked_add(len).ok_or(DecodeError::UnexpectedValue)?;
+            }
+        }
+        let delete_set_start = decoder.cursor.next;
+        let delete_set = DeleteSet::decode(decoder)?;
+        Ok(ParsedUpdate {
+            structs,
+            delete_set,
+            delete_set_start,
+        })
+    }
+}
+
+/// Resolution state of an index node
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Unknown,
+    InProgress,
+    Done(Option<usize>),
+}
+
+/// Clock range of one client mapped to the root it belongs to
+#[derive(Debug)]
+struct Deletion {
+    client: u64,
+    clocks: Range<u32>,
+    root: Option<usize>,
+}
+
+/// Maps item IDs to the root type they belong to
+#[derive(Default)]
+struct RootIndex {
+    by_id: BTreeMap<(u64, u32), usize>,
+    nodes: Vec<(ID, u32, Link)>,
+    state: Vec<State>,
+    roots: Vec<Arc<str>>,
+}
+
+impl RootIndex {
+    fn add(&mut self, s: &ParsedStruct) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push((s.id, s.len, s.link.clone()));
+        self.state.push(State::Unknown);
+        if s.is_item() && s.len > 0 {
+            self.by_id.entry((s.id.client, s.id.clock)).or_insert(node);
+        }
+        node
+    }
+
+    /// Forgets failed resolutions so they are retried after more nodes are added
+    fn reset_unresolved(&mut self) {
+        for state in &mut self.state {
+            if *state == State::Done(None) {
+                *state = State::Unknown;
+            }
+        }
+    }
+
+    /// Finds the node covering an ID
+    fn find(&self, id: &ID) -> Option<usize> {
+        let (_, &node) = self
+            .by_id
+            .range(..=(id.client, id.clock))
+            .next_back()
+            .filter(|((client, _), _)| *client == id.client)?;
+        let (start, len, _) = &self.nodes[node];
+        (id.clock < start.clock + len).then_some(node)
+    }
+
+    fn intern(&mut self, name: &Arc<str>) -> usize {
+        match self.roots.iter().position(|r| r == name) {
+            Some(i) => i,
+            None => {
+                self.roots.push(name.clone());
+                self.roots.len() - 1
+            }
+        }
+    }
+
+    /// Returns the root of a node, following links iteratively since origin
+    /// chains can be as long as the document
+    fn resolve(&mut self, node: usize) -> Option<usize> {
+        let mut path = Vec::new();
+        let mut current = node;
+        let result = loop {
+            match self.state[current] {
+                State::Done(root) => break root,
+                State::InProgress => break None,
+                State::Unknown => {}
+            }
+            self.state[current] = State::InProgress;
+            path.push(current);
+            match self.nodes[current].2.clone() {
+                Link::Root(name) => break Some(self.intern(&name)),
+                Link::Via(id) => match self.find(&id) {
+                    Some(next) => current = next,
+                    None => break None,
+                },
+                Link::None => break None,
+            }
+        };
+        for n in path {
+            self.state[n] = State::Done(result);
+        }
+        result
+    }
+
+    /// Splits deleted ranges along item boundaries and resolves each part
+    fn split_deletions(&mut self, delete_set: &DeleteSet) -> Vec<Deletion> {
+        let mut result = Vec::new();
+        for (&client, ranges) in delete_set.iter() {
+            for range in ranges.iter() {
+                let mut clock = range.start;
+                while clock < range.end {
+                    let (end, root) = match self.find(&ID::new(client, clock)) {
+                        Some(node) => {
+                            let (start, len, _) = self.nodes[node];
+                            (start.clock + len, self.resolve(node))
+                        }
+                        None => {
+                            // Not known here; skip to the next known item
+                            let next = self
+                                .by_id
+                                .range((client, clock)..(client, range.end))
+                                .next()
+                                .map(|((_, c), _)| *c);
+                            (next.unwrap_or(range.end), None)
+                        }
+                    };
+                    let end = end.min(range.end);
+                    result.push(Deletion {
+                        client,
+                        clocks: clock..end,
+                        root,
+                    });
+                    clock = end;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// A lib0 v1 decoder that exposes its position in the buffer
+pub(crate) struct SpanDecoder<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> SpanDecoder<'a> {
+    /// Decodes `buf` from its start
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        SpanDecoder {
+            cursor: Cursor::new(buf),
+        }
+    }
+
+    /// Number of bytes read so far
+    pub(crate) fn position(&self) -> usize {
+        self.cursor.next
+    }
+
+    /// Reads one struct and returns its clock length
+    pub(crate) fn skip_struct(&mut self) -> Result<u32, DecodeError> {
+        self.read_struct().map(|(len, _)| len)
+    }
+}
+
+impl SpanDecoder<'_> {
+    fn read_id(&mut self) -> Result<ID, DecodeError> {
+        let client: u32 = self.read_var()?;
+        let clock = self.read_var()?;
+        Ok(ID::new(client as u64, clock))
+    }
+
+    /// Reads one struct, returning its clock length and how it links to its root
+    fn read_struct(&mut self) -> Result<(u32, Link), DecodeError> {
+        let info = self.read_info()?;
+        match info {
+            BLOCK_SKIP_REF_NUMBER => Ok((self.read_var()?, Link::None)),
+            BLOCK_GC_REF_NUMBER => Ok((self.read_len()?, Link::None)),
+            info => {
+                let origin = (info & HAS_ORIGIN != 0)
+                    .then(|| self.read_left_id())
+                    .transpose()?;
+                let right_origin = (info & HAS_RIGHT_ORIGIN != 0)
+                    .then(|| self.read_right_id())
+                    .transpose()?;
+                let link = match origin.or(right_origin) {
+                    Some(id) => Link::Via(id),
+                    None => {
+                        let link = if self.read_parent_info()? {
+                            Link::Root(self.read_string()?.into())
+                        } else {
+                            Link::Via(self.read_left_id()?)
+                        };
+                        if info & HAS_PARENT_SUB != 0 {
+                            self.read_string()?;
+                        }
+                        link
+                    }
+                };
+                let content = ItemContent::decode(self, info)?;
+                Ok((content.len(OffsetKind::Utf16), link))
+            }
+        }
+    }
+}
+
+impl Read for SpanDecoder<'_> {
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        self.cursor.read_u8()
+    }
+
+    fn read_exact(&mut self, len: usize) -> Result<&[u8], DecodeError> {
+        self.cursor.read_exact(len)
+    }
+}
+
+impl Decoder for SpanDecoder<'_> {
+    fn reset_ds_cur_val(&mut self) {}
+
+    fn read_ds_clock(&mut self) -> Result<u32, DecodeError> {
+        self.read_var()
+    }
+
+    fn read_ds_len(&mut self) -> Result<u32, DecodeError> {
+        self.read_var()
+    }
+
+    fn read_left_id(&mut self) -> Result<ID, DecodeError> {
+        self.read_id()
+    }
+
+    fn read_right_id(&mut self) -> Result<ID, DecodeError> {
+        self.read_id()
+    }
+
+    fn read_client(&mut self) -> Result<u64, DecodeError> {
+        let client: u32 = self.read_var()?;
+        Ok(client as u64)
+    }
+
+    fn read_info(&mut self) -> Result<u8, DecodeError> {
+        self.read_u8()
+    }
+
+    fn read_parent_info(&mut self) -> Result<bool, DecodeError> {
+        let info: u32 = self.read_var()?;
+        Ok(info == 1)
+    }
+
+    fn read_type_ref(&mut self) -> Result<u8, DecodeError> {
+        self.read_u8()
+    }
+
+    fn read_len(&mut self) -> Result<u32, DecodeError> {
+        self.read_var()
+    }
+
+    fn read_any(&mut self) -> Result<Any, DecodeError> {
+        Any::decode(self)
+    }
+
+    fn read_json(&mut self) -> Result<Any, DecodeError> {
+        let src = self.read_string()?;
+        Any::from_json(src)
+    }
+
+    fn read_key(&mut self) -> Result<Arc<str>, DecodeError> {
+        Ok(self.read_string()?.into())
+    }
+
+    fn read_to_end(&mut self) -> Result<&[u8], DecodeError> {
+        Ok(&self.cursor.buf[self.cursor.next..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::updates::decoder::Decode;
+    use yrs::{Doc, GetString, Map, Text, Transact, Update};
+
+    fn filter(doc: &Doc, update: &[u8], allowed: &[&str]) -> FilteredUpdate {
+        let txn = doc.transact();
+        filter_update_v1(&txn, update, |root| {
+            Ok::<_, Infallible>(allowed.contains(&root))
+        })
+        .unwrap()
+    }
+
+    fn apply(doc: &Doc, update: &[u8]) {
+        let mut txn = doc.transact_mut();
+        txn.apply_update(Update::decode_v1(update).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_denied_root_is_dropped() {
+        let source = Doc::with_client_id(1);
+        let public = source.get_or_insert_text("public");
+        let secret = source.get_or_insert_map("secret");
+        {
+            let mut txn = source.transact_mut();
+            public.insert(&mut txn, 0, "hello");
+            secret.insert(&mut txn, "key", "value");
+            public.insert(&mut txn, 5, " world");
+        }
+        let update = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let target = Doc::with_client_id(2);
+        let result = filter(&target, &update, &["public"]);
+        assert_eq!(result.allowed, BTreeSet::from(["public".to_string()]));
+        assert_eq!(result.denied, BTreeSet::from(["secret".to_string()]));
+        apply(&target, &result.update);
+
+        let txn = target.transact();
+        let text = txn.get_text("public").unwrap();
+        assert_eq!(text.get_string(&txn), "hello world");
+        assert!(txn.get_map("secret").is_none_or(|m| m.len(&txn) == 0));
+        // Clocks stay contiguous, so the whole update counts as received
+        assert_eq!(txn.state_vector(), source.transact().state_vector());
+    }
+
+    #[test]
+    fn test_diff_is_encoded_for_selected_roots() {
+        let source = Doc::with_client_id(1);
+        let public = source.get_or_insert_text("public");
+        let secret = source.get_or_insert_map("secret");
+        public.insert(&mut source.transact_mut(), 0, "hello");
+        let target = Doc::with_client_id(2);
+        let roots = HashSet::from(["public".to_string()]);
+        let initial = encode_roots_diff_v1(&source.transact(), &StateVector::default(), &roots);
+        apply(&target, &initial.unwrap());
+
+        {
+            let mut txn = source.transact_mut();
+            secret.insert(&mut txn, "key", "value");
+            public.insert(&mut txn, 5, " world");
+            public.remove_range(&mut txn, 0, 1);
+        }
+        let sv = target.transact().state_vector();
+        let diff = encode_roots_diff_v1(&source.transact(), &sv, &roots);
+        apply(&target, &diff.unwrap());
+
+        let txn = target.transact();
+        let text = txn.get_text("public").unwrap();
+        assert_eq!(text.get_string(&txn), "ello world");
+        assert!(txn.get_map("secret").is_none_or(|m| m.len(&txn) == 0));
+        assert_eq!(txn.state_vector(), source.transact().state_vector());
+    }
+
+    #[test]
+    fn test_nested_and_deleted_content_follows_root() {
+        let source = Doc::with_client_id(1);
+        let public = source.get_or_insert_text("public");
+        let secret = source.get_or_insert_map("secret");
+        {
+            let mut txn = source.transact_mut();
+            public.insert(&mut txn, 0, "abc");
+            let nested = secret.insert(&mut txn, "nested", yrs::TextPrelim::new("xyz"));
+            nested.insert(&mut txn, 3, "!");
+        }
+        let target = Doc::with_client_id(2);
+        apply(
+            &target,
+            &source
+                .transact()
+                .encode_state_as_update_v1(&StateVector::default()),
+        );
+
+        let before = target.transact().state_vector();
+        {
+            let mut txn = source.transact_mut();
+            public.remove_range(&mut txn, 0, 1);
+            let nested = secret.get(&txn, "nested").unwrap();
+            let nested: yrs::TextRef = nested.cast().unwrap();
+            nested.remove_range(&mut txn, 0, 2);
+            nested.insert(&mut txn, 0, "q");
+        }
+        let update = source.transact().encode_diff_v1(&before);
+
+        let result = filter(&target, &update, &["public"]);
+        assert_eq!(result.denied, BTreeSet::from(["secret".to_string()]));
+        assert!(!result.dropped_unresolved);
+        apply(&target, &result.update);
+
+        let txn = target.transact();
+        assert_eq!(txn.get_text("public").unwrap().get_string(&txn), "bc");
+        let nested: yrs::TextRef = txn
+            .get_map("secret")
+            .unwrap()
+            .get(&txn, "nested")
+            .unwrap()
+            .cast()
+            .unwrap();
+        assert_eq!(nested.get_string(&txn), "xyz!");
+    }
+
+    #[test]
+    fn test_all_allowed_keeps_update_equivalent() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("t");
+        {
+            let mut txn = source.transact_mut();
+            text.insert(&mut txn, 0, "one two");
+            text.remove_range(&mut txn, 3, 4);
+        }
+        let update = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let target = Doc::new();
+        let result = filter(&target, &update, &["t"]);
+        assert!(result.denied.is_empty());
+        apply(&target, &result.update);
+        let txn = target.transact();
+        assert_eq!(txn.get_text("t").unwrap().get_string(&txn), "one");
+    }
+
+    #[test]
+    fn test_malformed_update_is_rejected() {
+        let doc = Doc::new();
+        let txn = doc.transact();
+        let result = filter_update_v1(&txn, &[5, 1, 2], |_| Ok::<_, Infallible>(true));
+        assert!(matches!(result, Err(FilterError::Decode(_))));
+    }
+
+    #[test]
+    fn test_overflowing_clock_is_rejected() {
+        let doc = Doc::new();
+        let txn = doc.transact();
+        // One client with a GC struct of length 2 starting at clock u32::MAX
+        let update = [1, 1, 1, 0xff, 0xff, 0xff, 0xff, 0x0f, 0, 2, 0];
+        let result = filter_update_v1(&txn, &update, |_| Ok::<_, Infallible>(true));
+        assert!(matches!(result, Err(FilterError::Decode(_))));
+    }
+}
@@ -0,0 +1,129 @@
+//! Size of a differential update, measured without building it.
+//!
+//! A transport deciding between sending a diff and asking the peer to load a
+//! fresh snapshot only needs the size of the diff. [`diff_size_v1`] runs the
+//! same encoding as `encode_diff_v1` through an encoder that counts bytes
+//! instead of storing them, so the result is exact but no buffer the size of
+//! the update is allocated or copied to Java.
+
+use yrs::block::{ClientID, ID};
+use yrs::encoding::write::Write;
+use yrs::updates::encoder::Encoder;
+use yrs::{Any, ReadTxn, StateVector};
+
+/// Encoder that counts the bytes of the lib0 v1 encoding
+#[derive(Default)]
+struct SizeEncoderV1 {
+    len: u64,
+}
+
+impl Write for SizeEncoderV1 {
+    fn write_all(&mut self, buf: &[u8]) {
+        self.len += buf.len() as u64;
+    }
+
+    fn write_u8(&mut self, _value: u8) {
+        self.len += 1;
+    }
+}
+
+// Mirrors `EncoderV1`, which writes every field directly
+impl Encoder for SizeEncoderV1 {
+    fn to_vec(self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn reset_ds_cur_val(&mut self) {}
+
+    fn write_ds_clock(&mut self, clock: u32) {
+        self.write_var(clock)
+    }
+
+    fn write_ds_len(&mut self, len: u32) {
+        self.write_var(len)
+    }
+
+    fn write_left_id(&mut self, id: &ID) {
+        self.write_var(id.client);
+        self.write_var(id.clock);
+    }
+
+    fn write_right_id(&mut self, id: &ID) {
+        self.write_left_id(id)
+    }
+
+    fn write_client(&mut self, client: ClientID) {
+        self.write_var(client)
+    }
+
+    fn write_info(&mut self, info: u8) {
+        self.write_u8(info)
+    }
+
+    fn write_parent_info(&mut self, is_y_key: bool) {
+        self.write_var(u32::from(is_y_key))
+    }
+
+    fn write_type_ref(&mut self, info: u8) {
+        self.write_u8(info)
+    }
+
+    fn write_len(&mut self, len: u32) {
+        self.write_var(len)
+    }
+
+    fn write_any(&mut self, any: &Any) {
+        any.encode(self)
+    }
+
+    fn write_json(&mut self, any: &Any) {
+        let mut buf = String::new();
+        any.to_json(&mut buf);
+        self.write_string(&buf)
+    }
+
+    fn write_key(&mut self, key: &str) {
+        self.write_string(key)
+    }
+}
+
+/// Length of the v1 update `encode_diff_v1(state_vector)` would return
+pub fn diff_size_v1<T: ReadTxn>(txn: &T, state_vector: &StateVector) -> u64 {
+    let mut encoder = SizeEncoderV1::default();
+    txn.encode_diff(state_vector, &mut encoder);
+    encoder.len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Array, Doc, Map, Text, Transact};
+
+    #[test]
+    fn test_size_matches_encoded_diff() {
+        let doc = Doc::with_options(yrs::Options::with_client_id(300));
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        let array = doc.get_or_insert_array("array");
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+        let before = doc.transact().state_vector();
+        {
+            let mut txn = doc.transact_mut();
+            text.remove_range(&mut txn, 0, 6);
+            map.insert(
+                &mut txn,
+                "key",
+                Any::from(vec![Any::from(1.5), Any::from("x")]),
+            );
+            array.push_back(&mut txn, Any::from(vec![1u8, 2, 3]));
+        }
+
+        let txn = doc.transact();
+        for sv in [StateVector::default(), before, txn.state_vector()] {
+            assert_eq!(
+                diff_size_v1(&txn, &sv),
+                txn.encode_diff_v1(&sv).len() as u64
+            );
+        }
+    }
+}
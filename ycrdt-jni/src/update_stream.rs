@@ -0,0 +1,545 @@
+//! Application of a v1 update that arrives in chunks.
+//!
+//! Decoding an update materializes all of its structs at once, so applying a
+//! large initial sync holds the encoded bytes, the decoded update and the
+//! integrated document in memory together. A stream instead buffers only the
+//! bytes not applied yet. Whenever complete structs are buffered, it wraps
+//! them in a standalone update of their own (one client, the clock of the
+//! first struct, an empty delete set) and applies that. Structs whose
+//! dependencies have not arrived yet wait in yrs' pending queue and are
+//! integrated by a later part.
+//!
+//! The delete set comes last in the update and can refer to any struct, so it
+//! is only read and applied when the stream is finished.
+//!
+//! A struct split across many small chunks is parsed again only once the
+//! buffer has doubled since the last attempt, keeping the parsing work linear
+//! in the size of the update.
+
+use crate::{
+    check_not_dispatching_or_throw, check_txn_doc_or_throw, decode_update_v1, free_if_valid,
+    get_mut_or_throw, get_ref_or_throw, mark_remote, throw_decode_error, throw_exception,
+    to_java_ptr, trace_native, DocPtr, DocWrapper, JavaPtr, SpanDecoder, TxnPtr, UpdateDecodeError,
+};
+use jni::objects::{JByteArray, JClass};
+use jni::sys::{jbyteArray, jint, jlong};
+use jni::JNIEnv;
+use yrs::encoding::read::{Error as DecodeError, Read};
+use yrs::encoding::write::Write;
+use yrs::error::UpdateError;
+use yrs::updates::decoder::{Decode, Decoder};
+use yrs::{DeleteSet, TransactionMut};
+
+/// Buffered bytes below which parsing is always attempted
+const MIN_PARSE_BYTES: usize = 64;
+
+/// Reasons a streamed update could not be applied
+#[derive(Debug)]
+pub enum StreamError {
+    /// The update is not valid v1 encoding; the offset is relative to the
+    /// bytes buffered when decoding failed
+    Decode(UpdateDecodeError),
+    /// yrs rejected part of the update
+    Apply(UpdateError),
+    /// The stream was finished before the whole update was written
+    Incomplete,
+    /// Bytes were written after the end of the update
+    TrailingBytes,
+}
+
+/// Part of the update the next buffered byte belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    /// Number of clients
+    Header,
+    /// Struct count, client and clock of the next client, with `clients`
+    /// clients left including it
+    Client { clients: u32 },
+    /// Structs of one client, `remaining` of them not applied yet
+    Structs {
+        clients: u32,
+        client: u64,
+        clock: u32,
+        remaining: u32,
+    },
+    /// The delete set, read when the stream is finished
+    DeleteSet,
+    /// The update has been applied
+    Done,
+}
+
+/// A v1 update applied as its bytes arrive
+pub struct UpdateStream {
+    /// Bytes written but not applied yet
+    buf: Vec<u8>,
+    section: Section,
+    /// Buffered bytes needed before parsing is attempted again
+    parse_at: usize,
+    /// Number of bytes written so far
+    bytes_written: u64,
+    /// Number of structs applied so far
+    structs_applied: u64,
+}
+
+impl Default for UpdateStream {
+    fn default() -> Self {
+        UpdateStream {
+            buf: Vec::new(),
+            section: Section::Header,
+            parse_at: 0,
+            bytes_written: 0,
+            structs_applied: 0,
+        }
+    }
+}
+
+impl UpdateStream {
+    /// Creates a stream expecting the start of an update
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of bytes written but not applied yet
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Number of bytes written so far
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Number of structs applied so far
+    pub fn structs_applied(&self) -> u64 {
+        self.structs_applied
+    }
+
+    /// Buffers `chunk` and applies every struct it completes
+    pub fn write(&mut self, txn: &mut TransactionMut, chunk: &[u8]) -> Result<(), StreamError> {
+        if self.section == Section::Done {
+            return Err(StreamError::TrailingBytes);
+        }
+        self.buf.extend_from_slice(chunk);
+        self.bytes_written += chunk.len() as u64;
+        if self.buf.len() < self.parse_at {
+            return Ok(());
+        }
+        let consumed = self.apply_structs(txn)?;
+        self.buf.drain(..consumed);
+        self.parse_at = if self.section == Section::DeleteSet {
+            usize::MAX
+        } else {
+            (self.buf.len() * 2).max(MIN_PARSE_BYTES)
+        };
+        Ok(())
+    }
+
+    /// Applies the delete set that ends the update
+    ///
+    /// Fails with [`StreamError::Incomplete`] if the update has not been
+    /// written in full.
+    pub fn finish(&mut self, txn: &mut TransactionMut) -> Result<(), StreamError> {
+        if self.section != Section::DeleteSet {
+            // Structs held back by the parse threshold may complete the update
+            let consumed = self.apply_structs(txn)?;
+            self.buf.drain(..consumed);
+        }
+        match self.section {
+            Section::DeleteSet => {}
+            Section::Done => return Ok(()),
+            _ => return Err(StreamError::Incomplete),
+        }
+        let mut decoder = SpanDecoder::new(&self.buf);
+        match DeleteSet::decode(&mut decoder) {
+            Ok(_) if decoder.position() < self.buf.len() => return Err(StreamError::TrailingBytes),
+            Ok(_) => {}
+            Err(DecodeError::EndOfBuffer(_)) => return Err(StreamError::Incomplete),
+            Err(e) => {
+                let offset = decoder.position();
+                return Err(StreamError::Decode(UpdateDecodeError::new(
+                    e, &self.buf, offset,
+                )));
+            }
+        }
+        let mut update = Vec::with_capacity(self.buf.len() + 1);
+        update.write_var(0u32);
+        update.extend_from_slice(&self.buf);
+        apply_part(txn, &update)?;
+        self.buf = Vec::new();
+        self.section = Section::Done;
+        Ok(())
+    }
+
+    /// Applies the complete structs at the start of the buffer, returning the
+    /// number of bytes consumed
+    fn apply_structs(&mut self, txn: &mut TransactionMut) -> Result<usize, StreamError> {
+        let buf = &self.buf;
+        let mut decoder = SpanDecoder::new(buf);
+        let mut consumed = 0;
+        loop {
+            let step = match self.section {
+                Section::Header => decoder.read_var().map(|clients| {
+                    self.section = match clients {
+                        0 => Section::DeleteSet,
+                        clients => Section::Client { clients },
+                    };
+                }),
+                Section::Client { clients } => {
+                    read_client_header(&mut decoder).map(|(count, client, clock)| {
+                        self.section = Section::Structs {
+                            clients,
+                            client,
+                            clock,
+                            remaining: count,
+                        };
+                    })
+                }
+                Section::Structs {
+                    clients,
+                    client,
+                    clock,
+                    remaining,
+                } => {
+                    let start = consumed;
+                    let mut end = start;
+                    let mut len = 0;
+                    let mut count = 0;
+                    let mut result = Ok(());
+                    while count < remaining {
+                        match decoder.skip_struct() {
+                            Ok(struct_len) => {
+                                len += struct_len;
+                                count += 1;
+                                end = decoder.position();
+                            }
+                            Err(e) => {
+                                result = Err(e);
+                                break;
+                            }
+                        }
+                    }
+                    if count > 0 {
+                        let part = encode_part(client, clock, count, &buf[start..end]);
+                        apply_part(txn, &part)?;
+                        self.structs_applied += count as u64;
+                        consumed = end;
+                    }
+                    self.section = if count < remaining {
+                        Section::Structs {
+                            clients,
+                            client,
+                            clock: clock + len,
+                            remaining: remaining - count,
+                        }
+                    } else if clients > 1 {
+                        Section::Client {
+                            clients: clients - 1,
+                        }
+                    } else {
+                        Section::DeleteSet
+                    };
+                    result
+                }
+                Section::DeleteSet | Section::Done => return Ok(consumed),
+            };
+            match step {
+                Ok(()) => consumed = decoder.position(),
+                Err(DecodeError::EndOfBuffer(_)) => return Ok(consumed),
+                Err(e) => {
+                    let offset = decoder.position();
+                    return Err(StreamError::Decode(UpdateDecodeError::new(e, buf, offset)));
+                }
+            }
+        }
+    }
+}
+
+/// Reads the struct count, client and clock that start a client's structs
+fn read_client_header(decoder: &mut SpanDecoder) -> Result<(u32, u64, u32), DecodeError> {
+    let count = decoder.read_var()?;
+    let client = decoder.read_client()?;
+    let clock = decoder.read_var()?;
+    Ok((count, client, clock))
+}
+
+/// Wraps `count` encoded structs of one client in a standalone v1 update
+fn encode_part(client: u64, clock: u32, count: u32, structs: &[u8]) -> Vec<u8> {
+    let mut update = Vec::with_capacity(structs.len() + 16);
+    update.write_var(1u32);
+    update.write_var(count);
+    update.write_var(client);
+    update.write_var(clock);
+    update.extend_from_slice(structs);
+    // Empty delete set
+    update.write_var(0u32);
+    update
+}
+
+fn apply_part(txn: &mut TransactionMut, part: &[u8]) -> Result<(), StreamError> {
+    let update = decode_update_v1(part).map_err(StreamError::Decode)?;
+    mark_remote(txn);
+    txn.apply_update(update).map_err(StreamError::Apply)
+}
+
+pub type UpdateStreamPtr = JavaPtr<UpdateStream>;
+
+/// Throws the Java exception for a stream that could not be applied
+fn throw_stream_error(env: &mut JNIEnv, wrapper: &DocWrapper, error: &StreamError) {
+    match error {
+        StreamError::Decode(e) => throw_decode_error(env, wrapper, e),
+        StreamError::Apply(e) => throw_exception(env, &format!("Failed to apply update: {:?}", e)),
+        StreamError::Incomplete => {
+            throw_exception(env, "Update stream ended before the end of the update")
+        }
+        StreamError::TrailingBytes => {
+            throw_exception(env, "Update stream has bytes after the end of the update")
+        }
+    }
+}
+
+/// Creates an update stream expecting the start of an update
+///
+/// # Returns
+/// A pointer to the update stream
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeCreate(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jlong {
+    trace_native!("JniUpdateStream.nativeCreate");
+    to_java_ptr(UpdateStream::new())
+}
+
+/// Destroys an update stream, discarding the bytes it has not applied
+///
+/// # Parameters
+/// - `ptr`: Pointer to the update stream
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeDestroy(
+    _env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) {
+    trace_native!("JniUpdateStream.nativeDestroy", ptr);
+    free_if_valid!(UpdateStreamPtr::from_raw(ptr), UpdateStream);
+}
+
+/// Writes part of a byte array to an update stream, applying the structs it
+/// completes
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `ptr`: Pointer to the update stream
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `chunk`: The bytes to write
+/// - `offset`: Index of the first byte to write
+/// - `length`: Number of bytes to write
+///
+/// # Safety
+/// `chunk` must be a valid byte array reference
+#[no_mangle]
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeWriteWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+    txn_ptr: jlong,
+    chunk: jbyteArray,
+    offset: jint,
+    length: jint,
+) {
+    trace_native!("JniUpdateStream.nativeWriteWithTxn", doc_ptr, ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    if !wrapper.accepts_updates() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "YDoc is read-only and does not accept updates",
+        );
+        return;
+    }
+    check_not_dispatching_or_throw!(&mut env, wrapper);
+    let stream = get_mut_or_throw!(&mut env, UpdateStreamPtr::from_raw(ptr), "UpdateStream");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return;
+    }
+
+    let mut bytes = vec![0i8; length.max(0) as usize];
+    let chunk = JByteArray::from_raw(chunk);
+    if env
+        .get_byte_array_region(&chunk, offset, &mut bytes)
+        .is_err()
+    {
+        // ArrayIndexOutOfBoundsException is pending
+        return;
+    }
+    let bytes: Vec<u8> = bytes.into_iter().map(|b| b as u8).collect();
+    if let Err(e) = stream.write(txn, &bytes) {
+        throw_stream_error(&mut env, wrapper, &e);
+    }
+}
+
+/// Applies the delete set that ends the update written to an update stream
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `ptr`: Pointer to the update stream
+/// - `txn_ptr`: Pointer to the transaction instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeFinishWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    ptr: jlong,
+    txn_ptr: jlong,
+) {
+    trace_native!("JniUpdateStream.nativeFinishWithTxn", doc_ptr, ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    if !wrapper.accepts_updates() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "YDoc is read-only and does not accept updates",
+        );
+        return;
+    }
+    check_not_dispatching_or_throw!(&mut env, wrapper);
+    let stream = get_mut_or_throw!(&mut env, UpdateStreamPtr::from_raw(ptr), "UpdateStream");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return;
+    }
+    if let Err(e) = stream.finish(txn) {
+        throw_stream_error(&mut env, wrapper, &e);
+    }
+}
+
+/// Returns the number of bytes written to an update stream but not applied
+///
+/// # Parameters
+/// - `ptr`: Pointer to the update stream
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeGetBufferedSize(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jlong {
+    trace_native!("JniUpdateStream.nativeGetBufferedSize", ptr);
+    let stream = get_ref_or_throw!(&mut env, UpdateStreamPtr::from_raw(ptr), "UpdateStream", 0);
+    stream.buffered() as jlong
+}
+
+/// Returns the number of structs an update stream has applied
+///
+/// # Parameters
+/// - `ptr`: Pointer to the update stream
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniUpdateStream_nativeGetStructsApplied(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jlong {
+    trace_native!("JniUpdateStream.nativeGetStructsApplied", ptr);
+    let stream = get_ref_or_throw!(&mut env, UpdateStreamPtr::from_raw(ptr), "UpdateStream", 0);
+    stream.structs_applied() as jlong
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Array, Doc, GetString, Map, ReadTxn, StateVector, Text, Transact};
+
+    /// A document edited by several clients, with deletions
+    fn source_update() -> Vec<u8> {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        for i in 0..200 {
+            let mut txn = doc.transact_mut();
+            let len = text.len(&txn);
+            text.insert(&mut txn, len / 2, &format!("line {} é😀\n", i));
+            map.insert(&mut txn, format!("key{}", i % 17), i as i64);
+        }
+        text.remove_range(&mut doc.transact_mut(), 10, 40);
+
+        let other = Doc::with_client_id(2);
+        other
+            .transact_mut()
+            .apply_update(
+                yrs::Update::decode_v1(
+                    &doc.transact()
+                        .encode_state_as_update_v1(&StateVector::default()),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let array = other.get_or_insert_array("array");
+        for i in 0..50 {
+            array.push_back(&mut other.transact_mut(), i as i64);
+        }
+        let other_text = other.get_or_insert_text("text");
+        other_text.remove_range(&mut other.transact_mut(), 0, 25);
+        let update = other
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        update
+    }
+
+    #[test]
+    fn test_chunked_update_matches_whole_update() {
+        let update = source_update();
+        let expected = Doc::new();
+        expected
+            .transact_mut()
+            .apply_update(yrs::Update::decode_v1(&update).unwrap())
+            .unwrap();
+        let expected_text = expected
+            .get_or_insert_text("text")
+            .get_string(&expected.transact());
+
+        for chunk_size in [1, 7, 64, update.len()] {
+            let doc = Doc::new();
+            let mut stream = UpdateStream::new();
+            for chunk in update.chunks(chunk_size) {
+                stream.write(&mut doc.transact_mut(), chunk).unwrap();
+                assert!(stream.buffered() <= update.len());
+            }
+            stream.finish(&mut doc.transact_mut()).unwrap();
+
+            let text = doc.get_or_insert_text("text");
+            let txn = doc.transact();
+            let expected_txn = expected.transact();
+            assert_eq!(stream.bytes_written(), update.len() as u64);
+            assert!(stream.structs_applied() > 0);
+            assert_eq!(
+                txn.encode_state_as_update_v1(&StateVector::default()),
+                expected_txn.encode_state_as_update_v1(&StateVector::default())
+            );
+            assert_eq!(text.get_string(&txn), expected_text);
+        }
+    }
+
+    #[test]
+    fn test_truncated_update_is_incomplete() {
+        let update = source_update();
+        let doc = Doc::new();
+        let mut stream = UpdateStream::new();
+        stream
+            .write(&mut doc.transact_mut(), &update[..update.len() - 1])
+            .unwrap();
+        assert!(matches!(
+            stream.finish(&mut doc.transact_mut()),
+            Err(StreamError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn test_bytes_after_the_update_are_rejected() {
+        let doc = Doc::new();
+        let mut stream = UpdateStream::new();
+        stream.write(&mut doc.transact_mut(), &[0, 0, 0]).unwrap();
+        assert!(matches!(
+            stream.finish(&mut doc.transact_mut()),
+            Err(StreamError::TrailingBytes)
+        ));
+    }
+}
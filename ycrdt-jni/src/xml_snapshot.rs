@@ -0,0 +1,233 @@
+//! Immutable snapshots of XML trees.
+//!
+//! A renderer that reads an XML fragment node by node through `JniYXmlElement`
+//! and `JniYXmlText` handles keeps a native pointer alive for every node and
+//! crosses the JNI boundary several times per node. A snapshot materializes
+//! the whole tree in one call instead: every node becomes a `JniXmlNode`
+//! holding its tag, attributes, children and formatted text, with no link back
+//! to the document. The class and constructor are cached like `YNativeRef`.
+
+use crate::{any_to_jobject, attrs_to_java_hashmap, find_callback_class, JniResult};
+use jni::objects::{GlobalRef, JClass, JMethodID, JObject, JValue};
+use jni::sys::jint;
+use jni::JNIEnv;
+use std::sync::OnceLock;
+use yrs::types::text::YChange;
+use yrs::{Out, ReadTxn, Text, Xml, XmlElementRef, XmlFragment, XmlOut, XmlTextRef};
+
+/// Node kinds, matching the declaration order of `JniXmlNode.Kind`
+pub const XML_NODE_FRAGMENT: jint = 0;
+pub const XML_NODE_ELEMENT: jint = 1;
+pub const XML_NODE_TEXT: jint = 2;
+
+const XML_NODE_CLASS: &str = "net/carcdr/ycrdt/jni/JniXmlNode";
+const XML_NODE_CTOR: &str = "(ILjava/lang/String;Ljava/util/Map;Ljava/util/List;Ljava/util/List;)V";
+const FORMATTING_CHUNK_CLASS: &str = "net/carcdr/ycrdt/jni/JniFormattingChunk";
+
+/// Cached classes and constructors of snapshot nodes, resolved in
+/// `JNI_OnLoad` (or on first use if the library was loaded without it)
+struct XmlNodeClasses {
+    node: GlobalRef,
+    node_ctor: JMethodID,
+    chunk: GlobalRef,
+    chunk_ctor: JMethodID,
+}
+
+static XML_NODE_CLASSES: OnceLock<XmlNodeClasses> = OnceLock::new();
+
+fn xml_node_classes(env: &mut JNIEnv) -> JniResult<&'static XmlNodeClasses> {
+    if let Some(cached) = XML_NODE_CLASSES.get() {
+        return Ok(cached);
+    }
+    let node = find_callback_class(env, XML_NODE_CLASS)?;
+    let node_ctor = env.get_method_id(&node, "<init>", XML_NODE_CTOR)?;
+    let chunk = find_callback_class(env, FORMATTING_CHUNK_CLASS)?;
+    let chunk_ctor = env.get_method_id(&chunk, "<init>", "(Ljava/lang/String;Ljava/util/Map;)V")?;
+    let node = env.new_global_ref(node)?;
+    let chunk = env.new_global_ref(chunk)?;
+    Ok(XML_NODE_CLASSES.get_or_init(|| XmlNodeClasses {
+        node,
+        node_ctor,
+        chunk,
+        chunk_ctor,
+    }))
+}
+
+/// Resolves the snapshot node classes and constructors ahead of first use
+pub(crate) fn init_xml_snapshot_cache(env: &mut JNIEnv) -> JniResult<()> {
+    xml_node_classes(env).map(|_| ())
+}
+
+/// Materializes a fragment and everything below it as a `JniXmlNode`
+pub fn snapshot_xml_fragment<'local, T: ReadTxn, F: XmlFragment>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    fragment: &F,
+) -> JniResult<JObject<'local>> {
+    let children = snapshot_children(env, txn, fragment)?;
+    new_xml_node(
+        env,
+        XML_NODE_FRAGMENT,
+        None,
+        JObject::null(),
+        children,
+        JObject::null(),
+    )
+}
+
+fn snapshot_node<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    node: XmlOut,
+) -> JniResult<JObject<'local>> {
+    match node {
+        XmlOut::Fragment(fragment) => snapshot_xml_fragment(env, txn, &fragment),
+        XmlOut::Element(element) => snapshot_element(env, txn, &element),
+        XmlOut::Text(text) => snapshot_text(env, txn, &text),
+    }
+}
+
+fn snapshot_element<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    element: &XmlElementRef,
+) -> JniResult<JObject<'local>> {
+    let attributes = snapshot_attributes(env, element.attributes(txn))?;
+    let children = snapshot_children(env, txn, element)?;
+    new_xml_node(
+        env,
+        XML_NODE_ELEMENT,
+        Some(element.tag()),
+        attributes,
+        children,
+        JObject::null(),
+    )
+}
+
+fn snapshot_text<'local, T: ReadTxn>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    text: &XmlTextRef,
+) -> JniResult<JObject<'local>> {
+    let attributes = snapshot_attributes(env, text.attributes(txn))?;
+    let chunks = env.new_object("java/util/ArrayList", "()V", &[])?;
+    let classes = xml_node_classes(env)?;
+    let chunk_class: &JClass = classes.chunk.as_obj().into();
+    for diff in text.diff(txn, YChange::identity) {
+        let content = JObject::from(env.new_string(diff.insert.to_string(txn))?);
+        let formatting = match &diff.attributes {
+            Some(attrs) => attrs_to_java_hashmap(env, attrs)?,
+            None => JObject::null(),
+        };
+        // SAFETY: the constructor ID was resolved against this class with the
+        // signature (Ljava/lang/String;Ljava/util/Map;)V, and the arguments
+        // match that signature.
+        let chunk = unsafe {
+            env.new_object_unchecked(
+                chunk_class,
+                classes.chunk_ctor,
+                &[
+                    JValue::Object(&content).as_jni(),
+                    JValue::Object(&formatting).as_jni(),
+                ],
+            )?
+        };
+        add(env, &chunks, chunk)?;
+        env.delete_local_ref(content)?;
+        env.delete_local_ref(formatting)?;
+    }
+    new_xml_node(
+        env,
+        XML_NODE_TEXT,
+        None,
+        attributes,
+        JObject::null(),
+        chunks,
+    )
+}
+
+fn snapshot_children<'local, T: ReadTxn, F: XmlFragment>(
+    env: &mut JNIEnv<'local>,
+    txn: &T,
+    parent: &F,
+) -> JniResult<JObject<'local>> {
+    let children = env.new_object("java/util/ArrayList", "()V", &[])?;
+    for child in parent.children(txn) {
+        let child = snapshot_node(env, txn, child)?;
+        add(env, &children, child)?;
+    }
+    Ok(children)
+}
+
+/// Converts attributes to a `Map<String, Object>`, leaving out values that are
+/// shared types rather than plain values
+fn snapshot_attributes<'local, 'a>(
+    env: &mut JNIEnv<'local>,
+    attributes: impl Iterator<Item = (&'a str, Out)>,
+) -> JniResult<JObject<'local>> {
+    let mut attributes: Vec<(&str, Out)> = attributes.collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    let map = env.new_object("java/util/LinkedHashMap", "()V", &[])?;
+    for (name, value) in attributes {
+        let Out::Any(value) = value else {
+            continue;
+        };
+        let name = JObject::from(env.new_string(name)?);
+        let value = any_to_jobject(env, &value)?;
+        env.call_method(
+            &map,
+            "put",
+            "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+            &[JValue::Object(&name), JValue::Object(&value)],
+        )?;
+        env.delete_local_ref(name)?;
+        env.delete_local_ref(value)?;
+    }
+    Ok(map)
+}
+
+fn new_xml_node<'local>(
+    env: &mut JNIEnv<'local>,
+    kind: jint,
+    tag: Option<&str>,
+    attributes: JObject<'local>,
+    children: JObject<'local>,
+    chunks: JObject<'local>,
+) -> JniResult<JObject<'local>> {
+    let tag = match tag {
+        Some(tag) => JObject::from(env.new_string(tag)?),
+        None => JObject::null(),
+    };
+    let classes = xml_node_classes(env)?;
+    let class: &JClass = classes.node.as_obj().into();
+    // SAFETY: the constructor ID was resolved against this class with the
+    // signature in `XML_NODE_CTOR`, and the arguments match that signature.
+    let node = unsafe {
+        env.new_object_unchecked(
+            class,
+            classes.node_ctor,
+            &[
+                JValue::Int(kind).as_jni(),
+                JValue::Object(&tag).as_jni(),
+                JValue::Object(&attributes).as_jni(),
+                JValue::Object(&children).as_jni(),
+                JValue::Object(&chunks).as_jni(),
+            ],
+        )?
+    };
+    for local in [tag, attributes, children, chunks] {
+        env.delete_local_ref(local)?;
+    }
+    Ok(node)
+}
+
+fn add<'local>(env: &mut JNIEnv<'local>, list: &JObject, value: JObject<'local>) -> JniResult<()> {
+    env.call_method(
+        list,
+        "add",
+        "(Ljava/lang/Object;)Z",
+        &[JValue::Object(&value)],
+    )?;
+    env.delete_local_ref(value)?;
+    Ok(())
+}
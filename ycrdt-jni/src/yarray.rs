@@ -1,15 +1,22 @@
 use crate::{
+    check_not_held_or_throw, check_writable_or_throw, find_callback_class, forget_root,
     free_if_valid, from_java_ptr, get_mut_or_throw, get_ref_or_throw, get_string_or_throw,
-    out_to_jobject, throw_exception, to_java_ptr, to_jstring, ArrayPtr, DocPtr, DocWrapper,
-    JniEnvExt, TxnPtr,
+    is_silent, jobject_to_any, long_array_to_java, new_java_event, object_array_to_any,
+    out_to_jobject, root_to_java_ptr, throw_exception, throw_jni_error, to_java_ptr,
+    to_json_string, to_jstring, trace_native, type_path_or_null, write_to_direct_buffer,
+    AnyConversionError, ArrayPtr, DocPtr, DocWrapper, JniEnvExt, JniResult, JniResultExt, TxnPtr,
 };
-use jni::objects::{JClass, JObject, JString, JValue};
-use jni::sys::{jdouble, jint, jlong, jstring};
-use jni::{Executor, JNIEnv};
-use std::sync::Arc;
+use jni::objects::{
+    JBooleanArray, JByteBuffer, JClass, JDoubleArray, JLongArray, JObject, JObjectArray, JString,
+    JValue,
+};
+use jni::sys::{
+    jboolean, jdouble, jdoubleArray, jint, jlong, jlongArray, jobjectArray, jstring, JNI_FALSE,
+};
+use jni::JNIEnv;
 use yrs::types::array::ArrayEvent;
 use yrs::types::{Change, ToJson};
-use yrs::{Array, ArrayRef, Doc, Observable, TransactionMut};
+use yrs::{Any, Array, ArrayRef, Doc, Observable, Out, ReadTxn, TransactionMut};
 
 /// Gets or creates a YArray instance from a YDoc
 ///
@@ -26,11 +33,16 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetArray(
     doc_ptr: jlong,
     name: JString,
 ) -> jlong {
+    trace_native!("JniYArray.nativeGetArray", doc_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
-    let name_str = get_string_or_throw!(&mut env, name, 0);
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
 
-    let array = wrapper.doc.get_or_insert_array(name_str.as_str());
-    to_java_ptr(array)
+    root_to_java_ptr(doc_ptr, wrapper, &name_str, || {
+        wrapper.doc.get_or_insert_array(name_str.as_str())
+    })
 }
 
 /// Destroys a YArray instance and frees its memory
@@ -46,7 +58,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeDestroy(
     _class: JClass,
     ptr: jlong,
 ) {
-    free_if_valid!(ArrayPtr::from_raw(ptr), ArrayRef);
+    trace_native!("JniYArray.nativeDestroy", ptr);
+    if forget_root(ptr) {
+        free_if_valid!(ArrayPtr::from_raw(ptr), ArrayRef);
+    }
 }
 
 /// Gets the length of the array using an existing transaction
@@ -57,7 +72,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeDestroy(
 /// - `txn_ptr`: Pointer to the transaction
 ///
 /// # Returns
-/// The length of the array as jint
+/// The length of the array as jlong
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeLengthWithTxn(
     mut env: JNIEnv,
@@ -65,12 +80,53 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeLengthWithTxn(
     doc_ptr: jlong,
     array_ptr: jlong,
     txn_ptr: jlong,
-) -> jint {
+) -> jlong {
+    trace_native!("JniYArray.nativeLengthWithTxn", doc_ptr, array_ptr, txn_ptr);
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray", 0);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
-    array.len(txn) as jint
+    jlong::from(array.len(txn))
+}
+
+/// Finds the root the YArray belongs to and the path leading to it
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// An `Object[]` holding the root name followed by a `String` key or `Integer`
+/// index per level, or null if the YArray was deleted or cannot be reached
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetPathWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobjectArray {
+    trace_native!(
+        "JniYArray.nativeGetPathWithTxn",
+        _doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let array = get_ref_or_throw!(
+        &mut env,
+        ArrayPtr::from_raw(array_ptr),
+        "YArray",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    type_path_or_null(&mut env, txn, array.as_ref())
 }
 
 /// Gets a string value from the array at the specified index using an existing transaction
@@ -92,6 +148,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetStringWithTx
     txn_ptr: jlong,
     index: jint,
 ) -> jstring {
+    trace_native!(
+        "JniYArray.nativeGetStringWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
@@ -139,6 +201,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetDoubleWithTx
     txn_ptr: jlong,
     index: jint,
 ) -> jdouble {
+    trace_native!(
+        "JniYArray.nativeGetDoubleWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0.0);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray", 0.0);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0.0);
@@ -149,6 +217,117 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetDoubleWithTx
     }
 }
 
+/// Reads every item of the array as a double using an existing transaction
+///
+/// Avoids boxing each item when the array holds only numbers. Integers are
+/// converted to the nearest double.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// A Java `double[]`, or null if the array holds anything other than numbers
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeToDoubleArrayWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+) -> jdoubleArray {
+    trace_native!(
+        "JniYArray.nativeToDoubleArrayWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let null = std::ptr::null_mut();
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", null);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray", null);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", null);
+
+    match array_as_doubles(array, txn) {
+        Some(values) => double_array_to_java(&mut env, &values).unwrap_or_throw(&mut env),
+        None => null,
+    }
+}
+
+/// Reads every item of the array as a long using an existing transaction
+///
+/// Avoids boxing each item when the array holds only integers. Doubles
+/// without a fractional part that fit in a long count as integers.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// A Java `long[]`, or null if the array holds anything other than integers
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeToLongArrayWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+) -> jlongArray {
+    trace_native!(
+        "JniYArray.nativeToLongArrayWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let null = std::ptr::null_mut();
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", null);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray", null);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", null);
+
+    match array_as_longs(array, txn) {
+        Some(values) => long_array_to_java(&mut env, &values).unwrap_or_throw(&mut env),
+        None => null,
+    }
+}
+
+/// The items of `array` as doubles, or `None` if any item is not a number
+pub fn array_as_doubles<T: ReadTxn>(array: &ArrayRef, txn: &T) -> Option<Vec<f64>> {
+    let mut values = Vec::with_capacity(array.len(txn) as usize);
+    for value in array.iter(txn) {
+        values.push(match value {
+            Out::Any(Any::Number(n)) => n,
+            Out::Any(Any::BigInt(n)) => n as f64,
+            _ => return None,
+        });
+    }
+    Some(values)
+}
+
+fn double_array_to_java(env: &mut JNIEnv, values: &[f64]) -> JniResult<jdoubleArray> {
+    let array = env.new_double_array(values.len() as i32)?;
+    env.set_double_array_region(&array, 0, values)?;
+    Ok(array.into_raw())
+}
+
+/// The items of `array` as longs, or `None` if any item is not an integer
+pub fn array_as_longs<T: ReadTxn>(array: &ArrayRef, txn: &T) -> Option<Vec<i64>> {
+    let mut values = Vec::with_capacity(array.len(txn) as usize);
+    for value in array.iter(txn) {
+        values.push(match value {
+            Out::Any(Any::BigInt(n)) => n,
+            // i64::MAX as f64 rounds up to 2^63, which does not fit
+            Out::Any(Any::Number(n))
+                if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 =>
+            {
+                n as i64
+            }
+            _ => return None,
+        });
+    }
+    Some(values)
+}
+
 /// Inserts a string value at the specified index using an existing transaction
 ///
 /// # Parameters
@@ -167,10 +346,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertStringWit
     index: jint,
     value: JString,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYArray.nativeInsertStringWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let value_str = get_string_or_throw!(&mut env, value);
+    let value_str = get_string_or_throw!(&mut env, wrapper, value);
 
     array.insert(txn, index as u32, value_str);
 }
@@ -193,7 +379,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertDoubleWit
     index: jint,
     value: jdouble,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYArray.nativeInsertDoubleWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
@@ -216,10 +409,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativePushStringWithT
     txn_ptr: jlong,
     value: JString,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYArray.nativePushStringWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let value_str = get_string_or_throw!(&mut env, value);
+    let value_str = get_string_or_throw!(&mut env, wrapper, value);
 
     array.push_back(txn, value_str);
 }
@@ -240,13 +440,316 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativePushDoubleWithT
     txn_ptr: jlong,
     value: jdouble,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYArray.nativePushDoubleWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
     array.push_back(txn, value);
 }
 
+/// Inserts several values at the specified index using an existing transaction
+///
+/// Every value is converted before anything is inserted, so an unsupported
+/// value leaves the array unchanged. Supported values are those accepted by
+/// `jobject_to_any`.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert
+/// - `values`: Java Object[] of values to insert, in order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertAllWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    values: JObjectArray,
+) {
+    trace_native!(
+        "JniYArray.nativeInsertAllWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    if !insert_index_or_throw(&mut env, array, txn, index) {
+        return;
+    }
+    let values = match object_array_to_any(&mut env, &values, wrapper.string_policy()) {
+        Ok(values) => values,
+        Err(e) => {
+            throw_array_value_error(&mut env, e);
+            return;
+        }
+    };
+    array.insert_range(txn, index as u32, values);
+}
+
+/// Inserts the values of a Java `double[]` at the specified index using an
+/// existing transaction
+///
+/// The elements are copied out in one call, so no value is boxed or
+/// inspected. Each becomes a number, as with `pushDouble`.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert
+/// - `values`: The values to insert, in order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertDoublesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    values: JDoubleArray,
+) {
+    trace_native!(
+        "JniYArray.nativeInsertDoublesWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    if !insert_index_or_throw(&mut env, array, txn, index) {
+        return;
+    }
+    let values = match read_doubles(&mut env, &values) {
+        Ok(values) => values,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return;
+        }
+    };
+    array.insert_range(txn, index as u32, values.into_iter().map(Any::Number));
+}
+
+/// Inserts the values of a Java `long[]` at the specified index using an
+/// existing transaction
+///
+/// The elements are copied out in one call, so no value is boxed or
+/// inspected. Each becomes an integer, as a `Long` does with `insertAll`.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert
+/// - `values`: The values to insert, in order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertLongsWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    values: JLongArray,
+) {
+    trace_native!(
+        "JniYArray.nativeInsertLongsWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    if !insert_index_or_throw(&mut env, array, txn, index) {
+        return;
+    }
+    let values = match read_longs(&mut env, &values) {
+        Ok(values) => values,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return;
+        }
+    };
+    array.insert_range(txn, index as u32, values.into_iter().map(Any::BigInt));
+}
+
+/// Inserts the values of a Java `boolean[]` at the specified index using an
+/// existing transaction
+///
+/// The elements are copied out in one call, so no value is boxed or
+/// inspected.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert
+/// - `values`: The values to insert, in order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertBooleansWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    values: JBooleanArray,
+) {
+    trace_native!(
+        "JniYArray.nativeInsertBooleansWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    if !insert_index_or_throw(&mut env, array, txn, index) {
+        return;
+    }
+    let values = match read_booleans(&mut env, &values) {
+        Ok(values) => values,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return;
+        }
+    };
+    array.insert_range(
+        txn,
+        index as u32,
+        values.into_iter().map(|b| Any::Bool(b != JNI_FALSE)),
+    );
+}
+
+fn read_doubles(env: &mut JNIEnv, values: &JDoubleArray) -> JniResult<Vec<f64>> {
+    let mut buf = vec![0.0; env.get_array_length(values)? as usize];
+    env.get_double_array_region(values, 0, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_longs(env: &mut JNIEnv, values: &JLongArray) -> JniResult<Vec<i64>> {
+    let mut buf = vec![0; env.get_array_length(values)? as usize];
+    env.get_long_array_region(values, 0, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_booleans(env: &mut JNIEnv, values: &JBooleanArray) -> JniResult<Vec<jboolean>> {
+    let mut buf = vec![JNI_FALSE; env.get_array_length(values)? as usize];
+    env.get_boolean_array_region(values, 0, &mut buf)?;
+    Ok(buf)
+}
+
+/// Checks that values can be inserted at `index`, throwing
+/// `IndexOutOfBoundsException` if not
+fn insert_index_or_throw(
+    env: &mut JNIEnv,
+    array: &ArrayRef,
+    txn: &TransactionMut,
+    index: jint,
+) -> bool {
+    let len = array.len(txn);
+    if index < 0 || index as u32 > len {
+        let _ = env.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            format!("Index {} out of bounds for length {}", index, len),
+        );
+        return false;
+    }
+    true
+}
+
+/// Throws the Java exception for an array value that could not be converted
+fn throw_array_value_error(env: &mut JNIEnv, error: AnyConversionError) {
+    match error {
+        AnyConversionError::Unsupported(class_name) => {
+            let msg = format!(
+                "Unsupported array value type: {}. Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, or null.",
+                class_name
+            );
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+        }
+        AnyConversionError::InvalidString(msg) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+        }
+        AnyConversionError::Jni(e) => {
+            throw_exception(env, &format!("JNI error: {:?}", e));
+        }
+    }
+}
+
+/// Replaces the element at an index using an existing transaction
+///
+/// The old element is removed and the new one inserted in the same
+/// transaction, so observers see a single change. The value is converted with
+/// `jobject_to_any`.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index of the element to replace
+/// - `value`: The new value
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeReplaceWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    value: JObject,
+) {
+    trace_native!(
+        "JniYArray.nativeReplaceWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let len = array.len(txn);
+    if index < 0 || index as u32 >= len {
+        let _ = env.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            format!("Index {} out of bounds for length {}", index, len),
+        );
+        return;
+    }
+    let value = match jobject_to_any(&mut env, &value, wrapper.string_policy()) {
+        Ok(value) => value,
+        Err(e) => {
+            throw_array_value_error(&mut env, e);
+            return;
+        }
+    };
+    array.remove(txn, index as u32);
+    array.insert(txn, index as u32, value);
+}
+
 /// Removes a range of elements from the array using an existing transaction
 ///
 /// # Parameters
@@ -265,7 +768,9 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeRemoveWithTxn(
     index: jint,
     length: jint,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYArray.nativeRemoveWithTxn", doc_ptr, array_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
@@ -289,7 +794,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeToJsonWithTxn(
     array_ptr: jlong,
     txn_ptr: jlong,
 ) -> jstring {
-    let _doc = get_ref_or_throw!(
+    trace_native!("JniYArray.nativeToJsonWithTxn", doc_ptr, array_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
         "YDoc",
@@ -308,10 +814,103 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeToJsonWithTxn(
         std::ptr::null_mut()
     );
 
-    let json = array.to_json(txn).to_string();
+    let json = to_json_string(&array.to_json(txn), wrapper.sorts_keys());
     to_jstring(&mut env, &json)
 }
 
+/// Writes the JSON form of the array as UTF-8 into a direct ByteBuffer using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `buffer`: A direct `java.nio.ByteBuffer`
+/// - `offset`: Position in the buffer at which to write
+/// - `length`: Number of bytes available at `offset`
+///
+/// # Returns
+/// The size of the JSON form of the array in bytes; nothing is written if it exceeds `length`
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeWriteJsonWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    buffer: JByteBuffer,
+    offset: jint,
+    length: jint,
+) -> jint {
+    trace_native!(
+        "JniYArray.nativeWriteJsonWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let content = to_json_string(&array.to_json(txn), wrapper.sorts_keys());
+    write_to_direct_buffer(&mut env, &buffer, offset, length, &content)
+}
+
+/// Converts a single element to a JSON string using an existing transaction
+///
+/// Nested shared types are converted recursively, and strings are quoted and
+/// escaped.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `array_ptr`: Pointer to the YArray instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `index`: The index of the element
+///
+/// # Returns
+/// A JSON string representation of the element, or null if index is out of bounds
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetJsonAtWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    array_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+) -> jstring {
+    trace_native!(
+        "JniYArray.nativeGetJsonAtWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let array = get_ref_or_throw!(
+        &mut env,
+        ArrayPtr::from_raw(array_ptr),
+        "YArray",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    match array.get(txn, index as u32) {
+        Some(value) => {
+            let json = to_json_string(&value.to_json(txn), wrapper.sorts_keys());
+            to_jstring(&mut env, &json)
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
 /// Inserts a YDoc subdocument at the specified index using an existing transaction
 ///
 /// # Parameters
@@ -330,7 +929,15 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeInsertDocWithTx
     index: jint,
     subdoc_ptr: jlong,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYArray.nativeInsertDocWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr,
+        subdoc_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
     // subdoc_ptr comes from Java YDoc which stores DocWrapper, not raw Doc
@@ -357,7 +964,15 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativePushDocWithTxn(
     txn_ptr: jlong,
     subdoc_ptr: jlong,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYArray.nativePushDocWithTxn",
+        doc_ptr,
+        array_ptr,
+        txn_ptr,
+        subdoc_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
     // subdoc_ptr comes from Java YDoc which stores DocWrapper, not raw Doc
@@ -387,6 +1002,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeGetDocWithTxn(
     txn_ptr: jlong,
     index: jint,
 ) -> jlong {
+    trace_native!("JniYArray.nativeGetDocWithTxn", doc_ptr, array_ptr, txn_ptr);
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray", 0);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
@@ -420,12 +1036,13 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeObserve(
     subscription_id: jlong,
     yarray_obj: JObject,
 ) {
-    let wrapper = get_mut_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYArray.nativeObserve", doc_ptr, array_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
     let array = get_ref_or_throw!(&mut env, ArrayPtr::from_raw(array_ptr), "YArray");
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
         Err(e) => {
             throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
             return;
@@ -441,15 +1058,21 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeObserve(
         }
     };
 
+    let gate = wrapper.dispatch_gate();
+
     // Create observer closure
     let subscription = array.observe(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
         // Use Executor for thread attachment with automatic local frame management
         let _ = executor
             .with_attached(|env| dispatch_array_event(env, doc_ptr, subscription_id, txn, event));
     });
 
     // Store subscription and GlobalRef in the DocWrapper
-    wrapper.add_subscription(subscription_id, subscription, global_ref);
+    wrapper.add_subscription(subscription_id, array_ptr, subscription, global_ref);
 }
 
 /// Unregisters an observer for the YArray
@@ -466,7 +1089,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeUnobserve(
     _array_ptr: jlong,
     subscription_id: jlong,
 ) {
-    let wrapper = get_mut_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYArray.nativeUnobserve", doc_ptr, _array_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
 
     // Remove subscription and GlobalRef from DocWrapper
     // Both the Subscription and GlobalRef are dropped here
@@ -474,13 +1098,16 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYArray_nativeUnobserve(
 }
 
 /// Helper function to dispatch an array event to Java
-fn dispatch_array_event(
+pub(crate) fn dispatch_array_event(
     env: &mut JNIEnv,
     doc_ptr: jlong,
     subscription_id: jlong,
     txn: &TransactionMut,
     event: &ArrayEvent,
 ) -> Result<(), jni::errors::Error> {
+    if is_silent(txn) {
+        return Ok(());
+    }
     // Get the Java YArray object from DocWrapper
     let yarray_ref = unsafe {
         let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
@@ -509,7 +1136,7 @@ fn dispatch_array_event(
                 // Convert items to Java ArrayList
                 let items_list = env.new_object("java/util/ArrayList", "()V", &[])?;
                 for item in items {
-                    let item_obj = out_to_jobject(env, item)?;
+                    let item_obj = out_to_jobject(env, txn, item)?;
                     env.call_method(
                         &items_list,
                         "add",
@@ -518,7 +1145,8 @@ fn dispatch_array_event(
                     )?;
                 }
 
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
                 env.new_object(
                     change_class,
                     "(Ljava/util/List;)V",
@@ -527,8 +1155,9 @@ fn dispatch_array_event(
             }
             Change::Removed(len) => {
                 // Create YArrayChange for DELETE
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
                 let delete_type =
                     env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
 
@@ -540,8 +1169,9 @@ fn dispatch_array_event(
             }
             Change::Retain(len) => {
                 // Create YArrayChange for RETAIN
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
                 let retain_type =
                     env.get_static_field(type_class, "RETAIN", "Lnet/carcdr/ycrdt/YChange$Type;")?;
 
@@ -562,20 +1192,8 @@ fn dispatch_array_event(
         )?;
     }
 
-    // Create YEvent
-    let event_class = env.find_class("net/carcdr/ycrdt/jni/JniYEvent")?;
-    let target = yarray_obj; // Use the YArray object as the target
-    let origin_jstr = env.new_string("")?; // Empty origin for now
-
-    let event_obj = env.new_object(
-        event_class,
-        "(Ljava/lang/Object;Ljava/util/List;Ljava/lang/String;)V",
-        &[
-            JValue::Object(target),
-            JValue::Object(&changes_list),
-            JValue::Object(&origin_jstr),
-        ],
-    )?;
+    // Create YEvent carrying the transaction's origin
+    let event_obj = new_java_event(env, txn, yarray_obj, &changes_list)?;
 
     // Call YArray.dispatchEvent(subscriptionId, event)
     env.call_method(
@@ -594,6 +1212,25 @@ mod tests {
     use crate::free_java_ptr;
     use yrs::{Doc, Transact};
 
+    #[test]
+    fn test_number_arrays() {
+        let doc = Doc::new();
+        let array = doc.get_or_insert_array("test");
+        let mut txn = doc.transact_mut();
+        array.push_back(&mut txn, 1.5);
+        array.push_back(&mut txn, Any::BigInt(2));
+        assert_eq!(array_as_doubles(&array, &txn), Some(vec![1.5, 2.0]));
+        assert_eq!(array_as_longs(&array, &txn), None);
+
+        array.remove(&mut txn, 0);
+        array.push_back(&mut txn, 3.0);
+        assert_eq!(array_as_longs(&array, &txn), Some(vec![2, 3]));
+
+        array.push_back(&mut txn, "four");
+        assert_eq!(array_as_doubles(&array, &txn), None);
+        assert_eq!(array_as_longs(&array, &txn), None);
+    }
+
     #[test]
     fn test_array_creation() {
         let doc = Doc::new();
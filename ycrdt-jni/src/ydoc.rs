@@ -1,14 +1,38 @@
 use crate::{
-    free_if_valid, free_transaction, get_mut_or_throw, get_ref_or_throw, throw_exception,
-    to_java_ptr, DocPtr, DocWrapper, JniEnvExt, JniResultExt, TxnPtr,
+    absorb, apply_deep_update, box_transaction, check_not_dispatching_or_throw,
+    check_not_held_or_throw, check_txn_doc_or_throw, check_txn_owner, check_writable_or_throw,
+    count_ops, decode_update_v1, diff_size_v1, dispatch_array_event, dispatch_map_event,
+    dispatch_text_event, encode_deep_update, encode_roots_diff_v1, encode_sync_step1,
+    encode_sync_update, export_roots, filter_update_v1, find_callback_class, free_if_valid,
+    free_transaction, get_mut_or_throw, get_ref_or_throw, get_string_or_throw, handle_address,
+    handle_sync_message, has_pending, hold_transaction, is_live_handle, jobject_to_any,
+    mark_remote, mark_silent, pending_state, record_txn_start, register_handle, register_txn_owner,
+    release_all_roots, set_txn_label, store_stats, string_array_to_set, strings_to_java,
+    subdoc_path, take_txn_start, throw_exception, throw_jni_error, to_hex, to_java_ptr,
+    trace_native, type_path_to_java, unregister_txn_owner, validate_schema, AnyConversionError,
+    ApplyProbe, BatchOp, DeepUpdateError, DocPtr, DocWrapper, FilterError, FilteredUpdate,
+    JniEnvExt, JniError, JniResult, JniResultExt, RootActivity, SchemaRule, StagingError,
+    StringPolicy, SyncMessageError, SyncOutcome, TextShadow, TxnPtr, TxnStats, UpdateDecodeError,
+    ACCESS_READ_ONLY_ALLOW_UPDATES, ACCESS_READ_WRITE, BATCH_OP_ARRAY_DELETE,
+    BATCH_OP_ARRAY_INSERT, BATCH_OP_MAP_REMOVE, BATCH_OP_MAP_SET, BATCH_OP_TEXT_DELETE,
+    BATCH_OP_TEXT_INSERT, SCHEMA_RULE_KEY, SCHEMA_RULE_NO_OTHER_ROOTS, SCHEMA_RULE_OPTIONAL_ROOT,
+    SCHEMA_RULE_ROOT, TREE_KIND_ARRAY, TREE_KIND_MAP, TREE_KIND_TEXT,
 };
-use jni::objects::{JByteArray, JClass, JObject, JValue};
-use jni::sys::{jbyteArray, jlong, jstring};
-use jni::{Executor, JNIEnv};
-use std::sync::Arc;
+#[cfg(feature = "xml")]
+use crate::{
+    dispatch_xmlelement_event, dispatch_xmlfragment_event, dispatch_xmltext_event,
+    root_xml_element, root_xml_text, TREE_KIND_XML_ELEMENT, TREE_KIND_XML_FRAGMENT,
+    TREE_KIND_XML_TEXT,
+};
+use jni::objects::{JByteArray, JClass, JIntArray, JObject, JObjectArray, JString, JValue};
+use jni::sys::{
+    jboolean, jbyteArray, jint, jlong, jlongArray, jobject, jobjectArray, jstring, JNI_FALSE,
+    JNI_TRUE,
+};
+use jni::JNIEnv;
 use yrs::updates::decoder::Decode;
-use yrs::updates::encoder::Encode;
-use yrs::{ReadTxn, Transact};
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::{Observable, ReadTxn, Transact};
 
 /// Creates a new YDoc instance
 ///
@@ -19,6 +43,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreate(
     _env: JNIEnv,
     _class: JClass,
 ) -> jlong {
+    trace_native!("JniYDoc.nativeCreate");
     let doc = DocWrapper::new();
     to_java_ptr(doc)
 }
@@ -36,29 +61,249 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateWithClientI
     _class: JClass,
     client_id: jlong,
 ) -> jlong {
+    trace_native!("JniYDoc.nativeCreateWithClientId");
+    let options = yrs::Options {
+        client_id: client_id as u64,
+        ..Default::default()
+    };
+    let doc = DocWrapper::with_options(options);
+    to_java_ptr(doc)
+}
+
+/// Creates a new YDoc instance with a specific client ID and GUID
+///
+/// Lets Java draw both from a random source of its choosing, so documents
+/// created from a seeded source are the same on every run.
+///
+/// # Parameters
+/// - `client_id`: The client ID to assign to this document
+/// - `guid`: The GUID to assign to this document
+///
+/// # Returns
+/// A pointer to the YDoc instance (as jlong), or 0 if the GUID could not be read
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateWithIds(
+    mut env: JNIEnv,
+    _class: JClass,
+    client_id: jlong,
+    guid: JString,
+) -> jlong {
+    trace_native!("JniYDoc.nativeCreateWithIds");
+    let guid = match env.get_rust_string(&guid, StringPolicy::default()) {
+        Ok(guid) => guid,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return 0;
+        }
+    };
+    let options = yrs::Options::with_guid_and_client_id(guid.into(), client_id as u64);
+    let doc = DocWrapper::with_options(options);
+    to_java_ptr(doc)
+}
+
+/// Creates a new YDoc instance with garbage collection of deleted content disabled
+///
+/// Deleted items keep their content, so snapshots taken earlier can still be
+/// rendered and diffed against later states.
+///
+/// # Parameters
+/// - `client_id`: The client ID to assign, or a negative value for a random one
+///
+/// # Returns
+/// A pointer to the YDoc instance (as jlong)
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateWithoutGc(
+    _env: JNIEnv,
+    _class: JClass,
+    client_id: jlong,
+) -> jlong {
+    trace_native!("JniYDoc.nativeCreateWithoutGc");
+    let mut options = yrs::Options {
+        skip_gc: true,
+        ..Default::default()
+    };
+    if client_id >= 0 {
+        options.client_id = client_id as u64;
+    }
+    let doc = DocWrapper::with_options(options);
+    to_java_ptr(doc)
+}
+
+/// Creates a YDoc instance whose output does not vary between runs
+///
+/// The client ID is fixed, and map keys are returned in sorted order from
+/// JSON output and key listings. Intended for tests that compare serialized
+/// output against stored files.
+///
+/// # Parameters
+/// - `client_id`: The client ID to assign
+///
+/// # Returns
+/// A pointer to the YDoc instance (as jlong)
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeCreateDeterministic(
+    _env: JNIEnv,
+    _class: JClass,
+    client_id: jlong,
+) -> jlong {
+    trace_native!("JniYDoc.nativeCreateDeterministic");
     let options = yrs::Options {
         client_id: client_id as u64,
         ..Default::default()
     };
     let doc = DocWrapper::with_options(options);
+    doc.set_sorted_keys(true);
     to_java_ptr(doc)
 }
 
+/// Checks if a YDoc returns map keys in sorted order
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsDeterministic(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeIsDeterministic", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    if wrapper.sorts_keys() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
 /// Destroys a YDoc instance and frees its memory
 ///
 /// # Parameters
 /// - `ptr`: Pointer to the YDoc instance
 ///
+/// Observer callbacks running on other threads are allowed to finish before the
+/// document is freed, and callbacks that start afterwards are skipped. Calling
+/// this from inside one of the document's own callbacks throws
+/// `IllegalStateException` and leaves the document open.
+///
 /// # Safety
 /// The pointer must be valid and point to a YDoc instance
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeDestroy(
-    _env: JNIEnv,
+    mut env: JNIEnv,
     _class: JClass,
     ptr: jlong,
 ) {
+    trace_native!("JniYDoc.nativeDestroy", ptr);
+    let doc_ptr = DocPtr::from_raw(ptr);
+    if doc_ptr.is_null() || !is_live_handle(ptr) {
+        return;
+    }
+    let gate = match unsafe { doc_ptr.as_ref() } {
+        Some(wrapper) => wrapper.dispatch_gate(),
+        None => return,
+    };
+    if gate.close_and_wait().is_err() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "Cannot close a YDoc from inside one of its own observer callbacks",
+        );
+        return;
+    }
+    if let Some(wrapper) = unsafe { doc_ptr.as_ref() } {
+        unsafe { release_all_roots(wrapper) };
+    }
     // When DocWrapper is dropped, all subscriptions and GlobalRefs are automatically cleaned up
-    free_if_valid!(DocPtr::from_raw(ptr), DocWrapper);
+    free_if_valid!(doc_ptr, DocWrapper);
+}
+
+/// Replaces the content of a YDoc with a new, empty document
+///
+/// The wrapper, its pointer and its settings stay as they are, so a pooled
+/// document can be reused without being destroyed and created again. All
+/// observers are detached, root-type handles are released, and staged updates,
+/// root activity and transaction totals are discarded. The new document keeps
+/// the options of the old one, except that it gets a new GUID and client ID.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `client_id`: The client ID of the new document, or a negative value for a
+///   random one
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeReset(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    client_id: jlong,
+) {
+    trace_native!("JniYDoc.nativeReset", ptr);
+    let wrapper = get_mut_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    if wrapper.doc.try_transact_mut().is_err() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "Cannot reset a YDoc while a transaction is open on it",
+        );
+        return;
+    }
+    let old = &wrapper.doc;
+    let fresh = yrs::Options::default();
+    let options = yrs::Options {
+        client_id: u64::try_from(client_id).unwrap_or(fresh.client_id),
+        collection_id: old.collection_id(),
+        offset_kind: old.offset_kind(),
+        skip_gc: old.skip_gc(),
+        auto_load: old.auto_load(),
+        should_load: old.should_load(),
+        ..fresh
+    };
+    wrapper.reset(yrs::Doc::with_options(options));
+}
+
+/// Releases every root-type handle obtained from this document by name
+///
+/// Lets a pooled document be handed to its next user without the handles of
+/// the previous one. Roots that still have observers are freed once the last
+/// observer is removed.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// The number of handles released
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeReleaseAllRoots(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniYDoc.nativeReleaseAllRoots", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    let released = unsafe { release_all_roots(wrapper) };
+    jint::try_from(released).unwrap_or(jint::MAX)
+}
+
+/// Returns the names of the open root-type handles of this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// The names, sorted, with one entry per open handle
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetOpenRootNames(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jobjectArray {
+    trace_native!("JniYDoc.nativeGetOpenRootNames", ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    strings_to_java(&mut env, wrapper.roots().names().iter())
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
 }
 
 /// Gets the client ID of a YDoc instance
@@ -74,10 +319,51 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetClientId(
     _class: JClass,
     ptr: jlong,
 ) -> jlong {
+    trace_native!("JniYDoc.nativeGetClientId", ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
     wrapper.doc.client_id() as jlong
 }
 
+/// Checks whether the current thread is running an observer of a YDoc
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// true while an observer of the document runs on the current thread
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsDispatching(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeIsDispatching", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    if wrapper.dispatch_gate.dispatching_on_current_thread() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Gets the number of committed transactions that changed a YDoc
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// The change counter as jlong
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetChangeCounter(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jlong {
+    trace_native!("JniYDoc.nativeGetChangeCounter", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    wrapper.change_counter().get() as jlong
+}
+
 /// Gets a unique identifier (GUID) for the YDoc instance
 ///
 /// # Parameters
@@ -91,6 +377,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetGuid(
     _class: JClass,
     ptr: jlong,
 ) -> jstring {
+    trace_native!("JniYDoc.nativeGetGuid", ptr);
     let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(ptr),
@@ -101,99 +388,2027 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetGuid(
     crate::to_jstring(&mut env, &guid)
 }
 
-/// Encodes the current state of the document as a byte array using an existing transaction
+/// Gets the GUID of the document a subdocument is embedded in
 ///
 /// # Parameters
 /// - `ptr`: Pointer to the YDoc instance
-/// - `txn_ptr`: Pointer to the transaction instance
 ///
 /// # Returns
-/// A Java byte array containing the encoded state
+/// The parent's GUID, or null if the document is not a subdocument
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateAsUpdateWithTxn(
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetParentGuid(
     mut env: JNIEnv,
     _class: JClass,
     ptr: jlong,
-    txn_ptr: jlong,
-) -> jbyteArray {
-    let _wrapper = get_ref_or_throw!(
+) -> jstring {
+    trace_native!("JniYDoc.nativeGetParentGuid", ptr);
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(ptr),
         "YDoc",
         std::ptr::null_mut()
     );
-    let txn = get_mut_or_throw!(
-        &mut env,
-        TxnPtr::from_raw(txn_ptr),
-        "YTransaction",
-        std::ptr::null_mut()
-    );
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return std::ptr::null_mut();
+    }
+    match wrapper.doc.parent_doc() {
+        Some(parent) => crate::to_jstring(&mut env, &parent.guid()),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Finds where a subdocument is embedded in its parent document
+///
+/// Reads the parent in a transaction of its own, so it must not be called
+/// while the current thread holds a transaction on the parent.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// An `Object[]` holding the parent's root name followed by a `String` key or
+/// `Integer` index per level, or null if the document is not a subdocument or
+/// was removed from its parent
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetEmbeddedPath(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jobjectArray {
+    trace_native!("JniYDoc.nativeGetEmbeddedPath", ptr);
+    let null = std::ptr::null_mut();
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", null);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return null;
+    }
+    let Some(parent) = wrapper.doc.parent_doc() else {
+        return null;
+    };
+    if !check_not_held_or_throw(&mut env, &parent) {
+        return null;
+    }
+    let txn = parent.transact();
+    match subdoc_path(&txn, &wrapper.doc) {
+        Some(path) => type_path_to_java(&mut env, &path)
+            .map(JObjectArray::into_raw)
+            .unwrap_or_throw(&mut env),
+        None => null,
+    }
+}
+
+/// Enables or disables single-writer checks for transactions on this document
+///
+/// When enabled, each transaction opened afterwards records the thread that
+/// opened it, and using it from any other thread throws `IllegalStateException`.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `enabled`: Whether to enforce single-writer access
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetSingleWriter(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    enabled: jboolean,
+) {
+    trace_native!("JniYDoc.nativeSetSingleWriter", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    wrapper.set_single_writer(enabled != JNI_FALSE);
+}
+
+/// Returns whether single-writer checks are enabled for this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsSingleWriter(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeIsSingleWriter", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    if wrapper.is_single_writer() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Sets the access mode of this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `access`: 0 for read-write, 1 for read-only, 2 for read-only except for
+///   applied updates
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetAccess(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    access: jint,
+) {
+    trace_native!("JniYDoc.nativeSetAccess", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    match u8::try_from(access) {
+        Ok(access @ ACCESS_READ_WRITE..=ACCESS_READ_ONLY_ALLOW_UPDATES) => {
+            wrapper.set_access(access)
+        }
+        _ => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Unknown access mode: {}", access),
+            );
+        }
+    }
+}
+
+/// Returns the access mode of this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetAccess(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniYDoc.nativeGetAccess", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    jint::from(wrapper.access())
+}
+
+/// Sets how strings passed from Java to this document are converted
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `policy`: 0 to replace unpaired surrogates with U+FFFD, 1 to reject them
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetStringPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    policy: jint,
+) {
+    trace_native!("JniYDoc.nativeSetStringPolicy", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    match StringPolicy::from_ordinal(policy) {
+        Some(policy) => wrapper.set_string_policy(policy),
+        None => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Unknown string policy: {}", policy),
+            );
+        }
+    }
+}
+
+/// Returns how strings passed from Java to this document are converted
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetStringPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniYDoc.nativeGetStringPolicy", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    wrapper.string_policy().ordinal()
+}
+
+/// Enables or disables transaction statistics for this document
+///
+/// When enabled, each transaction opened afterwards is timed, and its
+/// operation count and update size are measured on commit.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `enabled`: Whether to measure transactions
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetTransactionStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    enabled: jboolean,
+) {
+    trace_native!("JniYDoc.nativeSetTransactionStats", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    wrapper.set_txn_stats_enabled(enabled != JNI_FALSE);
+}
+
+/// Enables or disables logging of undecodable updates for this document
+///
+/// When enabled, an update that fails to decode is written to stderr in full,
+/// as hex, in addition to the exception thrown to the caller.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `enabled`: Whether to log undecodable updates
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetDecodeFailureLogging(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    enabled: jboolean,
+) {
+    trace_native!("JniYDoc.nativeSetDecodeFailureLogging", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    wrapper.set_log_decode_failures(enabled != JNI_FALSE);
+}
+
+/// Sets the longest inserted string that text observers receive in full
+///
+/// Longer insertions are delivered as a prefix of `limit` UTF-16 units with
+/// the full length and hash of the text.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `limit`: The limit in UTF-16 units, or a negative value for no limit
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetEventContentLimit(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    limit: jint,
+) {
+    trace_native!("JniYDoc.nativeSetEventContentLimit", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    wrapper.set_event_content_limit(usize::try_from(limit).ok());
+}
+
+/// Returns the longest inserted string that text observers receive in full
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// The limit in UTF-16 units, or -1 if there is no limit
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetEventContentLimit(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniYDoc.nativeGetEventContentLimit", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", -1);
+    wrapper
+        .event_content_limit()
+        .map_or(-1, |limit| jint::try_from(limit).unwrap_or(jint::MAX))
+}
+
+/// Returns whether undecodable updates are logged for this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsDecodeFailureLogging(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeIsDecodeFailureLogging", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    if wrapper.logs_decode_failures() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Returns whether transaction statistics are enabled for this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsTransactionStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeIsTransactionStats", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    if wrapper.is_txn_stats_enabled() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Returns the running totals of measured transactions
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// `[count, totalDurationNanos, maxDurationNanos, ops, updateBytes]`
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetTransactionStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jlongArray {
+    trace_native!("JniYDoc.nativeGetTransactionStats", ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let totals = wrapper.txn_stats().snapshot();
+    counters_to_java(&mut env, &totals).unwrap_or_throw(&mut env)
+}
+
+/// Resets the running totals of measured transactions to zero
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeResetTransactionStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) {
+    trace_native!("JniYDoc.nativeResetTransactionStats", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    wrapper.txn_stats().reset();
+}
+
+/// Sets the allowlist of formatting attribute keys for this document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `keys`: The allowed keys, or null to accept any key
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetAllowedAttributes(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    keys: JObjectArray,
+) {
+    trace_native!("JniYDoc.nativeSetAllowedAttributes", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    if keys.is_null() {
+        wrapper.attribute_schema().set_allowed(None);
+        return;
+    }
+    match string_array_to_set(&mut env, &keys, wrapper.string_policy()) {
+        Ok(keys) => wrapper.attribute_schema().set_allowed(Some(keys)),
+        Err(e) => throw_jni_error(&mut env, &e),
+    }
+}
+
+/// Returns whether the library was built with the `xml` feature
+///
+/// # Returns
+/// `true` if the YXmlElement, YXmlFragment and YXmlText natives are available
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeHasXmlSupport(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeHasXmlSupport");
+    if cfg!(feature = "xml") {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Update encodings the native library can produce and apply
+const SUPPORTED_UPDATE_ENCODINGS: &[&str] = &["v1"];
+
+/// Returns build and capability information about the native library
+///
+/// # Returns
+/// A Java `HashMap<String, Object>` with the keys:
+/// - `version`: version of this crate
+/// - `yrsVersion`: version of the yrs library it was built against
+/// - `features`: `List<String>` of enabled cargo features
+/// - `updateEncodings`: `List<String>` of supported update encodings
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetVersionInfo<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+) -> JObject<'local> {
+    trace_native!("JniYDoc.nativeGetVersionInfo");
+    let mut features = Vec::new();
+    if cfg!(feature = "xml") {
+        features.push("xml");
+    }
+    if cfg!(feature = "trace-jni") {
+        features.push("trace-jni");
+    }
+    version_info_to_java(&mut env, &features).unwrap_or_throw(&mut env)
+}
+
+fn version_info_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    features: &[&str],
+) -> JniResult<JObject<'local>> {
+    let map = env.new_object("java/util/HashMap", "()V", &[])?;
+    let entries: [(&str, JObject); 4] = [
+        ("version", env.new_string(env!("CARGO_PKG_VERSION"))?.into()),
+        ("yrsVersion", env.new_string(env!("YRS_VERSION"))?.into()),
+        ("features", string_list(env, features)?),
+        (
+            "updateEncodings",
+            string_list(env, SUPPORTED_UPDATE_ENCODINGS)?,
+        ),
+    ];
+    for (key, value) in entries {
+        let key = env.new_string(key)?;
+        env.call_method(
+            &map,
+            "put",
+            "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+            &[JValue::Object(&key), JValue::Object(&value)],
+        )?;
+    }
+    Ok(map)
+}
+
+fn string_list<'local>(env: &mut JNIEnv<'local>, items: &[&str]) -> JniResult<JObject<'local>> {
+    let list = env.new_object("java/util/ArrayList", "()V", &[])?;
+    for item in items {
+        let item = env.new_string(item)?;
+        env.call_method(
+            &list,
+            "add",
+            "(Ljava/lang/Object;)Z",
+            &[JValue::Object(&item)],
+        )?;
+    }
+    Ok(list)
+}
+
+/// Encodes the current state of the document as a byte array using an existing transaction
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A Java byte array containing the encoded state
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateAsUpdateWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeStateAsUpdateWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    // Encode against an empty state vector to get the full document state
+    let empty_sv = yrs::StateVector::default();
+    let update = txn.encode_state_as_update_v1(&empty_sv);
+
+    env.create_byte_array(&update).unwrap_or_throw(&mut env)
+}
+
+/// Applies an update to the document from a byte array using an existing transaction
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `update`: Java byte array containing the update
+///
+/// # Returns
+/// A `JniApplyResult` telling whether the update was applied, is pending or was
+/// a duplicate
+///
+/// # Safety
+/// The `update` parameter is a raw JNI pointer that must be valid
+#[no_mangle]
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    update: jbyteArray,
+) -> jobject {
+    trace_native!("JniYDoc.nativeApplyUpdateWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    if !wrapper.accepts_updates() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "YDoc is read-only and does not accept updates",
+        );
+        return std::ptr::null_mut();
+    }
+    check_not_dispatching_or_throw!(&mut env, wrapper, std::ptr::null_mut());
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return std::ptr::null_mut();
+    }
+
+    // Convert Java byte array to Rust Vec<u8>
+    let update_array = JByteArray::from_raw(update);
+    let update_bytes = match env.convert_byte_array(update_array) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return std::ptr::null_mut();
+        }
+    };
+
+    match decode_update_v1(&update_bytes) {
+        Ok(update) => {
+            mark_remote(txn);
+            let probe = ApplyProbe::new(txn);
+            if let Err(e) = txn.apply_update(update) {
+                throw_exception(&mut env, &format!("Failed to apply update: {:?}", e));
+                return std::ptr::null_mut();
+            }
+            let (status, missing) = probe.outcome(txn);
+            apply_result_to_java(&mut env, status, &missing)
+                .map(JObject::into_raw)
+                .unwrap_or_throw(&mut env)
+        }
+        Err(e) => {
+            throw_decode_error(&mut env, wrapper, &e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Builds a `JniApplyResult` from an `APPLY_*` status and the missing clocks
+fn apply_result_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    status: jint,
+    missing: &yrs::StateVector,
+) -> JniResult<JObject<'local>> {
+    let missing = JObject::from(env.byte_array_from_slice(&missing.encode_v1())?);
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniApplyResult")?;
+    let result = env.new_object(
+        &class,
+        "(I[B)V",
+        &[JValue::Int(status), JValue::Object(&missing)],
+    )?;
+    env.delete_local_ref(missing)?;
+    Ok(result)
+}
+
+/// Throws for an update that could not be decoded
+///
+/// The message gives the failing offset and the bytes around it. If the
+/// document logs decode failures, the whole update is also written to stderr.
+pub(crate) fn throw_decode_error(
+    env: &mut JNIEnv,
+    wrapper: &DocWrapper,
+    error: &UpdateDecodeError,
+) {
+    if wrapper.logs_decode_failures() {
+        eprintln!(
+            "ycrdt: undecodable update ({}): {}",
+            error,
+            to_hex(error.payload())
+        );
+    }
+    throw_exception(env, &format!("Failed to decode update: {}", error));
+}
+
+/// Encodes the full state of the document and its subdocuments as a deep update
+///
+/// The document is read through the given transaction and each subdocument
+/// through a transaction of its own; see `deep_update` for the format.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A Java byte array containing the deep update
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateAsUpdateDeepWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeStateAsUpdateDeepWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    match encode_deep_update(txn) {
+        Ok(update) => env.create_byte_array(&update).unwrap_or_throw(&mut env),
+        Err(e) => {
+            throw_deep_update_error(&mut env, wrapper, e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Applies a deep update to the document and its subdocuments
+///
+/// Each document is updated in a transaction of its own, so this must not be
+/// called while the current thread holds a transaction on the document.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `update`: Java byte array containing the deep update
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateDeep(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    update: JByteArray,
+) {
+    trace_native!("JniYDoc.nativeApplyUpdateDeep", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    if !wrapper.accepts_updates() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "YDoc is read-only and does not accept updates",
+        );
+        return;
+    }
+    check_not_dispatching_or_throw!(&mut env, wrapper);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return;
+    }
+    let bytes = match env.convert_byte_array(update) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return;
+        }
+    };
+
+    if let Err(e) = apply_deep_update(&wrapper.doc, &bytes) {
+        throw_deep_update_error(&mut env, wrapper, e);
+    }
+}
+
+fn throw_deep_update_error(env: &mut JNIEnv, wrapper: &DocWrapper, error: DeepUpdateError) {
+    match error {
+        DeepUpdateError::Decode(e) => throw_decode_error(env, wrapper, &e),
+        DeepUpdateError::Held(_) => {
+            let _ = env.throw_new("java/lang/IllegalStateException", error.to_string());
+        }
+        _ => throw_exception(env, &format!("Failed to apply deep update: {}", error)),
+    }
+}
+
+/// Applies the parts of an update that a Java predicate allows, using an existing transaction
+///
+/// The predicate is called once with the name of each root type the update
+/// touches. Content and deletions under denied roots are dropped; see
+/// `update_filter` for how the update is rewritten.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `update`: Java byte array containing the v1 update
+/// - `filter`: A `java.util.function.Predicate<String>` deciding per root name
+///
+/// # Returns
+/// A `JniFilteredUpdate` describing which roots were applied and which were dropped
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateFilteredWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    update: JByteArray,
+    filter: JObject,
+) -> jobject {
+    trace_native!("JniYDoc.nativeApplyUpdateFilteredWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    if !wrapper.accepts_updates() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "YDoc is read-only and does not accept updates",
+        );
+        return std::ptr::null_mut();
+    }
+    check_not_dispatching_or_throw!(&mut env, wrapper, std::ptr::null_mut());
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return std::ptr::null_mut();
+    }
+    let update_bytes = match env.convert_byte_array(&update) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return std::ptr::null_mut();
+        }
+    };
+
+    let filtered = filter_update_v1(txn, &update_bytes, |root| -> JniResult<bool> {
+        let name = env.new_string(root)?;
+        let allowed = env
+            .call_method(
+                &filter,
+                "test",
+                "(Ljava/lang/Object;)Z",
+                &[JValue::Object(&name)],
+            )?
+            .z()?;
+        env.delete_local_ref(name)?;
+        Ok(allowed)
+    });
+    let filtered = match filtered {
+        Ok(filtered) => filtered,
+        Err(FilterError::Decode(e)) => {
+            throw_decode_error(&mut env, wrapper, &e);
+            return std::ptr::null_mut();
+        }
+        Err(FilterError::Check(e)) => {
+            // An exception thrown by the predicate is already pending
+            if !env.exception_check().unwrap_or(false) {
+                throw_exception(&mut env, &e.to_string());
+            }
+            return std::ptr::null_mut();
+        }
+    };
+
+    match decode_update_v1(&filtered.update) {
+        Ok(update) => {
+            mark_remote(txn);
+            if let Err(e) = txn.apply_update(update) {
+                throw_exception(&mut env, &format!("Failed to apply update: {:?}", e));
+                return std::ptr::null_mut();
+            }
+        }
+        Err(e) => {
+            throw_decode_error(&mut env, wrapper, &e);
+            return std::ptr::null_mut();
+        }
+    }
+
+    filtered_update_to_java(&mut env, &filtered)
+        .map(JObject::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Builds a `JniFilteredUpdate` from the result of filtering an update
+fn filtered_update_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    filtered: &FilteredUpdate,
+) -> JniResult<JObject<'local>> {
+    let allowed = strings_to_java(env, filtered.allowed.iter())?;
+    let denied = strings_to_java(env, filtered.denied.iter())?;
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniFilteredUpdate")?;
+    let result = env.new_object(
+        &class,
+        "([Ljava/lang/String;[Ljava/lang/String;Z)V",
+        &[
+            JValue::Object(&allowed),
+            JValue::Object(&denied),
+            JValue::Bool(filtered.dropped_unresolved as u8),
+        ],
+    )?;
+    Ok(result)
+}
+
+/// Handles one y-websocket protocol message using an existing transaction
+///
+/// Sync step 1 is answered with a step 2 reply; step 2 and update messages
+/// are applied to the document. Other message types are decoded and returned
+/// without touching the document. See `sync_message` for the framing.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `message`: Java byte array containing one framed message
+///
+/// # Returns
+/// A `JniSyncMessage` describing the message and any reply to send
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplySyncMessageWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    message: JByteArray,
+) -> jobject {
+    trace_native!("JniYDoc.nativeApplySyncMessageWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return std::ptr::null_mut();
+    }
+    let message = match env.convert_byte_array(&message) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return std::ptr::null_mut();
+        }
+    };
+
+    check_not_dispatching_or_throw!(&mut env, wrapper, std::ptr::null_mut());
+    match handle_sync_message(txn, &message, wrapper.accepts_updates()) {
+        Ok(outcome) => sync_outcome_to_java(&mut env, &outcome)
+            .map(JObject::into_raw)
+            .unwrap_or_throw(&mut env),
+        Err(e) => {
+            throw_sync_error(&mut env, wrapper, e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn throw_sync_error(env: &mut JNIEnv, wrapper: &DocWrapper, error: SyncMessageError) {
+    match error {
+        SyncMessageError::Decode(e) => {
+            throw_exception(env, &format!("Failed to decode sync message: {}", e))
+        }
+        SyncMessageError::Update(e) => throw_decode_error(env, wrapper, &e),
+        SyncMessageError::Apply(e) => {
+            throw_exception(env, &format!("Failed to apply update: {:?}", e))
+        }
+        SyncMessageError::ReadOnly => {
+            let _ = env.throw_new(
+                "java/lang/IllegalStateException",
+                "YDoc is read-only and does not accept updates",
+            );
+        }
+    }
+}
+
+fn sync_outcome_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    outcome: &SyncOutcome,
+) -> JniResult<JObject<'local>> {
+    let reply = match &outcome.reply {
+        Some(reply) => JObject::from(env.byte_array_from_slice(reply)?),
+        None => JObject::null(),
+    };
+    let payload = match &outcome.payload {
+        Some(payload) => JObject::from(env.byte_array_from_slice(payload)?),
+        None => JObject::null(),
+    };
+    let reason = match &outcome.denied_reason {
+        Some(reason) => JObject::from(env.new_string(reason)?),
+        None => JObject::null(),
+    };
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniSyncMessage")?;
+    let result = env.new_object(
+        &class,
+        "(I[B[BILjava/lang/String;)V",
+        &[
+            JValue::Int(outcome.kind),
+            JValue::Object(&reply),
+            JValue::Object(&payload),
+            JValue::Int(i32::from(outcome.custom_type)),
+            JValue::Object(&reason),
+        ],
+    )?;
+    Ok(result)
+}
+
+/// Encodes a y-websocket sync step 1 message using an existing transaction
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// The framed message carrying the document's state vector
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeSyncStep1WithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeSyncStep1WithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    env.create_byte_array(&encode_sync_step1(txn))
+        .unwrap_or_throw(&mut env)
+}
+
+/// Frames a v1 update as a y-websocket sync update message
+///
+/// # Parameters
+/// - `update`: Java byte array containing the update
+///
+/// # Returns
+/// The framed message
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeSyncUpdate(
+    mut env: JNIEnv,
+    _class: JClass,
+    update: JByteArray,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeSyncUpdate");
+    let update = match env.convert_byte_array(&update) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return std::ptr::null_mut();
+        }
+    };
+    env.create_byte_array(&encode_sync_update(update))
+        .unwrap_or_throw(&mut env)
+}
+
+/// Encodes the current state vector of the document using an existing transaction
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A Java byte array containing the encoded state vector
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateVectorWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeStateVectorWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let state_vector = txn.state_vector();
+    let encoded = state_vector.encode_v1();
+
+    env.create_byte_array(&encoded).unwrap_or_throw(&mut env)
+}
+
+/// Returns the clock of each client in the document's state vector using an existing
+/// transaction
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// `[client0, clock0, client1, clock1, ...]` in ascending client order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetStateVectorEntriesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jlongArray {
+    trace_native!("JniYDoc.nativeGetStateVectorEntriesWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let state_vector = txn.state_vector();
+    let mut clients: Vec<_> = state_vector.iter().collect();
+    clients.sort_unstable();
+    let entries: Vec<jlong> = clients
+        .into_iter()
+        .flat_map(|(client, clock)| [*client as jlong, jlong::from(*clock)])
+        .collect();
+    long_array_to_java(&mut env, &entries).unwrap_or_throw(&mut env)
+}
+
+/// Starts or stops recording when each root type is modified
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `enabled`: Whether to record changes
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSetRootActivityTracking(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    enabled: jboolean,
+) {
+    trace_native!("JniYDoc.nativeSetRootActivityTracking", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    if let Err(e) = wrapper
+        .root_activity()
+        .set_enabled(&wrapper.doc, enabled != JNI_FALSE)
+    {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            format!(
+                "Cannot change activity tracking while a transaction is open: {}",
+                e
+            ),
+        );
+    }
+}
+
+/// Returns whether root type modifications are being recorded
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeIsRootActivityTracking(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeIsRootActivityTracking", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    if wrapper.root_activity().is_enabled() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Returns the recorded last modification of each root type
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// A `JniRootActivity[]` sorted by root name
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetRootActivity(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jobject {
+    trace_native!("JniYDoc.nativeGetRootActivity", ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let entries = wrapper.root_activity().snapshot();
+    root_activity_to_java(&mut env, &entries)
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Builds a `JniRootActivity[]` from recorded entries
+fn root_activity_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    entries: &[(String, RootActivity)],
+) -> JniResult<JObjectArray<'local>> {
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniRootActivity")?;
+    let array = env.new_object_array(entries.len() as i32, &class, JObject::null())?;
+    for (i, (name, activity)) in entries.iter().enumerate() {
+        let name = env.new_string(name)?;
+        let ids: Vec<jlong> = activity.clients.iter().map(|c| *c as jlong).collect();
+        let clients = env.new_long_array(ids.len() as i32)?;
+        env.set_long_array_region(&clients, 0, &ids)?;
+        let entry = env.new_object(
+            &class,
+            "(Ljava/lang/String;[JJ)V",
+            &[
+                JValue::Object(&name),
+                JValue::Object(&clients),
+                JValue::Long(activity.at_millis),
+            ],
+        )?;
+        env.set_object_array_element(&array, i as i32, &entry)?;
+        env.delete_local_ref(entry)?;
+        env.delete_local_ref(clients)?;
+        env.delete_local_ref(name)?;
+    }
+    Ok(array)
+}
+
+/// Returns whether the document is holding back updates whose dependencies are missing
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeHasMissingUpdatesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jboolean {
+    trace_native!("JniYDoc.nativeHasMissingUpdatesWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", JNI_FALSE);
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        JNI_FALSE
+    );
+    if has_pending(txn) {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+/// Number of leading document-wide values in the array returned by
+/// `nativeGetStoreStatsWithTxn`
+const STORE_STATS_HEADER: usize = 5;
+
+/// Number of values per client in the array returned by
+/// `nativeGetStoreStatsWithTxn`
+const STORE_STATS_PER_CLIENT: usize = 5;
+
+/// Returns block counts of the document's store
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// `[deletedLength, pendingStructs, pendingBytes, pendingDeletedLength,
+/// stagedUpdates]` followed by `[client, clock, blocks, deletedBlocks,
+/// gcBlocks]` for each client
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetStoreStatsWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jlongArray {
+    trace_native!("JniYDoc.nativeGetStoreStatsWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let stats = match store_stats(txn) {
+        Ok(stats) => stats,
+        Err(e) => {
+            throw_exception(
+                &mut env,
+                &format!("Failed to read the document store: {}", e),
+            );
+            return std::ptr::null_mut();
+        }
+    };
+    let pending = stats.pending.unwrap_or_default();
+    let mut values =
+        Vec::with_capacity(STORE_STATS_HEADER + STORE_STATS_PER_CLIENT * stats.clients.len());
+    values.extend([
+        stats.deleted_length,
+        pending.structs,
+        pending.bytes,
+        pending.deleted,
+        wrapper.staging().len() as u64,
+    ]);
+    let mut values: Vec<jlong> = values
+        .into_iter()
+        .map(|v| jlong::try_from(v).unwrap_or(jlong::MAX))
+        .collect();
+    for client in &stats.clients {
+        values.extend([
+            // Client IDs use all 64 bits; Java reads them back as unsigned
+            client.client as jlong,
+            jlong::from(client.clock),
+            jlong::try_from(client.blocks).unwrap_or(jlong::MAX),
+            jlong::try_from(client.deleted_blocks).unwrap_or(jlong::MAX),
+            jlong::try_from(client.gc_blocks).unwrap_or(jlong::MAX),
+        ]);
+    }
+    long_array_to_java(&mut env, &values).unwrap_or_throw(&mut env)
+}
+
+/// Returns the size of the content held back until missing updates arrive
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// `[structs, bytes, deletedLength]`, or null if nothing is pending
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetPendingStatsWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jlongArray {
+    trace_native!("JniYDoc.nativeGetPendingStatsWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    match pending_state(txn) {
+        Some(state) => counters_to_java(&mut env, &[state.structs, state.bytes, state.deleted])
+            .unwrap_or_throw(&mut env),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Encodes the lowest clock per client that must arrive before pending content can
+/// be integrated
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A v1 encoded state vector, or null if nothing is pending
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetMissingStateVectorWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeGetMissingStateVectorWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    match pending_state(txn) {
+        Some(state) => env
+            .create_byte_array(&state.missing.encode_v1())
+            .unwrap_or_throw(&mut env),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Applies an update once the document has the clocks it starts from, staging it
+/// until then
+///
+/// Applying an update also applies any staged updates that became ready, repeatedly;
+/// see `staging` for how readiness is decided.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `update`: Java byte array containing the v1 update
+///
+/// # Returns
+/// `[applied, flushed, remaining]`: 1 if the update was applied and 0 if it was
+/// staged, the number of staged updates applied, and the number still staged
+///
+/// # Safety
+/// The `update` parameter must be a valid JNI byte array reference
+#[no_mangle]
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeQueueUpdateWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    update: jbyteArray,
+) -> jlongArray {
+    trace_native!("JniYDoc.nativeQueueUpdateWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    if !wrapper.accepts_updates() {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "YDoc is read-only and does not accept updates",
+        );
+        return std::ptr::null_mut();
+    }
+    check_not_dispatching_or_throw!(&mut env, wrapper, std::ptr::null_mut());
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return std::ptr::null_mut();
+    }
+    let update_bytes = match env.convert_byte_array(JByteArray::from_raw(update)) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return std::ptr::null_mut();
+        }
+    };
+
+    match wrapper.staging().apply_or_stage(txn, update_bytes) {
+        Ok(outcome) => counters_to_java(
+            &mut env,
+            &[
+                u64::from(outcome.applied),
+                outcome.flushed as u64,
+                outcome.remaining as u64,
+            ],
+        )
+        .unwrap_or_throw(&mut env),
+        Err(StagingError::Decode(e)) => {
+            throw_decode_error(&mut env, wrapper, &e);
+            std::ptr::null_mut()
+        }
+        Err(StagingError::Apply(e)) => {
+            throw_exception(&mut env, &format!("Failed to apply update: {:?}", e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the number of updates staged on a document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// The number of staged updates
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeGetQueuedUpdateCount(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniYDoc.nativeGetQueuedUpdateCount", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    jint::try_from(wrapper.staging().len()).unwrap_or(jint::MAX)
+}
+
+/// Discards the updates staged on a document
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+///
+/// # Returns
+/// The number of updates discarded
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeClearQueuedUpdates(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) -> jint {
+    trace_native!("JniYDoc.nativeClearQueuedUpdates", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    jint::try_from(wrapper.staging().clear()).unwrap_or(jint::MAX)
+}
+
+/// Queues a batch of operations to run in one transaction on the document's worker
+/// thread
+///
+/// Operation `i` is described by `kinds[i]`, `roots[i]`, `positions[2 * i]` (index),
+/// `positions[2 * i + 1]` (length), `strings[i]` (text chunk or map key) and
+/// `values[i]`; fields an operation kind does not use are ignored. All values are
+/// converted before the batch is queued.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `kinds`: One of the `BATCH_OP_*` constants per operation
+/// - `roots`: Name of the root type each operation targets
+/// - `positions`: Index and length per operation
+/// - `strings`: Text chunk or map key per operation
+/// - `values`: Map or array value per operation
+/// - `completion`: Object whose `complete(byte[], int, String)` receives the outcome
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSubmitBatch(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    kinds: JIntArray,
+    roots: JObjectArray,
+    positions: JIntArray,
+    strings: JObjectArray,
+    values: JObjectArray,
+    completion: JObject,
+) {
+    trace_native!("JniYDoc.nativeSubmitBatch", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let ops = match batch_ops_from_java(
+        &mut env,
+        &kinds,
+        &roots,
+        &positions,
+        &strings,
+        &values,
+        wrapper.string_policy(),
+    ) {
+        Ok(ops) => ops,
+        Err(AnyConversionError::Jni(e)) => {
+            throw_exception(&mut env, &format!("JNI error: {:?}", e));
+            return;
+        }
+        Err(AnyConversionError::Unsupported(class_name)) => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Unsupported batch value type: {}", class_name),
+            );
+            return;
+        }
+        Err(AnyConversionError::InvalidString(msg)) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+            return;
+        }
+    };
+    let submitted = env
+        .new_global_ref(completion)
+        .map_err(JniError::from)
+        .and_then(|completion| {
+            let executor = wrapper.executor(&env)?;
+            wrapper
+                .batch_queue()
+                .submit(&wrapper.doc, &executor, ops, completion)
+                .map_err(|e| JniError::Other(format!("Failed to start batch worker: {}", e)))
+        });
+    if let Err(e) = submitted {
+        throw_jni_error(&mut env, &e);
+    }
+}
+
+/// Reads the parallel arrays passed to `nativeSubmitBatch` into operations
+#[allow(clippy::too_many_arguments)]
+fn batch_ops_from_java(
+    env: &mut JNIEnv,
+    kinds: &JIntArray,
+    roots: &JObjectArray,
+    positions: &JIntArray,
+    strings: &JObjectArray,
+    values: &JObjectArray,
+    policy: StringPolicy,
+) -> Result<Vec<BatchOp>, AnyConversionError> {
+    let len = env.get_array_length(kinds)?;
+    let mut kind_buf = vec![0; len as usize];
+    env.get_int_array_region(kinds, 0, &mut kind_buf)?;
+    let mut position_buf = vec![0; 2 * len as usize];
+    env.get_int_array_region(positions, 0, &mut position_buf)?;
+
+    let mut ops = Vec::with_capacity(len as usize);
+    for (i, kind) in kind_buf.into_iter().enumerate() {
+        let root = batch_string(env, roots, i, policy)?;
+        let (index, length) = (position_buf[2 * i], position_buf[2 * i + 1]);
+        let op = match kind {
+            BATCH_OP_TEXT_INSERT => BatchOp::TextInsert {
+                root,
+                index,
+                chunk: batch_string(env, strings, i, policy)?,
+            },
+            BATCH_OP_TEXT_DELETE => BatchOp::TextDelete {
+                root,
+                index,
+                length,
+            },
+            BATCH_OP_MAP_SET => BatchOp::MapSet {
+                root,
+                key: batch_string(env, strings, i, policy)?,
+                value: batch_value(env, values, i, policy)?,
+            },
+            BATCH_OP_MAP_REMOVE => BatchOp::MapRemove {
+                root,
+                key: batch_string(env, strings, i, policy)?,
+            },
+            BATCH_OP_ARRAY_INSERT => BatchOp::ArrayInsert {
+                root,
+                index,
+                value: batch_value(env, values, i, policy)?,
+            },
+            BATCH_OP_ARRAY_DELETE => BatchOp::ArrayDelete {
+                root,
+                index,
+                length,
+            },
+            _ => {
+                return Err(AnyConversionError::InvalidString(format!(
+                    "Unknown batch operation kind {}",
+                    kind
+                )))
+            }
+        };
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+fn batch_string(
+    env: &mut JNIEnv,
+    array: &JObjectArray,
+    i: usize,
+    policy: StringPolicy,
+) -> Result<String, AnyConversionError> {
+    let element = JString::from(env.get_object_array_element(array, i as i32)?);
+    let s = env.get_rust_string(&element, policy).map_err(|e| match e {
+        JniError::Jni(e) => AnyConversionError::Jni(e),
+        e => AnyConversionError::InvalidString(e.to_string()),
+    })?;
+    env.delete_local_ref(element)?;
+    Ok(s)
+}
+
+fn batch_value(
+    env: &mut JNIEnv,
+    array: &JObjectArray,
+    i: usize,
+    policy: StringPolicy,
+) -> Result<yrs::Any, AnyConversionError> {
+    let element = env.get_object_array_element(array, i as i32)?;
+    let value = jobject_to_any(env, &element, policy)?;
+    env.delete_local_ref(element)?;
+    Ok(value)
+}
+
+/// Checks a document against schema rules with transaction
+///
+/// The rules are passed as parallel arrays, one element per rule.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `kinds`: The `SCHEMA_RULE_*` kind of each rule
+/// - `roots`: The root name of each rule, null for `SCHEMA_RULE_NO_OTHER_ROOTS`
+/// - `keys`: The key of each key rule, null for other rules
+/// - `types`: The `JniTreeNode.Kind` ordinal of each root rule, or the
+///   `JniYMap.ValueType` ordinal of each key rule (-1 for any type)
+///
+/// # Returns
+/// The path and message of each violation, alternating, in rule order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeValidateWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    kinds: JIntArray,
+    roots: JObjectArray,
+    keys: JObjectArray,
+    types: JIntArray,
+) -> jobjectArray {
+    trace_native!("JniYDoc.nativeValidateWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let rules = match schema_rules_from_java(
+        &mut env,
+        &kinds,
+        &roots,
+        &keys,
+        &types,
+        wrapper.string_policy(),
+    ) {
+        Ok(rules) => rules,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    };
+    let violations = validate_schema(txn, &rules);
+    let parts: Vec<&str> = violations
+        .iter()
+        .flat_map(|v| [v.path.as_str(), v.message.as_str()])
+        .collect();
+    strings_to_java(&mut env, parts.into_iter())
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Reads the parallel arrays passed to `nativeValidateWithTxn` into rules
+fn schema_rules_from_java(
+    env: &mut JNIEnv,
+    kinds: &JIntArray,
+    roots: &JObjectArray,
+    keys: &JObjectArray,
+    types: &JIntArray,
+    policy: StringPolicy,
+) -> JniResult<Vec<SchemaRule>> {
+    let len = env.get_array_length(kinds)?;
+    let mut kind_buf = vec![0; len as usize];
+    env.get_int_array_region(kinds, 0, &mut kind_buf)?;
+    let mut type_buf = vec![0; len as usize];
+    env.get_int_array_region(types, 0, &mut type_buf)?;
+
+    let mut rules = Vec::with_capacity(len as usize);
+    for (i, (kind, value_type)) in kind_buf.into_iter().zip(type_buf).enumerate() {
+        let rule = match kind {
+            SCHEMA_RULE_ROOT | SCHEMA_RULE_OPTIONAL_ROOT => SchemaRule::Root {
+                name: schema_string(env, roots, i, policy)?,
+                kind: value_type,
+                required: kind == SCHEMA_RULE_ROOT,
+            },
+            SCHEMA_RULE_KEY => SchemaRule::Key {
+                root: schema_string(env, roots, i, policy)?,
+                key: schema_string(env, keys, i, policy)?,
+                value_type: (value_type >= 0).then_some(value_type),
+            },
+            SCHEMA_RULE_NO_OTHER_ROOTS => SchemaRule::NoOtherRoots,
+            _ => {
+                return Err(JniError::Other(format!(
+                    "Unknown schema rule kind {}",
+                    kind
+                )))
+            }
+        };
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+fn schema_string(
+    env: &mut JNIEnv,
+    array: &JObjectArray,
+    i: usize,
+    policy: StringPolicy,
+) -> JniResult<String> {
+    let element = JString::from(env.get_object_array_element(array, i as i32)?);
+    let s = env.get_rust_string(&element, policy)?;
+    env.delete_local_ref(element)?;
+    Ok(s)
+}
+
+/// Encodes a snapshot of the document using an existing transaction
+///
+/// A snapshot records the state vector and delete set at this point in time.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A Java byte array containing the encoded snapshot
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeSnapshotWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeSnapshotWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let encoded = txn.snapshot().encode_v1();
+
+    env.create_byte_array(&encoded).unwrap_or_throw(&mut env)
+}
+
+/// Encodes the document as it was at a snapshot, using an existing transaction
+///
+/// The result is a v1 update holding every change the snapshot includes, so
+/// applying it to an empty document reconstructs that version. Requires a
+/// document created without garbage collection, since otherwise the content of
+/// items deleted after the snapshot is gone.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `snapshot`: Java byte array containing a snapshot from `nativeEncodeSnapshotWithTxn`
+///
+/// # Returns
+/// A Java byte array containing the v1 update
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateFromSnapshotWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    snapshot: JByteArray,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeStateFromSnapshotWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let bytes = match env.convert_byte_array(&snapshot) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert byte array");
+            return std::ptr::null_mut();
+        }
+    };
+    let snapshot = match yrs::Snapshot::decode_v1(&bytes) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Invalid snapshot: {}", e),
+            );
+            return std::ptr::null_mut();
+        }
+    };
+    match encode_state_from_snapshot_v1(txn, &snapshot) {
+        Ok(update) => env.create_byte_array(&update).unwrap_or_throw(&mut env),
+        Err(yrs::error::Error::Gc) => {
+            let _ = env.throw_new(
+                "java/lang/IllegalStateException",
+                "Encoding from a snapshot requires a document created without garbage collection",
+            );
+            std::ptr::null_mut()
+        }
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to encode snapshot state: {}", e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Encodes the state of `txn` as of `snapshot` as a v1 update
+///
+/// Clients the snapshot lists with a clock of 0 contributed nothing yet and
+/// are left out; yrs expects every listed client to have at least one block.
+fn encode_state_from_snapshot_v1<T: ReadTxn>(
+    txn: &T,
+    snapshot: &yrs::Snapshot,
+) -> Result<Vec<u8>, yrs::error::Error> {
+    let state_map: yrs::StateVector = snapshot
+        .state_map
+        .iter()
+        .filter(|(_, &clock)| clock > 0)
+        .map(|(&client, &clock)| (client, clock))
+        .collect();
+    let snapshot = yrs::Snapshot::new(state_map, snapshot.delete_set.clone());
+    let mut encoder = EncoderV1::new();
+    txn.encode_state_from_snapshot(&snapshot, &mut encoder)?;
+    Ok(encoder.to_vec())
+}
+
+/// Exports every root type of the document as a tree of `JniTreeNode`s
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `max_depth`: Number of shared-type levels to expand below each root
+///
+/// # Returns
+/// A Java `Map<String, JniTreeNode>` keyed by root name
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeExportTreeWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    max_depth: jint,
+) -> jobject {
+    trace_native!("JniYDoc.nativeExportTreeWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    if max_depth < 0 {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "maxDepth must not be negative",
+        );
+        return std::ptr::null_mut();
+    }
+
+    export_roots(&mut env, txn, max_depth as u32)
+        .map(JObject::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Encodes a differential update containing only changes not yet observed by the remote peer
+/// using an existing transaction
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `state_vector`: Java byte array containing the remote peer's state vector
+///
+/// # Returns
+/// A Java byte array containing the differential update
+///
+/// # Safety
+/// The `state_vector` parameter is a raw JNI pointer that must be valid
+#[no_mangle]
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiffWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    state_vector: jbyteArray,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeDiffWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    // Convert Java byte array to Rust Vec<u8>
+    let sv_array = JByteArray::from_raw(state_vector);
+    let sv_bytes = match env.convert_byte_array(sv_array) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to convert state vector byte array");
+            return std::ptr::null_mut();
+        }
+    };
+
+    // Decode the state vector
+    let sv = match yrs::StateVector::decode_v1(&sv_bytes) {
+        Ok(sv) => sv,
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to decode state vector: {:?}", e));
+            return std::ptr::null_mut();
+        }
+    };
 
-    // Encode against an empty state vector to get the full document state
-    let empty_sv = yrs::StateVector::default();
-    let update = txn.encode_state_as_update_v1(&empty_sv);
+    // Encode the differential update
+    let diff = txn.encode_diff_v1(&sv);
 
-    env.create_byte_array(&update).unwrap_or_throw(&mut env)
+    env.create_byte_array(&diff).unwrap_or_throw(&mut env)
 }
 
-/// Applies an update to the document from a byte array using an existing transaction
+/// Measures the differential update for a remote peer without encoding it,
+/// using an existing transaction
 ///
 /// # Parameters
 /// - `ptr`: Pointer to the YDoc instance
 /// - `txn_ptr`: Pointer to the transaction instance
-/// - `update`: Java byte array containing the update
+/// - `state_vector`: Java byte array containing the remote peer's state vector
+///
+/// # Returns
+/// The length in bytes of the update `nativeEncodeDiffWithTxn` would return
 ///
 /// # Safety
-/// The `update` parameter is a raw JNI pointer that must be valid
+/// The `state_vector` parameter is a raw JNI pointer that must be valid
 #[no_mangle]
-pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeApplyUpdateWithTxn(
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEstimateUpdateSizeWithTxn(
     mut env: JNIEnv,
     _class: JClass,
     ptr: jlong,
     txn_ptr: jlong,
-    update: jbyteArray,
-) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
-    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    state_vector: jbyteArray,
+) -> jlong {
+    trace_native!("JniYDoc.nativeEstimateUpdateSizeWithTxn", ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
-    // Convert Java byte array to Rust Vec<u8>
-    let update_array = JByteArray::from_raw(update);
-    let update_bytes = match env.convert_byte_array(update_array) {
+    let sv_bytes = match env.convert_byte_array(JByteArray::from_raw(state_vector)) {
         Ok(bytes) => bytes,
         Err(_) => {
-            throw_exception(&mut env, "Failed to convert byte array");
-            return;
+            throw_exception(&mut env, "Failed to convert state vector byte array");
+            return 0;
         }
     };
-
-    match yrs::Update::decode_v1(&update_bytes) {
-        Ok(update) => {
-            if let Err(e) = txn.apply_update(update) {
-                throw_exception(&mut env, &format!("Failed to apply update: {:?}", e));
-            }
-        }
+    let sv = match yrs::StateVector::decode_v1(&sv_bytes) {
+        Ok(sv) => sv,
         Err(e) => {
-            throw_exception(&mut env, &format!("Failed to decode update: {:?}", e));
+            throw_exception(&mut env, &format!("Failed to decode state vector: {:?}", e));
+            return 0;
         }
-    }
+    };
+    jlong::try_from(diff_size_v1(txn, &sv)).unwrap_or(jlong::MAX)
 }
 
-/// Encodes the current state vector of the document using an existing transaction
+/// Encodes the changes missing from a state vector, keeping only content under
+/// the given roots
+///
+/// Content under other roots is encoded as GC structs; see `update_filter`.
 ///
 /// # Parameters
 /// - `ptr`: Pointer to the YDoc instance
 /// - `txn_ptr`: Pointer to the transaction instance
+/// - `state_vector`: Java byte array containing the remote peer's state vector,
+///   or null to encode the full state
+/// - `roots`: Names of the roots to keep
 ///
 /// # Returns
-/// A Java byte array containing the encoded state vector
+/// A Java byte array containing the update
+///
+/// # Safety
+/// The `state_vector` parameter is a raw JNI pointer that must be valid or null
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateVectorWithTxn(
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiffForRootsWithTxn(
     mut env: JNIEnv,
     _class: JClass,
     ptr: jlong,
     txn_ptr: jlong,
+    state_vector: jbyteArray,
+    roots: JObjectArray,
 ) -> jbyteArray {
-    let _wrapper = get_ref_or_throw!(
+    trace_native!("JniYDoc.nativeEncodeDiffForRootsWithTxn", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(ptr),
         "YDoc",
@@ -206,14 +2421,45 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateVector
         std::ptr::null_mut()
     );
 
-    let state_vector = txn.state_vector();
-    let encoded = state_vector.encode_v1();
+    let sv = if state_vector.is_null() {
+        yrs::StateVector::default()
+    } else {
+        let sv_bytes = match env.convert_byte_array(JByteArray::from_raw(state_vector)) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                throw_exception(&mut env, "Failed to convert state vector byte array");
+                return std::ptr::null_mut();
+            }
+        };
+        match yrs::StateVector::decode_v1(&sv_bytes) {
+            Ok(sv) => sv,
+            Err(e) => {
+                throw_exception(&mut env, &format!("Failed to decode state vector: {:?}", e));
+                return std::ptr::null_mut();
+            }
+        }
+    };
+    let roots = match string_array_to_set(&mut env, &roots, wrapper.string_policy()) {
+        Ok(roots) => roots,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    };
 
-    env.create_byte_array(&encoded).unwrap_or_throw(&mut env)
+    match encode_roots_diff_v1(txn, &sv, &roots) {
+        Ok(update) => env.create_byte_array(&update).unwrap_or_throw(&mut env),
+        Err(e) => {
+            throw_decode_error(&mut env, wrapper, &e);
+            std::ptr::null_mut()
+        }
+    }
 }
 
-/// Encodes a differential update containing only changes not yet observed by the remote peer
-/// using an existing transaction
+/// Encodes the changes a peer is missing, as the first half of a pairwise sync
+///
+/// This is the same diff as `nativeEncodeDiffWithTxn`. The peer passes it to
+/// `nativeAbsorb`, which returns the changes this document is missing.
 ///
 /// # Parameters
 /// - `ptr`: Pointer to the YDoc instance
@@ -226,14 +2472,44 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStateVector
 /// # Safety
 /// The `state_vector` parameter is a raw JNI pointer that must be valid
 #[no_mangle]
-pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiffWithTxn(
+pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeSyncWith(
+    env: JNIEnv,
+    class: JClass,
+    ptr: jlong,
+    txn_ptr: jlong,
+    state_vector: jbyteArray,
+) -> jbyteArray {
+    trace_native!("JniYDoc.nativeSyncWith", ptr, txn_ptr);
+    Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiffWithTxn(
+        env,
+        class,
+        ptr,
+        txn_ptr,
+        state_vector,
+    )
+}
+
+/// Applies a diff from a peer and encodes the changes that peer is missing
+///
+/// See `sync_message::absorb` for how the peer's state is estimated.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `diff`: Java byte array containing the peer's v1 diff
+///
+/// # Returns
+/// A Java byte array containing the reciprocal diff
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeAbsorb(
     mut env: JNIEnv,
     _class: JClass,
     ptr: jlong,
     txn_ptr: jlong,
-    state_vector: jbyteArray,
+    diff: JByteArray,
 ) -> jbyteArray {
-    let _wrapper = get_ref_or_throw!(
+    trace_native!("JniYDoc.nativeAbsorb", ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(ptr),
         "YDoc",
@@ -245,30 +2521,25 @@ pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiff
         "YTransaction",
         std::ptr::null_mut()
     );
-
-    // Convert Java byte array to Rust Vec<u8>
-    let sv_array = JByteArray::from_raw(state_vector);
-    let sv_bytes = match env.convert_byte_array(sv_array) {
+    if !check_txn_doc_or_throw(&mut env, &wrapper.doc, txn) {
+        return std::ptr::null_mut();
+    }
+    let diff = match env.convert_byte_array(&diff) {
         Ok(bytes) => bytes,
         Err(_) => {
-            throw_exception(&mut env, "Failed to convert state vector byte array");
+            throw_exception(&mut env, "Failed to convert byte array");
             return std::ptr::null_mut();
         }
     };
 
-    // Decode the state vector
-    let sv = match yrs::StateVector::decode_v1(&sv_bytes) {
-        Ok(sv) => sv,
+    check_not_dispatching_or_throw!(&mut env, wrapper, std::ptr::null_mut());
+    match absorb(txn, &diff, wrapper.accepts_updates()) {
+        Ok(reply) => env.create_byte_array(&reply).unwrap_or_throw(&mut env),
         Err(e) => {
-            throw_exception(&mut env, &format!("Failed to decode state vector: {:?}", e));
-            return std::ptr::null_mut();
+            throw_sync_error(&mut env, wrapper, e);
+            std::ptr::null_mut()
         }
-    };
-
-    // Encode the differential update
-    let diff = txn.encode_diff_v1(&sv);
-
-    env.create_byte_array(&diff).unwrap_or_throw(&mut env)
+    }
 }
 
 /// Merges multiple updates into a single compact update
@@ -285,8 +2556,9 @@ pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeDiff
 pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeMergeUpdates(
     mut env: JNIEnv,
     _class: JClass,
-    updates: jni::sys::jobjectArray,
+    updates: jobjectArray,
 ) -> jbyteArray {
+    trace_native!("JniYDoc.nativeMergeUpdates");
     use jni::objects::JObjectArray as JObjArray;
 
     // Convert Java 2D byte array to Vec<Vec<u8>>
@@ -355,6 +2627,7 @@ pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeEncodeStat
     _class: JClass,
     update: jbyteArray,
 ) -> jbyteArray {
+    trace_native!("JniYDoc.nativeEncodeStateVectorFromUpdate");
     // Convert Java byte array to Rust Vec<u8>
     let update_array = JByteArray::from_raw(update);
     let update_bytes = match env.convert_byte_array(update_array) {
@@ -398,11 +2671,104 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeBeginTransaction(
     _class: JClass,
     ptr: jlong,
 ) -> jlong {
+    trace_native!("JniYDoc.nativeBeginTransaction", ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    check_not_dispatching_or_throw!(&mut env, wrapper, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
     let txn = wrapper.doc.transact_mut();
+    register_transaction(&mut env, wrapper, txn)
+}
+
+/// Begins a new transaction carrying an origin
+///
+/// The origin is stored as the UTF-8 bytes of the string. Update observers
+/// receive it, and an `UndoManager` only captures the transaction if the
+/// origin is one of its tracked origins.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `origin`: The origin of the transaction
+///
+/// # Returns
+/// A transaction ID (as jlong) that can be used to reference this transaction
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeBeginTransactionWithOrigin(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    origin: JString,
+) -> jlong {
+    trace_native!("JniYDoc.nativeBeginTransactionWithOrigin", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc", 0);
+    let origin = get_string_or_throw!(&mut env, wrapper, origin, 0);
+    check_not_dispatching_or_throw!(&mut env, wrapper, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+    let txn = wrapper.doc.transact_mut_with(origin.as_str());
+    register_transaction(&mut env, wrapper, txn)
+}
+
+/// Hands a new transaction to Java, recording its owner and start time if the
+/// document asks for it
+fn register_transaction(
+    env: &mut JNIEnv,
+    wrapper: &DocWrapper,
+    txn: yrs::TransactionMut<'static>,
+) -> jlong {
+    let txn_ptr = box_transaction(txn);
+    register_handle(txn_ptr);
+    hold_transaction(txn_ptr, &wrapper.doc);
+    if wrapper.is_single_writer() {
+        register_txn_owner(env, txn_ptr);
+    }
+    if wrapper.is_txn_stats_enabled() {
+        record_txn_start(txn_ptr);
+    }
+    txn_ptr
+}
+
+/// Frees a transaction, committing it, and measures it if it was opened with
+/// statistics enabled. The measurement is added to the document's totals.
+///
+/// # Safety
+/// `txn_ptr` must be a live transaction pointer that is not used afterwards
+unsafe fn finish_transaction(wrapper: &DocWrapper, txn_ptr: jlong) -> Option<TxnStats> {
+    let Some(started) = take_txn_start(txn_ptr) else {
+        free_transaction(txn_ptr);
+        return None;
+    };
+    let (ops, update_bytes) = {
+        let txn = &*(handle_address(txn_ptr) as *const yrs::TransactionMut);
+        (count_ops(txn), txn.encode_update_v1().len() as u64)
+    };
+    // Dropping the transaction runs the commit and its observers, which are
+    // part of the measured duration
+    free_transaction(txn_ptr);
+    let stats = TxnStats {
+        duration_nanos: u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX),
+        ops,
+        update_bytes,
+    };
+    wrapper.txn_stats().record(&stats);
+    Some(stats)
+}
+
+/// Converts unsigned counters to a Java long array
+fn counters_to_java(env: &mut JNIEnv, values: &[u64]) -> JniResult<jlongArray> {
+    let values: Vec<jlong> = values
+        .iter()
+        .map(|v| jlong::try_from(*v).unwrap_or(jlong::MAX))
+        .collect();
+    long_array_to_java(env, &values)
+}
 
-    // Return raw transaction pointer
-    Box::into_raw(Box::new(txn)) as jlong
+pub(crate) fn long_array_to_java(env: &mut JNIEnv, values: &[jlong]) -> JniResult<jlongArray> {
+    let array = env.new_long_array(values.len() as i32)?;
+    env.set_long_array_region(&array, 0, values)?;
+    Ok(array.into_raw())
 }
 
 /// Commits a transaction, applying all batched operations
@@ -411,6 +2777,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeBeginTransaction(
 /// - `doc_ptr`: Pointer to the YDoc instance (for validation)
 /// - `txn_ptr`: Transaction ID returned from nativeBeginTransaction
 ///
+/// # Returns
+/// `[durationNanos, ops, updateBytes]` if the transaction was opened with
+/// statistics enabled, otherwise null
+///
 /// # Safety
 /// The transaction ID must be valid and not already committed/rolled back
 #[no_mangle]
@@ -419,16 +2789,83 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeCommit(
     _class: JClass,
     doc_ptr: jlong,
     txn_ptr: jlong,
-) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
-    let _txn = get_ref_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+) -> jlongArray {
+    trace_native!("JniYTransaction.nativeCommit", doc_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let _txn = get_ref_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    if !check_txn_owner(&mut env, txn_ptr) {
+        return std::ptr::null_mut();
+    }
+    unregister_txn_owner(txn_ptr);
 
     // Free transaction - this will drop it and commit
-    unsafe {
-        free_transaction(txn_ptr);
+    let stats = unsafe { finish_transaction(wrapper, txn_ptr) };
+    match stats {
+        Some(stats) => counters_to_java(
+            &mut env,
+            &[stats.duration_nanos, stats.ops, stats.update_bytes],
+        )
+        .unwrap_or_throw(&mut env),
+        None => std::ptr::null_mut(),
     }
 }
 
+/// Labels a transaction with the name of the code path that opened it
+///
+/// The label is printed if native code panics while the transaction is open,
+/// and is forgotten when the transaction is committed.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance (for validation)
+/// - `txn_ptr`: Transaction ID returned from nativeBeginTransaction
+/// - `label`: The label
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeSetLabel(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    txn_ptr: jlong,
+    label: JString,
+) {
+    trace_native!("JniYTransaction.nativeSetLabel", doc_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let _txn = get_ref_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let label = get_string_or_throw!(&mut env, wrapper, label);
+    set_txn_label(txn_ptr, &label);
+}
+
+/// Marks a transaction as silent
+///
+/// Observers of shared types skip the events of a silent transaction. It is
+/// still committed normally, so update observers and update streams see it.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance (for validation)
+/// - `txn_ptr`: Transaction ID returned from nativeBeginTransaction
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeSetSilent(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    txn_ptr: jlong,
+) {
+    trace_native!("JniYTransaction.nativeSetSilent", doc_ptr, txn_ptr);
+    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let txn = get_ref_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    mark_silent(txn as *const yrs::TransactionMut as jlong);
+}
+
 /// Rolls back a transaction, discarding all batched operations
 ///
 /// # Parameters
@@ -448,14 +2885,20 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYTransaction_nativeRollback(
     doc_ptr: jlong,
     txn_ptr: jlong,
 ) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYTransaction.nativeRollback", doc_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
     let _txn = get_ref_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
+    if !check_txn_owner(&mut env, txn_ptr) {
+        return;
+    }
+    unregister_txn_owner(txn_ptr);
+
     // Free transaction
     // Note: yrs doesn't support true rollback - dropping the transaction commits it
     // In the future, we might need to track changes and implement manual rollback
     unsafe {
-        free_transaction(txn_ptr);
+        finish_transaction(wrapper, txn_ptr);
     }
 }
 
@@ -473,11 +2916,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeObserveUpdateV1(
     subscription_id: jlong,
     ydoc_obj: JObject,
 ) {
+    trace_native!("JniYDoc.nativeObserveUpdateV1", ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
         Err(e) => {
             throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
             return;
@@ -493,11 +2937,23 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeObserveUpdateV1(
         }
     };
 
+    let gate = wrapper.dispatch_gate();
+
     // Create observer closure
-    let subscription = match wrapper.doc.observe_update_v1(move |_txn, event| {
+    let subscription = match wrapper.doc.observe_update_v1(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
         // Use Executor for thread attachment with automatic local frame management
         let _ = executor.with_attached(|env| {
-            dispatch_update_event(env, ptr, subscription_id, event.update.as_ref())
+            dispatch_update_event(
+                env,
+                ptr,
+                subscription_id,
+                event.update.as_ref(),
+                txn.origin(),
+            )
         });
     }) {
         Ok(sub) => sub,
@@ -508,7 +2964,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeObserveUpdateV1(
     };
 
     // Store subscription and global ref in the DocWrapper
-    wrapper.add_subscription(subscription_id, subscription, global_ref);
+    wrapper.add_subscription(subscription_id, 0, subscription, global_ref);
 }
 
 /// Unregisters an update observer for the YDoc
@@ -523,6 +2979,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeUnobserveUpdateV1
     ptr: jlong,
     subscription_id: jlong,
 ) {
+    trace_native!("JniYDoc.nativeUnobserveUpdateV1", ptr);
     let doc_ptr = DocPtr::from_raw(ptr);
     if doc_ptr.is_null() {
         return;
@@ -540,12 +2997,18 @@ fn dispatch_update_event(
     doc_ptr: jlong,
     subscription_id: jlong,
     update: &[u8],
+    origin: Option<&yrs::Origin>,
 ) -> Result<(), jni::errors::Error> {
     // Convert update to Java byte array
     let update_array = env.byte_array_from_slice(update)?;
 
-    // Get origin (if any) - yrs update events don't have origin, so we'll use null
-    let origin_jstr = JObject::null();
+    // The origin of the transaction, or null if it has none
+    let origin_jstr = match origin {
+        Some(origin) => env
+            .new_string(String::from_utf8_lossy(origin.as_ref()))?
+            .into(),
+        None => JObject::null(),
+    };
 
     // Get the Java YDoc object from DocWrapper
     let ptr = DocPtr::from_raw(doc_ptr);
@@ -580,10 +3043,132 @@ fn dispatch_update_event(
     Ok(())
 }
 
+/// Gets or creates a root type and registers an observer for it
+///
+/// The root's events are dispatched to the Java YDoc object, which is also
+/// their target, so no handle to the root type is created.
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YDoc instance
+/// - `name`: The name of the root type
+/// - `kind`: The ordinal of the root's `JniTreeNode.Kind`
+/// - `subscription_id`: The subscription ID from Java
+/// - `ydoc_obj`: The Java YDoc object for callbacks
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYDoc_nativeObserveRoot(
+    mut env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+    name: JString,
+    kind: jint,
+    subscription_id: jlong,
+    ydoc_obj: JObject,
+) {
+    trace_native!("JniYDoc.nativeObserveRoot", ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(ptr), "YDoc");
+    let name = get_string_or_throw!(&mut env, wrapper, name);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return;
+    }
+
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
+            return;
+        }
+    };
+
+    let global_ref = match env.new_global_ref(ydoc_obj) {
+        Ok(r) => r,
+        Err(e) => {
+            throw_exception(&mut env, &format!("Failed to create global ref: {:?}", e));
+            return;
+        }
+    };
+
+    let gate = wrapper.dispatch_gate();
+
+    // Subscribes `$root` with the dispatcher of its type, which is passed
+    // `$arg` after the event
+    macro_rules! observe_root {
+        ($root:expr, $dispatch:path $(, $arg:expr)?) => {
+            $root.observe(move |txn, event| {
+                // Skip the callback once the document has started closing
+                let Some(_dispatch) = gate.enter() else {
+                    return;
+                };
+                let _ = executor.with_attached(|env| {
+                    $dispatch(env, ptr, subscription_id, txn, event $(, $arg)?)
+                });
+            })
+        };
+    }
+
+    let doc = &wrapper.doc;
+    let subscription = match kind {
+        TREE_KIND_MAP => observe_root!(doc.get_or_insert_map(name.as_str()), dispatch_map_event),
+        TREE_KIND_ARRAY => {
+            observe_root!(doc.get_or_insert_array(name.as_str()), dispatch_array_event)
+        }
+        TREE_KIND_TEXT => {
+            let text = doc.get_or_insert_text(name.as_str());
+            let shadow = wrapper.text_shadows().share(&text, doc.offset_kind(), || {
+                TextShadow::new(&doc.transact(), &text)
+            });
+            observe_root!(text, dispatch_text_event, shadow.as_deref())
+        }
+        #[cfg(feature = "xml")]
+        TREE_KIND_XML_FRAGMENT => observe_root!(
+            doc.get_or_insert_xml_fragment(name.as_str()),
+            dispatch_xmlfragment_event
+        ),
+        #[cfg(feature = "xml")]
+        TREE_KIND_XML_ELEMENT => match root_xml_element(doc, name.as_str()) {
+            Some(element) => observe_root!(element, dispatch_xmlelement_event),
+            None => {
+                throw_root_kind_mismatch(&mut env, &name, "an XML element");
+                return;
+            }
+        },
+        #[cfg(feature = "xml")]
+        TREE_KIND_XML_TEXT => match root_xml_text(doc, name.as_str()) {
+            Some(text) => {
+                let shadow = wrapper.text_shadows().share(&text, doc.offset_kind(), || {
+                    TextShadow::new(&doc.transact(), &text)
+                });
+                observe_root!(text, dispatch_xmltext_event, shadow.as_deref())
+            }
+            None => {
+                throw_root_kind_mismatch(&mut env, &name, "an XML text");
+                return;
+            }
+        },
+        _ => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Root types of kind {} cannot be observed", kind),
+            );
+            return;
+        }
+    };
+
+    wrapper.add_subscription(subscription_id, 0, subscription, global_ref);
+}
+
+#[cfg(feature = "xml")]
+fn throw_root_kind_mismatch(env: &mut JNIEnv, name: &str, expected: &str) {
+    let _ = env.throw_new(
+        "java/lang/IllegalArgumentException",
+        format!("Root '{}' does not hold {}", name, expected),
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use yrs::{Text, Transact};
+    use yrs::{GetString, Text, Transact};
 
     #[test]
     fn test_doc_creation() {
@@ -604,6 +3189,34 @@ mod tests {
         assert_eq!(wrapper.doc.client_id(), 12345);
     }
 
+    #[test]
+    fn test_state_from_snapshot() {
+        let doc = yrs::Doc::with_options(yrs::Options {
+            skip_gc: true,
+            ..Default::default()
+        });
+        let text = doc.get_or_insert_text("test");
+        text.push(&mut doc.transact_mut(), "Hello");
+        let snapshot = doc.transact().snapshot();
+        text.push(&mut doc.transact_mut(), ", World!");
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
+
+        let update = encode_state_from_snapshot_v1(&doc.transact(), &snapshot).unwrap();
+        let restored = yrs::Doc::new();
+        let restored_text = restored.get_or_insert_text("test");
+        restored
+            .transact_mut()
+            .apply_update(yrs::Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(restored_text.get_string(&restored.transact()), "Hello");
+
+        let gc_doc = yrs::Doc::new();
+        assert!(matches!(
+            encode_state_from_snapshot_v1(&gc_doc.transact(), &snapshot),
+            Err(yrs::error::Error::Gc)
+        ));
+    }
+
     #[test]
     fn test_state_encoding() {
         let wrapper = DocWrapper::new();
@@ -0,0 +1,755 @@
+//! JSON trees kept under a root map.
+//!
+//! `JniYJson` stores a JSON value under one root: objects are nested maps,
+//! arrays are nested arrays and every other value is stored as is. Values are
+//! addressed with JSON Pointers (RFC 6901) and changed with the `add`,
+//! `replace` and `remove` operations of JSON Patch (RFC 6902).
+//!
+//! Changes to the tree, local or remote, are reported as JSON Patch
+//! operations. yrs reports the changes of each container separately, with
+//! paths as of the end of the transaction; the changes of outer containers
+//! are listed first, so the operations applied in order to the previous state
+//! give the new one. A text nested in the tree is reported as a `replace` of
+//! the whole string.
+//!
+//! Objects and arrays stored as plain values by other APIs can be read, but
+//! not changed in place.
+
+use crate::{
+    check_writable_or_throw, get_mut_or_throw, get_ref_or_throw, get_string_or_throw, is_silent,
+    new_java_event, to_json_string, to_jstring, trace_native, DocPtr, JniEnvExt, JniResult, MapPtr,
+    TxnPtr,
+};
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::sys::{jlong, jstring};
+use jni::JNIEnv;
+use std::fmt;
+use yrs::types::{Change, EntryChange, Event, Events, PathSegment, ToJson};
+use yrs::{
+    Any, Array, ArrayPrelim, ArrayRef, DeepObservable, GetString, In, Map, MapPrelim, MapRef, Out,
+    ReadTxn, TransactionMut,
+};
+
+/// Reasons a JSON Pointer cannot be read or written
+#[derive(Debug, PartialEq)]
+pub enum JsonPathError {
+    /// The pointer is neither empty nor starts with `/`
+    InvalidPointer(String),
+    /// Nothing exists at the pointer
+    NotFound(String),
+    /// The pointer goes through a value that is not a shared map or array
+    NotAContainer(String),
+    /// The pointer ends in an array index that is malformed or out of range
+    InvalidIndex(String),
+    /// The root cannot be removed, and can only be replaced by an object
+    Root,
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonPathError::InvalidPointer(p) => write!(f, "Invalid JSON Pointer: {:?}", p),
+            JsonPathError::NotFound(p) => write!(f, "No value at {:?}", p),
+            JsonPathError::NotAContainer(p) => {
+                write!(f, "Value at {:?} is not a shared object or array", p)
+            }
+            JsonPathError::InvalidIndex(p) => write!(f, "Invalid array index in {:?}", p),
+            JsonPathError::Root => write!(f, "The root can only be replaced by an object"),
+        }
+    }
+}
+
+/// Operations of JSON Patch that changes are reported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOp {
+    Add,
+    Replace,
+    Remove,
+}
+
+impl PatchOp {
+    /// The `op` member of the operation
+    pub fn name(self) -> &'static str {
+        match self {
+            PatchOp::Add => "add",
+            PatchOp::Replace => "replace",
+            PatchOp::Remove => "remove",
+        }
+    }
+}
+
+/// One JSON Patch operation
+#[derive(Debug, PartialEq)]
+pub struct PatchEntry {
+    pub op: PatchOp,
+    pub path: String,
+    /// The new value, absent for `remove`
+    pub value: Option<Any>,
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, JsonPathError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(tokens) = pointer.strip_prefix('/') else {
+        return Err(JsonPathError::InvalidPointer(pointer.to_string()));
+    };
+    Ok(tokens
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parses an array index, which has no sign and no leading zeros
+fn parse_index(token: &str) -> Option<u32> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    valid.then(|| token.parse().ok()).flatten()
+}
+
+/// A shared map or array in the tree
+enum Container {
+    Map(MapRef),
+    Array(ArrayRef),
+}
+
+/// Finds the container the last token of `tokens` refers into
+fn resolve_parent<T: ReadTxn>(
+    txn: &T,
+    root: &MapRef,
+    tokens: &[String],
+) -> Result<Container, JsonPathError> {
+    let mut container = Container::Map(root.clone());
+    for (i, token) in tokens.iter().enumerate() {
+        let child = match &container {
+            Container::Map(map) => map.get(txn, token),
+            Container::Array(array) => parse_index(token).and_then(|i| array.get(txn, i)),
+        };
+        let pointer = || to_pointer(&tokens[..=i]);
+        container = match child {
+            Some(Out::YMap(map)) => Container::Map(map),
+            Some(Out::YArray(array)) => Container::Array(array),
+            Some(_) => return Err(JsonPathError::NotAContainer(pointer())),
+            None => return Err(JsonPathError::NotFound(pointer())),
+        };
+    }
+    Ok(container)
+}
+
+fn to_pointer(tokens: &[String]) -> String {
+    tokens
+        .iter()
+        .map(|t| format!("/{}", escape_token(t)))
+        .collect()
+}
+
+/// Converts a JSON value to the shared types it is stored as
+fn to_in(value: Any) -> In {
+    match value {
+        Any::Map(entries) => In::Map(MapPrelim::from_iter(
+            entries.iter().map(|(k, v)| (k.as_str(), to_in(v.clone()))),
+        )),
+        Any::Array(items) => In::Array(ArrayPrelim::from_iter(
+            items.iter().map(|item| to_in(item.clone())),
+        )),
+        value => In::Any(value),
+    }
+}
+
+/// Returns the value at `pointer`, or `None` if there is none
+pub fn json_get<T: ReadTxn>(
+    txn: &T,
+    root: &MapRef,
+    pointer: &str,
+) -> Result<Option<Any>, JsonPathError> {
+    let mut current = Out::YMap(root.clone());
+    for token in parse_pointer(pointer)? {
+        let child = match &current {
+            Out::YMap(map) => map.get(txn, &token),
+            Out::YArray(array) => parse_index(&token).and_then(|i| array.get(txn, i)),
+            Out::Any(Any::Map(entries)) => entries.get(&token).cloned().map(Out::Any),
+            Out::Any(Any::Array(items)) => parse_index(&token)
+                .and_then(|i| items.get(i as usize))
+                .cloned()
+                .map(Out::Any),
+            _ => None,
+        };
+        match child {
+            Some(child) => current = child,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current.to_json(txn)))
+}
+
+/// Adds `value` at `pointer`: sets an object member, or inserts into an array
+/// at an index or at the end (`-`)
+pub fn json_add(
+    txn: &mut TransactionMut,
+    root: &MapRef,
+    pointer: &str,
+    value: Any,
+) -> Result<(), JsonPathError> {
+    let tokens = parse_pointer(pointer)?;
+    let Some((last, parents)) = tokens.split_last() else {
+        return replace_root(txn, root, value);
+    };
+    match resolve_parent(txn, root, parents)? {
+        Container::Map(map) => {
+            map.insert(txn, last.as_str(), to_in(value));
+        }
+        Container::Array(array) => {
+            let len = array.len(txn);
+            let index = if last == "-" {
+                len
+            } else {
+                parse_index(last)
+                    .filter(|&i| i <= len)
+                    .ok_or_else(|| JsonPathError::InvalidIndex(pointer.to_string()))?
+            };
+            array.insert(txn, index, to_in(value));
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the value at `pointer`, which must exist
+pub fn json_replace(
+    txn: &mut TransactionMut,
+    root: &MapRef,
+    pointer: &str,
+    value: Any,
+) -> Result<(), JsonPathError> {
+    let tokens = parse_pointer(pointer)?;
+    let Some((last, parents)) = tokens.split_last() else {
+        return replace_root(txn, root, value);
+    };
+    match resolve_parent(txn, root, parents)? {
+        Container::Map(map) => {
+            if !map.contains_key(txn, last) {
+                return Err(JsonPathError::NotFound(pointer.to_string()));
+            }
+            map.insert(txn, last.as_str(), to_in(value));
+        }
+        Container::Array(array) => {
+            let index = existing_index(txn, &array, last, pointer)?;
+            array.remove(txn, index);
+            array.insert(txn, index, to_in(value));
+        }
+    }
+    Ok(())
+}
+
+/// Removes the value at `pointer`, which must exist
+pub fn json_remove(
+    txn: &mut TransactionMut,
+    root: &MapRef,
+    pointer: &str,
+) -> Result<(), JsonPathError> {
+    let tokens = parse_pointer(pointer)?;
+    let Some((last, parents)) = tokens.split_last() else {
+        return Err(JsonPathError::Root);
+    };
+    match resolve_parent(txn, root, parents)? {
+        Container::Map(map) => {
+            if map.remove(txn, last).is_none() {
+                return Err(JsonPathError::NotFound(pointer.to_string()));
+            }
+        }
+        Container::Array(array) => {
+            let index = existing_index(txn, &array, last, pointer)?;
+            array.remove(txn, index);
+        }
+    }
+    Ok(())
+}
+
+fn existing_index<T: ReadTxn>(
+    txn: &T,
+    array: &ArrayRef,
+    token: &str,
+    pointer: &str,
+) -> Result<u32, JsonPathError> {
+    let index =
+        parse_index(token).ok_or_else(|| JsonPathError::InvalidIndex(pointer.to_string()))?;
+    if index < array.len(txn) {
+        Ok(index)
+    } else {
+        Err(JsonPathError::NotFound(pointer.to_string()))
+    }
+}
+
+/// Replaces the members of the root with those of the object `value`
+fn replace_root(txn: &mut TransactionMut, root: &MapRef, value: Any) -> Result<(), JsonPathError> {
+    let Any::Map(entries) = value else {
+        return Err(JsonPathError::Root);
+    };
+    let stale: Vec<String> = root
+        .keys(txn)
+        .filter(|key| !entries.contains_key(*key))
+        .map(str::to_string)
+        .collect();
+    for key in stale {
+        root.remove(txn, &key);
+    }
+    for (key, value) in entries.iter() {
+        root.insert(txn, key.as_str(), to_in(value.clone()));
+    }
+    Ok(())
+}
+
+/// Describes the events of a deep observer as JSON Patch operations
+pub fn patch_from_events(txn: &TransactionMut, events: &Events) -> Vec<PatchEntry> {
+    let mut events: Vec<(String, usize, &Event)> = events
+        .iter()
+        .map(|event| {
+            let path = event.path();
+            let depth = path.len();
+            let pointer = path
+                .into_iter()
+                .map(|segment| match segment {
+                    PathSegment::Key(key) => format!("/{}", escape_token(&key)),
+                    PathSegment::Index(index) => format!("/{}", index),
+                })
+                .collect();
+            (pointer, depth, event)
+        })
+        .collect();
+    // Events at the same depth change disjoint values; order them by path so
+    // the patch does not depend on the order yrs reports them in.
+    events.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+
+    let mut patch = Vec::new();
+    for (base, _, event) in events {
+        match event {
+            Event::Map(e) => {
+                let mut keys: Vec<_> = e.keys(txn).iter().collect();
+                keys.sort_by(|a, b| a.0.cmp(b.0));
+                for (key, change) in keys {
+                    let (op, value) = match change {
+                        EntryChange::Inserted(value) => (PatchOp::Add, Some(value.to_json(txn))),
+                        EntryChange::Updated(_, value) => {
+                            (PatchOp::Replace, Some(value.to_json(txn)))
+                        }
+                        EntryChange::Removed(_) => (PatchOp::Remove, None),
+                    };
+                    let path = format!("{}/{}", base, escape_token(key));
+                    patch.push(PatchEntry { op, path, value });
+                }
+            }
+            Event::Array(e) => {
+                let mut index = 0;
+                for change in e.delta(txn) {
+                    match change {
+                        Change::Added(values) => {
+                            for value in values {
+                                patch.push(PatchEntry {
+                                    op: PatchOp::Add,
+                                    path: format!("{}/{}", base, index),
+                                    value: Some(value.to_json(txn)),
+                                });
+                                index += 1;
+                            }
+                        }
+                        Change::Removed(len) => {
+                            for _ in 0..*len {
+                                patch.push(PatchEntry {
+                                    op: PatchOp::Remove,
+                                    path: format!("{}/{}", base, index),
+                                    value: None,
+                                });
+                            }
+                        }
+                        Change::Retain(len) => index += len,
+                    }
+                }
+            }
+            Event::Text(e) => patch.push(PatchEntry {
+                op: PatchOp::Replace,
+                path: base,
+                value: Some(Any::from(e.target().get_string(txn))),
+            }),
+            _ => {}
+        }
+    }
+    patch
+}
+
+/// Throws `IllegalArgumentException` for a pointer that cannot be used
+fn throw_path_error(env: &mut JNIEnv, error: &JsonPathError) {
+    let _ = env.throw_new("java/lang/IllegalArgumentException", error.to_string());
+}
+
+/// Parses the JSON text of a value, throwing `IllegalArgumentException` if it
+/// is not valid JSON
+fn parse_json_or_throw(env: &mut JNIEnv, json: &str) -> Option<Any> {
+    match Any::from_json(json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Invalid JSON: {}", e),
+            );
+            None
+        }
+    }
+}
+
+/// Returns the JSON text of the value at a JSON Pointer
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the root YMap
+/// - `txn_ptr`: Pointer to the transaction
+/// - `pointer`: The JSON Pointer
+///
+/// # Returns
+/// The JSON text, or null if there is no value at the pointer
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYJson_nativeGetWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    pointer: JString,
+) -> jstring {
+    trace_native!("JniYJson.nativeGetWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let null = std::ptr::null_mut();
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", null);
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", null);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", null);
+    let pointer = get_string_or_throw!(&mut env, wrapper, pointer, null);
+
+    match json_get(txn, map, &pointer) {
+        Ok(Some(value)) => to_jstring(&mut env, &to_json_string(&value, wrapper.sorts_keys())),
+        Ok(None) => null,
+        Err(e) => {
+            throw_path_error(&mut env, &e);
+            null
+        }
+    }
+}
+
+/// Adds a value at a JSON Pointer, as the JSON Patch `add` operation
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the root YMap
+/// - `txn_ptr`: Pointer to the transaction
+/// - `pointer`: The JSON Pointer
+/// - `json`: JSON text of the value
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYJson_nativeAddWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    pointer: JString,
+    json: JString,
+) {
+    trace_native!("JniYJson.nativeAddWithTxn", doc_ptr, map_ptr, txn_ptr);
+    write_with_txn(&mut env, doc_ptr, map_ptr, txn_ptr, pointer, json, json_add);
+}
+
+/// Replaces the value at a JSON Pointer, as the JSON Patch `replace` operation
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the root YMap
+/// - `txn_ptr`: Pointer to the transaction
+/// - `pointer`: The JSON Pointer
+/// - `json`: JSON text of the value
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYJson_nativeReplaceWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    pointer: JString,
+    json: JString,
+) {
+    trace_native!("JniYJson.nativeReplaceWithTxn", doc_ptr, map_ptr, txn_ptr);
+    write_with_txn(
+        &mut env,
+        doc_ptr,
+        map_ptr,
+        txn_ptr,
+        pointer,
+        json,
+        json_replace,
+    );
+}
+
+fn write_with_txn(
+    env: &mut JNIEnv,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    pointer: JString,
+    json: JString,
+    write: fn(&mut TransactionMut, &MapRef, &str, Any) -> Result<(), JsonPathError>,
+) {
+    let wrapper = get_ref_or_throw!(env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(env, wrapper);
+    let map = get_ref_or_throw!(env, MapPtr::from_raw(map_ptr), "YMap");
+    let txn = get_mut_or_throw!(env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let pointer = get_string_or_throw!(env, wrapper, pointer);
+    let json = get_string_or_throw!(env, wrapper, json);
+    let Some(value) = parse_json_or_throw(env, &json) else {
+        return;
+    };
+    if let Err(e) = write(txn, map, &pointer, value) {
+        throw_path_error(env, &e);
+    }
+}
+
+/// Removes the value at a JSON Pointer, as the JSON Patch `remove` operation
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the root YMap
+/// - `txn_ptr`: Pointer to the transaction
+/// - `pointer`: The JSON Pointer
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYJson_nativeRemoveWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    pointer: JString,
+) {
+    trace_native!("JniYJson.nativeRemoveWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let pointer = get_string_or_throw!(&mut env, wrapper, pointer);
+
+    if let Err(e) = json_remove(txn, map, &pointer) {
+        throw_path_error(&mut env, &e);
+    }
+}
+
+/// Registers an observer for changes anywhere in the JSON tree
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the root YMap
+/// - `subscription_id`: The subscription ID from Java
+/// - `json_obj`: The Java JniYJson object for callbacks
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYJson_nativeObserve(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    subscription_id: jlong,
+    json_obj: JObject,
+) {
+    trace_native!("JniYJson.nativeObserve", doc_ptr, map_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
+
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
+        Err(e) => {
+            crate::throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
+            return;
+        }
+    };
+    let global_ref = match env.new_global_ref(json_obj) {
+        Ok(r) => r,
+        Err(e) => {
+            crate::throw_exception(&mut env, &format!("Failed to create global ref: {:?}", e));
+            return;
+        }
+    };
+
+    let gate = wrapper.dispatch_gate();
+    let sort_keys = wrapper.sorts_keys();
+    let subscription = map.observe_deep(move |txn, events| {
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
+        if is_silent(txn) {
+            return;
+        }
+        let patch = patch_from_events(txn, events);
+        if patch.is_empty() {
+            return;
+        }
+        let _ = executor.with_attached(|env| {
+            dispatch_patch(env, doc_ptr, subscription_id, txn, &patch, sort_keys)
+        });
+    });
+
+    wrapper.add_subscription(subscription_id, map_ptr, subscription, global_ref);
+}
+
+/// Passes a patch to `JniYJson.dispatchEvent` as an event whose changes are
+/// `JniJsonPatchOp`s
+fn dispatch_patch(
+    env: &mut JNIEnv,
+    doc_ptr: jlong,
+    subscription_id: jlong,
+    txn: &TransactionMut,
+    patch: &[PatchEntry],
+    sort_keys: bool,
+) -> JniResult<()> {
+    let Some(wrapper) = (unsafe { DocPtr::from_raw(doc_ptr).as_ref() }) else {
+        return Ok(());
+    };
+    let Some(json_ref) = wrapper.get_java_ref(subscription_id) else {
+        return Ok(());
+    };
+    let json_obj = json_ref.as_obj();
+
+    let op_class = crate::find_callback_class(env, "net/carcdr/ycrdt/jni/JniJsonPatchOp")?;
+    let changes = env.new_object("java/util/ArrayList", "()V", &[])?;
+    for entry in patch {
+        let op = env.new_string(entry.op.name())?;
+        let path = env.new_string(&entry.path)?;
+        let value = match &entry.value {
+            Some(value) => JObject::from(env.new_string(to_json_string(value, sort_keys))?),
+            None => JObject::null(),
+        };
+        let change = env.new_object(
+            &op_class,
+            "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
+            &[
+                JValue::Object(&op),
+                JValue::Object(&path),
+                JValue::Object(&value),
+            ],
+        )?;
+        env.call_method(
+            &changes,
+            "add",
+            "(Ljava/lang/Object;)Z",
+            &[JValue::Object(&change)],
+        )?;
+        env.delete_local_ref(change)?;
+        env.delete_local_ref(op)?;
+        env.delete_local_ref(path)?;
+        env.delete_local_ref(value)?;
+    }
+
+    let event = new_java_event(env, txn, json_obj, &changes)?;
+    env.call_method(
+        json_obj,
+        "dispatchEvent",
+        "(JLnet/carcdr/ycrdt/jni/JniYEvent;)V",
+        &[JValue::Long(subscription_id), JValue::Object(&event)],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use yrs::{Doc, Text, TextPrelim, Transact};
+
+    fn json(text: &str) -> Any {
+        Any::from_json(text).unwrap()
+    }
+
+    #[test]
+    fn test_pointer_tokens_are_unescaped() {
+        assert_eq!(parse_pointer("").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_pointer("/a~1b/~0c/").unwrap(), vec!["a/b", "~c", ""]);
+        assert_eq!(
+            parse_pointer("a"),
+            Err(JsonPathError::InvalidPointer("a".to_string()))
+        );
+        assert_eq!(parse_index("0"), Some(0));
+        assert_eq!(parse_index("01"), None);
+        assert_eq!(parse_index("-1"), None);
+    }
+
+    #[test]
+    fn test_add_replace_and_remove() {
+        let doc = Doc::new();
+        let root = doc.get_or_insert_map("state");
+        let mut txn = doc.transact_mut();
+        json_add(&mut txn, &root, "/users", json(r#"[{"name":"ann"}]"#)).unwrap();
+        json_add(&mut txn, &root, "/users/-", json(r#"{"name":"bob"}"#)).unwrap();
+        json_add(&mut txn, &root, "/users/0/age", json("41")).unwrap();
+        json_replace(&mut txn, &root, "/users/1/name", json(r#""bo""#)).unwrap();
+        json_add(&mut txn, &root, "/users/1", json(r#"{"name":"cy"}"#)).unwrap();
+        json_remove(&mut txn, &root, "/users/2").unwrap();
+
+        // Nested objects and arrays are shared types
+        assert!(matches!(root.get(&txn, "users"), Some(Out::YArray(_))));
+        assert_eq!(
+            json_get(&txn, &root, "/users").unwrap(),
+            Some(json(r#"[{"name":"ann","age":41},{"name":"cy"}]"#))
+        );
+        assert_eq!(json_get(&txn, &root, "/users/5").unwrap(), None);
+        assert_eq!(
+            json_replace(&mut txn, &root, "/missing", json("1")),
+            Err(JsonPathError::NotFound("/missing".to_string()))
+        );
+        assert_eq!(
+            json_add(&mut txn, &root, "/users/0/age/x", json("1")),
+            Err(JsonPathError::NotAContainer("/users/0/age".to_string()))
+        );
+        assert_eq!(
+            json_add(&mut txn, &root, "/users/9", json("1")),
+            Err(JsonPathError::InvalidIndex("/users/9".to_string()))
+        );
+
+        json_replace(&mut txn, &root, "", json(r#"{"v":1}"#)).unwrap();
+        assert_eq!(json_get(&txn, &root, "").unwrap(), Some(json(r#"{"v":1}"#)));
+        assert_eq!(json_remove(&mut txn, &root, ""), Err(JsonPathError::Root));
+    }
+
+    #[test]
+    fn test_changes_are_reported_as_patch() {
+        let doc = Doc::new();
+        let root = doc.get_or_insert_map("state");
+        json_add(&mut doc.transact_mut(), &root, "/list", json(r#"[1,2,3]"#)).unwrap();
+        let note = root.insert(&mut doc.transact_mut(), "note", TextPrelim::new("hi"));
+
+        let patches = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let patches = patches.clone();
+            root.observe_deep(move |txn, events| {
+                patches.lock().unwrap().push(patch_from_events(txn, events));
+            })
+        };
+        {
+            let mut txn = doc.transact_mut();
+            json_remove(&mut txn, &root, "/list/0").unwrap();
+            json_add(&mut txn, &root, "/list/1", json(r#""x""#)).unwrap();
+            json_add(&mut txn, &root, "/a~b", json("true")).unwrap();
+            note.insert(&mut txn, 2, "!");
+        }
+
+        let patches = patches.lock().unwrap();
+        let entry = |op, path: &str, value: Option<&str>| PatchEntry {
+            op,
+            path: path.to_string(),
+            value: value.map(json),
+        };
+        assert_eq!(
+            patches[0],
+            vec![
+                entry(PatchOp::Add, "/a~0b", Some("true")),
+                entry(PatchOp::Remove, "/list/0", None),
+                entry(PatchOp::Add, "/list/1", Some(r#""x""#)),
+                entry(PatchOp::Replace, "/note", Some(r#""hi!""#)),
+            ]
+        );
+    }
+}
@@ -1,15 +1,19 @@
 use crate::{
+    check_not_held_or_throw, check_writable_or_throw, find_callback_class, forget_root,
     free_if_valid, from_java_ptr, get_mut_or_throw, get_ref_or_throw, get_string_or_throw,
-    out_to_jobject, throw_exception, to_java_ptr, to_jstring, DocPtr, DocWrapper, JniEnvExt,
-    MapPtr, TxnPtr,
+    is_silent, jobject_to_any, new_java_event, out_to_jobject, root_to_java_ptr, strings_to_java,
+    throw_exception, to_java_ptr, to_json_string, to_jstring, trace_native, type_path_or_null,
+    write_to_direct_buffer, AnyConversionError, DocPtr, DocWrapper, JniEnvExt, JniError, JniResult,
+    JniResultExt, MapPtr, TxnPtr,
 };
-use jni::objects::{JClass, JObject, JString, JValue};
-use jni::sys::{jdouble, jlong, jstring};
-use jni::{Executor, JNIEnv};
-use std::sync::Arc;
+use jni::objects::{JByteBuffer, JClass, JObject, JObjectArray, JString, JValue};
+use jni::sys::{
+    jboolean, jdouble, jint, jlong, jobject, jobjectArray, jstring, JNI_FALSE, JNI_TRUE,
+};
+use jni::JNIEnv;
 use yrs::types::map::MapEvent;
 use yrs::types::{EntryChange, ToJson};
-use yrs::{Doc, Map, MapRef, Observable, TransactionMut};
+use yrs::{Any, Doc, Map, MapRef, Observable, Out, ReadTxn, TransactionMut, WeakRef};
 
 /// Gets or creates a YMap instance from a YDoc
 ///
@@ -26,11 +30,16 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetMap(
     doc_ptr: jlong,
     name: JString,
 ) -> jlong {
+    trace_native!("JniYMap.nativeGetMap", doc_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
-    let name_str = get_string_or_throw!(&mut env, name, 0);
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
 
-    let map = wrapper.doc.get_or_insert_map(name_str.as_str());
-    to_java_ptr(map)
+    root_to_java_ptr(doc_ptr, wrapper, &name_str, || {
+        wrapper.doc.get_or_insert_map(name_str.as_str())
+    })
 }
 
 /// Destroys a YMap instance and frees its memory
@@ -46,7 +55,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeDestroy(
     _class: JClass,
     ptr: jlong,
 ) {
-    free_if_valid!(MapPtr::from_raw(ptr), MapRef);
+    trace_native!("JniYMap.nativeDestroy", ptr);
+    if forget_root(ptr) {
+        free_if_valid!(MapPtr::from_raw(ptr), MapRef);
+    }
 }
 
 /// Gets the size of the map (number of entries) with transaction
@@ -66,11 +78,47 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeSizeWithTxn(
     map_ptr: jlong,
     txn_ptr: jlong,
 ) -> jlong {
+    trace_native!("JniYMap.nativeSizeWithTxn", doc_ptr, map_ptr, txn_ptr);
     let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", 0);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
-    map.len(txn) as jlong
+    jlong::from(map.len(txn))
+}
+
+/// Finds the root the YMap belongs to and the path leading to it
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// An `Object[]` holding the root name followed by a `String` key or `Integer`
+/// index per level, or null if the YMap was deleted or cannot be reached
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetPathWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobjectArray {
+    trace_native!("JniYMap.nativeGetPathWithTxn", _doc_ptr, map_ptr, txn_ptr);
+    let map = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    type_path_or_null(&mut env, txn, map.as_ref())
 }
 
 /// Gets a string value from the map by key with transaction
@@ -92,7 +140,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetStringWithTxn(
     txn_ptr: jlong,
     key: JString,
 ) -> jstring {
-    let _wrapper = get_ref_or_throw!(
+    trace_native!("JniYMap.nativeGetStringWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
         "YDoc",
@@ -110,7 +159,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetStringWithTxn(
         "YTransaction",
         std::ptr::null_mut()
     );
-    let key_str = get_string_or_throw!(&mut env, key, std::ptr::null_mut());
+    let key_str = get_string_or_throw!(&mut env, wrapper, key, std::ptr::null_mut());
 
     match map.get(txn, &key_str) {
         Some(value) => {
@@ -140,10 +189,11 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetDoubleWithTxn(
     txn_ptr: jlong,
     key: JString,
 ) -> jdouble {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0.0);
+    trace_native!("JniYMap.nativeGetDoubleWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0.0);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", 0.0);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0.0);
-    let key_str = get_string_or_throw!(&mut env, key, 0.0);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key, 0.0);
 
     match map.get(txn, &key_str) {
         Some(value) => value.cast::<f64>().unwrap_or(0.0),
@@ -169,11 +219,13 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetStringWithTxn(
     key: JString,
     value: JString,
 ) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYMap.nativeSetStringWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let key_str = get_string_or_throw!(&mut env, key);
-    let value_str = get_string_or_throw!(&mut env, value);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key);
+    let value_str = get_string_or_throw!(&mut env, wrapper, value);
 
     map.insert(txn, key_str, value_str);
 }
@@ -196,10 +248,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetDoubleWithTxn(
     key: JString,
     value: jdouble,
 ) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYMap.nativeSetDoubleWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let key_str = get_string_or_throw!(&mut env, key);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key);
 
     map.insert(txn, key_str, value);
 }
@@ -220,10 +274,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeRemoveWithTxn(
     txn_ptr: jlong,
     key: JString,
 ) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYMap.nativeRemoveWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let key_str = get_string_or_throw!(&mut env, key);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key);
 
     map.remove(txn, &key_str);
 }
@@ -247,14 +303,181 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeContainsKeyWithTx
     txn_ptr: jlong,
     key: JString,
 ) -> bool {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", false);
+    trace_native!(
+        "JniYMap.nativeContainsKeyWithTxn",
+        doc_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", false);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", false);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", false);
-    let key_str = get_string_or_throw!(&mut env, key, false);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key, false);
 
     map.contains_key(txn, &key_str)
 }
 
+/// Value type tags returned by `nativeGetValueTypeWithTxn`, matching the
+/// declaration order of `JniYMap.ValueType`
+pub const VALUE_TYPE_ABSENT: jint = 0;
+pub const VALUE_TYPE_NULL: jint = 1;
+pub const VALUE_TYPE_STRING: jint = 2;
+pub const VALUE_TYPE_NUMBER: jint = 3;
+pub const VALUE_TYPE_BOOL: jint = 4;
+pub const VALUE_TYPE_BYTES: jint = 5;
+pub const VALUE_TYPE_MAP: jint = 6;
+pub const VALUE_TYPE_ARRAY: jint = 7;
+pub const VALUE_TYPE_TEXT: jint = 8;
+pub const VALUE_TYPE_DOC: jint = 9;
+pub const VALUE_TYPE_XML: jint = 10;
+pub const VALUE_TYPE_UNDEFINED: jint = 11;
+pub const VALUE_TYPE_LINK: jint = 12;
+
+/// Classifies a map value into one of the `VALUE_TYPE_*` tags
+pub fn value_type_tag(value: Option<&Out>) -> jint {
+    match value {
+        None => VALUE_TYPE_ABSENT,
+        Some(Out::Any(any)) => match any {
+            Any::Null => VALUE_TYPE_NULL,
+            Any::Undefined => VALUE_TYPE_UNDEFINED,
+            Any::Bool(_) => VALUE_TYPE_BOOL,
+            Any::Number(_) | Any::BigInt(_) => VALUE_TYPE_NUMBER,
+            Any::String(_) => VALUE_TYPE_STRING,
+            Any::Buffer(_) => VALUE_TYPE_BYTES,
+            Any::Array(_) => VALUE_TYPE_ARRAY,
+            Any::Map(_) => VALUE_TYPE_MAP,
+        },
+        Some(Out::YText(_)) => VALUE_TYPE_TEXT,
+        Some(Out::YArray(_)) => VALUE_TYPE_ARRAY,
+        Some(Out::YMap(_)) => VALUE_TYPE_MAP,
+        Some(Out::YXmlElement(_)) | Some(Out::YXmlFragment(_)) | Some(Out::YXmlText(_)) => {
+            VALUE_TYPE_XML
+        }
+        Some(Out::YDoc(_)) => VALUE_TYPE_DOC,
+        Some(Out::YWeakLink(_)) => VALUE_TYPE_LINK,
+        // A shared type whose kind is not known yet carries no readable value
+        Some(Out::UndefinedRef(_)) => VALUE_TYPE_NULL,
+    }
+}
+
+/// Reports whether a key is present and what kind of value it holds, with transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `key`: The key to look up
+///
+/// # Returns
+/// The ordinal of the matching `JniYMap.ValueType` constant
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetValueTypeWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    key: JString,
+) -> jint {
+    trace_native!(
+        "JniYMap.nativeGetValueTypeWithTxn",
+        doc_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        VALUE_TYPE_ABSENT
+    );
+    let map = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        VALUE_TYPE_ABSENT
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        VALUE_TYPE_ABSENT
+    );
+    let key_str = get_string_or_throw!(&mut env, wrapper, key, VALUE_TYPE_ABSENT);
+
+    value_type_tag(map.get(txn, &key_str).as_ref())
+}
+
+/// Gets the values of several keys in one call with transaction
+///
+/// Values are converted as by `out_to_jobject`: plain values become Java
+/// values, lists and maps, and shared types become `JniTreeNode`s.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `keys`: Java String[] of keys to look up
+///
+/// # Returns
+/// A Java Object[] holding the value of each key at the same index, or null
+/// where the key is not in the map
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetAllWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    keys: JObjectArray,
+) -> jobjectArray {
+    trace_native!("JniYMap.nativeGetAllWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let map = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    get_all(&mut env, wrapper, map, txn, &keys)
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+fn get_all<'local>(
+    env: &mut JNIEnv<'local>,
+    wrapper: &DocWrapper,
+    map: &MapRef,
+    txn: &TransactionMut,
+    keys: &JObjectArray,
+) -> JniResult<JObjectArray<'local>> {
+    let len = env.get_array_length(keys)?;
+    let result = env.new_object_array(len, "java/lang/Object", JObject::null())?;
+    for i in 0..len {
+        let key = JString::from(env.get_object_array_element(keys, i)?);
+        let key_str = env.get_rust_string(&key, wrapper.string_policy())?;
+        env.delete_local_ref(key)?;
+        if let Some(value) = map.get(txn, &key_str) {
+            let value = out_to_jobject(env, txn, &value)?;
+            env.set_object_array_element(&result, i, &value)?;
+            env.delete_local_ref(value)?;
+        }
+    }
+    Ok(result)
+}
+
 /// Gets all keys from the map as a Java array with transaction
 ///
 /// # Parameters
@@ -272,7 +495,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeKeysWithTxn<'a>(
     map_ptr: jlong,
     txn_ptr: jlong,
 ) -> JObject<'a> {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
+    trace_native!("JniYMap.nativeKeysWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", JObject::null());
     let txn = get_mut_or_throw!(
         &mut env,
@@ -282,7 +506,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeKeysWithTxn<'a>(
     );
 
     // Collect all keys
-    let keys: Vec<String> = map.keys(txn).map(|k| k.to_string()).collect();
+    let mut keys: Vec<String> = map.keys(txn).map(|k| k.to_string()).collect();
+    if wrapper.sorts_keys() {
+        keys.sort();
+    }
 
     // Create Java String array
     let string_class = match env.find_class("java/lang/String") {
@@ -322,6 +549,221 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeKeysWithTxn<'a>(
     JObject::from(array)
 }
 
+/// Returns one page of the keys of `map` that start with `prefix`
+///
+/// Matching keys are sorted so that pages are stable between calls; only the
+/// page itself is returned.
+pub fn keys_with_prefix<'a, T: ReadTxn>(
+    map: &'a MapRef,
+    txn: &'a T,
+    prefix: &str,
+    limit: usize,
+    offset: usize,
+) -> Vec<&'a str> {
+    let mut keys: Vec<&str> = map.keys(txn).filter(|k| k.starts_with(prefix)).collect();
+    if offset >= keys.len() {
+        return Vec::new();
+    }
+    let end = offset.saturating_add(limit).min(keys.len());
+    if end < keys.len() {
+        // Only the keys up to the end of the page need to be in order
+        keys.select_nth_unstable(end);
+        keys.truncate(end);
+    }
+    keys.sort_unstable();
+    keys.drain(..offset);
+    keys
+}
+
+/// Gets one page of the keys that start with a prefix with transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `prefix`: The prefix keys must start with; empty matches every key
+/// - `limit`: Maximum number of keys to return
+/// - `offset`: Number of matching keys to skip, in sorted order
+///
+/// # Returns
+/// A Java String[] with at most `limit` keys, sorted by code point
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeKeysWithPrefixWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    prefix: JString,
+    limit: jint,
+    offset: jint,
+) -> jobjectArray {
+    trace_native!(
+        "JniYMap.nativeKeysWithPrefixWithTxn",
+        doc_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let map = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let prefix = get_string_or_throw!(&mut env, wrapper, prefix, std::ptr::null_mut());
+
+    let keys = keys_with_prefix(
+        map,
+        txn,
+        &prefix,
+        limit.max(0) as usize,
+        offset.max(0) as usize,
+    );
+    strings_to_java(&mut env, keys.into_iter())
+        .map(JObjectArray::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Whether the value stored under a key equals `expected`
+///
+/// `Any::Null` matches a missing key as well as a stored null. Numbers compare
+/// by value, so an integer matches a double with the same value. Shared types
+/// never match.
+fn value_matches(current: Option<&Out>, expected: &Any) -> bool {
+    match (current, expected) {
+        (None, Any::Null) => true,
+        (Some(Out::Any(Any::Number(a))), Any::BigInt(b))
+        | (Some(Out::Any(Any::BigInt(b))), Any::Number(a)) => *a == *b as f64,
+        (Some(Out::Any(current)), expected) => current == expected,
+        _ => false,
+    }
+}
+
+/// Sets `key` to `new_value`, or removes it if `new_value` is `None`, if its
+/// current value matches `expected`
+///
+/// # Returns
+/// Whether the value matched and was replaced
+pub fn compare_and_set(
+    map: &MapRef,
+    txn: &mut TransactionMut,
+    key: &str,
+    expected: &Any,
+    new_value: Option<Any>,
+) -> bool {
+    if !value_matches(map.get(txn, key).as_ref(), expected) {
+        return false;
+    }
+    match new_value {
+        Some(value) => {
+            map.insert(txn, key, value);
+        }
+        None => {
+            map.remove(txn, key);
+        }
+    }
+    true
+}
+
+/// Converts a map value passed from Java, throwing `IllegalArgumentException`
+/// if its type is not supported
+fn map_value_or_throw(env: &mut JNIEnv, value: &JObject, wrapper: &DocWrapper) -> Option<Any> {
+    match jobject_to_any(env, value, wrapper.string_policy()) {
+        Ok(value) => Some(value),
+        Err(AnyConversionError::Unsupported(class_name)) => {
+            let msg = format!(
+                "Unsupported map value type: {}. Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, or null.",
+                class_name
+            );
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+            None
+        }
+        Err(AnyConversionError::InvalidString(msg)) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+            None
+        }
+        Err(AnyConversionError::Jni(e)) => {
+            throw_exception(env, &format!("JNI error: {:?}", e));
+            None
+        }
+    }
+}
+
+/// Replaces the value of a key if it equals an expected value, with transaction
+///
+/// The comparison and the write happen under the same transaction, so no other
+/// writer can change the key in between.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `key`: The key to update
+/// - `expected`: The value the key must hold; null matches a missing key
+/// - `new_value`: The value to store; null removes the key
+///
+/// # Returns
+/// `true` if the value matched and was replaced
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeCompareAndSetWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    key: JString,
+    expected: JObject,
+    new_value: JObject,
+) -> jboolean {
+    trace_native!(
+        "JniYMap.nativeCompareAndSetWithTxn",
+        doc_ptr,
+        map_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JNI_FALSE);
+    check_writable_or_throw!(&mut env, wrapper, JNI_FALSE);
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", JNI_FALSE);
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        JNI_FALSE
+    );
+    let key = get_string_or_throw!(&mut env, wrapper, key, JNI_FALSE);
+    let Some(expected) = map_value_or_throw(&mut env, &expected, wrapper) else {
+        return JNI_FALSE;
+    };
+    let new_value = if new_value.is_null() {
+        None
+    } else {
+        match map_value_or_throw(&mut env, &new_value, wrapper) {
+            Some(value) => Some(value),
+            None => return JNI_FALSE,
+        }
+    };
+
+    if compare_and_set(map, txn, &key, &expected, new_value) {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
 /// Clears all entries from the map with transaction
 ///
 /// # Parameters
@@ -336,7 +778,9 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeClearWithTxn(
     map_ptr: jlong,
     txn_ptr: jlong,
 ) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYMap.nativeClearWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
@@ -360,7 +804,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeToJsonWithTxn(
     map_ptr: jlong,
     txn_ptr: jlong,
 ) -> jstring {
-    let _wrapper = get_ref_or_throw!(
+    trace_native!("JniYMap.nativeToJsonWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
         "YDoc",
@@ -379,10 +824,42 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeToJsonWithTxn(
         std::ptr::null_mut()
     );
 
-    let json = map.to_json(txn).to_string();
+    let json = to_json_string(&map.to_json(txn), wrapper.sorts_keys());
     to_jstring(&mut env, &json)
 }
 
+/// Writes the JSON form of the map as UTF-8 into a direct ByteBuffer using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `buffer`: A direct `java.nio.ByteBuffer`
+/// - `offset`: Position in the buffer at which to write
+/// - `length`: Number of bytes available at `offset`
+///
+/// # Returns
+/// The size of the JSON form of the map in bytes; nothing is written if it exceeds `length`
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeWriteJsonWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    buffer: JByteBuffer,
+    offset: jint,
+    length: jint,
+) -> jint {
+    trace_native!("JniYMap.nativeWriteJsonWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let content = to_json_string(&map.to_json(txn), wrapper.sorts_keys());
+    write_to_direct_buffer(&mut env, &buffer, offset, length, &content)
+}
+
 /// Sets a YDoc subdocument value in the map with transaction
 ///
 /// # Parameters
@@ -401,11 +878,19 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetDocWithTxn(
     key: JString,
     subdoc_ptr: jlong,
 ) {
-    let _wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYMap.nativeSetDocWithTxn",
+        doc_ptr,
+        map_ptr,
+        txn_ptr,
+        subdoc_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
     let subdoc_wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(subdoc_ptr), "subdocument");
-    let key_str = get_string_or_throw!(&mut env, key);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key);
 
     // Clone the inner doc for insertion (Doc implements Prelim)
     let subdoc_clone = subdoc_wrapper.doc.clone();
@@ -432,39 +917,123 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetDocWithTxn(
     txn_ptr: jlong,
     key: JString,
 ) -> jlong {
-    if doc_ptr == 0 {
-        throw_exception(&mut env, "Invalid YDoc pointer");
-        return 0;
-    }
-    if map_ptr == 0 {
-        throw_exception(&mut env, "Invalid YMap pointer");
-        return 0;
-    }
-    if txn_ptr == 0 {
-        throw_exception(&mut env, "Invalid transaction pointer");
-        return 0;
+    trace_native!("JniYMap.nativeGetDocWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+    let key_str = get_string_or_throw!(&mut env, wrapper, key, 0);
+
+    match map.get(txn, &key_str) {
+        // Wrap in DocWrapper so nativeDestroy can properly free it
+        Some(value) => match value.cast::<Doc>() {
+            Ok(subdoc) => to_java_ptr(DocWrapper::from_doc(subdoc.clone())),
+            Err(_) => 0,
+        },
+        None => 0,
     }
-    let key_str = get_string_or_throw!(&mut env, key, 0);
-
-    unsafe {
-        let map = from_java_ptr::<MapRef>(map_ptr);
-        match crate::get_transaction_mut(txn_ptr) {
-            Some(txn) => match map.get(txn, &key_str) {
-                Some(value) => {
-                    // Try to cast to Doc
-                    match value.cast::<Doc>() {
-                        // Wrap in DocWrapper so nativeDestroy can properly free it
-                        Ok(subdoc) => to_java_ptr(DocWrapper::from_doc(subdoc.clone())),
-                        Err(_) => 0,
-                    }
-                }
-                None => 0,
-            },
-            None => {
-                throw_exception(&mut env, "Transaction not found");
-                0
-            }
+}
+
+/// Stores a weak link to an entry of a map under a key, with transaction
+///
+/// The link refers to whatever is stored under `source_key` in the source map,
+/// including values written there later, and to nothing while it is removed.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance the link is stored in
+/// - `txn_ptr`: Pointer to the transaction
+/// - `key`: The key to store the link under
+/// - `source_ptr`: Pointer to the YMap holding the linked entry
+/// - `source_key`: The key of the linked entry
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeSetLinkWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    key: JString,
+    source_ptr: jlong,
+    source_key: JString,
+) {
+    trace_native!(
+        "JniYMap.nativeSetLinkWithTxn",
+        doc_ptr,
+        map_ptr,
+        txn_ptr,
+        source_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let source = get_ref_or_throw!(&mut env, MapPtr::from_raw(source_ptr), "source YMap");
+    let key_str = get_string_or_throw!(&mut env, wrapper, key);
+    let source_key_str = get_string_or_throw!(&mut env, wrapper, source_key);
+
+    match source.link(txn, &source_key_str) {
+        Some(link) => {
+            map.insert(txn, key_str, link);
         }
+        None => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Source map has no entry for key '{}'", source_key_str),
+            );
+        }
+    }
+}
+
+/// Reads the value a weak link stored in the map refers to, with transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `map_ptr`: Pointer to the YMap instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `key`: The key the link is stored under
+///
+/// # Returns
+/// The linked value converted as by `out_to_jobject`, or null if the key does
+/// not hold a link or the linked entry has been removed
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetLinkedWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    map_ptr: jlong,
+    txn_ptr: jlong,
+    key: JString,
+) -> jobject {
+    trace_native!("JniYMap.nativeGetLinkedWithTxn", doc_ptr, map_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let map = get_ref_or_throw!(
+        &mut env,
+        MapPtr::from_raw(map_ptr),
+        "YMap",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let key_str = get_string_or_throw!(&mut env, wrapper, key, std::ptr::null_mut());
+
+    let Some(Out::YWeakLink(link)) = map.get(txn, &key_str) else {
+        return std::ptr::null_mut();
+    };
+    match WeakRef::<MapRef>::from(link).try_deref_value(txn) {
+        Some(value) => out_to_jobject(&mut env, txn, &value)
+            .map(JObject::into_raw)
+            .map_err(JniError::from)
+            .unwrap_or_throw(&mut env),
+        None => std::ptr::null_mut(),
     }
 }
 
@@ -475,6 +1044,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeGetDocWithTxn(
 /// - `map_ptr`: Pointer to the YMap instance
 /// - `subscription_id`: The subscription ID from Java
 /// - `ymap_obj`: The Java YMap object for callbacks
+/// - `keys_only`: Whether events carry only the changed keys, without their
+///   old and new values
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeObserve(
     mut env: JNIEnv,
@@ -483,19 +1054,15 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeObserve(
     map_ptr: jlong,
     subscription_id: jlong,
     ymap_obj: JObject,
+    keys_only: jboolean,
 ) {
-    if doc_ptr == 0 {
-        throw_exception(&mut env, "Invalid YDoc pointer");
-        return;
-    }
-    if map_ptr == 0 {
-        throw_exception(&mut env, "Invalid YMap pointer");
-        return;
-    }
+    trace_native!("JniYMap.nativeObserve", doc_ptr, map_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let map = get_ref_or_throw!(&mut env, MapPtr::from_raw(map_ptr), "YMap");
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
         Err(e) => {
             throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
             return;
@@ -511,20 +1078,24 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeObserve(
         }
     };
 
-    unsafe {
-        let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
-        let map = from_java_ptr::<MapRef>(map_ptr);
+    let gate = wrapper.dispatch_gate();
+    let with_values = keys_only == JNI_FALSE;
 
-        // Create observer closure
-        let subscription = map.observe(move |txn, event| {
-            // Use Executor for thread attachment with automatic local frame management
-            let _ = executor
-                .with_attached(|env| dispatch_map_event(env, doc_ptr, subscription_id, txn, event));
+    // Create observer closure
+    let subscription = map.observe(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
+        // Use Executor for thread attachment with automatic local frame management
+        let _ = executor.with_attached(|env| {
+            dispatch_map_changes(env, doc_ptr, subscription_id, txn, event, with_values)
         });
+    });
 
-        // Store subscription and GlobalRef in the DocWrapper
-        wrapper.add_subscription(subscription_id, subscription, global_ref);
-    }
+    // Store subscription and GlobalRef in the DocWrapper. The subscription is
+    // dropped on unobserve or when the document is destroyed.
+    wrapper.add_subscription(subscription_id, map_ptr, subscription, global_ref);
 }
 
 /// Unregisters an observer for the YMap
@@ -541,27 +1112,38 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYMap_nativeUnobserve(
     _map_ptr: jlong,
     subscription_id: jlong,
 ) {
-    if doc_ptr == 0 {
-        throw_exception(&mut env, "Invalid YDoc pointer");
-        return;
-    }
+    trace_native!("JniYMap.nativeUnobserve", doc_ptr, _map_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
 
-    unsafe {
-        let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
-        // Remove subscription and GlobalRef from DocWrapper
-        // Both the Subscription and GlobalRef are dropped here
-        wrapper.remove_subscription(subscription_id);
-    }
+    // Remove subscription and GlobalRef from DocWrapper
+    // Both the Subscription and GlobalRef are dropped here
+    wrapper.remove_subscription(subscription_id);
 }
 
 /// Helper function to dispatch a map event to Java
-fn dispatch_map_event(
+pub(crate) fn dispatch_map_event(
     env: &mut JNIEnv,
     doc_ptr: jlong,
     subscription_id: jlong,
     txn: &TransactionMut,
     event: &MapEvent,
 ) -> Result<(), jni::errors::Error> {
+    dispatch_map_changes(env, doc_ptr, subscription_id, txn, event, true)
+}
+
+/// Dispatches a map event to Java, converting the old and new values of each
+/// changed key only if `with_values` is set
+fn dispatch_map_changes(
+    env: &mut JNIEnv,
+    doc_ptr: jlong,
+    subscription_id: jlong,
+    txn: &TransactionMut,
+    event: &MapEvent,
+    with_values: bool,
+) -> Result<(), jni::errors::Error> {
+    if is_silent(txn) {
+        return Ok(());
+    }
     // Get the Java YMap object from DocWrapper
     let ymap_ref = unsafe {
         let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
@@ -583,77 +1165,41 @@ fn dispatch_map_event(
     let changes_list = env.new_object("java/util/ArrayList", "()V", &[])?;
 
     // Convert each EntryChange to a YMapChange
+    let change_class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYMapChange")?;
     for (key, change) in keys {
-        let key_str = key.to_string();
-        let change_obj = match change {
-            EntryChange::Inserted(new_value) => {
-                // Create YMapChange for INSERT
-                let new_value_obj = out_to_jobject(env, new_value)?;
-
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYMapChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let insert_type =
-                    env.get_static_field(type_class, "INSERT", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-                let key_jstr = env.new_string(&key_str)?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/Object;Ljava/lang/Object;)V",
-                    &[
-                        JValue::Object(&insert_type.l()?),
-                        JValue::Object(&key_jstr),
-                        JValue::Object(&new_value_obj),
-                        JValue::Object(&JObject::null()),
-                    ],
-                )?
-            }
+        let (type_name, new_value, old_value) = match change {
+            EntryChange::Inserted(new_value) => ("INSERT", Some(new_value), None),
             EntryChange::Updated(old_value, new_value) => {
-                // Create YMapChange for ATTRIBUTE (update)
-                let old_value_obj = out_to_jobject(env, old_value)?;
-                let new_value_obj = out_to_jobject(env, new_value)?;
-
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYMapChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let attribute_type = env.get_static_field(
-                    type_class,
-                    "ATTRIBUTE",
-                    "Lnet/carcdr/ycrdt/YChange$Type;",
-                )?;
-                let key_jstr = env.new_string(&key_str)?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/Object;Ljava/lang/Object;)V",
-                    &[
-                        JValue::Object(&attribute_type.l()?),
-                        JValue::Object(&key_jstr),
-                        JValue::Object(&new_value_obj),
-                        JValue::Object(&old_value_obj),
-                    ],
-                )?
-            }
-            EntryChange::Removed(old_value) => {
-                // Create YMapChange for DELETE
-                let old_value_obj = out_to_jobject(env, old_value)?;
-
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYMapChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let delete_type =
-                    env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-                let key_jstr = env.new_string(&key_str)?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/Object;Ljava/lang/Object;)V",
-                    &[
-                        JValue::Object(&delete_type.l()?),
-                        JValue::Object(&key_jstr),
-                        JValue::Object(&JObject::null()),
-                        JValue::Object(&old_value_obj),
-                    ],
-                )?
+                ("ATTRIBUTE", Some(new_value), Some(old_value))
             }
+            EntryChange::Removed(old_value) => ("DELETE", None, Some(old_value)),
+        };
+        // Keys-only observers get the type of each change without its values
+        let new_value_obj = match new_value {
+            Some(value) if with_values => out_to_jobject(env, txn, value)?,
+            _ => JObject::null(),
         };
+        let old_value_obj = match old_value {
+            Some(value) if with_values => out_to_jobject(env, txn, value)?,
+            _ => JObject::null(),
+        };
+
+        let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
+        let change_type = env
+            .get_static_field(type_class, type_name, "Lnet/carcdr/ycrdt/YChange$Type;")?
+            .l()?;
+        let key_jstr = env.new_string(key.as_ref())?;
+
+        let change_obj = env.new_object(
+            &change_class,
+            "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/Object;Ljava/lang/Object;)V",
+            &[
+                JValue::Object(&change_type),
+                JValue::Object(&key_jstr),
+                JValue::Object(&new_value_obj),
+                JValue::Object(&old_value_obj),
+            ],
+        )?;
 
         // Add to changes list
         env.call_method(
@@ -664,20 +1210,8 @@ fn dispatch_map_event(
         )?;
     }
 
-    // Create YEvent
-    let event_class = env.find_class("net/carcdr/ycrdt/jni/JniYEvent")?;
-    let target = ymap_obj; // Use the YMap object as the target
-    let origin_jstr = env.new_string("")?; // Empty origin for now
-
-    let event_obj = env.new_object(
-        event_class,
-        "(Ljava/lang/Object;Ljava/util/List;Ljava/lang/String;)V",
-        &[
-            JValue::Object(target),
-            JValue::Object(&changes_list),
-            JValue::Object(&origin_jstr),
-        ],
-    )?;
+    // Create YEvent carrying the transaction's origin
+    let event_obj = new_java_event(env, txn, ymap_obj, &changes_list)?;
 
     // Call YMap.dispatchEvent(subscriptionId, event)
     env.call_method(
@@ -708,6 +1242,94 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compare_and_set() {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("test");
+        let mut txn = doc.transact_mut();
+
+        let owner = || Some(Any::from("alice"));
+        assert!(compare_and_set(
+            &map,
+            &mut txn,
+            "owner",
+            &Any::Null,
+            owner()
+        ));
+        assert!(!compare_and_set(
+            &map,
+            &mut txn,
+            "owner",
+            &Any::Null,
+            Some(Any::from("bob"))
+        ));
+        assert!(!compare_and_set(
+            &map,
+            &mut txn,
+            "owner",
+            &Any::from("bob"),
+            None
+        ));
+        assert!(compare_and_set(
+            &map,
+            &mut txn,
+            "owner",
+            &Any::from("alice"),
+            None
+        ));
+        assert!(!map.contains_key(&txn, "owner"));
+
+        map.insert(&mut txn, "count", 2.0);
+        assert!(compare_and_set(
+            &map,
+            &mut txn,
+            "count",
+            &Any::BigInt(2),
+            Some(Any::BigInt(3))
+        ));
+        assert!(compare_and_set(
+            &map,
+            &mut txn,
+            "count",
+            &Any::Number(3.0),
+            Some(Any::Bool(true))
+        ));
+        assert!(!compare_and_set(
+            &map,
+            &mut txn,
+            "count",
+            &Any::BigInt(1),
+            None
+        ));
+        assert_eq!(map.get(&txn, "count"), Some(Out::Any(Any::Bool(true))));
+    }
+
+    #[test]
+    fn test_keys_with_prefix() {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("test");
+        {
+            let mut txn = doc.transact_mut();
+            for i in 0..20 {
+                map.insert(&mut txn, format!("user:{:02}", i), i as f64);
+            }
+            map.insert(&mut txn, "group:1", true);
+        }
+
+        let txn = doc.transact();
+        assert_eq!(
+            keys_with_prefix(&map, &txn, "user:", 3, 0),
+            vec!["user:00", "user:01", "user:02"]
+        );
+        assert_eq!(
+            keys_with_prefix(&map, &txn, "user:", 3, 18),
+            vec!["user:18", "user:19"]
+        );
+        assert!(keys_with_prefix(&map, &txn, "user:", 3, 20).is_empty());
+        assert_eq!(keys_with_prefix(&map, &txn, "", 1, 0), vec!["group:1"]);
+        assert_eq!(keys_with_prefix(&map, &txn, "", 100, 0).len(), 21);
+    }
+
     #[test]
     fn test_map_set_and_get() {
         let doc = Doc::new();
@@ -788,4 +1410,28 @@ mod tests {
         let retrieved_doc = retrieved.unwrap().cast::<Doc>();
         assert!(retrieved_doc.is_ok());
     }
+
+    #[test]
+    fn test_value_type_tag() {
+        let doc = Doc::new();
+        let map = doc.get_or_insert_map("test");
+        let mut txn = doc.transact_mut();
+        map.insert(&mut txn, "s", "text");
+        map.insert(&mut txn, "n", 1.5);
+        map.insert(&mut txn, "b", true);
+        map.insert(&mut txn, "null", Any::Null);
+        map.insert(&mut txn, "undefined", Any::Undefined);
+        map.insert(&mut txn, "bytes", Any::from(vec![1u8, 2]));
+        map.insert(&mut txn, "text", yrs::TextPrelim::new("t"));
+
+        let tag = |key: &str| value_type_tag(map.get(&txn, key).as_ref());
+        assert_eq!(tag("missing"), VALUE_TYPE_ABSENT);
+        assert_eq!(tag("s"), VALUE_TYPE_STRING);
+        assert_eq!(tag("n"), VALUE_TYPE_NUMBER);
+        assert_eq!(tag("b"), VALUE_TYPE_BOOL);
+        assert_eq!(tag("null"), VALUE_TYPE_NULL);
+        assert_eq!(tag("undefined"), VALUE_TYPE_UNDEFINED);
+        assert_eq!(tag("bytes"), VALUE_TYPE_BYTES);
+        assert_eq!(tag("text"), VALUE_TYPE_TEXT);
+    }
 }
@@ -1,92 +1,981 @@
 use crate::{
-    attrs_to_java_hashmap, free_if_valid, get_mut_or_throw, get_ref_or_throw, get_string_or_throw,
-    throw_exception, to_java_ptr, to_jstring, DocPtr, JniEnvExt, TextPtr, TxnPtr,
+    assoc_from_jint, check_not_held_or_throw, check_writable_or_throw, decode_sticky_index,
+    decode_utf16_units, delta_attributes, export_quill_delta, find_callback_class, forget_root,
+    free_if_valid, get_mut_or_throw, get_ref_or_throw, get_string_or_throw, grapheme_range,
+    is_silent, java_format_runs, java_map_to_attrs, jint_or_throw, long_array_to_java,
+    new_java_event, parse_quill_delta, resolve_in, root_to_java_ptr, span_utf16_lens,
+    stream_string_chunks, string_chunk_to_java, text_blocks, text_insert_change, text_span_change,
+    throw_exception, throw_jni_error, to_jstring, trace_native, type_path_or_null,
+    write_to_direct_buffer, DocPtr, DocWrapper, JniEnvExt, JniResult, JniResultExt, LineIndex,
+    TextBlock, TextPtr, TextShadow, TextStats, TxnPtr,
 };
-use jni::objects::{JClass, JObject, JString, JValue};
-use jni::sys::{jint, jlong, jstring};
-use jni::{Executor, JNIEnv};
+use jni::objects::{
+    JByteArray, JByteBuffer, JCharArray, JClass, JIntArray, JObject, JObjectArray, JString, JValue,
+};
+use jni::sys::{jbyteArray, jint, jintArray, jlong, jlongArray, jobject, jobjectArray, jstring};
+use jni::JNIEnv;
 use std::sync::Arc;
 use yrs::types::text::TextEvent;
-use yrs::{GetString, Observable, Text, TextRef, TransactionMut};
+use yrs::updates::encoder::Encode;
+use yrs::{Assoc, GetString, IndexedSequence, Observable, Text, TextRef, Transact, TransactionMut};
+
+/// Gets or creates a YText instance from a YDoc
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `name`: The name of the text object in the document
+///
+/// # Returns
+/// A pointer to the YText instance (as jlong)
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeGetText(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    name: JString,
+) -> jlong {
+    trace_native!("JniYText.nativeGetText", doc_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+
+    root_to_java_ptr(doc_ptr, wrapper, &name_str, || {
+        wrapper.doc.get_or_insert_text(name_str.as_str())
+    })
+}
+
+/// Destroys a YText instance and frees its memory
+///
+/// # Parameters
+/// - `ptr`: Pointer to the YText instance
+///
+/// # Safety
+/// The pointer must be valid and point to a YText instance
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeDestroy(
+    _env: JNIEnv,
+    _class: JClass,
+    ptr: jlong,
+) {
+    trace_native!("JniYText.nativeDestroy", ptr);
+    if forget_root(ptr) {
+        free_if_valid!(TextPtr::from_raw(ptr), TextRef);
+    }
+}
+
+/// Gets the length of the text with an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// The length of the text as jlong
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeLengthWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jlong {
+    trace_native!("JniYText.nativeLengthWithTxn", _doc_ptr, text_ptr, txn_ptr);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    jlong::from(text.len(txn))
+}
+
+/// Finds the root the YText belongs to and the path leading to it
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// An `Object[]` holding the root name followed by a `String` key or `Integer`
+/// index per level, or null if the YText was deleted or cannot be reached
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeGetPathWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobjectArray {
+    trace_native!("JniYText.nativeGetPathWithTxn", _doc_ptr, text_ptr, txn_ptr);
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    type_path_or_null(&mut env, txn, text.as_ref())
+}
+
+/// Gets the string content of the text using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A Java string containing the text content
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeToStringWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jstring {
+    trace_native!(
+        "JniYText.nativeToStringWithTxn",
+        _doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let content = text.get_string(txn);
+    to_jstring(&mut env, &content)
+}
+
+/// Writes the text content as UTF-8 into a direct ByteBuffer using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `buffer`: A direct `java.nio.ByteBuffer`
+/// - `offset`: Position in the buffer at which to write
+/// - `length`: Number of bytes available at `offset`
+///
+/// # Returns
+/// The size of the text content in bytes; nothing is written if it exceeds `length`
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeWriteStringWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    buffer: JByteBuffer,
+    offset: jint,
+    length: jint,
+) -> jint {
+    trace_native!(
+        "JniYText.nativeWriteStringWithTxn",
+        _doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let content = text.get_string(txn);
+    write_to_direct_buffer(&mut env, &buffer, offset, length, &content)
+}
+
+/// Returns a chunk of the text string content using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `offset`: UTF-8 byte offset of the chunk, which must be a character boundary
+/// - `max_bytes`: Maximum UTF-8 length of the chunk
+///
+/// # Returns
+/// A Java string with the chunk, empty at the end of the content
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeGetStringChunkWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    offset: jint,
+    max_bytes: jint,
+) -> jstring {
+    trace_native!(
+        "JniYText.nativeGetStringChunkWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let content = text.get_string(txn);
+    string_chunk_to_java(&mut env, &content, offset, max_bytes)
+}
+
+/// Passes the text string content to a Java consumer in chunks using an existing
+/// transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `max_bytes`: Maximum UTF-8 length of each chunk
+/// - `consumer`: A `java.util.function.Consumer<String>` receiving the chunks in order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeReadChunksWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    max_bytes: jint,
+    consumer: JObject,
+) {
+    trace_native!(
+        "JniYText.nativeReadChunksWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let content = text.get_string(txn);
+    stream_string_chunks(&mut env, &content, max_bytes, &consumer);
+}
+
+/// Returns the offsets at which the lines of the text start using an existing
+/// transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// A Java int array of line start offsets, starting with 0
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeLineStartsWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jintArray {
+    trace_native!(
+        "JniYText.nativeLineStartsWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let index = LineIndex::new(&text.get_string(txn), wrapper.doc.offset_kind());
+    let last = index.line_starts().last().copied().unwrap_or(0);
+    if jint_or_throw(&mut env, last, "Line start").is_none() {
+        return std::ptr::null_mut();
+    }
+    let starts: Vec<jint> = index.line_starts().iter().map(|&s| s as jint).collect();
+    let array = match env.new_int_array(starts.len() as jint) {
+        Ok(array) => array,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create int array");
+            return std::ptr::null_mut();
+        }
+    };
+    if env.set_int_array_region(&array, 0, &starts).is_err() {
+        throw_exception(&mut env, "Failed to fill line start array");
+        return std::ptr::null_mut();
+    }
+    array.into_raw()
+}
+
+/// Returns the number of lines of the text using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// The number of line breaks plus one
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeLineCountWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jint {
+    trace_native!(
+        "JniYText.nativeLineCountWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let breaks = text.get_string(txn).matches('\n').count() as u32;
+    jint_or_throw(&mut env, breaks + 1, "Line count").unwrap_or(0)
+}
+
+/// Counts the length, code points, words and lines of the text using an
+/// existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// `[length, codePoints, words, lines]`, with the length in the document's
+/// offset units
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeStatsWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jlongArray {
+    trace_native!("JniYText.nativeStatsWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let null = std::ptr::null_mut();
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", null);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", null);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", null);
+
+    let stats = TextStats::new(&text.get_string(txn), wrapper.doc.offset_kind());
+    long_array_to_java(&mut env, &stats.to_array()).unwrap_or_throw(&mut env)
+}
+
+/// Returns a range of fixed-size blocks of the text using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `block_size`: Length of a block in the document's offset units
+/// - `first_block`: Number of the first block to return
+/// - `max_blocks`: Maximum number of blocks to return
+///
+/// # Returns
+/// A Java `List<JniTextBlock>`, each with a sticky index on its first character
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeBlocksWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    block_size: jint,
+    first_block: jint,
+    max_blocks: jint,
+) -> jobject {
+    trace_native!("JniYText.nativeBlocksWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let blocks = text_blocks(
+        &text.get_string(txn),
+        wrapper.doc.offset_kind(),
+        block_size.max(1) as u32,
+        first_block.max(0) as u32,
+        max_blocks.max(0) as u32,
+    );
+    let end = blocks.last().map_or(0, |block| block.start + block.len);
+    if jint_or_throw(&mut env, end, "Block end").is_none() {
+        return std::ptr::null_mut();
+    }
+    blocks_to_java(&mut env, txn, text, &blocks)
+        .map(JObject::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
+/// Converts text blocks into a Java `List<JniTextBlock>`
+///
+/// Block positions must fit in a `jint`.
+fn blocks_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    txn: &TransactionMut,
+    text: &TextRef,
+    blocks: &[TextBlock],
+) -> JniResult<JObject<'local>> {
+    let list = env.new_object(
+        "java/util/ArrayList",
+        "(I)V",
+        &[JValue::Int(blocks.len() as jint)],
+    )?;
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniTextBlock")?;
+    for block in blocks {
+        let anchor = text
+            .sticky_index(txn, block.start, Assoc::After)
+            .map(|sticky| sticky.encode_v1())
+            .unwrap_or_default();
+        let anchor = env.byte_array_from_slice(&anchor)?;
+        let content = env.new_string(&block.content)?;
+        let item = env.new_object(
+            &class,
+            "(IILjava/lang/String;[B)V",
+            &[
+                JValue::Int(block.start as jint),
+                JValue::Int(block.len as jint),
+                JValue::Object(&content),
+                JValue::Object(&anchor),
+            ],
+        )?;
+        env.call_method(
+            &list,
+            "add",
+            "(Ljava/lang/Object;)Z",
+            &[JValue::Object(&item)],
+        )?;
+        env.delete_local_ref(item)?;
+        env.delete_local_ref(content)?;
+        env.delete_local_ref(anchor)?;
+    }
+    Ok(list)
+}
+
+/// Returns the line and column of an offset using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `offset`: The offset, from 0 to the length of the text
+///
+/// # Returns
+/// A Java int array holding the 0-based line and column
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativePositionWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    offset: jint,
+) -> jintArray {
+    trace_native!("JniYText.nativePositionWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let index = LineIndex::new(&text.get_string(txn), wrapper.doc.offset_kind());
+    let position = u32::try_from(offset)
+        .ok()
+        .and_then(|offset| index.position(offset));
+    let Some((line, column)) = position else {
+        let _ = env.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            format!(
+                "Offset {} out of bounds for length {}",
+                offset,
+                text.len(txn)
+            ),
+        );
+        return std::ptr::null_mut();
+    };
+    let array = match env.new_int_array(2) {
+        Ok(array) => array,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create int array");
+            return std::ptr::null_mut();
+        }
+    };
+    if env
+        .set_int_array_region(&array, 0, &[line as jint, column as jint])
+        .is_err()
+    {
+        throw_exception(&mut env, "Failed to fill position array");
+        return std::ptr::null_mut();
+    }
+    array.into_raw()
+}
+
+/// Returns the offset of a line and column using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `line`: The 0-based line
+/// - `column`: The 0-based column, at most the length of the line without its
+///   line break
+///
+/// # Returns
+/// The offset of the position in the text
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeOffsetWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    line: jint,
+    column: jint,
+) -> jint {
+    trace_native!("JniYText.nativeOffsetWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let index = LineIndex::new(&text.get_string(txn), wrapper.doc.offset_kind());
+    let offset = match (u32::try_from(line), u32::try_from(column)) {
+        (Ok(line), Ok(column)) => index.offset(line, column),
+        _ => None,
+    };
+    match offset {
+        Some(offset) => jint_or_throw(&mut env, offset, "Offset").unwrap_or(0),
+        None => {
+            let _ = env.throw_new(
+                "java/lang/IndexOutOfBoundsException",
+                format!(
+                    "Position {}:{} out of bounds for {} lines",
+                    line,
+                    column,
+                    index.line_count()
+                ),
+            );
+            0
+        }
+    }
+}
+
+/// Fills an empty text from a Quill document delta using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `delta`: The delta as JSON, either `{"ops":[...]}` or the bare operation array
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeImportDeltaWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    delta: JString,
+) {
+    trace_native!(
+        "JniYText.nativeImportDeltaWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let delta_str = get_string_or_throw!(&mut env, wrapper, delta);
+
+    if text.len(txn) != 0 {
+        let _ = env.throw_new(
+            "java/lang/IllegalStateException",
+            "A delta can only be imported into an empty YText",
+        );
+        return;
+    }
+    let deltas = match parse_quill_delta(&delta_str) {
+        Ok(deltas) => deltas,
+        Err(e) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", e);
+            return;
+        }
+    };
+    if !wrapper
+        .attribute_schema()
+        .check_or_throw(&mut env, &delta_attributes(&deltas))
+    {
+        return;
+    }
+    text.apply_delta(txn, deltas);
+}
+
+/// Writes the text as a Quill document delta using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+///
+/// # Returns
+/// The delta as JSON in the form `{"ops":[...]}`
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeExportDeltaWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jstring {
+    trace_native!(
+        "JniYText.nativeExportDeltaWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        TextPtr::from_raw(text_ptr),
+        "YText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
 
-/// Gets or creates a YText instance from a YDoc
+    let json = export_quill_delta(text, txn);
+    to_jstring(&mut env, &json)
+}
+
+/// Inserts text at the specified index using an existing transaction
 ///
 /// # Parameters
 /// - `doc_ptr`: Pointer to the YDoc instance
-/// - `name`: The name of the text object in the document
-///
-/// # Returns
-/// A pointer to the YText instance (as jlong)
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert the text
+/// - `chunk`: The text to insert
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeGetText(
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertWithTxn(
     mut env: JNIEnv,
     _class: JClass,
     doc_ptr: jlong,
-    name: JString,
-) -> jlong {
-    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
-    let name_str = get_string_or_throw!(&mut env, name, 0);
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    chunk: JString,
+) {
+    trace_native!("JniYText.nativeInsertWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let chunk_str = get_string_or_throw!(&mut env, wrapper, chunk);
 
-    let text = wrapper.doc.get_or_insert_text(name_str.as_str());
-    to_java_ptr(text)
+    text.insert(txn, index as u32, &chunk_str);
 }
 
-/// Destroys a YText instance and frees its memory
+/// Inserts a range of a `char[]` at the specified index using an existing transaction
 ///
 /// # Parameters
-/// - `ptr`: Pointer to the YText instance
-///
-/// # Safety
-/// The pointer must be valid and point to a YText instance
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert the text
+/// - `chars`: The array holding the UTF-16 code units to insert
+/// - `offset`: Index of the first code unit in `chars`
+/// - `length`: Number of code units to insert
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeDestroy(
-    _env: JNIEnv,
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertCharsWithTxn(
+    mut env: JNIEnv,
     _class: JClass,
-    ptr: jlong,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    chars: JCharArray,
+    offset: jint,
+    length: jint,
 ) {
-    free_if_valid!(TextPtr::from_raw(ptr), TextRef);
+    trace_native!(
+        "JniYText.nativeInsertCharsWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let mut units = vec![0u16; length.max(0) as usize];
+    // Throws ArrayIndexOutOfBoundsException if the range is outside the array
+    if env
+        .get_char_array_region(&chars, offset, &mut units)
+        .is_err()
+    {
+        return;
+    }
+    match decode_utf16_units(units, wrapper.string_policy()) {
+        Ok(chunk) => text.insert(txn, index as u32, &chunk),
+        Err(e) => throw_jni_error(&mut env, &e),
+    }
 }
 
-/// Gets the length of the text with an existing transaction
+/// Inserts a range of a direct `java.nio.CharBuffer` at the specified index
+/// using an existing transaction
+///
+/// The buffer must use the platform's native byte order.
 ///
 /// # Parameters
 /// - `doc_ptr`: Pointer to the YDoc instance
 /// - `text_ptr`: Pointer to the YText instance
-/// - `txn_ptr`: Pointer to the transaction instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The index at which to insert the text
+/// - `buffer`: A direct `CharBuffer` holding the UTF-16 code units to insert
+/// - `offset`: Index of the first code unit in the buffer
+/// - `length`: Number of code units to insert
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertDirectCharsWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    buffer: JObject,
+    offset: jint,
+    length: jint,
+) {
+    trace_native!(
+        "JniYText.nativeInsertDirectCharsWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    // The direct buffer functions accept any java.nio.Buffer; the address is
+    // that of the first element and the capacity is counted in chars
+    let buffer = JByteBuffer::from(buffer);
+    let region = env.get_direct_buffer_address(&buffer).and_then(|address| {
+        let capacity = env.get_direct_buffer_capacity(&buffer)?;
+        Ok((address, capacity))
+    });
+    let Ok((address, capacity)) = region else {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Buffer must be a direct CharBuffer",
+        );
+        return;
+    };
+    let (Ok(offset), Ok(length)) = (usize::try_from(offset), usize::try_from(length)) else {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            "Offset and length cannot be negative",
+        );
+        return;
+    };
+    if offset.checked_add(length).is_none_or(|end| end > capacity) {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            format!(
+                "Region of {} chars at {} exceeds buffer capacity {}",
+                length, offset, capacity
+            ),
+        );
+        return;
+    }
+    let start = address as *const u16;
+    // SAFETY: the region was checked against the buffer's capacity above. A
+    // view of a byte buffer need not be aligned to 2 bytes.
+    let units = (offset..offset + length).map(|i| unsafe { start.add(i).read_unaligned() });
+    match decode_utf16_units(units, wrapper.string_policy()) {
+        Ok(chunk) => text.insert(txn, index as u32, &chunk),
+        Err(e) => throw_jni_error(&mut env, &e),
+    }
+}
+
+/// Replaces a range of text using an existing transaction
 ///
-/// # Returns
-/// The length of the text as jint
+/// Deletes `length` characters at `index` and inserts `replacement` in their
+/// place, in one call.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The start of the range to replace
+/// - `length`: The length of the range to replace
+/// - `replacement`: The text to insert; may be empty
+/// - `attributes`: A Java Map<String, Object> of formatting attributes for the
+///   inserted text, or null to take the formatting at `index`
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeLengthWithTxn(
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeReplaceRangeWithTxn(
     mut env: JNIEnv,
     _class: JClass,
-    _doc_ptr: jlong,
+    doc_ptr: jlong,
     text_ptr: jlong,
     txn_ptr: jlong,
-) -> jint {
-    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", 0);
-    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+    index: jint,
+    length: jint,
+    replacement: JString,
+    attributes: JObject,
+) {
+    trace_native!(
+        "JniYText.nativeReplaceRangeWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let replacement = get_string_or_throw!(&mut env, wrapper, replacement);
+    // Check the attributes before deleting, so a rejected call changes nothing
+    let attrs = if attributes.is_null() {
+        None
+    } else {
+        match java_map_to_attrs(&mut env, &attributes, wrapper.string_policy()) {
+            Ok(attrs) => Some(attrs),
+            Err(e) => {
+                throw_jni_error(&mut env, &e);
+                return;
+            }
+        }
+    };
+    if let Some(attrs) = &attrs {
+        if !wrapper.attribute_schema().check_or_throw(&mut env, attrs) {
+            return;
+        }
+    }
 
-    text.len(txn) as jint
+    if length > 0 {
+        text.remove_range(txn, index as u32, length as u32);
+    }
+    if replacement.is_empty() {
+        return;
+    }
+    match attrs {
+        Some(attrs) => text.insert_with_attributes(txn, index as u32, &replacement, attrs),
+        None => text.insert(txn, index as u32, &replacement),
+    }
 }
 
-/// Gets the string content of the text using an existing transaction
+/// Creates a sticky index for a position in the text using an existing transaction
 ///
 /// # Parameters
 /// - `doc_ptr`: Pointer to the YDoc instance
 /// - `text_ptr`: Pointer to the YText instance
-/// - `txn_ptr`: Pointer to the transaction instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `index`: The position to track
+/// - `assoc`: 0 to stick to the character after the position, -1 for the one before
 ///
 /// # Returns
-/// A Java string containing the text content
+/// The v1-encoded sticky index, or null if the index is out of range
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeToStringWithTxn(
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeStickyIndexWithTxn(
     mut env: JNIEnv,
     _class: JClass,
     _doc_ptr: jlong,
     text_ptr: jlong,
     txn_ptr: jlong,
-) -> jstring {
+    index: jint,
+    assoc: jint,
+) -> jbyteArray {
+    trace_native!(
+        "JniYText.nativeStickyIndexWithTxn",
+        _doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
     let text = get_ref_or_throw!(
         &mut env,
         TextPtr::from_raw(text_ptr),
@@ -100,33 +989,111 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeToStringWithTxn(
         std::ptr::null_mut()
     );
 
-    let content = text.get_string(txn);
-    to_jstring(&mut env, &content)
+    match text.sticky_index(txn, index as u32, assoc_from_jint(assoc)) {
+        Some(sticky) => env
+            .byte_array_from_slice(&sticky.encode_v1())
+            .map(|arr| arr.into_raw())
+            .unwrap_or_else(|_| {
+                throw_exception(&mut env, "Failed to create byte array");
+                std::ptr::null_mut()
+            }),
+        None => std::ptr::null_mut(),
+    }
 }
 
-/// Inserts text at the specified index using an existing transaction
+/// Resolves a sticky index to its current position using an existing transaction
 ///
 /// # Parameters
 /// - `doc_ptr`: Pointer to the YDoc instance
 /// - `text_ptr`: Pointer to the YText instance
 /// - `txn_ptr`: Pointer to the transaction ID
-/// - `index`: The index at which to insert the text
-/// - `chunk`: The text to insert
+/// - `sticky`: The v1-encoded sticky index
+///
+/// # Returns
+/// The current index, or -1 if the sticky index does not resolve to this text
 #[no_mangle]
-pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertWithTxn(
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeResolveStickyIndexWithTxn(
     mut env: JNIEnv,
     _class: JClass,
     _doc_ptr: jlong,
     text_ptr: jlong,
     txn_ptr: jlong,
-    index: jint,
+    sticky: JByteArray,
+) -> jint {
+    trace_native!(
+        "JniYText.nativeResolveStickyIndexWithTxn",
+        _doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", -1);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
+    let Some(sticky) = decode_sticky_index(&mut env, &sticky) else {
+        return -1;
+    };
+
+    match resolve_in(txn, &sticky, text) {
+        Some(index) => jint_or_throw(&mut env, index, "Index").unwrap_or(-1),
+        None => -1,
+    }
+}
+
+/// Inserts text at the current position of a sticky index using an existing transaction
+///
+/// The sticky index is resolved inside the same transaction as the insert, so
+/// no concurrent change can move the position in between.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `sticky`: The v1-encoded sticky index
+/// - `chunk`: The text to insert
+///
+/// # Returns
+/// The index the text was inserted at. Throws `IllegalArgumentException` if the
+/// sticky index is malformed or does not resolve to this text.
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertAtStickyIndexWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    sticky: JByteArray,
     chunk: JString,
-) {
-    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
-    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let chunk_str = get_string_or_throw!(&mut env, chunk);
+) -> jint {
+    trace_native!(
+        "JniYText.nativeInsertAtStickyIndexWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", -1);
+    check_writable_or_throw!(&mut env, wrapper, -1);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", -1);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
+    let Some(sticky) = decode_sticky_index(&mut env, &sticky) else {
+        return -1;
+    };
+    let chunk_str = get_string_or_throw!(&mut env, wrapper, chunk, -1);
 
-    text.insert(txn, index as u32, &chunk_str);
+    match resolve_in(txn, &sticky, text) {
+        Some(index) => {
+            let Some(result) = jint_or_throw(&mut env, index, "Index") else {
+                return -1;
+            };
+            text.insert(txn, index, &chunk_str);
+            result
+        }
+        None => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                "Sticky index does not resolve to a position in this text",
+            );
+            -1
+        }
+    }
 }
 
 /// Appends text to the end using an existing transaction
@@ -140,18 +1107,100 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeInsertWithTxn(
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativePushWithTxn(
     mut env: JNIEnv,
     _class: JClass,
-    _doc_ptr: jlong,
+    doc_ptr: jlong,
     text_ptr: jlong,
     txn_ptr: jlong,
     chunk: JString,
 ) {
+    trace_native!("JniYText.nativePushWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let chunk_str = get_string_or_throw!(&mut env, chunk);
+    let chunk_str = get_string_or_throw!(&mut env, wrapper, chunk);
 
     text.push(txn, &chunk_str);
 }
 
+/// Applies bulk formatting runs to any text type.
+///
+/// All runs are decoded, bounds-checked and checked against the document's
+/// attribute allowlist before any is applied, so a bad run leaves the text
+/// unchanged. Throws `IllegalArgumentException` for malformed
+/// arguments and `IndexOutOfBoundsException` for runs past the end of the text.
+pub fn format_runs_or_throw<T: Text>(
+    env: &mut JNIEnv,
+    wrapper: &DocWrapper,
+    txn: &mut TransactionMut,
+    text: &T,
+    ranges: &JIntArray,
+    attributes: &JObjectArray,
+) {
+    let runs = match java_format_runs(env, ranges, attributes, wrapper.string_policy()) {
+        Ok(runs) => runs,
+        Err(e) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", e);
+            return;
+        }
+    };
+    let len = text.len(txn);
+    if let Some(run) = runs
+        .iter()
+        .find(|r| r.index as u64 + r.len as u64 > len as u64)
+    {
+        let _ = env.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            format!(
+                "Range ({}, {}) out of bounds for length {}",
+                run.index, run.len, len
+            ),
+        );
+        return;
+    }
+    let schema = wrapper.attribute_schema();
+    if !runs
+        .iter()
+        .all(|run| schema.check_or_throw(env, &run.attrs))
+    {
+        return;
+    }
+    for run in runs {
+        text.format(txn, run.index, run.len, run.attrs);
+    }
+}
+
+/// Applies several formatting runs using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction ID
+/// - `ranges`: `(index, length)` pairs, one per run
+/// - `attributes`: One `Map<String, Object>` per run; null values remove formatting
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeFormatRangesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    ranges: JIntArray,
+    attributes: JObjectArray,
+) {
+    trace_native!(
+        "JniYText.nativeFormatRangesWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    format_runs_or_throw(&mut env, wrapper, txn, text, &ranges, &attributes);
+}
+
 /// Deletes a range of text using an existing transaction
 ///
 /// # Parameters
@@ -164,23 +1213,82 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativePushWithTxn(
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeDeleteWithTxn(
     mut env: JNIEnv,
     _class: JClass,
-    _doc_ptr: jlong,
+    doc_ptr: jlong,
     text_ptr: jlong,
     txn_ptr: jlong,
     index: jint,
     length: jint,
 ) {
+    trace_native!("JniYText.nativeDeleteWithTxn", doc_ptr, text_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
     text.remove_range(txn, index as u32, length as u32);
 }
 
+/// Deletes whole grapheme clusters next to an index using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `index`: The index, from 0 to the length of the text
+/// - `count`: The number of clusters to delete from the one containing `index`
+///   onwards, or if negative, before `index`
+///
+/// # Returns
+/// The index at which the deleted range started
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeDeleteGraphemesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+    count: jint,
+) -> jint {
+    trace_native!(
+        "JniYText.nativeDeleteGraphemesWithTxn",
+        doc_ptr,
+        text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    check_writable_or_throw!(&mut env, wrapper, 0);
+    let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText", 0);
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let content = text.get_string(txn);
+    let range = u32::try_from(index)
+        .ok()
+        .and_then(|index| grapheme_range(&content, wrapper.doc.offset_kind(), index, count));
+    let Some((start, len)) = range else {
+        let _ = env.throw_new(
+            "java/lang/IndexOutOfBoundsException",
+            format!("Index {} out of bounds for length {}", index, text.len(txn)),
+        );
+        return 0;
+    };
+    if len > 0 {
+        text.remove_range(txn, start, len);
+    }
+    start as jint
+}
+
 /// Registers an observer for the YText
 ///
+/// Events carry the UTF-16 lengths of retained and deleted spans, measured
+/// against a copy of the text shared by all of its observers. If the text has
+/// no copy yet, it is read with the given transaction, or with a read-only
+/// transaction of its own if there is none.
+///
 /// # Parameters
 /// - `doc_ptr`: Pointer to the YDoc instance
 /// - `text_ptr`: Pointer to the YText instance
+/// - `txn_ptr`: Pointer to the caller's open transaction, or 0
 /// - `subscription_id`: The subscription ID from Java
 /// - `ytext_obj`: The Java YText object for callbacks
 #[no_mangle]
@@ -189,17 +1297,57 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeObserve(
     _class: JClass,
     doc_ptr: jlong,
     text_ptr: jlong,
+    txn_ptr: jlong,
     subscription_id: jlong,
     ytext_obj: JObject,
 ) {
+    trace_native!("JniYText.nativeObserve", doc_ptr, text_ptr, txn_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
     let text = get_ref_or_throw!(&mut env, TextPtr::from_raw(text_ptr), "YText");
+    let offset_kind = wrapper.doc.offset_kind();
+    let shadow = if txn_ptr == 0 {
+        if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+            return;
+        }
+        wrapper.text_shadows().share(text, offset_kind, || {
+            TextShadow::new(&wrapper.doc.transact(), text)
+        })
+    } else {
+        let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+        wrapper
+            .text_shadows()
+            .share(text, offset_kind, || TextShadow::in_transaction(txn, text))
+    };
+    observe_text(
+        &mut env,
+        wrapper,
+        text,
+        doc_ptr,
+        text_ptr,
+        subscription_id,
+        ytext_obj,
+        shadow,
+    );
+}
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+/// Subscribes a Java YText to the events of a text, measuring their spans
+/// against `shadow` if given
+#[allow(clippy::too_many_arguments)]
+fn observe_text(
+    env: &mut JNIEnv,
+    wrapper: &DocWrapper,
+    text: &TextRef,
+    doc_ptr: jlong,
+    text_ptr: jlong,
+    subscription_id: jlong,
+    ytext_obj: JObject,
+    shadow: Option<Arc<TextShadow>>,
+) {
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(env) {
+        Ok(executor) => executor,
         Err(e) => {
-            throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
+            throw_exception(env, &format!("Failed to get JavaVM: {:?}", e));
             return;
         }
     };
@@ -208,20 +1356,27 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeObserve(
     let global_ref = match env.new_global_ref(ytext_obj) {
         Ok(r) => r,
         Err(e) => {
-            throw_exception(&mut env, &format!("Failed to create global ref: {:?}", e));
+            throw_exception(env, &format!("Failed to create global ref: {:?}", e));
             return;
         }
     };
 
+    let gate = wrapper.dispatch_gate();
+
     // Create observer closure
     let subscription = text.observe(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
         // Use Executor for thread attachment with automatic local frame management
-        let _ = executor
-            .with_attached(|env| dispatch_text_event(env, doc_ptr, subscription_id, txn, event));
+        let _ = executor.with_attached(|env| {
+            dispatch_text_event(env, doc_ptr, subscription_id, txn, event, shadow.as_deref())
+        });
     });
 
     // Store subscription and GlobalRef in the DocWrapper
-    wrapper.add_subscription(subscription_id, subscription, global_ref);
+    wrapper.add_subscription(subscription_id, text_ptr, subscription, global_ref);
 }
 
 /// Unregisters an observer for the YText
@@ -238,6 +1393,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeUnobserve(
     _text_ptr: jlong,
     subscription_id: jlong,
 ) {
+    trace_native!("JniYText.nativeUnobserve", doc_ptr, _text_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
 
     // Remove subscription and GlobalRef from DocWrapper
@@ -246,13 +1402,21 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYText_nativeUnobserve(
 }
 
 /// Helper function to dispatch a text event to Java
-fn dispatch_text_event(
+pub(crate) fn dispatch_text_event(
     env: &mut JNIEnv,
     doc_ptr: jlong,
     subscription_id: jlong,
     txn: &TransactionMut,
     event: &TextEvent,
+    shadow: Option<&TextShadow>,
 ) -> Result<(), jni::errors::Error> {
+    if is_silent(txn) {
+        // Keep the copy current for the next event that is dispatched
+        if let Some(shadow) = shadow {
+            shadow.reset(txn, event.target());
+        }
+        return Ok(());
+    }
     // Get the Java YText object from DocWrapper
     let wrapper = match unsafe { DocPtr::from_raw(doc_ptr).as_ref() } {
         Some(w) => w,
@@ -278,64 +1442,17 @@ fn dispatch_text_event(
     let changes_list = env.new_object("java/util/ArrayList", "()V", &[])?;
 
     // Convert each delta to a YTextChange
-    for d in delta {
+    let utf16_lens = span_utf16_lens(shadow, txn, event.target(), delta);
+    for (d, utf16_len) in delta.iter().zip(utf16_lens) {
         let change_obj = match d {
-            yrs::types::Delta::Inserted(value, attrs) => {
-                // Convert value to string
-                let content = value.to_string();
-                let content_jstr = env.new_string(&content)?;
-
-                // Convert attributes to HashMap (or null)
-                let attrs_map = if let Some(attrs) = attrs {
-                    attrs_to_java_hashmap(env, attrs)?
-                } else {
-                    JObject::null()
-                };
-
-                // Create YTextChange for INSERT
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYTextChange")?;
-                env.new_object(
-                    change_class,
-                    "(Ljava/lang/String;Ljava/util/Map;)V",
-                    &[JValue::Object(&content_jstr), JValue::Object(&attrs_map)],
-                )?
-            }
-            yrs::types::Delta::Deleted(len) => {
-                // Create YTextChange for DELETE
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYTextChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let delete_type =
-                    env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;I)V",
-                    &[JValue::Object(&delete_type.l()?), JValue::Int(*len as i32)],
-                )?
-            }
-            yrs::types::Delta::Retain(len, attrs) => {
-                // Create YTextChange for RETAIN
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYTextChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let retain_type =
-                    env.get_static_field(type_class, "RETAIN", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-
-                let attrs_map = if let Some(attrs) = attrs {
-                    attrs_to_java_hashmap(env, attrs)?
-                } else {
-                    JObject::null()
-                };
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;ILjava/util/Map;)V",
-                    &[
-                        JValue::Object(&retain_type.l()?),
-                        JValue::Int(*len as i32),
-                        JValue::Object(&attrs_map),
-                    ],
-                )?
-            }
+            yrs::types::Delta::Inserted(value, attrs) => text_insert_change(
+                env,
+                txn,
+                value,
+                attrs.as_deref(),
+                wrapper.event_content_limit(),
+            )?,
+            span => text_span_change(env, span, utf16_len)?,
         };
 
         // Add to changes list
@@ -347,20 +1464,8 @@ fn dispatch_text_event(
         )?;
     }
 
-    // Create YEvent
-    let event_class = env.find_class("net/carcdr/ycrdt/jni/JniYEvent")?;
-    let target = ytext_obj; // Use the YText object as the target
-    let origin_jstr = env.new_string("")?; // Empty origin for now
-
-    let event_obj = env.new_object(
-        event_class,
-        "(Ljava/lang/Object;Ljava/util/List;Ljava/lang/String;)V",
-        &[
-            JValue::Object(target),
-            JValue::Object(&changes_list),
-            JValue::Object(&origin_jstr),
-        ],
-    )?;
+    // Create YEvent carrying the transaction's origin
+    let event_obj = new_java_event(env, txn, ytext_obj, &changes_list)?;
 
     // Call YText.dispatchEvent(subscriptionId, event)
     env.call_method(
@@ -376,8 +1481,8 @@ fn dispatch_text_event(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::free_java_ptr;
-    use yrs::{Doc, Transact};
+    use crate::{free_java_ptr, to_java_ptr};
+    use yrs::{Assoc, Doc, Transact};
 
     #[test]
     fn test_text_creation() {
@@ -445,4 +1550,24 @@ mod tests {
         assert_eq!(content, "Hello");
         assert_eq!(text.len(&txn), 5);
     }
+
+    #[test]
+    fn test_sticky_index_resolves_after_concurrent_insert() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("test");
+        let other = doc.get_or_insert_text("other");
+        let sticky = {
+            let mut txn = doc.transact_mut();
+            text.insert(&mut txn, 0, "Hello World");
+            text.sticky_index(&txn, 5, Assoc::After).unwrap()
+        };
+
+        let mut txn = doc.transact_mut();
+        text.insert(&mut txn, 0, ">> ");
+        let index = resolve_in(&txn, &sticky, &text).unwrap();
+        assert_eq!(index, 8);
+        text.insert(&mut txn, index, ",");
+        assert_eq!(text.get_string(&txn), ">> Hello, World");
+        assert_eq!(resolve_in(&txn, &sticky, &other), None);
+    }
 }
@@ -1,19 +1,48 @@
 use crate::{
-    any_to_jobject, free_if_valid, from_java_ptr, get_mut_or_throw, get_ref_or_throw,
-    get_string_or_throw, jobject_to_any, out_to_jobject, throw_exception, to_java_ptr, to_jstring,
-    AnyConversionError, DocPtr, DocWrapper, JniEnvExt, TxnPtr, XmlElementPtr,
+    any_to_jobject, check_not_held_or_throw, check_writable_or_throw, event_origin_to_java,
+    find_callback_class, forget_root, free_if_valid, from_java_ptr, get_mut_or_throw,
+    get_ref_or_throw, get_string_or_throw, insert_nested_fragment, is_silent, jobject_to_any,
+    new_java_event, out_to_jobject, register_root_handle, string_array_to_set, throw_exception,
+    throw_jni_error, throw_negative_index, throw_unexpected_node, to_java_ptr, to_jstring,
+    trace_native, type_path_or_null, xml_child_index_or_throw, xml_index_in_parent,
+    xml_out_to_native_ref, AnyConversionError, DocPtr, DocWrapper, JniEnvExt, JniError,
+    StringPolicy, TxnPtr, XmlElementPtr, NATIVE_REF_ELEMENT, NATIVE_REF_FRAGMENT, NATIVE_REF_TEXT,
 };
-use jni::objects::{JClass, JObject, JString, JValue};
-use jni::sys::{jlong, jobject, jstring};
-use jni::{Executor, JNIEnv};
-use std::sync::Arc;
+use jni::objects::{JClass, JMap, JObject, JObjectArray, JString, JValue};
+use jni::sys::{jlong, jobject, jobjectArray, jstring};
+use jni::JNIEnv;
 use yrs::types::xml::XmlEvent;
-use yrs::types::Change;
+use yrs::types::{Change, EntryChange};
+use yrs::Any;
 use yrs::{
-    GetString, Observable, Transact, TransactionMut, Xml, XmlElementPrelim, XmlElementRef,
-    XmlFragment,
+    Doc, GetString, Observable, Transact, TransactionMut, Xml, XmlElementPrelim, XmlElementRef,
+    XmlFragment, XmlOut,
 };
 
+/// Gets or creates the XML element stored at index 0 of the root fragment `name`
+///
+/// The fragment is only written to if it is empty. Returns `None` if its first
+/// child is not an XML element.
+pub fn root_xml_element(doc: &Doc, name: &str) -> Option<XmlElementRef> {
+    let fragment = doc.get_or_insert_xml_fragment(name);
+
+    // Ensure the fragment has an element child at index 0
+    {
+        let txn = doc.transact();
+        if fragment.len(&txn) == 0 {
+            drop(txn);
+            let mut txn = doc.transact_mut();
+            fragment.insert(&mut txn, 0, XmlElementPrelim::empty(name));
+        }
+    }
+
+    // The element at index 0, not the fragment
+    let txn = doc.transact();
+    fragment
+        .get(&txn, 0)
+        .and_then(|child| child.into_xml_element())
+}
+
 /// Gets or creates a YXmlElement instance from a YDoc
 ///
 /// # Parameters
@@ -29,46 +58,39 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetXmlElem
     doc_ptr: jlong,
     name: JString,
 ) -> jlong {
+    trace_native!("JniYXmlElement.nativeGetXmlElement", doc_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
-    let name_str = get_string_or_throw!(&mut env, name, 0);
-
-    let fragment = wrapper.doc.get_or_insert_xml_fragment(name_str.as_str());
-
-    // Ensure the fragment has an element child at index 0
-    {
-        let txn = wrapper.doc.transact();
-        if fragment.len(&txn) == 0 {
-            drop(txn);
-            let mut txn = wrapper.doc.transact_mut();
-            fragment.insert(&mut txn, 0, XmlElementPrelim::empty(name_str.as_str()));
-        }
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
     }
 
-    // Return a pointer to the element at index 0, not the fragment
-    let txn = wrapper.doc.transact();
-    if let Some(child) = fragment.get(&txn, 0) {
-        if let Some(element) = child.into_xml_element() {
-            return to_java_ptr(element);
-        }
+    match root_xml_element(&wrapper.doc, name_str.as_str()) {
+        Some(element) => register_root_handle(doc_ptr, wrapper, &name_str, element),
+        None => 0,
     }
-    0
 }
 
 /// Destroys a YXmlElement instance and frees its memory
 ///
 /// # Parameters
-/// - `ptr`: Pointer to the YXmlElement instance (can be XmlFragmentRef or XmlElementRef)
+/// - `ptr`: Pointer to the YXmlElement instance
 ///
 /// # Safety
-/// The pointer must be valid and point to either a YXmlElement instance
-/// Note: We try to free as XmlElementRef first (new pattern), then XmlFragmentRef (old pattern)
+/// The pointer must be valid and point to a YXmlElement instance. Root-level
+/// elements are stored inside a fragment, but the pointer handed to Java is
+/// always the element itself; use `nativeGetOwningFragmentWithTxn` to reach the
+/// fragment.
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeDestroy(
     _env: JNIEnv,
     _class: JClass,
     ptr: jlong,
 ) {
-    free_if_valid!(XmlElementPtr::from_raw(ptr), XmlElementRef);
+    trace_native!("JniYXmlElement.nativeDestroy", ptr);
+    if forget_root(ptr) {
+        free_if_valid!(XmlElementPtr::from_raw(ptr), XmlElementRef);
+    }
 }
 
 /// Gets the tag name of the XML element
@@ -87,6 +109,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetTagWith
     xml_element_ptr: jlong,
     txn_ptr: jlong,
 ) -> jstring {
+    trace_native!(
+        "JniYXmlElement.nativeGetTagWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
@@ -110,6 +138,46 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetTagWith
     to_jstring(&mut env, tag.as_ref())
 }
 
+/// Finds the root the YXmlElement belongs to and the path leading to it
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// An `Object[]` holding the root name followed by a `String` key or `Integer`
+/// index per level, or null if the YXmlElement was deleted or cannot be reached
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetPathWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobjectArray {
+    trace_native!(
+        "JniYXmlElement.nativeGetPathWithTxn",
+        _doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    type_path_or_null(&mut env, txn, element.as_ref())
+}
+
 /// Gets an attribute value by name using an existing transaction
 ///
 /// # Parameters
@@ -130,7 +198,13 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetAttribu
     txn_ptr: jlong,
     name: JString,
 ) -> jobject {
-    let _doc = get_ref_or_throw!(
+    trace_native!(
+        "JniYXmlElement.nativeGetAttributeWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
         "YDoc",
@@ -148,7 +222,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetAttribu
         "YTransaction",
         std::ptr::null_mut()
     );
-    let name_str = get_string_or_throw!(&mut env, name, std::ptr::null_mut());
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, std::ptr::null_mut());
 
     match element.get_attribute(txn, &name_str) {
         Some(yrs::Out::Any(any)) => match any_to_jobject(&mut env, &any) {
@@ -188,32 +262,119 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeSetAttribu
     name: JString,
     value: JObject,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlElement.nativeSetAttributeWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let element = get_ref_or_throw!(
         &mut env,
         XmlElementPtr::from_raw(xml_element_ptr),
         "YXmlElement"
     );
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let name_str = get_string_or_throw!(&mut env, name);
+    let name_str = get_string_or_throw!(&mut env, wrapper, name);
 
-    let any_value = match jobject_to_any(&mut env, &value) {
+    let any_value = match jobject_to_any(&mut env, &value, wrapper.string_policy()) {
         Ok(a) => a,
-        Err(AnyConversionError::Unsupported(class_name)) => {
-            let msg = format!(
-                "Unsupported attribute value type: {}. Expected String, Long, Integer, Double, Float, Boolean, or null.",
-                class_name
-            );
-            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+        Err(e) => {
+            throw_attribute_error(&mut env, e);
             return;
         }
-        Err(AnyConversionError::Jni(e)) => {
-            throw_exception(&mut env, &format!("JNI error: {:?}", e));
+    };
+
+    element.insert_attribute(txn, name_str, any_value);
+}
+
+/// Sets several attributes using an existing transaction
+///
+/// Every entry is converted before any attribute is set, so an invalid entry
+/// leaves the element unchanged.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `attributes`: A `java.util.Map<String, Object>` of attribute names to values
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeSetAttributesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+    attributes: JObject,
+) {
+    trace_native!(
+        "JniYXmlElement.nativeSetAttributesWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement"
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let entries = match attribute_entries(&mut env, &attributes, wrapper.string_policy()) {
+        Ok(entries) => entries,
+        Err(e) => {
+            throw_attribute_error(&mut env, e);
             return;
         }
     };
+    for (name, value) in entries {
+        element.insert_attribute(txn, name, value);
+    }
+}
 
-    element.insert_attribute(txn, name_str, any_value);
+/// Reads the entries of a Java `Map<String, Object>` of attribute values
+fn attribute_entries(
+    env: &mut JNIEnv,
+    attributes: &JObject,
+    policy: StringPolicy,
+) -> Result<Vec<(String, Any)>, AnyConversionError> {
+    let map = JMap::from_env(env, attributes)?;
+    let mut iter = map.iter(env)?;
+    let mut entries = Vec::new();
+    while let Some((key, value)) = iter.next(env)? {
+        let name = env
+            .get_rust_string(&JString::from(key), policy)
+            .map_err(|e| match e {
+                JniError::Jni(e) => AnyConversionError::Jni(e),
+                e => AnyConversionError::InvalidString(e.to_string()),
+            })?;
+        let value_any = jobject_to_any(env, &value, policy)?;
+        env.delete_local_ref(value)?;
+        entries.push((name, value_any));
+    }
+    Ok(entries)
+}
+
+/// Throws the exception for an attribute name or value that could not be converted
+fn throw_attribute_error(env: &mut JNIEnv, error: AnyConversionError) {
+    match error {
+        AnyConversionError::Unsupported(class_name) => {
+            let msg = format!(
+                "Unsupported attribute value type: {}. Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, or null.",
+                class_name
+            );
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+        }
+        AnyConversionError::InvalidString(msg) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+        }
+        AnyConversionError::Jni(e) => {
+            throw_exception(env, &format!("JNI error: {:?}", e));
+        }
+    }
 }
 
 /// Removes an attribute using an existing transaction
@@ -232,18 +393,68 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveAttr
     txn_ptr: jlong,
     name: JString,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlElement.nativeRemoveAttributeWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let element = get_ref_or_throw!(
         &mut env,
         XmlElementPtr::from_raw(xml_element_ptr),
         "YXmlElement"
     );
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let name_str = get_string_or_throw!(&mut env, name);
+    let name_str = get_string_or_throw!(&mut env, wrapper, name);
 
     element.remove_attribute(txn, &name_str);
 }
 
+/// Removes several attributes using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `names`: A Java String[] of attribute names; names that are not set are ignored
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveAttributesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+    names: JObjectArray,
+) {
+    trace_native!(
+        "JniYXmlElement.nativeRemoveAttributesWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement"
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let names = match string_array_to_set(&mut env, &names, wrapper.string_policy()) {
+        Ok(names) => names,
+        Err(e) => {
+            throw_jni_error(&mut env, &e);
+            return;
+        }
+    };
+    for name in names {
+        element.remove_attribute(txn, &name);
+    }
+}
+
 /// Gets all attribute names using an existing transaction
 ///
 /// # Parameters
@@ -263,6 +474,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetAttribu
     xml_element_ptr: jlong,
     txn_ptr: jlong,
 ) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlElement.nativeGetAttributeNamesWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
     let element = get_ref_or_throw!(
         &mut env,
@@ -337,6 +554,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeToStringWi
     xml_element_ptr: jlong,
     txn_ptr: jlong,
 ) -> jstring {
+    trace_native!(
+        "JniYXmlElement.nativeToStringWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
@@ -377,6 +600,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeChildCount
     xml_element_ptr: jlong,
     txn_ptr: jlong,
 ) -> jni::sys::jint {
+    trace_native!(
+        "JniYXmlElement.nativeChildCountWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
     let element = get_ref_or_throw!(
         &mut env,
@@ -389,6 +618,39 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeChildCount
     element.len(txn) as jni::sys::jint
 }
 
+/// Gets the number of child nodes in this element using a read transaction
+///
+/// The read transaction is opened and released within this call, so
+/// concurrent readers do not wait for each other.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+///
+/// # Returns
+/// The number of child nodes
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeChildCount(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+) -> jni::sys::jint {
+    trace_native!("JniYXmlElement.nativeChildCount", doc_ptr, xml_element_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement",
+        0
+    );
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+
+    element.len(&wrapper.doc.transact()) as jni::sys::jint
+}
+
 /// Inserts an XML element child at the specified index using an existing transaction
 ///
 /// # Parameters
@@ -410,7 +672,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertElem
     index: jni::sys::jint,
     tag: JString,
 ) -> jlong {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    trace_native!(
+        "JniYXmlElement.nativeInsertElementWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    check_writable_or_throw!(&mut env, wrapper, 0);
     let element = get_ref_or_throw!(
         &mut env,
         XmlElementPtr::from_raw(xml_element_ptr),
@@ -420,10 +689,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertElem
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
     if index < 0 {
-        throw_exception(&mut env, "Index cannot be negative");
+        throw_negative_index(&mut env, index);
         return 0;
     }
-    let tag_str = get_string_or_throw!(&mut env, tag, 0);
+    let tag_str = get_string_or_throw!(&mut env, wrapper, tag, 0);
 
     let new_element = element.insert(txn, index as u32, XmlElementPrelim::empty(tag_str.as_str()));
     to_java_ptr(new_element)
@@ -448,7 +717,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertText
     txn_ptr: jlong,
     index: jni::sys::jint,
 ) -> jlong {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    trace_native!(
+        "JniYXmlElement.nativeInsertTextWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    check_writable_or_throw!(&mut env, wrapper, 0);
     let element = get_ref_or_throw!(
         &mut env,
         XmlElementPtr::from_raw(xml_element_ptr),
@@ -458,7 +734,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertText
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
     if index < 0 {
-        throw_exception(&mut env, "Index cannot be negative");
+        throw_negative_index(&mut env, index);
         return 0;
     }
 
@@ -467,6 +743,92 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertText
     to_java_ptr(new_text)
 }
 
+/// Inserts an empty nested XML fragment at the specified index using an
+/// existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `index`: The index at which to insert the fragment
+///
+/// # Returns
+/// A pointer to the new YXmlFragment child
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertFragmentWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+    index: jni::sys::jint,
+) -> jlong {
+    trace_native!(
+        "JniYXmlElement.nativeInsertFragmentWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    check_writable_or_throw!(&mut env, wrapper, 0);
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement",
+        0
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    if index < 0 {
+        throw_negative_index(&mut env, index);
+        return 0;
+    }
+
+    let new_fragment = insert_nested_fragment(txn, element, index as u32);
+    to_java_ptr(new_fragment)
+}
+
+/// Finds the index of a child node using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `child_ptr`: Pointer to the child's YXmlElement, YXmlText or YXmlFragment instance
+/// - `child_type`: 0 if `child_ptr` is an element, 1 if it is a text, 2 if it
+///   is a nested fragment
+///
+/// # Returns
+/// The index of the child, or -1 if the node is not a child of this element
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeIndexOfWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+    child_ptr: jlong,
+    child_type: jni::sys::jint,
+) -> jni::sys::jint {
+    trace_native!(
+        "JniYXmlElement.nativeIndexOfWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr,
+        child_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", -1);
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement",
+        -1
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
+
+    xml_child_index_or_throw(&mut env, txn, element, child_ptr, child_type)
+}
+
 /// Gets the child node at the specified index using an existing transaction
 ///
 /// # Parameters
@@ -476,7 +838,9 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeInsertText
 /// - `index`: The index of the child to retrieve
 ///
 /// # Returns
-/// A Java Object array [type, pointer] where type is 0 for Element, 1 for Text, or null if not found
+/// A `YNativeRef` with type 0 for Element, 1 for Text or 2 for a nested
+/// Fragment, or null if `index` is past the last child. Throws
+/// `IndexOutOfBoundsException` if `index` is negative.
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetChildWithTxn<'a>(
     mut env: JNIEnv<'a>,
@@ -486,6 +850,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetChildWi
     txn_ptr: jlong,
     index: jni::sys::jint,
 ) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlElement.nativeGetChildWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
     let element = get_ref_or_throw!(
         &mut env,
@@ -501,93 +871,206 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetChildWi
     );
 
     if index < 0 {
-        throw_exception(&mut env, "Index cannot be negative");
+        throw_negative_index(&mut env, index);
         return JObject::null();
     }
 
     match element.get(txn, index as u32) {
-        Some(child) => {
-            use yrs::XmlOut;
-
-            // Create Object array [type, pointer]
-            let object_class = match env.find_class("java/lang/Object") {
-                Ok(cls) => cls,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to find Object class");
-                    return JObject::null();
-                }
-            };
+        Some(child) => match xml_out_to_native_ref(&mut env, child) {
+            Ok(obj) => obj,
+            Err(e) => {
+                throw_exception(&mut env, &e.to_string());
+                JObject::null()
+            }
+        },
+        None => JObject::null(),
+    }
+}
 
-            let array = match env.new_object_array(2, object_class, JObject::null()) {
-                Ok(arr) => arr,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to create Object array");
-                    return JObject::null();
-                }
-            };
-
-            let (type_val, ptr) = match child {
-                XmlOut::Element(elem) => (0i32, to_java_ptr(elem)),
-                XmlOut::Text(text) => (1i32, to_java_ptr(text)),
-                XmlOut::Fragment(_) => {
-                    throw_exception(&mut env, "Unexpected XmlFragment as child");
-                    return JObject::null();
-                }
-            };
-
-            // Set type as Integer
-            let integer_class = match env.find_class("java/lang/Integer") {
-                Ok(cls) => cls,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to find Integer class");
-                    return JObject::null();
-                }
-            };
-
-            let type_obj = match env.new_object(
-                integer_class,
-                "(I)V",
-                &[jni::objects::JValue::Int(type_val)],
-            ) {
-                Ok(obj) => obj,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to create Integer object");
-                    return JObject::null();
-                }
-            };
+/// Maximum number of characters included in the preview of a text child
+const CHILD_TEXT_PREVIEW_CHARS: usize = 32;
 
-            if env.set_object_array_element(&array, 0, &type_obj).is_err() {
-                throw_exception(&mut env, "Failed to set type in array");
-                return JObject::null();
+/// Gets all child nodes in a single call using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// A Java Object array [types, pointers, previews] of three parallel arrays:
+/// - types: `int[]` holding the `NATIVE_REF_*` type tag of each child
+/// - pointers: `long[]` of Java pointers to each child
+/// - previews: `String[]` holding the tag name for elements, the first
+///   characters of the XML string for text nodes, and an empty string for
+///   nested fragments
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetChildrenWithTxn<'a>(
+    mut env: JNIEnv<'a>,
+    _class: JClass<'a>,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlElement.nativeGetChildrenWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    use yrs::XmlOut;
+
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement",
+        JObject::null()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        JObject::null()
+    );
+
+    let children: Vec<XmlOut> = element.children(txn).collect();
+    let mut types = Vec::with_capacity(children.len());
+    let mut previews = Vec::with_capacity(children.len());
+    for child in &children {
+        match child {
+            XmlOut::Element(elem) => {
+                types.push(NATIVE_REF_ELEMENT);
+                previews.push(elem.tag().to_string());
+            }
+            XmlOut::Text(text) => {
+                types.push(NATIVE_REF_TEXT);
+                previews.push(
+                    text.get_string(txn)
+                        .chars()
+                        .take(CHILD_TEXT_PREVIEW_CHARS)
+                        .collect(),
+                );
             }
+            XmlOut::Fragment(_) => {
+                types.push(NATIVE_REF_FRAGMENT);
+                previews.push(String::new());
+            }
+        }
+    }
 
-            // Set pointer as Long
-            let long_class = match env.find_class("java/lang/Long") {
-                Ok(cls) => cls,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to find Long class");
-                    return JObject::null();
-                }
-            };
-
-            let ptr_obj =
-                match env.new_object(long_class, "(J)V", &[jni::objects::JValue::Long(ptr)]) {
-                    Ok(obj) => obj,
-                    Err(_) => {
-                        throw_exception(&mut env, "Failed to create Long object");
-                        return JObject::null();
-                    }
-                };
-
-            if env.set_object_array_element(&array, 1, &ptr_obj).is_err() {
-                throw_exception(&mut env, "Failed to set pointer in array");
+    let len = children.len() as i32;
+    let type_array = match env.new_int_array(len) {
+        Ok(arr) => arr,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create int array");
+            return JObject::null();
+        }
+    };
+    if env.set_int_array_region(&type_array, 0, &types).is_err() {
+        throw_exception(&mut env, "Failed to fill type array");
+        return JObject::null();
+    }
+
+    let string_class = match env.find_class("java/lang/String") {
+        Ok(cls) => cls,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to find String class");
+            return JObject::null();
+        }
+    };
+    let preview_array = match env.new_object_array(len, string_class, JObject::null()) {
+        Ok(arr) => arr,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create String array");
+            return JObject::null();
+        }
+    };
+    for (i, preview) in previews.iter().enumerate() {
+        let jpreview = match env.new_string(preview) {
+            Ok(s) => s,
+            Err(_) => {
+                throw_exception(&mut env, "Failed to create Java string");
                 return JObject::null();
             }
+        };
+        if env
+            .set_object_array_element(&preview_array, i as i32, &jpreview)
+            .is_err()
+        {
+            throw_exception(&mut env, "Failed to set array element");
+            return JObject::null();
+        }
+    }
 
-            JObject::from(array)
+    let object_class = match env.find_class("java/lang/Object") {
+        Ok(cls) => cls,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to find Object class");
+            return JObject::null();
         }
-        None => JObject::null(),
+    };
+    let result = match env.new_object_array(3, object_class, JObject::null()) {
+        Ok(arr) => arr,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create Object array");
+            return JObject::null();
+        }
+    };
+    if env
+        .set_object_array_element(&result, 0, &type_array)
+        .is_err()
+        || env
+            .set_object_array_element(&result, 2, &preview_array)
+            .is_err()
+    {
+        throw_exception(&mut env, "Failed to set array element");
+        return JObject::null();
     }
+    let pointer_array = match env.new_long_array(len) {
+        Ok(arr) => arr,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create long array");
+            return JObject::null();
+        }
+    };
+
+    // The array is filled in place, so it can be stored before the pointers
+    if env
+        .set_object_array_element(&result, 1, &pointer_array)
+        .is_err()
+    {
+        throw_exception(&mut env, "Failed to set array element");
+        return JObject::null();
+    }
+
+    // Allocate child pointers last so that no earlier failure can leak them
+    let pointers: Vec<jlong> = children
+        .into_iter()
+        .map(|child| match child {
+            XmlOut::Element(elem) => to_java_ptr(elem),
+            XmlOut::Text(text) => to_java_ptr(text),
+            XmlOut::Fragment(frag) => to_java_ptr(frag),
+        })
+        .collect();
+    if env
+        .set_long_array_region(&pointer_array, 0, &pointers)
+        .is_err()
+    {
+        for (ptr, type_val) in pointers.iter().zip(types.iter()) {
+            unsafe {
+                match *type_val {
+                    NATIVE_REF_ELEMENT => crate::free_java_ptr::<XmlElementRef>(*ptr),
+                    NATIVE_REF_TEXT => crate::free_java_ptr::<yrs::XmlTextRef>(*ptr),
+                    _ => crate::free_java_ptr::<yrs::XmlFragmentRef>(*ptr),
+                }
+            }
+        }
+        throw_exception(&mut env, "Failed to fill pointer array");
+        return JObject::null();
+    }
+
+    JObject::from(result)
 }
 
 /// Removes the child node at the specified index using an existing transaction
@@ -606,7 +1089,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveChil
     txn_ptr: jlong,
     index: jni::sys::jint,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlElement.nativeRemoveChildWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let element = get_ref_or_throw!(
         &mut env,
         XmlElementPtr::from_raw(xml_element_ptr),
@@ -615,7 +1105,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveChil
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
     if index < 0 {
-        throw_exception(&mut env, "Index cannot be negative");
+        throw_negative_index(&mut env, index);
         return;
     }
 
@@ -630,7 +1120,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeRemoveChil
 /// - `txn_ptr`: Pointer to the transaction
 ///
 /// # Returns
-/// A Java Object array [type, pointer] where type is 0 for Element, 1 for Fragment, or null if no parent
+/// A `YNativeRef` with type 0 for Element or 2 for Fragment, or null if no parent
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetParentWithTxn<'a>(
     mut env: JNIEnv<'a>,
@@ -639,6 +1129,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetParentW
     xml_element_ptr: jlong,
     txn_ptr: jlong,
 ) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlElement.nativeGetParentWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
     let element = get_ref_or_throw!(
         &mut env,
@@ -654,87 +1150,63 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetParentW
     );
 
     match element.parent() {
-        Some(parent) => {
-            use yrs::XmlOut;
-
-            // Create Object array [type, pointer]
-            let object_class = match env.find_class("java/lang/Object") {
-                Ok(cls) => cls,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to find Object class");
-                    return JObject::null();
-                }
-            };
-
-            let array = match env.new_object_array(2, object_class, JObject::null()) {
-                Ok(arr) => arr,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to create Object array");
-                    return JObject::null();
-                }
-            };
-
-            let (type_val, ptr) = match parent {
-                XmlOut::Element(elem) => (0i32, to_java_ptr(elem)),
-                XmlOut::Fragment(frag) => (1i32, to_java_ptr(frag)),
-                XmlOut::Text(_) => {
-                    throw_exception(&mut env, "Unexpected XmlText as parent");
-                    return JObject::null();
-                }
-            };
-
-            // Set type as Integer
-            let integer_class = match env.find_class("java/lang/Integer") {
-                Ok(cls) => cls,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to find Integer class");
-                    return JObject::null();
-                }
-            };
-
-            let type_obj = match env.new_object(
-                integer_class,
-                "(I)V",
-                &[jni::objects::JValue::Int(type_val)],
-            ) {
-                Ok(obj) => obj,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to create Integer object");
-                    return JObject::null();
-                }
-            };
-
-            if env.set_object_array_element(&array, 0, &type_obj).is_err() {
-                throw_exception(&mut env, "Failed to set type in array");
-                return JObject::null();
-            }
-
-            // Set pointer as Long
-            let long_class = match env.find_class("java/lang/Long") {
-                Ok(cls) => cls,
-                Err(_) => {
-                    throw_exception(&mut env, "Failed to find Long class");
-                    return JObject::null();
-                }
-            };
-
-            let ptr_obj =
-                match env.new_object(long_class, "(J)V", &[jni::objects::JValue::Long(ptr)]) {
-                    Ok(obj) => obj,
-                    Err(_) => {
-                        throw_exception(&mut env, "Failed to create Long object");
-                        return JObject::null();
-                    }
-                };
-
-            if env.set_object_array_element(&array, 1, &ptr_obj).is_err() {
-                throw_exception(&mut env, "Failed to set pointer in array");
-                return JObject::null();
+        Some(yrs::XmlOut::Text(_)) => {
+            throw_unexpected_node(&mut env, "Unexpected XmlText as parent");
+            JObject::null()
+        }
+        Some(parent) => match xml_out_to_native_ref(&mut env, parent) {
+            Ok(obj) => obj,
+            Err(e) => {
+                throw_exception(&mut env, &e.to_string());
+                JObject::null()
             }
+        },
+        None => JObject::null(),
+    }
+}
 
-            JObject::from(array)
+/// Gets the nearest fragment containing this element using an existing transaction
+///
+/// Walks up through parent elements until a fragment is reached. For root-level
+/// elements this is the root fragment the element is stored in.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_element_ptr`: Pointer to the YXmlElement instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// Pointer to the XmlFragmentRef, or 0 if no fragment contains the element
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetOwningFragmentWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_element_ptr: jlong,
+    txn_ptr: jlong,
+) -> jlong {
+    trace_native!(
+        "JniYXmlElement.nativeGetOwningFragmentWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let element = get_ref_or_throw!(
+        &mut env,
+        XmlElementPtr::from_raw(xml_element_ptr),
+        "YXmlElement",
+        0
+    );
+    let _txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let mut parent = element.parent();
+    loop {
+        match parent {
+            Some(XmlOut::Element(element)) => parent = element.parent(),
+            Some(XmlOut::Fragment(fragment)) => return to_java_ptr(fragment),
+            Some(XmlOut::Text(_)) | None => return 0,
         }
-        None => JObject::null(),
     }
 }
 
@@ -755,6 +1227,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetIndexIn
     xml_element_ptr: jlong,
     txn_ptr: jlong,
 ) -> jni::sys::jint {
+    trace_native!(
+        "JniYXmlElement.nativeGetIndexInParentWithTxn",
+        doc_ptr,
+        xml_element_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", -1);
     let element = get_ref_or_throw!(
         &mut env,
@@ -765,44 +1243,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeGetIndexIn
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
 
     // Get parent and iterate through children to find index
-    match element.parent() {
-        Some(parent) => {
-            use yrs::XmlOut;
-
-            use yrs::branch::Branch;
-            let my_id = <XmlElementRef as AsRef<Branch>>::as_ref(element).id();
-
-            // Match on parent type and iterate children directly
-            match parent {
-                XmlOut::Element(elem) => {
-                    // Iterate through parent's children to find our index
-                    for index in 0..elem.len(txn) {
-                        if let Some(child) = elem.get(txn, index) {
-                            let child_id = child.as_ptr().id();
-                            if child_id == my_id {
-                                return index as jni::sys::jint;
-                            }
-                        }
-                    }
-                    -1
-                }
-                XmlOut::Fragment(frag) => {
-                    // Iterate through parent's children to find our index
-                    for index in 0..frag.len(txn) {
-                        if let Some(child) = frag.get(txn, index) {
-                            let child_id = child.as_ptr().id();
-                            if child_id == my_id {
-                                return index as jni::sys::jint;
-                            }
-                        }
-                    }
-                    -1
-                }
-                XmlOut::Text(_) => -1, // Text can't be a parent
-            }
-        }
-        None => -1, // No parent
-    }
+    xml_index_in_parent(txn, element).map_or(-1, |index| index as jni::sys::jint)
 }
 
 /// Registers an observer for the YXmlElement
@@ -821,16 +1262,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeObserve(
     subscription_id: jlong,
     yxmlelement_obj: JObject,
 ) {
-    let wrapper = get_mut_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYXmlElement.nativeObserve", doc_ptr, xml_element_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
     let element = get_ref_or_throw!(
         &mut env,
         XmlElementPtr::from_raw(xml_element_ptr),
         "YXmlElement"
     );
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
         Err(e) => {
             throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
             return;
@@ -846,8 +1288,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeObserve(
         }
     };
 
+    let gate = wrapper.dispatch_gate();
+
     // Create observer closure
     let subscription = element.observe(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
         // Use Executor for thread attachment with automatic local frame management
         let _ = executor.with_attached(|env| {
             dispatch_xmlelement_event(env, doc_ptr, subscription_id, txn, event)
@@ -855,7 +1303,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeObserve(
     });
 
     // Store subscription and GlobalRef in the DocWrapper
-    wrapper.add_subscription(subscription_id, subscription, global_ref);
+    wrapper.add_subscription(subscription_id, xml_element_ptr, subscription, global_ref);
 }
 
 /// Unregisters an observer for the YXmlElement
@@ -872,7 +1320,8 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeUnobserve(
     _xml_element_ptr: jlong,
     subscription_id: jlong,
 ) {
-    let wrapper = get_mut_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYXmlElement.nativeUnobserve", doc_ptr, _xml_element_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
 
     // Remove subscription and GlobalRef from DocWrapper
     // Both the Subscription and GlobalRef are dropped here
@@ -880,13 +1329,16 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlElement_nativeUnobserve(
 }
 
 /// Helper function to dispatch an XML element event to Java
-fn dispatch_xmlelement_event(
+pub(crate) fn dispatch_xmlelement_event(
     env: &mut JNIEnv,
     doc_ptr: jlong,
     subscription_id: jlong,
     txn: &TransactionMut,
     event: &XmlEvent,
 ) -> Result<(), jni::errors::Error> {
+    if is_silent(txn) {
+        return Ok(());
+    }
     // Get the Java YXmlElement object from DocWrapper
     let yxmlelement_ref = unsafe {
         let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
@@ -912,7 +1364,7 @@ fn dispatch_xmlelement_event(
                 // Create YArrayChange for INSERT (children are like array items)
                 let items_list = env.new_object("java/util/ArrayList", "()V", &[])?;
                 for item in items {
-                    let item_obj = out_to_jobject(env, item)?;
+                    let item_obj = out_to_jobject(env, txn, item)?;
                     env.call_method(
                         &items_list,
                         "add",
@@ -921,7 +1373,8 @@ fn dispatch_xmlelement_event(
                     )?;
                 }
 
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
                 env.new_object(
                     change_class,
                     "(Ljava/util/List;)V",
@@ -930,8 +1383,9 @@ fn dispatch_xmlelement_event(
             }
             Change::Removed(len) => {
                 // Create YArrayChange for DELETE
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
                 let delete_type =
                     env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
 
@@ -943,8 +1397,9 @@ fn dispatch_xmlelement_event(
             }
             Change::Retain(len) => {
                 // Create YArrayChange for RETAIN
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
                 let retain_type =
                     env.get_static_field(type_class, "RETAIN", "Lnet/carcdr/ycrdt/YChange$Type;")?;
 
@@ -965,105 +1420,23 @@ fn dispatch_xmlelement_event(
         )?;
     }
 
-    // Process attribute changes
+    // Process attribute changes, each carrying the transaction's origin
     let keys = event.keys(txn);
-    for (attr_name, change) in keys.iter() {
-        use yrs::types::EntryChange;
-
-        let attr_change_obj = match change {
-            EntryChange::Inserted(new_val) => {
-                let new_str = new_val.to_string();
-                let attr_name_jstr = env.new_string(attr_name)?;
-                let new_val_jstr = env.new_string(&new_str)?;
-
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYXmlElementChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let insert_type =
-                    env.get_static_field(type_class, "INSERT", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
-                    &[
-                        JValue::Object(&insert_type.l()?),
-                        JValue::Object(&attr_name_jstr),
-                        JValue::Object(&new_val_jstr),
-                        JValue::Object(&JObject::null()),
-                    ],
-                )?
-            }
-            EntryChange::Updated(old_val, new_val) => {
-                let old_str = old_val.to_string();
-                let new_str = new_val.to_string();
-                let attr_name_jstr = env.new_string(attr_name)?;
-                let old_val_jstr = env.new_string(&old_str)?;
-                let new_val_jstr = env.new_string(&new_str)?;
-
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYXmlElementChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let attribute_type = env.get_static_field(
-                    type_class,
-                    "ATTRIBUTE",
-                    "Lnet/carcdr/ycrdt/YChange$Type;",
-                )?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
-                    &[
-                        JValue::Object(&attribute_type.l()?),
-                        JValue::Object(&attr_name_jstr),
-                        JValue::Object(&new_val_jstr),
-                        JValue::Object(&old_val_jstr),
-                    ],
-                )?
-            }
-            EntryChange::Removed(old_val) => {
-                let old_str = old_val.to_string();
-                let attr_name_jstr = env.new_string(attr_name)?;
-                let old_val_jstr = env.new_string(&old_str)?;
-
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYXmlElementChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let delete_type =
-                    env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
-                    &[
-                        JValue::Object(&delete_type.l()?),
-                        JValue::Object(&attr_name_jstr),
-                        JValue::Object(&JObject::null()),
-                        JValue::Object(&old_val_jstr),
-                    ],
-                )?
-            }
-        };
-
-        // Add to changes list
-        env.call_method(
-            &changes_list,
-            "add",
-            "(Ljava/lang/Object;)Z",
-            &[JValue::Object(&attr_change_obj)],
-        )?;
+    if !keys.is_empty() {
+        let origin = event_origin_to_java(env, txn)?;
+        for (attr_name, change) in keys.iter() {
+            let attr_change_obj = xml_attribute_change(env, txn, attr_name, change, &origin)?;
+            env.call_method(
+                &changes_list,
+                "add",
+                "(Ljava/lang/Object;)Z",
+                &[JValue::Object(&attr_change_obj)],
+            )?;
+        }
     }
 
-    // Create YEvent
-    let event_class = env.find_class("net/carcdr/ycrdt/jni/JniYEvent")?;
-    let target = yxmlelement_obj; // Use the YXmlElement object as the target
-    let origin_jstr = env.new_string("")?; // Empty origin for now
-
-    let event_obj = env.new_object(
-        event_class,
-        "(Ljava/lang/Object;Ljava/util/List;Ljava/lang/String;)V",
-        &[
-            JValue::Object(target),
-            JValue::Object(&changes_list),
-            JValue::Object(&origin_jstr),
-        ],
-    )?;
+    // Create YEvent carrying the transaction's origin
+    let event_obj = new_java_event(env, txn, yxmlelement_obj, &changes_list)?;
 
     // Call YXmlElement.dispatchEvent(subscriptionId, event)
     env.call_method(
@@ -1076,6 +1449,58 @@ fn dispatch_xmlelement_event(
     Ok(())
 }
 
+/// Builds the `JniYXmlElementChange` for one attribute change
+///
+/// Values are passed both as strings, for `YXmlElementChange`, and converted
+/// with the shared converter, so attributes set to numbers, booleans or maps
+/// keep their type.
+fn xml_attribute_change<'local>(
+    env: &mut JNIEnv<'local>,
+    txn: &TransactionMut,
+    attr_name: &str,
+    change: &EntryChange,
+    origin: &JObject,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    let (type_name, new_val, old_val) = match change {
+        EntryChange::Inserted(new_val) => ("INSERT", Some(new_val), None),
+        EntryChange::Updated(old_val, new_val) => ("ATTRIBUTE", Some(new_val), Some(old_val)),
+        EntryChange::Removed(old_val) => ("DELETE", None, Some(old_val)),
+    };
+    let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
+    let change_type = env
+        .get_static_field(type_class, type_name, "Lnet/carcdr/ycrdt/YChange$Type;")?
+        .l()?;
+    let attr_name_jstr = env.new_string(attr_name)?;
+    let mut values = Vec::with_capacity(4);
+    for value in [new_val, old_val] {
+        match value {
+            Some(value) => {
+                values.push(JObject::from(env.new_string(value.to_string())?));
+                values.push(out_to_jobject(env, txn, value)?);
+            }
+            None => {
+                values.push(JObject::null());
+                values.push(JObject::null());
+            }
+        }
+    }
+
+    let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniYXmlElementChange")?;
+    env.new_object(
+        &class,
+        "(Lnet/carcdr/ycrdt/YChange$Type;Ljava/lang/String;Ljava/lang/String;Ljava/lang/Object;Ljava/lang/String;Ljava/lang/Object;Lnet/carcdr/ycrdt/jni/JniEventOrigin;)V",
+        &[
+            JValue::Object(&change_type),
+            JValue::Object(&attr_name_jstr),
+            JValue::Object(&values[0]),
+            JValue::Object(&values[1]),
+            JValue::Object(&values[2]),
+            JValue::Object(&values[3]),
+            JValue::Object(origin),
+        ],
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1145,7 +1570,7 @@ mod tests {
             let mut txn = doc.transact_mut();
             let element = fragment.insert(&mut txn, 0, XmlElementPrelim::empty("div"));
             element.insert_attribute(&mut txn, "count", yrs::Any::BigInt(42));
-            element.insert_attribute(&mut txn, "ratio", yrs::Any::Number(3.14));
+            element.insert_attribute(&mut txn, "ratio", yrs::Any::Number(2.5));
             element.insert_attribute(&mut txn, "draft", yrs::Any::Bool(true));
             element.insert_attribute(&mut txn, "empty", yrs::Any::Null);
         }
@@ -1158,7 +1583,7 @@ mod tests {
         );
         assert_eq!(
             element.get_attribute(&txn, "ratio"),
-            Some(yrs::Out::Any(yrs::Any::Number(3.14)))
+            Some(yrs::Out::Any(yrs::Any::Number(2.5)))
         );
         assert_eq!(
             element.get_attribute(&txn, "draft"),
@@ -1196,4 +1621,28 @@ mod tests {
             Some(yrs::Out::Any(yrs::Any::String("main".into())))
         );
     }
+
+    #[test]
+    fn test_xml_element_children_iteration() {
+        use yrs::{XmlOut, XmlTextPrelim};
+
+        let doc = Doc::new();
+        let fragment = doc.get_or_insert_xml_fragment("test");
+
+        let mut txn = doc.transact_mut();
+        let element = fragment.insert(&mut txn, 0, XmlElementPrelim::empty("div"));
+        element.insert(&mut txn, 0, XmlElementPrelim::empty("span"));
+        element.insert(&mut txn, 1, XmlTextPrelim::new("hello"));
+
+        let children: Vec<XmlOut> = element.children(&txn).collect();
+        assert_eq!(children.len(), 2);
+        match &children[0] {
+            XmlOut::Element(child) => assert_eq!(child.tag().as_ref(), "span"),
+            other => panic!("expected element, got {:?}", other),
+        }
+        match &children[1] {
+            XmlOut::Text(child) => assert_eq!(child.get_string(&txn), "hello"),
+            other => panic!("expected text, got {:?}", other),
+        }
+    }
 }
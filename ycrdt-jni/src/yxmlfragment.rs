@@ -1,19 +1,137 @@
 use crate::{
+    check_not_held_or_throw, check_writable_or_throw, find_callback_class, forget_root,
     free_if_valid, from_java_ptr, get_mut_or_throw, get_ref_or_throw, get_string_or_throw,
-    out_to_jobject, throw_exception, to_java_ptr, to_jstring, DocPtr, DocWrapper, JniEnvExt,
-    TxnPtr, XmlFragmentPtr,
+    is_silent, jint_or_throw, native_ref_type, new_java_event, out_to_jobject, root_to_java_ptr,
+    snapshot_xml_fragment, throw_exception, throw_negative_index, to_java_ptr, to_jstring,
+    trace_native, xml_out_to_native_ref, DocPtr, DocWrapper, JniEnvExt, JniResultExt, TxnPtr,
+    XmlElementPtr, XmlFragmentPtr, XmlTextPtr,
 };
 use jni::objects::{JClass, JObject, JString, JValue};
-use jni::sys::{jint, jlong, jstring};
-use jni::{Executor, JNIEnv};
-use std::sync::Arc;
-use yrs::types::xml::XmlEvent;
+use jni::sys::{jint, jlong, jobject, jstring};
+use jni::JNIEnv;
+use yrs::branch::Branch;
+use yrs::types::xml::{XmlEvent, XmlIn};
 use yrs::types::Change;
 use yrs::{
-    GetString, Observable, TransactionMut, XmlElementPrelim, XmlFragment, XmlFragmentRef,
-    XmlTextPrelim,
+    GetString, Observable, ReadTxn, Transact, TransactionMut, Xml, XmlElementPrelim, XmlFragment,
+    XmlFragmentPrelim, XmlFragmentRef, XmlOut, XmlTextPrelim,
 };
 
+/// Finds the index of `child` among the children of `parent`
+///
+/// Children are compared by branch ID, in a single pass over `parent`.
+/// Returns `None` if `child` is not a child of `parent`, or has been deleted.
+pub fn xml_child_index<P, C, T>(txn: &T, parent: &P, child: &C) -> Option<u32>
+where
+    P: XmlFragment + AsRef<Branch>,
+    C: Xml + AsRef<Branch>,
+    T: ReadTxn,
+{
+    branch_child_index(txn, parent, child.parent()?, child.as_ref())
+}
+
+/// Finds the index of the nested fragment `child` among the children of `parent`
+fn xml_fragment_child_index<P, T>(txn: &T, parent: &P, child: &XmlFragmentRef) -> Option<u32>
+where
+    P: XmlFragment + AsRef<Branch>,
+    T: ReadTxn,
+{
+    branch_child_index(txn, parent, child.parent()?, child.as_ref())
+}
+
+fn branch_child_index<P, T>(
+    txn: &T,
+    parent: &P,
+    child_parent: XmlOut,
+    child: &Branch,
+) -> Option<u32>
+where
+    P: XmlFragment + AsRef<Branch>,
+    T: ReadTxn,
+{
+    if child_parent.as_ptr().id() != parent.as_ref().id() {
+        return None;
+    }
+    let child_id = child.id();
+    let index = parent
+        .children(txn)
+        .position(|node| node.as_ptr().id() == child_id)?;
+    Some(index as u32)
+}
+
+/// Inserts an empty nested fragment among the children of `parent`
+///
+/// yrs only accepts a fragment as an XML child when it is wrapped in `XmlIn`.
+pub fn insert_nested_fragment<P: XmlFragment>(
+    txn: &mut TransactionMut,
+    parent: &P,
+    index: u32,
+) -> XmlFragmentRef {
+    let node = parent.insert(txn, index, XmlIn::Fragment(XmlFragmentPrelim::default()));
+    XmlFragmentRef::from(node.as_ptr())
+}
+
+/// Finds the index of `node` within its parent's children
+pub fn xml_index_in_parent<C, T>(txn: &T, node: &C) -> Option<u32>
+where
+    C: Xml + AsRef<Branch>,
+    T: ReadTxn,
+{
+    match node.parent()? {
+        XmlOut::Element(parent) => xml_child_index(txn, &parent, node),
+        XmlOut::Fragment(parent) => xml_child_index(txn, &parent, node),
+        XmlOut::Text(_) => None,
+    }
+}
+
+/// Finds the index of a child passed from Java as a handle and node type
+///
+/// `child_type` is 0 for an element handle, 1 for a text handle and 2 for a
+/// nested fragment handle, as returned by `nativeGetNodeTypeWithTxn`. Throws if
+/// the handle is invalid.
+///
+/// # Returns
+/// The index, or -1 if the node is not a child of `parent`
+pub fn xml_child_index_or_throw<P, T>(
+    env: &mut JNIEnv,
+    txn: &T,
+    parent: &P,
+    child_ptr: jlong,
+    child_type: jint,
+) -> jint
+where
+    P: XmlFragment + AsRef<Branch>,
+    T: ReadTxn,
+{
+    let index = match child_type {
+        0 => {
+            let child =
+                get_ref_or_throw!(env, XmlElementPtr::from_raw(child_ptr), "YXmlElement", -1);
+            xml_child_index(txn, parent, child)
+        }
+        1 => {
+            let child = get_ref_or_throw!(env, XmlTextPtr::from_raw(child_ptr), "YXmlText", -1);
+            xml_child_index(txn, parent, child)
+        }
+        2 => {
+            let child =
+                get_ref_or_throw!(env, XmlFragmentPtr::from_raw(child_ptr), "YXmlFragment", -1);
+            xml_fragment_child_index(txn, parent, child)
+        }
+        _ => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Unknown XML node type: {}", child_type),
+            );
+            return -1;
+        }
+    };
+    match index {
+        Some(index) => jint_or_throw(env, index, "Child index").unwrap_or(-1),
+        None => -1,
+    }
+}
+
 /// Gets or creates a YXmlFragment instance from a YDoc
 ///
 /// # Parameters
@@ -29,11 +147,16 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetFragme
     doc_ptr: jlong,
     name: JString,
 ) -> jlong {
+    trace_native!("JniYXmlFragment.nativeGetFragment", doc_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
-    let name_str = get_string_or_throw!(&mut env, name, 0);
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
 
-    let fragment = wrapper.doc.get_or_insert_xml_fragment(name_str.as_str());
-    to_java_ptr(fragment)
+    root_to_java_ptr(doc_ptr, wrapper, &name_str, || {
+        wrapper.doc.get_or_insert_xml_fragment(name_str.as_str())
+    })
 }
 
 /// Destroys a YXmlFragment instance and frees its memory
@@ -49,7 +172,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeDestroy(
     _class: JClass,
     ptr: jlong,
 ) {
-    free_if_valid!(XmlFragmentPtr::from_raw(ptr), XmlFragmentRef);
+    trace_native!("JniYXmlFragment.nativeDestroy", ptr);
+    if forget_root(ptr) {
+        free_if_valid!(XmlFragmentPtr::from_raw(ptr), XmlFragmentRef);
+    }
 }
 
 /// Gets the number of children in the fragment using an existing transaction
@@ -60,7 +186,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeDestroy(
 /// - `txn_ptr`: Pointer to the transaction
 ///
 /// # Returns
-/// The number of children as jint
+/// The number of children as jlong
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLengthWithTxn(
     mut env: JNIEnv,
@@ -68,7 +194,13 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLengthWit
     _doc_ptr: jlong,
     fragment_ptr: jlong,
     txn_ptr: jlong,
-) -> jint {
+) -> jlong {
+    trace_native!(
+        "JniYXmlFragment.nativeLengthWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
@@ -77,7 +209,41 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLengthWit
     );
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
-    fragment.len(txn) as jint
+    jlong::from(fragment.len(txn))
+}
+
+/// Returns the number of children in the fragment using a read transaction
+///
+/// The read transaction is opened and released within this call. Unlike the
+/// implicit write transaction Java would otherwise open, it does not exclude
+/// other readers or run the commit machinery.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `fragment_ptr`: Pointer to the YXmlFragment instance
+///
+/// # Returns
+/// The number of children as jlong
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLength(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    fragment_ptr: jlong,
+) -> jlong {
+    trace_native!("JniYXmlFragment.nativeLength", doc_ptr, fragment_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let fragment = get_ref_or_throw!(
+        &mut env,
+        XmlFragmentPtr::from_raw(fragment_ptr),
+        "YXmlFragment",
+        0
+    );
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+
+    jlong::from(fragment.len(&wrapper.doc.transact()))
 }
 
 /// Inserts an XML element as a child at the specified index using an existing transaction
@@ -92,19 +258,27 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeLengthWit
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertElementWithTxn(
     mut env: JNIEnv,
     _class: JClass,
-    _doc_ptr: jlong,
+    doc_ptr: jlong,
     fragment_ptr: jlong,
     txn_ptr: jlong,
     index: jint,
     tag: JString,
 ) {
+    trace_native!(
+        "JniYXmlFragment.nativeInsertElementWithTxn",
+        doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
         "YXmlFragment"
     );
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let tag_str = get_string_or_throw!(&mut env, tag);
+    let tag_str = get_string_or_throw!(&mut env, wrapper, tag);
 
     fragment.insert(txn, index as u32, XmlElementPrelim::empty(tag_str.as_str()));
 }
@@ -121,23 +295,71 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertEle
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertTextWithTxn(
     mut env: JNIEnv,
     _class: JClass,
-    _doc_ptr: jlong,
+    doc_ptr: jlong,
     fragment_ptr: jlong,
     txn_ptr: jlong,
     index: jint,
     content: JString,
 ) {
+    trace_native!(
+        "JniYXmlFragment.nativeInsertTextWithTxn",
+        doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
         "YXmlFragment"
     );
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let content_str = get_string_or_throw!(&mut env, content);
+    let content_str = get_string_or_throw!(&mut env, wrapper, content);
 
     fragment.insert(txn, index as u32, XmlTextPrelim::new(content_str.as_str()));
 }
 
+/// Inserts an empty nested XML fragment as a child at the specified index using
+/// an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `fragment_ptr`: Pointer to the YXmlFragment instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `index`: The index at which to insert the fragment
+///
+/// # Returns
+/// A pointer to the new YXmlFragment child
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertFragmentWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    fragment_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+) -> jlong {
+    trace_native!(
+        "JniYXmlFragment.nativeInsertFragmentWithTxn",
+        doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    check_writable_or_throw!(&mut env, wrapper, 0);
+    let fragment = get_ref_or_throw!(
+        &mut env,
+        XmlFragmentPtr::from_raw(fragment_ptr),
+        "YXmlFragment",
+        0
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    let nested = insert_nested_fragment(txn, fragment, index as u32);
+    to_java_ptr(nested)
+}
+
 /// Removes children from the fragment using an existing transaction
 ///
 /// # Parameters
@@ -150,12 +372,20 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeInsertTex
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeRemoveWithTxn(
     mut env: JNIEnv,
     _class: JClass,
-    _doc_ptr: jlong,
+    doc_ptr: jlong,
     fragment_ptr: jlong,
     txn_ptr: jlong,
     index: jint,
     length: jint,
 ) {
+    trace_native!(
+        "JniYXmlFragment.nativeRemoveWithTxn",
+        doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
@@ -175,7 +405,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeRemoveWit
 /// - `index`: The index of the child
 ///
 /// # Returns
-/// 0 for ELEMENT, 1 for TEXT, -1 if no node at index
+/// The `NATIVE_REF_*` type tag of the child, or -1 if no node at index
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetNodeTypeWithTxn(
     mut env: JNIEnv,
@@ -185,6 +415,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetNodeTy
     txn_ptr: jlong,
     index: jint,
 ) -> jint {
+    trace_native!(
+        "JniYXmlFragment.nativeGetNodeTypeWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
@@ -193,15 +429,49 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetNodeTy
     );
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
 
-    if let Some(child) = fragment.get(txn, index as u32) {
-        // Check element first, then text
-        if child.clone().into_xml_element().is_some() {
-            return 0; // ELEMENT
-        } else if child.into_xml_text().is_some() {
-            return 1; // TEXT
-        }
-    }
-    -1 // No node at index
+    fragment
+        .get(txn, index as u32)
+        .map_or(-1, |child| native_ref_type(&child))
+}
+
+/// Finds the index of a child node using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `fragment_ptr`: Pointer to the YXmlFragment instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `child_ptr`: Pointer to the child's YXmlElement, YXmlText or YXmlFragment instance
+/// - `child_type`: 0 if `child_ptr` is an element, 1 if it is a text, 2 if it
+///   is a nested fragment
+///
+/// # Returns
+/// The index of the child, or -1 if the node is not a child of this fragment
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeIndexOfWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    fragment_ptr: jlong,
+    txn_ptr: jlong,
+    child_ptr: jlong,
+    child_type: jint,
+) -> jint {
+    trace_native!(
+        "JniYXmlFragment.nativeIndexOfWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr,
+        child_ptr
+    );
+    let fragment = get_ref_or_throw!(
+        &mut env,
+        XmlFragmentPtr::from_raw(fragment_ptr),
+        "YXmlFragment",
+        -1
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
+
+    xml_child_index_or_throw(&mut env, txn, fragment, child_ptr, child_type)
 }
 
 /// Gets the XML element at the specified index (if it is an element) using an existing transaction
@@ -223,6 +493,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetElemen
     txn_ptr: jlong,
     index: jint,
 ) -> jlong {
+    trace_native!(
+        "JniYXmlFragment.nativeGetElementWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
@@ -243,6 +519,53 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetElemen
     0
 }
 
+/// Gets the element a fragment wraps using an existing transaction
+///
+/// A fragment wraps an element when the element is its only child, which is how
+/// root-level elements are stored.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `fragment_ptr`: Pointer to the YXmlFragment instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// Pointer to the XmlElementRef, or 0 if the fragment does not wrap an element
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetWrappedElementWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    fragment_ptr: jlong,
+    txn_ptr: jlong,
+) -> jlong {
+    trace_native!(
+        "JniYXmlFragment.nativeGetWrappedElementWithTxn",
+        doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let fragment = get_ref_or_throw!(
+        &mut env,
+        XmlFragmentPtr::from_raw(fragment_ptr),
+        "YXmlFragment",
+        0
+    );
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
+
+    if fragment.len(txn) != 1 {
+        return 0;
+    }
+    match fragment
+        .get(txn, 0)
+        .and_then(|child| child.into_xml_element())
+    {
+        Some(element) => to_java_ptr(element),
+        None => 0,
+    }
+}
+
 /// Gets the XML text at the specified index (if it is text) using an existing transaction
 ///
 /// # Parameters
@@ -262,6 +585,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetTextWi
     txn_ptr: jlong,
     index: jint,
 ) -> jlong {
+    trace_native!(
+        "JniYXmlFragment.nativeGetTextWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
@@ -282,6 +611,63 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetTextWi
     0
 }
 
+/// Gets the child node at the specified index using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `fragment_ptr`: Pointer to the YXmlFragment instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `index`: The index of the child
+///
+/// # Returns
+/// A `YNativeRef` with type 0 for Element, 1 for Text or 2 for a nested
+/// Fragment, or null if `index` is past the last child. Throws
+/// `IndexOutOfBoundsException` if `index` is negative.
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeGetChildWithTxn<'a>(
+    mut env: JNIEnv<'a>,
+    _class: JClass<'a>,
+    _doc_ptr: jlong,
+    fragment_ptr: jlong,
+    txn_ptr: jlong,
+    index: jint,
+) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlFragment.nativeGetChildWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let fragment = get_ref_or_throw!(
+        &mut env,
+        XmlFragmentPtr::from_raw(fragment_ptr),
+        "YXmlFragment",
+        JObject::null()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        JObject::null()
+    );
+
+    if index < 0 {
+        throw_negative_index(&mut env, index);
+        return JObject::null();
+    }
+
+    match fragment.get(txn, index as u32) {
+        Some(child) => match xml_out_to_native_ref(&mut env, child) {
+            Ok(obj) => obj,
+            Err(e) => {
+                throw_exception(&mut env, &e.to_string());
+                JObject::null()
+            }
+        },
+        None => JObject::null(),
+    }
+}
+
 /// Returns the XML string representation of the fragment using an existing transaction
 ///
 /// # Parameters
@@ -299,6 +685,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeToXmlStri
     fragment_ptr: jlong,
     txn_ptr: jlong,
 ) -> jstring {
+    trace_native!(
+        "JniYXmlFragment.nativeToXmlStringWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
     let fragment = get_ref_or_throw!(
         &mut env,
         XmlFragmentPtr::from_raw(fragment_ptr),
@@ -316,6 +708,47 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeToXmlStri
     to_jstring(&mut env, &xml_string)
 }
 
+/// Materializes the fragment as an immutable tree using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `fragment_ptr`: Pointer to the YXmlFragment instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// A `JniXmlNode` of kind `FRAGMENT` holding the whole tree
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeSnapshotTreeWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    fragment_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobject {
+    trace_native!(
+        "JniYXmlFragment.nativeSnapshotTreeWithTxn",
+        _doc_ptr,
+        fragment_ptr,
+        txn_ptr
+    );
+    let fragment = get_ref_or_throw!(
+        &mut env,
+        XmlFragmentPtr::from_raw(fragment_ptr),
+        "YXmlFragment",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    snapshot_xml_fragment(&mut env, txn, fragment)
+        .map(JObject::into_raw)
+        .unwrap_or_throw(&mut env)
+}
+
 /// Registers an observer for the YXmlFragment
 ///
 /// # Parameters
@@ -332,6 +765,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeObserve(
     subscription_id: jlong,
     fragment_obj: JObject,
 ) {
+    trace_native!("JniYXmlFragment.nativeObserve", doc_ptr, fragment_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
     let fragment = get_ref_or_throw!(
         &mut env,
@@ -339,9 +773,9 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeObserve(
         "YXmlFragment"
     );
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(&env) {
+        Ok(executor) => executor,
         Err(e) => {
             throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
             return;
@@ -357,8 +791,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeObserve(
         }
     };
 
+    let gate = wrapper.dispatch_gate();
+
     // Create observer closure
     let subscription = fragment.observe(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
         // Use Executor for thread attachment with automatic local frame management
         let _ = executor.with_attached(|env| {
             dispatch_xmlfragment_event(env, doc_ptr, subscription_id, txn, event)
@@ -366,7 +806,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeObserve(
     });
 
     // Store subscription and GlobalRef in the DocWrapper
-    wrapper.add_subscription(subscription_id, subscription, global_ref);
+    wrapper.add_subscription(subscription_id, fragment_ptr, subscription, global_ref);
 }
 
 /// Unregisters an observer for the YXmlFragment
@@ -383,6 +823,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeUnobserve
     _fragment_ptr: jlong,
     subscription_id: jlong,
 ) {
+    trace_native!("JniYXmlFragment.nativeUnobserve", doc_ptr, _fragment_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
 
     // Remove subscription and GlobalRef from DocWrapper
@@ -391,13 +832,16 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlFragment_nativeUnobserve
 }
 
 /// Helper function to dispatch an XML fragment event to Java
-fn dispatch_xmlfragment_event(
+pub(crate) fn dispatch_xmlfragment_event(
     env: &mut JNIEnv,
     doc_ptr: jlong,
     subscription_id: jlong,
     txn: &TransactionMut,
     event: &XmlEvent,
 ) -> Result<(), jni::errors::Error> {
+    if is_silent(txn) {
+        return Ok(());
+    }
     // Get the Java YXmlFragment object from DocWrapper
     let fragment_ref = unsafe {
         let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
@@ -426,7 +870,7 @@ fn dispatch_xmlfragment_event(
                 // Convert items to Java ArrayList
                 let items_list = env.new_object("java/util/ArrayList", "()V", &[])?;
                 for item in items {
-                    let item_obj = out_to_jobject(env, item)?;
+                    let item_obj = out_to_jobject(env, txn, item)?;
                     env.call_method(
                         &items_list,
                         "add",
@@ -435,7 +879,8 @@ fn dispatch_xmlfragment_event(
                     )?;
                 }
 
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
                 env.new_object(
                     change_class,
                     "(Ljava/util/List;)V",
@@ -444,8 +889,9 @@ fn dispatch_xmlfragment_event(
             }
             Change::Removed(len) => {
                 // Create YArrayChange for DELETE
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
                 let delete_type =
                     env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
 
@@ -457,8 +903,9 @@ fn dispatch_xmlfragment_event(
             }
             Change::Retain(len) => {
                 // Create YArrayChange for RETAIN
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYArrayChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
+                let change_class =
+                    find_callback_class(env, "net/carcdr/ycrdt/jni/JniYArrayChange")?;
+                let type_class = find_callback_class(env, "net/carcdr/ycrdt/YChange$Type")?;
                 let retain_type =
                     env.get_static_field(type_class, "RETAIN", "Lnet/carcdr/ycrdt/YChange$Type;")?;
 
@@ -479,20 +926,8 @@ fn dispatch_xmlfragment_event(
         )?;
     }
 
-    // Create YEvent
-    let event_class = env.find_class("net/carcdr/ycrdt/jni/JniYEvent")?;
-    let target = fragment_obj; // Use the YXmlFragment object as the target
-    let origin_jstr = env.new_string("")?; // Empty origin for now
-
-    let event_obj = env.new_object(
-        event_class,
-        "(Ljava/lang/Object;Ljava/util/List;Ljava/lang/String;)V",
-        &[
-            JValue::Object(target),
-            JValue::Object(&changes_list),
-            JValue::Object(&origin_jstr),
-        ],
-    )?;
+    // Create YEvent carrying the transaction's origin
+    let event_obj = new_java_event(env, txn, fragment_obj, &changes_list)?;
 
     // Call YXmlFragment.dispatchEvent(subscriptionId, event)
     env.call_method(
@@ -636,4 +1071,23 @@ mod tests {
             free_java_ptr::<XmlTextRef>(text_ptr);
         }
     }
+
+    #[test]
+    fn test_xml_child_index() {
+        let doc = Doc::new();
+        let fragment = doc.get_or_insert_xml_fragment("test");
+        let mut txn = doc.transact_mut();
+        let div = fragment.insert(&mut txn, 0, XmlElementPrelim::empty("div"));
+        let text = fragment.insert(&mut txn, 1, XmlTextPrelim::new("tail"));
+        let nested = div.insert(&mut txn, 0, XmlTextPrelim::new("inner"));
+
+        assert_eq!(xml_child_index(&txn, &fragment, &div), Some(0));
+        assert_eq!(xml_child_index(&txn, &fragment, &text), Some(1));
+        assert_eq!(xml_child_index(&txn, &fragment, &nested), None);
+        assert_eq!(xml_index_in_parent(&txn, &nested), Some(0));
+
+        fragment.remove_range(&mut txn, 0, 1);
+        assert_eq!(xml_child_index(&txn, &fragment, &div), None);
+        assert_eq!(xml_child_index(&txn, &fragment, &text), Some(0));
+    }
 }
@@ -1,19 +1,49 @@
 use crate::{
-    attrs_to_java_hashmap, free_if_valid, from_java_ptr, get_mut_or_throw, get_ref_or_throw,
-    get_string_or_throw, throw_exception, to_java_ptr, to_jstring, DocPtr, DocWrapper, JniEnvExt,
-    TxnPtr, XmlTextPtr,
+    any_to_jobject, attrs_to_java_hashmap, check_not_held_or_throw, check_writable_or_throw,
+    find_callback_class, forget_root, format_runs_or_throw, free_if_valid, from_java_ptr,
+    get_mut_or_throw, get_ref_or_throw, get_string_or_throw, is_silent, java_map_to_attrs,
+    jobject_to_any, new_java_event, out_to_jobject, register_root_handle, span_utf16_lens,
+    stream_string_chunks, string_chunk_to_java, text_insert_change, text_span_change,
+    throw_exception, throw_jni_error, throw_unexpected_node, to_jstring, trace_native,
+    type_path_or_null, xml_index_in_parent, xml_out_to_native_ref, AnyConversionError, DocPtr,
+    DocWrapper, JniEnvExt, JniError, JniResultExt, TextShadow, TxnPtr, XmlTextPtr,
 };
-use jni::objects::{JClass, JMap, JObject, JString, JValue};
-use jni::sys::{jint, jlong, jstring};
-use jni::{Executor, JNIEnv};
-use std::collections::HashMap;
+use jni::objects::{JByteArray, JClass, JIntArray, JObject, JObjectArray, JString, JValue};
+use jni::sys::{jint, jlong, jobject, jobjectArray, jstring};
+use jni::JNIEnv;
 use std::sync::Arc;
+use yrs::types::text::{ChangeKind, Diff, YChange};
 use yrs::types::xml::XmlTextEvent;
+use yrs::updates::decoder::Decode;
 use yrs::{
-    Any, GetString, Observable, Text, Transact, TransactionMut, Xml, XmlFragment, XmlTextPrelim,
-    XmlTextRef,
+    Doc, GetString, Observable, ReadTxn, Snapshot, Text, Transact, TransactionMut, Xml,
+    XmlFragment, XmlTextPrelim, XmlTextRef,
 };
 
+/// Gets or creates the XML text stored at index 0 of the root fragment `name`
+///
+/// The fragment is only written to if it is empty. Returns `None` if its first
+/// child is not an XML text.
+pub fn root_xml_text(doc: &Doc, name: &str) -> Option<XmlTextRef> {
+    let fragment = doc.get_or_insert_xml_fragment(name);
+
+    // Ensure the fragment has a text child at index 0
+    {
+        let txn = doc.transact();
+        if fragment.len(&txn) == 0 {
+            drop(txn);
+            let mut txn = doc.transact_mut();
+            fragment.insert(&mut txn, 0, XmlTextPrelim::new(""));
+        }
+    }
+
+    // The text at index 0, not the fragment
+    let txn = doc.transact();
+    fragment
+        .get(&txn, 0)
+        .and_then(|child| child.into_xml_text())
+}
+
 /// Gets or creates a YXmlText instance from a YDoc
 ///
 /// # Parameters
@@ -29,29 +59,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetXmlText(
     doc_ptr: jlong,
     name: JString,
 ) -> jlong {
+    trace_native!("JniYXmlText.nativeGetXmlText", doc_ptr);
     let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
-    let name_str = get_string_or_throw!(&mut env, name, 0);
-
-    let fragment = wrapper.doc.get_or_insert_xml_fragment(name_str.as_str());
-
-    // Ensure the fragment has a text child at index 0
-    {
-        let txn = wrapper.doc.transact();
-        if fragment.len(&txn) == 0 {
-            drop(txn);
-            let mut txn = wrapper.doc.transact_mut();
-            fragment.insert(&mut txn, 0, XmlTextPrelim::new(""));
-        }
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
     }
 
-    // Return a pointer to the text at index 0, not the fragment
-    let txn = wrapper.doc.transact();
-    if let Some(child) = fragment.get(&txn, 0) {
-        if let Some(text) = child.into_xml_text() {
-            return to_java_ptr(text);
-        }
+    match root_xml_text(&wrapper.doc, name_str.as_str()) {
+        Some(text) => register_root_handle(doc_ptr, wrapper, &name_str, text),
+        None => 0,
     }
-    0
 }
 
 /// Destroys a YXmlText instance and frees its memory
@@ -67,7 +85,10 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDestroy(
     _class: JClass,
     ptr: jlong,
 ) {
-    free_if_valid!(XmlTextPtr::from_raw(ptr), XmlTextRef);
+    trace_native!("JniYXmlText.nativeDestroy", ptr);
+    if forget_root(ptr) {
+        free_if_valid!(XmlTextPtr::from_raw(ptr), XmlTextRef);
+    }
 }
 
 /// Gets the length of the XML text (number of characters) using an existing transaction
@@ -78,7 +99,7 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDestroy(
 /// - `txn_ptr`: Pointer to the transaction
 ///
 /// # Returns
-/// The length of the text as jint
+/// The length of the text as jlong
 #[no_mangle]
 pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeLengthWithTxn(
     mut env: JNIEnv,
@@ -86,12 +107,86 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeLengthWithTxn
     doc_ptr: jlong,
     xml_text_ptr: jlong,
     txn_ptr: jlong,
-) -> jint {
+) -> jlong {
+    trace_native!(
+        "JniYXmlText.nativeLengthWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText", 0);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", 0);
 
-    text.len(txn) as jint
+    jlong::from(text.len(txn))
+}
+
+/// Finds the root the YXmlText belongs to and the path leading to it
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// An `Object[]` holding the root name followed by a `String` key or `Integer`
+/// index per level, or null if the YXmlText was deleted or cannot be reached
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetPathWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    _doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+) -> jobjectArray {
+    trace_native!(
+        "JniYXmlText.nativeGetPathWithTxn",
+        _doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        XmlTextPtr::from_raw(xml_text_ptr),
+        "YXmlText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    type_path_or_null(&mut env, txn, text.as_ref())
+}
+
+/// Gets the length of the XML text (number of characters) using a read transaction
+///
+/// The read transaction is opened and released within this call, so
+/// concurrent readers do not wait for each other.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+///
+/// # Returns
+/// The length of the text as jlong
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeLength(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+) -> jlong {
+    trace_native!("JniYXmlText.nativeLength", doc_ptr, xml_text_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", 0);
+    let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText", 0);
+    if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+        return 0;
+    }
+
+    jlong::from(text.len(&wrapper.doc.transact()))
 }
 
 /// Returns the string representation of the XML text using an existing transaction
@@ -111,6 +206,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeToStringWithT
     xml_text_ptr: jlong,
     txn_ptr: jlong,
 ) -> jstring {
+    trace_native!(
+        "JniYXmlText.nativeToStringWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(
         &mut env,
         DocPtr::from_raw(doc_ptr),
@@ -134,6 +235,89 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeToStringWithT
     to_jstring(&mut env, &string)
 }
 
+/// Returns a chunk of the XML text string content using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `offset`: UTF-8 byte offset of the chunk, which must be a character boundary
+/// - `max_bytes`: Maximum UTF-8 length of the chunk
+///
+/// # Returns
+/// A Java string with the chunk, empty at the end of the content
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetStringChunkWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    offset: jint,
+    max_bytes: jint,
+) -> jstring {
+    trace_native!(
+        "JniYXmlText.nativeGetStringChunkWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        XmlTextPtr::from_raw(xml_text_ptr),
+        "YXmlText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+
+    let content = text.get_string(txn);
+    string_chunk_to_java(&mut env, &content, offset, max_bytes)
+}
+
+/// Passes the XML text string content to a Java consumer in chunks using an existing
+/// transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction instance
+/// - `max_bytes`: Maximum UTF-8 length of each chunk
+/// - `consumer`: A `java.util.function.Consumer<String>` receiving the chunks in order
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeReadChunksWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    max_bytes: jint,
+    consumer: JObject,
+) {
+    trace_native!(
+        "JniYXmlText.nativeReadChunksWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+
+    let content = text.get_string(txn);
+    stream_string_chunks(&mut env, &content, max_bytes, &consumer);
+}
+
 /// Inserts text at the specified index using an existing transaction
 ///
 /// # Parameters
@@ -152,10 +336,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeInsertWithTxn
     index: jint,
     chunk: JString,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlText.nativeInsertWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let chunk_str = get_string_or_throw!(&mut env, chunk);
+    let chunk_str = get_string_or_throw!(&mut env, wrapper, chunk);
 
     text.insert(txn, index as u32, &chunk_str);
 }
@@ -176,10 +367,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativePushWithTxn(
     txn_ptr: jlong,
     chunk: JString,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlText.nativePushWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let chunk_str = get_string_or_throw!(&mut env, chunk);
+    let chunk_str = get_string_or_throw!(&mut env, wrapper, chunk);
 
     text.push(txn, &chunk_str);
 }
@@ -202,7 +400,14 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDeleteWithTxn
     index: jint,
     length: jint,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlText.nativeDeleteWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
@@ -232,20 +437,31 @@ pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeInsert
     chunk: JString,
     attributes: JObject,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlText.nativeInsertWithAttributesWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
-    let chunk_str = get_string_or_throw!(&mut env, chunk);
+    let chunk_str = get_string_or_throw!(&mut env, wrapper, chunk);
 
     // Convert Java Map to Rust HashMap<Arc<str>, Any>
-    let attrs = match convert_java_map_to_attrs(&mut env, &attributes) {
+    let attrs = match java_map_to_attrs(&mut env, &attributes, wrapper.string_policy()) {
         Ok(attrs) => attrs,
         Err(e) => {
-            throw_exception(&mut env, &e);
+            throw_jni_error(&mut env, &e);
             return;
         }
     };
 
+    if !wrapper.attribute_schema().check_or_throw(&mut env, &attrs) {
+        return;
+    }
+
     text.insert_with_attributes(txn, index as u32, &chunk_str, attrs);
 }
 
@@ -273,129 +489,291 @@ pub unsafe extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeFormat
     length: jint,
     attributes: JObject,
 ) {
-    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!(
+        "JniYXmlText.nativeFormatWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
     // Convert Java Map to Rust HashMap<Arc<str>, Any>
-    let attrs = match convert_java_map_to_attrs(&mut env, &attributes) {
+    let attrs = match java_map_to_attrs(&mut env, &attributes, wrapper.string_policy()) {
         Ok(attrs) => attrs,
         Err(e) => {
-            throw_exception(&mut env, &e);
+            throw_jni_error(&mut env, &e);
             return;
         }
     };
 
+    if !wrapper.attribute_schema().check_or_throw(&mut env, &attrs) {
+        return;
+    }
+
     text.format(txn, index as u32, length as u32, attrs);
 }
 
-/// Helper function to convert a Java Map<String, Object> to Rust HashMap<Arc<str>, Any>
-fn convert_java_map_to_attrs(
-    env: &mut JNIEnv,
-    java_map: &JObject,
-) -> Result<HashMap<Arc<str>, Any>, String> {
-    let mut attrs = HashMap::new();
+/// Applies several formatting runs using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `ranges`: `(index, length)` pairs, one per run
+/// - `attributes`: One `Map<String, Object>` per run; null values remove formatting
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeFormatRangesWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    ranges: JIntArray,
+    attributes: JObjectArray,
+) {
+    trace_native!(
+        "JniYXmlText.nativeFormatRangesWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
 
-    // Get the Map interface
-    let map = JMap::from_env(env, java_map).map_err(|e| format!("Failed to get map: {:?}", e))?;
+    format_runs_or_throw(&mut env, wrapper, txn, text, &ranges, &attributes);
+}
 
-    // Iterate through the map entries
-    let mut iter = map
-        .iter(env)
-        .map_err(|e| format!("Failed to iterate map: {:?}", e))?;
+/// Gets a node-level attribute value by name using an existing transaction
+///
+/// Node attributes belong to the XML text node itself (e.g. `data-id` on an
+/// inline node) and are distinct from the formatting attributes of text runs.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `name`: The attribute name
+///
+/// # Returns
+/// The attribute value as a boxed Java object (String, Long, Double, Boolean,
+/// or null for absent or null-valued attributes).
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetAttributeWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    name: JString,
+) -> jobject {
+    trace_native!(
+        "JniYXmlText.nativeGetAttributeWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(
+        &mut env,
+        DocPtr::from_raw(doc_ptr),
+        "YDoc",
+        std::ptr::null_mut()
+    );
+    let text = get_ref_or_throw!(
+        &mut env,
+        XmlTextPtr::from_raw(xml_text_ptr),
+        "YXmlText",
+        std::ptr::null_mut()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        std::ptr::null_mut()
+    );
+    let name_str = get_string_or_throw!(&mut env, wrapper, name, std::ptr::null_mut());
+
+    match text.get_attribute(txn, &name_str) {
+        Some(yrs::Out::Any(any)) => match any_to_jobject(&mut env, &any) {
+            Ok(obj) => obj.into_raw(),
+            Err(_) => {
+                throw_exception(&mut env, "Failed to convert attribute value to Java object");
+                std::ptr::null_mut()
+            }
+        },
+        // Shared types can only be stored by other peers; they are exported
+        // rather than reported as missing
+        Some(value) => out_to_jobject(&mut env, txn, &value)
+            .map(JObject::into_raw)
+            .map_err(JniError::from)
+            .unwrap_or_throw(&mut env),
+        None => std::ptr::null_mut(),
+    }
+}
 
-    while let Some((key, value)) = iter
-        .next(env)
-        .map_err(|e| format!("Failed to get next entry: {:?}", e))?
-    {
-        // Get the key as String
-        let key_jstring = JString::from(key);
-        let key_str: String = env
-            .get_string(&key_jstring)
-            .map_err(|e| format!("Failed to get key string: {:?}", e))?
-            .into();
-
-        // Convert the value to yrs::Any
-        let any_value = if value.is_null() {
-            Any::Null
-        } else {
-            // Check the type of the value
-            let value_class = env
-                .get_object_class(&value)
-                .map_err(|e| format!("Failed to get value class: {:?}", e))?;
-
-            let class_name = env
-                .call_method(&value_class, "getName", "()Ljava/lang/String;", &[])
-                .map_err(|e| format!("Failed to get class name: {:?}", e))?;
-
-            let class_name_obj = class_name
-                .l()
-                .map_err(|e| format!("Failed to get class name object: {:?}", e))?;
-            let class_name_str: String = env
-                .get_string(&JString::from(class_name_obj))
-                .map_err(|e| format!("Failed to convert class name: {:?}", e))?
-                .into();
-
-            match class_name_str.as_str() {
-                "java.lang.Boolean" => {
-                    let bool_val = env
-                        .call_method(&value, "booleanValue", "()Z", &[])
-                        .map_err(|e| format!("Failed to get boolean value: {:?}", e))?;
-                    Any::Bool(
-                        bool_val
-                            .z()
-                            .map_err(|e| format!("Failed to convert to bool: {:?}", e))?,
-                    )
-                }
-                "java.lang.Integer" | "java.lang.Long" => {
-                    let long_val = env
-                        .call_method(&value, "longValue", "()J", &[])
-                        .map_err(|e| format!("Failed to get long value: {:?}", e))?;
-                    Any::BigInt(
-                        long_val
-                            .j()
-                            .map_err(|e| format!("Failed to convert to long: {:?}", e))?,
-                    )
-                }
-                "java.lang.Double" | "java.lang.Float" => {
-                    let double_val = env
-                        .call_method(&value, "doubleValue", "()D", &[])
-                        .map_err(|e| format!("Failed to get double value: {:?}", e))?;
-                    Any::Number(
-                        double_val
-                            .d()
-                            .map_err(|e| format!("Failed to convert to double: {:?}", e))?,
-                    )
-                }
-                "java.lang.String" => {
-                    let string_val = JString::from(value);
-                    let rust_str: String = env
-                        .get_string(&string_val)
-                        .map_err(|e| format!("Failed to get string value: {:?}", e))?
-                        .into();
-                    Any::String(rust_str.into())
-                }
-                _ => {
-                    // Try to convert to string as fallback
-                    let string_val = env
-                        .call_method(&value, "toString", "()Ljava/lang/String;", &[])
-                        .map_err(|e| format!("Failed to call toString: {:?}", e))?;
-                    let string_obj = string_val
-                        .l()
-                        .map_err(|e| format!("Failed to get string object: {:?}", e))?;
-                    let rust_str: String = env
-                        .get_string(&JString::from(string_obj))
-                        .map_err(|e| format!("Failed to convert to string: {:?}", e))?
-                        .into();
-                    Any::String(rust_str.into())
-                }
+/// Sets a node-level attribute value using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `name`: The attribute name
+/// - `value`: The attribute value as a boxed Java object (String, Long,
+///   Integer, Double, Float, Boolean, or null). Unsupported types throw
+///   `IllegalArgumentException`.
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeSetAttributeWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    name: JString,
+    value: JObject,
+) {
+    trace_native!(
+        "JniYXmlText.nativeSetAttributeWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let name_str = get_string_or_throw!(&mut env, wrapper, name);
+
+    let any_value = match jobject_to_any(&mut env, &value, wrapper.string_policy()) {
+        Ok(a) => a,
+        Err(AnyConversionError::Unsupported(class_name)) => {
+            let msg = format!(
+                "Unsupported attribute value type: {}. Expected String, Long, Integer, Double, Float, Boolean, JniUndefined, or null.",
+                class_name
+            );
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+            return;
+        }
+        Err(AnyConversionError::InvalidString(msg)) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
+            return;
+        }
+        Err(AnyConversionError::Jni(e)) => {
+            throw_exception(&mut env, &format!("JNI error: {:?}", e));
+            return;
+        }
+    };
+
+    text.insert_attribute(txn, name_str, any_value);
+}
+
+/// Removes a node-level attribute using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `name`: The attribute name to remove
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeRemoveAttributeWithTxn(
+    mut env: JNIEnv,
+    _class: JClass,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    name: JString,
+) {
+    trace_native!(
+        "JniYXmlText.nativeRemoveAttributeWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    check_writable_or_throw!(&mut env, wrapper);
+    let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText");
+    let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+    let name_str = get_string_or_throw!(&mut env, wrapper, name);
+
+    text.remove_attribute(txn, &name_str);
+}
+
+/// Gets all node-level attribute names using an existing transaction
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+///
+/// # Returns
+/// A Java String[] array containing all attribute names
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetAttributeNamesWithTxn<'a>(
+    mut env: JNIEnv<'a>,
+    _class: JClass<'a>,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlText.nativeGetAttributeNamesWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
+    let text = get_ref_or_throw!(
+        &mut env,
+        XmlTextPtr::from_raw(xml_text_ptr),
+        "YXmlText",
+        JObject::null()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        JObject::null()
+    );
+
+    let names: Vec<String> = text.attributes(txn).map(|(k, _)| k.to_string()).collect();
+
+    let string_class = match env.find_class("java/lang/String") {
+        Ok(cls) => cls,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to find String class");
+            return JObject::null();
+        }
+    };
+
+    let array = match env.new_object_array(names.len() as i32, string_class, JObject::null()) {
+        Ok(arr) => arr,
+        Err(_) => {
+            throw_exception(&mut env, "Failed to create String array");
+            return JObject::null();
+        }
+    };
+
+    for (i, name) in names.iter().enumerate() {
+        let jname = match env.new_string(name) {
+            Ok(s) => s,
+            Err(_) => {
+                throw_exception(&mut env, "Failed to create Java string");
+                return JObject::null();
             }
         };
-
-        attrs.insert(Arc::from(key_str.as_str()), any_value);
+        if env
+            .set_object_array_element(&array, i as i32, &jname)
+            .is_err()
+        {
+            throw_exception(&mut env, "Failed to set array element");
+            return JObject::null();
+        }
     }
 
-    Ok(attrs)
+    JObject::from(array)
 }
 
 /// Gets the parent of this XML text node using an existing transaction
@@ -407,9 +785,7 @@ fn convert_java_map_to_attrs(
 ///
 /// # Returns
 ///
-/// An Object array [type, pointer] where:
-/// - type: 0 = XmlElement, 1 = XmlFragment
-/// - pointer: Java pointer to the parent object
+/// A `YNativeRef` with type 0 for XmlElement or 2 for XmlFragment.
 ///
 /// Returns null if this node has no parent
 #[no_mangle]
@@ -420,6 +796,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetParentWith
     xml_text_ptr: jlong,
     _txn_ptr: jlong,
 ) -> JObject<'a> {
+    trace_native!(
+        "JniYXmlText.nativeGetParentWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        _txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
     let text = get_ref_or_throw!(
         &mut env,
@@ -429,67 +811,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetParentWith
     );
 
     match text.parent() {
-        Some(parent) => {
-            use yrs::XmlOut;
-
-            // Create Object array [type, pointer]
-            // type: 0=Element, 1=Fragment
-            let (type_val, ptr) = match parent {
-                XmlOut::Element(elem) => (0i32, to_java_ptr(elem)),
-                XmlOut::Fragment(frag) => (1i32, to_java_ptr(frag)),
-                XmlOut::Text(_) => {
-                    throw_exception(&mut env, "Unexpected XmlText as parent");
-                    return JObject::null();
-                }
-            };
-
-            // Create Object array
-            let array = match env.new_object_array(2, "java/lang/Object", JObject::null()) {
-                Ok(arr) => arr,
-                Err(e) => {
-                    throw_exception(&mut env, &format!("Failed to create array: {:?}", e));
-                    return JObject::null();
-                }
-            };
-
-            // Set type (Integer)
-            let type_obj = match env.new_object(
-                "java/lang/Integer",
-                "(I)V",
-                &[jni::objects::JValueGen::Int(type_val)],
-            ) {
-                Ok(obj) => obj,
-                Err(e) => {
-                    throw_exception(&mut env, &format!("Failed to create Integer: {:?}", e));
-                    return JObject::null();
-                }
-            };
-
-            if let Err(e) = env.set_object_array_element(&array, 0, type_obj) {
-                throw_exception(&mut env, &format!("Failed to set type: {:?}", e));
-                return JObject::null();
-            }
-
-            // Set pointer (Long)
-            let ptr_obj = match env.new_object(
-                "java/lang/Long",
-                "(J)V",
-                &[jni::objects::JValueGen::Long(ptr)],
-            ) {
-                Ok(obj) => obj,
-                Err(e) => {
-                    throw_exception(&mut env, &format!("Failed to create Long: {:?}", e));
-                    return JObject::null();
-                }
-            };
-
-            if let Err(e) = env.set_object_array_element(&array, 1, ptr_obj) {
-                throw_exception(&mut env, &format!("Failed to set pointer: {:?}", e));
-                return JObject::null();
-            }
-
-            JObject::from(array)
+        Some(yrs::XmlOut::Text(_)) => {
+            throw_unexpected_node(&mut env, "Unexpected XmlText as parent");
+            JObject::null()
         }
+        Some(parent) => match xml_out_to_native_ref(&mut env, parent) {
+            Ok(obj) => obj,
+            Err(e) => {
+                throw_exception(&mut env, &e.to_string());
+                JObject::null()
+            }
+        },
         None => JObject::null(),
     }
 }
@@ -512,55 +844,30 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetIndexInPar
     xml_text_ptr: jlong,
     txn_ptr: jlong,
 ) -> jni::sys::jint {
+    trace_native!(
+        "JniYXmlText.nativeGetIndexInParentWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", -1);
     let text = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xml_text_ptr), "YXmlText", -1);
     let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction", -1);
 
-    match text.parent() {
-        Some(parent) => {
-            use yrs::XmlOut;
-
-            use yrs::branch::Branch;
-            let my_id = <XmlTextRef as AsRef<Branch>>::as_ref(text).id();
-
-            // Match on parent type and iterate children directly
-            match parent {
-                XmlOut::Element(elem) => {
-                    // Iterate through parent's children to find our index
-                    for index in 0..elem.len(txn) {
-                        if let Some(child) = elem.get(txn, index) {
-                            let child_id = child.as_ptr().id();
-                            if child_id == my_id {
-                                return index as jni::sys::jint;
-                            }
-                        }
-                    }
-                    -1
-                }
-                XmlOut::Fragment(frag) => {
-                    // Iterate through parent's children to find our index
-                    for index in 0..frag.len(txn) {
-                        if let Some(child) = frag.get(txn, index) {
-                            let child_id = child.as_ptr().id();
-                            if child_id == my_id {
-                                return index as jni::sys::jint;
-                            }
-                        }
-                    }
-                    -1
-                }
-                XmlOut::Text(_) => -1, // Text can't be a parent
-            }
-        }
-        None => -1, // No parent
-    }
+    xml_index_in_parent(txn, text).map_or(-1, |index| index as jni::sys::jint)
 }
 
 /// Registers an observer for the YXmlText
 ///
+/// Events carry the UTF-16 lengths of retained and deleted spans, measured
+/// against a copy of the text shared by all of its observers. If the text has
+/// no copy yet, it is read with the given transaction, or with a read-only
+/// transaction of its own if there is none.
+///
 /// # Parameters
 /// - `doc_ptr`: Pointer to the YDoc instance
 /// - `xmltext_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the caller's open transaction, or 0
 /// - `subscription_id`: The subscription ID from Java
 /// - `yxmltext_obj`: The Java YXmlText object for callbacks
 #[no_mangle]
@@ -569,17 +876,57 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeObserve(
     _class: JClass,
     doc_ptr: jlong,
     xmltext_ptr: jlong,
+    txn_ptr: jlong,
     subscription_id: jlong,
     yxmltext_obj: JObject,
 ) {
-    let wrapper = get_mut_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
+    trace_native!("JniYXmlText.nativeObserve", doc_ptr, xmltext_ptr, txn_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
     let xmltext = get_ref_or_throw!(&mut env, XmlTextPtr::from_raw(xmltext_ptr), "YXmlText");
+    let offset_kind = wrapper.doc.offset_kind();
+    let shadow = if txn_ptr == 0 {
+        if !check_not_held_or_throw(&mut env, &wrapper.doc) {
+            return;
+        }
+        wrapper.text_shadows().share(xmltext, offset_kind, || {
+            TextShadow::new(&wrapper.doc.transact(), xmltext)
+        })
+    } else {
+        let txn = get_mut_or_throw!(&mut env, TxnPtr::from_raw(txn_ptr), "YTransaction");
+        wrapper.text_shadows().share(xmltext, offset_kind, || {
+            TextShadow::in_transaction(txn, xmltext)
+        })
+    };
+    observe_xmltext(
+        &mut env,
+        wrapper,
+        xmltext,
+        doc_ptr,
+        xmltext_ptr,
+        subscription_id,
+        yxmltext_obj,
+        shadow,
+    );
+}
 
-    // Get JavaVM and create Executor for callback handling
-    let executor = match env.get_java_vm() {
-        Ok(vm) => Executor::new(Arc::new(vm)),
+/// Subscribes a Java YXmlText to the events of an XML text, measuring their
+/// spans against `shadow` if given
+#[allow(clippy::too_many_arguments)]
+fn observe_xmltext(
+    env: &mut JNIEnv,
+    wrapper: &DocWrapper,
+    xmltext: &XmlTextRef,
+    doc_ptr: jlong,
+    xmltext_ptr: jlong,
+    subscription_id: jlong,
+    yxmltext_obj: JObject,
+    shadow: Option<Arc<TextShadow>>,
+) {
+    // Executor shared by all of the document's callbacks
+    let executor = match wrapper.executor(env) {
+        Ok(executor) => executor,
         Err(e) => {
-            throw_exception(&mut env, &format!("Failed to get JavaVM: {:?}", e));
+            throw_exception(env, &format!("Failed to get JavaVM: {:?}", e));
             return;
         }
     };
@@ -588,20 +935,27 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeObserve(
     let global_ref = match env.new_global_ref(yxmltext_obj) {
         Ok(r) => r,
         Err(e) => {
-            throw_exception(&mut env, &format!("Failed to create global ref: {:?}", e));
+            throw_exception(env, &format!("Failed to create global ref: {:?}", e));
             return;
         }
     };
 
+    let gate = wrapper.dispatch_gate();
+
     // Create observer closure
     let subscription = xmltext.observe(move |txn, event| {
+        // Skip the callback once the document has started closing
+        let Some(_dispatch) = gate.enter() else {
+            return;
+        };
         // Use Executor for thread attachment with automatic local frame management
-        let _ = executor
-            .with_attached(|env| dispatch_xmltext_event(env, doc_ptr, subscription_id, txn, event));
+        let _ = executor.with_attached(|env| {
+            dispatch_xmltext_event(env, doc_ptr, subscription_id, txn, event, shadow.as_deref())
+        });
     });
 
     // Store subscription and GlobalRef in the DocWrapper
-    wrapper.add_subscription(subscription_id, subscription, global_ref);
+    wrapper.add_subscription(subscription_id, xmltext_ptr, subscription, global_ref);
 }
 
 /// Unregisters an observer for the YXmlText
@@ -618,36 +972,37 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeUnobserve(
     _xmltext_ptr: jlong,
     subscription_id: jlong,
 ) {
-    if doc_ptr == 0 {
-        throw_exception(&mut env, "Invalid YDoc pointer");
-        return;
-    }
+    trace_native!("JniYXmlText.nativeUnobserve", doc_ptr, _xmltext_ptr);
+    let wrapper = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc");
 
-    unsafe {
-        let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
-        // Remove subscription and GlobalRef from DocWrapper
-        // Both the Subscription and GlobalRef are dropped here
-        wrapper.remove_subscription(subscription_id);
-    }
+    // Remove subscription and GlobalRef from DocWrapper
+    // Both the Subscription and GlobalRef are dropped here
+    wrapper.remove_subscription(subscription_id);
 }
 
 /// Helper function to dispatch an xmltext event to Java
-fn dispatch_xmltext_event(
+pub(crate) fn dispatch_xmltext_event(
     env: &mut JNIEnv,
     doc_ptr: jlong,
     subscription_id: jlong,
     txn: &TransactionMut,
     event: &XmlTextEvent,
+    shadow: Option<&TextShadow>,
 ) -> Result<(), jni::errors::Error> {
+    if is_silent(txn) {
+        // Keep the copy current for the next event that is dispatched
+        if let Some(shadow) = shadow {
+            shadow.reset(txn, event.target());
+        }
+        return Ok(());
+    }
     // Get the Java YXmlText object from DocWrapper
-    let yxmltext_ref = unsafe {
-        let wrapper = from_java_ptr::<DocWrapper>(doc_ptr);
-        match wrapper.get_java_ref(subscription_id) {
-            Some(r) => r,
-            None => {
-                eprintln!("No Java object found for subscription {}", subscription_id);
-                return Ok(());
-            }
+    let wrapper = unsafe { from_java_ptr::<DocWrapper>(doc_ptr) };
+    let yxmltext_ref = match wrapper.get_java_ref(subscription_id) {
+        Some(r) => r,
+        None => {
+            eprintln!("No Java object found for subscription {}", subscription_id);
+            return Ok(());
         }
     };
 
@@ -660,64 +1015,17 @@ fn dispatch_xmltext_event(
     let changes_list = env.new_object("java/util/ArrayList", "()V", &[])?;
 
     // Convert each delta to a YTextChange (XmlText uses same delta as Text)
-    for d in delta {
+    let utf16_lens = span_utf16_lens(shadow, txn, event.target(), delta);
+    for (d, utf16_len) in delta.iter().zip(utf16_lens) {
         let change_obj = match d {
-            yrs::types::Delta::Inserted(value, attrs) => {
-                // Convert value to string
-                let content = value.to_string();
-                let content_jstr = env.new_string(&content)?;
-
-                // Convert attributes to HashMap (or null)
-                let attrs_map = if let Some(attrs) = attrs {
-                    attrs_to_java_hashmap(env, attrs)?
-                } else {
-                    JObject::null()
-                };
-
-                // Create YTextChange for INSERT
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYTextChange")?;
-                env.new_object(
-                    change_class,
-                    "(Ljava/lang/String;Ljava/util/Map;)V",
-                    &[JValue::Object(&content_jstr), JValue::Object(&attrs_map)],
-                )?
-            }
-            yrs::types::Delta::Deleted(len) => {
-                // Create YTextChange for DELETE
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYTextChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let delete_type =
-                    env.get_static_field(type_class, "DELETE", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;I)V",
-                    &[JValue::Object(&delete_type.l()?), JValue::Int(*len as i32)],
-                )?
-            }
-            yrs::types::Delta::Retain(len, attrs) => {
-                // Create YTextChange for RETAIN
-                let change_class = env.find_class("net/carcdr/ycrdt/jni/JniYTextChange")?;
-                let type_class = env.find_class("net/carcdr/ycrdt/YChange$Type")?;
-                let retain_type =
-                    env.get_static_field(type_class, "RETAIN", "Lnet/carcdr/ycrdt/YChange$Type;")?;
-
-                let attrs_map = if let Some(attrs) = attrs {
-                    attrs_to_java_hashmap(env, attrs)?
-                } else {
-                    JObject::null()
-                };
-
-                env.new_object(
-                    change_class,
-                    "(Lnet/carcdr/ycrdt/YChange$Type;ILjava/util/Map;)V",
-                    &[
-                        JValue::Object(&retain_type.l()?),
-                        JValue::Int(*len as i32),
-                        JValue::Object(&attrs_map),
-                    ],
-                )?
-            }
+            yrs::types::Delta::Inserted(value, attrs) => text_insert_change(
+                env,
+                txn,
+                value,
+                attrs.as_deref(),
+                wrapper.event_content_limit(),
+            )?,
+            span => text_span_change(env, span, utf16_len)?,
         };
 
         // Add to changes list
@@ -729,20 +1037,8 @@ fn dispatch_xmltext_event(
         )?;
     }
 
-    // Create YEvent
-    let event_class = env.find_class("net/carcdr/ycrdt/jni/JniYEvent")?;
-    let target = yxmltext_obj; // Use the YXmlText object as the target
-    let origin_jstr = env.new_string("")?; // Empty origin for now
-
-    let event_obj = env.new_object(
-        event_class,
-        "(Ljava/lang/Object;Ljava/util/List;Ljava/lang/String;)V",
-        &[
-            JValue::Object(target),
-            JValue::Object(&changes_list),
-            JValue::Object(&origin_jstr),
-        ],
-    )?;
+    // Create YEvent carrying the transaction's origin
+    let event_obj = new_java_event(env, txn, yxmltext_obj, &changes_list)?;
 
     // Call YXmlText.dispatchEvent(subscriptionId, event)
     env.call_method(
@@ -774,6 +1070,12 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetFormatting
     xml_text_ptr: jlong,
     txn_ptr: jlong,
 ) -> JObject<'local> {
+    trace_native!(
+        "JniYXmlText.nativeGetFormattingChunksWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
     let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
     let text = get_ref_or_throw!(
         &mut env,
@@ -826,16 +1128,17 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetFormatting
         };
 
         // Create FormattingChunk(text, attributes)
-        let chunk_class = match env.find_class("net/carcdr/ycrdt/jni/JniFormattingChunk") {
-            Ok(cls) => cls,
-            Err(e) => {
-                throw_exception(
-                    &mut env,
-                    &format!("Failed to find FormattingChunk class: {:?}", e),
-                );
-                return JObject::null();
-            }
-        };
+        let chunk_class =
+            match find_callback_class(&mut env, "net/carcdr/ycrdt/jni/JniFormattingChunk") {
+                Ok(cls) => cls,
+                Err(e) => {
+                    throw_exception(
+                        &mut env,
+                        &format!("Failed to find FormattingChunk class: {:?}", e),
+                    );
+                    return JObject::null();
+                }
+            };
 
         let chunk_obj = match env.new_object(
             chunk_class,
@@ -867,11 +1170,158 @@ pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeGetFormatting
     chunks_list
 }
 
+/// Decodes an optional snapshot argument, throwing `IllegalArgumentException` on malformed input
+///
+/// Returns `Err(())` if an exception has been thrown.
+fn decode_snapshot_arg(env: &mut JNIEnv, bytes: &JByteArray) -> Result<Option<Snapshot>, ()> {
+    if bytes.is_null() {
+        return Ok(None);
+    }
+    let decoded = env
+        .convert_byte_array(bytes)
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|data| Snapshot::decode_v1(&data).map_err(|e| format!("{:?}", e)));
+    match decoded {
+        Ok(snapshot) => Ok(Some(snapshot)),
+        Err(e) => {
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Invalid snapshot: {}", e),
+            );
+            Err(())
+        }
+    }
+}
+
+/// Change kinds of snapshot diff chunks, matching the declaration order of
+/// `JniSnapshotChunk.ChangeKind`
+pub const CHANGE_KIND_ADDED: jint = 0;
+pub const CHANGE_KIND_REMOVED: jint = 1;
+/// Change kind of content unchanged between the two snapshots
+pub const CHANGE_KIND_UNCHANGED: jint = -1;
+
+/// Converts snapshot diff chunks into a Java List<JniSnapshotChunk>
+fn snapshot_chunks_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    txn: &TransactionMut,
+    diff: Vec<Diff<YChange>>,
+) -> jni::errors::Result<JObject<'local>> {
+    let chunks_list = env.new_object("java/util/ArrayList", "()V", &[])?;
+    for d in diff {
+        let text_jstr = env.new_string(d.insert.to_string(txn))?;
+        let attrs_map = match d.attributes {
+            Some(attrs) => attrs_to_java_hashmap(env, &attrs)?,
+            None => JObject::null(),
+        };
+        let (kind, client, clock) = match d.ychange {
+            Some(change) => {
+                let kind = match change.kind {
+                    ChangeKind::Added => CHANGE_KIND_ADDED,
+                    ChangeKind::Removed => CHANGE_KIND_REMOVED,
+                };
+                (kind, change.id.client as jlong, change.id.clock as jlong)
+            }
+            None => (CHANGE_KIND_UNCHANGED, 0, 0),
+        };
+        let class = find_callback_class(env, "net/carcdr/ycrdt/jni/JniSnapshotChunk")?;
+        let chunk_obj = env.new_object(
+            &class,
+            "(Ljava/lang/String;Ljava/util/Map;IJJ)V",
+            &[
+                JValue::Object(&text_jstr),
+                JValue::Object(&attrs_map),
+                JValue::Int(kind),
+                JValue::Long(client),
+                JValue::Long(clock),
+            ],
+        )?;
+        env.call_method(
+            &chunks_list,
+            "add",
+            "(Ljava/lang/Object;)Z",
+            &[JValue::Object(&chunk_obj)],
+        )?;
+        env.delete_local_ref(chunk_obj)?;
+        env.delete_local_ref(text_jstr)?;
+        env.delete_local_ref(attrs_map)?;
+    }
+    Ok(chunks_list)
+}
+
+/// Gets the formatted content of the XML text as of a snapshot, attributing
+/// changes relative to an earlier snapshot, using an existing transaction
+///
+/// Insertions and deletions made between `prev_snapshot` and `snapshot` are
+/// reported with the ID of the item that introduced them. Removed content is
+/// only available if the document was created with garbage collection disabled.
+///
+/// # Parameters
+/// - `doc_ptr`: Pointer to the YDoc instance
+/// - `xml_text_ptr`: Pointer to the YXmlText instance
+/// - `txn_ptr`: Pointer to the transaction
+/// - `snapshot`: Encoded snapshot to render, or null for the current state
+/// - `prev_snapshot`: Encoded snapshot to compare against, or null for no attribution
+///
+/// # Returns
+/// A Java List<JniSnapshotChunk> containing the text chunks with their
+/// formatting attributes and change attribution
+#[no_mangle]
+pub extern "system" fn Java_net_carcdr_ycrdt_jni_JniYXmlText_nativeDiffSnapshotsWithTxn<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    doc_ptr: jlong,
+    xml_text_ptr: jlong,
+    txn_ptr: jlong,
+    snapshot: JByteArray<'local>,
+    prev_snapshot: JByteArray<'local>,
+) -> JObject<'local> {
+    trace_native!(
+        "JniYXmlText.nativeDiffSnapshotsWithTxn",
+        doc_ptr,
+        xml_text_ptr,
+        txn_ptr
+    );
+    let _doc = get_ref_or_throw!(&mut env, DocPtr::from_raw(doc_ptr), "YDoc", JObject::null());
+    let text = get_ref_or_throw!(
+        &mut env,
+        XmlTextPtr::from_raw(xml_text_ptr),
+        "YXmlText",
+        JObject::null()
+    );
+    let txn = get_mut_or_throw!(
+        &mut env,
+        TxnPtr::from_raw(txn_ptr),
+        "YTransaction",
+        JObject::null()
+    );
+    let Ok(hi) = decode_snapshot_arg(&mut env, &snapshot) else {
+        return JObject::null();
+    };
+    // Rendering treats a missing upper snapshot as "include everything", so the
+    // current state has to be captured explicitly for deletions to be honoured
+    let hi = hi.unwrap_or_else(|| txn.snapshot());
+    let Ok(lo) = decode_snapshot_arg(&mut env, &prev_snapshot) else {
+        return JObject::null();
+    };
+
+    let diff = text.diff_range(txn, Some(&hi), lo.as_ref(), YChange::identity);
+    match snapshot_chunks_to_java(&mut env, txn, diff) {
+        Ok(list) => list,
+        Err(e) => {
+            throw_exception(
+                &mut env,
+                &format!("Failed to build snapshot chunks: {:?}", e),
+            );
+            JObject::null()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::free_java_ptr;
-    use yrs::{Doc, Transact, XmlFragment, XmlFragmentRef};
+    use crate::{free_java_ptr, to_java_ptr};
+    use yrs::{Any, Doc, ReadTxn, Transact, XmlFragment, XmlFragmentRef};
 
     #[test]
     fn test_xml_text_creation() {
@@ -1011,4 +1461,85 @@ mod tests {
         let text = fragment.get(&txn, 0).unwrap().into_xml_text().unwrap();
         assert_eq!(text.get_string(&txn), "world");
     }
+
+    #[test]
+    fn test_xml_text_node_attributes() {
+        let doc = Doc::new();
+        let fragment = doc.get_or_insert_xml_fragment("test");
+
+        {
+            let mut txn = doc.transact_mut();
+            let text = fragment.insert(&mut txn, 0, XmlTextPrelim::new("hello"));
+            text.insert_attribute(&mut txn, "data-id", "span-1");
+            text.insert_attribute(&mut txn, "level", Any::BigInt(2));
+            text.insert_attribute(&mut txn, "tmp", true);
+            text.remove_attribute(&mut txn, &"tmp");
+        }
+
+        let txn = doc.transact();
+        let text = fragment.get(&txn, 0).unwrap().into_xml_text().unwrap();
+        assert_eq!(
+            text.get_attribute(&txn, "data-id"),
+            Some(yrs::Out::Any(Any::String("span-1".into())))
+        );
+        assert_eq!(
+            text.get_attribute(&txn, "level"),
+            Some(yrs::Out::Any(Any::BigInt(2)))
+        );
+        assert_eq!(text.get_attribute(&txn, "tmp"), None);
+
+        let mut names: Vec<String> = text.attributes(&txn).map(|(k, _)| k.to_string()).collect();
+        names.sort();
+        assert_eq!(names, vec!["data-id", "level"]);
+        // Node attributes do not affect the text content
+        assert_eq!(text.get_string(&txn), "hello");
+    }
+
+    #[test]
+    fn test_xml_text_snapshot_diff() {
+        let doc = Doc::with_options(yrs::Options {
+            skip_gc: true,
+            ..Default::default()
+        });
+        let fragment = doc.get_or_insert_xml_fragment("test");
+
+        let (text, before) = {
+            let mut txn = doc.transact_mut();
+            let text = fragment.insert(&mut txn, 0, XmlTextPrelim::new("hello world"));
+            let snapshot = txn.snapshot();
+            (text, snapshot)
+        };
+        {
+            let mut txn = doc.transact_mut();
+            text.remove_range(&mut txn, 5, 6);
+            text.push(&mut txn, "!");
+        }
+
+        let mut txn = doc.transact_mut();
+        let after = txn.snapshot();
+        let diff = text.diff_range(&mut txn, Some(&after), Some(&before), YChange::identity);
+        let parts: Vec<(String, Option<ChangeKind>)> = diff
+            .iter()
+            .map(|d| {
+                (
+                    d.insert.clone().to_string(&txn),
+                    d.ychange.as_ref().map(|c| c.kind),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("hello".to_string(), None),
+                (" world".to_string(), Some(ChangeKind::Removed)),
+                ("!".to_string(), Some(ChangeKind::Added)),
+            ]
+        );
+
+        // Without a previous snapshot nothing is attributed
+        let plain = text.diff_range(&mut txn, Some(&before), None, YChange::identity);
+        assert_eq!(plain.len(), 1);
+        assert_eq!(plain[0].insert.clone().to_string(&txn), "hello world");
+        assert!(plain[0].ychange.is_none());
+    }
 }